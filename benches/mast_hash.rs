@@ -0,0 +1,57 @@
+use divan::Bencher;
+use neptune_core::models::blockchain::transaction::transaction_kernel::TransactionKernel;
+use neptune_core::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use neptune_core::models::proof_abstractions::mast_hash::MastHash;
+use neptune_core::models::proof_abstractions::timestamp::Timestamp;
+use neptune_core::util_types::mutator_set::commit;
+use neptune_core::util_types::test_shared::mutator_set::random_removal_record;
+use rand::random;
+
+// Measures the MAST hash of transaction kernels with many inputs and outputs,
+// both for kernels that differ from the previous one only in the timestamp,
+// as when a block template is rebuilt, and for kernels with new inputs.
+
+const NUM_INPUTS_AND_OUTPUTS: [usize; 3] = [10, 100, 500];
+
+fn main() {
+    divan::main();
+}
+
+fn kernel(num_inputs_and_outputs: usize) -> TransactionKernel {
+    TransactionKernel {
+        inputs: (0..num_inputs_and_outputs)
+            .map(|_| random_removal_record())
+            .collect(),
+        outputs: (0..num_inputs_and_outputs)
+            .map(|_| commit(random(), random(), random()))
+            .collect(),
+        public_announcements: vec![],
+        fee: NeptuneCoins::new(1),
+        coinbase: None,
+        timestamp: Timestamp::now(),
+        mutator_set_hash: random(),
+    }
+}
+
+#[divan::bench(args = NUM_INPUTS_AND_OUTPUTS)]
+fn rebuilt_template(bencher: Bencher, num_inputs_and_outputs: usize) {
+    let mut kernel = kernel(num_inputs_and_outputs);
+    bencher
+        .with_inputs(|| {
+            kernel.timestamp = kernel.timestamp + Timestamp::millis(1);
+            kernel.clone()
+        })
+        .bench_local_values(|kernel| kernel.mast_hash());
+}
+
+#[divan::bench(args = NUM_INPUTS_AND_OUTPUTS)]
+fn new_inputs(bencher: Bencher, num_inputs_and_outputs: usize) {
+    let mut kernel = kernel(num_inputs_and_outputs);
+    bencher
+        .with_inputs(|| {
+            kernel.inputs.rotate_left(1);
+            kernel.outputs.rotate_left(1);
+            kernel.clone()
+        })
+        .bench_local_values(|kernel| kernel.mast_hash());
+}
@@ -0,0 +1,66 @@
+use std::collections::VecDeque;
+
+use divan::Bencher;
+use neptune_core::models::state::wallet::membership_proof_compression::CompressedMembershipProofs;
+use neptune_core::util_types::mutator_set::commit;
+use neptune_core::util_types::mutator_set::ms_membership_proof::MsMembershipProof;
+use neptune_core::util_types::mutator_set::mutator_set_accumulator::MutatorSetAccumulator;
+use rand::random;
+use tasm_lib::Digest;
+
+// Measures (de)compression of the membership proofs that a monitored UTXO
+// keeps for recent blocks, and prints the storage size with and without
+// compression before running the timed benchmarks.
+
+const NUM_PROOFS: [usize; 3] = [10, 100, 1000];
+
+fn main() {
+    for num_proofs in NUM_PROOFS {
+        let proofs = successive_proofs(num_proofs);
+        let uncompressed = bincode::serialize(&proofs).unwrap().len();
+        let compressed = bincode::serialize(&CompressedMembershipProofs::compress(&proofs))
+            .unwrap()
+            .len();
+        println!(
+            "{num_proofs} membership proofs: {uncompressed} bytes uncompressed, \
+            {compressed} bytes compressed ({:.1}%)",
+            100.0 * compressed as f64 / uncompressed as f64
+        );
+    }
+
+    divan::main();
+}
+
+/// Membership proofs of one item across a sequence of blocks that each add one
+/// item to the mutator set, newest first.
+fn successive_proofs(num_blocks: usize) -> VecDeque<(Digest, MsMembershipProof)> {
+    let mut msa = MutatorSetAccumulator::default();
+    let item: Digest = random();
+    let sender_randomness: Digest = random();
+    let receiver_preimage: Digest = random();
+    let mut mp = msa.prove(item, sender_randomness, receiver_preimage);
+    msa.add(&commit(item, sender_randomness, receiver_preimage.hash()));
+
+    let mut proofs = VecDeque::new();
+    for _ in 0..num_blocks {
+        let addition_record = commit(random(), random(), random());
+        mp.update_from_addition(item, &msa, &addition_record)
+            .unwrap();
+        msa.add(&addition_record);
+        proofs.push_front((random(), mp.clone()));
+    }
+
+    proofs
+}
+
+#[divan::bench(args = NUM_PROOFS)]
+fn compress(bencher: Bencher, num_proofs: usize) {
+    let proofs = successive_proofs(num_proofs);
+    bencher.bench_local(|| CompressedMembershipProofs::compress(&proofs));
+}
+
+#[divan::bench(args = NUM_PROOFS)]
+fn decompress(bencher: Bencher, num_proofs: usize) {
+    let compressed = CompressedMembershipProofs::compress(&successive_proofs(num_proofs));
+    bencher.bench_local(|| compressed.decompress().unwrap());
+}
@@ -0,0 +1,94 @@
+use divan::Bencher;
+use neptune_core::config_models::cli_args::Args;
+use neptune_core::config_models::data_directory::DataDirectory;
+use neptune_core::config_models::network::Network;
+use neptune_core::models::blockchain::transaction::transaction_kernel::TransactionKernel;
+use neptune_core::models::blockchain::transaction::transaction_output::TxOutput;
+use neptune_core::models::blockchain::transaction::transaction_output::TxOutputList;
+use neptune_core::models::blockchain::transaction::transaction_output::UtxoNotificationMedium;
+use neptune_core::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use neptune_core::models::proof_abstractions::timestamp::Timestamp;
+use neptune_core::models::state::wallet::address::KeyType;
+use neptune_core::models::state::wallet::wallet_state::WalletState;
+use neptune_core::models::state::wallet::WalletSecret;
+use rand::distributions::Alphanumeric;
+use rand::distributions::DistString;
+use rand::random;
+
+// Measures the scan of a batch of blocks for UTXOs announced to the wallet,
+// block by block as when following the tip, and as one batch as when syncing.
+
+const NUM_BLOCKS: [usize; 3] = [1, 10, 100];
+
+/// Announced UTXOs per block.
+const NUM_ANNOUNCEMENTS: usize = 10;
+
+fn main() {
+    divan::main();
+}
+
+fn wallet_state() -> WalletState {
+    let tmp_root = std::env::temp_dir()
+        .join("neptune-benchmarks")
+        .join(Alphanumeric.sample_string(&mut rand::thread_rng(), 16));
+    let data_dir = DataDirectory::get(Some(tmp_root), Network::RegTest).unwrap();
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(WalletState::new_from_wallet_secret(
+        &data_dir,
+        WalletSecret::new_random(),
+        &Args::default(),
+    ))
+}
+
+/// Kernels of `num_blocks` blocks, each announcing UTXOs to the wallet.
+fn kernels(wallet_state: &mut WalletState, num_blocks: usize) -> Vec<TransactionKernel> {
+    let address = wallet_state
+        .next_unused_spending_key(KeyType::Generation)
+        .to_address();
+    (0..num_blocks)
+        .map(|_| {
+            let outputs: TxOutputList = (0..NUM_ANNOUNCEMENTS)
+                .map(|_| {
+                    TxOutput::auto(
+                        wallet_state,
+                        address.clone(),
+                        NeptuneCoins::new(1),
+                        random(),
+                        UtxoNotificationMedium::OnChain,
+                    )
+                })
+                .collect::<Vec<_>>()
+                .into();
+            TransactionKernel {
+                inputs: vec![],
+                outputs: outputs.addition_records(),
+                public_announcements: outputs.public_announcements(),
+                fee: NeptuneCoins::new(1),
+                coinbase: None,
+                timestamp: Timestamp::now(),
+                mutator_set_hash: random(),
+            }
+        })
+        .collect()
+}
+
+#[divan::bench(args = NUM_BLOCKS)]
+fn block_by_block(bencher: Bencher, num_blocks: usize) {
+    let mut wallet_state = wallet_state();
+    let kernels = kernels(&mut wallet_state, num_blocks);
+    bencher.bench_local(|| {
+        kernels
+            .iter()
+            .map(|kernel| wallet_state.scan_batch_for_announced_utxos(&[kernel]))
+            .collect::<Vec<_>>()
+    });
+}
+
+#[divan::bench(args = NUM_BLOCKS)]
+fn batch(bencher: Bencher, num_blocks: usize) {
+    let mut wallet_state = wallet_state();
+    let kernels = kernels(&mut wallet_state, num_blocks);
+    let kernels = kernels.iter().collect::<Vec<_>>();
+    bencher.bench_local(|| wallet_state.scan_batch_for_announced_utxos(&kernels));
+}
@@ -0,0 +1,326 @@
+//! A library-facing facade for embedding a Neptune node in an application.
+//!
+//! The rest of this crate is public so that documentation gets generated, but
+//! modules such as `models::state` are internals that change with every
+//! release. Applications should depend on this module instead: everything
+//! defined or re-exported here is semver-guarded, meaning that breaking
+//! changes to it are only made in releases that bump the (pre-1.0: minor)
+//! version number. Enums and structs exposed here are `#[non_exhaustive]`,
+//! such that variants and fields can be added in any release.
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! use neptune_core::api;
+//!
+//! let node = api::start_node(api::Args::default()).await?;
+//! let mut events = node.subscribe().await;
+//! while let Ok(event) = events.recv().await {
+//!     if let api::NodeEvent::NewTip { height, .. } = event {
+//!         println!("new tip at height {height}");
+//!     }
+//! }
+//! node.shutdown().await
+//! # }
+//! ```
+
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+
+use anyhow::Result;
+use tarpc::context;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+pub use crate::config_models::cli_args::Args;
+pub use crate::config_models::network::Network;
+pub use crate::models::blockchain::block::block_header::BlockHeader;
+pub use crate::models::blockchain::block::block_height::BlockHeight;
+pub use crate::models::blockchain::block::block_info::BlockInfo;
+pub use crate::models::blockchain::block::block_selector::BlockSelector;
+pub use crate::models::blockchain::block::chain_params::ChainParams;
+pub use crate::models::blockchain::transaction::transaction_output::UtxoNotificationMedium;
+pub use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+pub use crate::models::proof_abstractions::timestamp::Timestamp;
+pub use crate::models::state::node_event::NodeEvent;
+pub use crate::models::state::transaction_kernel_id::TransactionKernelId;
+pub use crate::models::state::wallet::address::KeyType;
+pub use crate::models::state::wallet::address::ReceivingAddress;
+pub use crate::models::state::wallet::wallet_status::WalletStatus;
+pub use crate::prelude::twenty_first::math::digest::Digest;
+use crate::rpc_server::NeptuneRPCServer;
+use crate::rpc_server::RPC;
+
+/// Handle to a running, embedded node.
+///
+/// Dropping the handle does not stop the node; call [`Node::shutdown`] for a
+/// graceful shutdown.
+pub struct Node {
+    server: NeptuneRPCServer,
+    main_loop: JoinHandle<Result<()>>,
+}
+
+/// Start a node with the given configuration.
+///
+/// Returns once databases are open and all tasks are running. The main loop
+/// keeps running in the background until [`Node::shutdown`] is called, or
+/// until the process receives a termination signal.
+pub async fn start_node(args: Args) -> Result<Node> {
+    let components = crate::setup_node(args).await?;
+    let server = NeptuneRPCServer {
+        socket_address: SocketAddr::new(
+            Ipv4Addr::LOCALHOST.into(),
+            components.global_state_lock.cli().rpc_port,
+        ),
+        state: components.global_state_lock.clone(),
+        rpc_server_to_main_tx: components.rpc_server_to_main_tx.clone(),
+    };
+    let main_loop = tokio::spawn(components.run_main_loop());
+
+    Ok(Node { server, main_loop })
+}
+
+impl Node {
+    /// The network this node is running on.
+    pub fn network(&self) -> Network {
+        self.server.state.cli().network
+    }
+
+    /// Consensus constants in effect on this node's network.
+    pub fn chain_params(&self) -> ChainParams {
+        ChainParams::for_network(self.network())
+    }
+
+    /// Header of the current tip of the canonical chain.
+    pub async fn tip_header(&self) -> BlockHeader {
+        self.server
+            .state
+            .lock_guard()
+            .await
+            .chain
+            .light_state()
+            .header()
+            .clone()
+    }
+
+    /// Information about the specified block, if it is known.
+    pub async fn block_info(&self, block_selector: BlockSelector) -> Option<BlockInfo> {
+        self.server
+            .clone()
+            .block_info(context::current(), block_selector)
+            .await
+    }
+
+    /// Header of the specified block, if it is known.
+    pub async fn header(&self, block_selector: BlockSelector) -> Option<BlockHeader> {
+        self.server
+            .clone()
+            .header(context::current(), block_selector)
+            .await
+    }
+
+    /// Status of the funds in the wallet, relative to the current tip.
+    pub async fn wallet_status(&self) -> WalletStatus {
+        self.server.clone().wallet_status(context::current()).await
+    }
+
+    /// Sum of the confirmed, unspent and currently spendable UTXOs.
+    pub async fn synced_balance(&self) -> NeptuneCoins {
+        self.server.clone().synced_balance(context::current()).await
+    }
+
+    /// Derive the next unused receiving address of the given type.
+    pub async fn next_receiving_address(&self, key_type: KeyType) -> ReceivingAddress {
+        self.server
+            .clone()
+            .next_receiving_address(context::current(), key_type)
+            .await
+    }
+
+    /// Send coins to multiple recipients. Returns the ID of the resulting
+    /// transaction, or `None` if it could not be created.
+    ///
+    /// Fails if the wallet requires a spend passphrase; use
+    /// [`Node::send_to_many_with_spend_passphrase`] for such wallets.
+    ///
+    /// See [`RPC::send_to_many`] for the meaning of the arguments.
+    pub async fn send_to_many(
+        &self,
+        outputs: Vec<(ReceivingAddress, NeptuneCoins)>,
+        owned_utxo_notification_medium: UtxoNotificationMedium,
+        fee: NeptuneCoins,
+    ) -> Option<TransactionKernelId> {
+        self.send_to_many_with_spend_passphrase(outputs, owned_utxo_notification_medium, fee, None)
+            .await
+    }
+
+    /// Like [`Node::send_to_many`], authorized by the wallet's spend
+    /// passphrase, if one is set.
+    pub async fn send_to_many_with_spend_passphrase(
+        &self,
+        outputs: Vec<(ReceivingAddress, NeptuneCoins)>,
+        owned_utxo_notification_medium: UtxoNotificationMedium,
+        fee: NeptuneCoins,
+        spend_passphrase: Option<String>,
+    ) -> Option<TransactionKernelId> {
+        self.server
+            .clone()
+            .send_to_many(
+                context::current(),
+                outputs,
+                owned_utxo_notification_medium,
+                fee,
+                spend_passphrase,
+            )
+            .await
+    }
+
+    /// Subscribe to events such as new tips and mempool changes.
+    pub async fn subscribe(&self) -> broadcast::Receiver<NodeEvent> {
+        self.server.state.lock_guard().await.subscribe_to_events()
+    }
+
+    /// Shut the node down gracefully and wait for it to finish.
+    pub async fn shutdown(self) -> Result<()> {
+        self.server.clone().shutdown(context::current()).await;
+        self.main_loop.await?
+    }
+}
+
+#[cfg(test)]
+mod api_tests {
+    use itertools::Itertools;
+
+    /// Signatures of the public items of this module. Changing this list
+    /// changes the semver-guarded API, so do it deliberately.
+    const PUBLIC_API: &[&str] = &[
+        "pub use crate::config_models::cli_args::Args",
+        "pub use crate::config_models::network::Network",
+        "pub use crate::models::blockchain::block::block_header::BlockHeader",
+        "pub use crate::models::blockchain::block::block_height::BlockHeight",
+        "pub use crate::models::blockchain::block::block_info::BlockInfo",
+        "pub use crate::models::blockchain::block::block_selector::BlockSelector",
+        "pub use crate::models::blockchain::block::chain_params::ChainParams",
+        "pub use crate::models::blockchain::transaction::transaction_output::UtxoNotificationMedium",
+        "pub use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins",
+        "pub use crate::models::proof_abstractions::timestamp::Timestamp",
+        "pub use crate::models::state::node_event::NodeEvent",
+        "pub use crate::models::state::transaction_kernel_id::TransactionKernelId",
+        "pub use crate::models::state::wallet::address::KeyType",
+        "pub use crate::models::state::wallet::address::ReceivingAddress",
+        "pub use crate::models::state::wallet::wallet_status::WalletStatus",
+        "pub use crate::prelude::twenty_first::math::digest::Digest",
+        "pub struct Node",
+        "pub async fn start_node(args: Args) -> Result<Node>",
+        "pub fn network(&self) -> Network",
+        "pub fn chain_params(&self) -> ChainParams",
+        "pub async fn tip_header(&self) -> BlockHeader",
+        "pub async fn block_info(&self, block_selector: BlockSelector) -> Option<BlockInfo>",
+        "pub async fn header(&self, block_selector: BlockSelector) -> Option<BlockHeader>",
+        "pub async fn wallet_status(&self) -> WalletStatus",
+        "pub async fn synced_balance(&self) -> NeptuneCoins",
+        "pub async fn next_receiving_address(&self, key_type: KeyType) -> ReceivingAddress",
+        "pub async fn send_to_many(&self, outputs: Vec<(ReceivingAddress, NeptuneCoins)>, owned_utxo_notification_medium: UtxoNotificationMedium, fee: NeptuneCoins) -> Option<TransactionKernelId>",
+        "pub async fn send_to_many_with_spend_passphrase(&self, outputs: Vec<(ReceivingAddress, NeptuneCoins)>, owned_utxo_notification_medium: UtxoNotificationMedium, fee: NeptuneCoins, spend_passphrase: Option<String>) -> Option<TransactionKernelId>",
+        "pub async fn subscribe(&self) -> broadcast::Receiver<NodeEvent>",
+        "pub async fn shutdown(self) -> Result<()>",
+    ];
+
+    /// Signatures of the items declared `pub` in the given source, with
+    /// whitespace normalized.
+    fn public_items(source: &str) -> Vec<String> {
+        let mut items = vec![];
+        let mut remainder = source;
+        while let Some(start) = remainder
+            .lines()
+            .find(|line| line.trim_start().starts_with("pub "))
+            .map(|line| line.as_ptr() as usize - remainder.as_ptr() as usize)
+        {
+            let item = &remainder[start..];
+            let end = item.find(['{', ';']).unwrap();
+            items.push(
+                item[..end]
+                    .split_whitespace()
+                    .join(" ")
+                    .replace("( ", "(")
+                    .replace(", )", ")"),
+            );
+            remainder = &item[end..];
+        }
+
+        items
+    }
+
+    #[test]
+    fn public_api_matches_snapshot() {
+        let source = include_str!("api.rs");
+        let source = &source[..source.find("#[cfg(test)]").unwrap()];
+        let public_api = public_items(source);
+
+        assert_eq!(
+            PUBLIC_API, public_api,
+            "public API of `api` changed; if intended, update `PUBLIC_API`"
+        );
+    }
+
+    #[test]
+    fn re_exported_types_are_non_exhaustive() {
+        let definitions = [
+            (
+                include_str!("config_models/cli_args.rs"),
+                "pub struct Args ",
+            ),
+            (
+                include_str!("config_models/network.rs"),
+                "pub enum Network ",
+            ),
+            (
+                include_str!("models/blockchain/block/block_header.rs"),
+                "pub struct BlockHeader ",
+            ),
+            (
+                include_str!("models/blockchain/block/block_info.rs"),
+                "pub struct BlockInfo ",
+            ),
+            (
+                include_str!("models/blockchain/block/block_selector.rs"),
+                "pub enum BlockSelector ",
+            ),
+            (
+                include_str!("models/blockchain/block/chain_params.rs"),
+                "pub struct ChainParams ",
+            ),
+            (
+                include_str!("models/blockchain/transaction/transaction_output.rs"),
+                "pub enum UtxoNotificationMedium ",
+            ),
+            (
+                include_str!("models/proof_abstractions/timestamp.rs"),
+                "pub struct Timestamp(",
+            ),
+            (
+                include_str!("models/state/node_event.rs"),
+                "pub enum NodeEvent ",
+            ),
+            (
+                include_str!("models/state/wallet/address/address_type.rs"),
+                "pub enum KeyType ",
+            ),
+            (
+                include_str!("models/state/wallet/address/address_type.rs"),
+                "pub enum ReceivingAddress ",
+            ),
+            (
+                include_str!("models/state/wallet/wallet_status.rs"),
+                "pub struct WalletStatus ",
+            ),
+        ];
+
+        for (source, definition) in definitions {
+            let non_exhaustive_definition = format!("#[non_exhaustive]\n{definition}");
+            assert!(
+                source.contains(&non_exhaustive_definition),
+                "`{definition}` must be `#[non_exhaustive]`"
+            );
+        }
+    }
+}
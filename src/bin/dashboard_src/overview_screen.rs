@@ -12,6 +12,8 @@ use neptune_core::config_models::network::Network;
 use neptune_core::models::blockchain::block::block_header::BlockHeader;
 use neptune_core::models::blockchain::block::block_height::BlockHeight;
 use neptune_core::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use neptune_core::models::proof_abstractions::tasm::proving_progress::ProvingJobStatus;
+use neptune_core::models::state::sync_progress::SyncProgressReport;
 use neptune_core::prelude::twenty_first;
 use neptune_core::rpc_server::RPCClient;
 use num_traits::Zero;
@@ -42,8 +44,10 @@ pub struct OverviewData {
     synchronization_percentage: Option<f64>,
 
     network: Network,
-    syncing: bool,
+    sync_progress: Option<SyncProgressReport>,
     is_mining: Option<bool>,
+    proving_jobs: Vec<ProvingJobStatus>,
+    unknown_consensus_rules: bool,
     tip_digest: Option<Digest>,
     block_header: Option<BlockHeader>,
     block_interval: Option<u64>,
@@ -79,8 +83,10 @@ impl OverviewData {
             confirmations: Default::default(),
             synchronization_percentage: Default::default(),
             network,
-            syncing: Default::default(),
+            sync_progress: Default::default(),
             is_mining: Default::default(),
+            proving_jobs: Default::default(),
+            unknown_consensus_rules: Default::default(),
             listen_address,
             tip_digest: Default::default(),
             block_header: Default::default(),
@@ -112,7 +118,9 @@ impl OverviewData {
             listen_address: None,
             network: Network::Testnet,
             is_mining: Some(false),
-            syncing: false,
+            proving_jobs: vec![],
+            unknown_consensus_rules: false,
+            sync_progress: None,
             tip_digest: Some(
                 neptune_core::models::blockchain::block::Block::genesis_block(Network::Testnet)
                     .hash(),
@@ -219,11 +227,13 @@ impl OverviewScreen {
                                 own_overview_data.mempool_tx_count = Some(resp.mempool_tx_count.try_into().unwrap());
                                 own_overview_data.peer_count=resp.peer_count;
                                 own_overview_data.authenticated_peer_count=Some(0);
-                                own_overview_data.syncing=resp.syncing;
+                                own_overview_data.sync_progress=resp.sync_progress;
                                 own_overview_data.available_balance = Some(resp.available_balance);
                                 own_overview_data.available_unconfirmed_balance = Some(resp.available_unconfirmed_balance);
                                 own_overview_data.timelocked_balance = Some(resp.timelocked_balance);
                                 own_overview_data.is_mining = resp.is_mining;
+                                own_overview_data.proving_jobs = resp.proving_jobs;
+                                own_overview_data.unknown_consensus_rules = resp.unknown_consensus_rules;
                                 own_overview_data.confirmations = resp.confirmations;
                                 own_overview_data.cpu_temperature = resp.cpu_temp;
                             }
@@ -409,11 +419,27 @@ impl Widget for OverviewScreen {
         lines = vec![];
 
         lines.push(format!("network: {}", data.network));
+        if data.unknown_consensus_rules {
+            lines.push(
+                "WARNING: peers follow consensus rules this version does not know. Upgrade!"
+                    .to_string(),
+            );
+        }
 
-        lines.push(format!("synchronizing: {}", data.syncing));
+        lines.push(match &data.sync_progress {
+            Some(sync_progress) => format!("synchronizing: {sync_progress}"),
+            None => "synchronizing: false".to_string(),
+        });
 
         lines.push(format!("mining: {}", dashifnotset!(data.is_mining)));
 
+        let prover_status = if data.proving_jobs.is_empty() {
+            "idle".to_string()
+        } else {
+            data.proving_jobs.iter().join("; ")
+        };
+        lines.push(format!("prover: {prover_status}"));
+
         let tip_digest_hex = data.tip_digest.map(|d| d.to_hex());
         lines.push(format!("tip (hex): {}\n", dashifnotset!(tip_digest_hex),));
         lines.push(format!("tip (raw): {}\n\n", dashifnotset!(data.tip_digest),));
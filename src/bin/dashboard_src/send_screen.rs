@@ -94,7 +94,8 @@ impl SendScreen {
         let valid_address = match maybe_valid_address {
             Some(add) => add,
             None => {
-                *notice_arc.lock().await = "Invalid address.".to_string();
+                *notice_arc.lock().await =
+                    "Invalid address, or address of another network.".to_string();
                 *focus_arc.lock().await = SendScreenWidget::Address;
                 return;
             }
@@ -143,6 +144,8 @@ impl SendScreen {
                 valid_address,
                 UtxoNotificationMedium::OnChain,
                 fee,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -3,25 +3,53 @@ use std::io::stdout;
 use std::io::Write;
 use std::net::IpAddr;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
+use std::time::SystemTime;
 
+use anyhow::anyhow;
 use anyhow::bail;
+use anyhow::Context;
 use anyhow::Result;
 use clap::CommandFactory;
 use clap::Parser;
 use clap_complete::generate;
 use clap_complete::Shell;
+use itertools::Itertools;
+use neptune_core::api::Digest;
+use neptune_core::api::Timestamp;
+use neptune_core::api::TransactionKernelId;
 use neptune_core::config_models::data_directory::DataDirectory;
 use neptune_core::config_models::network::Network;
 use neptune_core::models::blockchain::block::block_selector::BlockSelector;
+use neptune_core::models::blockchain::transaction::memo::Memo;
+use neptune_core::models::blockchain::transaction::partially_proved::PsntFinalization;
+use neptune_core::models::blockchain::transaction::transaction_kernel::TransactionKernelField;
+use neptune_core::models::blockchain::transaction::transaction_kernel::TransactionKernelFieldDisclosure;
 use neptune_core::models::blockchain::transaction::transaction_output::UtxoNotificationMedium;
 use neptune_core::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use neptune_core::models::state::checkpoint_beacon::SignedCheckpoint;
+use neptune_core::models::state::db_diagnostics::DatabaseKind;
+use neptune_core::models::state::spend_audit_log::SpendAuditEntry;
 use neptune_core::models::state::wallet::address::KeyType;
 use neptune_core::models::state::wallet::address::ReceivingAddress;
+use neptune_core::models::state::wallet::coin_selection::CoinSelectionPolicy;
 use neptune_core::models::state::wallet::coin_with_possible_timelock::CoinWithPossibleTimeLock;
+use neptune_core::models::state::wallet::encrypted_secret::EncryptedWalletSecret;
+use neptune_core::models::state::wallet::expected_utxo_bundle::ExpectedUtxoBundle;
+use neptune_core::models::state::wallet::hd_derivation::AccountDescriptor;
+use neptune_core::models::state::wallet::hd_derivation::DerivationChain;
+use neptune_core::models::state::wallet::key_rotation::NEXT_WALLET_SECRET_FILE_NAME;
+use neptune_core::models::state::wallet::spend_authorization::SpendAuthorization;
 use neptune_core::models::state::wallet::wallet_status::WalletStatus;
 use neptune_core::models::state::wallet::WalletSecret;
+use neptune_core::prelude::twenty_first::math::b_field_element::BFieldElement;
+use neptune_core::rpc_client::NeptuneRpcClient;
+use neptune_core::rpc_client::RpcClientConfig;
 use neptune_core::rpc_server::RPCClient;
+use neptune_core::rpc_server::MAX_BLOCK_SUBSCRIPTION_WAIT;
+use neptune_core::safe_mode::SafeModeRPCClient;
 use tarpc::client;
 use tarpc::context;
 use tarpc::tokio_serde::formats::Json;
@@ -71,9 +99,14 @@ impl TransactionOutput {
     pub fn to_receiving_address_amount_tuple(
         &self,
         network: Network,
+        allow_other_network: bool,
     ) -> Result<(ReceivingAddress, NeptuneCoins)> {
         Ok((
-            ReceivingAddress::from_bech32m(&self.address, network)?,
+            ReceivingAddress::from_bech32m_for_network(
+                &self.address,
+                network,
+                allow_other_network,
+            )?,
             self.amount,
         ))
     }
@@ -86,25 +119,65 @@ enum Command {
 
     /******** READ STATE ********/
     Network,
+    ChainParams,
+
+    /// Show how the difficulty would evolve from the tip on if the next blocks
+    /// were spaced apart by the given numbers of seconds.
+    SimulateDifficulty {
+        #[clap(required = true)]
+        block_intervals_secs: Vec<u64>,
+
+        /// target block interval in seconds, if not the network's
+        #[clap(long)]
+        target_block_interval_secs: Option<u64>,
+    },
     OwnListenAddressForPeers,
     OwnInstanceId,
     BlockHeight,
+    /// Show synchronization progress by stage, with rates and ETAs
+    SyncProgress,
+
+    /// Check whether peers follow consensus rules this version does not know
+    ForkWatch,
     BlockInfo {
-        /// one of: `genesis, tip, height/<n>, digest/<hex>`
+        /// one of: `genesis, tip, height/<n>, digest/<hex>, timestamp/<t>, depth/<n>`
         block_selector: BlockSelector,
     },
+
+    /// Show whether the block with the given digest (hex) is on the chain
+    /// neptune-core follows.
+    IsCanonical {
+        block_digest: String,
+    },
     Confirmations,
     PeerInfo,
     AllSanctionedPeers,
+
+    /// the recorded violations of the peer at an IP, with their evidence
+    PeerViolations {
+        ip: IpAddr,
+    },
     TipDigest,
     LatestTipDigests {
         n: usize,
     },
     TipHeader,
     Header {
-        /// one of: `genesis, tip, height/<n>, digest/<hex>`
+        /// one of: `genesis, tip, height/<n>, digest/<hex>, timestamp/<t>, depth/<n>`
         block_selector: BlockSelector,
     },
+
+    /// List the canonical blocks containing public announcements for a
+    /// receiver identifier. Requires the node to run with `--build-tx-index`.
+    BlocksForAnnouncement {
+        receiver_identifier: u64,
+    },
+
+    /// List the UTXOs received by the wallet with a lock script hash, given in
+    /// hex. Requires the node to run with `--build-tx-index`.
+    UtxosForLockScriptHash {
+        lock_script_hash: String,
+    },
     SyncedBalance,
     SyncedBalanceUnconfirmed,
     WalletStatus,
@@ -113,6 +186,50 @@ enum Command {
     MempoolTxCount,
     MempoolSize,
 
+    /// Estimate the fee per 1000 bytes of transaction for inclusion within
+    /// `target_blocks` blocks
+    EstimateFee {
+        #[clap(default_value = "1")]
+        target_blocks: usize,
+    },
+
+    /// Show mempool transactions grouped by fee, highest fee first, with the
+    /// bytes queued ahead of each group
+    MempoolFeeHistogram {
+        #[clap(default_value = "10")]
+        bucket_count: usize,
+    },
+
+    /// Disclose one kernel field of a transaction in the mempool or sent by
+    /// this wallet, e.g., `fee` or `timestamp`, as JSON
+    DiscloseKernelField {
+        txid: TransactionKernelId,
+        field: TransactionKernelField,
+    },
+
+    /// Show UTXO count, age of outputs, and Bloom filter density of the chain
+    UtxoSetStats,
+    BeaconStatus,
+
+    /// Export monitored UTXOs as JSON for audit tooling
+    WalletAuditExport,
+
+    /// List memos of UTXOs sent and received
+    Memos,
+
+    /// List own transactions held back because they spend unconfirmed change
+    UnconfirmedSpends,
+
+    /// Show UTXO count, balances, and sync backlog of the wallet
+    WalletSummary,
+
+    /// Print the JSON schemas of all RPC methods, for generating clients in
+    /// other languages
+    RpcSchemas,
+
+    /// Print blocks as they become canonical, until interrupted
+    SubscribeBlocks,
+
     /******** CHANGE STATE ********/
     Shutdown,
     ClearAllStandings,
@@ -123,6 +240,16 @@ enum Command {
         amount: NeptuneCoins,
         address: String,
         fee: NeptuneCoins,
+
+        /// note for the receiver, encrypted along with the UTXO notification
+        #[clap(long)]
+        memo: Option<Memo>,
+
+        /// which UTXOs to spend: oldest-first, largest-first, smallest-first,
+        /// branch-and-bound or privacy-preferring. Defaults to the node's
+        /// `--coin-selection`.
+        #[clap(long, conflicts_with = "memo")]
+        coin_selection: Option<CoinSelectionPolicy>,
     },
     SendToMany {
         /// format: address:amount address:amount ...
@@ -132,8 +259,203 @@ enum Command {
     },
     PauseMiner,
     RestartMiner,
+
+    /// Print the latest block template for external miners as JSON.
+    GetBlockTemplate,
+
+    /// Print what changed since the block template with the given proposal
+    /// id as JSON, or the whole latest template if that one is outdated.
+    GetBlockTemplateUpdate {
+        known_proposal_id: Option<u64>,
+    },
+
+    /// Submit a solution to a block template obtained with `get-block-template`.
+    SubmitBlockSolution {
+        proposal_id: u64,
+
+        /// the three elements of the nonce
+        #[clap(num_args = 3, required = true)]
+        nonce: Vec<u64>,
+
+        /// timestamp of the block, in milliseconds since the Unix epoch, if
+        /// it differs from the template's
+        #[clap(long)]
+        timestamp_millis: Option<u64>,
+    },
+
+    /// Generate blocks on top of the tip instantly, paying the block subsidy
+    /// to the given address. RegTest only.
+    GenerateBlocks {
+        num_blocks: usize,
+        address: String,
+
+        /// prove the blocks as mined blocks are, and share them with peers
+        #[clap(long)]
+        real_proofs: bool,
+    },
+
+    /// Broadcast a transaction constructed elsewhere, read from a file holding
+    /// its bincode encoding.
+    BroadcastTransaction {
+        file: PathBuf,
+    },
+
+    /// Create a partially proved transaction (PSNT) funded by this wallet,
+    /// and write it to a file.
+    CreatePsnt {
+        /// format: address:amount address:amount ...
+        #[clap(value_parser, num_args = 1.., required=true, value_delimiter = ' ')]
+        outputs: Vec<TransactionOutput>,
+        fee: NeptuneCoins,
+
+        /// file to write the PSNT to
+        #[clap(long)]
+        out: PathBuf,
+    },
+
+    /// Combine PSNTs read from files into one, and write it to a file.
+    CombinePsnt {
+        #[clap(num_args = 1.., required = true)]
+        files: Vec<PathBuf>,
+
+        /// file to write the combined PSNT to
+        #[clap(long)]
+        out: PathBuf,
+    },
+
+    /// Prove the lock scripts of this wallet's inputs of a PSNT read from a
+    /// file, and broadcast the transaction if it is then complete. Otherwise
+    /// the PSNT is written back to the file.
+    FinalizePsnt {
+        file: PathBuf,
+
+        /// the most this wallet may spend on the transaction: the amount of
+        /// its inputs less that of the outputs paying it back, typically what
+        /// it pays others plus its share of the fee
+        max_net_spend: NeptuneCoins,
+    },
+
+    /// Show the most recent blocks mined by this node, with their revenue and
+    /// the age of the template they were mined on.
+    MinedBlocks {
+        #[clap(default_value = "20")]
+        max_num: usize,
+    },
+
+    /// Show which consensus rules the most recently validated blocks passed
+    /// or failed, and how long each check took. Requires a node running with
+    /// `--trace-validation`.
+    ValidationReports {
+        #[clap(default_value = "10")]
+        max_num: usize,
+    },
+
+    /// Show the state transitions journaled in the last `hours` hours.
+    EventJournal {
+        #[clap(long, default_value = "24")]
+        hours: usize,
+        #[clap(long, default_value = "100")]
+        max_num: usize,
+    },
     PruneAbandonedMonitoredUtxos,
 
+    /// Verify the hash chain of the node's spend audit log, and write the log
+    /// to `file` as JSON lines.
+    ExportSpendAuditLog {
+        file: PathBuf,
+    },
+
+    /// Stop accepting peers and transactions and flush all databases, or
+    /// resume normal operation with `--off`.
+    MaintenanceMode {
+        #[clap(long)]
+        off: bool,
+    },
+
+    /// Rotate the wallet to a new seed: start, then sweep until the status
+    /// says to finish, then finish.
+    KeyRotationStart,
+    KeyRotationStatus,
+    KeyRotationSweep {
+        fee: NeptuneCoins,
+    },
+    KeyRotationFinish,
+
+    /// Import a bundle of expected UTXOs, given as a JSON file, from a
+    /// payment processor. With `--signer`, the bundle must be signed by that
+    /// key, given as hex.
+    ImportExpectedUtxos {
+        file: PathBuf,
+
+        #[clap(long)]
+        signer: Option<String>,
+    },
+
+    /// Export the descriptor of an account, from which its receiving
+    /// addresses can be derived offline with `derive-account-address`. The
+    /// descriptor cannot spend but reveals the account's incoming UTXOs.
+    ExportAccountDescriptor {
+        account: u32,
+    },
+
+    /// Unlock a wallet encrypted at rest, for `timeout` seconds if given. The
+    /// passphrase is taken from `NEPTUNE_WALLET_PASSPHRASE` if set, otherwise
+    /// read from standard input.
+    WalletUnlock {
+        timeout: Option<u64>,
+    },
+    WalletLock,
+
+    /// Submit a signed checkpoint, given as JSON, to the checkpoint beacon.
+    BeaconSubmitCheckpoint {
+        checkpoint: String,
+    },
+
+    /// Ask connected peers to connect back to this node's listen port
+    CheckReachability,
+
+    /// List the coinjoin sessions coordinated by connected peers. Run again
+    /// after a few seconds for the answers to the refresh it triggers.
+    CoinJoinSessions,
+
+    /// Contribute to the coinjoin session of a coordinator listed by
+    /// `coin-join-sessions`. Proves a single proof, which takes minutes.
+    CoinJoinJoin {
+        coordinator: SocketAddr,
+        fee: NeptuneCoins,
+    },
+
+    /******** SAFE MODE ********/
+    /// List the database issues that made the node start in safe mode
+    SafeModeIssues,
+
+    /// Show the block digests recorded for `count` heights from `from`
+    SafeModeDumpIndex {
+        from: u64,
+
+        #[clap(default_value = "100")]
+        count: u64,
+    },
+
+    /// Check that the canonical blocks with heights `from` through `to` can
+    /// be read
+    SafeModeVerify {
+        from: u64,
+        to: u64,
+    },
+
+    /// Copy the wallet files to a directory on the node's machine
+    SafeModeExportWallet {
+        target_dir: PathBuf,
+    },
+
+    /// Move a database (`chain`, `peers` or `wallet`) into quarantine, such
+    /// that it is rebuilt on the next start
+    SafeModeQuarantine {
+        database: DatabaseKind,
+    },
+    SafeModeShutdown,
+
     /******** WALLET ********/
     GenerateWallet {
         #[clap(long, default_value_t=Network::default())]
@@ -151,6 +473,44 @@ enum Command {
         #[clap(long, default_value_t=Network::default())]
         network: Network,
     },
+
+    /// Encrypt the wallet secret with a passphrase and delete the plaintext
+    /// wallet file. Then start neptune-core with `--encrypted-wallet`.
+    EncryptWallet {
+        #[clap(long, default_value_t=Network::default())]
+        network: Network,
+    },
+
+    /// Derive a receiving address from an exported account descriptor,
+    /// without connecting to neptune-core.
+    DeriveAccountAddress {
+        descriptor: String,
+        index: u16,
+
+        /// derive a change address instead
+        #[clap(long)]
+        change: bool,
+    },
+
+    /// Verify a disclosed kernel field against the kernel MAST hash (hex) of
+    /// the transaction, without connecting to neptune-core.
+    VerifyKernelDisclosure {
+        kernel_mast_hash: String,
+
+        /// file with the JSON output of `disclose-kernel-field`
+        disclosure_file: PathBuf,
+    },
+
+    /// Set the passphrase that sends over RPC must carry, or remove it. Takes
+    /// effect when neptune-core is restarted.
+    SetSpendPassphrase {
+        #[clap(long, default_value_t=Network::default())]
+        network: Network,
+
+        /// remove the passphrase, such that sends need none
+        #[clap(long)]
+        remove: bool,
+    },
 }
 
 #[derive(Debug, Parser)]
@@ -165,6 +525,92 @@ struct Config {
 
     #[structopt(long, short, default_value = "alpha")]
     pub network: Network,
+
+    /// Send to addresses of networks other than the one neptune-core runs on.
+    /// Funds sent to such an address are likely lost.
+    #[clap(long)]
+    pub allow_network_mismatch: bool,
+}
+
+fn is_safe_mode_command(command: &Command) -> bool {
+    matches!(
+        command,
+        Command::SafeModeIssues
+            | Command::SafeModeDumpIndex { .. }
+            | Command::SafeModeVerify { .. }
+            | Command::SafeModeExportWallet { .. }
+            | Command::SafeModeQuarantine { .. }
+            | Command::SafeModeShutdown
+    )
+}
+
+async fn safe_mode_command(server_addr: SocketAddr, command: Command) -> Result<()> {
+    let transport = tarpc::serde_transport::tcp::connect(server_addr, Json::default)
+        .await
+        .with_context(|| format!("could not connect to node at {server_addr}"))?;
+    let client = SafeModeRPCClient::new(client::Config::default(), transport).spawn();
+    let ctx = context::current();
+
+    match command {
+        Command::SafeModeIssues => {
+            let issues = client.startup_issues(ctx).await?;
+            if issues.is_empty() {
+                println!("No issues found at startup.");
+            }
+            for issue in issues {
+                println!("{issue}  [quarantine: {}]", issue.database());
+            }
+        }
+        Command::SafeModeDumpIndex { from, count } => {
+            let dump = client
+                .dump_block_index(ctx, from.into(), count)
+                .await?
+                .map_err(|err| anyhow!(err))?;
+            println!("tip: {}", dump.tip_digest);
+            for (height, digests) in dump.heights {
+                println!("{height}: {}", digests.iter().join(", "));
+            }
+        }
+        Command::SafeModeVerify { from, to } => {
+            let issues = client
+                .verify_block_range(ctx, from.into(), to.into())
+                .await?
+                .map_err(|err| anyhow!(err))?;
+            if issues.is_empty() {
+                println!("No issues found.");
+            }
+            for issue in issues {
+                println!("{issue}");
+            }
+        }
+        Command::SafeModeExportWallet { target_dir } => {
+            let files = client
+                .export_wallet_files(ctx, target_dir)
+                .await?
+                .map_err(|err| anyhow!(err))?;
+            for file in files {
+                println!("exported {}", file.display());
+            }
+        }
+        Command::SafeModeQuarantine { database } => {
+            let target_dir = client
+                .quarantine(ctx, database)
+                .await?
+                .map_err(|err| anyhow!(err))?;
+            println!(
+                "Moved {database} databases to {}. They are rebuilt when neptune-core is \
+                restarted without --safe-mode.",
+                target_dir.display()
+            );
+        }
+        Command::SafeModeShutdown => {
+            client.shutdown(ctx).await?;
+            println!("Node is shutting down.");
+        }
+        _ => unreachable!("not a safe mode command"),
+    }
+
+    Ok(())
 }
 
 #[tokio::main]
@@ -312,12 +758,126 @@ async fn main() -> Result<()> {
             }
             return Ok(());
         }
+        Command::SetSpendPassphrase { network, remove } => {
+            let data_dir = DataDirectory::get(None, network)?;
+            let wallet_dir = data_dir.wallet_directory_path();
+            if remove {
+                SpendAuthorization::remove(&wallet_dir)?;
+                println!("Removed spend passphrase. Restart neptune-core for this to take effect.");
+                return Ok(());
+            }
+
+            let passphrase = read_line("New spend passphrase: ")?;
+            if passphrase.is_empty() {
+                bail!("Spend passphrase must not be empty.");
+            }
+            if read_line("Repeat spend passphrase: ")? != passphrase {
+                bail!("Passphrases do not match.");
+            }
+            DataDirectory::create_dir_if_not_exists(&wallet_dir).await?;
+            SpendAuthorization::new(&passphrase)?.store(&wallet_dir)?;
+            println!(
+                "Spend passphrase stored in {}. Restart neptune-core for this to take effect.",
+                SpendAuthorization::file_path(&wallet_dir).display()
+            );
+            return Ok(());
+        }
+        Command::EncryptWallet { network } => {
+            let data_dir = DataDirectory::get(None, network)?;
+            let wallet_dir = data_dir.wallet_directory_path();
+            let wallet_file = WalletSecret::wallet_secret_path(&wallet_dir);
+            if EncryptedWalletSecret::file_path(&wallet_dir).exists() {
+                bail!("Wallet in {} is already encrypted.", wallet_dir.display());
+            }
+            let next_wallet_secret_file = wallet_dir.join(NEXT_WALLET_SECRET_FILE_NAME);
+            if next_wallet_secret_file.exists() {
+                bail!(
+                    "Key rotation in progress; finish it before encrypting the wallet, \
+                    since the new seed in {} would stay unencrypted.",
+                    next_wallet_secret_file.display()
+                );
+            }
+            let wallet_secret = WalletSecret::read_from_file(&wallet_file)?;
+
+            println!("Make sure you have a backup of the seed phrase before you continue.");
+            let passphrase = read_line("New wallet passphrase: ")?;
+            if passphrase.is_empty() {
+                bail!("Wallet passphrase must not be empty.");
+            }
+            if read_line("Repeat wallet passphrase: ")? != passphrase {
+                bail!("Passphrases do not match.");
+            }
+
+            let encrypted = EncryptedWalletSecret::encrypt(&wallet_secret, &passphrase)?;
+            if encrypted.decrypt(&passphrase)? != wallet_secret {
+                bail!("Encrypted wallet does not decrypt to the wallet; nothing changed.");
+            }
+            encrypted.store(&wallet_dir)?;
+            std::fs::remove_file(&wallet_file)?;
+            println!(
+                "Encrypted wallet stored in {} and {} deleted. Start neptune-core with --encrypted-wallet.",
+                EncryptedWalletSecret::file_path(&wallet_dir).display(),
+                wallet_file.display()
+            );
+            return Ok(());
+        }
+        Command::DeriveAccountAddress {
+            descriptor,
+            index,
+            change,
+        } => {
+            let descriptor = AccountDescriptor::from_bech32m(&descriptor)?;
+            let chain = if change {
+                DerivationChain::Change
+            } else {
+                DerivationChain::Receiving
+            };
+            let address = descriptor.derive_address(chain, index);
+            println!("{}", address.to_bech32m(descriptor.network)?);
+            return Ok(());
+        }
+        Command::VerifyKernelDisclosure {
+            kernel_mast_hash,
+            disclosure_file,
+        } => {
+            let kernel_mast_hash = Digest::try_from_hex(kernel_mast_hash.trim())?;
+            let disclosure: TransactionKernelFieldDisclosure =
+                serde_json::from_str(&std::fs::read_to_string(&disclosure_file)?)?;
+            if !disclosure.verify(kernel_mast_hash) {
+                bail!("disclosure is not valid for this kernel");
+            }
+
+            let value = match disclosure.field {
+                TransactionKernelField::Fee => disclosure.decode::<NeptuneCoins>()?.to_string(),
+                TransactionKernelField::Coinbase => {
+                    match disclosure.decode::<Option<NeptuneCoins>>()? {
+                        Some(coinbase) => coinbase.to_string(),
+                        None => "none".to_string(),
+                    }
+                }
+                TransactionKernelField::Timestamp => {
+                    disclosure.decode::<Timestamp>()?.standard_format()
+                }
+                _ => format!("{} field elements", disclosure.value.len()),
+            };
+            println!("valid: {} = {value}", disclosure.field);
+            return Ok(());
+        }
         _ => {}
     }
 
+    // a node in safe mode serves a different RPC
+    if is_safe_mode_command(&args.command) {
+        return safe_mode_command(args.server_addr, args.command).await;
+    }
+
     // all other operations need a connection to the server
-    let transport = tarpc::serde_transport::tcp::connect(args.server_addr, Json::default);
-    let client = RPCClient::new(client::Config::default(), transport.await?).spawn();
+    let client = NeptuneRpcClient::connect(RpcClientConfig {
+        server_addr: args.server_addr,
+        ..Default::default()
+    })
+    .await?
+    .into_inner();
     let ctx = context::current();
 
     match args.command {
@@ -325,7 +885,17 @@ async fn main() -> Result<()> {
         | Command::GenerateWallet { .. }
         | Command::WhichWallet { .. }
         | Command::ExportSeedPhrase { .. }
-        | Command::ImportSeedPhrase { .. } => unreachable!("Case should be handled earlier."),
+        | Command::ImportSeedPhrase { .. }
+        | Command::SetSpendPassphrase { .. }
+        | Command::EncryptWallet { .. }
+        | Command::DeriveAccountAddress { .. }
+        | Command::VerifyKernelDisclosure { .. }
+        | Command::SafeModeIssues
+        | Command::SafeModeDumpIndex { .. }
+        | Command::SafeModeVerify { .. }
+        | Command::SafeModeExportWallet { .. }
+        | Command::SafeModeQuarantine { .. }
+        | Command::SafeModeShutdown => unreachable!("Case should be handled earlier."),
 
         /******** READ STATE ********/
         Command::ListCoins => {
@@ -336,6 +906,31 @@ async fn main() -> Result<()> {
             let network = client.network(ctx).await?;
             println!("{network}")
         }
+        Command::ChainParams => {
+            let chain_params = client.chain_params(ctx).await?;
+            println!("{chain_params}")
+        }
+        Command::SimulateDifficulty {
+            block_intervals_secs,
+            target_block_interval_secs,
+        } => {
+            let block_intervals = block_intervals_secs
+                .into_iter()
+                .map(Timestamp::seconds)
+                .collect();
+            let target_block_interval = target_block_interval_secs.map(Timestamp::seconds);
+            let blocks = client
+                .simulate_difficulty(ctx, block_intervals, target_block_interval)
+                .await?;
+            for block in blocks {
+                println!(
+                    "height {}: {} s after predecessor, difficulty {}",
+                    block.height,
+                    block.block_interval.0.value() / 1000,
+                    block.difficulty
+                );
+            }
+        }
         Command::OwnListenAddressForPeers => {
             let own_listen_addres = client.own_listen_address_for_peers(ctx).await?;
             match own_listen_addres {
@@ -351,6 +946,28 @@ async fn main() -> Result<()> {
             let block_height = client.block_height(ctx).await?;
             println!("Block height: {}", block_height)
         }
+        Command::SyncProgress => match client.sync_progress(ctx).await? {
+            Some(sync_progress) => println!("{sync_progress}"),
+            None => println!("Not syncing"),
+        },
+        Command::ForkWatch => {
+            let status = client.fork_watch(ctx).await?;
+            if status.unknown_rules_active {
+                println!(
+                    "{} peers announce heavier chains with unknown block header version {} up to \
+                    height {}. Upgrade neptune-core.",
+                    status.num_peers,
+                    status.highest_unknown_version.unwrap_or_default(),
+                    status.highest_height.unwrap_or_default(),
+                );
+            } else {
+                println!(
+                    "No activation of unknown consensus rules detected ({} peers announce \
+                    unknown rules).",
+                    status.num_peers
+                );
+            }
+        }
         Command::BlockInfo { block_selector } => {
             let data = client.block_info(ctx, block_selector).await?;
             match data {
@@ -358,6 +975,11 @@ async fn main() -> Result<()> {
                 None => println!("Not found"),
             }
         }
+        Command::IsCanonical { block_digest } => {
+            let block_digest = Digest::try_from_hex(block_digest.trim())?;
+            let is_canonical = client.is_canonical(ctx, block_digest).await?;
+            println!("{is_canonical}");
+        }
         Command::Confirmations => {
             let val = client.confirmations(ctx).await?;
             match val {
@@ -384,6 +1006,11 @@ async fn main() -> Result<()> {
                 );
             }
         }
+        Command::PeerViolations { ip } => {
+            let violations = client.get_peer_violations(ctx, ip).await?;
+            println!("{} recorded violations", violations.len());
+            println!("{}", serde_json::to_string(&violations)?);
+        }
         Command::TipDigest => {
             let head_hash = client
                 .block_digest(ctx, BlockSelector::Tip)
@@ -412,6 +1039,45 @@ async fn main() -> Result<()> {
                 println!("{}", res.unwrap());
             }
         }
+        Command::BlocksForAnnouncement {
+            receiver_identifier,
+        } => {
+            let receiver_identifier = BFieldElement::new(receiver_identifier);
+            match client
+                .get_blocks_for_announcement(ctx, receiver_identifier)
+                .await?
+            {
+                Some(blocks) => {
+                    for digest in blocks {
+                        println!("{}", digest.to_hex());
+                    }
+                }
+                None => println!(
+                    "Transaction index is not enabled. Start the node with --build-tx-index."
+                ),
+            }
+        }
+        Command::UtxosForLockScriptHash { lock_script_hash } => {
+            let lock_script_hash = Digest::try_from_hex(lock_script_hash.trim())?;
+            match client
+                .get_utxos_for_lock_script_hash(ctx, lock_script_hash)
+                .await?
+            {
+                Some(utxos) => {
+                    for utxo in utxos {
+                        println!(
+                            "height {}: {} | {}",
+                            utxo.height,
+                            utxo.amount,
+                            utxo.block.to_hex()
+                        );
+                    }
+                }
+                None => println!(
+                    "Transaction index is not enabled. Start the node with --build-tx-index."
+                ),
+            }
+        }
         Command::SyncedBalance => {
             let val = client.synced_balance(ctx).await?;
             println!("{val}");
@@ -438,6 +1104,115 @@ async fn main() -> Result<()> {
             let size_in_bytes: usize = client.mempool_size(ctx).await?;
             println!("{} bytes", size_in_bytes);
         }
+        Command::EstimateFee { target_blocks } => {
+            let fee_per_kb = client.estimate_fee(ctx, target_blocks).await?;
+            println!("{fee_per_kb} per 1000 bytes");
+        }
+        Command::MempoolFeeHistogram { bucket_count } => {
+            let histogram = client.mempool_fee_histogram(ctx, bucket_count).await?;
+            if histogram.is_empty() {
+                println!("mempool is empty");
+            }
+            for bucket in histogram {
+                println!(
+                    "{} - {} per 1000 bytes: {} transactions, {} bytes, {} bytes cumulative",
+                    bucket.min_fee_per_kb,
+                    bucket.max_fee_per_kb,
+                    bucket.num_transactions,
+                    bucket.size,
+                    bucket.cumulative_size
+                );
+            }
+        }
+        Command::DiscloseKernelField { txid, field } => {
+            match client
+                .disclose_transaction_kernel_field(ctx, txid, field)
+                .await?
+            {
+                Some(disclosure) => {
+                    eprintln!("kernel MAST hash: {}", disclosure.kernel_mast_hash.to_hex());
+                    println!("{}", serde_json::to_string_pretty(&disclosure)?);
+                }
+                None => bail!("unknown transaction {txid}"),
+            }
+        }
+        Command::UtxoSetStats => {
+            let mut stats_ctx = context::current();
+            stats_ctx.deadline = SystemTime::now() + Duration::from_secs(120);
+            let utxo_set_stats = client.utxo_set_stats(stats_ctx).await?;
+            println!("{}", serde_json::to_string_pretty(&utxo_set_stats)?);
+        }
+        Command::BeaconStatus => {
+            println!("{}", client.beacon_status(ctx).await?);
+        }
+        Command::WalletAuditExport => {
+            let export = client.wallet_audit_export(ctx).await?;
+            println!("{}", serde_json::to_string_pretty(&export)?);
+        }
+        Command::Memos => {
+            for memo in client.memos(ctx).await? {
+                println!(
+                    "{:?}\t{}\t{}\t{}\t{}",
+                    memo.direction,
+                    memo.timestamp.standard_format(),
+                    memo.amount,
+                    memo.utxo_digest,
+                    memo.memo
+                );
+            }
+        }
+        Command::UnconfirmedSpends => {
+            let unconfirmed_spends = client.unconfirmed_spends(ctx).await?;
+            if unconfirmed_spends.is_empty() {
+                println!("No transactions spend unconfirmed change.");
+            }
+            for unconfirmed_spend in unconfirmed_spends {
+                println!(
+                    "{}\twaiting for: {}\t(invalidated if any of these is dropped)",
+                    unconfirmed_spend.txid,
+                    unconfirmed_spend
+                        .parents
+                        .iter()
+                        .map(|parent| parent.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+        }
+
+        Command::WalletSummary => {
+            let wallet_summary = client.wallet_summary(ctx).await?;
+            println!("{}", serde_json::to_string_pretty(&wallet_summary)?);
+        }
+        Command::RpcSchemas => {
+            let rpc_schemas = client.rpc_schemas(ctx).await?;
+            println!("{}", serde_json::to_string_pretty(&rpc_schemas)?);
+        }
+        Command::SubscribeBlocks => {
+            let mut known_tip = None;
+            loop {
+                let mut subscribe_ctx = context::current();
+                subscribe_ctx.deadline =
+                    SystemTime::now() + MAX_BLOCK_SUBSCRIPTION_WAIT + Duration::from_secs(10);
+                let Some(update) = client
+                    .subscribe_blocks(
+                        subscribe_ctx,
+                        known_tip,
+                        MAX_BLOCK_SUBSCRIPTION_WAIT.as_secs(),
+                    )
+                    .await?
+                else {
+                    continue;
+                };
+                if let Some(reorg_depth) = update.reorg_depth.filter(|depth| *depth > 0) {
+                    println!("reorganization: {reorg_depth} blocks abandoned");
+                }
+                for block_info in &update.new_blocks {
+                    println!("{}\t{}", block_info.height, block_info.digest);
+                }
+                known_tip = update.new_blocks.last().map(|block_info| block_info.digest);
+            }
+        }
 
         /******** CHANGE STATE ********/
         Command::Shutdown => {
@@ -445,6 +1220,16 @@ async fn main() -> Result<()> {
             client.shutdown(ctx).await?;
             println!("Shutdown-command completed successfully.");
         }
+        Command::MaintenanceMode { off } => {
+            let flushed = client.maintenance_mode(ctx, !off).await?;
+            if off {
+                println!("Left maintenance mode.");
+            } else if flushed {
+                println!("Entered maintenance mode. Databases are flushed.");
+            } else {
+                println!("Entered maintenance mode, but databases could not be flushed.");
+            }
+        }
         Command::ClearAllStandings => {
             client.clear_all_standings(ctx).await?;
             println!("Cleared all standings.");
@@ -457,19 +1242,44 @@ async fn main() -> Result<()> {
             amount,
             address,
             fee,
+            memo,
+            coin_selection,
         } => {
-            // Parse on client
-            let receiving_address = ReceivingAddress::from_bech32m(&address, args.network)?;
+            // Parse on client, for the network the node actually runs on
+            let node_network = client.network(ctx).await?;
+            let receiving_address = ReceivingAddress::from_bech32m_for_network(
+                &address,
+                node_network,
+                args.allow_network_mismatch,
+            )?;
+            let spend_passphrase = spend_passphrase(&client, ctx).await?;
 
-            let txid = client
-                .send(
-                    ctx,
-                    amount,
-                    receiving_address,
-                    UtxoNotificationMedium::OnChain,
-                    fee,
-                )
-                .await?;
+            let txid = match memo {
+                Some(memo) => {
+                    client
+                        .send_to_many_with_memos(
+                            ctx,
+                            vec![(receiving_address, amount, Some(memo))],
+                            UtxoNotificationMedium::OnChain,
+                            fee,
+                            spend_passphrase,
+                        )
+                        .await?
+                }
+                None => {
+                    client
+                        .send(
+                            ctx,
+                            amount,
+                            receiving_address,
+                            UtxoNotificationMedium::OnChain,
+                            fee,
+                            spend_passphrase,
+                            coin_selection,
+                        )
+                        .await?
+                }
+            };
 
             match txid {
                 Some(txid) => println!("Successfully created transaction: {txid}"),
@@ -477,13 +1287,24 @@ async fn main() -> Result<()> {
             }
         }
         Command::SendToMany { outputs, fee } => {
+            let node_network = client.network(ctx).await?;
             let parsed_outputs = outputs
                 .into_iter()
-                .map(|o| o.to_receiving_address_amount_tuple(args.network))
+                .map(|o| {
+                    o.to_receiving_address_amount_tuple(node_network, args.allow_network_mismatch)
+                })
                 .collect::<Result<Vec<_>>>()?;
 
+            let spend_passphrase = spend_passphrase(&client, ctx).await?;
+
             let txid = client
-                .send_to_many(ctx, parsed_outputs, UtxoNotificationMedium::OnChain, fee)
+                .send_to_many(
+                    ctx,
+                    parsed_outputs,
+                    UtxoNotificationMedium::OnChain,
+                    fee,
+                    spend_passphrase,
+                )
                 .await?;
             match txid {
                 Some(txid) => println!("Successfully created transaction: {txid}"),
@@ -500,12 +1321,356 @@ async fn main() -> Result<()> {
             client.restart_miner(ctx).await?;
             println!("Command completed successfully");
         }
+        Command::GetBlockTemplate => match client.get_block_template(ctx).await? {
+            Some(template) => println!("{}", serde_json::to_string_pretty(&template)?),
+            None => println!("No block template available."),
+        },
+        Command::GetBlockTemplateUpdate { known_proposal_id } => {
+            match client
+                .get_block_template_update(ctx, known_proposal_id)
+                .await?
+            {
+                Some(update) => println!("{}", serde_json::to_string_pretty(&update)?),
+                None => println!("No block template available."),
+            }
+        }
+        Command::SubmitBlockSolution {
+            proposal_id,
+            nonce,
+            timestamp_millis,
+        } => {
+            let nonce = [
+                BFieldElement::new(nonce[0]),
+                BFieldElement::new(nonce[1]),
+                BFieldElement::new(nonce[2]),
+            ];
+            let timestamp = timestamp_millis.map(Timestamp::millis);
+            match client
+                .submit_block_solution(ctx, proposal_id, nonce, timestamp)
+                .await?
+            {
+                Ok(digest) => println!("Block {} accepted.", digest.to_hex()),
+                Err(err) => println!("Solution rejected: {err}"),
+            }
+        }
+        Command::GenerateBlocks {
+            num_blocks,
+            address,
+            real_proofs,
+        } => {
+            let node_network = client.network(ctx).await?;
+            let receiving_address = ReceivingAddress::from_bech32m_for_network(
+                &address,
+                node_network,
+                args.allow_network_mismatch,
+            )?;
+            match client
+                .generate_blocks(ctx, num_blocks, receiving_address, real_proofs)
+                .await?
+            {
+                Ok(digests) => {
+                    for digest in digests {
+                        println!("{}", digest.to_hex());
+                    }
+                }
+                Err(err) => println!("Block generation failed: {err}"),
+            }
+        }
+        Command::CreatePsnt { outputs, fee, out } => {
+            let node_network = client.network(ctx).await?;
+            let parsed_outputs = outputs
+                .into_iter()
+                .map(|o| {
+                    o.to_receiving_address_amount_tuple(node_network, args.allow_network_mismatch)
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            match client
+                .create_psnt(ctx, parsed_outputs, UtxoNotificationMedium::OnChain, fee)
+                .await?
+            {
+                Some(psnt) => {
+                    std::fs::write(&out, psnt)?;
+                    println!("Wrote PSNT to {}", out.display());
+                }
+                None => println!("Failed to create PSNT. Please check the log."),
+            }
+        }
+        Command::CombinePsnt { files, out } => {
+            let psnts = files
+                .iter()
+                .map(std::fs::read)
+                .collect::<std::io::Result<Vec<_>>>()?;
+            match client.combine_psnt(ctx, psnts).await? {
+                Ok(psnt) => {
+                    std::fs::write(&out, psnt)?;
+                    println!("Wrote combined PSNT to {}", out.display());
+                }
+                Err(err) => println!("Could not combine PSNTs: {err}"),
+            }
+        }
+        Command::FinalizePsnt {
+            file,
+            max_net_spend,
+        } => {
+            let psnt = std::fs::read(&file)?;
+            let spend_passphrase = spend_passphrase(&client, ctx).await?;
+
+            // Proving takes minutes.
+            let mut finalize_ctx = context::current();
+            finalize_ctx.deadline = SystemTime::now() + Duration::from_secs(60 * 60);
+            match client
+                .finalize_psnt(finalize_ctx, psnt, max_net_spend, spend_passphrase)
+                .await?
+            {
+                Ok(PsntFinalization::Incomplete {
+                    psnt,
+                    num_unproven_inputs,
+                }) => {
+                    std::fs::write(&file, psnt)?;
+                    println!(
+                        "Lock scripts of {num_unproven_inputs} inputs remain to be proven. \
+                        Wrote PSNT to {}",
+                        file.display()
+                    );
+                }
+                Ok(PsntFinalization::Broadcast(txid)) => {
+                    println!("Broadcast transaction {txid}")
+                }
+                Err(err) => println!("Could not finalize PSNT: {err}"),
+            }
+        }
+        Command::BroadcastTransaction { file } => {
+            let transaction_bytes = std::fs::read(file)?;
+            match client.broadcast_transaction(ctx, transaction_bytes).await? {
+                Ok(txid) => println!("Broadcast transaction {txid}"),
+                Err(err) => println!("Transaction rejected: {err}"),
+            }
+        }
+        Command::MinedBlocks { max_num } => {
+            let reports = client.mined_blocks(ctx, max_num).await?;
+            if reports.is_empty() {
+                println!("No mined blocks logged.");
+            }
+            for report in reports {
+                println!(
+                    "height {}: {} | coinbase {} | fees {} | {} transactions | template age {:.1}s | {}",
+                    report.height,
+                    report.timestamp.standard_format(),
+                    report.coinbase,
+                    report.fees,
+                    report.num_transactions,
+                    report.template_age.to_millis() as f64 / 1000.0,
+                    report.digest.to_hex(),
+                );
+            }
+        }
+
+        Command::ValidationReports { max_num } => {
+            let reports = client.validation_reports(ctx, max_num).await?;
+            if reports.is_empty() {
+                println!("No validation reports. Is the node running with --trace-validation?");
+            }
+            for report in reports {
+                let outcome = match report.failed_rule() {
+                    Some(rule) => format!("invalid, failing {rule:?}"),
+                    None => "valid".to_owned(),
+                };
+                println!(
+                    "height {}: {} | {} | {} | {:?}",
+                    report.height,
+                    report.validated_at.standard_format(),
+                    outcome,
+                    report.block_digest.to_hex(),
+                    report.total_duration(),
+                );
+                for rule in report.rules {
+                    println!(
+                        "    {:?}: {} in {:?}",
+                        rule.rule,
+                        if rule.passed { "passed" } else { "failed" },
+                        rule.duration
+                    );
+                }
+            }
+        }
+
+        Command::EventJournal { hours, max_num } => {
+            let to = Timestamp::now();
+            let from = to - Timestamp::hours(hours);
+            let entries = client.event_journal(ctx, from, to, max_num).await?;
+            if entries.is_empty() {
+                println!("No events journaled in the last {hours} hours.");
+            }
+            for entry in entries {
+                println!("{}: {:?}", entry.timestamp.standard_format(), entry.event);
+            }
+        }
+
+        Command::ExportSpendAuditLog { file } => {
+            let Some(entries) = client.spend_audit_log(ctx).await? else {
+                bail!("Could not read spend audit log. Please check the node's log.");
+            };
+            SpendAuditEntry::verify_chain(&entries)
+                .context("spend audit log has been tampered with")?;
+            let mut lines = String::default();
+            for entry in &entries {
+                lines.push_str(&serde_json::to_string(entry)?);
+                lines.push('\n');
+            }
+            std::fs::write(&file, lines)?;
+            match entries.last() {
+                Some(last) => println!(
+                    "Exported {} entries to {}. Latest hash: {}",
+                    entries.len(),
+                    file.display(),
+                    last.hash
+                ),
+                None => println!("Spend audit log is empty."),
+            }
+        }
 
         Command::PruneAbandonedMonitoredUtxos => {
             let prunt_res_count = client.prune_abandoned_monitored_utxos(ctx).await?;
             println!("{prunt_res_count} monitored UTXOs marked as abandoned");
         }
+
+        Command::KeyRotationStart => match client.key_rotation_start(ctx).await? {
+            Some(status) => {
+                println!("Key rotation in progress. Back up the new seed file in the wallet directory before sweeping.");
+                println!(
+                    "destination: {}",
+                    status.destination.to_bech32m(args.network)?
+                );
+                println!("{status}");
+            }
+            None => println!("Failed to start key rotation. Please check the log."),
+        },
+        Command::KeyRotationStatus => match client.key_rotation_status(ctx).await? {
+            Some(status) => println!("{status}"),
+            None => println!("No key rotation in progress."),
+        },
+        Command::KeyRotationSweep { fee } => {
+            let spend_passphrase = spend_passphrase(&client, ctx).await?;
+            match client
+                .key_rotation_sweep(ctx, fee, spend_passphrase)
+                .await?
+            {
+                Some(status) => println!("{status}"),
+                None => println!("Failed to sweep. Please check the log."),
+            }
+        }
+        Command::KeyRotationFinish => {
+            if client.key_rotation_finish(ctx).await? {
+                println!("Key rotation finished. The wallet now uses the new seed.");
+            } else {
+                println!("Failed to finish key rotation. Please check the log.");
+            }
+        }
+        Command::WalletUnlock { timeout } => {
+            let passphrase = match std::env::var("NEPTUNE_WALLET_PASSPHRASE") {
+                Ok(passphrase) => passphrase,
+                Err(_) => read_line("Wallet passphrase: ")?,
+            };
+            if client.wallet_unlock(ctx, passphrase, timeout).await? {
+                println!("Wallet unlocked.");
+            } else {
+                println!("Could not unlock wallet; see node log.");
+            }
+        }
+        Command::WalletLock => {
+            if client.wallet_lock(ctx).await? {
+                println!("Wallet locked.");
+            } else {
+                println!("Wallet is not encrypted.");
+            }
+        }
+        Command::ExportAccountDescriptor { account } => {
+            match client.export_account_descriptor(ctx, account).await? {
+                Some(descriptor) => println!("{}", descriptor.to_bech32m()?),
+                None => println!("Could not export account descriptor; see node log."),
+            }
+        }
+        Command::ImportExpectedUtxos { file, signer } => {
+            let bundle: ExpectedUtxoBundle = serde_json::from_str(&std::fs::read_to_string(file)?)?;
+            match client.import_expected_utxos(ctx, bundle, signer).await? {
+                Ok(import) => println!(
+                    "Imported {} UTXOs, skipped {} duplicates.",
+                    import.imported, import.duplicates
+                ),
+                Err(err) => println!("Bundle rejected: {err}"),
+            }
+        }
+        Command::BeaconSubmitCheckpoint { checkpoint } => {
+            let checkpoint: SignedCheckpoint = serde_json::from_str(&checkpoint)?;
+            if client.beacon_submit_checkpoint(ctx, checkpoint).await? {
+                println!("Checkpoint accepted.");
+            } else {
+                println!("Checkpoint rejected. Please check the log.");
+            }
+        }
+        Command::CheckReachability => {
+            let reports = client.check_reachability(ctx).await?;
+            if reports.is_empty() {
+                println!("No peers to ask, or not listening for peers.");
+            }
+            for report in reports {
+                println!("{report}");
+            }
+        }
+        Command::CoinJoinSessions => {
+            let sessions = client.coinjoin_sessions(ctx).await?;
+            if sessions.is_empty() {
+                println!("No coinjoin sessions known yet.");
+            }
+            for (coordinator, session) in sessions {
+                println!(
+                    "{coordinator}: session {} of {}, {}/{} participants (min {}), deadline {}",
+                    session.id,
+                    session.denomination,
+                    session.num_participants,
+                    session.max_participants,
+                    session.min_participants,
+                    session.deadline.standard_format()
+                );
+            }
+        }
+        Command::CoinJoinJoin { coordinator, fee } => {
+            let spend_passphrase = spend_passphrase(&client, ctx).await?;
+            let mut join_ctx = context::current();
+            join_ctx.deadline = SystemTime::now() + Duration::from_secs(60 * 60);
+            match client
+                .coinjoin_join(join_ctx, coordinator, fee, spend_passphrase)
+                .await?
+            {
+                Some(txid) => println!("Contributed transaction {txid}."),
+                None => println!("Failed to contribute. Please check the log."),
+            }
+        }
     }
 
     Ok(())
 }
+
+/// Print a prompt and read a line from standard input, without the line
+/// break.
+fn read_line(prompt: &str) -> Result<String> {
+    print!("{prompt}");
+    io::stdout().flush()?;
+    let mut buffer = String::new();
+    std::io::stdin().read_line(&mut buffer)?;
+    Ok(buffer.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// The spend passphrase to send with, if the node requires one. Taken from
+/// the environment variable `NEPTUNE_SPEND_PASSPHRASE` if set, otherwise
+/// read from standard input.
+async fn spend_passphrase(client: &RPCClient, ctx: context::Context) -> Result<Option<String>> {
+    if !client.spend_passphrase_required(ctx).await? {
+        return Ok(None);
+    }
+    match std::env::var("NEPTUNE_SPEND_PASSPHRASE") {
+        Ok(passphrase) => Ok(Some(passphrase)),
+        Err(_) => Ok(Some(read_line("Spend passphrase: ")?)),
+    }
+}
@@ -5,10 +5,9 @@ use anyhow::bail;
 use anyhow::Result;
 use clap::Parser;
 use dashboard_src::dashboard_app::DashboardApp;
-use neptune_core::rpc_server::RPCClient;
-use tarpc::client;
+use neptune_core::rpc_client::NeptuneRpcClient;
+use neptune_core::rpc_client::RpcClientConfig;
 use tarpc::context;
-use tarpc::tokio_serde::formats::Json;
 
 pub mod dashboard_src;
 
@@ -25,9 +24,12 @@ async fn main() -> Result<()> {
     // Create connection to RPC server
     let args: Config = Config::parse();
     let server_socket = SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::LOCALHOST), args.port);
-    let transport = tarpc::serde_transport::tcp::connect(server_socket, Json::default).await;
-    let transport = match transport {
-        Ok(transp) => transp,
+    let config = RpcClientConfig {
+        server_addr: server_socket,
+        ..Default::default()
+    };
+    let client = match NeptuneRpcClient::connect(config).await {
+        Ok(client) => client.into_inner(),
         Err(err) => {
             eprintln!("{err}");
             bail!(
@@ -36,7 +38,6 @@ async fn main() -> Result<()> {
             );
         }
     };
-    let client = RPCClient::new(client::Config::default(), transport).spawn();
 
     // Read what network the client is running and ensure that client is up and running
     let network = match client.network(context::current()).await {
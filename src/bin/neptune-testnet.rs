@@ -0,0 +1,510 @@
+//! Launch a local network of `neptune-core` nodes and drive scenarios on it.
+//!
+//! Every node runs on regtest as a child process with its own data directory,
+//! peer port, and RPC port. Nodes are connected according to the selected
+//! topology. Once all nodes answer RPC requests, a scenario is read line by
+//! line from `--script` (or stdin) and executed. Commands:
+//!
+//! ```text
+//!  # comment
+//!  sleep <seconds>
+//!  height <node>
+//!  wait-height <node|all> <height> [timeout-seconds]
+//!  wait-synced [timeout-seconds]
+//!  send <from> <to> <amount> <fee>
+//!  balance <node>
+//!  mempool <node>
+//!  pause-miner <node>
+//!  restart-miner <node>
+//!  stop <node>
+//! ```
+//!
+//! The process exits with a non-zero status on the first failing command, so
+//! scenarios can be used directly as integration tests.
+
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use clap::Parser;
+use clap::ValueEnum;
+use neptune_core::models::blockchain::block::block_height::BlockHeight;
+use neptune_core::models::blockchain::block::block_selector::BlockSelector;
+use neptune_core::models::blockchain::transaction::transaction_output::UtxoNotificationMedium;
+use neptune_core::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use neptune_core::models::state::wallet::address::KeyType;
+use neptune_core::rpc_client::NeptuneRpcClient;
+use neptune_core::rpc_client::RpcClientConfig;
+use neptune_core::rpc_server::RPCClient;
+use tarpc::context;
+use tokio::process::Child;
+use tokio::process::Command;
+use tokio::time::sleep;
+use tokio::time::Instant;
+
+/// How long to wait for a freshly started node to accept RPC connections.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Default timeout for `wait-*` commands that do not specify one.
+const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(600);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// File marking a data directory as created by this tool, and thus safe to
+/// wipe.
+const DATA_DIR_MARKER: &str = ".neptune-testnet";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Topology {
+    /// Node `i` connects to node `i - 1`.
+    Line,
+
+    /// Node `i` connects to every node `j < i`.
+    Full,
+}
+
+#[derive(Debug, Parser)]
+#[clap(
+    name = "neptune-testnet",
+    about = "Run a local multi-node regtest network and drive scenarios on it"
+)]
+struct Config {
+    /// Number of nodes to launch.
+    #[clap(long, short, default_value = "3")]
+    nodes: usize,
+
+    /// Indices of the nodes that mine, e.g. `--miners 0 --miners 2`.
+    #[clap(long)]
+    miners: Vec<usize>,
+
+    /// How nodes are connected to each other.
+    #[clap(long, value_enum, default_value = "line")]
+    topology: Topology,
+
+    /// Peer port of node 0. Node `i` listens on `base_peer_port + i`.
+    #[clap(long, default_value = "29790")]
+    base_peer_port: u16,
+
+    /// RPC port of node 0. Node `i` listens on `base_rpc_port + i`.
+    #[clap(long, default_value = "19790")]
+    base_rpc_port: u16,
+
+    /// Directory under which each node gets its own data directory. Wiped on
+    /// startup if it was created by this tool; any other non-empty directory
+    /// is refused.
+    #[clap(long, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
+
+    /// Path to the `neptune-core` binary. Defaults to the one next to this
+    /// executable.
+    #[clap(long, value_name = "PATH")]
+    core_binary: Option<PathBuf>,
+
+    /// Scenario to execute. Read from stdin if absent.
+    #[clap(long, value_name = "FILE")]
+    script: Option<PathBuf>,
+
+    /// Keep the nodes running after the scenario finished, until Ctrl-C.
+    #[clap(long)]
+    keep_running: bool,
+
+    /// Additional arguments passed verbatim to every node.
+    #[clap(last = true)]
+    extra_args: Vec<String>,
+}
+
+struct TestnetNode {
+    index: usize,
+    process: Child,
+    rpc_address: SocketAddr,
+    client: RPCClient,
+}
+
+struct Testnet {
+    nodes: Vec<TestnetNode>,
+}
+
+impl Config {
+    fn data_dir(&self) -> PathBuf {
+        self.data_dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("neptune-testnet"))
+    }
+
+    fn core_binary(&self) -> Result<PathBuf> {
+        if let Some(path) = &self.core_binary {
+            return Ok(path.clone());
+        }
+        let this_executable = std::env::current_exe()?;
+        let directory = this_executable
+            .parent()
+            .context("executable must live in a directory")?;
+        Ok(directory.join(format!("neptune-core{}", std::env::consts::EXE_SUFFIX)))
+    }
+
+    fn peer_address(&self, index: usize) -> SocketAddr {
+        SocketAddr::new(
+            Ipv4Addr::LOCALHOST.into(),
+            self.base_peer_port + index as u16,
+        )
+    }
+
+    fn rpc_address(&self, index: usize) -> SocketAddr {
+        SocketAddr::new(
+            Ipv4Addr::LOCALHOST.into(),
+            self.base_rpc_port + index as u16,
+        )
+    }
+
+    fn peers_of(&self, index: usize) -> Vec<SocketAddr> {
+        match self.topology {
+            Topology::Line if index > 0 => vec![self.peer_address(index - 1)],
+            Topology::Line => vec![],
+            Topology::Full => (0..index).map(|j| self.peer_address(j)).collect(),
+        }
+    }
+}
+
+impl Testnet {
+    async fn launch(config: &Config) -> Result<Self> {
+        if let Some(miner) = config.miners.iter().find(|&&m| m >= config.nodes) {
+            bail!(
+                "Miner index {miner} is out of range for {} nodes",
+                config.nodes
+            );
+        }
+
+        let root = config.data_dir();
+        prepare_data_dir(&root)?;
+        let core_binary = config.core_binary()?;
+
+        let mut nodes = vec![];
+        for index in 0..config.nodes {
+            let mut command = Command::new(&core_binary);
+            command
+                .arg("--network")
+                .arg("regtest")
+                .arg("--data-dir")
+                .arg(root.join(index.to_string()))
+                .arg("--peer-port")
+                .arg(config.peer_address(index).port().to_string())
+                .arg("--rpc-port")
+                .arg(config.rpc_address(index).port().to_string());
+            for peer in config.peers_of(index) {
+                command.arg("--peers").arg(peer.to_string());
+            }
+            if config.miners.contains(&index) {
+                command.arg("--mine").arg("--unrestricted-mining");
+            }
+            command
+                .args(&config.extra_args)
+                .stdin(Stdio::null())
+                .kill_on_drop(true);
+
+            let process = command
+                .spawn()
+                .with_context(|| format!("could not start {}", core_binary.display()))?;
+            println!("node {index}: started with pid {:?}", process.id());
+
+            let rpc_address = config.rpc_address(index);
+            let client = connect_with_retry(rpc_address).await?;
+            nodes.push(TestnetNode {
+                index,
+                process,
+                rpc_address,
+                client,
+            });
+        }
+
+        Ok(Self { nodes })
+    }
+
+    fn node(&self, index: &str) -> Result<&TestnetNode> {
+        let index = usize::from_str(index).with_context(|| format!("bad node index {index}"))?;
+        self.nodes
+            .get(index)
+            .with_context(|| format!("no node with index {index}"))
+    }
+
+    /// Resolve a node argument that may also be `all`.
+    fn nodes(&self, selector: &str) -> Result<Vec<&TestnetNode>> {
+        if selector == "all" {
+            Ok(self.nodes.iter().collect())
+        } else {
+            Ok(vec![self.node(selector)?])
+        }
+    }
+
+    async fn execute(&mut self, line: &str) -> Result<()> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            [] => {}
+            ["sleep", seconds] => sleep(Duration::from_secs_f64(seconds.parse()?)).await,
+            ["height", node] => {
+                let node = self.node(node)?;
+                let height = node.client.block_height(context::current()).await?;
+                println!("node {}: height {height}", node.index);
+            }
+            ["wait-height", selector, height, rest @ ..] => {
+                let target = BlockHeight::from(u64::from_str(height)?);
+                let deadline = Instant::now() + parse_timeout(rest)?;
+                for node in self.nodes(selector)? {
+                    loop {
+                        let height = node.client.block_height(context::current()).await?;
+                        if height >= target {
+                            println!("node {}: reached height {height}", node.index);
+                            break;
+                        }
+                        if Instant::now() > deadline {
+                            bail!("node {}: stuck at height {height}", node.index);
+                        }
+                        sleep(POLL_INTERVAL).await;
+                    }
+                }
+            }
+            ["wait-synced", rest @ ..] => {
+                let deadline = Instant::now() + parse_timeout(rest)?;
+                loop {
+                    let mut tips = vec![];
+                    for node in &self.nodes {
+                        let tip = node
+                            .client
+                            .block_digest(context::current(), BlockSelector::Tip)
+                            .await?;
+                        tips.push(tip);
+                    }
+                    if tips.windows(2).all(|pair| pair[0] == pair[1]) {
+                        println!("all nodes agree on tip");
+                        break;
+                    }
+                    if Instant::now() > deadline {
+                        bail!("nodes did not converge on a common tip: {tips:?}");
+                    }
+                    sleep(POLL_INTERVAL).await;
+                }
+            }
+            ["send", from, to, amount, fee] => {
+                let address = self
+                    .node(to)?
+                    .client
+                    .next_receiving_address(context::current(), KeyType::Generation)
+                    .await?;
+                let from = self.node(from)?;
+                let txid = from
+                    .client
+                    .send(
+                        context::current(),
+                        NeptuneCoins::from_str(amount)?,
+                        address,
+                        UtxoNotificationMedium::OnChain,
+                        NeptuneCoins::from_str(fee)?,
+                        None,
+                        None,
+                    )
+                    .await?
+                    .with_context(|| {
+                        format!("node {}: could not create transaction", from.index)
+                    })?;
+                println!("node {}: created transaction {txid}", from.index);
+            }
+            ["balance", node] => {
+                let node = self.node(node)?;
+                let balance = node.client.synced_balance(context::current()).await?;
+                println!("node {}: balance {balance}", node.index);
+            }
+            ["mempool", node] => {
+                let node = self.node(node)?;
+                let count = node.client.mempool_tx_count(context::current()).await?;
+                println!("node {}: {count} transactions in mempool", node.index);
+            }
+            ["pause-miner", node] => {
+                self.node(node)?
+                    .client
+                    .pause_miner(context::current())
+                    .await?
+            }
+            ["restart-miner", node] => {
+                self.node(node)?
+                    .client
+                    .restart_miner(context::current())
+                    .await?
+            }
+            ["stop", node] => {
+                let node = self.node(node)?;
+                node.client.shutdown(context::current()).await?;
+                println!("node {}: shut down", node.index);
+            }
+            _ => bail!("unknown command or wrong number of arguments"),
+        }
+
+        Ok(())
+    }
+
+    /// Ask all nodes to shut down gracefully and wait for their processes.
+    async fn shutdown(mut self) {
+        for node in &mut self.nodes {
+            // a node that was stopped by the scenario no longer answers
+            let _ = node.client.shutdown(context::current()).await;
+            if let Err(err) = node.process.wait().await {
+                eprintln!(
+                    "node {} ({}): could not wait for process: {err}",
+                    node.index, node.rpc_address
+                );
+            }
+        }
+    }
+}
+
+/// Create an empty data directory at `root`, wiping what a previous run of
+/// this tool left there. Directories holding anything else are refused, such
+/// that a mistyped path cannot destroy unrelated data.
+fn prepare_data_dir(root: &Path) -> Result<()> {
+    let marker = root.join(DATA_DIR_MARKER);
+    if marker.exists() {
+        std::fs::remove_dir_all(root)
+            .with_context(|| format!("could not wipe {}", root.display()))?;
+    } else if root.exists() && std::fs::read_dir(root)?.next().is_some() {
+        bail!(
+            "refusing to wipe {}: it is not empty and was not created by neptune-testnet",
+            root.display()
+        );
+    }
+
+    std::fs::create_dir_all(root)
+        .with_context(|| format!("could not create {}", root.display()))?;
+    File::create(&marker).with_context(|| format!("could not create {}", marker.display()))?;
+
+    Ok(())
+}
+
+fn parse_timeout(rest: &[&str]) -> Result<Duration> {
+    match rest {
+        [] => Ok(DEFAULT_WAIT_TIMEOUT),
+        [seconds] => Ok(Duration::from_secs(seconds.parse()?)),
+        _ => bail!("too many arguments"),
+    }
+}
+
+async fn connect_with_retry(rpc_address: SocketAddr) -> Result<RPCClient> {
+    let config = RpcClientConfig {
+        server_addr: rpc_address,
+        max_retries: (STARTUP_TIMEOUT.as_millis() / POLL_INTERVAL.as_millis()) as u32,
+        retry_interval: POLL_INTERVAL,
+        ..Default::default()
+    };
+    match NeptuneRpcClient::connect(config).await {
+        Ok(client) => Ok(client.into_inner()),
+        Err(err) => bail!("node did not start accepting RPC connections: {err}"),
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let config = Config::parse();
+    let mut testnet = Testnet::launch(&config).await?;
+
+    let script: Box<dyn BufRead> = match &config.script {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(BufReader::new(io::stdin())),
+    };
+
+    let mut result = Ok(());
+    for (line_number, line) in script.lines().enumerate() {
+        let line = line?;
+        let command = line.split('#').next().unwrap_or_default().trim();
+        if let Err(err) = testnet.execute(command).await {
+            result = Err(err.context(format!("line {}: `{command}`", line_number + 1)));
+            break;
+        }
+    }
+
+    if config.keep_running && result.is_ok() {
+        println!("scenario finished; press Ctrl-C to stop the network");
+        tokio::signal::ctrl_c().await?;
+    }
+
+    testnet.shutdown().await;
+    result
+}
+
+#[cfg(test)]
+mod testnet_tests {
+    use super::*;
+
+    fn unique_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("neptune-testnet-{}", rand::random::<u64>()))
+    }
+
+    #[test]
+    fn data_dir_of_previous_run_is_wiped() {
+        let root = unique_dir();
+        prepare_data_dir(&root).unwrap();
+        std::fs::create_dir(root.join("0")).unwrap();
+
+        prepare_data_dir(&root).unwrap();
+        assert!(!root.join("0").exists());
+        assert!(root.join(DATA_DIR_MARKER).exists());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn foreign_data_dir_is_not_wiped() {
+        let root = unique_dir();
+        std::fs::create_dir_all(&root).unwrap();
+        let precious = root.join("wallet.dat");
+        std::fs::write(&precious, b"precious").unwrap();
+
+        assert!(prepare_data_dir(&root).is_err());
+        assert!(precious.exists());
+        assert!(!root.join(DATA_DIR_MARKER).exists());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn empty_or_missing_data_dir_is_claimed() {
+        let missing = unique_dir();
+        prepare_data_dir(&missing).unwrap();
+        assert!(missing.join(DATA_DIR_MARKER).exists());
+
+        let empty = unique_dir();
+        std::fs::create_dir_all(&empty).unwrap();
+        prepare_data_dir(&empty).unwrap();
+        assert!(empty.join(DATA_DIR_MARKER).exists());
+
+        std::fs::remove_dir_all(missing).unwrap();
+        std::fs::remove_dir_all(empty).unwrap();
+    }
+
+    #[test]
+    fn line_and_full_topologies_connect_to_earlier_nodes() {
+        let mut config = Config::parse_from(["neptune-testnet", "--nodes", "3"]);
+        assert!(config.peers_of(0).is_empty());
+        assert_eq!(vec![config.peer_address(1)], config.peers_of(2));
+
+        config.topology = Topology::Full;
+        assert_eq!(
+            vec![config.peer_address(0), config.peer_address(1)],
+            config.peers_of(2)
+        );
+    }
+
+    #[test]
+    fn timeouts_parse() {
+        assert_eq!(DEFAULT_WAIT_TIMEOUT, parse_timeout(&[]).unwrap());
+        assert_eq!(Duration::from_secs(7), parse_timeout(&["7"]).unwrap());
+        assert!(parse_timeout(&["7", "8"]).is_err());
+    }
+}
@@ -3,17 +3,30 @@ use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::time::Duration;
 
+use anyhow::bail;
+use anyhow::Result;
 use bytesize::ByteSize;
 use clap::builder::RangedI64ValueParser;
 use clap::Parser;
 use num_traits::Zero;
 
 use super::network::Network;
+use super::network_params::NetworkParams;
+use crate::models::blockchain::block::validation_checkpoints::ValidationCheckpoint;
+use crate::models::blockchain::block::validation_checkpoints::ValidationCheckpoints;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::models::state::checkpoint_beacon::BeaconKey;
+use crate::models::state::coinjoin::CoinJoinConfig;
 use crate::models::state::tx_proving_capability::TxProvingCapability;
+use crate::models::state::wallet::coin_selection::CoinSelectionPolicy;
+use crate::models::state::wallet::coinbase_address_rotation::CoinbaseAddressRotation;
+use crate::models::state::wallet::wallet_fsync_policy::WalletFsyncPolicy;
 
 /// The `neptune-core` command-line program starts a Neptune node.
 #[derive(Parser, Debug, Clone)]
 #[clap(author, version, about)]
+#[non_exhaustive]
 pub struct Args {
     /// The data directory that contains the wallet and blockchain state
     ///
@@ -27,6 +40,29 @@ pub struct Args {
     #[clap(long, value_name = "DIR")]
     pub data_dir: Option<PathBuf>,
 
+    /// Delete stored blocks that are more than this many blocks below the tip,
+    /// to bound disk usage.
+    ///
+    /// Block headers and the archival mutator set are retained. Pruned blocks
+    /// cannot be served to peers, so the node tells peers it is not archival,
+    /// and they do not sync from it. Reorganizations deeper than this cannot
+    /// be handled. Blocks are stored on disk in files of many blocks, so
+    /// somewhat more blocks than this are retained.
+    ///
+    /// All blocks are stored by default.
+    #[clap(long, value_name = "DEPTH", value_parser(RangedI64ValueParser::<u64>::new().range(1000..)))]
+    pub prune_depth: Option<u64>,
+
+    /// Index the blocks containing public announcements by receiver
+    /// identifier, and the UTXOs received by this node's wallet by lock script
+    /// hash, for block explorer style queries.
+    ///
+    /// UTXOs are encrypted on chain, so only those the wallet can decrypt are
+    /// indexed by lock script hash. Blocks stored before the index was enabled
+    /// are indexed at startup.
+    #[clap(long)]
+    pub build_tx_index: bool,
+
     /// Ban connections to this node from IP address.
     ///
     /// This node can still make outgoing connections to IP address.
@@ -52,6 +88,52 @@ pub struct Args {
     #[clap(long, default_value = "10", value_name = "COUNT")]
     pub max_peers: u16,
 
+    /// Max number of block requests a peer may send per minute, on average.
+    ///
+    /// Bursts of up to this many requests are allowed. Requests beyond the
+    /// limit are ignored and lower the peer's standing. Set to 0 for no limit.
+    #[clap(long, default_value = "120", value_name = "COUNT")]
+    pub max_peer_block_requests_per_minute: u32,
+
+    /// Max number of transactions and transaction notifications a peer may
+    /// send per minute, on average.
+    ///
+    /// Bursts of up to this many messages are allowed. Messages beyond the
+    /// limit are ignored and lower the peer's standing. Set to 0 for no limit.
+    #[clap(long, default_value = "600", value_name = "COUNT")]
+    pub max_peer_transactions_per_minute: u32,
+
+    /// Max number of incoming connection attempts per minute from one IP
+    /// address, on average.
+    ///
+    /// Attempts beyond the limit are refused and lower the standing of the IP
+    /// address. Set to 0 for no limit.
+    #[clap(long, default_value = "10", value_name = "COUNT")]
+    pub max_handshakes_per_minute: u32,
+
+    /// Max number of blocks served to a peer per minute, on average.
+    ///
+    /// Batch responses are cut to the minimum batch size when the limit is
+    /// reached, and requests for single blocks beyond it go unanswered. Set to
+    /// 0 for no limit.
+    #[clap(long, default_value = "1200", value_name = "COUNT")]
+    pub max_peer_blocks_served_per_minute: u32,
+
+    /// Max number of block requests served concurrently, across all peers.
+    ///
+    /// Free slots go to the peer with the fewest requests in progress, such
+    /// that one peer cannot monopolize the disk. Set to 0 for no limit.
+    #[clap(long, default_value = "4", value_name = "COUNT")]
+    pub max_concurrent_archival_requests: usize,
+
+    /// Fetch new blocks announced by peers as compact blocks, which leave out
+    /// the removal records found in the mempool.
+    ///
+    /// Peers running versions without compact block support disconnect when
+    /// asked for one, so this is disabled by default.
+    #[clap(long)]
+    pub compact_block_relay: bool,
+
     /// Should this node participate in competitive mining?
     ///
     /// Mining is disabled by default.
@@ -62,6 +144,47 @@ pub struct Args {
     #[clap(long)]
     pub unrestricted_mining: bool,
 
+    /// Number of threads mining on the block template. The threads share the
+    /// template's nonces, such that no nonce is tried twice. Ignored if mine
+    /// flag not set.
+    #[clap(long, default_value = "1", value_parser(RangedI64ValueParser::<usize>::new().range(1..1024)))]
+    pub mining_threads: usize,
+
+    /// Address of an external hasher, such as a GPU miner, to leave the search
+    /// for nonces to instead of mining on the CPU. The hasher listens on this
+    /// address, and receives block templates and returns solutions as
+    /// newline-delimited JSON. Ignored if mine flag not set.
+    #[clap(long, value_name = "ADDRESS", conflicts_with = "external_mining")]
+    pub pow_solver: Option<SocketAddr>,
+
+    /// Build block templates for external mining software instead of mining
+    /// them in-process. Templates are served by the `get_block_template` RPC,
+    /// and solutions are accepted by the `submit_block_solution` RPC.
+    #[clap(long, requires = "mine")]
+    pub external_mining: bool,
+
+    /// Port on which to distribute the block templates built for external
+    /// mining to mining workers, which submit shares in return. Listens on
+    /// the same address as for peer connections. Disabled if not set.
+    #[clap(long, value_name = "PORT", requires = "external_mining")]
+    pub work_server_port: Option<u16>,
+
+    /// Difficulty of the shares mining workers submit to the work server.
+    /// Lower than the block difficulty, such that workers can prove their
+    /// work long before one finds a block.
+    #[clap(long, default_value = "1000000")]
+    pub share_difficulty: u64,
+
+    /// Which address coinbase rewards of blocks mined by this node are paid to.
+    ///
+    /// `fixed` pays to the first generation address, `fresh` to a newly
+    /// derived address after every block mined, and `round-robin:<N>` to the
+    /// first N generation addresses in turn.
+    ///
+    /// E.g. --coinbase-address-rotation round-robin:10
+    #[clap(long, default_value = "fixed", value_name = "POLICY")]
+    pub coinbase_address_rotation: CoinbaseAddressRotation,
+
     /// Prune the mempool when it exceeds this size in RAM.
     ///
     /// Units: B (bytes), K (kilobytes), M (megabytes), G (gigabytes)
@@ -70,6 +193,14 @@ pub struct Args {
     #[clap(long, default_value = "1G", value_name = "SIZE")]
     pub max_mempool_size: ByteSize,
 
+    /// Prune the mempool when its transactions exceed this many megabytes,
+    /// evicting those with the lowest fee per byte first. Overrides
+    /// --max-mempool-size.
+    ///
+    /// E.g. --max-mempool-size-mb 300
+    #[clap(long, value_name = "MB", conflicts_with = "max_mempool_size")]
+    pub max_mempool_size_mb: Option<u64>,
+
     /// Maximum number of transactions permitted in the mempool.
     ///
     /// If too much time is spent updating transaction proofs, this
@@ -79,6 +210,21 @@ pub struct Args {
     #[clap(long)]
     pub max_mempool_num_tx: Option<usize>,
 
+    /// Maximum number of mempool transactions merged into a block template,
+    /// not counting those merged into the previous template already.
+    ///
+    /// Every merger produces a new single proof, and the template cannot be
+    /// mined until all mergers are done. Capping them bounds that delay, at
+    /// the cost of leaving fees in the mempool.
+    #[clap(long)]
+    pub max_template_mergers: Option<usize>,
+
+    /// Maximum number of transactions received from peers that are verified
+    /// concurrently. Transactions arriving while this many are being verified
+    /// are dropped.
+    #[clap(long, default_value = "16", value_name = "COUNT")]
+    pub mempool_admission_queue_size: usize,
+
     /// Port on which to listen for peer connections.
     #[clap(long, default_value = "9798", value_name = "PORT")]
     pub(crate) peer_port: u16,
@@ -87,10 +233,31 @@ pub struct Args {
     #[clap(long, default_value = "9799", value_name = "PORT")]
     pub rpc_port: u16,
 
+    /// Port on which to serve JSON-RPC 2.0 over HTTP, for clients not written
+    /// in Rust. Disabled if not set. Like the RPC port, only listens on
+    /// localhost.
+    #[clap(long, value_name = "PORT")]
+    pub jsonrpc_port: Option<u16>,
+
+    /// Start in safe mode, without connecting to peers or mining, and only
+    /// serve the RPCs for inspecting and repairing the databases.
+    ///
+    /// The node also starts in safe mode if its databases are inconsistent.
+    #[clap(long)]
+    pub safe_mode: bool,
+
     /// IP on which to listen for peer connections. Will default to all network interfaces, IPv4 and IPv6.
     #[clap(short, long, default_value = "::")]
     pub listen_addr: IpAddr,
 
+    /// Outbound-only mode, for networks that do not allow incoming
+    /// connections: never listen for peer connections and advertise no listen
+    /// port to peers. Peers are found by connecting out, starting from
+    /// `--peers`, and all `--max-peers` slots are used for outgoing
+    /// connections.
+    #[clap(long, conflicts_with = "hole_punching")]
+    pub no_listen: bool,
+
     /// Max number of blocks that the client can catch up to before going into syncing mode.
     ///
     /// The process running this program should have access to at least the number of blocks
@@ -99,18 +266,110 @@ pub struct Args {
     #[clap(long, default_value = "100", value_parser(RangedI64ValueParser::<usize>::new().range(2..100000)))]
     pub max_number_of_blocks_before_syncing: usize,
 
+    /// Max number of blocks received before their parent that are kept until
+    /// the parent arrives. Each may take up to the max block size in RAM. Set
+    /// to 0 to discard such blocks.
+    #[clap(long, default_value = "20")]
+    pub max_orphan_blocks: usize,
+
     /// IPs of nodes to connect to, e.g.: --peers 8.8.8.8:9798 --peers 8.8.4.4:1337.
     #[structopt(long)]
     pub peers: Vec<SocketAddr>,
 
-    /// Specify network, `alpha`, `testnet`, or `regtest`
+    /// Specify network, `alpha`, `testnet`, `regtest`, or `custom`
     #[structopt(long, short, default_value = "alpha")]
     pub network: Network,
 
+    /// TOML file with the consensus parameters of the custom network. Required
+    /// with `--network custom`, and not allowed with any other network.
+    #[structopt(long, value_name = "FILE")]
+    pub network_params: Option<PathBuf>,
+
     /// Max number of membership proofs stored per owned UTXO
     #[structopt(long, default_value = "3")]
     pub number_of_mps_per_utxo: usize,
 
+    /// Depth below the tip at which membership proofs of owned UTXOs are
+    /// anchored. Proofs synced to the anchor survive reorganizations shallower
+    /// than this depth. Set to 0 to disable anchoring.
+    #[structopt(long, default_value = "6")]
+    pub membership_proof_anchor_depth: usize,
+
+    /// Allow spending change of own transactions that are not confirmed yet,
+    /// if confirmed funds do not suffice.
+    ///
+    /// A transaction spending unconfirmed change is held back until the
+    /// transactions paying the change are confirmed, and is dropped if any of
+    /// them is dropped from the mempool instead.
+    #[clap(long)]
+    pub spend_unconfirmed: bool,
+
+    /// How the wallet selects the UTXOs to spend, unless a `send` request
+    /// specifies otherwise.
+    ///
+    /// One of `oldest-first`, `largest-first`, `smallest-first`,
+    /// `branch-and-bound` (avoids change if possible) and `privacy-preferring`
+    /// (avoids spending from several addresses if possible).
+    #[clap(long, default_value = "oldest-first", value_name = "POLICY")]
+    pub coin_selection: CoinSelectionPolicy,
+
+    /// Number of outputs the change of a transaction is split into, of about
+    /// equal amounts.
+    ///
+    /// Change cannot be spent until its transaction is confirmed, so a wallet
+    /// whose funds sit in one UTXO can only send one transaction per block.
+    /// Splitting change gives later transactions separate UTXOs to spend, so
+    /// they can be sent in parallel. Change is split into fewer outputs where
+    /// more would make them smaller than --change-dust-threshold.
+    #[clap(long, default_value = "1", value_name = "COUNT")]
+    pub change_outputs: usize,
+
+    /// Smallest amount worth giving a change output of its own, when change
+    /// is split into several outputs.
+    #[clap(long, default_value = "1", value_name = "AMOUNT")]
+    pub change_dust_threshold: NeptuneCoins,
+
+    /// When writes to the wallet database are synced to disk.
+    ///
+    /// One of `always`, `per-block` (once a block has been applied to the
+    /// wallet, instead of after each write it causes) and `periodic` (every
+    /// --wallet-fsync-interval seconds and on shutdown). Syncing less often
+    /// speeds up processing blocks with many own UTXOs, but a power loss may
+    /// lose the most recent wallet updates.
+    #[clap(long, default_value = "always", value_name = "POLICY")]
+    pub wallet_fsync: WalletFsyncPolicy,
+
+    /// Seconds between syncs of the wallet database to disk if the wallet
+    /// fsync policy is `periodic`.
+    #[clap(long, default_value = "10", value_name = "SECONDS")]
+    pub wallet_fsync_interval: u64,
+
+    /// Run a watch-only wallet for the addresses in the given file, one
+    /// bech32m-encoded address per line, instead of the wallet in the data
+    /// directory. Its keys never touch this node, and it cannot spend or mine.
+    ///
+    /// Use a data directory of its own, since the wallet database holds the
+    /// watched UTXOs.
+    #[clap(long, value_name = "FILE", conflicts_with = "mine")]
+    pub watch_only: Option<PathBuf>,
+
+    /// Read the wallet secret from the encrypted file created by
+    /// `neptune-cli encrypt-wallet` instead of the plaintext wallet file. The
+    /// passphrase is taken from the environment variable
+    /// `NEPTUNE_WALLET_PASSPHRASE` if set, otherwise read from standard input.
+    ///
+    /// The wallet is locked after startup. While locked it receives funds but
+    /// cannot send or mine; unlock it with the `wallet_unlock` RPC.
+    #[clap(long, conflicts_with = "watch_only")]
+    pub encrypted_wallet: bool,
+
+    /// Record every call of a spend-capable RPC method in a hash-chained log
+    /// in the data directory: the method, a digest of its parameters, the
+    /// outcome and the transaction ID. Export the log with
+    /// `neptune-cli export-spend-audit-log`.
+    #[clap(long)]
+    pub spend_audit_log: bool,
+
     /// Configure how complicated proofs this machine is capable of producing.
     /// If no value is set, this parameter is estimated. For privacy, this level
     /// must not be set to [`TxProvingCapability::LockScript`], as this leaks
@@ -122,6 +381,12 @@ pub struct Args {
     #[clap(long)]
     pub tx_proving_capability: Option<TxProvingCapability>,
 
+    /// Ask peers to only relay transactions backed by a single proof, not
+    /// those backed by a proof collection. Announced to peers in the
+    /// handshake.
+    #[clap(long)]
+    pub(crate) single_proofs_only: bool,
+
     /// The number of seconds between each attempt to upgrade transactions in
     /// the mempool to proofs of a higher quality. Will only run if the machine
     /// on which the client runs is powerful enough to produce `SingleProof`s.
@@ -130,6 +395,103 @@ pub struct Args {
     #[structopt(long, default_value = "1800")]
     pub(crate) tx_proof_upgrade_interval: u64,
 
+    /// Maximum number of seconds to wait before announcing a transaction
+    /// initiated by this node, first to a few random peers and then, after
+    /// another random delay of at most this length, to all peers. This makes
+    /// it harder to infer that the transaction originated here.
+    ///
+    /// Set to 0 to announce own transactions to all peers immediately. Always
+    /// 0 on regtest.
+    #[clap(long, default_value = "5", value_name = "SECONDS")]
+    pub(crate) tx_diffusion_max_delay: u64,
+
+    /// Number of random peers that own transactions are announced to before
+    /// they are announced to all peers. See `--tx-diffusion-max-delay`.
+    #[clap(long, default_value = "2", value_name = "COUNT")]
+    pub(crate) tx_diffusion_initial_peers: usize,
+
+    /// Public key, as 64 hex characters, of a trusted checkpoint signer. May
+    /// be given multiple times. Enables the checkpoint beacon: signed
+    /// checkpoints submitted over RPC are used to prioritize sync targets and
+    /// to warn about eclipse attacks. They never override proof-of-work
+    /// validity.
+    #[clap(long, value_name = "HEX")]
+    pub(crate) beacon_key: Vec<BeaconKey>,
+
+    /// Digest of the canonical block at a height, as `<height>:<digest>`. May
+    /// be given multiple times, and takes precedence over the network's
+    /// hardcoded checkpoint at the same height. Blocks that do not match the
+    /// checkpoint at their height are rejected. During the initial sync, the
+    /// proofs of blocks at or below the highest checkpoint are not verified;
+    /// all other consensus rules are still checked.
+    #[clap(long, value_name = "HEIGHT:DIGEST")]
+    pub(crate) checkpoint: Vec<ValidationCheckpoint>,
+
+    /// Verify all block proofs, also during the initial sync, ignoring
+    /// hardcoded checkpoints.
+    #[clap(long, conflicts_with = "checkpoint")]
+    pub(crate) no_checkpoints: bool,
+
+    /// Record which consensus rules each validated block passed or failed,
+    /// and how long each check took. Reports are logged, and those of the most
+    /// recent blocks can be retrieved over RPC.
+    #[clap(long)]
+    pub(crate) trace_validation: bool,
+
+    /// Experimental: take part in NAT traversal. Outgoing peer connections
+    /// are made from the peer port. Peers that cannot accept incoming
+    /// connections are introduced to each other, and introductions from peers
+    /// are followed by connecting directly to the introduced peer.
+    #[clap(long)]
+    pub(crate) hole_punching: bool,
+
+    /// Ask the router to forward the peer port, through UPnP or NAT-PMP, and
+    /// announce the external address it reports to peers. For nodes behind a
+    /// home router that should accept incoming connections.
+    #[clap(long, conflicts_with = "no_listen")]
+    pub(crate) port_mapping: bool,
+
+    /// Coordinate coinjoin sessions for peers, merging contributions that each
+    /// pay this amount to one of their outputs. Disabled if not set.
+    ///
+    /// Merging a session takes one merger of single proofs per participant,
+    /// so a coordinator should be capable of producing single proofs.
+    ///
+    /// E.g. --coinjoin-denomination 10
+    #[clap(long, value_name = "AMOUNT")]
+    pub(crate) coinjoin_denomination: Option<NeptuneCoins>,
+
+    /// Minimum number of participants of a coinjoin session coordinated by
+    /// this node. Sessions with fewer participants at their deadline fail.
+    #[clap(long, default_value = "3", value_name = "COUNT")]
+    pub(crate) coinjoin_min_participants: usize,
+
+    /// Number of participants at which a coinjoin session coordinated by this
+    /// node is merged without waiting for its deadline.
+    #[clap(long, default_value = "8", value_name = "COUNT")]
+    pub(crate) coinjoin_max_participants: usize,
+
+    /// Number of seconds a coinjoin session coordinated by this node collects
+    /// contributions.
+    #[clap(long, default_value = "600", value_name = "SECONDS")]
+    pub(crate) coinjoin_session_duration: u64,
+
+    /// Number of worker processes that verify proofs received from peers.
+    /// Workers isolate the memory spikes of verification from the node and
+    /// verify in parallel. If 0, proofs are verified in the node process.
+    #[clap(long, default_value = "0", value_name = "COUNT")]
+    pub(crate) verifier_workers: usize,
+
+    /// Run as a verifier worker, serving requests on stdin. Used internally
+    /// by the node to start its verifier workers.
+    #[clap(long, hide = true)]
+    pub verifier_worker: bool,
+
+    /// Log the duration of every database operation, lock acquisition, and
+    /// proof generation, for diagnosing performance problems.
+    #[clap(long)]
+    pub profile_io: bool,
+
     /// Enable tokio tracing for consumption by the tokio-console application
     /// note: this will attempt to connect to localhost:6669
     #[structopt(long, name = "tokio-console", default_value = "false")]
@@ -146,7 +508,7 @@ impl Default for Args {
 impl Args {
     /// Indicates if all incoming peer connections are disallowed.
     pub(crate) fn disallow_all_incoming_peer_connections(&self) -> bool {
-        self.max_peers.is_zero()
+        self.no_listen || self.max_peers.is_zero()
     }
 
     /// Return the port that peer can connect on. None if incoming connections
@@ -159,6 +521,53 @@ impl Args {
         }
     }
 
+    /// Return the local port that outgoing peer connections are made from, if
+    /// it is fixed for hole punching.
+    pub(crate) fn hole_punching_port(&self) -> Option<u16> {
+        self.hole_punching.then_some(self.peer_port)
+    }
+
+    /// Maximum combined size of the transactions in the mempool.
+    pub(crate) fn mempool_size_limit(&self) -> ByteSize {
+        self.max_mempool_size_mb
+            .map(ByteSize::mb)
+            .unwrap_or(self.max_mempool_size)
+    }
+
+    /// Limits of the coinjoin sessions coordinated by this node. `None` if
+    /// this node does not coordinate coinjoins.
+    pub(crate) fn coinjoin_config(&self) -> Option<CoinJoinConfig> {
+        let denomination = self.coinjoin_denomination?;
+        let min_participants = self.coinjoin_min_participants.max(2);
+        Some(CoinJoinConfig {
+            denomination,
+            min_participants,
+            max_participants: self.coinjoin_max_participants.max(min_participants),
+            session_duration: Timestamp::seconds(self.coinjoin_session_duration),
+        })
+    }
+
+    /// The checkpoints below which block proofs are not verified during the
+    /// initial sync.
+    pub(crate) fn validation_checkpoints(&self) -> ValidationCheckpoints {
+        if self.no_checkpoints {
+            return ValidationCheckpoints::none();
+        }
+        ValidationCheckpoints::new(self.network, &self.checkpoint)
+    }
+
+    /// Install the parameters of the custom network, if the node runs on it.
+    pub(crate) fn install_network_params(&self) -> Result<()> {
+        match (self.network, &self.network_params) {
+            (Network::Custom, Some(path)) => NetworkParams::load(path)?.install(),
+            (Network::Custom, None) => bail!("--network custom requires --network-params"),
+            (network, Some(_)) => {
+                bail!("--network-params is only allowed with --network custom, not {network}")
+            }
+            (_, None) => Ok(()),
+        }
+    }
+
     /// Returns how often we should attempt to upgrade transaction proofs.
     pub(crate) fn tx_upgrade_interval(&self) -> Option<Duration> {
         match self.tx_proof_upgrade_interval {
@@ -180,6 +589,19 @@ mod cli_args_tests {
 
         assert_eq!(100, default_args.peer_tolerance);
         assert_eq!(10, default_args.max_peers);
+        assert_eq!(120, default_args.max_peer_block_requests_per_minute);
+        assert_eq!(600, default_args.max_peer_transactions_per_minute);
+        assert_eq!(10, default_args.max_handshakes_per_minute);
+        assert_eq!(1200, default_args.max_peer_blocks_served_per_minute);
+        assert_eq!(4, default_args.max_concurrent_archival_requests);
+        assert_eq!(None, default_args.coinjoin_config());
+        assert!(!default_args.single_proofs_only);
+        assert!(!default_args.spend_audit_log);
+        assert!(default_args.checkpoint.is_empty());
+        assert!(!default_args.no_checkpoints);
+        assert!(!default_args.trace_validation);
+        assert_eq!(None, default_args.network_params);
+        assert!(default_args.install_network_params().is_ok());
         assert_eq!(9798, default_args.peer_port);
         assert_eq!(9799, default_args.rpc_port);
         assert_eq!(
@@ -187,7 +609,19 @@ mod cli_args_tests {
             default_args.listen_addr
         );
         assert_eq!(None, default_args.max_mempool_num_tx);
+        assert_eq!(None, default_args.max_template_mergers);
+        assert!(!default_args.build_tx_index);
+        assert_eq!(None, default_args.pow_solver);
+        assert!(!default_args.external_mining);
+        assert_eq!(None, default_args.work_server_port);
+        assert_eq!(1_000_000, default_args.share_difficulty);
+        assert_eq!(ByteSize::gb(1), default_args.mempool_size_limit());
+        assert_eq!(WalletFsyncPolicy::Always, default_args.wallet_fsync);
+        assert_eq!(1, default_args.change_outputs);
+        assert_eq!(NeptuneCoins::one(), default_args.change_dust_threshold);
         assert_eq!(1800, default_args.tx_proof_upgrade_interval);
+        assert_eq!(5, default_args.tx_diffusion_max_delay);
+        assert_eq!(2, default_args.tx_diffusion_initial_peers);
     }
 
     #[test]
@@ -207,4 +641,14 @@ mod cli_args_tests {
         };
         assert!(args.disallow_all_incoming_peer_connections());
     }
+
+    #[test]
+    fn no_listen_means_no_incoming_connections() {
+        let args = Args::parse_from(["neptune-core", "--no-listen"]);
+        assert!(args.disallow_all_incoming_peer_connections());
+        assert!(args.own_listen_port().is_none());
+        assert!(args.max_peers > 0);
+
+        assert!(Args::try_parse_from(["neptune-core", "--no-listen", "--hole-punching"]).is_err());
+    }
 }
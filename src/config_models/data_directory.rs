@@ -9,10 +9,12 @@ use crate::config_models::network::Network;
 use crate::models::database::DATABASE_DIRECTORY_ROOT_NAME;
 use crate::models::state::archival_state::BLOCK_INDEX_DB_NAME;
 use crate::models::state::archival_state::MUTATOR_SET_DIRECTORY_NAME;
+use crate::models::state::event_journal::EVENT_JOURNAL_DB_NAME;
 use crate::models::state::networking_state::BANNED_IPS_DB_NAME;
 use crate::models::state::shared::BLOCK_FILENAME_EXTENSION;
 use crate::models::state::shared::BLOCK_FILENAME_PREFIX;
 use crate::models::state::shared::DIR_NAME_FOR_BLOCKS;
+use crate::models::state::tx_index::TX_INDEX_DB_NAME;
 use crate::models::state::wallet::WALLET_DB_NAME;
 use crate::models::state::wallet::WALLET_DIRECTORY;
 use crate::models::state::wallet::WALLET_OUTPUT_COUNT_DB_NAME;
@@ -91,6 +93,25 @@ impl DataDirectory {
         self.database_dir_path().join(Path::new(BANNED_IPS_DB_NAME))
     }
 
+    ///////////////////////////////////////////////////////////////////////////
+    ///
+    /// The event journal database directory path.
+    ///
+    /// This directory lives within `DataDirectory::database_dir_path()`.
+    pub fn event_journal_database_dir_path(&self) -> PathBuf {
+        self.database_dir_path()
+            .join(Path::new(EVENT_JOURNAL_DB_NAME))
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+    ///
+    /// The transaction index database directory path.
+    ///
+    /// This directory lives within `DataDirectory::database_dir_path()`.
+    pub fn tx_index_database_dir_path(&self) -> PathBuf {
+        self.database_dir_path().join(Path::new(TX_INDEX_DB_NAME))
+    }
+
     ///////////////////////////////////////////////////////////////////////////
     ///
     /// The wallet file path
@@ -3,14 +3,21 @@ use std::str::FromStr;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
 
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use strum::EnumIter;
+use strum::IntoEnumIterator;
 use tasm_lib::twenty_first::math::b_field_element::BFieldElement;
 
+use super::network_params::NetworkParams;
+use crate::models::peer::message_codec::MessageSizeLimits;
 use crate::models::proof_abstractions::timestamp::Timestamp;
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default, EnumIter)]
+#[derive(
+    Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default, EnumIter, JsonSchema,
+)]
+#[non_exhaustive]
 pub enum Network {
     /// Main net. Feature-complete. Fixed launch date. Not ready yet.
     Main,
@@ -36,12 +43,124 @@ pub enum Network {
     /// this will invalidate the stored proofs when the rounded timestamp
     /// changes.
     RegTest,
+
+    /// Private network with the consensus parameters given in a file with
+    /// `--network-params`. See [`NetworkParams`].
+    Custom,
+}
+
+/// How the timestamp of the genesis block is determined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LaunchDate {
+    /// Fixed, in milliseconds since the UNIX epoch.
+    Fixed(u64),
+
+    /// Now, rounded down to a multiple of seven days.
+    RoundedNow,
+
+    /// Set by the installed [`NetworkParams`].
+    Configured,
+}
+
+/// Everything that distinguishes one network from another. Network-dependent
+/// behavior derives from this, so adding a network takes a new [`Network`]
+/// variant and its definition in [`Network::definition`], without touching
+/// consensus code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct NetworkDefinition {
+    /// Name on the command line and in logs.
+    name: &'static str,
+
+    /// Appended to the magic values of peer handshakes, such that nodes on
+    /// different networks refuse each other as early as possible.
+    magic: [u8; 4],
+
+    /// Last character of the human-readable part of generation addresses.
+    address_network_byte: char,
+
+    launch_date: LaunchDate,
+
+    /// Mixed into the premine commitments of the genesis block, such that
+    /// networks have distinct genesis blocks even if they share a launch
+    /// date.
+    genesis_seed: u64,
+
+    /// Maximum sizes of peer messages, enforced before deserialization.
+    message_size_limits: MessageSizeLimits,
+
+    /// Heights and hex-encoded digests of canonical blocks, below which
+    /// block proofs are not verified during the initial sync. See
+    /// [`ValidationCheckpoints`](crate::models::blockchain::block::validation_checkpoints::ValidationCheckpoints).
+    validation_checkpoints: &'static [(u64, &'static str)],
 }
 
 impl Network {
-    pub(crate) fn launch_date(&self) -> Timestamp {
+    fn definition(&self) -> NetworkDefinition {
+        // 1 July 2024 (might be revised though)
+        const LAUNCH_DATE: LaunchDate = LaunchDate::Fixed(1719792000000u64);
+
         match self {
-            Network::RegTest => {
+            Network::Main => NetworkDefinition {
+                name: "main",
+                magic: *b"NPTm",
+                address_network_byte: 'm',
+                launch_date: LAUNCH_DATE,
+                genesis_seed: 0,
+                message_size_limits: MessageSizeLimits::STANDARD,
+                validation_checkpoints: &[],
+            },
+            Network::Alpha => NetworkDefinition {
+                name: "alpha",
+                magic: *b"NPTa",
+                address_network_byte: 'm',
+                launch_date: LAUNCH_DATE,
+                genesis_seed: 1,
+                message_size_limits: MessageSizeLimits::STANDARD,
+                validation_checkpoints: &[],
+            },
+            Network::Beta => NetworkDefinition {
+                name: "beta",
+                magic: *b"NPTb",
+                address_network_byte: 'm',
+                launch_date: LAUNCH_DATE,
+                genesis_seed: 2,
+                message_size_limits: MessageSizeLimits::STANDARD,
+                validation_checkpoints: &[],
+            },
+            Network::Testnet => NetworkDefinition {
+                name: "testnet",
+                magic: *b"NPTt",
+                address_network_byte: 't',
+                launch_date: LAUNCH_DATE,
+                genesis_seed: 3,
+                message_size_limits: MessageSizeLimits::STANDARD,
+                validation_checkpoints: &[],
+            },
+            Network::RegTest => NetworkDefinition {
+                name: "regtest",
+                magic: *b"NPTr",
+                address_network_byte: 'r',
+                launch_date: LaunchDate::RoundedNow,
+                genesis_seed: 4,
+                message_size_limits: MessageSizeLimits::STANDARD,
+                validation_checkpoints: &[],
+            },
+            Network::Custom => NetworkDefinition {
+                name: "custom",
+                magic: *b"NPTc",
+                address_network_byte: 'c',
+                launch_date: LaunchDate::Configured,
+                genesis_seed: 5,
+                message_size_limits: MessageSizeLimits::STANDARD,
+                validation_checkpoints: &[],
+            },
+        }
+    }
+
+    pub(crate) fn launch_date(&self) -> Timestamp {
+        match self.definition().launch_date {
+            LaunchDate::Fixed(millis) => Timestamp(BFieldElement::new(millis)),
+            LaunchDate::RoundedNow => {
                 let now = SystemTime::now()
                     .duration_since(UNIX_EPOCH)
                     .unwrap()
@@ -50,43 +169,60 @@ impl Network {
                 let now_rounded = (now / SEVEN_DAYS) * SEVEN_DAYS;
                 Timestamp(BFieldElement::new(now_rounded))
             }
-            // 1 July 2024 (might be revised though)
-            Network::Alpha | Network::Testnet | Network::Beta | Network::Main => {
-                Timestamp(BFieldElement::new(1719792000000u64))
-            }
+            LaunchDate::Configured => NetworkParams::in_effect().launch_date(),
+        }
+    }
+
+    /// Network-specific suffix of the peer handshake magic values.
+    pub(crate) fn magic(&self) -> [u8; 4] {
+        self.definition().magic
+    }
+
+    pub(crate) fn address_network_byte(&self) -> char {
+        self.definition().address_network_byte
+    }
+
+    pub(crate) fn genesis_seed(&self) -> u64 {
+        self.definition().genesis_seed
+    }
+
+    pub(crate) fn message_size_limits(&self) -> MessageSizeLimits {
+        self.definition().message_size_limits
+    }
+
+    pub(crate) fn validation_checkpoints(&self) -> &'static [(u64, &'static str)] {
+        self.definition().validation_checkpoints
+    }
+
+    /// The consensus parameters that can be configured for the custom
+    /// network. The built-in networks share the standard ones, except for
+    /// their launch dates and premines, which are hardcoded.
+    pub(crate) fn params(&self) -> &'static NetworkParams {
+        match self {
+            Network::Custom => NetworkParams::in_effect(),
+            _ => NetworkParams::standard(),
         }
     }
 }
 
 impl fmt::Display for Network {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let string = match self {
-            Network::Alpha => "alpha".to_string(),
-            Network::Testnet => "testnet".to_string(),
-            Network::RegTest => "regtest".to_string(),
-            Network::Beta => "beta".to_string(),
-            Network::Main => "main".to_string(),
-        };
-        write!(f, "{}", string)
+        write!(f, "{}", self.definition().name)
     }
 }
 
 impl FromStr for Network {
     type Err = String;
     fn from_str(input: &str) -> Result<Network, Self::Err> {
-        match input {
-            "alpha" => Ok(Network::Alpha),
-            "testnet" => Ok(Network::Testnet),
-            "regtest" => Ok(Network::RegTest),
-            "beta" => Ok(Network::Beta),
-            "main" => Ok(Network::Main),
-            _ => Err(format!("Failed to parse {} as network", input)),
-        }
+        Network::iter()
+            .find(|network| network.definition().name == input)
+            .ok_or_else(|| format!("Failed to parse {} as network", input))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use itertools::Itertools;
     use num_traits::Zero;
 
     use super::*;
@@ -95,4 +231,24 @@ mod tests {
     fn main_variant_is_zero() {
         assert!((Network::Main as u32).is_zero());
     }
+
+    #[test]
+    fn network_definitions_are_distinct() {
+        let definitions = Network::iter().map(|n| n.definition()).collect_vec();
+        assert!(definitions.iter().map(|d| d.name).all_unique());
+        assert!(definitions.iter().map(|d| d.magic).all_unique());
+        assert!(definitions.iter().map(|d| d.genesis_seed).all_unique());
+
+        for network in Network::iter() {
+            assert_eq!(network, network.to_string().parse().unwrap());
+        }
+    }
+
+    #[test]
+    fn genesis_seed_matches_variant_index() {
+        // Genesis blocks of existing networks must not change.
+        for network in Network::iter() {
+            assert_eq!(network as u64, network.genesis_seed());
+        }
+    }
 }
@@ -0,0 +1,203 @@
+//! Consensus parameters of the `custom` network.
+//!
+//! Private networks and regtest setups that need other values than the
+//! built-in networks run on [`Network::Custom`], whose parameters are read
+//! from a TOML file given with `--network-params`. Parameters missing from
+//! the file take the values of the built-in networks. A node runs on a single
+//! network, so the parameters are installed once at startup, and consensus
+//! code that is not told the network reads them through
+//! [`NetworkParams::in_effect`].
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use tasm_lib::twenty_first::math::b_field_element::BFieldElement;
+
+use super::network::Network;
+use crate::models::blockchain::block::block_header::MINIMUM_BLOCK_TIME;
+use crate::models::blockchain::block::block_header::TARGET_BLOCK_INTERVAL;
+use crate::models::blockchain::block::difficulty_control::Difficulty;
+use crate::models::blockchain::block::MAX_BLOCK_SIZE;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::models::state::wallet::address::ReceivingAddress;
+
+/// Parameters of the custom network, once installed.
+static CUSTOM_NETWORK_PARAMS: OnceLock<NetworkParams> = OnceLock::new();
+
+/// Parameters of the built-in networks.
+static STANDARD_NETWORK_PARAMS: OnceLock<NetworkParams> = OnceLock::new();
+
+/// A recipient of part of the premine.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PremineAllocation {
+    /// Generation address, encoded for the custom network
+    pub address: String,
+
+    /// In whole coins
+    pub amount: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkParams {
+    /// Timestamp of the genesis block, in milliseconds since the UNIX epoch.
+    pub launch_date: u64,
+    pub premine: Vec<PremineAllocation>,
+
+    /// Difficulty of the genesis block, below which the difficulty never
+    /// drops.
+    pub minimum_difficulty: u32,
+
+    /// Desired average time between blocks, in milliseconds.
+    pub target_block_interval: u64,
+
+    /// In number of `BFieldElement`s.
+    pub max_block_size: usize,
+}
+
+impl Default for NetworkParams {
+    fn default() -> Self {
+        Self {
+            launch_date: Network::Main.launch_date().to_millis(),
+            premine: vec![],
+            minimum_difficulty: Difficulty::MINIMUM.into_iter().next().unwrap_or_default(),
+            target_block_interval: TARGET_BLOCK_INTERVAL.to_millis(),
+            max_block_size: MAX_BLOCK_SIZE,
+        }
+    }
+}
+
+impl NetworkParams {
+    /// Read and check the parameters in a TOML file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let toml = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        Self::from_toml(&toml)
+            .with_context(|| format!("invalid network parameters in {}", path.display()))
+    }
+
+    fn from_toml(toml: &str) -> Result<Self> {
+        let params: Self = toml::from_str(toml)?;
+        ensure!(
+            params.minimum_difficulty() >= Difficulty::MINIMUM,
+            "minimum difficulty must be at least {}",
+            Difficulty::MINIMUM
+        );
+        ensure!(
+            params.target_block_interval() > MINIMUM_BLOCK_TIME,
+            "target block interval must exceed the minimum block time of {} ms",
+            MINIMUM_BLOCK_TIME.to_millis()
+        );
+        ensure!(
+            params.max_block_size > 0,
+            "maximum block size must be positive"
+        );
+        params.premine_distribution()?;
+
+        Ok(params)
+    }
+
+    /// Make these the parameters of [`Network::Custom`] for the rest of the
+    /// process.
+    pub fn install(self) -> Result<()> {
+        if CUSTOM_NETWORK_PARAMS.set(self).is_err() {
+            bail!("network parameters were already installed");
+        }
+        Ok(())
+    }
+
+    /// The parameters of the network the node runs on: the installed ones,
+    /// which is the case on the custom network only, or those of the built-in
+    /// networks.
+    pub(crate) fn in_effect() -> &'static Self {
+        CUSTOM_NETWORK_PARAMS.get().unwrap_or_else(Self::standard)
+    }
+
+    /// The parameters of the built-in networks.
+    pub(crate) fn standard() -> &'static Self {
+        STANDARD_NETWORK_PARAMS.get_or_init(Self::default)
+    }
+
+    pub(crate) fn launch_date(&self) -> Timestamp {
+        Timestamp(BFieldElement::new(self.launch_date))
+    }
+
+    pub(crate) fn minimum_difficulty(&self) -> Difficulty {
+        Difficulty::from(self.minimum_difficulty)
+    }
+
+    pub(crate) fn target_block_interval(&self) -> Timestamp {
+        Timestamp::millis(self.target_block_interval)
+    }
+
+    pub(crate) fn premine_distribution(&self) -> Result<Vec<(ReceivingAddress, NeptuneCoins)>> {
+        self.premine
+            .iter()
+            .map(|allocation| {
+                let address = ReceivingAddress::from_bech32m(&allocation.address, Network::Custom)
+                    .with_context(|| format!("invalid premine address {}", allocation.address))?;
+                Ok((address, NeptuneCoins::new(allocation.amount)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod network_params_tests {
+    use super::*;
+    use crate::models::state::wallet::WalletSecret;
+
+    #[test]
+    fn missing_parameters_take_standard_values() {
+        let params = NetworkParams::from_toml("target_block_interval = 60001").unwrap();
+        assert_eq!(Timestamp::millis(60001), params.target_block_interval());
+        assert_eq!(Difficulty::MINIMUM, params.minimum_difficulty());
+        assert_eq!(MAX_BLOCK_SIZE, params.max_block_size);
+        assert_eq!(Network::Main.launch_date(), params.launch_date());
+        assert_eq!(
+            NetworkParams::default(),
+            NetworkParams::from_toml("").unwrap()
+        );
+    }
+
+    #[test]
+    fn premine_is_paid_to_custom_network_addresses() {
+        let address = WalletSecret::devnet_wallet()
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+        let toml = format!(
+            "launch_date = 1719792000000\n\
+             minimum_difficulty = 5000\n\
+             \n\
+             [[premine]]\n\
+             address = \"{}\"\n\
+             amount = 100\n",
+            address.to_bech32m(Network::Custom).unwrap()
+        );
+
+        let params = NetworkParams::from_toml(&toml).unwrap();
+        let premine = params.premine_distribution().unwrap();
+        assert_eq!(vec![(address.into(), NeptuneCoins::new(100))], premine);
+        assert_eq!(Difficulty::from(5000u32), params.minimum_difficulty());
+
+        let main_address = address.to_bech32m(Network::Main).unwrap();
+        let toml = format!("[[premine]]\naddress = \"{main_address}\"\namount = 1\n");
+        assert!(NetworkParams::from_toml(&toml).is_err());
+    }
+
+    #[test]
+    fn invalid_parameters_are_rejected() {
+        assert!(NetworkParams::from_toml("minimum_difficulty = 1").is_err());
+        assert!(NetworkParams::from_toml("target_block_interval = 1000").is_err());
+        assert!(NetworkParams::from_toml("max_block_size = 0").is_err());
+        assert!(NetworkParams::from_toml("block_reward = 10").is_err());
+    }
+}
@@ -1,5 +1,9 @@
 use std::fmt::Debug;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
 use std::net::SocketAddr;
+use std::time::Duration;
 
 use anyhow::bail;
 use anyhow::Result;
@@ -8,20 +12,24 @@ use futures::SinkExt;
 use futures::TryStreamExt;
 use tokio::io::AsyncRead;
 use tokio::io::AsyncWrite;
+use tokio::net::TcpListener;
+use tokio::net::TcpSocket;
+use tokio::net::TcpStream;
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tokio_serde::formats::Bincode;
 use tokio_serde::formats::SymmetricalBincode;
 use tokio_serde::SymmetricallyFramed;
 use tokio_util::codec::Framed;
-use tokio_util::codec::LengthDelimitedCodec;
 use tracing::debug;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
 
+use crate::config_models::network::Network;
 use crate::models::channel::MainToPeerTask;
 use crate::models::channel::PeerTaskToMain;
+use crate::models::peer::message_codec::PeerMessageCodec;
 use crate::models::peer::ConnectionRefusedReason;
 use crate::models::peer::ConnectionStatus;
 use crate::models::peer::HandshakeData;
@@ -32,16 +40,29 @@ use crate::peer_loop::PeerLoopHandler;
 use crate::MAGIC_STRING_REQUEST;
 use crate::MAGIC_STRING_RESPONSE;
 
-// Max peer message size is 2000MB
-pub const MAX_PEER_FRAME_LENGTH_IN_BYTES: usize = 2000 * 1024 * 1024;
+const PEER_LISTENER_BACKLOG: u32 = 1024;
+const HOLE_PUNCH_ATTEMPTS: usize = 5;
+const HOLE_PUNCH_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a peer attempts to connect back to a node asking whether it is
+/// reachable.
+pub(crate) const REACHABILITY_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Magic value opening a handshake request from a node on the given network.
+pub(crate) fn handshake_request_magic(network: Network) -> Vec<u8> {
+    [MAGIC_STRING_REQUEST, &network.magic()].concat()
+}
+
+/// Magic value opening a handshake response from a node on the given network.
+pub(crate) fn handshake_response_magic(network: Network) -> Vec<u8> {
+    [MAGIC_STRING_RESPONSE, &network.magic()].concat()
+}
 
 /// Use this function to ensure that the same rules apply for both
 /// ingoing and outgoing connections. This limits the size of messages
 /// peers can send.
-fn get_codec_rules() -> LengthDelimitedCodec {
-    let mut codec_rules = LengthDelimitedCodec::new();
-    codec_rules.set_max_frame_length(MAX_PEER_FRAME_LENGTH_IN_BYTES);
-    codec_rules
+fn get_codec_rules(network: Network) -> PeerMessageCodec {
+    PeerMessageCodec::new(network.message_size_limits())
 }
 
 /// Check if connection is allowed. Used for both ingoing and outgoing connections.
@@ -134,6 +155,16 @@ async fn check_if_connection_is_allowed(
         return ConnectionStatus::Refused(ConnectionRefusedReason::IncompatibleVersion);
     }
 
+    // Disallow connection if the peer asks for transactions with secrets that
+    // must not be shared on this network
+    if !other_handshake
+        .accepted_tx_proofs
+        .is_allowed_on(own_handshake.network)
+    {
+        warn!("Peer asks for primitive witnesses outside of regtest");
+        return ConnectionStatus::Refused(ConnectionRefusedReason::IncompatibleVersion);
+    }
+
     info!("ConnectionStatus::Accepted");
     ConnectionStatus::Accepted
 }
@@ -197,9 +228,9 @@ where
     info!("Established incoming TCP connection with {peer_address}");
 
     // Build the communication/serialization/frame handler
-    let length_delimited = Framed::new(stream, get_codec_rules());
+    let length_delimited = Framed::new(stream, get_codec_rules(own_handshake_data.network));
     let mut peer: tokio_serde::Framed<
-        Framed<S, LengthDelimitedCodec>,
+        Framed<S, PeerMessageCodec>,
         PeerMessage,
         PeerMessage,
         Bincode<PeerMessage, PeerMessage>,
@@ -209,12 +240,16 @@ where
     let peer_handshake_data: HandshakeData = match peer.try_next().await? {
         Some(PeerMessage::Handshake(payload)) => {
             let (v, hsd) = *payload;
-            if v != crate::MAGIC_STRING_REQUEST {
-                bail!("Expected magic value, got {:?}", v);
+            if v != handshake_request_magic(own_handshake_data.network) {
+                bail!(
+                    "Expected magic value of network {}, got {:?}",
+                    own_handshake_data.network,
+                    v
+                );
             }
 
             peer.send(PeerMessage::Handshake(Box::new((
-                crate::MAGIC_STRING_RESPONSE.to_vec(),
+                handshake_response_magic(own_handshake_data.network),
                 own_handshake_data.clone(),
             ))))
             .await?;
@@ -272,6 +307,59 @@ where
     Ok(())
 }
 
+/// Open an outgoing TCP connection. With hole punching enabled, the
+/// connection is made from the port on which this node listens for peers, such
+/// that NAT mappings created by outgoing connections can be reused to punch
+/// holes.
+async fn connect(peer_address: SocketAddr, local_port: Option<u16>) -> std::io::Result<TcpStream> {
+    let Some(local_port) = local_port else {
+        return TcpStream::connect(peer_address).await;
+    };
+
+    let (socket, local_ip) = if peer_address.is_ipv4() {
+        (TcpSocket::new_v4()?, IpAddr::from(Ipv4Addr::UNSPECIFIED))
+    } else {
+        (TcpSocket::new_v6()?, IpAddr::from(Ipv6Addr::UNSPECIFIED))
+    };
+    socket.set_reuseaddr(true)?;
+    #[cfg(unix)]
+    socket.set_reuseport(true)?;
+    socket.bind(SocketAddr::new(local_ip, local_port))?;
+    socket.connect(peer_address).await
+}
+
+/// Check whether a TCP connection to the given address can be established,
+/// on behalf of a peer asking whether it is reachable. The connection is
+/// closed right away, without a handshake.
+pub(crate) async fn probe_reachability(address: SocketAddr) -> bool {
+    matches!(
+        tokio::time::timeout(REACHABILITY_CHECK_TIMEOUT, TcpStream::connect(address)).await,
+        Ok(Ok(_))
+    )
+}
+
+/// Bind the listener for incoming peer connections. With hole punching
+/// enabled, the port may be shared with outgoing connections.
+pub(crate) fn bind_peer_listener(
+    listen_address: SocketAddr,
+    hole_punching: bool,
+) -> std::io::Result<TcpListener> {
+    let socket = if listen_address.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    socket.set_reuseaddr(true)?;
+    #[cfg(unix)]
+    if hole_punching {
+        socket.set_reuseport(true)?;
+    }
+    #[cfg(not(unix))]
+    let _ = hole_punching;
+    socket.bind(listen_address)?;
+    socket.listen(PEER_LISTENER_BACKLOG)
+}
+
 /// Perform handshake and establish connection to a new peer while handling any panics in the peer
 /// task gracefully.
 pub(crate) async fn call_peer_wrapper(
@@ -281,32 +369,113 @@ pub(crate) async fn call_peer_wrapper(
     peer_task_to_main_tx: mpsc::Sender<PeerTaskToMain>,
     own_handshake_data: HandshakeData,
     distance: u8,
+) {
+    debug!("Attempting to initiate connection");
+    match connect(peer_address, state.cli().hole_punching_port()).await {
+        Err(e) => {
+            warn!("Failed to establish connection: {}", e);
+        }
+        Ok(stream) => {
+            call_connected_peer_wrapper(
+                stream,
+                state,
+                peer_address,
+                main_to_peer_task_rx,
+                peer_task_to_main_tx,
+                own_handshake_data,
+                distance,
+            )
+            .await;
+        }
+    }
+}
+
+/// Establish a direct connection with a peer behind a NAT, as introduced by a
+/// rendezvous peer, by connecting to it while it connects to this node. The
+/// initiator performs the handshake as the calling side, the other node as the
+/// answering side.
+pub(crate) async fn hole_punch_wrapper(
+    endpoint: SocketAddr,
+    initiator: bool,
+    state: GlobalStateLock,
+    main_to_peer_task_rx: broadcast::Receiver<MainToPeerTask>,
+    peer_task_to_main_tx: mpsc::Sender<PeerTaskToMain>,
+    own_handshake_data: HandshakeData,
+) {
+    let Some(local_port) = state.cli().hole_punching_port() else {
+        return;
+    };
+
+    let mut stream = None;
+    for attempt in 1..=HOLE_PUNCH_ATTEMPTS {
+        match connect(endpoint, Some(local_port)).await {
+            Ok(connected) => {
+                stream = Some(connected);
+                break;
+            }
+            Err(e) => {
+                debug!("Hole punching attempt {attempt} to {endpoint} failed: {e}");
+                tokio::time::sleep(HOLE_PUNCH_RETRY_INTERVAL).await;
+            }
+        }
+    }
+    let Some(stream) = stream else {
+        info!("Could not punch hole to {endpoint}");
+        return;
+    };
+
+    info!("Punched hole to {endpoint}");
+    if initiator {
+        call_connected_peer_wrapper(
+            stream,
+            state,
+            endpoint,
+            main_to_peer_task_rx,
+            peer_task_to_main_tx,
+            own_handshake_data,
+            1, // the introduced peer is a direct neighbor of a neighbor
+        )
+        .await;
+    } else if let Err(e) = answer_peer_wrapper(
+        stream,
+        state,
+        endpoint,
+        main_to_peer_task_rx,
+        peer_task_to_main_tx,
+        own_handshake_data,
+    )
+    .await
+    {
+        error!("An error occurred: {}. Connection closing", e);
+    }
+}
+
+async fn call_connected_peer_wrapper(
+    stream: TcpStream,
+    state: GlobalStateLock,
+    peer_address: std::net::SocketAddr,
+    main_to_peer_task_rx: broadcast::Receiver<MainToPeerTask>,
+    peer_task_to_main_tx: mpsc::Sender<PeerTaskToMain>,
+    own_handshake_data: HandshakeData,
+    distance: u8,
 ) {
     let state_clone = state.clone();
     let peer_task_to_main_tx_clone = peer_task_to_main_tx.clone();
     let panic_result = std::panic::AssertUnwindSafe(async {
-        debug!("Attempting to initiate connection");
-        match tokio::net::TcpStream::connect(peer_address).await {
-            Err(e) => {
-                warn!("Failed to establish connection: {}", e);
-            }
-            Ok(stream) => {
-                match call_peer(
-                    stream,
-                    state,
-                    peer_address,
-                    main_to_peer_task_rx,
-                    peer_task_to_main_tx,
-                    &own_handshake_data,
-                    distance,
-                )
-                .await
-                {
-                    Ok(()) => (),
-                    Err(e) => error!("An error occurred: {}. Connection closing", e),
-                }
-            }
-        };
+        match call_peer(
+            stream,
+            state,
+            peer_address,
+            main_to_peer_task_rx,
+            peer_task_to_main_tx,
+            &own_handshake_data,
+            distance,
+        )
+        .await
+        {
+            Ok(()) => (),
+            Err(e) => error!("An error occurred: {}. Connection closing", e),
+        }
 
         info!("Connection closing");
     })
@@ -342,9 +511,9 @@ where
     info!("Established outgoing TCP connection with {peer_address}");
 
     // Build the communication/serialization/frame handler
-    let length_delimited = Framed::new(stream, get_codec_rules());
+    let length_delimited = Framed::new(stream, get_codec_rules(own_handshake.network));
     let mut peer: tokio_serde::Framed<
-        Framed<S, LengthDelimitedCodec>,
+        Framed<S, PeerMessageCodec>,
         PeerMessage,
         PeerMessage,
         Bincode<PeerMessage, PeerMessage>,
@@ -352,7 +521,7 @@ where
 
     // Make Neptune handshake
     peer.send(PeerMessage::Handshake(Box::new((
-        Vec::from(MAGIC_STRING_REQUEST),
+        handshake_request_magic(own_handshake.network),
         own_handshake.to_owned(),
     ))))
     .await?;
@@ -361,8 +530,11 @@ where
     let other_handshake: HandshakeData = match peer.try_next().await? {
         Some(PeerMessage::Handshake(payload)) => {
             let (v, hsd) = *payload;
-            if v != MAGIC_STRING_RESPONSE {
-                bail!("Didn't get expected magic value for handshake");
+            if v != handshake_response_magic(own_handshake.network) {
+                bail!(
+                    "Didn't get expected magic value of network {} for handshake",
+                    own_handshake.network
+                );
             }
             if hsd.network != own_handshake.network {
                 bail!(
@@ -440,6 +612,7 @@ pub(crate) async fn close_peer_connected_callback(
     let mut global_state_mut = global_state_lock.lock_guard_mut().await;
     // Store any new peer-standing to database
     let peer_info_writeback = global_state_mut.net.peer_map.remove(&peer_address);
+    global_state_mut.net.coinjoin_sessions.remove(&peer_address);
 
     let new_standing = match peer_info_writeback {
         Some(new) => new.standing,
@@ -474,7 +647,6 @@ mod connect_tests {
     use twenty_first::math::digest::Digest;
 
     use super::*;
-    use crate::config_models::network::Network;
     use crate::models::peer::ConnectionStatus;
     use crate::models::peer::PeerInfo;
     use crate::models::peer::PeerMessage;
@@ -486,8 +658,6 @@ mod connect_tests {
     use crate::tests::shared::get_dummy_socket_address;
     use crate::tests::shared::get_test_genesis_setup;
     use crate::tests::shared::to_bytes;
-    use crate::MAGIC_STRING_REQUEST;
-    use crate::MAGIC_STRING_RESPONSE;
 
     #[traced_test]
     #[tokio::test]
@@ -497,11 +667,11 @@ mod connect_tests {
         let own_handshake = get_dummy_handshake_data_for_genesis(network).await;
         let mock = Builder::new()
             .write(&to_bytes(&PeerMessage::Handshake(Box::new((
-                MAGIC_STRING_REQUEST.to_vec(),
+                handshake_request_magic(network),
                 own_handshake.clone(),
             ))))?)
             .read(&to_bytes(&PeerMessage::Handshake(Box::new((
-                MAGIC_STRING_RESPONSE.to_vec(),
+                handshake_response_magic(network),
                 other_handshake,
             ))))?)
             .read(&to_bytes(&PeerMessage::ConnectionStatus(
@@ -652,6 +822,7 @@ mod connect_tests {
                 Digest::default(),
             ))),
             timestamp_of_latest_sanction: Some(SystemTime::now()),
+            violations: vec![],
         };
 
         state_lock
@@ -680,7 +851,7 @@ mod connect_tests {
     async fn test_incoming_connection_succeed() -> Result<()> {
         // This builds a mock object which expects to have a certain
         // sequence of methods called on it: First it expects to have
-        // the `MAGIC_STRING_REQUEST` and then the `MAGIC_STRING_RESPONSE`
+        // the handshake request magic and then the handshake response magic
         // value written. This is followed by a read of the bye message,
         // as this is a way to close the connection by the peer initiating
         // the connection. If this sequence is not followed, the `mock`
@@ -691,11 +862,11 @@ mod connect_tests {
         let own_handshake = get_dummy_handshake_data_for_genesis(network).await;
         let mock = Builder::new()
             .read(&to_bytes(&PeerMessage::Handshake(Box::new((
-                MAGIC_STRING_REQUEST.to_vec(),
+                handshake_request_magic(network),
                 other_handshake,
             ))))?)
             .write(&to_bytes(&PeerMessage::Handshake(Box::new((
-                MAGIC_STRING_RESPONSE.to_vec(),
+                handshake_response_magic(network),
                 own_handshake.clone(),
             ))))?)
             .write(&to_bytes(&PeerMessage::ConnectionStatus(
@@ -732,7 +903,7 @@ mod connect_tests {
         let own_handshake = get_dummy_handshake_data_for_genesis(network).await;
         let mock = Builder::new()
             .read(&to_bytes(&PeerMessage::Handshake(Box::new((
-                MAGIC_STRING_RESPONSE.to_vec(),
+                handshake_response_magic(network),
                 other_handshake,
             ))))?)
             .build();
@@ -761,13 +932,9 @@ mod connect_tests {
         let own_handshake = get_dummy_handshake_data_for_genesis(Network::Alpha).await;
         let mock = Builder::new()
             .read(&to_bytes(&PeerMessage::Handshake(Box::new((
-                MAGIC_STRING_REQUEST.to_vec(),
+                handshake_request_magic(Network::Testnet),
                 other_handshake,
             ))))?)
-            .write(&to_bytes(&PeerMessage::Handshake(Box::new((
-                MAGIC_STRING_RESPONSE.to_vec(),
-                own_handshake.clone(),
-            ))))?)
             .build();
 
         let (_peer_broadcast_tx, from_main_rx_clone, to_main_tx, _to_main_rx1, state, _hsd) =
@@ -818,14 +985,14 @@ mod connect_tests {
         let mock = Builder::new()
             .read(
                 &to_bytes(&PeerMessage::Handshake(Box::new((
-                    MAGIC_STRING_REQUEST.to_vec(),
+                    handshake_request_magic(own_handshake.network),
                     other_handshake,
                 ))))
                 .unwrap(),
             )
             .write(
                 &to_bytes(&PeerMessage::Handshake(Box::new((
-                    MAGIC_STRING_RESPONSE.to_vec(),
+                    handshake_response_magic(own_handshake.network),
                     own_handshake.clone(),
                 ))))
                 .unwrap(),
@@ -857,11 +1024,11 @@ mod connect_tests {
         let own_handshake = get_dummy_handshake_data_for_genesis(network).await;
         let mock = Builder::new()
             .read(&to_bytes(&PeerMessage::Handshake(Box::new((
-                MAGIC_STRING_REQUEST.to_vec(),
+                handshake_request_magic(network),
                 other_handshake,
             ))))?)
             .write(&to_bytes(&PeerMessage::Handshake(Box::new((
-                MAGIC_STRING_RESPONSE.to_vec(),
+                handshake_response_magic(network),
                 own_handshake.clone(),
             ))))?)
             .write(&to_bytes(&PeerMessage::ConnectionStatus(
@@ -907,11 +1074,11 @@ mod connect_tests {
         let own_handshake = get_dummy_handshake_data_for_genesis(network).await;
         let mock = Builder::new()
             .read(&to_bytes(&PeerMessage::Handshake(Box::new((
-                MAGIC_STRING_REQUEST.to_vec(),
+                handshake_request_magic(network),
                 other_handshake,
             ))))?)
             .write(&to_bytes(&PeerMessage::Handshake(Box::new((
-                MAGIC_STRING_RESPONSE.to_vec(),
+                handshake_response_magic(network),
                 own_handshake.clone(),
             ))))?)
             .write(&to_bytes(&PeerMessage::ConnectionStatus(
@@ -939,6 +1106,7 @@ mod connect_tests {
                 Digest::default(),
             ))),
             timestamp_of_latest_sanction: Some(SystemTime::now()),
+            violations: vec![],
         };
         let peer_address = get_dummy_socket_address(3);
 
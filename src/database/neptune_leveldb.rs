@@ -11,8 +11,10 @@ use leveldb_sys::Compression;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use tokio::task;
+use tracing::Instrument;
 
 use super::leveldb::DB;
+use crate::util_types::fault_injection;
 
 struct NeptuneLevelDbInternal<Key, Value>
 where
@@ -117,7 +119,7 @@ where
         self.database.put_u8(key, value).unwrap()
     }
 
-    fn batch_write(&mut self, entries: WriteBatchAsync<Key, Value>) {
+    fn batch_write(&mut self, entries: WriteBatchAsync<Key, Value>, sync: bool) {
         let batch = WriteBatch::new();
         for op in entries.0.into_iter() {
             match op {
@@ -133,7 +135,7 @@ where
             }
         }
 
-        self.database.write(&batch, true).unwrap();
+        self.database.write(&batch, sync).unwrap();
     }
 
     fn delete(&mut self, key: Key) -> Option<Value> {
@@ -236,43 +238,66 @@ where
     /// Get database value asynchronously
     pub async fn get(&self, key: Key) -> Option<Value> {
         let inner = self.0.clone();
-        task::spawn_blocking(move || inner.get(key)).await.unwrap()
+        task::spawn_blocking(move || inner.get(key))
+            .instrument(self.io_span("get"))
+            .await
+            .unwrap()
     }
 
     pub async fn get_u8(&self, key: Vec<u8>) -> Option<Vec<u8>> {
         let mut inner = self.0.clone();
         task::spawn_blocking(move || inner.get_u8(&key))
+            .instrument(self.io_span("get"))
             .await
             .unwrap()
     }
 
     /// Set database value asynchronously
     pub async fn put(&mut self, key: Key, value: Value) {
+        fault_injection::check_db_write(self.path());
         let mut inner = self.0.clone();
         task::spawn_blocking(move || inner.put(key, value))
+            .instrument(self.io_span("put"))
             .await
             .unwrap()
     }
 
     pub async fn put_u8(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        fault_injection::check_db_write(self.path());
         let mut inner = self.0.clone();
         task::spawn_blocking(move || inner.put_u8(&key, &value))
+            .instrument(self.io_span("put"))
             .await
             .unwrap()
     }
 
     /// Write database values as a batch asynchronously
     pub async fn batch_write(&mut self, entries: WriteBatchAsync<Key, Value>) {
+        self.batch_write_with_sync(entries, true).await
+    }
+
+    /// Write database values as a batch asynchronously, without waiting for
+    /// the write to reach the disk. The batch is still applied atomically, but
+    /// may be lost if the machine crashes before the next synced write.
+    pub async fn batch_write_unsynced(&mut self, entries: WriteBatchAsync<Key, Value>) {
+        self.batch_write_with_sync(entries, false).await
+    }
+
+    async fn batch_write_with_sync(&mut self, entries: WriteBatchAsync<Key, Value>, sync: bool) {
+        fault_injection::check_db_write(self.path());
         let mut inner = self.0.clone();
-        task::spawn_blocking(move || inner.batch_write(entries))
+        task::spawn_blocking(move || inner.batch_write(entries, sync))
+            .instrument(self.io_span("batch_write"))
             .await
             .unwrap()
     }
 
     /// Delete database value asynchronously
     pub async fn delete(&mut self, key: Key) -> Option<Value> {
+        fault_injection::check_db_write(self.path());
         let mut inner = self.0.clone();
         task::spawn_blocking(move || inner.delete(key))
+            .instrument(self.io_span("delete"))
             .await
             .unwrap()
     }
@@ -280,7 +305,10 @@ where
     /// Delete database value asynchronously
     pub async fn flush(&mut self) {
         let mut inner = self.0.clone();
-        task::spawn_blocking(move || inner.flush()).await.unwrap()
+        task::spawn_blocking(move || inner.flush())
+            .instrument(self.io_span("flush"))
+            .await
+            .unwrap()
     }
 
     /// returns the directory path of the database files on disk.
@@ -288,6 +316,16 @@ where
     pub fn path(&self) -> &std::path::PathBuf {
         self.0.database.path()
     }
+
+    /// A span timing one database operation. Only enabled with `--profile-io`.
+    fn io_span(&self, operation: &'static str) -> tracing::Span {
+        tracing::trace_span!(
+            target: crate::PROFILE_IO_TARGET,
+            "db",
+            operation,
+            path = %self.path().display(),
+        )
+    }
 }
 
 impl<Key, Value> NeptuneLevelDb<Key, Value>
@@ -22,22 +22,7 @@ pub struct SimpleRustyStorage {
 impl StorageWriter for SimpleRustyStorage {
     #[inline]
     async fn persist(&mut self) {
-        let mut write_ops = WriteBatchAsync::new();
-
-        // note: we read all pending ops and perform mutations
-        // in a single atomic operation.
-        {
-            let mut pending_writes = self.schema.pending_writes.lock_guard_mut().await;
-            for op in pending_writes.write_ops.iter() {
-                match op.clone() {
-                    WriteOperation::Write(key, value) => write_ops.op_write(key, value),
-                    WriteOperation::Delete(key) => write_ops.op_delete(key),
-                }
-            }
-            pending_writes.write_ops.clear();
-            pending_writes.persist_count += 1;
-        }
-
+        let write_ops = self.take_pending_writes().await;
         self.db.batch_write(write_ops).await
     }
 }
@@ -64,4 +49,31 @@ impl SimpleRustyStorage {
         );
         Self { schema, db }
     }
+
+    /// Like [`StorageWriter::persist`], but without waiting for the write to
+    /// reach the disk.
+    pub async fn persist_unsynced(&mut self) {
+        let write_ops = self.take_pending_writes().await;
+        self.db.batch_write_unsynced(write_ops).await
+    }
+
+    async fn take_pending_writes(&mut self) -> WriteBatchAsync<RustyKey, RustyValue> {
+        let mut write_ops = WriteBatchAsync::new();
+
+        // note: we read all pending ops and perform mutations
+        // in a single atomic operation.
+        {
+            let mut pending_writes = self.schema.pending_writes.lock_guard_mut().await;
+            for op in pending_writes.write_ops.iter() {
+                match op.clone() {
+                    WriteOperation::Write(key, value) => write_ops.op_write(key, value),
+                    WriteOperation::Delete(key) => write_ops.op_delete(key),
+                }
+            }
+            pending_writes.write_ops.clear();
+            pending_writes.persist_count += 1;
+        }
+
+        write_ops
+    }
 }
@@ -4,6 +4,7 @@
 
 // danda: making all of these pub for now, so docs are generated.
 // later maybe we ought to split some stuff out into re-usable crate(s)...?
+pub mod api;
 pub mod config_models;
 pub mod connect_to_peers;
 pub mod database;
@@ -11,11 +12,18 @@ pub mod locks;
 pub mod macros;
 pub mod main_loop;
 pub mod mine_loop;
+pub mod mining;
 pub mod models;
 pub mod peer_loop;
+pub mod port_mapping;
 pub mod prelude;
+pub mod rpc_client;
 pub mod rpc_server;
+pub mod safe_mode;
+pub mod sd_notify;
 pub mod util_types;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 #[cfg(test)]
 pub mod tests;
@@ -24,6 +32,7 @@ use std::collections::HashMap;
 use std::env;
 use std::net::SocketAddr;
 
+use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use chrono::DateTime;
@@ -34,6 +43,7 @@ use config_models::cli_args;
 use futures::future;
 use futures::Future;
 use futures::StreamExt;
+use itertools::Itertools;
 use models::blockchain::block::Block;
 use models::blockchain::shared::Hash;
 use models::peer::PeerInfo;
@@ -51,29 +61,44 @@ use tokio::sync::watch;
 use tokio::time::Instant;
 use tracing::info;
 use tracing::trace;
+use tracing::warn;
 use triton_vm::prelude::BFieldElement;
 
 use crate::config_models::data_directory::DataDirectory;
+use crate::connect_to_peers::bind_peer_listener;
 use crate::connect_to_peers::call_peer_wrapper;
 use crate::locks::tokio as sync_tokio;
 use crate::locks::tokio::LockCallbackFn;
 use crate::locks::tokio::LockEvent;
 use crate::main_loop::MainLoopHandler;
+use crate::models::blockchain::block::validation_trace;
 use crate::models::channel::MainToMiner;
 use crate::models::channel::MainToPeerTask;
 use crate::models::channel::MinerToMain;
 use crate::models::channel::PeerTaskToMain;
 use crate::models::channel::RPCServerToMain;
+use crate::models::peer::anchor_peers;
 use crate::models::peer::HandshakeData;
+use crate::models::proof_abstractions::verifier_pool::VerifierPool;
 use crate::models::state::archival_state::ArchivalState;
 use crate::models::state::blockchain_state::BlockchainArchivalState;
 use crate::models::state::blockchain_state::BlockchainState;
+use crate::models::state::checkpoint_beacon::CheckpointBeacon;
+use crate::models::state::db_diagnostics::DatabaseInconsistency;
+use crate::models::state::db_diagnostics::DatabaseKind;
+use crate::models::state::db_diagnostics::STARTUP_CHECK_DEPTH;
+use crate::models::state::event_journal::EventJournal;
 use crate::models::state::light_state::LightState;
 use crate::models::state::mempool::Mempool;
 use crate::models::state::networking_state::NetworkingState;
+use crate::models::state::state_checkpoint::StateCheckpoint;
+use crate::models::state::tx_index::TxIndex;
+use crate::models::state::wallet::encrypted_secret::EncryptedWalletSecret;
 use crate::models::state::wallet::wallet_state::WalletState;
+use crate::models::state::wallet::watch_only::WatchOnlyAddresses;
 use crate::models::state::wallet::WalletSecret;
 use crate::models::state::GlobalStateLock;
+use crate::port_mapping::PortMapping;
 use crate::rpc_server::RPC;
 
 /// Magic string to ensure other program is Neptune Core
@@ -84,7 +109,68 @@ const MINER_CHANNEL_CAPACITY: usize = 3;
 const RPC_CHANNEL_CAPACITY: usize = 1000;
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Tracing target of the timing spans around database I/O, lock acquisition,
+/// and proving. These spans are only emitted with `--profile-io`.
+pub const PROFILE_IO_TARGET: &str = "profile_io";
+
+/// A node whose databases are open and whose peer, miner and RPC tasks have
+/// been spawned, but whose main loop has not been started yet.
+pub(crate) struct NodeComponents {
+    pub(crate) global_state_lock: GlobalStateLock,
+    pub(crate) rpc_server_to_main_tx: mpsc::Sender<RPCServerToMain>,
+    main_loop_handler: MainLoopHandler,
+    peer_task_to_main_rx: mpsc::Receiver<PeerTaskToMain>,
+    miner_to_main_rx: mpsc::Receiver<MinerToMain>,
+    rpc_server_to_main_rx: mpsc::Receiver<RPCServerToMain>,
+    task_join_handles: Vec<tokio::task::JoinHandle<()>>,
+}
+
+impl NodeComponents {
+    /// Run the main loop until shutdown.
+    pub(crate) async fn run_main_loop(mut self) -> Result<()> {
+        info!("Starting main loop");
+        sd_notify::ready(&self.main_loop_handler.service_status().await);
+        self.main_loop_handler
+            .run(
+                self.peer_task_to_main_rx,
+                self.miner_to_main_rx,
+                self.rpc_server_to_main_rx,
+                self.task_join_handles,
+            )
+            .await
+    }
+}
+
 pub async fn initialize(cli_args: cli_args::Args) -> Result<()> {
+    cli_args.install_network_params()?;
+    if cli_args.trace_validation {
+        validation_trace::enable();
+    }
+
+    if cli_args.verifier_workers > 0 {
+        let node_binary = std::env::current_exe()?;
+        VerifierPool::new(node_binary, cli_args.verifier_workers)?.install()?;
+        info!("Started {} verifier workers", cli_args.verifier_workers);
+    }
+
+    if cli_args.safe_mode {
+        return safe_mode::run(cli_args, vec![]).await;
+    }
+
+    match setup_node(cli_args.clone()).await {
+        Ok(node) => node.run_main_loop().await,
+        Err(err) => match err.downcast_ref::<DatabaseInconsistency>() {
+            Some(inconsistency) => safe_mode::run(cli_args, inconsistency.issues.clone()).await,
+            None => Err(err),
+        },
+    }
+}
+
+/// Open all databases and spawn all tasks of a node, except for the main loop.
+///
+/// Fails with [DatabaseInconsistency] if a database cannot be opened, or does
+/// not pass the startup consistency check.
+pub(crate) async fn setup_node(cli_args: cli_args::Args) -> Result<NodeComponents> {
     // Get data directory (wallet, block database), create one if none exists
     let data_dir = DataDirectory::get(cli_args.data_dir.clone(), cli_args.network)?;
     DataDirectory::create_dir_if_not_exists(&data_dir.root_dir_path()).await?;
@@ -93,44 +179,118 @@ pub async fn initialize(cli_args: cli_args::Args) -> Result<()> {
     // Get wallet object, create various wallet secret files
     let wallet_dir = data_dir.wallet_directory_path();
     DataDirectory::create_dir_if_not_exists(&wallet_dir).await?;
-    let (wallet_secret, _) =
-        WalletSecret::read_from_file_or_create(&data_dir.wallet_directory_path())?;
-    info!("Now getting wallet state. This may take a while if the database needs pruning.");
-    let wallet_state =
-        WalletState::new_from_wallet_secret(&data_dir, wallet_secret, &cli_args).await;
+    let wallet_state = match &cli_args.watch_only {
+        Some(path) => {
+            let addresses = WatchOnlyAddresses::read_from_file(path, cli_args.network)?;
+            info!(
+                "Running watch-only wallet for {} addresses",
+                addresses.addresses().len()
+            );
+            WalletState::new_watch_only(&data_dir, addresses, &cli_args).await
+        }
+        None if cli_args.encrypted_wallet => {
+            let encrypted = EncryptedWalletSecret::read_from_wallet_dir(&wallet_dir)?;
+            let passphrase = wallet_passphrase()?;
+            info!("Now getting wallet state. This may take a while if the database needs pruning.");
+            let wallet_state = WalletState::new_from_encrypted_wallet_secret(
+                &data_dir,
+                encrypted,
+                &passphrase,
+                &cli_args,
+            )
+            .await?;
+            info!("Wallet is locked; unlock it to send or mine");
+            wallet_state
+        }
+        None => {
+            if EncryptedWalletSecret::file_path(&wallet_dir).exists() {
+                bail!(
+                    "Found encrypted wallet in {}; start with --encrypted-wallet",
+                    wallet_dir.display()
+                );
+            }
+            let (wallet_secret, _) =
+                WalletSecret::read_from_file_or_create(&data_dir.wallet_directory_path())?;
+            info!("Now getting wallet state. This may take a while if the database needs pruning.");
+            WalletState::new_from_wallet_secret(&data_dir, wallet_secret, &cli_args).await
+        }
+    };
     info!("Got wallet state.");
 
     // Connect to or create databases for block index, peers, mutator set, block sync
-    let block_index_db = ArchivalState::initialize_block_index_database(&data_dir).await?;
+    let block_index_db = ArchivalState::initialize_block_index_database(&data_dir)
+        .await
+        .map_err(|err| DatabaseInconsistency::unopenable(DatabaseKind::Chain, err))?;
     info!("Got block index database");
 
-    let peer_databases = NetworkingState::initialize_peer_databases(&data_dir).await?;
+    let peer_databases = NetworkingState::initialize_peer_databases(&data_dir)
+        .await
+        .map_err(|err| DatabaseInconsistency::unopenable(DatabaseKind::Peers, err))?;
     info!("Got peer database");
 
-    let archival_mutator_set = ArchivalState::initialize_mutator_set(&data_dir).await?;
+    let archival_mutator_set = ArchivalState::initialize_mutator_set(&data_dir)
+        .await
+        .map_err(|err| DatabaseInconsistency::unopenable(DatabaseKind::Chain, err))?;
     info!("Got archival mutator set");
 
-    let archival_state = ArchivalState::new(
-        data_dir,
+    let mut archival_state = ArchivalState::new(
+        data_dir.clone(),
         block_index_db,
         archival_mutator_set,
         cli_args.network,
     )
     .await;
 
+    let issues = archival_state.check_consistency(STARTUP_CHECK_DEPTH).await;
+    if !issues.is_empty() {
+        return Err(DatabaseInconsistency { issues }.into());
+    }
+
+    if cli_args.build_tx_index {
+        info!("Updating transaction index");
+        let tx_index = TxIndex::open(&data_dir).await?;
+        archival_state.enable_tx_index(tx_index).await?;
+    }
+
+    // A checkpoint of the state from the previous run lets us skip reading the
+    // tip block and re-checking the wallet's recovery data.
+    let checkpoint = match StateCheckpoint::load(&data_dir.root_dir_path(), cli_args.network) {
+        Ok(checkpoint) => checkpoint,
+        Err(err) => {
+            warn!("Could not read state checkpoint: {err:#}");
+            None
+        }
+    };
+
     // Get latest block. Use hardcoded genesis block if nothing is in database.
-    let latest_block: Block = archival_state.get_tip().await;
+    let tip_digest = archival_state.get_tip_digest().await;
+    let latest_block: Block = match checkpoint
+        .as_ref()
+        .and_then(|checkpoint| checkpoint.tip_if_current(tip_digest))
+    {
+        Some(block) => {
+            info!("Resuming from state checkpoint");
+            block
+        }
+        None => archival_state.get_tip().await,
+    };
 
     // Bind socket to port on this machine, to handle incoming connections from peers
     let incoming_peer_listener = if let Some(incoming_peer_listener) = cli_args.own_listen_port() {
-        let ret = TcpListener::bind((cli_args.listen_addr, incoming_peer_listener))
-           .await
+        let listen_address = SocketAddr::new(cli_args.listen_addr, incoming_peer_listener);
+        let ret = bind_peer_listener(listen_address, cli_args.hole_punching)
            .with_context(|| format!("Failed to bind to local TCP port {}:{}. Is an instance of this program already running?", cli_args.listen_addr, incoming_peer_listener))?;
         info!("Now listening for incoming peer-connections");
-        ret
+        Some(ret)
+    } else if cli_args.no_listen {
+        info!("Running in outbound-only mode: not listening for peer-connections and not advertising a listen port");
+        if cli_args.peers.is_empty() {
+            warn!("No peers given with --peers; an outbound-only node can only find peers through those it connects to");
+        }
+        None
     } else {
         info!("Not accepting incoming peer-connections");
-        TcpListener::bind("127.0.0.1:0").await?
+        None
     };
 
     let peer_map: HashMap<SocketAddr, PeerInfo> = HashMap::new();
@@ -146,12 +306,39 @@ pub async fn initialize(cli_args: cli_args::Args) -> Result<()> {
     // Create handshake data which is used when connecting to outgoing peers specified in the
     // CLI arguments
     let syncing = false;
-    let networking_state = NetworkingState::new(
+    let mut networking_state = NetworkingState::new(
         peer_map,
         peer_databases,
         syncing,
         cli_args.tx_proving_capability,
+        CheckpointBeacon::new(cli_args.beacon_key.clone()),
     );
+    let port_mapping = match cli_args.own_listen_port().filter(|_| cli_args.port_mapping) {
+        Some(listen_port) => match PortMapping::create(listen_port).await {
+            Ok(port_mapping) => {
+                info!(
+                    "Router forwards {} to the peer port, mapped through {}",
+                    port_mapping.external_address, port_mapping.protocol
+                );
+                Some(port_mapping)
+            }
+            Err(err) => {
+                warn!("Could not map the peer port on the router: {err:#}");
+                None
+            }
+        },
+        None => None,
+    };
+    networking_state.external_address = port_mapping
+        .as_ref()
+        .map(|port_mapping| port_mapping.external_address);
+    networking_state.anchor_peers = match anchor_peers::take(&data_dir.root_dir_path()) {
+        Ok(anchor_peers) => anchor_peers,
+        Err(err) => {
+            warn!("Could not read anchor peers: {err:#}");
+            vec![]
+        }
+    };
 
     let light_state: LightState = LightState::from(latest_block.clone());
     let blockchain_archival_state = BlockchainArchivalState {
@@ -160,7 +347,7 @@ pub async fn initialize(cli_args: cli_args::Args) -> Result<()> {
     };
     let blockchain_state = BlockchainState::Archival(blockchain_archival_state);
     let mempool = Mempool::new(
-        cli_args.max_mempool_size,
+        cli_args.mempool_size_limit(),
         cli_args.max_mempool_num_tx,
         latest_block.hash(),
     );
@@ -172,6 +359,11 @@ pub async fn initialize(cli_args: cli_args::Args) -> Result<()> {
         mempool,
         false,
     );
+    let event_journal = EventJournal::open(&data_dir).await?;
+    global_state_lock
+        .lock_guard_mut()
+        .await
+        .set_event_journal(event_journal);
     let own_handshake_data: HandshakeData = global_state_lock
         .lock_guard()
         .await
@@ -184,16 +376,37 @@ pub async fn initialize(cli_args: cli_args::Args) -> Result<()> {
 
     // Check if we need to restore the wallet database, and if so, do it.
     info!("Checking if we need to restore UTXOs");
-    global_state_lock
-        .lock_guard_mut()
-        .await
-        .restore_monitored_utxos_from_recovery_data()
-        .await?;
+    {
+        let mut global_state = global_state_lock.lock_guard_mut().await;
+        let verified_len = match &checkpoint {
+            Some(checkpoint) => checkpoint.verified_recovery_data_len(&global_state).await,
+            None => 0,
+        };
+        global_state
+            .restore_monitored_utxos_from_recovery_data_since(verified_len)
+            .await?;
+        if let Some(checkpoint) = checkpoint {
+            global_state.net.checkpointed_mempool_txids = checkpoint.mempool_txids;
+        }
+    }
     info!("UTXO restoration check complete");
 
-    // Connect to peers, and provide each peer task with a thread-safe copy of the state
+    // Connect to peers, and provide each peer task with a thread-safe copy of the state.
+    // Anchor peers from the previous run are connected to first.
     let mut task_join_handles = vec![];
-    for peer_address in global_state_lock.cli().peers.clone() {
+    let cli_peers = global_state_lock.cli().peers.clone();
+    let anchor_peers = global_state_lock
+        .lock_guard()
+        .await
+        .net
+        .anchor_peers
+        .clone();
+    let outgoing_peers = anchor_peers
+        .into_iter()
+        .filter(|anchor| !cli_peers.contains(anchor))
+        .chain(cli_peers.iter().copied())
+        .collect_vec();
+    for peer_address in outgoing_peers {
         let peer_state_var = global_state_lock.clone(); // bump arc refcount
         let main_to_peer_broadcast_rx_clone: broadcast::Receiver<MainToPeerTask> =
             main_to_peer_broadcast_tx.subscribe();
@@ -216,6 +429,15 @@ pub async fn initialize(cli_args: cli_args::Args) -> Result<()> {
     }
     info!("Made outgoing connections to peers");
 
+    // Keep the router forwarding the peer port
+    if let Some(port_mapping) = port_mapping {
+        let port_mapping_state_lock = global_state_lock.clone(); // bump arc refcount
+        let port_mapping_join_handle = tokio::task::Builder::new()
+            .name("port_mapping")
+            .spawn(port_mapping.keep_alive(port_mapping_state_lock))?;
+        task_join_handles.push(port_mapping_join_handle);
+    }
+
     // Start mining tasks if requested
     let (miner_to_main_tx, miner_to_main_rx) = mpsc::channel::<MinerToMain>(MINER_CHANNEL_CAPACITY);
     let (main_to_miner_tx, main_to_miner_rx) = watch::channel::<MainToMiner>(MainToMiner::Empty);
@@ -249,6 +471,7 @@ pub async fn initialize(cli_args: cli_args::Args) -> Result<()> {
     rpc_listener.config_mut().max_frame_length(usize::MAX);
 
     let rpc_state_lock = global_state_lock.clone();
+    let rpc_server_to_main_tx_for_node = rpc_server_to_main_tx.clone();
 
     async fn spawn(fut: impl Future<Output = ()> + Send + 'static) {
         tokio::spawn(fut);
@@ -280,23 +503,66 @@ pub async fn initialize(cli_args: cli_args::Args) -> Result<()> {
     task_join_handles.push(rpc_join_handle);
     info!("Started RPC server");
 
+    if let Some(jsonrpc_port) = global_state_lock.cli().jsonrpc_port {
+        let jsonrpc_listener = TcpListener::bind(format!("127.0.0.1:{jsonrpc_port}")).await?;
+        let jsonrpc_join_handle = tokio::spawn(rpc_server::jsonrpc::run(
+            jsonrpc_listener,
+            global_state_lock.clone(),
+            rpc_server_to_main_tx_for_node.clone(),
+        ));
+        task_join_handles.push(jsonrpc_join_handle);
+        info!("Started JSON-RPC server on port {jsonrpc_port}");
+    }
+
+    if let Some(work_server_port) = global_state_lock.cli().work_server_port {
+        let work_server_listener =
+            TcpListener::bind((global_state_lock.cli().listen_addr, work_server_port)).await?;
+        let work_server_join_handle =
+            tokio::task::Builder::new()
+                .name("work_server")
+                .spawn(mining::work_server::run(
+                    work_server_listener,
+                    global_state_lock.clone(),
+                    rpc_server_to_main_tx_for_node.clone(),
+                ))?;
+        task_join_handles.push(work_server_join_handle);
+        info!("Started mining work server on port {work_server_port}");
+    }
+
     // Handle incoming connections, messages from peer tasks, and messages from the mining task
-    info!("Starting main loop");
-    let mut main_loop_handler = MainLoopHandler::new(
+    let main_loop_handler = MainLoopHandler::new(
         incoming_peer_listener,
-        global_state_lock,
+        global_state_lock.clone(),
         main_to_peer_broadcast_tx,
         peer_task_to_main_tx,
         main_to_miner_tx,
     );
-    main_loop_handler
-        .run(
-            peer_task_to_main_rx,
-            miner_to_main_rx,
-            rpc_server_to_main_rx,
-            task_join_handles,
-        )
-        .await
+
+    Ok(NodeComponents {
+        global_state_lock,
+        rpc_server_to_main_tx: rpc_server_to_main_tx_for_node,
+        main_loop_handler,
+        peer_task_to_main_rx,
+        miner_to_main_rx,
+        rpc_server_to_main_rx,
+        task_join_handles,
+    })
+}
+
+/// The passphrase of an encrypted wallet, from the environment variable
+/// `NEPTUNE_WALLET_PASSPHRASE` if set, otherwise from standard input.
+fn wallet_passphrase() -> Result<String> {
+    if let Ok(passphrase) = std::env::var("NEPTUNE_WALLET_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+
+    print!("Wallet passphrase: ");
+    std::io::Write::flush(&mut std::io::stdout())?;
+    let mut passphrase = String::new();
+    std::io::stdin()
+        .read_line(&mut passphrase)
+        .context("could not read wallet passphrase")?;
+    Ok(passphrase.trim_end_matches(['\r', '\n']).to_string())
 }
 
 /// Time a fn call.  Duration is returned as a float in seconds.
@@ -5,7 +5,9 @@ use std::sync::Arc;
 use futures::future::BoxFuture;
 use tokio::sync::Mutex;
 use tokio::sync::MutexGuard;
+use tracing::Instrument;
 
+use super::shared::acquire_span;
 use super::LockAcquisition;
 use super::LockCallbackFn;
 use super::LockCallbackInfo;
@@ -217,7 +219,14 @@ impl<T> AtomicMutex<T> {
     /// ```
     pub async fn lock_guard(&self) -> AtomicMutexGuard<T> {
         self.try_acquire_read_cb();
-        let guard = self.inner.lock().await;
+        let guard = self
+            .inner
+            .lock()
+            .instrument(acquire_span(
+                &self.lock_callback_info,
+                LockAcquisition::Read,
+            ))
+            .await;
         AtomicMutexGuard::new(guard, &self.lock_callback_info, LockAcquisition::Read)
     }
 
@@ -248,7 +257,14 @@ impl<T> AtomicMutex<T> {
     /// ```
     pub async fn lock_guard_mut(&mut self) -> AtomicMutexGuard<T> {
         self.try_acquire_write_cb();
-        let guard = self.inner.lock().await;
+        let guard = self
+            .inner
+            .lock()
+            .instrument(acquire_span(
+                &self.lock_callback_info,
+                LockAcquisition::Write,
+            ))
+            .await;
         AtomicMutexGuard::new(guard, &self.lock_callback_info, LockAcquisition::Write)
     }
 
@@ -271,7 +287,14 @@ impl<T> AtomicMutex<T> {
         F: FnOnce(&T) -> R,
     {
         self.try_acquire_read_cb();
-        let inner_guard = self.inner.lock().await;
+        let inner_guard = self
+            .inner
+            .lock()
+            .instrument(acquire_span(
+                &self.lock_callback_info,
+                LockAcquisition::Read,
+            ))
+            .await;
         let guard =
             AtomicMutexGuard::new(inner_guard, &self.lock_callback_info, LockAcquisition::Read);
         f(&guard)
@@ -296,7 +319,14 @@ impl<T> AtomicMutex<T> {
         F: FnOnce(&mut T) -> R,
     {
         self.try_acquire_write_cb();
-        let inner_guard = self.inner.lock().await;
+        let inner_guard = self
+            .inner
+            .lock()
+            .instrument(acquire_span(
+                &self.lock_callback_info,
+                LockAcquisition::Write,
+            ))
+            .await;
         let mut guard = AtomicMutexGuard::new(
             inner_guard,
             &self.lock_callback_info,
@@ -326,7 +356,14 @@ impl<T> AtomicMutex<T> {
     // design background: https://stackoverflow.com/a/77657788/10087197
     pub async fn lock_async<R>(&self, f: impl FnOnce(&T) -> BoxFuture<'_, R>) -> R {
         self.try_acquire_read_cb();
-        let inner_guard = self.inner.lock().await;
+        let inner_guard = self
+            .inner
+            .lock()
+            .instrument(acquire_span(
+                &self.lock_callback_info,
+                LockAcquisition::Read,
+            ))
+            .await;
         let guard =
             AtomicMutexGuard::new(inner_guard, &self.lock_callback_info, LockAcquisition::Read);
         f(&guard).await
@@ -353,7 +390,14 @@ impl<T> AtomicMutex<T> {
     // design background: https://stackoverflow.com/a/77657788/10087197
     pub async fn lock_mut_async<R>(&mut self, f: impl FnOnce(&mut T) -> BoxFuture<'_, R>) -> R {
         self.try_acquire_write_cb();
-        let inner_guard = self.inner.lock().await;
+        let inner_guard = self
+            .inner
+            .lock()
+            .instrument(acquire_span(
+                &self.lock_callback_info,
+                LockAcquisition::Write,
+            ))
+            .await;
         let mut guard = AtomicMutexGuard::new(
             inner_guard,
             &self.lock_callback_info,
@@ -7,7 +7,9 @@ use tokio::sync::RwLock;
 use tokio::sync::RwLockReadGuard;
 use tokio::sync::RwLockWriteGuard;
 use tokio::sync::TryLockError;
+use tracing::Instrument;
 
+use super::shared::acquire_span;
 use super::LockAcquisition;
 use super::LockCallbackFn;
 use super::LockCallbackInfo;
@@ -218,7 +220,14 @@ impl<T> AtomicRw<T> {
     /// ```
     pub async fn lock_guard(&self) -> AtomicRwReadGuard<T> {
         self.try_acquire_read_cb();
-        let guard = self.inner.read().await;
+        let guard = self
+            .inner
+            .read()
+            .instrument(acquire_span(
+                &self.lock_callback_info,
+                LockAcquisition::Read,
+            ))
+            .await;
         AtomicRwReadGuard::new(guard, &self.lock_callback_info)
     }
 
@@ -237,7 +246,14 @@ impl<T> AtomicRw<T> {
     /// ```
     pub async fn lock_guard_mut(&mut self) -> AtomicRwWriteGuard<T> {
         self.try_acquire_write_cb();
-        let guard = self.inner.write().await;
+        let guard = self
+            .inner
+            .write()
+            .instrument(acquire_span(
+                &self.lock_callback_info,
+                LockAcquisition::Write,
+            ))
+            .await;
         AtomicRwWriteGuard::new(guard, &self.lock_callback_info)
     }
 
@@ -269,7 +285,14 @@ impl<T> AtomicRw<T> {
         F: FnOnce(&T) -> R,
     {
         self.try_acquire_read_cb();
-        let inner_guard = self.inner.read().await;
+        let inner_guard = self
+            .inner
+            .read()
+            .instrument(acquire_span(
+                &self.lock_callback_info,
+                LockAcquisition::Read,
+            ))
+            .await;
         let guard = AtomicRwReadGuard::new(inner_guard, &self.lock_callback_info);
         f(&guard)
     }
@@ -293,7 +316,14 @@ impl<T> AtomicRw<T> {
         F: FnOnce(&mut T) -> R,
     {
         self.try_acquire_write_cb();
-        let inner_guard = self.inner.write().await;
+        let inner_guard = self
+            .inner
+            .write()
+            .instrument(acquire_span(
+                &self.lock_callback_info,
+                LockAcquisition::Write,
+            ))
+            .await;
         let mut guard = AtomicRwWriteGuard::new(inner_guard, &self.lock_callback_info);
         f(&mut guard)
     }
@@ -319,7 +349,14 @@ impl<T> AtomicRw<T> {
     // design background: https://stackoverflow.com/a/77657788/10087197
     pub async fn lock_async<R>(&self, f: impl FnOnce(&T) -> BoxFuture<'_, R>) -> R {
         self.try_acquire_read_cb();
-        let inner_guard = self.inner.read().await;
+        let inner_guard = self
+            .inner
+            .read()
+            .instrument(acquire_span(
+                &self.lock_callback_info,
+                LockAcquisition::Read,
+            ))
+            .await;
         let guard = AtomicRwReadGuard::new(inner_guard, &self.lock_callback_info);
         f(&guard).await
     }
@@ -345,7 +382,14 @@ impl<T> AtomicRw<T> {
     // design background: https://stackoverflow.com/a/77657788/10087197
     pub async fn lock_mut_async<R>(&mut self, f: impl FnOnce(&mut T) -> BoxFuture<'_, R>) -> R {
         self.try_acquire_write_cb();
-        let inner_guard = self.inner.write().await;
+        let inner_guard = self
+            .inner
+            .write()
+            .instrument(acquire_span(
+                &self.lock_callback_info,
+                LockAcquisition::Write,
+            ))
+            .await;
         let mut guard = AtomicRwWriteGuard::new(inner_guard, &self.lock_callback_info);
         f(&mut guard).await
     }
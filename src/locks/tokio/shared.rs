@@ -105,6 +105,19 @@ pub enum LockEvent<'a> {
     },
 }
 
+/// A span covering the wait for a lock, for diagnosing lock contention.
+pub(super) fn acquire_span(
+    lock_callback_info: &LockCallbackInfo,
+    acquisition: LockAcquisition,
+) -> tracing::Span {
+    tracing::trace_span!(
+        target: crate::PROFILE_IO_TARGET,
+        "lock_acquire",
+        lock = lock_callback_info.lock_info_owned.name.as_deref().unwrap_or("?"),
+        %acquisition,
+    )
+}
+
 /// A callback fn for receiving [LockEvent] event
 /// each time a lock is acquired or released.
 pub type LockCallbackFn = fn(lock_event: LockEvent);
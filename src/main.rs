@@ -1,6 +1,9 @@
 use anyhow::Result;
 use clap::Parser;
 use neptune_core::config_models::cli_args;
+use neptune_core::models::proof_abstractions::verifier_pool;
+use neptune_core::PROFILE_IO_TARGET;
+use tracing_subscriber::fmt::format::FmtSpan;
 use tracing_subscriber::EnvFilter;
 use tracing_subscriber::FmtSubscriber;
 
@@ -9,6 +12,11 @@ pub async fn main() -> Result<()> {
     // Fetch the CLI arguments
     let args: cli_args::Args = cli_args::Args::parse();
 
+    // Workers talk to the node over stdout, so they must not log to it.
+    if args.verifier_worker {
+        return verifier_pool::run_worker();
+    }
+
     if args.tokio_console {
         console_subscriber::init();
     } else {
@@ -18,11 +26,21 @@ pub async fn main() -> Result<()> {
         // Accepted `RUST_LOG` values are `trace`, `debug`, `info`, `warn`,
         // and `error`.
 
-        let info_env_filter =
+        let mut info_env_filter =
             EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+        // Timing spans are reported when they close.
+        let span_events = if args.profile_io {
+            info_env_filter =
+                info_env_filter.add_directive(format!("{PROFILE_IO_TARGET}=trace").parse()?);
+            FmtSpan::CLOSE
+        } else {
+            FmtSpan::NONE
+        };
         let subscriber = FmtSubscriber::builder()
             .with_timer(tracing_subscriber::fmt::time::UtcTime::rfc_3339())
             .with_env_filter(info_env_filter)
+            .with_span_events(span_events)
             .with_thread_ids(true)
             .finish();
         tracing::subscriber::set_global_default(subscriber)
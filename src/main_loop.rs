@@ -1,19 +1,32 @@
+mod coinjoin_merger;
+mod difficulty_alarm;
 pub mod proof_upgrader;
+mod rendezvous;
+mod tip_watchdog;
+mod tx_diffusion;
 
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use std::thread::sleep;
 use std::time::Duration;
 use std::time::SystemTime;
 
 use anyhow::Result;
+use difficulty_alarm::DifficultyAlarm;
+use difficulty_alarm::DIFFICULTY_ALARM_WINDOW;
 use itertools::Itertools;
 use proof_upgrader::get_upgrade_task_from_mempool;
 use proof_upgrader::UpgradeJob;
 use rand::prelude::IteratorRandom;
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
+use rendezvous::RendezvousState;
+use tasm_lib::triton_vm::prelude::Digest;
+use tip_watchdog::TipCheck;
+use tip_watchdog::TipWatchdog;
 use tokio::net::TcpListener;
+use tokio::net::TcpStream;
 use tokio::select;
 use tokio::signal;
 use tokio::sync::broadcast;
@@ -26,26 +39,49 @@ use tracing::error;
 use tracing::info;
 use tracing::trace;
 use tracing::warn;
+use tx_diffusion::relay_own_transaction;
 
+use crate::config_models::data_directory::DataDirectory;
+use crate::config_models::network::Network;
 use crate::connect_to_peers::answer_peer_wrapper;
 use crate::connect_to_peers::call_peer_wrapper;
+use crate::connect_to_peers::hole_punch_wrapper;
 use crate::models::blockchain::block::block_header::BlockHeader;
 use crate::models::blockchain::block::block_height::BlockHeight;
 use crate::models::blockchain::block::difficulty_control::ProofOfWork;
+use crate::models::blockchain::block::fork_choice;
+use crate::models::blockchain::block::Block;
+use crate::models::blockchain::block::BlockProof;
+use crate::models::blockchain::transaction::Transaction;
 use crate::models::blockchain::transaction::TransactionProof;
 use crate::models::channel::MainToMiner;
 use crate::models::channel::MainToPeerTask;
 use crate::models::channel::MainToPeerTaskBatchBlockRequest;
 use crate::models::channel::MinerToMain;
+use crate::models::channel::NewBlockFound;
 use crate::models::channel::PeerTaskToMain;
 use crate::models::channel::RPCServerToMain;
+use crate::models::peer::anchor_peers;
+use crate::models::peer::network_group::NetworkGroup;
+use crate::models::peer::own_block::OwnBlockAnnouncement;
 use crate::models::peer::transaction_notification::TransactionNotification;
+use crate::models::peer::transfer_transaction::TransferTransaction;
 use crate::models::peer::HandshakeData;
 use crate::models::peer::PeerInfo;
 use crate::models::peer::PeerSynchronizationState;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::models::state::checkpoint_beacon::BeaconAssessment;
+use crate::models::state::coinjoin::CoinJoinContribution;
+use crate::models::state::coinjoin::CoinJoinError;
+use crate::models::state::coinjoin::SessionOutcome;
+use crate::models::state::mining_log::MinedBlockReport;
+use crate::models::state::node_event::NodeEvent;
+use crate::models::state::state_checkpoint::StateCheckpoint;
 use crate::models::state::tx_proving_capability::TxProvingCapability;
+use crate::models::state::wallet::wallet_fsync_policy::WalletFsyncPolicy;
 use crate::models::state::GlobalState;
 use crate::models::state::GlobalStateLock;
+use crate::sd_notify;
 
 const PEER_DISCOVERY_INTERVAL_IN_SECONDS: u64 = 120;
 const SYNC_REQUEST_INTERVAL_IN_SECONDS: u64 = 3;
@@ -61,10 +97,22 @@ const TRANSACTION_UPGRADE_CHECK_INTERVAL_IN_SECONDS: u64 = 60; // 1 minute
 const SANCTION_PEER_TIMEOUT_FACTOR: u64 = 40;
 const POTENTIAL_PEER_MAX_COUNT_AS_A_FACTOR_OF_MAX_PEERS: usize = 20;
 const STANDARD_BATCH_BLOCK_LOOKBEHIND_SIZE: usize = 100;
+const PEER_ROTATION_INTERVAL_IN_SECONDS: u64 = 30 * 60; // 30 mins
+const STATE_CHECKPOINT_INTERVAL_IN_SECONDS: u64 = 5 * 60; // 5 mins
+const TIP_WATCHDOG_INTERVAL_IN_SECONDS: u64 = 5 * 60; // 5 mins
+const COINJOIN_POLL_INTERVAL_IN_SECONDS: u64 = 10;
+
+/// The parent of an orphan block is requested from all peers at most once
+/// within this interval.
+const ORPHAN_PARENT_REQUEST_INTERVAL: Duration = Duration::from_secs(30);
+
+/// One in this many outbound peers is disconnected on every peer rotation.
+const PEER_ROTATION_FRACTION_DENOMINATOR: usize = 4;
 
 /// MainLoop is the immutable part of the input for the main loop function
 pub struct MainLoopHandler {
-    incoming_peer_listener: TcpListener,
+    /// `None` if this node does not accept incoming peer connections.
+    incoming_peer_listener: Option<TcpListener>,
     global_state_lock: GlobalStateLock,
     main_to_peer_broadcast_tx: broadcast::Sender<MainToPeerTask>,
     peer_task_to_main_tx: mpsc::Sender<PeerTaskToMain>,
@@ -78,8 +126,15 @@ pub struct MainLoopHandler {
 struct MutableMainLoopState {
     sync_state: SyncState,
     potential_peers: PotentialPeersState,
+    rendezvous: RendezvousState,
     task_handles: Vec<JoinHandle<()>>,
     proof_upgrader_task: Option<JoinHandle<()>>,
+    coinjoin_merger_task: Option<JoinHandle<()>>,
+    tip_watchdog: TipWatchdog,
+    difficulty_alarm: DifficultyAlarm,
+
+    /// When the parent of an orphan block was last requested from all peers
+    orphan_parent_requests: HashMap<Digest, SystemTime>,
 }
 
 impl MutableMainLoopState {
@@ -87,8 +142,13 @@ impl MutableMainLoopState {
         Self {
             sync_state: SyncState::default(),
             potential_peers: PotentialPeersState::default(),
+            rendezvous: RendezvousState::default(),
             task_handles,
             proof_upgrader_task: None,
+            coinjoin_merger_task: None,
+            tip_watchdog: TipWatchdog::default(),
+            difficulty_alarm: DifficultyAlarm::default(),
+            orphan_parent_requests: HashMap::new(),
         }
     }
 }
@@ -116,14 +176,50 @@ impl SyncState {
         self.last_sync_request = Some((now, requested_block_height, peer));
     }
 
-    /// Return a list of peers that have reported to be in possession of blocks
-    /// with a PoW above a threshold.
-    fn get_potential_peers_for_sync_request(&self, threshold_pow: ProofOfWork) -> Vec<SocketAddr> {
-        self.peer_sync_states
+    /// Return a list of archival peers that have reported to be in possession
+    /// of blocks with a PoW above a threshold. If the height of a trusted
+    /// checkpoint is given, only peers claiming to reach it are returned,
+    /// unless there are none.
+    fn get_potential_peers_for_sync_request(
+        &self,
+        threshold_pow: ProofOfWork,
+        checkpoint_height: Option<BlockHeight>,
+        is_archival: impl Fn(&SocketAddr) -> bool,
+    ) -> Vec<SocketAddr> {
+        let candidates = self
+            .peer_sync_states
+            .iter()
+            .filter(|(sa, sync_state)| {
+                sync_state.claimed_max_pow > threshold_pow && is_archival(sa)
+            })
+            .collect_vec();
+        let reaching_checkpoint = candidates
             .iter()
-            .filter(|(_sa, sync_state)| sync_state.claimed_max_pow > threshold_pow)
-            .map(|(sa, _)| *sa)
-            .collect()
+            .filter(|(_sa, sync_state)| {
+                checkpoint_height.is_some_and(|height| sync_state.claimed_max_height >= height)
+            })
+            .map(|(sa, _)| **sa)
+            .collect_vec();
+        if !reaching_checkpoint.is_empty() {
+            return reaching_checkpoint;
+        }
+
+        candidates.into_iter().map(|(sa, _)| *sa).collect()
+    }
+
+    /// The highest tip height claimed by a peer.
+    fn max_claimed_height(&self) -> Option<BlockHeight> {
+        self.peer_sync_states
+            .values()
+            .map(|sync_state| sync_state.claimed_max_height)
+            .max()
+    }
+
+    /// Return true if some peer claims to have blocks up to the given height.
+    fn some_peer_reaches(&self, height: BlockHeight) -> bool {
+        self.peer_sync_states
+            .values()
+            .any(|sync_state| sync_state.claimed_max_height >= height)
     }
 
     /// Determine if a peer should be sanctioned for failing to respond to a synchronization
@@ -236,7 +332,8 @@ impl PotentialPeersState {
     }
 
     /// Return a random peer from the potential peer list that we aren't connected to
-    /// and that isn't our own address. Returns (socket address, peer distance)
+    /// and that isn't our own address, nor in the network group of an outbound
+    /// peer. Returns (socket address, peer distance)
     fn get_distant_candidate(
         &self,
         connected_clients: &[PeerInfo],
@@ -251,6 +348,14 @@ impl PotentialPeersState {
             .filter_map(|x| x.listen_address())
             .collect();
 
+        // Spread outbound connections across network groups
+        let outbound_network_groups: Vec<NetworkGroup> = connected_clients
+            .iter()
+            .filter(|x| !x.inbound)
+            .map(|x| NetworkGroup::of(x.connected_address.ip()))
+            .filter(|group| group.is_limited())
+            .collect();
+
         // Find the appropriate candidates
         let not_connected_peers = self
             .potential_peers
@@ -262,6 +367,9 @@ impl PotentialPeersState {
             // Prevent connecting to peer we already are connected to
             .filter(|potential_peer| !peers_instance_ids.contains(&potential_peer.1.instance_id))
             .filter(|potential_peer| !peers_listen_addresses.contains(potential_peer.0))
+            .filter(|potential_peer| {
+                !outbound_network_groups.contains(&NetworkGroup::of(potential_peer.0.ip()))
+            })
             .collect::<Vec<_>>();
 
         // Get the candidate list with the highest distance
@@ -310,9 +418,20 @@ fn stay_in_sync_mode(
     }
 }
 
+/// Accept the next incoming peer connection, or never resolve if this node
+/// does not listen for peer connections.
+async fn accept_incoming(
+    listener: Option<&TcpListener>,
+) -> std::io::Result<(TcpStream, SocketAddr)> {
+    match listener {
+        Some(listener) => listener.accept().await,
+        None => std::future::pending().await,
+    }
+}
+
 impl MainLoopHandler {
     pub(crate) fn new(
-        incoming_peer_listener: TcpListener,
+        incoming_peer_listener: Option<TcpListener>,
         global_state_lock: GlobalStateLock,
         main_to_peer_broadcast_tx: broadcast::Sender<MainToPeerTask>,
         peer_task_to_main_tx: mpsc::Sender<PeerTaskToMain>,
@@ -352,45 +471,141 @@ impl MainLoopHandler {
     async fn handle_miner_task_message(&mut self, msg: MinerToMain) -> Result<()> {
         match msg {
             MinerToMain::NewBlockFound(new_block_info) => {
-                let new_block = new_block_info.block;
-
-                info!("Miner found new block: {}", new_block.kernel.header.height);
-
-                // Store block in database
-                // This block spans global state write lock for updating.
-                let prover_lock = self.global_state_lock.proving_lock.clone();
-                let mut global_state_mut = self.global_state_lock.lock_guard_mut().await;
-
-                if !global_state_mut.incoming_block_is_more_canonical(&new_block) {
-                    warn!("Got new block from miner task that was not child of tip. Discarding.");
+                info!(
+                    "Miner found new block: {}",
+                    new_block_info.block.kernel.header.height
+                );
+                let announced = self.announce_own_block(&new_block_info.block).await;
+                let Some(new_block) = self.store_self_mined_block(new_block_info).await? else {
                     return Ok(());
-                }
-
-                global_state_mut
-                    .set_new_self_mined_tip(
-                        new_block.as_ref().clone(),
-                        new_block_info.coinbase_utxo_info.as_ref().clone(),
-                        &prover_lock,
-                    )
-                    .await?;
-                drop(global_state_mut);
+                };
 
                 // Inform miner that mempool has been updated and that it is safe
                 // to mine the next block
                 self.main_to_miner_tx
                     .send(MainToMiner::ReadyToMineNextBlock)?;
 
-                // Share block with peers
-                self.main_to_peer_broadcast_tx
-                    .send(MainToPeerTask::Block(new_block.clone()))
-                    .expect(
-                        "Peer handler broadcast channel prematurely closed. This should never happen.",
-                    );
+                // Share block with peers, unless already announced
+                if !announced {
+                    self.main_to_peer_broadcast_tx
+                        .send(MainToPeerTask::Block(new_block))
+                        .expect(
+                            "Peer handler broadcast channel prematurely closed. This should never happen.",
+                        );
+                }
             }
         }
         Ok(())
     }
 
+    /// Announce a block found by this node to all peers before it is stored,
+    /// such that they can fetch it in the meantime. Returns `false` if the
+    /// block was not announced, in which case it is to be shared like any
+    /// other block once stored.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for write
+    async fn announce_own_block(&mut self, block: &Block) -> bool {
+        let announcement = match OwnBlockAnnouncement::new(block) {
+            Ok(announcement) => Arc::new(announcement),
+            Err(err) => {
+                warn!("Cannot announce own block ahead of storing it: {err}");
+                return false;
+            }
+        };
+
+        {
+            let mut global_state_mut = self.global_state_lock.lock_guard_mut().await;
+            if !global_state_mut.incoming_block_is_more_canonical(block) {
+                return false;
+            }
+            global_state_mut.net.own_block_announcement = Some(announcement.clone());
+        }
+
+        self.main_to_peer_broadcast_tx
+            .send(MainToPeerTask::OwnBlock(announcement))
+            .expect("Peer handler broadcast channel prematurely closed. This should never happen.");
+        true
+    }
+
+    /// Store a block mined by this node, by the miner task or by an external
+    /// miner, as the new tip, and log it. Returns the block, or `None` if it
+    /// no longer builds on the tip.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for write
+    async fn store_self_mined_block(
+        &mut self,
+        new_block_info: NewBlockFound,
+    ) -> Result<Option<Box<Block>>> {
+        let new_block = new_block_info.block;
+
+        // Store block in database
+        // This block spans global state write lock for updating.
+        let prover_lock = self.global_state_lock.proving_lock.clone();
+        let mut global_state_mut = self.global_state_lock.lock_guard_mut().await;
+
+        if !global_state_mut.incoming_block_is_more_canonical(&new_block) {
+            warn!("Got new self-mined block that was not child of tip. Discarding.");
+            return Ok(None);
+        }
+
+        global_state_mut
+            .set_new_self_mined_tip(
+                new_block.as_ref().clone(),
+                new_block_info.coinbase_utxo_info.as_ref().clone(),
+                &prover_lock,
+            )
+            .await?;
+        let data_dir = DataDirectory::get(
+            global_state_mut.cli().data_dir.clone(),
+            global_state_mut.cli().network,
+        )?;
+        drop(global_state_mut);
+
+        let report = MinedBlockReport::new(&new_block, new_block_info.template);
+        if let Err(err) = report.append_to_log(&data_dir.root_dir_path()) {
+            warn!("Could not log mined block: {err:#}");
+        }
+
+        Ok(Some(new_block))
+    }
+
+    /// Store a block generated on RegTest, and let the miner and, if the block
+    /// has a proof, peers know about it. Returns whether it became the tip.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for write
+    async fn store_generated_block(&mut self, block: Block) -> Result<bool> {
+        let prover_lock = self.global_state_lock.proving_lock.clone();
+        let mut global_state_mut = self.global_state_lock.lock_guard_mut().await;
+        if !global_state_mut.incoming_block_is_more_canonical(&block) {
+            warn!("Got generated block that was not child of tip. Discarding.");
+            return Ok(false);
+        }
+        global_state_mut
+            .set_new_tip(block.clone(), &prover_lock)
+            .await?;
+        drop(global_state_mut);
+
+        let block = Box::new(block);
+        if self.global_state_lock.cli().mine {
+            self.main_to_miner_tx
+                .send(MainToMiner::NewBlock(block.clone()))?;
+        }
+
+        // Peers would punish this node for a block without proof.
+        if matches!(block.proof, BlockProof::SingleProof(_)) {
+            self.main_to_peer_broadcast_tx
+                .send(MainToPeerTask::Block(block))
+                .expect(
+                    "Peer handler broadcast channel prematurely closed. This should never happen.",
+                );
+        }
+
+        Ok(true)
+    }
+
     /// Locking:
     ///   * acquires `global_state_lock` for write
     async fn handle_peer_task_message(
@@ -402,7 +617,7 @@ impl MainLoopHandler {
         match msg {
             PeerTaskToMain::NewBlocks(blocks) => {
                 let last_block = blocks.last().unwrap().to_owned();
-                {
+                let resurrected_transactions = {
                     // The peer tasks also check this condition, if block is more canonical than current
                     // tip, but we have to check it again since the block update might have already been applied
                     // through a message from another peer (or from own miner).
@@ -431,33 +646,65 @@ impl MainLoopHandler {
                         );
                         if !stay_in_sync_mode {
                             info!("Exiting sync mode");
-                            global_state_mut.net.syncing = false;
+                            global_state_mut.net.stop_syncing();
                             self.main_to_miner_tx.send(MainToMiner::StopSyncing)?;
                         }
                     }
 
-                    for new_block in blocks {
+                    // Blocks not building on the current tip constitute a
+                    // reorganization, which may un-confirm own transactions.
+                    let old_tip_height = global_state_mut.chain.light_state().header().height;
+                    let is_reorganization = blocks[0].header().prev_block_digest
+                        != global_state_mut.chain.light_state().hash();
+                    let first_replaced_height = blocks[0].header().height;
+                    if is_reorganization
+                        && fork_choice::is_final(first_replaced_height, old_tip_height)
+                    {
+                        warn!(
+                            "Reorganization abandons blocks from height {first_replaced_height} to {old_tip_height}, beyond the finality depth of {}",
+                            fork_choice::FINALITY_DEPTH
+                        );
+                    }
+
+                    for new_block in &blocks {
                         debug!(
                             "Storing block {} in database. Height: {}, Mined: {}",
                             new_block.hash(),
                             new_block.kernel.header.height,
                             new_block.kernel.header.timestamp.standard_format()
                         );
+                    }
 
-                        // Potential race condition here.
-                        // What if last block is new and canonical, but first
-                        // block is already known then we'll store the same block
-                        // twice. That should be OK though, as the appropriate
-                        // database entries are simply overwritten with the new
-                        // block info. See the
-                        // [GlobalState::test::setting_same_tip_twice_is_allowed]
-                        // test for a test of this phenomenon.
-
-                        global_state_mut
-                            .set_new_tip(new_block, &prover_lock)
-                            .await?;
+                    // Potential race condition here.
+                    // What if last block is new and canonical, but first
+                    // block is already known then we'll store the same block
+                    // twice. That should be OK though, as the appropriate
+                    // database entries are simply overwritten with the new
+                    // block info. See the
+                    // [GlobalState::test::setting_same_tip_twice_is_allowed]
+                    // test for a test of this phenomenon.
+
+                    // The wallet scans the whole batch for announced UTXOs in
+                    // parallel before the blocks are applied in order.
+                    global_state_mut.set_new_tips(blocks, &prover_lock).await?;
+
+                    if let Some(sync_progress) = global_state_mut.net.sync_progress.as_mut() {
+                        sync_progress.record_applied(last_block.kernel.header.height);
                     }
-                }
+
+                    let has_unconfirmed_spends = !global_state_mut
+                        .wallet_state
+                        .own_transactions
+                        .unconfirmed_spends()
+                        .is_empty();
+                    if (is_reorganization || has_unconfirmed_spends)
+                        && !global_state_mut.net.syncing
+                    {
+                        global_state_mut.resurrect_own_transactions().await?
+                    } else {
+                        vec![]
+                    }
+                };
 
                 // Inform miner to work on a new block
                 if self.global_state_lock.cli().mine {
@@ -469,6 +716,11 @@ impl MainLoopHandler {
                 self.main_to_peer_broadcast_tx
                     .send(MainToPeerTask::Block(Box::new(last_block)))
                     .expect("Peer handler broadcast was closed. This should never happen");
+
+                for transaction in resurrected_transactions {
+                    self.broadcast_own_transaction(Box::new(transaction))
+                        .await?;
+                }
             }
             PeerTaskToMain::AddPeerMaxBlockHeight((
                 socket_addr,
@@ -487,7 +739,11 @@ impl MainLoopHandler {
                 // TODO: If we are not checking the PoW claims of the tip this can be abused by forcing
                 // the client into synchronization mode.
                 let mut global_state_mut = self.global_state_lock.lock_guard_mut().await;
-                if enter_sync_mode(
+                if global_state_mut.net.syncing {
+                    if let Some(sync_progress) = global_state_mut.net.sync_progress.as_mut() {
+                        sync_progress.raise_target_height(claimed_max_height);
+                    }
+                } else if enter_sync_mode(
                     global_state_mut.chain.light_state().header(),
                     claimed_state,
                     global_state_mut.cli().max_number_of_blocks_before_syncing / 3,
@@ -496,7 +752,14 @@ impl MainLoopHandler {
                     "Entering synchronization mode due to peer {} indicating tip height {}; pow family: {:?}",
                     socket_addr, claimed_max_height, claimed_max_pow_family
                 );
-                    global_state_mut.net.syncing = true;
+                    let tip_height = global_state_mut.chain.light_state().header().height;
+                    let target_height = main_loop_state
+                        .sync_state
+                        .max_claimed_height()
+                        .unwrap_or(claimed_max_height);
+                    global_state_mut
+                        .net
+                        .start_syncing(tip_height, target_height, Timestamp::now());
                     self.main_to_miner_tx.send(MainToMiner::StartSyncing)?;
                 }
             }
@@ -521,7 +784,7 @@ impl MainLoopHandler {
                     );
                     if !stay_in_sync_mode {
                         info!("Exiting sync mode");
-                        global_state_mut.net.syncing = false;
+                        global_state_mut.net.stop_syncing();
                     }
                 }
             }
@@ -553,6 +816,25 @@ impl MainLoopHandler {
                     return Ok(());
                 }
 
+                // Another peer may have relayed the same transaction after the
+                // peer task checked the mempool.
+                let transaction = &pt2m_transaction.transaction;
+                if transaction
+                    .proof
+                    .proof_quality()
+                    .is_ok_and(|proof_quality| {
+                        global_state_mut.mempool.contains_with_higher_proof_quality(
+                            transaction.kernel.txid(),
+                            proof_quality,
+                        )
+                    })
+                {
+                    debug!(
+                        "main loop got transaction that is already known, discarding transaction"
+                    );
+                    return Ok(());
+                }
+
                 // Insert into mempool
                 global_state_mut
                     .mempool_insert(pt2m_transaction.transaction.to_owned())
@@ -566,11 +848,296 @@ impl MainLoopHandler {
                         transaction_notification,
                     ))?;
             }
+            PeerTaskToMain::RendezvousRequest(requester) => {
+                let Some((initiator, other)) =
+                    main_loop_state.rendezvous.request(requester, self.now())
+                else {
+                    return Ok(());
+                };
+
+                info!("Introducing peers {initiator} and {other} to each other");
+                self.main_to_peer_broadcast_tx
+                    .send(MainToPeerTask::RendezvousIntroduction {
+                        to: initiator,
+                        endpoint: other,
+                        initiator: true,
+                    })?;
+                self.main_to_peer_broadcast_tx
+                    .send(MainToPeerTask::RendezvousIntroduction {
+                        to: other,
+                        endpoint: initiator,
+                        initiator: false,
+                    })?;
+            }
+            PeerTaskToMain::RendezvousIntroduction {
+                endpoint,
+                initiator,
+            } => {
+                let global_state = self.global_state_lock.lock_guard().await;
+                let connected_peers = &global_state.net.peer_map;
+                if connected_peers.contains_key(&endpoint)
+                    || connected_peers.len() >= global_state.cli().max_peers as usize
+                {
+                    return Ok(());
+                }
+
+                info!("Punching hole to introduced peer {endpoint}");
+                let own_handshake_data: HandshakeData = global_state.get_own_handshakedata().await;
+                let main_to_peer_broadcast_rx = self.main_to_peer_broadcast_tx.subscribe();
+                let global_state_lock_clone = self.global_state_lock.clone();
+                let peer_task_to_main_tx_clone = self.peer_task_to_main_tx.to_owned();
+                let hole_punch_task = tokio::task::Builder::new()
+                    .name("hole_punch_wrapper")
+                    .spawn(async move {
+                        hole_punch_wrapper(
+                            endpoint,
+                            initiator,
+                            global_state_lock_clone,
+                            main_to_peer_broadcast_rx,
+                            peer_task_to_main_tx_clone,
+                            own_handshake_data,
+                        )
+                        .await;
+                    })?;
+                main_loop_state.task_handles.push(hole_punch_task);
+                main_loop_state.task_handles.retain(|th| !th.is_finished());
+            }
+            PeerTaskToMain::OrphanParentMissing {
+                parent_digest,
+                requested_from,
+            } => {
+                let now = self.now();
+                main_loop_state
+                    .orphan_parent_requests
+                    .retain(|_, requested_at| {
+                        now.duration_since(*requested_at)
+                            .is_ok_and(|age| age < ORPHAN_PARENT_REQUEST_INTERVAL)
+                    });
+                if main_loop_state
+                    .orphan_parent_requests
+                    .contains_key(&parent_digest)
+                {
+                    debug!("Parent {parent_digest} of orphan block was already requested");
+                    return Ok(());
+                }
+                main_loop_state
+                    .orphan_parent_requests
+                    .insert(parent_digest, now);
+
+                debug!("Requesting parent {parent_digest} of orphan block from all peers");
+                self.main_to_peer_broadcast_tx
+                    .send(MainToPeerTask::RequestBlock {
+                        block_digest: parent_digest,
+                        except: requested_from,
+                    })?;
+            }
+            PeerTaskToMain::CoinJoinFailed(transaction) => {
+                let is_confirmable = transaction.is_confirmable_relative_to(
+                    &self
+                        .global_state_lock
+                        .lock_guard()
+                        .await
+                        .chain
+                        .light_state()
+                        .body()
+                        .mutator_set_accumulator,
+                );
+                if !is_confirmable {
+                    warn!(
+                        "Own coinjoin contribution {} was outdated by a new block and is not \
+                        broadcast. Its inputs remain unspent.",
+                        transaction.kernel.txid()
+                    );
+                    return Ok(());
+                }
+
+                info!(
+                    "Broadcasting own coinjoin contribution {} on its own",
+                    transaction.kernel.txid()
+                );
+                self.broadcast_own_transaction(transaction).await?;
+            }
         }
 
         Ok(())
     }
 
+    /// Disconnect from a fraction of the outbound peers, such that peer
+    /// discovery replaces them. Peers specified in the CLI arguments and anchor
+    /// peers are kept. Peers sharing their network group with other outbound
+    /// peers are rotated out first.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for read
+    async fn rotate_outbound_peers(&self) -> Result<()> {
+        let global_state = self.global_state_lock.lock_guard().await;
+        let outbound_peers = global_state
+            .net
+            .peer_map
+            .values()
+            .filter(|peer| !peer.inbound)
+            .collect_vec();
+        let rotation_count = outbound_peers.len() / PEER_ROTATION_FRACTION_DENOMINATOR;
+        if rotation_count == 0 {
+            return Ok(());
+        }
+
+        let group_sizes = outbound_peers
+            .iter()
+            .map(|peer| NetworkGroup::of(peer.connected_address.ip()))
+            .counts();
+        let mut rotatable_peers = outbound_peers
+            .into_iter()
+            .filter(|peer| !global_state.cli().peers.contains(&peer.connected_address))
+            .filter(|peer| {
+                !global_state
+                    .net
+                    .anchor_peers
+                    .contains(&peer.connected_address)
+            })
+            .collect_vec();
+        rotatable_peers.shuffle(&mut thread_rng());
+        rotatable_peers.sort_by_key(|peer| {
+            std::cmp::Reverse(group_sizes[&NetworkGroup::of(peer.connected_address.ip())])
+        });
+
+        for peer in rotatable_peers.into_iter().take(rotation_count) {
+            info!("Rotating out outbound peer {}", peer.connected_address);
+            self.main_to_peer_broadcast_tx
+                .send(MainToPeerTask::Disconnect(peer.connected_address))?;
+        }
+
+        Ok(())
+    }
+
+    /// Check whether the tip stopped advancing although peers claim more
+    /// proof-of-work. If so, rotate out peers and restart synchronization,
+    /// and alert once per stale tip.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for write
+    async fn check_tip_staleness(&self, main_loop_state: &mut MutableMainLoopState) -> Result<()> {
+        let mut global_state_mut = self.global_state_lock.lock_guard_mut().await;
+        let tip = global_state_mut.chain.light_state();
+        let (tip_digest, tip_height) = (tip.hash(), tip.header().height);
+        let max_claimed_pow = main_loop_state
+            .sync_state
+            .peer_sync_states
+            .values()
+            .map(|peer_sync_state| peer_sync_state.claimed_max_pow)
+            .max();
+        let check = main_loop_state.tip_watchdog.check(
+            tip_digest,
+            tip.header(),
+            max_claimed_pow,
+            self.now(),
+        );
+
+        match check {
+            TipCheck::Fresh => return Ok(()),
+            TipCheck::NewlyStale { age } => {
+                warn!(
+                    "Tip {tip_digest} at height {tip_height} is {} minutes old, while peers \
+                    claim more proof-of-work. Synchronization appears stuck; rotating peers and \
+                    restarting synchronization.",
+                    age.as_secs() / 60
+                );
+                global_state_mut.publish_event(NodeEvent::TipStale {
+                    digest: tip_digest,
+                    height: tip_height,
+                    age,
+                });
+            }
+            TipCheck::StillStale => {
+                info!("Tip is still stale; rotating peers and restarting synchronization");
+            }
+        }
+
+        main_loop_state.sync_state.last_sync_request = None;
+        if !global_state_mut.net.syncing {
+            let target_height = main_loop_state
+                .sync_state
+                .max_claimed_height()
+                .unwrap_or(tip_height);
+            global_state_mut
+                .net
+                .start_syncing(tip_height, target_height, Timestamp::now());
+            self.main_to_miner_tx.send(MainToMiner::StartSyncing)?;
+        }
+        drop(global_state_mut);
+
+        self.rotate_outbound_peers().await
+    }
+
+    /// On networks other than main, warn if the difficulty of the most recent
+    /// blocks follows a trajectory that makes the chain unusable, such as
+    /// being stuck at the minimum with slow blocks, along with the likely fix.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for read
+    async fn check_difficulty_trajectory(&self, main_loop_state: &mut MutableMainLoopState) {
+        let global_state = self.global_state_lock.lock_guard().await;
+        if global_state.cli().network == Network::Main {
+            return;
+        }
+
+        let tip_header = global_state.chain.light_state().header().clone();
+        let archival_state = global_state.chain.archival_state();
+        let mut headers = vec![tip_header];
+        while headers.len() < DIFFICULTY_ALARM_WINDOW {
+            let parent_digest = headers.last().unwrap().prev_block_digest;
+            match archival_state.get_block_header(parent_digest).await {
+                Some(parent_header) => headers.push(parent_header),
+                None => break,
+            }
+        }
+        headers.reverse();
+
+        if let Some(trajectory) = main_loop_state.difficulty_alarm.check(&headers) {
+            warn!("{trajectory}");
+        }
+    }
+
+    /// Compare the canonical chain with the latest trusted checkpoint, and warn
+    /// about a possible eclipse attack if the canonical chain conflicts with
+    /// it, or if the checkpoint is above the tip but no peer offers blocks
+    /// that high. Never affects fork choice.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for read
+    async fn check_beacon(&self, main_loop_state: &MutableMainLoopState) {
+        let global_state = self.global_state_lock.lock_guard().await;
+        if !global_state.net.beacon.is_enabled() {
+            return;
+        }
+
+        let tip = global_state.chain.light_state();
+        let assessment = global_state
+            .net
+            .beacon
+            .assess(
+                global_state.chain.archival_state(),
+                tip.hash(),
+                tip.header().height,
+            )
+            .await;
+        match assessment {
+            BeaconAssessment::Conflict { .. } => {
+                warn!("Possible eclipse attack: {assessment}");
+            }
+            BeaconAssessment::Behind {
+                checkpoint_height, ..
+            } if !global_state.net.syncing
+                && !main_loop_state
+                    .sync_state
+                    .some_peer_reaches(checkpoint_height) =>
+            {
+                warn!("Possible eclipse attack: {assessment}, and no peer offers blocks that high");
+            }
+            _ => debug!("Checkpoint beacon: {assessment}"),
+        }
+    }
+
     /// Function to perform peer discovery: Finds potential peers from connected peers and attempts
     /// to establish connections with one of those potential peers.
     ///
@@ -582,6 +1149,11 @@ impl MainLoopHandler {
     ) -> Result<()> {
         let global_state = self.global_state_lock.lock_guard().await;
 
+        // No new connections are made while in maintenance mode.
+        if global_state.net.maintenance_mode {
+            return Ok(());
+        }
+
         let connected_peers: Vec<PeerInfo> = global_state.net.peer_map.values().cloned().collect();
 
         // Check if we are connected to too many peers
@@ -673,10 +1245,14 @@ impl MainLoopHandler {
         }
 
         // We don't make an outgoing connection if we've reached the peer limit, *or* if we are
-        // one below the peer limit as we reserve this last slot for an ingoing connection.
-        if connected_peers.len() == global_state.cli().max_peers as usize
-            || connected_peers.len() > 2
-                && connected_peers.len() - 1 == global_state.cli().max_peers as usize
+        // one below the peer limit as we reserve this last slot for an ingoing connection. Nodes
+        // that accept no incoming connections use all slots for outgoing connections.
+        let reserve_slot_for_incoming =
+            !global_state.cli().disallow_all_incoming_peer_connections();
+        if connected_peers.len() >= global_state.cli().max_peers as usize
+            || reserve_slot_for_incoming
+                && connected_peers.len() > 2
+                && connected_peers.len() + 1 == global_state.cli().max_peers as usize
         {
             return Ok(());
         }
@@ -700,7 +1276,23 @@ impl MainLoopHandler {
             .get_distant_candidate(&connected_peers, global_state.net.instance_id)
         {
             Some(candidate) => candidate,
-            None => return Ok(()),
+            None => {
+                // No peer we know of accepts incoming connections. Ask a
+                // connected peer for an introduction to a node behind a NAT.
+                if global_state.cli().hole_punching {
+                    let rendezvous_peer = connected_peers
+                        .iter()
+                        .filter(|peer| !peer.inbound)
+                        .choose(&mut thread_rng());
+                    if let Some(rendezvous_peer) = rendezvous_peer {
+                        self.main_to_peer_broadcast_tx
+                            .send(MainToPeerTask::RequestRendezvous(
+                                rendezvous_peer.connected_address,
+                            ))?;
+                    }
+                }
+                return Ok(());
+            }
         };
 
         // 2)
@@ -752,7 +1344,12 @@ impl MainLoopHandler {
             return Ok(());
         }
 
-        info!("Running sync");
+        match global_state.net.sync_progress {
+            Some(sync_progress) => {
+                info!("Running sync: {}", sync_progress.report(Timestamp::now()))
+            }
+            None => info!("Running sync"),
+        }
 
         // Check when latest batch of blocks was requested
         let (current_block_hash, current_block_height, current_block_proof_of_work_family) = (
@@ -784,16 +1381,33 @@ impl MainLoopHandler {
         // Create the next request from the reported
         info!("Creating new sync request");
 
-        // Pick a random peer that has reported to have relevant blocks
+        // Pick a random peer that has reported to have relevant blocks,
+        // preferring those that reach the latest trusted checkpoint
+        let checkpoint_height = global_state
+            .net
+            .beacon
+            .latest()
+            .map(|checkpoint| checkpoint.height)
+            .filter(|height| *height > current_block_height);
+        // Pruned peers cannot serve the blocks of the sync.
         let candidate_peers = main_loop_state
             .sync_state
-            .get_potential_peers_for_sync_request(current_block_proof_of_work_family);
+            .get_potential_peers_for_sync_request(
+                current_block_proof_of_work_family,
+                checkpoint_height,
+                |peer| {
+                    global_state
+                        .net
+                        .peer_map
+                        .get(peer)
+                        .is_some_and(|peer_info| peer_info.is_archival_node)
+                },
+            );
         let mut rng = thread_rng();
-        let chosen_peer = candidate_peers.choose(&mut rng);
-        assert!(
-            chosen_peer.is_some(),
-            "A synchronization candidate must be available for a request. Otherwise the data structure is in an invalid state and syncing should not be active"
-        );
+        let Some(chosen_peer) = candidate_peers.choose(&mut rng) else {
+            info!("No archival peer to sync from. Waiting for one to connect.");
+            return Ok(());
+        };
 
         // Find the blocks to request
         let tip_digest = current_block_hash;
@@ -808,7 +1422,6 @@ impl MainLoopHandler {
         let most_canonical_digests = [vec![tip_digest], most_canonical_digests].concat();
 
         // Send message to the relevant peer loop to request the blocks
-        let chosen_peer = chosen_peer.unwrap();
         info!(
             "Sending block batch request to {}\nrequesting blocks descending from {}\n height {}",
             chosen_peer, current_block_hash, current_block_height
@@ -895,6 +1508,7 @@ impl MainLoopHandler {
         // not block the main loop.
         let skip_if_busy = self.global_state_lock.skip_if_busy();
         let perform_ms_update_if_needed = false;
+        let is_own_transaction = false;
 
         let global_state_lock_clone = self.global_state_lock.clone();
         let main_to_peer_broadcast_tx_clone = self.main_to_peer_broadcast_tx.clone();
@@ -906,6 +1520,7 @@ impl MainLoopHandler {
                         .handle_upgrade(
                             skip_if_busy,
                             perform_ms_update_if_needed,
+                            is_own_transaction,
                             global_state_lock_clone,
                             main_to_peer_broadcast_tx_clone,
                         )
@@ -917,6 +1532,86 @@ impl MainLoopHandler {
         Ok(())
     }
 
+    /// End the coinjoin session coordinated by this node if it is full, due,
+    /// or outdated, and merge it in a spawned task if it has enough
+    /// contributions. A session that becomes ready while the previous one is
+    /// being merged waits for that merger to finish.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for write
+    async fn coordinate_coinjoin(
+        &mut self,
+        main_loop_state: &mut MutableMainLoopState,
+    ) -> Result<()> {
+        let previous_merger_is_still_running = main_loop_state
+            .coinjoin_merger_task
+            .as_ref()
+            .is_some_and(|task| !task.is_finished());
+        if previous_merger_is_still_running {
+            return Ok(());
+        }
+
+        let outcome = {
+            let mut global_state = self.global_state_lock.lock_guard_mut().await;
+            let mutator_set_hash = global_state
+                .chain
+                .light_state()
+                .body()
+                .mutator_set_accumulator
+                .hash();
+            global_state
+                .net
+                .coinjoin_coordinator
+                .poll(mutator_set_hash, Timestamp::now())
+        };
+
+        match outcome {
+            None => {}
+            Some(SessionOutcome::Failed {
+                session_id,
+                contributors,
+            }) => {
+                info!(
+                    "Coinjoin session {session_id} failed with {} contributions",
+                    contributors.len()
+                );
+                self.main_to_peer_broadcast_tx
+                    .send(MainToPeerTask::CoinJoinStatus {
+                        contributors,
+                        session_id,
+                        result: Err(CoinJoinError::SessionFailed),
+                    })?;
+            }
+            Some(SessionOutcome::Ready {
+                session_id,
+                contributors,
+                transactions,
+            }) => {
+                info!(
+                    "Merging {} contributions to coinjoin session {session_id}",
+                    transactions.len()
+                );
+                let global_state_lock_clone = self.global_state_lock.clone();
+                let main_to_peer_broadcast_tx_clone = self.main_to_peer_broadcast_tx.clone();
+                let coinjoin_merger_task = tokio::task::Builder::new()
+                    .name("coinjoin_merger")
+                    .spawn(async move {
+                        coinjoin_merger::merge_session(
+                            session_id,
+                            contributors,
+                            transactions,
+                            global_state_lock_clone,
+                            main_to_peer_broadcast_tx_clone,
+                        )
+                        .await
+                    })?;
+                main_loop_state.coinjoin_merger_task = Some(coinjoin_merger_task);
+            }
+        }
+
+        Ok(())
+    }
+
     pub(crate) async fn run(
         &mut self,
         mut peer_task_to_main_rx: mpsc::Receiver<PeerTaskToMain>,
@@ -960,6 +1655,42 @@ impl MainLoopHandler {
         let tx_proof_upgrade_timer = time::sleep(tx_proof_upgrade_interval);
         tokio::pin!(tx_proof_upgrade_timer);
 
+        // Set rotation of outbound peers timer to run every N seconds.
+        let peer_rotation_interval = Duration::from_secs(PEER_ROTATION_INTERVAL_IN_SECONDS);
+        let peer_rotation_timer = time::sleep(peer_rotation_interval);
+        tokio::pin!(peer_rotation_timer);
+
+        // Set checkpointing of the global state to run every N seconds.
+        let state_checkpoint_interval = Duration::from_secs(STATE_CHECKPOINT_INTERVAL_IN_SECONDS);
+        let state_checkpoint_timer = time::sleep(state_checkpoint_interval);
+        tokio::pin!(state_checkpoint_timer);
+
+        // Set the tip-staleness watchdog to run every N seconds.
+        let tip_watchdog_interval = Duration::from_secs(TIP_WATCHDOG_INTERVAL_IN_SECONDS);
+        let tip_watchdog_timer = time::sleep(tip_watchdog_interval);
+        tokio::pin!(tip_watchdog_timer);
+
+        // Set coinjoin sessions coordinated by this node, if any, to be
+        // checked every N seconds.
+        let coordinate_coinjoin = self.global_state_lock.cli().coinjoin_config().is_some();
+        let coinjoin_interval = Duration::from_secs(COINJOIN_POLL_INTERVAL_IN_SECONDS);
+        let coinjoin_timer = time::sleep(coinjoin_interval);
+        tokio::pin!(coinjoin_timer);
+
+        // Set syncing of the wallet database to run every N seconds, if
+        // wallet writes are not synced as they happen.
+        let sync_wallet_periodically =
+            self.global_state_lock.cli().wallet_fsync == WalletFsyncPolicy::Periodic;
+        let wallet_fsync_interval =
+            Duration::from_secs(self.global_state_lock.cli().wallet_fsync_interval);
+        let wallet_fsync_timer = time::sleep(wallet_fsync_interval);
+        tokio::pin!(wallet_fsync_timer);
+
+        // Ping the service manager's watchdog, if it expects pings.
+        let service_watchdog_interval = sd_notify::watchdog_interval();
+        let service_watchdog_timer = time::sleep(service_watchdog_interval.unwrap_or_default());
+        tokio::pin!(service_watchdog_timer);
+
         // Spawn tasks to monitor for SIGTERM, SIGINT, and SIGQUIT. These
         // signals are only used on Unix systems.
         let (_tx_term, mut rx_term): (mpsc::Sender<()>, mpsc::Receiver<()>) =
@@ -1029,15 +1760,19 @@ impl MainLoopHandler {
                 }
 
                 // Handle incoming connections from peer
-                Ok((stream, peer_address)) = self.incoming_peer_listener.accept() => {
-                    // Return early if no incoming connections are accepted. Do
-                    // not send application-handshake.
-                    if self.global_state_lock.cli().disallow_all_incoming_peer_connections() {
-                        warn!("Got incoming connection despite not accepting any. Ignoring");
+                Ok((stream, peer_address)) = accept_incoming(self.incoming_peer_listener.as_ref()) => {
+                    let mut state = self.global_state_lock.lock_guard_mut().await;
+                    if state.net.maintenance_mode {
+                        info!("Refusing incoming connection from {peer_address}: maintenance mode");
+                        continue;
+                    }
+
+                    let max_handshakes_per_minute = state.cli().max_handshakes_per_minute;
+                    if !state.net.admit_handshake_attempt(peer_address.ip(), max_handshakes_per_minute).await {
+                        warn!("Refusing incoming connection from {peer_address}: too many connection attempts");
                         continue;
                     }
 
-                    let state = self.global_state_lock.lock_guard().await;
                     let main_to_peer_broadcast_rx_clone: broadcast::Receiver<MainToPeerTask> = self.main_to_peer_broadcast_tx.subscribe();
                     let peer_task_to_main_tx_clone: mpsc::Sender<PeerTaskToMain> = self.peer_task_to_main_tx.clone();
                     let own_handshake_data: HandshakeData = state.get_own_handshakedata().await;
@@ -1090,6 +1825,7 @@ impl MainLoopHandler {
                     // if needed.
                     debug!("Timer: peer discovery job");
                     self.peer_discovery_and_reconnector(&mut main_loop_state).await?;
+                    self.check_beacon(&main_loop_state).await;
 
                     // Reset the timer to run this branch again in N seconds
                     peer_discovery_timer.as_mut().reset(tokio::time::Instant::now() + peer_discovery_timer_interval);
@@ -1145,6 +1881,58 @@ impl MainLoopHandler {
                     tx_proof_upgrade_timer.as_mut().reset(tokio::time::Instant::now() + tx_proof_upgrade_interval);
                 }
 
+                // Handle rotation of outbound peers
+                _ = &mut peer_rotation_timer => {
+                    debug!("Timer: peer rotation job");
+                    self.rotate_outbound_peers().await?;
+
+                    peer_rotation_timer.as_mut().reset(tokio::time::Instant::now() + peer_rotation_interval);
+                }
+
+                // Handle checkpointing of the global state
+                _ = &mut state_checkpoint_timer => {
+                    debug!("Timer: state checkpoint job");
+                    if let Err(err) = self.store_state_checkpoint().await {
+                        warn!("Could not store state checkpoint: {err:#}");
+                    }
+
+                    state_checkpoint_timer.as_mut().reset(tokio::time::Instant::now() + state_checkpoint_interval);
+                }
+
+                // Handle detection of a stuck tip
+                _ = &mut tip_watchdog_timer => {
+                    debug!("Timer: tip watchdog job");
+                    self.check_tip_staleness(&mut main_loop_state).await?;
+                    self.check_difficulty_trajectory(&mut main_loop_state).await;
+
+                    tip_watchdog_timer.as_mut().reset(tokio::time::Instant::now() + tip_watchdog_interval);
+                }
+
+                // Handle coinjoin sessions coordinated by this node
+                _ = &mut coinjoin_timer, if coordinate_coinjoin => {
+                    trace!("Timer: coinjoin coordinator");
+                    self.coordinate_coinjoin(&mut main_loop_state).await?;
+
+                    coinjoin_timer.as_mut().reset(tokio::time::Instant::now() + coinjoin_interval);
+                }
+
+                // Handle syncing of the wallet database
+                _ = &mut wallet_fsync_timer, if sync_wallet_periodically => {
+                    debug!("Timer: wallet fsync job");
+                    self.global_state_lock.lock_guard_mut().await.wallet_state.wallet_db.sync().await;
+
+                    wallet_fsync_timer.as_mut().reset(tokio::time::Instant::now() + wallet_fsync_interval);
+                }
+
+                // Handle service manager watchdog pings
+                _ = &mut service_watchdog_timer, if service_watchdog_interval.is_some() => {
+                    trace!("Timer: service watchdog ping");
+                    sd_notify::watchdog_ping();
+                    sd_notify::status(&self.service_status().await);
+
+                    service_watchdog_timer.as_mut().reset(tokio::time::Instant::now() + service_watchdog_interval.unwrap());
+                }
+
             }
         }
 
@@ -1153,6 +1941,123 @@ impl MainLoopHandler {
         Ok(())
     }
 
+    /// Insert a transaction initiated by this node into the mempool and share
+    /// it with peers, upgrading its proof first if it contains secret data.
+    async fn broadcast_own_transaction(&mut self, transaction: Box<Transaction>) -> Result<()> {
+        // remember the witness, such that the transaction can be rebuilt if
+        // a reorganization un-confirms it
+        if let TransactionProof::Witness(primitive_witness) = &transaction.proof {
+            let mut global_state_mut = self.global_state_lock.lock_guard_mut().await;
+
+            // A transaction spending unconfirmed change cannot be confirmed
+            // before its parents are, so it is held until then.
+            let tip_msa = &global_state_mut
+                .chain
+                .light_state()
+                .body()
+                .mutator_set_accumulator;
+            if !transaction.is_confirmable_relative_to(tip_msa) {
+                let parents = global_state_mut
+                    .wallet_state
+                    .unconfirmed_change
+                    .parents_of(&primitive_witness.input_utxos.utxos);
+                if !parents.is_empty() {
+                    info!(
+                        "Holding own transaction {} until {} parent transactions are confirmed",
+                        transaction.kernel.txid(),
+                        parents.len()
+                    );
+                    global_state_mut
+                        .wallet_state
+                        .own_transactions
+                        .track_unconfirmed_spend(primitive_witness.clone(), parents);
+                    return Ok(());
+                }
+            }
+
+            global_state_mut
+                .wallet_state
+                .own_transactions
+                .track(primitive_witness.clone());
+        }
+
+        self.insert_and_share_transaction(transaction).await
+    }
+
+    /// Insert a transaction constructed elsewhere and submitted over RPC into
+    /// the mempool and share it with peers, upgrading its proof first if it
+    /// contains secret data. Unlike [`Self::broadcast_own_transaction`], the
+    /// wallet does not track the transaction, so it is not rebuilt if a
+    /// reorganization un-confirms it.
+    async fn broadcast_external_transaction(
+        &mut self,
+        transaction: Box<Transaction>,
+    ) -> Result<()> {
+        self.insert_and_share_transaction(transaction).await
+    }
+
+    /// Insert a transaction originating at this node into the mempool and
+    /// share it with peers according to the diffusion policy, upgrading its
+    /// proof first if it contains secret data.
+    async fn insert_and_share_transaction(&mut self, transaction: Box<Transaction>) -> Result<()> {
+        // insert transaction into mempool
+        self.global_state_lock
+            .lock_guard_mut()
+            .await
+            .mempool_insert(*transaction.clone())
+            .await;
+
+        // Is this a transaction we can share with peers? If so, share
+        // it according to the diffusion policy.
+        if let Ok(notification) = transaction.as_ref().try_into() {
+            relay_own_transaction(
+                notification,
+                self.global_state_lock.clone(),
+                self.main_to_peer_broadcast_tx.clone(),
+            );
+        } else {
+            // Otherwise upgrade its proof quality, and share it by
+            // spinning up the proof upgrader.
+            let TransactionProof::Witness(primitive_witness) = transaction.proof else {
+                panic!("Expected Primitive witness. Got: {:?}", transaction.proof);
+            };
+
+            let proving_capability = self
+                .global_state_lock
+                .lock_guard()
+                .await
+                .net
+                .tx_proving_capability;
+            let upgrade_job =
+                UpgradeJob::from_primitive_witness(proving_capability, primitive_witness);
+
+            // TODO: Replace this logic with a proof queue
+            let wait_if_busy = self.global_state_lock.wait_if_busy();
+            let global_state_lock_clone = self.global_state_lock.clone();
+            let main_to_peer_broadcast_tx_clone = self.main_to_peer_broadcast_tx.clone();
+            let _proof_upgrader_task =
+                tokio::task::Builder::new()
+                    .name("proof_upgrader")
+                    .spawn(async move {
+                        upgrade_job
+                            .handle_upgrade(
+                                wait_if_busy,
+                                true,
+                                true,
+                                global_state_lock_clone,
+                                main_to_peer_broadcast_tx_clone,
+                            )
+                            .await
+                    })?;
+
+            // main_loop_state.proof_upgrader_task = Some(proof_upgrader_task);
+            // If transaction could not be shared immediately because
+            // it contains secret data, upgrade its proof-type.
+        }
+
+        Ok(())
+    }
+
     /// Handle messages from the RPC server. Returns `true` iff the client should shut down
     /// after handling this message.
     async fn handle_rpc_server_message(&mut self, msg: RPCServerToMain) -> Result<bool> {
@@ -1165,55 +2070,18 @@ impl MainLoopHandler {
                     transaction.kernel.mutator_set_hash
                 );
 
-                // insert transaction into mempool
-                self.global_state_lock
-                    .lock_guard_mut()
-                    .await
-                    .mempool_insert(*transaction.clone())
-                    .await;
-
-                // Is this a transaction we can share with peers? If so, share
-                // it immediately.
-                if let Ok(notification) = transaction.as_ref().try_into() {
-                    self.main_to_peer_broadcast_tx
-                        .send(MainToPeerTask::TransactionNotification(notification))?;
-                } else {
-                    // Otherwise upgrade its proof quality, and share it by
-                    // spinning up the proof upgrader.
-                    let TransactionProof::Witness(primitive_witness) = transaction.proof else {
-                        panic!("Expected Primitive witness. Got: {:?}", transaction.proof);
-                    };
+                self.broadcast_own_transaction(transaction).await?;
 
-                    let proving_capability = self
-                        .global_state_lock
-                        .lock_guard()
-                        .await
-                        .net
-                        .tx_proving_capability;
-                    let upgrade_job =
-                        UpgradeJob::from_primitive_witness(proving_capability, primitive_witness);
-
-                    // TODO: Replace this logic with a proof queue
-                    let wait_if_busy = self.global_state_lock.wait_if_busy();
-                    let global_state_lock_clone = self.global_state_lock.clone();
-                    let main_to_peer_broadcast_tx_clone = self.main_to_peer_broadcast_tx.clone();
-                    let _proof_upgrader_task = tokio::task::Builder::new()
-                        .name("proof_upgrader")
-                        .spawn(async move {
-                        upgrade_job
-                            .handle_upgrade(
-                                wait_if_busy,
-                                true,
-                                global_state_lock_clone,
-                                main_to_peer_broadcast_tx_clone,
-                            )
-                            .await
-                    })?;
+                // do not shut down
+                Ok(false)
+            }
+            RPCServerToMain::BroadcastExternalTx(transaction) => {
+                debug!(
+                    "`main` received external transaction {} from RPC Server",
+                    transaction.kernel.txid()
+                );
 
-                    // main_loop_state.proof_upgrader_task = Some(proof_upgrader_task);
-                    // If transaction could not be shared immediately because
-                    // it contains secret data, upgrade its proof-type.
-                }
+                self.broadcast_external_transaction(transaction).await?;
 
                 // do not shut down
                 Ok(false)
@@ -1229,6 +2097,80 @@ impl MainLoopHandler {
                 self.main_to_miner_tx.send(MainToMiner::StartMining)?;
                 Ok(false)
             }
+            RPCServerToMain::CheckReachability(peers) => {
+                info!("Asking {} peers to check our reachability", peers.len());
+                self.main_to_peer_broadcast_tx
+                    .send(MainToPeerTask::RequestReachabilityCheck(peers))?;
+                Ok(false)
+            }
+            RPCServerToMain::RequestCoinJoinSessions => {
+                self.main_to_peer_broadcast_tx
+                    .send(MainToPeerTask::RequestCoinJoinSessions)?;
+                Ok(false)
+            }
+            RPCServerToMain::CoinJoinContribute(own_contribution) => {
+                let transaction = match TransferTransaction::try_from(&own_contribution.transaction)
+                {
+                    Ok(transaction) => transaction,
+                    Err(err) => {
+                        error!("Cannot send coinjoin contribution to coordinator: {err}");
+                        return Ok(false);
+                    }
+                };
+                let coordinator = own_contribution.coordinator;
+                let contribution = CoinJoinContribution {
+                    session_id: own_contribution.session_id,
+                    transaction,
+                    disclosed_output: own_contribution.disclosed_output.clone(),
+                };
+                info!(
+                    "Contributing transaction {} to coinjoin session {} of {coordinator}",
+                    own_contribution.transaction.kernel.txid(),
+                    own_contribution.session_id,
+                );
+                self.global_state_lock
+                    .lock_guard_mut()
+                    .await
+                    .net
+                    .own_coinjoin_contribution = Some(*own_contribution);
+                self.main_to_peer_broadcast_tx
+                    .send(MainToPeerTask::CoinJoinContribute {
+                        coordinator,
+                        contribution: Box::new(contribution),
+                    })?;
+                Ok(false)
+            }
+            RPCServerToMain::BlockSolutionFound(new_block_info) => {
+                info!(
+                    "External miner found new block: {}",
+                    new_block_info.block.kernel.header.height
+                );
+                let announced = self.announce_own_block(&new_block_info.block).await;
+                let Some(new_block) = self.store_self_mined_block(*new_block_info).await? else {
+                    return Ok(false);
+                };
+
+                // The miner task did not find this block, so let it know
+                // about the new tip.
+                if self.global_state_lock.cli().mine {
+                    self.main_to_miner_tx
+                        .send(MainToMiner::NewBlock(new_block.clone()))?;
+                }
+
+                if !announced {
+                    self.main_to_peer_broadcast_tx
+                        .send(MainToPeerTask::Block(new_block))
+                        .expect(
+                            "Peer handler broadcast channel prematurely closed. This should never happen.",
+                        );
+                }
+                Ok(false)
+            }
+            RPCServerToMain::GeneratedBlock(block, stored_tx) => {
+                let stored = self.store_generated_block(*block).await?;
+                let _ = stored_tx.send(stored);
+                Ok(false)
+            }
             RPCServerToMain::Shutdown => {
                 info!("Recived RPC shutdown request.");
 
@@ -1238,20 +2180,81 @@ impl MainLoopHandler {
         }
     }
 
+    /// Locking:
+    ///   * acquires `global_state_lock` for read
+    async fn store_anchor_peers(&self) -> Result<()> {
+        let global_state = self.global_state_lock.lock_guard().await;
+        let data_dir = DataDirectory::get(
+            global_state.cli().data_dir.clone(),
+            global_state.cli().network,
+        )?;
+        let connected_peers = global_state.net.peer_map.values().cloned().collect_vec();
+        anchor_peers::store(&data_dir.root_dir_path(), &connected_peers)
+    }
+
+    /// Locking:
+    ///   * acquires `global_state_lock` for read
+    async fn store_state_checkpoint(&self) -> Result<()> {
+        let global_state = self.global_state_lock.lock_guard().await;
+        let data_dir = DataDirectory::get(
+            global_state.cli().data_dir.clone(),
+            global_state.cli().network,
+        )?;
+        let checkpoint = StateCheckpoint::capture(&global_state).await;
+        drop(global_state);
+
+        checkpoint.store(&data_dir.root_dir_path())
+    }
+
+    /// One-line summary of the node's state for the service manager.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for read
+    pub(crate) async fn service_status(&self) -> String {
+        let state = self.global_state_lock.lock_guard().await;
+        let height = state.chain.light_state().header().height;
+        let num_peers = state.net.peer_map.len();
+        if state.net.syncing {
+            format!("Syncing, at block height {height}, {num_peers} peers")
+        } else {
+            format!("At block height {height}, {num_peers} peers")
+        }
+    }
+
     async fn graceful_shutdown(&mut self, task_handles: Vec<JoinHandle<()>>) -> Result<()> {
         info!("Shutdown initiated.");
+        sd_notify::stopping();
 
         // Stop mining
         let __result = self.main_to_miner_tx.send(MainToMiner::Shutdown);
 
+        // Remember good outbound peers, to connect to them first on startup.
+        if let Err(err) = self.store_anchor_peers().await {
+            warn!("Could not store anchor peers: {err:#}");
+        }
+
         // Send 'bye' message to all peers.
         let _result = self
             .main_to_peer_broadcast_tx
             .send(MainToPeerTask::DisconnectAll());
         debug!("sent bye");
 
-        // Flush all databases
+        // Flush all databases, and sync wallet writes not yet synced
+        sd_notify::status("Shutting down: flushing databases");
         self.global_state_lock.flush_databases().await?;
+        self.global_state_lock
+            .lock_guard_mut()
+            .await
+            .wallet_state
+            .wallet_db
+            .sync()
+            .await;
+
+        // Checkpoint the state, such that the next startup is fast.
+        sd_notify::status("Shutting down: storing state checkpoint");
+        if let Err(err) = self.store_state_checkpoint().await {
+            warn!("Could not store state checkpoint: {err:#}");
+        }
 
         // wait 0.5 seconds to ensure that child processes have been shut down
         sleep(Duration::new(0, 500 * 1_000_000));
@@ -1309,7 +2312,7 @@ mod tests {
             "Test assumption: All initial peers must represent outgoing connections."
         );
 
-        let incoming_peer_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let incoming_peer_listener = Some(TcpListener::bind("127.0.0.1:0").await.unwrap());
 
         const CHANNEL_CAPACITY: usize = 10;
         let (main_to_miner_tx, _main_to_miner_rx) =
@@ -1511,6 +2514,49 @@ mod tests {
             drop(rpc_server_to_main_rx);
             drop(main_to_peer_rx);
         }
+
+        #[tokio::test]
+        #[traced_test]
+        async fn external_transaction_is_not_tracked_by_wallet() {
+            let TestSetup {
+                peer_to_main_rx,
+                miner_to_main_rx,
+                rpc_server_to_main_rx,
+                task_join_handles: _,
+                mut main_loop_handler,
+                main_to_peer_rx,
+            } = setup(0).await;
+
+            let transaction = a_transaction(
+                &main_loop_handler.global_state_lock,
+                TxProvingCapability::PrimitiveWitness,
+            )
+            .await;
+            let txid = transaction.kernel.txid();
+            let shutdown = main_loop_handler
+                .handle_rpc_server_message(RPCServerToMain::BroadcastExternalTx(Box::new(
+                    transaction,
+                )))
+                .await
+                .unwrap();
+            assert!(!shutdown);
+
+            let global_state = main_loop_handler.global_state_lock.lock_guard().await;
+            assert!(global_state.mempool.contains(txid));
+            assert!(
+                global_state
+                    .wallet_state
+                    .own_transactions
+                    .txids()
+                    .is_empty(),
+                "External transaction must not be rebuilt on reorganization"
+            );
+
+            drop(peer_to_main_rx);
+            drop(miner_to_main_rx);
+            drop(rpc_server_to_main_rx);
+            drop(main_to_peer_rx);
+        }
     }
 
     mod peer_discovery {
@@ -1632,4 +2678,148 @@ mod tests {
             drop(main_to_peer_rx);
         }
     }
+
+    mod orphan_blocks {
+        use super::*;
+        use crate::tests::shared::get_dummy_socket_address;
+
+        #[tokio::test]
+        #[traced_test]
+        async fn parent_of_orphans_is_requested_once_per_interval() {
+            let TestSetup {
+                peer_to_main_rx,
+                miner_to_main_rx,
+                rpc_server_to_main_rx,
+                task_join_handles,
+                mut main_loop_handler,
+                mut main_to_peer_rx,
+            } = setup(1).await;
+            let mut mutable_main_loop_state = MutableMainLoopState::new(task_join_handles);
+
+            let parent_digest: Digest = rand::random();
+            let now = SystemTime::now();
+            main_loop_handler.mock_now = Some(now);
+            for count in 0..2 {
+                main_loop_handler
+                    .handle_peer_task_message(
+                        PeerTaskToMain::OrphanParentMissing {
+                            parent_digest,
+                            requested_from: get_dummy_socket_address(count),
+                        },
+                        &mut mutable_main_loop_state,
+                    )
+                    .await
+                    .unwrap();
+            }
+
+            let Ok(MainToPeerTask::RequestBlock {
+                block_digest,
+                except,
+            }) = main_to_peer_rx.try_recv()
+            else {
+                panic!("Parent of orphan must be requested from all peers");
+            };
+            assert_eq!(parent_digest, block_digest);
+            assert_eq!(get_dummy_socket_address(0), except);
+            assert!(
+                main_to_peer_rx.try_recv().is_err(),
+                "Parent of orphan must be requested only once"
+            );
+
+            main_loop_handler.mock_now = Some(now + ORPHAN_PARENT_REQUEST_INTERVAL);
+            main_loop_handler
+                .handle_peer_task_message(
+                    PeerTaskToMain::OrphanParentMissing {
+                        parent_digest,
+                        requested_from: get_dummy_socket_address(1),
+                    },
+                    &mut mutable_main_loop_state,
+                )
+                .await
+                .unwrap();
+            assert!(matches!(
+                main_to_peer_rx.try_recv(),
+                Ok(MainToPeerTask::RequestBlock { .. })
+            ));
+
+            drop(peer_to_main_rx);
+            drop(miner_to_main_rx);
+            drop(rpc_server_to_main_rx);
+        }
+    }
+
+    mod block_sync {
+        use super::*;
+
+        #[tokio::test]
+        #[traced_test]
+        async fn batches_are_not_requested_from_pruned_peers() {
+            let TestSetup {
+                peer_to_main_rx,
+                miner_to_main_rx,
+                rpc_server_to_main_rx,
+                task_join_handles,
+                main_loop_handler,
+                mut main_to_peer_rx,
+            } = setup(2).await;
+            let mut mutable_main_loop_state = MutableMainLoopState::new(task_join_handles);
+
+            let (archival_peer, pruned_peer) = {
+                let mut global_state = main_loop_handler.global_state_lock.lock_guard_mut().await;
+                global_state.net.syncing = true;
+                let peers = global_state.net.peer_map.keys().copied().collect_vec();
+                global_state
+                    .net
+                    .peer_map
+                    .get_mut(&peers[1])
+                    .unwrap()
+                    .is_archival_node = false;
+                (peers[0], peers[1])
+            };
+            let claimed_state = PeerSynchronizationState::new(
+                BlockHeight::from(1000u64),
+                ProofOfWork::new([u32::MAX; ProofOfWork::NUM_LIMBS]),
+            );
+            for peer in [archival_peer, pruned_peer] {
+                mutable_main_loop_state
+                    .sync_state
+                    .peer_sync_states
+                    .insert(peer, claimed_state);
+            }
+
+            for _ in 0..5 {
+                mutable_main_loop_state.sync_state.last_sync_request = None;
+                main_loop_handler
+                    .block_sync(&mut mutable_main_loop_state)
+                    .await
+                    .unwrap();
+                let Ok(MainToPeerTask::RequestBlockBatch(request)) = main_to_peer_rx.try_recv()
+                else {
+                    panic!("Batch must be requested from archival peer");
+                };
+                assert_eq!(archival_peer, request.peer_addr_target);
+            }
+
+            // Without archival peers, syncing waits for one to connect.
+            main_loop_handler
+                .global_state_lock
+                .lock_guard_mut()
+                .await
+                .net
+                .peer_map
+                .get_mut(&archival_peer)
+                .unwrap()
+                .is_archival_node = false;
+            mutable_main_loop_state.sync_state.last_sync_request = None;
+            main_loop_handler
+                .block_sync(&mut mutable_main_loop_state)
+                .await
+                .unwrap();
+            assert!(main_to_peer_rx.try_recv().is_err());
+
+            drop(peer_to_main_rx);
+            drop(miner_to_main_rx);
+            drop(rpc_server_to_main_rx);
+        }
+    }
 }
@@ -0,0 +1,79 @@
+//! Merging of the coinjoin sessions coordinated by this node.
+//!
+//! Contributions are merged into one transaction one at a time, and every
+//! merger proves a new single proof, so merging a session takes minutes per
+//! participant. Like proof upgrades, it runs in a task of its own, and the
+//! merged transaction enters the mempool and is announced to peers like an
+//! upgraded transaction. If the prover is busy, or a block changes the mutator
+//! set while merging, the session fails, and its contributors broadcast their
+//! contributions on their own.
+
+use std::net::SocketAddr;
+
+use tokio::sync::broadcast;
+use tracing::info;
+use tracing::warn;
+
+use crate::models::blockchain::transaction::Transaction;
+use crate::models::channel::MainToPeerTask;
+use crate::models::state::coinjoin::CoinJoinError;
+use crate::models::state::GlobalStateLock;
+
+/// Merge the contributions of a session and insert the result into the
+/// mempool, or tell the contributors that the session failed.
+pub(super) async fn merge_session(
+    session_id: u64,
+    contributors: Vec<SocketAddr>,
+    transactions: Vec<Transaction>,
+    mut global_state_lock: GlobalStateLock,
+    main_to_peer_channel: broadcast::Sender<MainToPeerTask>,
+) {
+    let priority = global_state_lock.wait_if_busy();
+    let mut transactions = transactions.into_iter();
+    let mut merged = transactions.next();
+    for transaction in transactions {
+        let Some(left) = merged else {
+            break;
+        };
+        merged = match left
+            .merge_with(transaction, rand::random(), &priority)
+            .await
+        {
+            Ok(transaction) => Some(transaction),
+            Err(err) => {
+                warn!("Could not merge coinjoin session {session_id}: {err}");
+                None
+            }
+        };
+    }
+
+    if let Some(merged) = merged {
+        let mut global_state = global_state_lock.lock_guard_mut().await;
+        let tip_mutator_set_hash = global_state
+            .chain
+            .light_state()
+            .body()
+            .mutator_set_accumulator
+            .hash();
+        if merged.kernel.mutator_set_hash == tip_mutator_set_hash {
+            info!(
+                "Merged {} contributions to coinjoin session {session_id} into transaction {}",
+                contributors.len(),
+                merged.kernel.txid()
+            );
+            let notification = (&merged).try_into().unwrap();
+            let _ =
+                main_to_peer_channel.send(MainToPeerTask::TransactionNotification(notification));
+            global_state.mempool_insert(merged).await;
+            return;
+        }
+
+        warn!("Coinjoin session {session_id} was outdated by a new block while merging");
+    }
+
+    let _ = main_to_peer_channel.send(MainToPeerTask::CoinJoinStatus {
+        contributors,
+        session_id,
+        result: Err(CoinJoinError::SessionFailed),
+    });
+}
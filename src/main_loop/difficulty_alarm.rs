@@ -0,0 +1,178 @@
+//! Detection of difficulty trajectories that make a chain unusable, for
+//! operators of private networks and regtest setups.
+//!
+//! The difficulty control adjusts by at most a few percent per block, and
+//! never below the minimum difficulty. With a hash rate far from what the
+//! genesis difficulty and target block interval assume, it silently produces
+//! a chain whose blocks come either at the minimum block time for a long
+//! stretch, or much slower than the target at minimum difficulty. Neither
+//! resolves quickly, so the main loop warns with the likely fix.
+
+use std::fmt::Display;
+
+use crate::models::blockchain::block::block_header::BlockHeader;
+use crate::models::blockchain::block::block_header::MINIMUM_BLOCK_TIME;
+use crate::models::blockchain::block::block_header::TARGET_BLOCK_INTERVAL;
+use crate::models::blockchain::block::difficulty_control::Difficulty;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+
+/// Number of most recent blocks whose difficulties are assessed.
+pub(super) const DIFFICULTY_ALARM_WINDOW: usize = 16;
+
+/// Largest relative increase of the difficulty from one block to the next.
+const MAX_DIFFICULTY_INCREASE_PER_BLOCK: f64 = 1.0 / 16.0;
+
+/// A pathological difficulty trajectory over the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum DifficultyTrajectory {
+    /// Difficulty at the minimum, with blocks much slower than the target
+    PinnedAtMinimum { average_block_time: Timestamp },
+
+    /// Difficulty rising with every block, with blocks close to the minimum
+    /// block time
+    Runaway { average_block_time: Timestamp },
+}
+
+impl DifficultyTrajectory {
+    /// Assess the headers of consecutive blocks, oldest first.
+    fn assess(headers: &[BlockHeader]) -> Option<Self> {
+        if headers.len() < DIFFICULTY_ALARM_WINDOW {
+            return None;
+        }
+
+        let (first, last) = (headers.first().unwrap(), headers.last().unwrap());
+        if last.timestamp <= first.timestamp {
+            return None;
+        }
+        let num_intervals = (headers.len() - 1) as u64;
+        let average_block_time =
+            Timestamp::millis((last.timestamp - first.timestamp).to_millis() / num_intervals);
+
+        let pinned_at_minimum = headers
+            .iter()
+            .all(|header| header.difficulty == Difficulty::MINIMUM);
+        if pinned_at_minimum && average_block_time > TARGET_BLOCK_INTERVAL * 2 {
+            return Some(Self::PinnedAtMinimum { average_block_time });
+        }
+
+        let rising = headers
+            .windows(2)
+            .all(|pair| pair[1].difficulty > pair[0].difficulty);
+        if rising && average_block_time < MINIMUM_BLOCK_TIME * 2 {
+            return Some(Self::Runaway { average_block_time });
+        }
+
+        None
+    }
+
+    /// Number of blocks it takes the difficulty to bring the block time up to
+    /// the target, assuming the hash rate stays constant.
+    fn blocks_to_converge(average_block_time: Timestamp) -> u64 {
+        let ratio =
+            TARGET_BLOCK_INTERVAL.to_millis() as f64 / average_block_time.to_millis().max(1) as f64;
+        (ratio.ln() / MAX_DIFFICULTY_INCREASE_PER_BLOCK.ln_1p()).ceil() as u64
+    }
+}
+
+impl Display for DifficultyTrajectory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let target_seconds = TARGET_BLOCK_INTERVAL.to_millis() / 1000;
+        match *self {
+            DifficultyTrajectory::PinnedAtMinimum { average_block_time } => write!(
+                f,
+                "Difficulty has been at its minimum for the last {DIFFICULTY_ALARM_WINDOW} \
+                blocks, which came {} seconds apart on average against a target of \
+                {target_seconds} seconds. The hash rate of this network is too low for the target \
+                block interval. Mine with --unrestricted-mining, since restricted mining pauses \
+                between guesses, or add miners.",
+                average_block_time.to_millis() / 1000,
+            ),
+            DifficultyTrajectory::Runaway { average_block_time } => write!(
+                f,
+                "Difficulty has risen with each of the last {DIFFICULTY_ALARM_WINDOW} blocks, \
+                which came {} seconds apart on average against a target of {target_seconds} \
+                seconds. The hash rate of this network far exceeds what the genesis difficulty \
+                assumes, and at the current hash rate it takes about {} more blocks to reach the \
+                target block interval. Mine without --unrestricted-mining, or with fewer miners, \
+                until the difficulty has caught up.",
+                average_block_time.to_millis() / 1000,
+                Self::blocks_to_converge(average_block_time),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct DifficultyAlarm {
+    /// The trajectory last reported, to report each trajectory once
+    reported: Option<DifficultyTrajectory>,
+}
+
+impl DifficultyAlarm {
+    /// Check the headers of the most recent blocks, oldest first. Returns the
+    /// trajectory if it is pathological and was not reported before.
+    pub(super) fn check(&mut self, headers: &[BlockHeader]) -> Option<DifficultyTrajectory> {
+        let trajectory = DifficultyTrajectory::assess(headers);
+        let kind = |trajectory: &Option<DifficultyTrajectory>| {
+            trajectory.as_ref().map(std::mem::discriminant)
+        };
+        let is_new = kind(&trajectory) != kind(&self.reported);
+        self.reported = trajectory;
+
+        trajectory.filter(|_| is_new)
+    }
+}
+
+#[cfg(test)]
+mod difficulty_alarm_tests {
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::blockchain::block::Block;
+
+    fn headers_with(
+        block_time: Timestamp,
+        difficulty: impl Fn(usize) -> Difficulty,
+    ) -> Vec<BlockHeader> {
+        let genesis = Block::genesis_block(Network::RegTest).header().clone();
+        (0..DIFFICULTY_ALARM_WINDOW)
+            .map(|i| BlockHeader {
+                timestamp: genesis.timestamp + block_time * i,
+                difficulty: difficulty(i),
+                ..genesis.clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pathological_trajectories_are_reported_once() {
+        let mut alarm = DifficultyAlarm::default();
+
+        let pinned = headers_with(TARGET_BLOCK_INTERVAL * 3, |_| Difficulty::MINIMUM);
+        assert!(matches!(
+            alarm.check(&pinned),
+            Some(DifficultyTrajectory::PinnedAtMinimum { .. })
+        ));
+        assert_eq!(None, alarm.check(&pinned));
+
+        let runaway = headers_with(MINIMUM_BLOCK_TIME, |i| {
+            Difficulty::from(1000u32 * (i as u32 + 1))
+        });
+        let Some(DifficultyTrajectory::Runaway { average_block_time }) = alarm.check(&runaway)
+        else {
+            panic!("rising difficulty at minimum block time must be reported");
+        };
+        assert_eq!(MINIMUM_BLOCK_TIME, average_block_time);
+        assert_eq!(
+            38,
+            DifficultyTrajectory::blocks_to_converge(average_block_time)
+        );
+
+        // healthy: on target
+        let healthy = headers_with(TARGET_BLOCK_INTERVAL, |_| Difficulty::from(5000u32));
+        assert_eq!(None, alarm.check(&healthy));
+        assert!(alarm.check(&pinned).is_some());
+
+        // too few blocks to tell
+        assert_eq!(None, DifficultyTrajectory::assess(&pinned[1..]));
+    }
+}
@@ -11,6 +11,7 @@ use tracing::error;
 use tracing::info;
 use tracing::warn;
 
+use super::tx_diffusion::relay_own_transaction;
 use crate::models::blockchain::block::mutator_set_update::MutatorSetUpdate;
 use crate::models::blockchain::transaction::primitive_witness::PrimitiveWitness;
 use crate::models::blockchain::transaction::transaction_kernel::TransactionKernel;
@@ -148,11 +149,13 @@ impl UpgradeJob {
     }
 
     /// Upgrade transaction proofs, inserts upgraded tx into the mempool and
-    /// informs peers of this new transaction.
+    /// informs peers of this new transaction. Transactions initiated by this
+    /// node are announced according to the diffusion policy.
     pub(super) async fn handle_upgrade(
         self,
         priority: TritonProverSync,
         perform_ms_update_if_needed: bool,
+        is_own_transaction: bool,
         mut global_state_lock: GlobalStateLock,
         main_to_peer_channel: tokio::sync::broadcast::Sender<MainToPeerTask>,
     ) {
@@ -200,11 +203,18 @@ impl UpgradeJob {
                 // Happy path
 
                 // Inform all peers about our hard work
-                main_to_peer_channel
-                    .send(MainToPeerTask::TransactionNotification(
-                        (&upgraded).try_into().unwrap(),
-                    ))
-                    .unwrap();
+                let notification = (&upgraded).try_into().unwrap();
+                if is_own_transaction {
+                    relay_own_transaction(
+                        notification,
+                        global_state_lock.clone(),
+                        main_to_peer_channel,
+                    );
+                } else {
+                    main_to_peer_channel
+                        .send(MainToPeerTask::TransactionNotification(notification))
+                        .unwrap();
+                }
 
                 global_state.mempool_insert(upgraded).await;
 
@@ -0,0 +1,77 @@
+//! Pairing of peers asking this node for an introduction to another node that
+//! cannot accept incoming connections, such that they can punch holes in their
+//! NATs and connect to each other directly.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+use std::time::SystemTime;
+
+/// How long a rendezvous request waits for a second request to pair with.
+const RENDEZVOUS_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default)]
+pub(super) struct RendezvousState {
+    waiting: Option<(SocketAddr, SystemTime)>,
+}
+
+impl RendezvousState {
+    /// Register a rendezvous request. Once two distinct peers have requested a
+    /// rendezvous within the timeout, returns them as (initiator, other).
+    pub(super) fn request(
+        &mut self,
+        requester: SocketAddr,
+        now: SystemTime,
+    ) -> Option<(SocketAddr, SocketAddr)> {
+        match self.waiting.take() {
+            Some((waiting, requested_at))
+                if waiting != requester
+                    && now
+                        .duration_since(requested_at)
+                        .is_ok_and(|age| age < RENDEZVOUS_REQUEST_TIMEOUT) =>
+            {
+                Some((waiting, requester))
+            }
+            _ => {
+                self.waiting = Some((requester, now));
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod rendezvous_tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn two_distinct_requesters_are_paired() {
+        let a = SocketAddr::from_str("1.1.1.1:9798").unwrap();
+        let b = SocketAddr::from_str("2.2.2.2:9798").unwrap();
+        let now = SystemTime::now();
+        let mut rendezvous = RendezvousState::default();
+
+        assert!(rendezvous.request(a, now).is_none());
+        assert!(rendezvous.request(a, now).is_none());
+        assert_eq!(Some((a, b)), rendezvous.request(b, now));
+        assert!(rendezvous.waiting.is_none());
+    }
+
+    #[test]
+    fn stale_requests_are_not_paired() {
+        let a = SocketAddr::from_str("1.1.1.1:9798").unwrap();
+        let b = SocketAddr::from_str("2.2.2.2:9798").unwrap();
+        let now = SystemTime::now();
+        let mut rendezvous = RendezvousState::default();
+
+        assert!(rendezvous.request(a, now).is_none());
+        assert!(rendezvous
+            .request(b, now + RENDEZVOUS_REQUEST_TIMEOUT)
+            .is_none());
+        assert_eq!(
+            Some((b, a)),
+            rendezvous.request(a, now + RENDEZVOUS_REQUEST_TIMEOUT)
+        );
+    }
+}
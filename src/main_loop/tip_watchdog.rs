@@ -0,0 +1,125 @@
+//! Detection of a tip that stopped advancing although peers claim to have more
+//! proof-of-work, which means that synchronization is stuck, for instance on
+//! unresponsive peers.
+
+use std::time::Duration;
+use std::time::SystemTime;
+
+use tasm_lib::triton_vm::prelude::Digest;
+
+use crate::config_models::network_params::NetworkParams;
+use crate::models::blockchain::block::block_header::BlockHeader;
+use crate::models::blockchain::block::difficulty_control::ProofOfWork;
+
+/// The tip is stale once it has been the tip, and its timestamp has been in
+/// the past, for this many target block intervals.
+const STALE_TIP_TARGET_INTERVALS: u32 = 6;
+
+/// Outcome of a watchdog check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum TipCheck {
+    Fresh,
+
+    /// Stale for the first time since it became the tip
+    NewlyStale {
+        age: Duration,
+    },
+
+    /// Stale, and reported as such before
+    StillStale,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct TipWatchdog {
+    /// The tip last seen, when it was first seen, and whether it was reported
+    /// as stale.
+    tip: Option<(Digest, SystemTime, bool)>,
+}
+
+impl TipWatchdog {
+    fn stale_after() -> Duration {
+        Duration::from_millis(
+            NetworkParams::in_effect()
+                .target_block_interval()
+                .to_millis(),
+        ) * STALE_TIP_TARGET_INTERVALS
+    }
+
+    /// Check the tip, given the highest proof-of-work claimed by any peer.
+    pub(super) fn check(
+        &mut self,
+        tip_digest: Digest,
+        tip_header: &BlockHeader,
+        max_claimed_pow: Option<ProofOfWork>,
+        now: SystemTime,
+    ) -> TipCheck {
+        if self.tip.map(|(digest, _, _)| digest) != Some(tip_digest) {
+            self.tip = Some((tip_digest, now, false));
+        }
+        let (_, seen_since, reported) = self.tip.as_mut().unwrap();
+
+        let tip_timestamp =
+            SystemTime::UNIX_EPOCH + Duration::from_millis(tip_header.timestamp.to_millis());
+        let age = now.duration_since(tip_timestamp).unwrap_or_default();
+        let unchanged_for = now.duration_since(*seen_since).unwrap_or_default();
+        let peers_ahead =
+            max_claimed_pow.is_some_and(|pow| pow > tip_header.cumulative_proof_of_work);
+        if !peers_ahead || age < Self::stale_after() || unchanged_for < Self::stale_after() {
+            return TipCheck::Fresh;
+        }
+
+        if std::mem::replace(reported, true) {
+            TipCheck::StillStale
+        } else {
+            TipCheck::NewlyStale { age }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tip_watchdog_tests {
+    use rand::random;
+
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::blockchain::block::Block;
+
+    #[test]
+    fn tip_is_stale_only_if_old_unchanged_and_behind_peers() {
+        let genesis = Block::genesis_block(Network::RegTest);
+        let header = genesis.header();
+        let tip_time = SystemTime::UNIX_EPOCH + Duration::from_millis(header.timestamp.to_millis());
+        let more_pow = Some(ProofOfWork::MAXIMUM);
+        let mut watchdog = TipWatchdog::default();
+
+        // A tip observed just now is not stale, however old it is
+        let now = tip_time + 2 * TipWatchdog::stale_after();
+        assert_eq!(
+            TipCheck::Fresh,
+            watchdog.check(genesis.hash(), header, more_pow, now)
+        );
+
+        // Once it remained the tip for long enough, it is
+        let later = now + TipWatchdog::stale_after();
+        assert!(matches!(
+            watchdog.check(genesis.hash(), header, more_pow, later),
+            TipCheck::NewlyStale { .. }
+        ));
+        assert_eq!(
+            TipCheck::StillStale,
+            watchdog.check(genesis.hash(), header, more_pow, later)
+        );
+
+        // Not if no peer claims more proof-of-work
+        assert_eq!(
+            TipCheck::Fresh,
+            watchdog.check(genesis.hash(), header, None, later)
+        );
+
+        // A new tip resets the watchdog
+        assert_eq!(
+            TipCheck::Fresh,
+            watchdog.check(random(), header, more_pow, later)
+        );
+    }
+}
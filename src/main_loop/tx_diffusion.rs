@@ -0,0 +1,197 @@
+//! Diffusion-style relay of transactions initiated by this node.
+//!
+//! If every node announced its own transactions to all of its peers the
+//! instant they are created, an observer connected to many nodes could infer
+//! the origin of a transaction from which node announced it first. To make
+//! this harder, own transactions are first held back for a random delay, then
+//! announced to a small random subset of peers, and only after another random
+//! delay announced to everyone. By then the transaction has usually been
+//! relayed by other nodes as well.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use rand::seq::IteratorRandom;
+use rand::Rng;
+use tokio::sync::broadcast;
+use tracing::debug;
+use tracing::warn;
+
+use crate::config_models::cli_args::Args;
+use crate::config_models::network::Network;
+use crate::models::channel::MainToPeerTask;
+use crate::models::peer::transaction_notification::TransactionNotification;
+use crate::models::state::GlobalStateLock;
+
+/// Parameters of the diffusion relay of own transactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TxDiffusionPolicy {
+    /// Upper bound of each of the two random delays.
+    max_delay: Duration,
+
+    /// Number of peers that are informed before all others.
+    initial_peer_count: usize,
+}
+
+impl TxDiffusionPolicy {
+    /// The policy configured on the command line, or `None` if own
+    /// transactions should be announced to all peers immediately.
+    ///
+    /// Diffusion is always disabled on regtest, where it would only slow down
+    /// tests.
+    pub(crate) fn from_cli(cli: &Args) -> Option<Self> {
+        if cli.network == Network::RegTest || cli.tx_diffusion_max_delay == 0 {
+            return None;
+        }
+
+        Some(Self {
+            max_delay: Duration::from_secs(cli.tx_diffusion_max_delay),
+            initial_peer_count: cli.tx_diffusion_initial_peers,
+        })
+    }
+
+    fn random_delay<R: Rng>(&self, rng: &mut R) -> Duration {
+        rng.gen_range(Duration::ZERO..=self.max_delay)
+    }
+
+    /// Choose the peers to inform first, uniformly at random.
+    fn initial_peers<R: Rng>(
+        &self,
+        connected_peers: impl IntoIterator<Item = SocketAddr>,
+        rng: &mut R,
+    ) -> Vec<SocketAddr> {
+        connected_peers
+            .into_iter()
+            .choose_multiple(rng, self.initial_peer_count)
+    }
+}
+
+/// Announce a transaction that was initiated by this node to peers, according
+/// to the configured [`TxDiffusionPolicy`].
+///
+/// Does not block: if a delay applies, announcing happens in a spawned task.
+pub(super) fn relay_own_transaction(
+    notification: TransactionNotification,
+    global_state_lock: GlobalStateLock,
+    main_to_peer_broadcast_tx: broadcast::Sender<MainToPeerTask>,
+) {
+    let Some(policy) = TxDiffusionPolicy::from_cli(global_state_lock.cli()) else {
+        if main_to_peer_broadcast_tx
+            .send(MainToPeerTask::TransactionNotification(notification))
+            .is_err()
+        {
+            warn!("Could not announce own transaction: no peer tasks are listening");
+        }
+        return;
+    };
+
+    let spawn_result = tokio::task::Builder::new()
+        .name("tx_diffusion")
+        .spawn(async move {
+            let initial_delay = policy.random_delay(&mut rand::thread_rng());
+            tokio::time::sleep(initial_delay).await;
+
+            let connected_peers = global_state_lock
+                .lock_guard()
+                .await
+                .net
+                .peer_map
+                .keys()
+                .copied()
+                .collect::<Vec<_>>();
+            let initial_peers = policy.initial_peers(connected_peers, &mut rand::thread_rng());
+            debug!(
+                "Announcing own transaction to {} initial peers after {initial_delay:?}",
+                initial_peers.len()
+            );
+            let _ = main_to_peer_broadcast_tx.send(MainToPeerTask::TransactionNotificationToPeers(
+                notification,
+                initial_peers,
+            ));
+
+            let relay_delay = policy.random_delay(&mut rand::thread_rng());
+            tokio::time::sleep(relay_delay).await;
+            debug!("Announcing own transaction to all peers after {relay_delay:?}");
+            let _ = main_to_peer_broadcast_tx
+                .send(MainToPeerTask::TransactionNotification(notification));
+        });
+
+    if let Err(err) = spawn_result {
+        warn!("Could not spawn task to announce own transaction: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tx_diffusion_tests {
+    use std::collections::HashSet;
+    use std::net::IpAddr;
+    use std::net::Ipv4Addr;
+
+    use rand::thread_rng;
+
+    use super::*;
+
+    fn peers(count: u16) -> Vec<SocketAddr> {
+        (0..count)
+            .map(|i| SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9798 + i))
+            .collect()
+    }
+
+    #[test]
+    fn diffusion_is_disabled_on_regtest() {
+        let cli = Args {
+            network: Network::RegTest,
+            ..Default::default()
+        };
+        assert!(TxDiffusionPolicy::from_cli(&cli).is_none());
+    }
+
+    #[test]
+    fn diffusion_is_disabled_by_zero_delay() {
+        let cli = Args {
+            tx_diffusion_max_delay: 0,
+            ..Default::default()
+        };
+        assert!(TxDiffusionPolicy::from_cli(&cli).is_none());
+    }
+
+    #[test]
+    fn diffusion_is_enabled_by_default() {
+        assert!(TxDiffusionPolicy::from_cli(&Args::default()).is_some());
+    }
+
+    #[test]
+    fn delay_is_bounded() {
+        let policy = TxDiffusionPolicy::from_cli(&Args::default()).unwrap();
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            assert!(policy.random_delay(&mut rng) <= policy.max_delay);
+        }
+    }
+
+    #[test]
+    fn initial_peers_are_distinct_subset() {
+        let policy = TxDiffusionPolicy {
+            max_delay: Duration::from_secs(1),
+            initial_peer_count: 3,
+        };
+        let mut rng = thread_rng();
+        let connected = peers(10);
+        let initial = policy.initial_peers(connected.clone(), &mut rng);
+
+        assert_eq!(3, initial.len());
+        assert_eq!(3, initial.iter().collect::<HashSet<_>>().len());
+        assert!(initial.iter().all(|peer| connected.contains(peer)));
+    }
+
+    #[test]
+    fn initial_peers_with_few_connections() {
+        let policy = TxDiffusionPolicy {
+            max_delay: Duration::from_secs(1),
+            initial_peer_count: 3,
+        };
+        let mut rng = thread_rng();
+        assert_eq!(2, policy.initial_peers(peers(2), &mut rng).len());
+        assert!(policy.initial_peers(peers(0), &mut rng).is_empty());
+    }
+}
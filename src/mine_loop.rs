@@ -1,22 +1,34 @@
+pub mod pow_solver;
+
+use std::collections::HashSet;
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use futures::channel::oneshot;
+use itertools::Itertools;
 use num_traits::identities::Zero;
+use pow_solver::next_solution;
+use pow_solver::CpuSolver;
+use pow_solver::LoopbackSolver;
+use pow_solver::PowJob;
+use pow_solver::PowSolver;
 use rand::rngs::StdRng;
-use rand::thread_rng;
 use rand::Rng;
 use rand::SeedableRng;
 use tokio::select;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::mpsc;
 use tokio::sync::watch;
-use tokio::task::JoinHandle;
 use tracing::*;
 use transaction_output::TxOutput;
 use twenty_first::math::digest::Digest;
 
+use crate::config_models::data_directory::DataDirectory;
+use crate::config_models::network_params::NetworkParams;
 use crate::models::blockchain::block::block_height::BlockHeight;
 use crate::models::blockchain::block::difficulty_control::difficulty_control;
 use crate::models::blockchain::block::*;
@@ -24,8 +36,16 @@ use crate::models::blockchain::transaction::*;
 use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
 use crate::models::channel::*;
 use crate::models::proof_abstractions::timestamp::Timestamp;
-use crate::models::shared::SIZE_20MB_IN_BYTES;
+use crate::models::state::block_packing::BlockPacker;
+use crate::models::state::block_packing::PackingCandidate;
+use crate::models::state::block_proposal::set_solution;
+use crate::models::state::block_proposal::BlockProposal;
+use crate::models::state::mining_checkpoint::MiningCheckpoint;
+use crate::models::state::mining_checkpoint::NonceSequence;
+use crate::models::state::mining_log::BlockTemplateInfo;
+use crate::models::state::node_event::NodeEvent;
 use crate::models::state::transaction_details::TransactionDetails;
+use crate::models::state::transaction_kernel_id::TransactionKernelId;
 use crate::models::state::tx_proving_capability::TxProvingCapability;
 use crate::models::state::wallet::expected_utxo::ExpectedUtxo;
 use crate::models::state::wallet::expected_utxo::UtxoNotifier;
@@ -33,49 +53,15 @@ use crate::models::state::GlobalState;
 use crate::models::state::GlobalStateLock;
 use crate::prelude::twenty_first;
 
-/// Attempt to mine a valid block for the network
-#[allow(clippy::too_many_arguments)]
-async fn mine_block(
-    block: Block,
-    previous_block: Block,
-    sender: oneshot::Sender<NewBlockFound>,
-    coinbase_utxo_info: ExpectedUtxo,
-    unrestricted_mining: bool,
-    target_block_interval: Option<Timestamp>,
-) {
-    // We wrap mining loop with spawn_blocking() because it is a
-    // very lengthy and CPU intensive task, which should execute
-    // on its own thread.
-    //
-    // Instead of spawn_blocking(), we could start a native OS
-    // thread which avoids using one from tokio's threadpool
-    // but that doesn't seem a concern for neptune-core.
-    // Also we would need to use a oneshot channel to avoid
-    // blocking while joining the thread.
-    // see: https://ryhl.io/blog/async-what-is-blocking/
-    //
-    // note: there is no async code inside the mining loop.
-    tokio::task::spawn_blocking(move || {
-        mine_block_worker(
-            block,
-            previous_block,
-            sender,
-            coinbase_utxo_info,
-            unrestricted_mining,
-            target_block_interval,
-        )
-    })
-    .await
-    .unwrap()
-}
-
+/// Search nonces for the block template until one gives it proof-of-work, and
+/// send the mined block. Stops when the receiver is dropped.
 fn mine_block_worker(
     mut block: Block,
     previous_block: Block,
-    sender: oneshot::Sender<NewBlockFound>,
-    coinbase_utxo_info: ExpectedUtxo,
+    sender: oneshot::Sender<Block>,
     unrestricted_mining: bool,
     target_block_interval: Option<Timestamp>,
+    nonces: &NonceSequence,
 ) {
     // This must match the rules in `[Block::has_proof_of_work]`.
     let prev_difficulty = previous_block.header().difficulty;
@@ -88,11 +74,6 @@ fn mine_block_worker(
         threshold
     );
 
-    // The RNG used to sample nonces must be thread-safe, which `thread_rng()` is not.
-    // Solution: use `thread_rng()` to generate a seed, and generate a thread-safe RNG
-    // seeded with that seed. The `thread_rng()` object is dropped immediately.
-    let mut rng: StdRng = SeedableRng::from_seed(thread_rng().gen());
-
     // Mining loop
     while !mine_iteration(
         &mut block,
@@ -101,7 +82,7 @@ fn mine_block_worker(
         target_block_interval,
         threshold,
         unrestricted_mining,
-        &mut rng,
+        nonces,
     ) {}
     // If the sender is cancelled, the parent to this thread most
     // likely received a new block, and this thread hasn't been stopped
@@ -117,8 +98,11 @@ fn mine_block_worker(
 
     let nonce = block.kernel.header.nonce;
     info!(
-        "Found valid block with nonce: ({}, {}, {}).",
-        nonce[0], nonce[1], nonce[2]
+        "Found valid block with nonce: ({}, {}, {}), after {} nonces tried on the template.",
+        nonce[0],
+        nonce[1],
+        nonce[2],
+        nonces.progress().num_tried()
     );
 
     let timestamp = block.kernel.header.timestamp;
@@ -137,13 +121,8 @@ Difficulty threshold: {threshold}
 "#
     );
 
-    let new_block_found = NewBlockFound {
-        block: Box::new(block),
-        coinbase_utxo_info: Box::new(coinbase_utxo_info),
-    };
-
     sender
-        .send(new_block_found)
+        .send(block)
         .unwrap_or_else(|_| warn!("Receiver in mining loop closed prematurely"))
 }
 
@@ -154,11 +133,11 @@ Difficulty threshold: {threshold}
 fn mine_iteration(
     block: &mut Block,
     previous_block: &Block,
-    sender: &oneshot::Sender<NewBlockFound>,
+    sender: &oneshot::Sender<Block>,
     target_block_interval: Option<Timestamp>,
     threshold: Digest,
     unrestricted_mining: bool,
-    rng: &mut StdRng,
+    nonces: &NonceSequence,
 ) -> bool {
     if sender.is_canceled() {
         info!(
@@ -170,7 +149,7 @@ fn mine_iteration(
 
     // mutate nonce in the block's header.
     // Block::hash() will subsequently return a new digest.
-    block.set_header_nonce(rng.gen());
+    block.set_header_nonce(nonces.next());
 
     // See issue #149 and test block_timestamp_represents_time_block_found()
     // this ensures header timestamp represents the moment block is found.
@@ -183,6 +162,7 @@ fn mine_iteration(
         previous_block.header().difficulty,
         target_block_interval,
         previous_block.header().height,
+        NetworkParams::in_effect(),
     );
     block.set_header_timestamp_and_difficulty(now, new_difficulty);
 
@@ -200,37 +180,39 @@ pub(crate) async fn make_coinbase_transaction(
     transaction_fees: NeptuneCoins,
     timestamp: Timestamp,
 ) -> Result<(Transaction, ExpectedUtxo)> {
-    // note: it is Ok to always use the same key here because:
-    //  1. if we find a block, the utxo will go to our wallet
-    //     and notification occurs offchain, so there is no privacy issue.
-    //  2. if we were to derive a new addr for each block then we would
-    //     have large gaps since an address only receives funds when
-    //     we actually win the mining lottery.
-    //  3. also this way we do not have to modify global/wallet state.
-
-    let coinbase_recipient_spending_key = global_state_lock
-        .lock_guard()
-        .await
-        .wallet_state
-        .wallet_secret
-        .nth_generation_spending_key(0);
+    // note: by default the same key is used for every block, which is Ok
+    // because the utxo goes to our wallet and notification occurs offchain.
+    // But paying all rewards to one address links all mining income of this
+    // node together, so the key is chosen by the configured rotation policy.
+    // Keys handed out for rotation are persisted by the wallet, such that
+    // rewards remain discoverable after a restore.
+    let (coinbase_recipient_spending_key, latest_block) = {
+        let global_state = global_state_lock.lock_guard().await;
+        if global_state.wallet_state.is_locked() {
+            bail!("Cannot make coinbase transaction: wallet is locked");
+        }
+        let latest_block = global_state.chain.light_state().clone();
+        let spending_key = global_state.wallet_state.coinbase_spending_key(
+            global_state.cli().coinbase_address_rotation,
+            latest_block.header().height.next(),
+        );
+        (spending_key, latest_block)
+    };
     let receiving_address = coinbase_recipient_spending_key.to_address();
-    let latest_block = global_state_lock
-        .lock_guard()
-        .await
-        .chain
-        .light_state()
-        .clone();
     let mutator_set_accumulator = latest_block.body().mutator_set_accumulator.clone();
     let next_block_height: BlockHeight = latest_block.header().height.next();
 
     let coinbase_amount = Block::get_mining_reward(next_block_height) + transaction_fees;
-    let sender_randomness: Digest = global_state_lock
-        .lock_guard()
-        .await
-        .wallet_state
-        .wallet_secret
-        .generate_sender_randomness(next_block_height, receiving_address.privacy_digest);
+    let sender_randomness: Digest = {
+        let global_state = global_state_lock.lock_guard().await;
+        if global_state.wallet_state.is_locked() {
+            bail!("Cannot make coinbase transaction: wallet is locked");
+        }
+        global_state
+            .wallet_state
+            .wallet_secret
+            .generate_sender_randomness(next_block_height, receiving_address.privacy_digest)
+    };
 
     // There is no reason to put coinbase UTXO notifications on chain, because:
     // Both sender randomness and receiver preimage are derived
@@ -279,6 +261,50 @@ pub(crate) async fn make_coinbase_transaction(
     Ok((transaction, utxo_info_for_coinbase))
 }
 
+/// The mempool transactions merged into the block transaction of the most
+/// recent block template.
+///
+/// Merging is by far the most expensive part of building a block template, so
+/// when the next template is built on the same predecessor and includes all
+/// previously merged transactions, only the newly selected transactions are
+/// merged into the cached aggregate. Any other change, a new predecessor or a
+/// transaction that dropped out of the selection, means a full rebuild, as
+/// merged transactions cannot be separated again.
+#[derive(Debug, Default)]
+pub(crate) struct BlockTemplateCache {
+    predecessor: Digest,
+    txids: HashSet<TransactionKernelId>,
+
+    /// All merged mempool transactions, without the coinbase transaction
+    merged: Option<Transaction>,
+}
+
+impl BlockTemplateCache {
+    /// Drop the cached aggregate unless it can be extended into one holding
+    /// exactly the selected transactions for a block on top of `predecessor`.
+    fn invalidate_unless_reusable(
+        &mut self,
+        predecessor: Digest,
+        selection: &HashSet<TransactionKernelId>,
+    ) {
+        if self.predecessor != predecessor || !self.txids.is_subset(selection) {
+            *self = Self {
+                predecessor,
+                ..Default::default()
+            };
+        }
+    }
+
+    fn contains(&self, txid: &TransactionKernelId) -> bool {
+        self.txids.contains(txid)
+    }
+
+    fn insert(&mut self, txid: TransactionKernelId, merged: Transaction) {
+        self.txids.insert(txid);
+        self.merged = Some(merged);
+    }
+}
+
 /// Create the transaction that goes into the block template. The transaction is
 /// built from the mempool and from the coinbase transaction. Also returns the
 /// "sender randomness" used in the coinbase transaction.
@@ -287,14 +313,45 @@ pub(crate) async fn create_block_transaction(
     global_state_lock: &GlobalStateLock,
     timestamp: Timestamp,
 ) -> Result<(Transaction, ExpectedUtxo)> {
-    let block_capacity_for_transactions = SIZE_20MB_IN_BYTES;
+    create_block_transaction_with_cache(
+        predecessor_block,
+        global_state_lock,
+        timestamp,
+        &mut BlockTemplateCache::default(),
+    )
+    .await
+}
 
+/// Like [create_block_transaction], but reuses the mempool transactions merged
+/// for a previous template on the same predecessor, and updates the cache.
+pub(crate) async fn create_block_transaction_with_cache(
+    predecessor_block: &Block,
+    global_state_lock: &GlobalStateLock,
+    timestamp: Timestamp,
+    cache: &mut BlockTemplateCache,
+) -> Result<(Transaction, ExpectedUtxo)> {
     // Get most valuable transactions from mempool
-    let transactions_to_include = global_state_lock
-        .lock_guard()
-        .await
-        .mempool
-        .get_transactions_for_block(block_capacity_for_transactions, None);
+    let packer = BlockPacker::new(
+        predecessor_block,
+        global_state_lock.cli().max_template_mergers,
+    );
+    let transactions_to_include = {
+        let global_state = global_state_lock.lock_guard().await;
+        let mempool = &global_state.mempool;
+        let reusable_cache = cache.predecessor == predecessor_block.hash();
+        let candidates = mempool
+            .get_sorted_iter()
+            .filter_map(|(txid, _)| mempool.get(txid))
+            .map(|tx| {
+                PackingCandidate::new(tx, reusable_cache && cache.contains(&tx.kernel.txid()))
+            })
+            .collect_vec();
+        packer
+            .pack(candidates)
+            .into_iter()
+            .filter_map(|txid| mempool.get(txid).cloned())
+            .collect_vec()
+    };
 
     // Build coinbase UTXO
     let transaction_fees = transactions_to_include
@@ -312,29 +369,90 @@ pub(crate) async fn create_block_transaction(
     let mut rng: StdRng =
         SeedableRng::from_seed(global_state_lock.lock_guard().await.shuffle_seed());
 
-    // Merge incoming transactions with the coinbase transaction
-    let num_transactions_to_include = transactions_to_include.len();
-    let mut block_transaction = coinbase_transaction;
+    let selection = transactions_to_include
+        .iter()
+        .map(|tx| tx.kernel.txid())
+        .collect::<HashSet<_>>();
+    cache.invalidate_unless_reusable(predecessor_block.hash(), &selection);
+    let transactions_to_merge = transactions_to_include
+        .into_iter()
+        .filter(|tx| !cache.contains(&tx.kernel.txid()))
+        .collect_vec();
+    if cache.merged.is_some() {
+        debug!(
+            "Reusing {} merged transactions from previous block template",
+            cache.txids.len()
+        );
+    }
+
+    // Merge the newly selected transactions into the cached ones
+    let num_transactions_to_merge = transactions_to_merge.len();
     let wait_if_busy = global_state_lock.wait_if_busy();
-    for (i, transaction_to_include) in transactions_to_include.into_iter().enumerate() {
+    for (i, transaction_to_include) in transactions_to_merge.into_iter().enumerate() {
         info!(
             "Merging transaction {} / {}",
             i + 1,
-            num_transactions_to_include
+            num_transactions_to_merge
         );
-        block_transaction = Transaction::merge_with(
-            block_transaction,
-            transaction_to_include,
-            rng.gen(),
-            &wait_if_busy,
-        )
-        .await
-        .expect("Must be able to merge transactions in mining context");
+        let txid = transaction_to_include.kernel.txid();
+        let merged = match cache.merged.take() {
+            Some(merged) => {
+                Transaction::merge_with(merged, transaction_to_include, rng.gen(), &wait_if_busy)
+                    .await
+                    .expect("Must be able to merge transactions in mining context")
+            }
+            None => transaction_to_include,
+        };
+        cache.insert(txid, merged);
     }
 
+    // Merge the coinbase transaction, which depends on the fees, last
+    let block_transaction = match cache.merged.clone() {
+        Some(merged) => {
+            Transaction::merge_with(coinbase_transaction, merged, rng.gen(), &wait_if_busy)
+                .await
+                .expect("Must be able to merge transactions in mining context")
+        }
+        None => coinbase_transaction,
+    };
+
     Ok((block_transaction, coinbase_as_expected_utxo))
 }
 
+/// Wait until a transaction merged into the block template is removed from
+/// the mempool, for instance because it was evicted to make room for
+/// transactions paying more per byte. Returns the transaction's ID, or None if
+/// events were missed, in which case the template may be affected.
+async fn template_transaction_removed(
+    events: &mut broadcast::Receiver<NodeEvent>,
+    template_txids: &HashSet<TransactionKernelId>,
+) -> Option<TransactionKernelId> {
+    loop {
+        match events.recv().await {
+            Ok(NodeEvent::MempoolTransactionRemoved(txid)) if template_txids.contains(&txid) => {
+                return Some(txid)
+            }
+            Ok(_) => continue,
+            Err(RecvError::Lagged(_)) => return None,
+            Err(RecvError::Closed) => std::future::pending().await,
+        }
+    }
+}
+
+/// Mark the node as not mining, and withdraw the block templates published to
+/// external miners.
+///
+/// Locking:
+///   * acquires `global_state_lock` for write
+async fn stop_mining(global_state_lock: &mut GlobalStateLock) {
+    global_state_lock.set_mining(false).await;
+    global_state_lock
+        .lock_guard_mut()
+        .await
+        .block_proposals
+        .clear();
+}
+
 /// Locking:
 ///   * acquires `global_state_lock` for write
 pub async fn mine(
@@ -350,52 +468,172 @@ pub async fn mine(
     tokio::time::sleep(Duration::from_secs(INITIAL_MINING_SLEEP_IN_SECONDS)).await;
 
     let mut pause_mine = false;
+    let mut template_cache = BlockTemplateCache::default();
+    let mut node_events = global_state_lock.lock_guard().await.subscribe_to_events();
+
+    // Resume the template mined on before the last shutdown, if it still
+    // extends the tip and pays this wallet
+    let network = global_state_lock.cli().network;
+    let data_dir =
+        DataDirectory::get(global_state_lock.cli().data_dir.clone(), network)?.root_dir_path();
+    let mut resumable_template =
+        match MiningCheckpoint::take(&data_dir, network, latest_block.hash()) {
+            Ok(checkpoint) => checkpoint,
+            Err(err) => {
+                warn!("Could not read mining checkpoint: {err:#}");
+                None
+            }
+        };
+    if let Some(checkpoint) = &resumable_template {
+        let pays_own_wallet = global_state_lock
+            .lock_guard()
+            .await
+            .wallet_state
+            .can_unlock(&checkpoint.coinbase_utxo_info.utxo);
+        if !pays_own_wallet {
+            info!("Not resuming stored block template, since it pays another wallet");
+            resumable_template = None;
+        }
+    }
+    let mut current_template: Option<(MiningCheckpoint, Arc<NonceSequence>)> = None;
+
+    let mut solver: Box<dyn PowSolver> = match global_state_lock.cli().pow_solver {
+        Some(address) => {
+            info!("Leaving the search for nonces to the external hasher at {address}");
+            Box::new(LoopbackSolver::new(address))
+        }
+        None => {
+            let num_workers = global_state_lock.cli().mining_threads;
+            if num_workers > 1 {
+                info!(
+                    "Mining with {num_workers} workers, each trying nonces no other worker tries"
+                );
+            }
+            Box::new(CpuSolver::new(
+                num_workers,
+                global_state_lock.cli().unrestricted_mining,
+                None, // using default TARGET_BLOCK_INTERVAL
+            ))
+        }
+    };
+
     loop {
-        let (worker_task_tx, worker_task_rx) = oneshot::channel::<NewBlockFound>();
+        // Any search from the previous iteration is for an outdated template
+        solver.cancel();
         let is_syncing = global_state_lock.lock(|s| s.net.syncing).await;
 
-        let miner_task: Option<JoinHandle<()>> = if is_syncing {
+        let mut template_published = false;
+        let solving = if is_syncing {
             info!("Not mining because we are syncing");
-            global_state_lock.set_mining(false).await;
-            None
+            stop_mining(&mut global_state_lock).await;
+            false
         } else if pause_mine {
             info!("Not mining because mining was paused");
-            global_state_lock.set_mining(false).await;
-            None
+            stop_mining(&mut global_state_lock).await;
+            false
+        } else if global_state_lock.lock(|s| s.wallet_state.is_locked()).await {
+            // Mining resumes on the next block after the wallet is unlocked.
+            info!("Not mining because the wallet is locked");
+            stop_mining(&mut global_state_lock).await;
+            false
         } else {
-            // Build the block template and spawn the worker task to mine on it
-            let now = Timestamp::now();
-
-            // TODO: Spawn a task for generating this transaction, such that it
-            // can be aborted on shutdown.
-            let (transaction, coinbase_utxo_info) =
-                create_block_transaction(&latest_block, &global_state_lock, now).await?;
-            let proof_sync = global_state_lock.wait_if_busy();
-            let block_template =
-                Block::make_block_template(&latest_block, transaction, now, None, &proof_sync)
+            // Resume the stored block template, or build one, and hand it to
+            // the solver to search a nonce for
+            let resumed = resumable_template.take().filter(|checkpoint| {
+                checkpoint.template.header().prev_block_digest == latest_block.hash()
+            });
+            let checkpoint = match resumed {
+                Some(checkpoint) => {
+                    info!(
+                        "Resuming stored block template for height {}, after {} nonces tried",
+                        checkpoint.template.header().height,
+                        checkpoint.nonce_progress.num_tried()
+                    );
+                    checkpoint
+                }
+                None => {
+                    let now = Timestamp::now();
+
+                    // TODO: Spawn a task for generating this transaction, such that it
+                    // can be aborted on shutdown.
+                    let (transaction, coinbase_utxo_info) = create_block_transaction_with_cache(
+                        &latest_block,
+                        &global_state_lock,
+                        now,
+                        &mut template_cache,
+                    )
+                    .await?;
+                    let proof_sync = global_state_lock.wait_if_busy();
+                    let block_template = Block::make_block_template(
+                        &latest_block,
+                        transaction,
+                        now,
+                        None,
+                        &proof_sync,
+                    )
                     .await;
-            let block_template = match block_template {
-                Ok(template) => template,
-                Err(_) => bail!("Miner failed to generate block template"),
+                    let block_template = match block_template {
+                        Ok(template) => template,
+                        Err(_) => bail!("Miner failed to generate block template"),
+                    };
+
+                    let checkpoint = MiningCheckpoint {
+                        network,
+                        template: block_template,
+                        coinbase_utxo_info,
+                        num_transactions: template_cache.txids.len(),
+                        nonce_progress: NonceSequence::random().progress(),
+                    };
+                    if let Err(err) = checkpoint.store(&data_dir) {
+                        warn!("Could not store mining checkpoint: {err:#}");
+                    }
+                    checkpoint
+                }
             };
-            let miner_task = mine_block(
-                block_template,
-                latest_block.clone(),
-                worker_task_tx,
-                coinbase_utxo_info,
-                global_state_lock.cli().unrestricted_mining,
-                None, // using default TARGET_BLOCK_INTERVAL
-            );
-            global_state_lock.set_mining(true).await;
-            Some(
-                tokio::task::Builder::new()
-                    .name("mine_block")
-                    .spawn(miner_task)?,
-            )
+
+            if global_state_lock.cli().external_mining {
+                // Leave the search for a nonce to external miners
+                let proposal = BlockProposal {
+                    template: checkpoint.template.clone(),
+                    coinbase_utxo_info: checkpoint.coinbase_utxo_info.clone(),
+                    info: BlockTemplateInfo {
+                        created: checkpoint.template.header().timestamp,
+                        num_transactions: checkpoint.num_transactions,
+                    },
+                };
+                let proposal_id = global_state_lock
+                    .lock_guard_mut()
+                    .await
+                    .block_proposals
+                    .publish(proposal);
+                let height = checkpoint.template.header().height;
+                info!("Published block template for height {height} as proposal {proposal_id}");
+                global_state_lock.set_mining(true).await;
+                template_published = true;
+                false
+            } else {
+                let nonces = Arc::new(NonceSequence::resume(checkpoint.nonce_progress));
+                solver.set_template(PowJob {
+                    template: checkpoint.template.clone(),
+                    predecessor: latest_block.clone(),
+                    nonces: nonces.clone(),
+                });
+                current_template = Some((checkpoint, nonces));
+                global_state_lock.set_mining(true).await;
+                true
+            }
         };
 
-        // Await a message from either the worker task or from the main loop
+        // Await a solution from the solver or a message from the main loop,
+        // or a change to the mempool that invalidates the block template
         select! {
+            removed = template_transaction_removed(&mut node_events, &template_cache.txids), if solving || template_published => {
+                match removed {
+                    Some(txid) => info!("Transaction {txid} was removed from the mempool. Rebuilding block template."),
+                    None => info!("Missed mempool events. Rebuilding block template."),
+                }
+                solver.cancel();
+            }
             changed = from_main.changed() => {
                 info!("Mining task got message from main");
                 if let e@Err(_) = changed {
@@ -409,17 +647,24 @@ pub async fn mine(
                     MainToMiner::Shutdown => {
                         debug!("Miner shutting down.");
 
-                        if let Some(mt) = miner_task {
-                            mt.abort();
-                            debug!("Abort-signal sent to mining worker.");
+                        if solving {
+                            solver.cancel();
+                            debug!("Search for a nonce cancelled.");
+
+                            // Keep the template and the nonces tried on it
+                            // for the next start
+                            if let Some((mut checkpoint, nonces)) = current_template.take() {
+                                checkpoint.nonce_progress = nonces.progress();
+                                if let Err(err) = checkpoint.store(&data_dir) {
+                                    warn!("Could not store mining checkpoint: {err:#}");
+                                }
+                            }
                         }
 
                         break;
                     }
                     MainToMiner::NewBlock(block) => {
-                        if let Some(mt) = miner_task {
-                            mt.abort();
-                        }
+                        solver.cancel();
                         latest_block = *block;
                         info!("Miner task received {} block height {}", global_state_lock.lock(|s| s.cli().network).await, latest_block.kernel.header.height);
                     }
@@ -428,10 +673,8 @@ pub async fn mine(
                     MainToMiner::StopMining => {
                         pause_mine = true;
 
-                        if let Some(mt) = miner_task {
-                            mt.abort();
-                            debug!("Abort-signal sent to mining worker.");
-                        }
+                        solver.cancel();
+                        debug!("Search for a nonce cancelled.");
                     }
                     MainToMiner::StartMining => {
                         pause_mine = false;
@@ -447,22 +690,26 @@ pub async fn mine(
                         // variable, because it reflects the logical on/off
                         // of mining, which syncing can temporarily override
                         // but not alter the setting.
-                        if let Some(mt) = miner_task {
-                            mt.abort();
-                        }
+                        solver.cancel();
                     }
                 }
             }
-            new_block_res = worker_task_rx => {
-                let new_block_found = match new_block_res {
-                    Ok(res) => res,
-                    Err(err) => {
-                        warn!("Mining task was cancelled prematurely. Got: {}", err);
-                        continue;
-                    }
+            solution = next_solution(solver.as_mut()), if solving => {
+                let (checkpoint, _) = current_template
+                    .as_ref()
+                    .expect("solver has a template to solve");
+                let mut block = checkpoint.template.clone();
+                set_solution(&mut block, latest_block.header(), solution.nonce, solution.timestamp);
+                let new_block_found = NewBlockFound {
+                    block: Box::new(block),
+                    coinbase_utxo_info: Box::new(checkpoint.coinbase_utxo_info.clone()),
+                    template: BlockTemplateInfo {
+                        created: checkpoint.template.header().timestamp,
+                        num_transactions: checkpoint.num_transactions,
+                    },
                 };
 
-                debug!("Worker task reports new block of height {}", new_block_found.block.kernel.header.height);
+                debug!("Solver reports new block of height {}", new_block_found.block.kernel.header.height);
 
                 // Sanity check, remove for more efficient mining.
                 // The below PoW check could fail due to race conditions. So we don't panic,
@@ -516,6 +763,7 @@ pub(crate) mod mine_loop_tests {
     use difficulty_control::Difficulty;
     use num_bigint::BigUint;
     use num_traits::Pow;
+    use rand::thread_rng;
     use tracing_test::traced_test;
     use transaction_output::TxOutput;
     use transaction_output::UtxoNotificationMedium;
@@ -551,7 +799,7 @@ pub(crate) mod mine_loop_tests {
         target_block_interval: Option<Timestamp>,
         unrestricted_mining: bool,
     ) -> f64 {
-        let mut rng: StdRng = SeedableRng::from_rng(thread_rng()).unwrap();
+        let nonces = NonceSequence::random();
         let network = Network::RegTest;
         let global_state_lock =
             mock_genesis_global_state(network, 2, WalletSecret::devnet_wallet()).await;
@@ -574,7 +822,7 @@ pub(crate) mod mine_loop_tests {
         );
         let threshold = previous_block.header().difficulty.target();
 
-        let (worker_task_tx, _worker_task_rx) = oneshot::channel::<NewBlockFound>();
+        let (worker_task_tx, _worker_task_rx) = oneshot::channel::<Block>();
 
         let num_iterations = 10000;
         let tick = std::time::SystemTime::now();
@@ -586,7 +834,7 @@ pub(crate) mod mine_loop_tests {
                 target_block_interval,
                 threshold,
                 unrestricted_mining,
-                &mut rng,
+                &nonces,
             );
         }
         let time_spent_mining = tick.elapsed().unwrap();
@@ -619,6 +867,34 @@ pub(crate) mod mine_loop_tests {
         tock
     }
 
+    #[test]
+    fn block_template_cache_is_reused_only_for_supersets_on_same_predecessor() {
+        let predecessor: Digest = rand::random();
+        let txids = (0..3)
+            .map(|_| random_transaction_kernel().txid())
+            .collect_vec();
+        let mut cache = BlockTemplateCache {
+            predecessor,
+            txids: txids[..2].iter().copied().collect(),
+            merged: None,
+        };
+
+        // More transactions selected: cached ones are kept
+        cache.invalidate_unless_reusable(predecessor, &txids.iter().copied().collect());
+        assert!(cache.contains(&txids[0]) && cache.contains(&txids[1]));
+
+        // A cached transaction dropped out of the selection
+        cache.invalidate_unless_reusable(predecessor, &txids[1..].iter().copied().collect());
+        assert!(cache.txids.is_empty());
+
+        // New predecessor
+        cache.txids = txids.iter().copied().collect();
+        let new_predecessor: Digest = rand::random();
+        cache.invalidate_unless_reusable(new_predecessor, &txids.iter().copied().collect());
+        assert!(cache.txids.is_empty());
+        assert_eq!(new_predecessor, cache.predecessor);
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn block_template_is_valid_test() {
@@ -768,9 +1044,9 @@ pub(crate) mod mine_loop_tests {
             mock_genesis_global_state(network, 2, WalletSecret::devnet_wallet()).await;
         let tip_block_orig = Block::genesis_block(network);
         let launch_date = tip_block_orig.header().timestamp;
-        let (worker_task_tx, worker_task_rx) = oneshot::channel::<NewBlockFound>();
+        let (worker_task_tx, worker_task_rx) = oneshot::channel::<Block>();
 
-        let (transaction, coinbase_utxo_info) =
+        let (transaction, _coinbase_utxo_info) =
             make_coinbase_transaction(&global_state_lock, NeptuneCoins::zero(), launch_date)
                 .await
                 .unwrap();
@@ -784,14 +1060,14 @@ pub(crate) mod mine_loop_tests {
             block,
             tip_block_orig.clone(),
             worker_task_tx,
-            coinbase_utxo_info,
             unrestricted_mining,
             None,
+            &NonceSequence::random(),
         );
 
-        let mined_block_info = worker_task_rx.await.unwrap();
+        let mined_block = worker_task_rx.await.unwrap();
 
-        assert!(mined_block_info.block.has_proof_of_work(&tip_block_orig));
+        assert!(mined_block.has_proof_of_work(&tip_block_orig));
     }
 
     /// This test mines a single block at height 1 on the main network
@@ -809,7 +1085,7 @@ pub(crate) mod mine_loop_tests {
         let network = Network::Main;
         let global_state_lock =
             mock_genesis_global_state(network, 2, WalletSecret::devnet_wallet()).await;
-        let (worker_task_tx, worker_task_rx) = oneshot::channel::<NewBlockFound>();
+        let (worker_task_tx, worker_task_rx) = oneshot::channel::<Block>();
 
         let tip_block_orig = global_state_lock
             .lock_guard()
@@ -823,7 +1099,7 @@ pub(crate) mod mine_loop_tests {
         // pretend/simulate that it takes at least 10 seconds to mine the block.
         let ten_seconds_ago = now - Timestamp::seconds(10);
 
-        let (transaction, coinbase_utxo_info) =
+        let (transaction, _coinbase_utxo_info) =
             make_coinbase_transaction(&global_state_lock, NeptuneCoins::zero(), ten_seconds_ago)
                 .await
                 .unwrap();
@@ -845,14 +1121,14 @@ pub(crate) mod mine_loop_tests {
             template,
             tip_block_orig.clone(),
             worker_task_tx,
-            coinbase_utxo_info,
             unrestricted_mining,
             None,
+            &NonceSequence::random(),
         );
 
-        let mined_block_info = worker_task_rx.await.unwrap();
+        let mined_block = worker_task_rx.await.unwrap();
 
-        let block_timestamp = mined_block_info.block.kernel.header.timestamp;
+        let block_timestamp = mined_block.kernel.header.timestamp;
 
         // Mining updates the timestamp. So block timestamp will be >= to what
         // was set in the block template, and <= current time.
@@ -965,7 +1241,7 @@ pub(crate) mod mine_loop_tests {
             let start_time = Timestamp::now();
             let start_st = std::time::SystemTime::now();
 
-            let (transaction, coinbase_utxo_info) =
+            let (transaction, _coinbase_utxo_info) =
                 { (make_mock_transaction(vec![], vec![]), dummy_expected_utxo()) };
 
             let block = Block::block_template_invalid_proof(
@@ -975,28 +1251,28 @@ pub(crate) mod mine_loop_tests {
                 Some(target_block_interval),
             );
 
-            let (worker_task_tx, worker_task_rx) = oneshot::channel::<NewBlockFound>();
+            let (worker_task_tx, worker_task_rx) = oneshot::channel::<Block>();
             let height = block.header().height;
 
             mine_block_worker(
                 block,
                 prev_block.clone(),
                 worker_task_tx,
-                coinbase_utxo_info,
                 unrestricted_mining,
                 Some(target_block_interval),
+                &NonceSequence::random(),
             );
 
-            let mined_block_info = worker_task_rx.await.unwrap();
+            let mined_block = worker_task_rx.await.unwrap();
 
             // note: this assertion often fails prior to fix for #154.
             // Also note that `is_valid` is a wrapper around `is_valid_extended`
             // which is the method we need here because it allows us to override
             // default values for the target block interval and the minimum
             // block interval.
-            assert!(mined_block_info.block.has_proof_of_work(&prev_block,));
+            assert!(mined_block.has_proof_of_work(&prev_block,));
 
-            prev_block = *mined_block_info.block;
+            prev_block = mined_block;
 
             let block_time = start_st.elapsed()?.as_millis();
             println!(
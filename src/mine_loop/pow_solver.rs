@@ -0,0 +1,376 @@
+//! Pluggable search for a nonce that gives a block template proof-of-work.
+//!
+//! The mining task hands each block template it builds to a [`PowSolver`],
+//! and polls the solver for a solution: a nonce, and the timestamp to set
+//! along with it. By default, the search runs on this machine's CPU. With
+//! `--pow-solver`, it is left to an external hasher, for instance a GPU
+//! miner, which listens on a local socket.
+//!
+//! # Loopback protocol
+//!
+//! The node connects to the external hasher over TCP, and both sides send
+//! newline-delimited JSON messages. The node sends [`NodeToSolver`] messages:
+//! a template to search a nonce for, superseding any previous template, or a
+//! request to stop searching. The hasher answers with a
+//! [`SolverToNode::Solution`] once it finds a nonce for which the block
+//! digest does not exceed the template's target. Nonces should start with
+//! the given prefix, such that separate searches do not repeat each other.
+
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::net::Shutdown;
+use std::net::SocketAddr;
+use std::net::TcpStream;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::channel::oneshot;
+use itertools::Itertools;
+use serde::Deserialize;
+use serde::Serialize;
+use tasm_lib::twenty_first::math::b_field_element::BFieldElement;
+use tracing::debug;
+use tracing::warn;
+
+use super::mine_block_worker;
+use crate::models::blockchain::block::Block;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::models::state::block_proposal::BlockTemplate;
+use crate::models::state::mining_checkpoint::NonceSequence;
+
+/// How often the mining task asks the solver for a solution.
+const SOLUTION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long to wait for the external hasher to accept a connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A nonce that gives the template proof-of-work, and the timestamp with
+/// which it does. The difficulty in the header follows from the timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PowSolution {
+    pub nonce: [BFieldElement; 3],
+    pub timestamp: Timestamp,
+}
+
+/// A block template to search a nonce for.
+#[derive(Debug, Clone)]
+pub(crate) struct PowJob {
+    pub(crate) template: Block,
+    pub(crate) predecessor: Block,
+    pub(crate) nonces: Arc<NonceSequence>,
+}
+
+pub(crate) trait PowSolver: Send {
+    /// Start searching for a solution to `job`, abandoning any previous job.
+    fn set_template(&mut self, job: PowJob);
+
+    /// The solution to the current job, if one was found. Does not block.
+    /// Once a solution is returned, the solver is idle until the next job.
+    fn poll_solution(&mut self) -> Option<PowSolution>;
+
+    /// Stop searching.
+    fn cancel(&mut self);
+}
+
+/// Wait for the solver to find a solution to its current job.
+pub(crate) async fn next_solution(solver: &mut dyn PowSolver) -> PowSolution {
+    loop {
+        if let Some(solution) = solver.poll_solution() {
+            return solution;
+        }
+        tokio::time::sleep(SOLUTION_POLL_INTERVAL).await;
+    }
+}
+
+/// Searches on this machine's CPU, with workers sharing the job's nonces.
+#[derive(Debug)]
+pub(crate) struct CpuSolver {
+    num_workers: usize,
+    unrestricted_mining: bool,
+    target_block_interval: Option<Timestamp>,
+    workers: Vec<oneshot::Receiver<Block>>,
+}
+
+impl CpuSolver {
+    pub(crate) fn new(
+        num_workers: usize,
+        unrestricted_mining: bool,
+        target_block_interval: Option<Timestamp>,
+    ) -> Self {
+        Self {
+            num_workers: num_workers.max(1),
+            unrestricted_mining,
+            target_block_interval,
+            workers: vec![],
+        }
+    }
+}
+
+impl PowSolver for CpuSolver {
+    fn set_template(&mut self, job: PowJob) {
+        // Each worker runs in spawn_blocking() because mining is a very
+        // lengthy and CPU intensive task, which should execute on its own
+        // thread. There is no async code inside the mining loop.
+        // see: https://ryhl.io/blog/async-what-is-blocking/
+        //
+        // Replacing the receivers of a previous job stops its workers.
+        self.workers = (0..self.num_workers)
+            .map(|_| {
+                let (worker_tx, worker_rx) = oneshot::channel::<Block>();
+                let template = job.template.clone();
+                let predecessor = job.predecessor.clone();
+                let nonces = job.nonces.clone();
+                let unrestricted_mining = self.unrestricted_mining;
+                let target_block_interval = self.target_block_interval;
+                tokio::task::spawn_blocking(move || {
+                    mine_block_worker(
+                        template,
+                        predecessor,
+                        worker_tx,
+                        unrestricted_mining,
+                        target_block_interval,
+                        &nonces,
+                    )
+                });
+                worker_rx
+            })
+            .collect_vec();
+    }
+
+    fn poll_solution(&mut self) -> Option<PowSolution> {
+        // The first worker to find a block wins.
+        let block = self
+            .workers
+            .iter_mut()
+            .find_map(|worker| worker.try_recv().ok().flatten())?;
+        self.cancel();
+
+        Some(PowSolution {
+            nonce: block.header().nonce,
+            timestamp: block.header().timestamp,
+        })
+    }
+
+    fn cancel(&mut self) {
+        self.workers.clear();
+    }
+}
+
+/// Message from the node to an external hasher.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NodeToSolver {
+    Template {
+        template: BlockTemplate,
+        nonce_prefix: [BFieldElement; 2],
+    },
+    Cancel,
+}
+
+/// Message from an external hasher to the node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SolverToNode {
+    Solution {
+        /// `proposal_id` of the template solved
+        template_id: u64,
+        nonce: [BFieldElement; 3],
+        timestamp: Timestamp,
+    },
+}
+
+/// Leaves the search to an external hasher listening on a local socket.
+#[derive(Debug)]
+pub(crate) struct LoopbackSolver {
+    address: SocketAddr,
+    connection: Option<(TcpStream, mpsc::Receiver<SolverToNode>)>,
+    next_template_id: u64,
+    current_template_id: Option<u64>,
+}
+
+impl LoopbackSolver {
+    /// A solver for the external hasher at `address`. Connects on the first
+    /// job, and reconnects if the connection is lost.
+    pub(crate) fn new(address: SocketAddr) -> Self {
+        Self {
+            address,
+            connection: None,
+            next_template_id: 0,
+            current_template_id: None,
+        }
+    }
+
+    fn connect(&self) -> std::io::Result<(TcpStream, mpsc::Receiver<SolverToNode>)> {
+        let stream = TcpStream::connect_timeout(&self.address, CONNECT_TIMEOUT)?;
+        let reader = BufReader::new(stream.try_clone()?);
+        let (solutions_tx, solutions_rx) = mpsc::channel();
+        let address = self.address;
+        std::thread::Builder::new()
+            .name("pow_solver".to_string())
+            .spawn(move || {
+                for line in reader.lines() {
+                    let Ok(line) = line else {
+                        break;
+                    };
+                    match serde_json::from_str(&line) {
+                        Ok(message) => {
+                            if solutions_tx.send(message).is_err() {
+                                break;
+                            }
+                        }
+                        Err(err) => {
+                            warn!("Ignoring invalid message from hasher at {address}: {err}")
+                        }
+                    }
+                }
+                debug!("Connection to external hasher at {address} closed");
+            })?;
+        Ok((stream, solutions_rx))
+    }
+
+    fn disconnect(&mut self) {
+        if let Some((stream, _)) = self.connection.take() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    fn write(&mut self, line: &str) -> std::io::Result<()> {
+        if self.connection.is_none() {
+            self.connection = Some(self.connect()?);
+        }
+        let (stream, _) = self.connection.as_mut().unwrap();
+        stream.write_all(line.as_bytes())
+    }
+
+    /// Send a message, reconnecting once if the connection was lost.
+    fn send(&mut self, message: &NodeToSolver) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        if self.write(&line).is_ok() {
+            return Ok(());
+        }
+
+        self.disconnect();
+        let result = self.write(&line);
+        if result.is_err() {
+            self.disconnect();
+        }
+        result
+    }
+}
+
+impl PowSolver for LoopbackSolver {
+    fn set_template(&mut self, job: PowJob) {
+        let template_id = self.next_template_id;
+        self.next_template_id += 1;
+        self.current_template_id = Some(template_id);
+
+        let nonce = job.nonces.next();
+        let message = NodeToSolver::Template {
+            template: BlockTemplate::new(
+                template_id,
+                &job.template,
+                job.predecessor.header(),
+                Timestamp::now(),
+            ),
+            nonce_prefix: [nonce[0], nonce[1]],
+        };
+        if let Err(err) = self.send(&message) {
+            warn!(
+                "Could not hand block template to external hasher at {}: {err}",
+                self.address
+            );
+        }
+    }
+
+    fn poll_solution(&mut self) -> Option<PowSolution> {
+        let current_template_id = self.current_template_id?;
+        let (_, solutions) = self.connection.as_ref()?;
+        while let Ok(message) = solutions.try_recv() {
+            let SolverToNode::Solution {
+                template_id,
+                nonce,
+                timestamp,
+            } = message;
+            if template_id != current_template_id {
+                debug!("Ignoring solution to superseded template {template_id}");
+                continue;
+            }
+
+            self.current_template_id = None;
+            return Some(PowSolution { nonce, timestamp });
+        }
+
+        None
+    }
+
+    fn cancel(&mut self) {
+        if self.current_template_id.take().is_some() && self.connection.is_some() {
+            if let Err(err) = self.send(&NodeToSolver::Cancel) {
+                debug!("Could not cancel search of external hasher: {err}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod pow_solver_tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::config_models::network::Network;
+
+    #[test]
+    fn loopback_solver_relays_template_and_solution() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let hasher = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let NodeToSolver::Template {
+                template,
+                nonce_prefix,
+            } = serde_json::from_str(&line).unwrap()
+            else {
+                panic!("expected template");
+            };
+
+            // a stale solution first, then the one for this template
+            for template_id in [template.proposal_id + 1, template.proposal_id] {
+                let solution = SolverToNode::Solution {
+                    template_id,
+                    nonce: [nonce_prefix[0], nonce_prefix[1], BFieldElement::new(7)],
+                    timestamp: template.min_timestamp,
+                };
+                let mut reply = serde_json::to_string(&solution).unwrap();
+                reply.push('\n');
+                stream.write_all(reply.as_bytes()).unwrap();
+            }
+            template
+        });
+
+        let genesis = Block::genesis_block(Network::RegTest);
+        let mut solver = LoopbackSolver::new(address);
+        assert_eq!(None, solver.poll_solution());
+        solver.set_template(PowJob {
+            template: genesis.clone(),
+            predecessor: genesis,
+            nonces: Arc::new(NonceSequence::random()),
+        });
+        let template = hasher.join().unwrap();
+
+        let solution = (0..100)
+            .find_map(|_| {
+                std::thread::sleep(Duration::from_millis(10));
+                solver.poll_solution()
+            })
+            .unwrap();
+        assert_eq!(BFieldElement::new(7), solution.nonce[2]);
+        assert_eq!(template.min_timestamp, solution.timestamp);
+        assert_eq!(None, solver.poll_solution());
+    }
+}
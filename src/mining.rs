@@ -0,0 +1,5 @@
+//! Mining against this node from other processes and machines, and block
+//! generation on request.
+
+pub mod block_generation;
+pub mod work_server;
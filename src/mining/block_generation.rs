@@ -0,0 +1,146 @@
+//! Instant block generation on RegTest, for integration test harnesses.
+//!
+//! A generated block is fully determined by its predecessor and the address
+//! it pays to. It is timestamped one target block interval after its
+//! predecessor, which keeps the difficulty at the minimum, and its nonce is
+//! the first one, counting up from zero, that gives it proof-of-work.
+//!
+//! Real proofs take as long as they do for mined blocks. Without them, the
+//! coinbase transaction carries its primitive witness and the block no proof,
+//! so the block is accepted by this node only and not shared with peers.
+
+use anyhow::ensure;
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tasm_lib::twenty_first::math::b_field_element::BFieldElement;
+use thiserror::Error;
+
+use crate::config_models::network_params::NetworkParams;
+use crate::models::blockchain::block::Block;
+use crate::models::blockchain::block::FUTUREDATING_LIMIT;
+use crate::models::blockchain::shared::Hash;
+use crate::models::blockchain::transaction::transaction_output::TxOutput;
+use crate::models::proof_abstractions::tasm::program::TritonProverSync;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::models::state::transaction_details::TransactionDetails;
+use crate::models::state::tx_proving_capability::TxProvingCapability;
+use crate::models::state::wallet::address::ReceivingAddress;
+use crate::models::state::GlobalState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error, Serialize, Deserialize, JsonSchema)]
+pub enum GenerateBlocksError {
+    #[error("blocks can only be generated on regtest")]
+    NotRegTest,
+
+    #[error("the block would be timestamped too far into the future")]
+    TooFarInFuture,
+
+    #[error("the block could not be generated")]
+    GenerationFailed,
+
+    #[error("the generated block did not become the tip")]
+    NotStored,
+}
+
+/// Generate a block on top of `predecessor` that pays the block subsidy to
+/// `address`.
+pub(crate) async fn generate_block(
+    predecessor: &Block,
+    address: ReceivingAddress,
+    real_proofs: bool,
+    sync_device: &TritonProverSync,
+) -> Result<Block> {
+    let timestamp =
+        predecessor.header().timestamp + NetworkParams::in_effect().target_block_interval();
+    ensure!(
+        timestamp <= Timestamp::now() + FUTUREDATING_LIMIT,
+        GenerateBlocksError::TooFarInFuture
+    );
+
+    let coinbase_amount = Block::get_mining_reward(predecessor.header().height.next());
+    let sender_randomness = Hash::hash_pair(predecessor.hash(), address.privacy_digest());
+    let coinbase_output =
+        TxOutput::onchain_native_currency(coinbase_amount, sender_randomness, address);
+    let transaction_details = TransactionDetails::new_with_coinbase(
+        vec![],
+        vec![coinbase_output].into(),
+        coinbase_amount,
+        timestamp,
+        predecessor.body().mutator_set_accumulator.clone(),
+    )?;
+
+    let proving_capability = if real_proofs {
+        TxProvingCapability::SingleProof
+    } else {
+        TxProvingCapability::PrimitiveWitness
+    };
+    let transaction =
+        GlobalState::create_raw_transaction(transaction_details, proving_capability, sync_device)
+            .await?;
+
+    let mut block = if real_proofs {
+        Block::make_block_template(predecessor, transaction, timestamp, None, sync_device).await?
+    } else {
+        Block::block_template_invalid_proof(predecessor, transaction, timestamp, None)
+    };
+    solve(&mut block, predecessor);
+
+    Ok(block)
+}
+
+/// Set the first nonce that gives the block proof-of-work.
+fn solve(block: &mut Block, predecessor: &Block) {
+    let threshold = predecessor.header().difficulty.target();
+    for counter in 0u64.. {
+        block.set_header_nonce([
+            BFieldElement::new(counter),
+            BFieldElement::ZERO,
+            BFieldElement::ZERO,
+        ]);
+        if block.hash() <= threshold {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod block_generation_tests {
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::blockchain::block::difficulty_control::Difficulty;
+    use crate::models::state::wallet::WalletSecret;
+
+    #[tokio::test]
+    async fn generated_blocks_are_deterministic_and_have_proof_of_work() {
+        let genesis = Block::genesis_block(Network::RegTest);
+        let address: ReceivingAddress = WalletSecret::devnet_wallet()
+            .nth_generation_spending_key_for_tests(0)
+            .to_address()
+            .into();
+        let sync_device = TritonProverSync::dummy();
+
+        let block_1 = generate_block(&genesis, address.clone(), false, &sync_device)
+            .await
+            .unwrap();
+        let block_2 = generate_block(&block_1, address.clone(), false, &sync_device)
+            .await
+            .unwrap();
+
+        for (block, predecessor) in [(&block_1, &genesis), (&block_2, &block_1)] {
+            assert_eq!(predecessor.hash(), block.header().prev_block_digest);
+            assert!(block.has_proof_of_work(predecessor));
+            assert_eq!(Difficulty::MINIMUM, block.header().difficulty);
+            assert_eq!(
+                predecessor.header().timestamp + NetworkParams::in_effect().target_block_interval(),
+                block.header().timestamp
+            );
+        }
+
+        let again = generate_block(&genesis, address, false, &sync_device)
+            .await
+            .unwrap();
+        assert_eq!(block_1.hash(), again.hash());
+    }
+}
@@ -0,0 +1,541 @@
+//! TCP work server, for small mining farms to mine against one node.
+//!
+//! With `--external-mining` and `--work-server-port`, the node distributes the
+//! block templates it builds to mining workers. Each worker is pushed a job:
+//! the latest template, a nonce prefix unique to the worker, and a range of
+//! nonce counters to try. Workers ask for the next range once they exhausted
+//! theirs, and submit every nonce whose block digest meets the share target,
+//! which is set by `--share-difficulty` and is far easier to meet than the
+//! block target. Shares let the operator see that every worker does its work;
+//! a share that also meets the block target is a block, which the node
+//! validates, stores, and shares with peers like a block mined in-process.
+//!
+//! Messages are JSON objects, one per line, tagged by `type`:
+//!
+//! | from   | type              | fields                                        |
+//! |--------|-------------------|-----------------------------------------------|
+//! | server | `job`             | `job_id`, `template`, `share_target`, `nonce_prefix`, `nonce_start`, `nonce_end` |
+//! | server | `share_accepted`  | `job_id`                                      |
+//! | server | `share_rejected`  | `job_id`, `reason`                            |
+//! | server | `block_found`     | `job_id`, `digest`                            |
+//! | worker | `submit`          | `job_id`, `nonce`, `timestamp` (optional)     |
+//! | worker | `range_exhausted` | `job_id`                                      |
+//!
+//! A nonce is the job's two-element prefix followed by a counter in
+//! `nonce_start..nonce_end`. A new job is pushed whenever the node builds a
+//! new template, and invalidates all previous ones.
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde::Serialize;
+use tasm_lib::twenty_first::math::b_field_element::BFieldElement;
+use tasm_lib::twenty_first::math::digest::Digest;
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::select;
+use tokio::sync::mpsc;
+use tokio::sync::watch;
+use tokio::time;
+use tokio_util::codec::FramedRead;
+use tokio_util::codec::LinesCodec;
+use tracing::debug;
+use tracing::info;
+use tracing::warn;
+
+use crate::models::blockchain::block::difficulty_control::Difficulty;
+use crate::models::blockchain::block::Block;
+use crate::models::blockchain::block::FUTUREDATING_LIMIT;
+use crate::models::channel::RPCServerToMain;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::models::state::block_proposal::set_solution;
+use crate::models::state::block_proposal::BlockProposal;
+use crate::models::state::block_proposal::BlockTemplate;
+use crate::models::state::GlobalStateLock;
+
+/// How often to check whether the node built a new template.
+const JOB_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Number of nonce counters in the range handed to a worker at a time.
+pub const NONCE_RANGE_SIZE: u64 = 1 << 32;
+
+/// Maximum length of a message from a worker, in bytes.
+const MAX_WORKER_MESSAGE_LENGTH: usize = 1024;
+
+/// A block template to mine on, with the nonces to try.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Job {
+    /// Proposal id of the template
+    pub job_id: u64,
+    pub template: BlockTemplate,
+
+    /// Digests less than or equal to this are shares
+    pub share_target: Digest,
+
+    pub nonce_prefix: [BFieldElement; 2],
+    pub nonce_start: u64,
+    pub nonce_end: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Job(Job),
+    ShareAccepted { job_id: u64 },
+    ShareRejected { job_id: u64, reason: ShareRejection },
+    BlockFound { job_id: u64, digest: Digest },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorkerMessage {
+    Submit {
+        job_id: u64,
+        nonce: [BFieldElement; 3],
+
+        /// Timestamp of the block, if it differs from the template's
+        timestamp: Option<Timestamp>,
+    },
+    RangeExhausted {
+        job_id: u64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareRejection {
+    #[error("job was superseded")]
+    StaleJob,
+
+    #[error("nonce was not handed to this worker")]
+    NonceOutOfRange,
+
+    #[error("share was submitted before")]
+    Duplicate,
+
+    #[error("timestamp is out of the template's range")]
+    InvalidTimestamp,
+
+    #[error("block digest exceeds the share target")]
+    AboveShareTarget,
+
+    #[error("block meets the target but is invalid")]
+    InvalidBlock,
+}
+
+/// The template workers currently mine on.
+#[derive(Debug)]
+struct CurrentJob {
+    template: BlockTemplate,
+    proposal: BlockProposal,
+    predecessor: Block,
+}
+
+/// The id of the latest proposal built on the tip, if any.
+///
+/// Locking:
+///   * acquires `global_state_lock` for read
+async fn latest_proposal_id(global_state_lock: &GlobalStateLock) -> Option<u64> {
+    let state = global_state_lock.lock_guard().await;
+    let tip_digest = state.chain.light_state().hash();
+    state
+        .block_proposals
+        .latest()
+        .filter(|(_, proposal)| proposal.template.header().prev_block_digest == tip_digest)
+        .map(|(proposal_id, _)| proposal_id)
+}
+
+/// The latest template built on the tip, if any.
+///
+/// Locking:
+///   * acquires `global_state_lock` for read
+async fn latest_job(global_state_lock: &GlobalStateLock) -> Option<Arc<CurrentJob>> {
+    let state = global_state_lock.lock_guard().await;
+    let tip = state.chain.light_state();
+    let template = state
+        .block_proposals
+        .latest_template(tip.header(), Timestamp::now())
+        .filter(|template| template.header.prev_block_digest == tip.hash())?;
+    let proposal = state.block_proposals.get(template.proposal_id)?.clone();
+
+    Some(Arc::new(CurrentJob {
+        template,
+        proposal,
+        predecessor: tip.clone(),
+    }))
+}
+
+/// Serve mining workers until the node shuts down.
+pub async fn run(
+    listener: TcpListener,
+    global_state_lock: GlobalStateLock,
+    rpc_server_to_main_tx: mpsc::Sender<RPCServerToMain>,
+) {
+    let share_difficulty = global_state_lock.cli().share_difficulty;
+    let share_difficulty = Difficulty::new([
+        share_difficulty as u32,
+        (share_difficulty >> 32) as u32,
+        0,
+        0,
+        0,
+    ]);
+    let salt: BFieldElement = rand::random();
+    let (jobs_tx, jobs_rx) = watch::channel::<Option<Arc<CurrentJob>>>(None);
+
+    let mut next_worker_id = 0u64;
+    let mut job_poll_timer = time::interval(JOB_POLL_INTERVAL);
+    loop {
+        select! {
+            _ = job_poll_timer.tick() => {
+                // Templates are large, so only copy new ones
+                let current_proposal_id = jobs_tx
+                    .borrow()
+                    .as_ref()
+                    .map(|job| job.template.proposal_id);
+                if latest_proposal_id(&global_state_lock).await != current_proposal_id {
+                    jobs_tx.send_replace(latest_job(&global_state_lock).await);
+                }
+            }
+            accepted = listener.accept() => {
+                let (stream, worker_address) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(err) => {
+                        warn!("Could not accept mining worker: {err}");
+                        continue;
+                    }
+                };
+
+                let session = WorkerSession::new(
+                    worker_address,
+                    [salt, BFieldElement::new(next_worker_id)],
+                    share_difficulty,
+                );
+                next_worker_id += 1;
+                info!("Mining worker {worker_address} connected");
+                tokio::spawn(serve_worker(
+                    stream,
+                    session,
+                    jobs_rx.clone(),
+                    rpc_server_to_main_tx.clone(),
+                ));
+            }
+        }
+    }
+}
+
+async fn serve_worker(
+    stream: TcpStream,
+    mut session: WorkerSession,
+    mut jobs: watch::Receiver<Option<Arc<CurrentJob>>>,
+    rpc_server_to_main_tx: mpsc::Sender<RPCServerToMain>,
+) {
+    let (reader, mut writer) = stream.into_split();
+    let mut messages = FramedRead::new(
+        reader,
+        LinesCodec::new_with_max_length(MAX_WORKER_MESSAGE_LENGTH),
+    );
+
+    // push the current job, if any, right away
+    jobs.mark_changed();
+    let result: Result<()> = async {
+        loop {
+            select! {
+                changed = jobs.changed() => {
+                    changed?;
+                    let job = jobs.borrow_and_update().clone();
+                    if let Some(job) = session.start_job(job) {
+                        send(&mut writer, &ServerMessage::Job(job)).await?;
+                    }
+                }
+                message = messages.next() => {
+                    let Some(message) = message else {
+                        return Ok(());
+                    };
+                    let message: WorkerMessage = serde_json::from_str(&message?)?;
+                    if let Some(reply) = session.handle(message, &rpc_server_to_main_tx).await {
+                        send(&mut writer, &reply).await?;
+                    }
+                }
+            }
+        }
+    }
+    .await;
+
+    match result {
+        Ok(()) => info!(
+            "Mining worker {} disconnected after {} accepted shares",
+            session.address, session.num_accepted
+        ),
+        Err(err) => warn!(
+            "Disconnected mining worker {} after {} accepted shares: {err:#}",
+            session.address, session.num_accepted
+        ),
+    }
+}
+
+async fn send(writer: &mut OwnedWriteHalf, message: &ServerMessage) -> Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    Ok(())
+}
+
+/// A connected worker and the work handed to it.
+#[derive(Debug)]
+struct WorkerSession {
+    address: SocketAddr,
+    nonce_prefix: [BFieldElement; 2],
+    share_difficulty: Difficulty,
+
+    job: Option<Arc<CurrentJob>>,
+
+    /// Copy of the job's template, to set the worker's nonces in
+    block: Option<Block>,
+
+    /// Counters below this were handed to the worker for the current job
+    next_nonce_start: u64,
+
+    /// Nonce counters and timestamps of the shares accepted for the current
+    /// job
+    shares: HashSet<(u64, Timestamp)>,
+    num_accepted: u64,
+}
+
+impl WorkerSession {
+    fn new(
+        address: SocketAddr,
+        nonce_prefix: [BFieldElement; 2],
+        share_difficulty: Difficulty,
+    ) -> Self {
+        Self {
+            address,
+            nonce_prefix,
+            share_difficulty,
+            job: None,
+            block: None,
+            next_nonce_start: 0,
+            shares: HashSet::new(),
+            num_accepted: 0,
+        }
+    }
+
+    /// Switch to a new job, returning its first nonce range for the worker.
+    fn start_job(&mut self, job: Option<Arc<CurrentJob>>) -> Option<Job> {
+        self.block = job.as_ref().map(|job| job.proposal.template.clone());
+        self.job = job;
+        self.next_nonce_start = 0;
+        self.shares.clear();
+        self.next_range()
+    }
+
+    /// Hand the next nonce range of the current job to the worker.
+    fn next_range(&mut self) -> Option<Job> {
+        let current = self.job.as_ref()?;
+        let share_target = self.share_difficulty.target().max(current.template.target);
+        let nonce_start = self.next_nonce_start;
+        let nonce_end = nonce_start.saturating_add(NONCE_RANGE_SIZE);
+        self.next_nonce_start = nonce_end;
+
+        Some(Job {
+            job_id: current.template.proposal_id,
+            template: current.template.clone(),
+            share_target,
+            nonce_prefix: self.nonce_prefix,
+            nonce_start,
+            nonce_end,
+        })
+    }
+
+    async fn handle(
+        &mut self,
+        message: WorkerMessage,
+        rpc_server_to_main_tx: &mpsc::Sender<RPCServerToMain>,
+    ) -> Option<ServerMessage> {
+        match message {
+            WorkerMessage::RangeExhausted { job_id } => {
+                if self.current_job_id() != Some(job_id) {
+                    return None;
+                }
+                self.next_range().map(ServerMessage::Job)
+            }
+            WorkerMessage::Submit {
+                job_id,
+                nonce,
+                timestamp,
+            } => match self.check_share(job_id, nonce, timestamp, Timestamp::now()) {
+                Ok(None) => Some(ServerMessage::ShareAccepted { job_id }),
+                Ok(Some(block)) => {
+                    let current = self.job.as_ref()?;
+                    let new_block_found = match current.proposal.verify_solution(
+                        block,
+                        &current.predecessor,
+                        Timestamp::now(),
+                    ) {
+                        Ok(new_block_found) => new_block_found,
+                        Err(err) => {
+                            warn!("Mining worker {} found invalid block: {err}", self.address);
+                            return Some(ServerMessage::ShareRejected {
+                                job_id,
+                                reason: ShareRejection::InvalidBlock,
+                            });
+                        }
+                    };
+
+                    let digest = new_block_found.block.hash();
+                    info!("Mining worker {} found block {digest}", self.address);
+                    let _ = rpc_server_to_main_tx
+                        .send(RPCServerToMain::BlockSolutionFound(Box::new(
+                            new_block_found,
+                        )))
+                        .await;
+                    Some(ServerMessage::BlockFound { job_id, digest })
+                }
+                Err(reason) => {
+                    debug!("Rejected share of mining worker {}: {reason}", self.address);
+                    Some(ServerMessage::ShareRejected { job_id, reason })
+                }
+            },
+        }
+    }
+
+    fn current_job_id(&self) -> Option<u64> {
+        self.job.as_ref().map(|job| job.template.proposal_id)
+    }
+
+    /// Check a share, returning the block if it also meets the block target.
+    fn check_share(
+        &mut self,
+        job_id: u64,
+        nonce: [BFieldElement; 3],
+        timestamp: Option<Timestamp>,
+        now: Timestamp,
+    ) -> Result<Option<Block>, ShareRejection> {
+        if self.current_job_id() != Some(job_id) {
+            return Err(ShareRejection::StaleJob);
+        }
+        let (Some(current), Some(block)) = (self.job.as_ref(), self.block.as_mut()) else {
+            return Err(ShareRejection::StaleJob);
+        };
+
+        let counter = nonce[2].value();
+        if nonce[..2] != self.nonce_prefix || counter >= self.next_nonce_start {
+            return Err(ShareRejection::NonceOutOfRange);
+        }
+
+        let timestamp = timestamp.unwrap_or(current.template.header.timestamp);
+        if timestamp < current.template.min_timestamp || timestamp > now + FUTUREDATING_LIMIT {
+            return Err(ShareRejection::InvalidTimestamp);
+        }
+        if self.shares.contains(&(counter, timestamp)) {
+            return Err(ShareRejection::Duplicate);
+        }
+
+        set_solution(block, current.predecessor.header(), nonce, timestamp);
+        let digest = block.hash();
+        let share_target = self.share_difficulty.target().max(current.template.target);
+        if digest > share_target {
+            return Err(ShareRejection::AboveShareTarget);
+        }
+
+        self.shares.insert((counter, timestamp));
+        self.num_accepted += 1;
+        Ok(Some(block.clone()).filter(|_| digest <= current.template.target))
+    }
+}
+
+#[cfg(test)]
+mod work_server_tests {
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::blockchain::transaction::Transaction;
+    use crate::models::state::block_proposal::BlockProposals;
+    use crate::models::state::mining_log::BlockTemplateInfo;
+    use crate::tests::shared::dummy_expected_utxo;
+    use crate::tests::shared::make_mock_transaction;
+
+    #[test]
+    fn shares_are_checked_against_the_work_handed_out() {
+        let genesis = Block::genesis_block(Network::RegTest);
+        let transaction: Transaction = make_mock_transaction(vec![], vec![]);
+        let created = genesis.header().timestamp + Timestamp::minutes(10);
+        let proposal = BlockProposal {
+            template: Block::block_template_invalid_proof(&genesis, transaction, created, None),
+            coinbase_utxo_info: dummy_expected_utxo(),
+            info: BlockTemplateInfo {
+                created,
+                num_transactions: 0,
+            },
+        };
+        let mut proposals = BlockProposals::default();
+        proposals.publish(proposal.clone());
+        let template = proposals
+            .latest_template(genesis.header(), created)
+            .unwrap();
+        let job_id = template.proposal_id;
+        let current = Arc::new(CurrentJob {
+            template,
+            proposal,
+            predecessor: genesis,
+        });
+
+        // at minimal difficulty, roughly one digest in a thousand is a share
+        let prefix = [BFieldElement::new(7), BFieldElement::new(0)];
+        let mut session = WorkerSession::new(
+            "127.0.0.1:9801".parse().unwrap(),
+            prefix,
+            Difficulty::MINIMUM,
+        );
+        let job = session.start_job(Some(current)).unwrap();
+        assert_eq!(prefix, job.nonce_prefix);
+        assert_eq!((0, NONCE_RANGE_SIZE), (job.nonce_start, job.nonce_end));
+
+        let second_range = session.next_range().unwrap();
+        assert_eq!(NONCE_RANGE_SIZE, second_range.nonce_start);
+
+        let now = created;
+        let nonce = |counter: u64| [prefix[0], prefix[1], BFieldElement::new(counter)];
+        assert_eq!(
+            Err(ShareRejection::StaleJob),
+            session.check_share(job_id + 1, nonce(0), None, now)
+        );
+        assert_eq!(
+            Err(ShareRejection::NonceOutOfRange),
+            session.check_share(job_id, nonce(2 * NONCE_RANGE_SIZE), None, now)
+        );
+        assert_eq!(
+            Err(ShareRejection::NonceOutOfRange),
+            session.check_share(
+                job_id,
+                [BFieldElement::new(8), prefix[1], prefix[1]],
+                None,
+                now
+            )
+        );
+        assert_eq!(
+            Err(ShareRejection::InvalidTimestamp),
+            session.check_share(job_id, nonce(0), Some(now + Timestamp::days(1)), now)
+        );
+
+        let mut counter = 0;
+        let share = loop {
+            match session.check_share(job_id, nonce(counter), None, now) {
+                Err(ShareRejection::AboveShareTarget) => counter += 1,
+                share => break share,
+            }
+        };
+        assert!(share.is_ok());
+        assert_eq!(1, session.num_accepted);
+        assert_eq!(
+            Err(ShareRejection::Duplicate),
+            session.check_share(job_id, nonce(counter), None, now)
+        );
+    }
+}
@@ -42,6 +42,11 @@ pub struct BlockBody {
     pub(crate) mutator_set_accumulator: MutatorSetAccumulator,
 
     /// Lock-free UTXOs do not come with lock scripts and do not live in the mutator set.
+    ///
+    /// Reserved: transaction kernels have no lock-free inputs or outputs yet,
+    /// as adding them changes the kernel's MAST hash and every consensus
+    /// program that commits to it. Until then this MMR is empty in the genesis
+    /// block and must be carried over unchanged by every block.
     pub(crate) lock_free_mmr_accumulator: MmrAccumulator,
 
     /// All blocks live in an MMR, so that we can efficiently prove that a given block
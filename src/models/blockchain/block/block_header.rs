@@ -2,6 +2,7 @@ use std::fmt::Display;
 
 use arbitrary::Arbitrary;
 use get_size::GetSize;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use strum::EnumCount;
@@ -16,6 +17,8 @@ use crate::models::proof_abstractions::mast_hash::HasDiscriminant;
 use crate::models::proof_abstractions::mast_hash::MastHash;
 use crate::models::proof_abstractions::timestamp::Timestamp;
 use crate::prelude::twenty_first;
+use crate::util_types::json_schema::BFieldElementSchema;
+use crate::util_types::json_schema::DigestSchema;
 
 /// Desired/average time between blocks.
 ///
@@ -59,16 +62,22 @@ pub(crate) const ADVANCE_DIFFICULTY_CORRECTION_FACTOR: usize = 4;
 
 pub(crate) const BLOCK_HEADER_VERSION: BFieldElement = BFieldElement::new(0);
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, BFieldCodec, GetSize, Arbitrary)]
+#[derive(
+    Clone, Debug, Serialize, Deserialize, PartialEq, Eq, BFieldCodec, GetSize, Arbitrary, JsonSchema,
+)]
+#[non_exhaustive]
 pub struct BlockHeader {
+    #[schemars(with = "BFieldElementSchema")]
     pub version: BFieldElement,
     pub height: BlockHeight,
+    #[schemars(with = "DigestSchema")]
     pub prev_block_digest: Digest,
 
     /// Time since unix epoch, in milliseconds
     pub timestamp: Timestamp,
 
     // TODO: Consider making a type for `nonce`
+    #[schemars(with = "[BFieldElementSchema; 3]")]
     pub nonce: [BFieldElement; 3],
 
     /// Total proof-of-work accumulated by this chain
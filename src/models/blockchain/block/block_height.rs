@@ -7,12 +7,14 @@ use arbitrary::Arbitrary;
 use get_size::GetSize;
 use num_traits::One;
 use num_traits::Zero;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use twenty_first::math::b_field_element::BFieldElement;
 use twenty_first::math::bfield_codec::BFieldCodec;
 
 use crate::prelude::twenty_first;
+use crate::util_types::json_schema::BFieldElementSchema;
 
 #[derive(
     Clone,
@@ -27,8 +29,9 @@ use crate::prelude::twenty_first;
     BFieldCodec,
     GetSize,
     Arbitrary,
+    JsonSchema,
 )]
-pub struct BlockHeight(BFieldElement);
+pub struct BlockHeight(#[schemars(with = "BFieldElementSchema")] BFieldElement);
 
 // Assuming a block time of 10 minutes, and a halving every three years,
 // the number of blocks per halving cycle is 157680.
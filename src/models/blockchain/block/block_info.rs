@@ -1,6 +1,7 @@
 //! BlockInfo is a concise summary of a block intended for human
 //! consumption/reporting in block explorers, cli, dashboard, etc.
 
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use twenty_first::math::digest::Digest;
@@ -12,12 +13,16 @@ use crate::models::blockchain::block::Block;
 use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
 use crate::models::proof_abstractions::timestamp::Timestamp;
 use crate::prelude::twenty_first;
+use crate::util_types::json_schema::DigestSchema;
 
 /// Provides summary information about a Block
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[non_exhaustive]
 pub struct BlockInfo {
     pub height: BlockHeight,
+    #[schemars(with = "DigestSchema")]
     pub digest: Digest,
+    #[schemars(with = "DigestSchema")]
     pub prev_block_digest: Digest,
     pub timestamp: Timestamp,
     pub cumulative_proof_of_work: ProofOfWork,
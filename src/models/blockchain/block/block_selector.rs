@@ -6,6 +6,8 @@
 //!  * A BlockHeight
 //!  * Genesis
 //!  * Tip
+//!  * A Timestamp, selecting the last block at or before it
+//!  * A depth below the tip
 //!
 //! Then call BlockSelector::to_digest() to obtain the block's Digest, if it
 //! exists.
@@ -16,22 +18,29 @@
 use std::num::ParseIntError;
 use std::str::FromStr;
 
+use chrono::DateTime;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use thiserror::Error;
 
 use super::block_height::BlockHeight;
+use crate::models::proof_abstractions::timestamp::Timestamp;
 use crate::models::state::GlobalState;
 use crate::twenty_first::error::TryFromHexDigestError;
 use crate::twenty_first::math::digest::Digest;
+use crate::util_types::json_schema::DigestSchema;
 
 /// Provides alternatives for looking up a block.
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[non_exhaustive]
 pub enum BlockSelector {
-    Digest(Digest),      // Identifies block by Digest (hash)
-    Height(BlockHeight), // Identifies block by Height (count from genesis)
-    Genesis,             // Indicates the genesis block
-    Tip,                 // Indicates the latest canonical block
+    Digest(#[schemars(with = "DigestSchema")] Digest), // Identifies block by Digest (hash)
+    Height(BlockHeight),  // Identifies block by Height (count from genesis)
+    Genesis,              // Indicates the genesis block
+    Tip,                  // Indicates the latest canonical block
+    Timestamp(Timestamp), // Last canonical block with timestamp at or before
+    Depth(u64),           // Canonical block this many blocks below the tip
 }
 
 /// BlockSelector can be written out as any of:
@@ -40,6 +49,8 @@ pub enum BlockSelector {
 ///  tip
 ///  height/<N>
 ///  digest/<hex>
+///  timestamp/<millis since unix epoch, or RFC 3339 date-time>
+///  depth/<N>
 /// ```
 ///
 /// This is intended to be easy for humans to read and also input, ie suitable
@@ -51,6 +62,8 @@ impl std::fmt::Display for BlockSelector {
             Self::Height(h) => write!(f, "height/{}", h),
             Self::Genesis => write!(f, "genesis"),
             Self::Tip => write!(f, "tip"),
+            Self::Timestamp(t) => write!(f, "timestamp/{}", t),
+            Self::Depth(d) => write!(f, "depth/{}", d),
         }
     }
 }
@@ -60,7 +73,9 @@ pub enum BlockSelectorParseError {
     #[error("Invalid selector {0}.  Try genesis or tip")]
     InvalidSelector(String),
 
-    #[error("Invalid pair selector {0}.  Try height/<N> or digest/<hex>")]
+    #[error(
+        "Invalid pair selector {0}.  Try height/<N>, digest/<hex>, timestamp/<T> or depth/<N>"
+    )]
     InvalidPairSelector(String),
 
     #[error("Wrong selector length {0}.  (too many or too few '/')")]
@@ -71,6 +86,9 @@ pub enum BlockSelectorParseError {
 
     #[error("Bad Height")]
     BadHeight(#[from] ParseIntError),
+
+    #[error("Bad Timestamp {0}.  Try milliseconds since unix epoch or RFC 3339")]
+    BadTimestamp(String),
 }
 
 impl FromStr for BlockSelector {
@@ -91,6 +109,8 @@ impl FromStr for BlockSelector {
             match parts[0] {
                 "digest" => Ok(Self::Digest(Digest::try_from_hex(parts[1])?)),
                 "height" => Ok(Self::Height(parts[1].parse::<u64>()?.into())),
+                "timestamp" => Ok(Self::Timestamp(parse_timestamp(parts[1])?)),
+                "depth" => Ok(Self::Depth(parts[1].parse::<u64>()?)),
                 other => Err(BlockSelectorParseError::InvalidPairSelector(
                     other.to_string(),
                 )),
@@ -101,6 +121,17 @@ impl FromStr for BlockSelector {
     }
 }
 
+fn parse_timestamp(s: &str) -> Result<Timestamp, BlockSelectorParseError> {
+    if let Ok(millis) = s.parse::<u64>() {
+        return Ok(Timestamp::millis(millis));
+    }
+    DateTime::parse_from_rfc3339(s)
+        .ok()
+        .and_then(|date_time| u64::try_from(date_time.timestamp_millis()).ok())
+        .map(Timestamp::millis)
+        .ok_or_else(|| BlockSelectorParseError::BadTimestamp(s.to_string()))
+}
+
 impl BlockSelector {
     /// returns Digest for this selector, if it exists.
     pub async fn as_digest(&self, state: &GlobalState) -> Option<Digest> {
@@ -115,6 +146,52 @@ impl BlockSelector {
             }
             BlockSelector::Tip => Some(state.chain.light_state().hash()),
             BlockSelector::Genesis => Some(state.chain.archival_state().genesis_block().hash()),
+            BlockSelector::Timestamp(t) => {
+                state
+                    .chain
+                    .archival_state()
+                    .canonical_block_digest_at_or_before(*t, state.chain.light_state().hash())
+                    .await
+            }
+            BlockSelector::Depth(d) => {
+                let tip_height = u64::from(state.chain.light_state().header().height);
+                let height = tip_height.checked_sub(*d)?;
+                state
+                    .chain
+                    .archival_state()
+                    .block_height_to_canonical_block_digest(
+                        height.into(),
+                        state.chain.light_state().hash(),
+                    )
+                    .await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod block_selector_tests {
+    use super::*;
+
+    #[test]
+    fn display_and_parse_round_trip() {
+        for selector in [
+            BlockSelector::Genesis,
+            BlockSelector::Tip,
+            BlockSelector::Height(7u64.into()),
+            BlockSelector::Timestamp(Timestamp::millis(1_700_000_000_000)),
+            BlockSelector::Depth(6),
+        ] {
+            assert_eq!(selector, selector.to_string().parse().unwrap());
         }
     }
+
+    #[test]
+    fn timestamp_accepts_rfc_3339() {
+        assert_eq!(
+            BlockSelector::Timestamp(Timestamp::millis(1_700_000_000_000)),
+            "timestamp/2023-11-14T22:13:20Z".parse().unwrap()
+        );
+        assert!("timestamp/yesterday".parse::<BlockSelector>().is_err());
+    }
 }
@@ -0,0 +1,169 @@
+//! ChainParams lists the consensus constants that are in effect on a network,
+//! so that external tools can query them instead of hardcoding values that
+//! drift from the implementation.
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::block_header::ADVANCE_DIFFICULTY_CORRECTION_FACTOR;
+use super::block_header::ADVANCE_DIFFICULTY_CORRECTION_WAIT;
+use super::block_header::BLOCK_HEADER_VERSION;
+use super::block_header::MINIMUM_BLOCK_TIME;
+use super::block_height::BlockHeight;
+use super::block_height::BLOCKS_PER_GENERATION;
+use super::coinbase_accounting::FEE_BURN_PER_MILLE;
+use super::difficulty_control::Difficulty;
+use super::Block;
+use super::FUTUREDATING_LIMIT;
+use super::PREMINE_TIME_LOCK_PERIOD;
+use crate::config_models::network::Network;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+
+/// Consensus constants of a network.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[non_exhaustive]
+pub struct ChainParams {
+    pub network: Network,
+
+    /// Timestamp of the genesis block.
+    pub launch_date: Timestamp,
+    pub block_header_version: u64,
+
+    /// Desired average time between blocks.
+    pub target_block_interval: Timestamp,
+
+    /// Blocks spaced apart by less than this are invalid.
+    pub minimum_block_time: Timestamp,
+
+    /// Blocks with a timestamp further than this ahead of the validating
+    /// node's clock are invalid.
+    pub future_dating_limit: Timestamp,
+    pub minimum_difficulty: Difficulty,
+    pub genesis_difficulty: Difficulty,
+    pub advance_difficulty_correction_wait: usize,
+    pub advance_difficulty_correction_factor: usize,
+
+    /// In number of `BFieldElement`s.
+    pub max_block_size: usize,
+
+    /// Block subsidy of the first generation. Halves every generation.
+    pub initial_block_subsidy: NeptuneCoins,
+    pub blocks_per_generation: u64,
+
+    /// Parts per thousand of transaction fees that are burned.
+    pub fee_burn_per_mille: u32,
+    pub premine_total: NeptuneCoins,
+    pub premine_time_lock_period: Timestamp,
+
+    /// Height at which each consensus change took effect, by name. Empty as
+    /// long as every network still runs its launch rules.
+    pub activation_heights: Vec<(String, BlockHeight)>,
+}
+
+impl std::fmt::Display for ChainParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let activation_heights = self
+            .activation_heights
+            .iter()
+            .map(|(name, height)| format!("{name}@{height}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let buf = String::new()
+            + &format!("network: {}\n", self.network)
+            + &format!("launch_date: {}\n", self.launch_date.standard_format())
+            + &format!("block_header_version: {}\n", self.block_header_version)
+            + &format!(
+                "target_block_interval: {} ms\n",
+                self.target_block_interval.to_millis()
+            )
+            + &format!(
+                "minimum_block_time: {} ms\n",
+                self.minimum_block_time.to_millis()
+            )
+            + &format!(
+                "future_dating_limit: {} ms\n",
+                self.future_dating_limit.to_millis()
+            )
+            + &format!("minimum_difficulty: {}\n", self.minimum_difficulty)
+            + &format!("genesis_difficulty: {}\n", self.genesis_difficulty)
+            + &format!(
+                "advance_difficulty_correction_wait: {}\n",
+                self.advance_difficulty_correction_wait
+            )
+            + &format!(
+                "advance_difficulty_correction_factor: {}\n",
+                self.advance_difficulty_correction_factor
+            )
+            + &format!("max_block_size: {}\n", self.max_block_size)
+            + &format!("initial_block_subsidy: {}\n", self.initial_block_subsidy)
+            + &format!("blocks_per_generation: {}\n", self.blocks_per_generation)
+            + &format!("fee_burn_per_mille: {}\n", self.fee_burn_per_mille)
+            + &format!("premine_total: {}\n", self.premine_total)
+            + &format!(
+                "premine_time_lock_period: {} ms\n",
+                self.premine_time_lock_period.to_millis()
+            )
+            + &format!("activation_heights: [{activation_heights}]\n");
+
+        write!(f, "{}", buf)
+    }
+}
+
+impl ChainParams {
+    pub fn for_network(network: Network) -> Self {
+        let params = network.params();
+        Self {
+            network,
+            launch_date: network.launch_date(),
+            block_header_version: BLOCK_HEADER_VERSION.value(),
+            target_block_interval: params.target_block_interval(),
+            minimum_block_time: MINIMUM_BLOCK_TIME,
+            future_dating_limit: FUTUREDATING_LIMIT,
+            minimum_difficulty: params.minimum_difficulty(),
+            genesis_difficulty: Block::genesis_block(network).header().difficulty,
+            advance_difficulty_correction_wait: ADVANCE_DIFFICULTY_CORRECTION_WAIT,
+            advance_difficulty_correction_factor: ADVANCE_DIFFICULTY_CORRECTION_FACTOR,
+            max_block_size: params.max_block_size,
+            initial_block_subsidy: Block::get_mining_reward(BlockHeight::genesis()),
+            blocks_per_generation: BLOCKS_PER_GENERATION,
+            fee_burn_per_mille: FEE_BURN_PER_MILLE,
+            premine_total: Block::premine_distribution(network)
+                .into_iter()
+                .map(|(_address, amount)| amount)
+                .sum(),
+            premine_time_lock_period: PREMINE_TIME_LOCK_PERIOD,
+            activation_heights: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod chain_params_tests {
+    use strum::IntoEnumIterator;
+
+    use super::*;
+
+    #[test]
+    fn premine_total_matches_genesis_coinbase() {
+        for network in Network::iter() {
+            let params = ChainParams::for_network(network);
+            let genesis = Block::genesis_block(network);
+            assert_eq!(
+                genesis.body().transaction_kernel.coinbase,
+                Some(params.premine_total)
+            );
+            assert_eq!(genesis.header().timestamp, params.launch_date);
+        }
+    }
+
+    #[test]
+    fn subsidy_halves_per_generation() {
+        let params = ChainParams::for_network(Network::Main);
+        let second_generation = BlockHeight::from(params.blocks_per_generation);
+        let mut halved = params.initial_block_subsidy;
+        halved.div_two();
+        assert_eq!(halved, Block::get_mining_reward(second_generation));
+    }
+}
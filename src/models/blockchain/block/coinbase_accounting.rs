@@ -0,0 +1,202 @@
+//! Consensus accounting of the coins a block is allowed to mint.
+//!
+//! A block may claim a coinbase no larger than the block subsidy plus the
+//! part of the transaction fee that is not burned. All arithmetic here is done
+//! on unbounded integers so that adversarial fee or coinbase values, such as
+//! negative amounts encoded in two's complement or amounts close to the
+//! representable maximum, cannot overflow into a seemingly valid coinbase.
+
+use num_bigint::BigInt;
+use thiserror::Error;
+
+use super::block_height::BlockHeight;
+use super::Block;
+use crate::models::blockchain::transaction::transaction_kernel::TransactionKernel;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+
+/// Portion of transaction fees, in parts per thousand, that is burned rather
+/// than being made available to the miner's coinbase.
+pub(crate) const FEE_BURN_PER_MILLE: u32 = 0;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CoinbaseAccountingError {
+    #[error("transaction fee is negative: {0}")]
+    NegativeFee(NeptuneCoins),
+
+    #[error("claimed coinbase is negative: {0}")]
+    NegativeCoinbase(NeptuneCoins),
+
+    #[error("claimed coinbase {claimed} exceeds subsidy plus unburned fee {allowed}")]
+    ExcessiveCoinbase {
+        claimed: NeptuneCoins,
+        allowed: NeptuneCoins,
+    },
+}
+
+/// The split of the coins that a block's transaction moves into and out of
+/// circulation, as mandated by consensus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoinbaseAccounting {
+    /// Newly minted coins for this block height.
+    pub subsidy: NeptuneCoins,
+
+    /// Transaction fee paid by the block's transaction.
+    pub fee: NeptuneCoins,
+
+    /// Part of `fee` that is destroyed.
+    pub burned_fee: NeptuneCoins,
+}
+
+impl CoinbaseAccounting {
+    pub fn new(
+        block_height: BlockHeight,
+        fee: NeptuneCoins,
+    ) -> Result<Self, CoinbaseAccountingError> {
+        if fee.is_negative() {
+            return Err(CoinbaseAccountingError::NegativeFee(fee));
+        }
+
+        let burned_nau = fee.to_nau() * BigInt::from(FEE_BURN_PER_MILLE) / BigInt::from(1000);
+        let burned_fee = NeptuneCoins::from_nau(burned_nau)
+            .expect("burned fee cannot exceed fee, which is a valid amount");
+
+        Ok(Self {
+            subsidy: Block::get_mining_reward(block_height),
+            fee,
+            burned_fee,
+        })
+    }
+
+    /// The accounting for the transaction contained in a block at the given
+    /// height.
+    pub fn for_transaction(
+        block_height: BlockHeight,
+        transaction_kernel: &TransactionKernel,
+    ) -> Result<Self, CoinbaseAccountingError> {
+        Self::new(block_height, transaction_kernel.fee)
+    }
+
+    /// Upper bound, in Neptune atomic units, on the coinbase: subsidy plus
+    /// unburned fee.
+    fn max_coinbase_nau(&self) -> BigInt {
+        self.subsidy.to_nau() + self.fee.to_nau() - self.burned_fee.to_nau()
+    }
+
+    /// Verify that the claimed coinbase does not mint more coins than the
+    /// subsidy and the unburned fee.
+    pub fn verify_coinbase(
+        &self,
+        claimed_coinbase: Option<NeptuneCoins>,
+    ) -> Result<(), CoinbaseAccountingError> {
+        let Some(claimed) = claimed_coinbase else {
+            return Ok(());
+        };
+
+        if claimed.is_negative() {
+            return Err(CoinbaseAccountingError::NegativeCoinbase(claimed));
+        }
+
+        let allowed_nau = self.max_coinbase_nau();
+        if claimed.to_nau() > allowed_nau {
+            // `allowed` is only reported, so saturate if it is unrepresentable
+            let allowed = NeptuneCoins::from_nau(allowed_nau).unwrap_or(self.subsidy);
+            return Err(CoinbaseAccountingError::ExcessiveCoinbase { claimed, allowed });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod coinbase_accounting_tests {
+    use num_traits::Zero;
+
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::state::wallet::WalletSecret;
+    use crate::tests::shared::make_mock_block;
+
+    fn block_1_accounting(
+        fee: NeptuneCoins,
+    ) -> Result<CoinbaseAccounting, CoinbaseAccountingError> {
+        CoinbaseAccounting::new(BlockHeight::genesis().next(), fee)
+    }
+
+    #[test]
+    fn coinbase_equal_to_subsidy_plus_fee_is_allowed() {
+        let fee = NeptuneCoins::new(3);
+        let accounting = block_1_accounting(fee).unwrap();
+        let subsidy = accounting.subsidy;
+        assert!(accounting.verify_coinbase(Some(subsidy + fee)).is_ok());
+        assert!(accounting.verify_coinbase(Some(subsidy)).is_ok());
+        assert!(accounting.verify_coinbase(None).is_ok());
+    }
+
+    #[test]
+    fn coinbase_exceeding_subsidy_plus_fee_is_rejected() {
+        let fee = NeptuneCoins::new(3);
+        let accounting = block_1_accounting(fee).unwrap();
+        let too_much = accounting.subsidy + fee + NeptuneCoins::one();
+        assert!(matches!(
+            accounting.verify_coinbase(Some(too_much)),
+            Err(CoinbaseAccountingError::ExcessiveCoinbase { .. })
+        ));
+    }
+
+    #[test]
+    fn negative_fee_cannot_fund_coinbase() {
+        // A negative fee wraps around to a huge unsigned value, which would
+        // pass a naive unsigned comparison.
+        let negative_fee = -NeptuneCoins::new(1);
+        assert_eq!(
+            Err(CoinbaseAccountingError::NegativeFee(negative_fee)),
+            block_1_accounting(negative_fee)
+        );
+    }
+
+    #[test]
+    fn negative_coinbase_is_rejected() {
+        let accounting = block_1_accounting(NeptuneCoins::zero()).unwrap();
+        let negative_coinbase = -NeptuneCoins::new(1);
+        assert_eq!(
+            Err(CoinbaseAccountingError::NegativeCoinbase(negative_coinbase)),
+            accounting.verify_coinbase(Some(negative_coinbase))
+        );
+    }
+
+    #[test]
+    fn huge_fee_does_not_overflow() {
+        let max_positive = NeptuneCoins::from_nau(BigInt::from(u128::MAX >> 1)).unwrap();
+        let accounting = block_1_accounting(max_positive).unwrap();
+        assert!(accounting.verify_coinbase(Some(max_positive)).is_ok());
+    }
+
+    #[test]
+    fn adversarial_block_cannot_mint_via_fee_mismatch() {
+        let network = Network::Main;
+        let genesis_block = Block::genesis_block(network);
+        let address = WalletSecret::new_random()
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+        let (mut block, _, _) = make_mock_block(&genesis_block, None, address, rand::random());
+        let height = block.header().height;
+        let subsidy = Block::get_mining_reward(height);
+
+        // claim the fee as coinbase without the transaction paying it
+        let kernel = &mut block.kernel.body.transaction_kernel;
+        kernel.fee = NeptuneCoins::zero();
+        kernel.coinbase = Some(subsidy + NeptuneCoins::new(1));
+        let accounting = CoinbaseAccounting::for_transaction(height, kernel).unwrap();
+        assert!(accounting.verify_coinbase(kernel.coinbase).is_err());
+
+        // negative fee offsetting an inflated coinbase
+        kernel.fee = -NeptuneCoins::new(1);
+        kernel.coinbase = Some(subsidy + NeptuneCoins::new(1));
+        assert!(CoinbaseAccounting::for_transaction(height, kernel).is_err());
+
+        // honest block
+        kernel.fee = NeptuneCoins::new(1);
+        let accounting = CoinbaseAccounting::for_transaction(height, kernel).unwrap();
+        assert!(accounting.verify_coinbase(kernel.coinbase).is_ok());
+    }
+}
@@ -12,6 +12,7 @@ use num_traits::Zero;
 use rand::Rng;
 use rand_distr::Distribution;
 use rand_distr::Standard;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use tasm_lib::triton_vm::prelude::BFieldCodec;
@@ -19,9 +20,9 @@ use tasm_lib::triton_vm::prelude::BFieldElement;
 use tasm_lib::triton_vm::prelude::Digest;
 
 use super::block_height::BlockHeight;
+use crate::config_models::network_params::NetworkParams;
 use crate::models::blockchain::block::block_header::ADVANCE_DIFFICULTY_CORRECTION_FACTOR;
 use crate::models::blockchain::block::block_header::ADVANCE_DIFFICULTY_CORRECTION_WAIT;
-use crate::models::blockchain::block::block_header::TARGET_BLOCK_INTERVAL;
 use crate::models::proof_abstractions::timestamp::Timestamp;
 
 const DIFFICULTY_NUM_LIMBS: usize = 5;
@@ -35,7 +36,17 @@ const DIFFICULTY_NUM_LIMBS: usize = 5;
 /// The `Difficulty` is set by the `difficulty_control` mechanism such that the
 /// target block interval is by actual block times.
 #[derive(
-    Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, BFieldCodec, GetSize, Arbitrary,
+    Copy,
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    BFieldCodec,
+    GetSize,
+    Arbitrary,
+    JsonSchema,
 )]
 pub struct Difficulty([u32; DIFFICULTY_NUM_LIMBS]);
 
@@ -211,7 +222,17 @@ const POW_NUM_LIMBS: usize = 6;
 /// two forks of different height, a node will choose the one with the greater
 /// amount of proof-of-work.
 #[derive(
-    Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, BFieldCodec, GetSize, Arbitrary,
+    Copy,
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    BFieldCodec,
+    GetSize,
+    Arbitrary,
+    JsonSchema,
 )]
 pub struct ProofOfWork([u32; POW_NUM_LIMBS]);
 
@@ -318,6 +339,9 @@ impl Distribution<ProofOfWork> for Standard {
 /// timestamp. It regulates the block interval by tuning the difficulty.
 /// It assumes that the block timestamp is valid.
 ///
+/// The target block interval and the minimum difficulty are those of
+/// `network_params`, unless `target_block_interval` overrides the former.
+///
 /// This mechanism is a PID controller with P = -2^-4 (and I = D = 0) and with
 /// with a few modifications such as clamping and advance correction.
 /// The following diagram describes the mechanism.
@@ -369,6 +393,7 @@ pub(crate) fn difficulty_control(
     mut old_difficulty: Difficulty,
     target_block_interval: Option<Timestamp>,
     previous_block_height: BlockHeight,
+    network_params: &NetworkParams,
 ) -> Difficulty {
     // no adjustment if the previous block is the genesis block
     if previous_block_height.is_genesis() {
@@ -378,7 +403,8 @@ pub(crate) fn difficulty_control(
     // otherwise, compute PID control signal
 
     // target; signal to follow
-    let target_block_interval = target_block_interval.unwrap_or(TARGET_BLOCK_INTERVAL);
+    let target_block_interval =
+        target_block_interval.unwrap_or_else(|| network_params.target_block_interval());
 
     // most recent observed block time
     let delta_t = new_timestamp - old_timestamp;
@@ -423,10 +449,67 @@ pub(crate) fn difficulty_control(
     if overflow > 0 {
         Difficulty::MAXIMUM
     } else {
-        new_difficulty
+        new_difficulty.max(network_params.minimum_difficulty())
     }
 }
 
+/// Maximum number of blocks [`simulate`] runs for.
+pub const MAX_SIMULATED_BLOCKS: usize = 100_000;
+
+/// A block in a difficulty simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SimulatedBlock {
+    pub height: BlockHeight,
+
+    /// Time since the predecessor
+    pub block_interval: Timestamp,
+
+    /// The difficulty for the *next* block, as it would be set in this
+    /// block's header
+    pub difficulty: Difficulty,
+}
+
+/// Replay [`difficulty_control`] over a synthetic sequence of times between
+/// blocks, starting from a block at `start_height` with `start_difficulty`.
+/// Returns the difficulty set by each following block, at most
+/// [`MAX_SIMULATED_BLOCKS`] many.
+///
+/// Intervals shorter than the minimum block time would make the blocks
+/// invalid, but are simulated as given.
+pub fn simulate(
+    start_difficulty: Difficulty,
+    start_height: BlockHeight,
+    block_intervals: &[Timestamp],
+    target_block_interval: Option<Timestamp>,
+    network_params: &NetworkParams,
+) -> Vec<SimulatedBlock> {
+    let mut difficulty = start_difficulty;
+    let mut height = start_height;
+    let mut timestamp = Timestamp::zero();
+    block_intervals
+        .iter()
+        .take(MAX_SIMULATED_BLOCKS)
+        .map(|&block_interval| {
+            let new_timestamp = timestamp + block_interval;
+            difficulty = difficulty_control(
+                new_timestamp,
+                timestamp,
+                difficulty,
+                target_block_interval,
+                height,
+                network_params,
+            );
+            timestamp = new_timestamp;
+            height = height.next();
+            SimulatedBlock {
+                height,
+                block_interval,
+                difficulty,
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use itertools::Itertools;
@@ -447,6 +530,8 @@ mod test {
     use test_strategy::proptest;
 
     use super::difficulty_control;
+    use super::simulate;
+    use crate::config_models::network_params::NetworkParams;
     use crate::models::blockchain::block::block_header::ADVANCE_DIFFICULTY_CORRECTION_FACTOR;
     use crate::models::blockchain::block::block_header::ADVANCE_DIFFICULTY_CORRECTION_WAIT;
     use crate::models::blockchain::block::block_height::BlockHeight;
@@ -597,6 +682,7 @@ mod test {
                     difficulty,
                     Some(target_block_interval),
                     block_height,
+                    NetworkParams::standard(),
                 );
                 block_height = block_height.next();
             }
@@ -643,6 +729,7 @@ mod test {
             old_difficulty,
             Some(target_block_interval),
             previous_block_height,
+            NetworkParams::standard(),
         );
     }
 
@@ -723,4 +810,46 @@ mod test {
         running_diff >>= a;
         prop_assert_eq!(diff >> a, running_diff);
     }
+
+    #[test]
+    fn simulation_follows_hash_rate_swings() {
+        let target = Timestamp::minutes(10);
+        let start = Difficulty::from(1_000_000u32);
+        let height = BlockHeight::genesis().next();
+        let params = NetworkParams::standard();
+
+        let on_target = simulate(start, height, &[target; 10], Some(target), params);
+        assert!(on_target.iter().all(|block| block.difficulty == start));
+        assert_eq!(height + 10, on_target.last().unwrap().height);
+
+        // hash rate doubles: blocks come too fast, so difficulty rises
+        let faster = simulate(
+            start,
+            height,
+            &[Timestamp::minutes(5); 10],
+            Some(target),
+            params,
+        );
+        assert!(faster.windows(2).all(|w| w[0].difficulty < w[1].difficulty));
+
+        // hash rate halves: blocks come too slowly, so difficulty falls
+        let slower = simulate(
+            start,
+            height,
+            &[Timestamp::minutes(20); 10],
+            Some(target),
+            params,
+        );
+        assert!(slower.windows(2).all(|w| w[0].difficulty > w[1].difficulty));
+
+        // no adjustment on top of genesis
+        let after_genesis = simulate(
+            start,
+            BlockHeight::genesis(),
+            &[Timestamp::minutes(5)],
+            Some(target),
+            params,
+        );
+        assert_eq!(start, after_genesis[0].difficulty);
+    }
 }
@@ -0,0 +1,296 @@
+//! Simulation of the difficulty control mechanism against synthetic hash
+//! rates, for evaluating changes to its parameters.
+//!
+//! Each simulated block takes a proving time, during which no guessing
+//! happens, followed by a guessing time that is sampled from the hash rate
+//! and the difficulty set by [`difficulty_control`], taking advance correction
+//! into account. The next difficulty is then set by [`difficulty_control`]
+//! itself, such that a change to it shows up here as is.
+//!
+//! Only compiled with the `difficulty-sim` feature, and in unit tests.
+
+use std::io;
+use std::io::Write;
+
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use num_traits::Zero;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rand_distr::Distribution;
+use rand_distr::Exp;
+
+use super::block_header::ADVANCE_DIFFICULTY_CORRECTION_FACTOR;
+use super::block_header::ADVANCE_DIFFICULTY_CORRECTION_WAIT;
+use super::block_height::BlockHeight;
+use super::difficulty_control::difficulty_control;
+use super::difficulty_control::Difficulty;
+use crate::config_models::network_params::NetworkParams;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+
+/// Hash rate, in hashes per second, as a function of the number of blocks
+/// since the start of the simulation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HashRateSchedule {
+    Constant(f64),
+
+    /// `before` up to block `at`, and `after` from then on
+    Step {
+        before: f64,
+        after: f64,
+        at: usize,
+    },
+
+    /// Sine wave around `mean`, deviating by at most `amplitude` times the
+    /// mean, and repeating every `period` blocks
+    Oscillation {
+        mean: f64,
+        amplitude: f64,
+        period: usize,
+    },
+
+    /// `initial`, multiplied by `1 + growth_per_block` every block
+    ExponentialGrowth {
+        initial: f64,
+        growth_per_block: f64,
+    },
+}
+
+impl HashRateSchedule {
+    pub fn hash_rate(&self, block: usize) -> f64 {
+        match *self {
+            Self::Constant(hash_rate) => hash_rate,
+            Self::Step { before, after, at } => {
+                if block < at {
+                    before
+                } else {
+                    after
+                }
+            }
+            Self::Oscillation {
+                mean,
+                amplitude,
+                period,
+            } => {
+                let phase = std::f64::consts::TAU * (block as f64) / (period as f64);
+                mean * (1.0 + amplitude * phase.sin())
+            }
+            Self::ExponentialGrowth {
+                initial,
+                growth_per_block,
+            } => initial * (1.0 + growth_per_block).powi(block as i32),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DifficultySimulation {
+    pub schedule: HashRateSchedule,
+
+    /// Seconds spent proving before guessing can start
+    pub proving_time: f64,
+    pub target_block_interval: Timestamp,
+    pub num_blocks: usize,
+    pub seed: u64,
+}
+
+/// A block in a [`DifficultySimulation`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimulationRecord {
+    pub height: BlockHeight,
+    pub hash_rate: f64,
+
+    /// Seconds since the predecessor
+    pub block_time: f64,
+
+    /// The difficulty set by this block for the next one
+    pub difficulty: Difficulty,
+}
+
+impl DifficultySimulation {
+    /// Run the simulation, starting from the difficulty at which the initial
+    /// hash rate finds blocks at the target interval.
+    pub fn run(&self) -> Vec<SimulationRecord> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let target = self.target_seconds();
+        let mut difficulty =
+            difficulty_from_f64(self.schedule.hash_rate(0) * (target - self.proving_time));
+        let mut height = BlockHeight::genesis().next();
+        let mut timestamp = Timestamp::zero();
+
+        let mut records = Vec::with_capacity(self.num_blocks);
+        for block in 0..self.num_blocks {
+            let hash_rate = self.schedule.hash_rate(block);
+            let block_time = self.sample_block_time(hash_rate, difficulty, &mut rng);
+            let new_timestamp = timestamp + Timestamp::millis((block_time * 1000.0).round() as u64);
+            difficulty = difficulty_control(
+                new_timestamp,
+                timestamp,
+                difficulty,
+                Some(self.target_block_interval),
+                height,
+                NetworkParams::standard(),
+            );
+            timestamp = new_timestamp;
+            height = height.next();
+            records.push(SimulationRecord {
+                height,
+                hash_rate,
+                block_time,
+                difficulty,
+            });
+        }
+
+        records
+    }
+
+    fn target_seconds(&self) -> f64 {
+        self.target_block_interval.to_millis() as f64 / 1000.0
+    }
+
+    /// Guessing succeeds at a rate of `hash_rate / difficulty` per second.
+    /// Once the block time exceeds the target interval, the difficulty drops
+    /// with every further `ADVANCE_DIFFICULTY_CORRECTION_WAIT` target
+    /// intervals, as [`difficulty_control`] does.
+    fn sample_block_time(&self, hash_rate: f64, difficulty: Difficulty, rng: &mut StdRng) -> f64 {
+        let target = self.target_seconds();
+        let window = target * ADVANCE_DIFFICULTY_CORRECTION_WAIT as f64;
+        let shift = ADVANCE_DIFFICULTY_CORRECTION_FACTOR.ilog2() as usize;
+
+        let mut block_time = self.proving_time;
+        let mut effective_difficulty = difficulty;
+        let mut window_end = target + window;
+        loop {
+            let rate = hash_rate / difficulty_to_f64(effective_difficulty);
+            let guessing_time = Exp::new(rate).unwrap().sample(rng);
+            if block_time + guessing_time < window_end {
+                return block_time + guessing_time;
+            }
+
+            // The rate is constant within a window, and guessing memoryless.
+            block_time = block_time.max(window_end);
+            window_end += window;
+            effective_difficulty >>= shift;
+        }
+    }
+}
+
+/// Write the records as CSV, with block times in seconds.
+pub fn write_csv(records: &[SimulationRecord], mut writer: impl Write) -> io::Result<()> {
+    writeln!(writer, "height,hash_rate,block_time,difficulty")?;
+    for record in records {
+        writeln!(
+            writer,
+            "{},{},{:.3},{}",
+            record.height, record.hash_rate, record.block_time, record.difficulty
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Mean block time, in seconds, of the records.
+pub fn mean_block_time(records: &[SimulationRecord]) -> f64 {
+    records.iter().map(|record| record.block_time).sum::<f64>() / records.len() as f64
+}
+
+fn difficulty_to_f64(difficulty: Difficulty) -> f64 {
+    BigUint::from(difficulty).to_f64().unwrap()
+}
+
+fn difficulty_from_f64(value: f64) -> Difficulty {
+    let value = value as u128;
+    Difficulty::new([
+        value as u32,
+        (value >> 32) as u32,
+        (value >> 64) as u32,
+        (value >> 96) as u32,
+        0,
+    ])
+}
+
+#[cfg(test)]
+mod difficulty_sim_tests {
+    use super::*;
+
+    /// Fraction by which the mean block time may deviate from the target,
+    /// covering the controller's systematic error of up to 5%.
+    const MARGIN: f64 = 0.1;
+
+    /// Blocks after the start or a change of hash rate that are not held to
+    /// the margin.
+    const ADJUSTMENT_PERIOD: usize = 500;
+
+    fn simulation(schedule: HashRateSchedule, num_blocks: usize) -> DifficultySimulation {
+        DifficultySimulation {
+            schedule,
+            proving_time: 60.0,
+            target_block_interval: Timestamp::seconds(600),
+            num_blocks,
+            seed: 5550001,
+        }
+    }
+
+    fn assert_tracks_target(records: &[SimulationRecord]) {
+        let mean = mean_block_time(records);
+        assert!(
+            (mean - 600.0).abs() < 600.0 * MARGIN,
+            "mean block time {mean} deviates from the target by more than {MARGIN}"
+        );
+    }
+
+    #[test]
+    fn constant_hash_rate_is_stable() {
+        let records = simulation(HashRateSchedule::Constant(1e6), 4000).run();
+        assert_tracks_target(&records[ADJUSTMENT_PERIOD..]);
+    }
+
+    #[test]
+    fn recovers_from_step_changes() {
+        let at = 2000;
+        for after in [1e8, 1e4] {
+            let schedule = HashRateSchedule::Step {
+                before: 1e6,
+                after,
+                at,
+            };
+            let records = simulation(schedule, at + 4000).run();
+            assert_tracks_target(&records[ADJUSTMENT_PERIOD..at]);
+            assert_tracks_target(&records[at + ADJUSTMENT_PERIOD..]);
+        }
+    }
+
+    #[test]
+    fn tracks_oscillating_hash_rate() {
+        let schedule = HashRateSchedule::Oscillation {
+            mean: 1e6,
+            amplitude: 0.5,
+            period: 200,
+        };
+        let records = simulation(schedule, 4000).run();
+        assert_tracks_target(&records[ADJUSTMENT_PERIOD..]);
+    }
+
+    #[test]
+    fn tracks_exponentially_growing_hash_rate() {
+        let schedule = HashRateSchedule::ExponentialGrowth {
+            initial: 1e6,
+            growth_per_block: 0.002,
+        };
+        let records = simulation(schedule, 4000).run();
+        assert_tracks_target(&records[ADJUSTMENT_PERIOD..]);
+    }
+
+    #[test]
+    fn csv_has_header_and_one_line_per_block() {
+        let records = simulation(HashRateSchedule::Constant(1e6), 10).run();
+        let mut csv = vec![];
+        write_csv(&records, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(11, lines.len());
+        assert_eq!("height,hash_rate,block_time,difficulty", lines[0]);
+        assert!(lines[1].starts_with("2,1000000,"));
+    }
+}
@@ -0,0 +1,104 @@
+//! The fork choice rule: which of two chains a node follows.
+//!
+//! Chains are compared by their tips, which are assumed to be valid and to
+//! carry the accumulated proof-of-work they declare. The rule is applied in
+//! two places: in `peer_loop`, which only passes blocks on to `main_loop` if
+//! they win against the current tip, and in `main_loop` again, as the tip may
+//! have changed in the meantime through another peer or the own miner.
+
+use std::cmp::Ordering;
+
+use super::block_height::BlockHeight;
+use super::Block;
+
+/// Number of blocks on top of a block after which it is considered final.
+/// Not enforced by the fork choice rule, which always follows the heaviest
+/// chain; a reorganization abandoning final blocks is reported loudly.
+pub const FINALITY_DEPTH: u64 = 100;
+
+/// Compare two chains by their tips. `Greater` if the chain of `tip_a` is
+/// preferred, `Less` if that of `tip_b` is, and `Equal` if neither is.
+///
+///  - Tips at different heights are compared by accumulated proof-of-work.
+///  - Of two tips at the same height, one whose transaction spends inputs is
+///    preferred over one whose transaction does not.
+pub fn compare_chains(tip_a: &Block, tip_b: &Block) -> Ordering {
+    if tip_a.hash() == tip_b.hash() {
+        return Ordering::Equal;
+    }
+
+    if tip_a.header().height != tip_b.header().height {
+        return tip_a
+            .header()
+            .cumulative_proof_of_work
+            .cmp(&tip_b.header().cumulative_proof_of_work);
+    }
+
+    spends_inputs(tip_a).cmp(&spends_inputs(tip_b))
+}
+
+/// Whether the node should switch from the chain of `current_tip` to that of
+/// `incoming_block`. If the chains compare equal, the current one is kept,
+/// unless they are distinct tips at the same height and the current tip spends
+/// no inputs.
+pub fn incoming_block_wins(current_tip: &Block, incoming_block: &Block) -> bool {
+    match compare_chains(current_tip, incoming_block) {
+        Ordering::Less => true,
+        Ordering::Greater => false,
+        Ordering::Equal => {
+            current_tip.hash() != incoming_block.hash()
+                && current_tip.header().height == incoming_block.header().height
+                && !spends_inputs(current_tip)
+        }
+    }
+}
+
+/// Whether a block at `height` is final given a tip at `tip_height`.
+pub fn is_final(height: BlockHeight, tip_height: BlockHeight) -> bool {
+    tip_height - height >= FINALITY_DEPTH as i128
+}
+
+fn spends_inputs(block: &Block) -> bool {
+    !block.body().transaction_kernel.inputs.is_empty()
+}
+
+#[cfg(test)]
+mod fork_choice_tests {
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::state::wallet::WalletSecret;
+    use crate::tests::shared::make_mock_block;
+
+    #[test]
+    fn heavier_chain_and_incumbent_win() {
+        let address = WalletSecret::new_random()
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+        let genesis = Block::genesis_block(Network::RegTest);
+        let (block_1_a, _, _) = make_mock_block(&genesis, None, address, rand::random());
+        let (block_1_b, _, _) = make_mock_block(&genesis, None, address, rand::random());
+
+        assert_eq!(Ordering::Less, compare_chains(&genesis, &block_1_a));
+        assert_eq!(Ordering::Greater, compare_chains(&block_1_a, &genesis));
+        assert!(incoming_block_wins(&genesis, &block_1_a));
+        assert!(!incoming_block_wins(&block_1_a, &genesis));
+
+        assert_eq!(Ordering::Equal, compare_chains(&block_1_a, &block_1_a));
+        assert!(!incoming_block_wins(&block_1_a, &block_1_a));
+
+        // Neither spends inputs, so the current tip is replaced.
+        assert_eq!(Ordering::Equal, compare_chains(&block_1_a, &block_1_b));
+        assert!(incoming_block_wins(&block_1_a, &block_1_b));
+    }
+
+    #[test]
+    fn blocks_become_final_at_finality_depth() {
+        let height = BlockHeight::from(10u64);
+        assert!(!is_final(height, height));
+        assert!(!is_final(
+            height,
+            BlockHeight::from(10 + FINALITY_DEPTH - 1)
+        ));
+        assert!(is_final(height, BlockHeight::from(10 + FINALITY_DEPTH)));
+    }
+}
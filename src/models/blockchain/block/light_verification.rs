@@ -0,0 +1,270 @@
+//! Consensus rules that need neither the node's state nor block proofs.
+//!
+//! Light clients, for instance on mobile devices or in the browser, follow the
+//! chain of block headers and apply the mutator set updates of the blocks they
+//! care about, without storing or proving anything. This module holds the
+//! rules they need as plain functions on headers, digests and accumulators.
+//! It uses neither tokio nor the databases, and the node's own block
+//! validation calls into it, such that light clients embed exactly the
+//! consensus code that full nodes run.
+
+use serde::Deserialize;
+use serde::Serialize;
+use tasm_lib::triton_vm::prelude::BFieldCodec;
+use tasm_lib::twenty_first::math::digest::Digest;
+use tasm_lib::twenty_first::prelude::MerkleTreeMaker;
+use tasm_lib::twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
+use tasm_lib::twenty_first::util_types::merkle_tree::CpuParallel;
+use thiserror::Error;
+
+use super::block_header::BlockHeader;
+use super::block_header::ADVANCE_DIFFICULTY_CORRECTION_FACTOR;
+use super::block_header::ADVANCE_DIFFICULTY_CORRECTION_WAIT;
+use super::block_header::MINIMUM_BLOCK_TIME;
+use super::block_height::BlockHeight;
+use super::difficulty_control::difficulty_control;
+use super::difficulty_control::Difficulty;
+use super::difficulty_control::ProofOfWork;
+use super::mutator_set_update::MutatorSetUpdate;
+use super::FUTUREDATING_LIMIT;
+use crate::config_models::network_params::NetworkParams;
+use crate::models::blockchain::shared::Hash;
+use crate::models::proof_abstractions::mast_hash::MastHash;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::util_types::mutator_set::addition_record::AdditionRecord;
+use crate::util_types::mutator_set::mutator_set_accumulator::MutatorSetAccumulator;
+use crate::util_types::mutator_set::removal_record::RemovalRecord;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum LightVerificationError {
+    #[error("block height {height} does not follow previous height {previous_height}")]
+    Height {
+        height: BlockHeight,
+        previous_height: BlockHeight,
+    },
+
+    #[error("block does not point to the previous block")]
+    PrevBlockDigest,
+
+    #[error("block timestamp {timestamp} is less than the minimum block time after {previous_timestamp}")]
+    MinimumBlockTime {
+        timestamp: Timestamp,
+        previous_timestamp: Timestamp,
+    },
+
+    #[error("block difficulty {actual} does not match expected difficulty {expected}")]
+    Difficulty {
+        actual: Difficulty,
+        expected: Difficulty,
+    },
+
+    #[error("block's cumulative proof-of-work does not match the previous block's")]
+    CumulativeProofOfWork,
+
+    #[error("block timestamp {0} is too far in the future")]
+    FutureDating(Timestamp),
+
+    #[error("mutator set update cannot be applied: {0}")]
+    MutatorSetUpdate(String),
+}
+
+/// A block header along with the digests that, together with the header,
+/// determine the block's digest. This is all a light client needs to follow
+/// the chain of proof-of-work.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BFieldCodec)]
+pub struct LightBlockHeader {
+    pub header: BlockHeader,
+    pub body_mast_hash: Digest,
+
+    /// Hash of the encoded block appendix
+    pub appendix_digest: Digest,
+}
+
+impl LightBlockHeader {
+    /// The digest of the block, identical to that of the full block.
+    pub fn hash(&self) -> Digest {
+        // The leaves of the block kernel's Merkle tree, padded to a power of
+        // two, as in [`MastHash`].
+        let leaves = [
+            Hash::hash_varlen(&self.header.mast_hash().encode()),
+            Hash::hash_varlen(&self.body_mast_hash.encode()),
+            self.appendix_digest,
+            Digest::default(),
+        ];
+        CpuParallel::from_digests(&leaves).unwrap().root()
+    }
+}
+
+/// The difficulty that a block with the given timestamp must have on the
+/// network with the given parameters.
+pub fn expected_difficulty(
+    timestamp: Timestamp,
+    previous_header: &BlockHeader,
+    target_block_interval: Option<Timestamp>,
+    network_params: &NetworkParams,
+) -> Difficulty {
+    difficulty_control(
+        timestamp,
+        previous_header.timestamp,
+        previous_header.difficulty,
+        target_block_interval,
+        previous_header.height,
+        network_params,
+    )
+}
+
+/// The cumulative proof-of-work that a block succeeding the given one must
+/// have.
+pub fn expected_cumulative_proof_of_work(previous_header: &BlockHeader) -> ProofOfWork {
+    previous_header.cumulative_proof_of_work + previous_header.difficulty
+}
+
+/// Determine whether the proof-of-work puzzle was solved correctly.
+///
+/// The block digest must not exceed the target of the previous block's
+/// difficulty. If the timestamp difference exceeds the target block interval
+/// by a factor `ADVANCE_DIFFICULTY_CORRECTION_WAIT`, then the effective
+/// difficulty is reduced by a factor `ADVANCE_DIFFICULTY_CORRECTION_FACTOR`.
+pub fn has_proof_of_work(
+    block_digest: Digest,
+    header: &BlockHeader,
+    previous_header: &BlockHeader,
+    network_params: &NetworkParams,
+) -> bool {
+    if block_digest <= previous_header.difficulty.target() {
+        return true;
+    }
+
+    let delta_t = header.timestamp - previous_header.timestamp;
+    let target_block_interval = network_params.target_block_interval();
+    let Ok(excess_multiple) =
+        usize::try_from(delta_t.to_millis() / target_block_interval.to_millis())
+    else {
+        return false;
+    };
+    let shift = usize::try_from(ADVANCE_DIFFICULTY_CORRECTION_FACTOR.ilog2()).unwrap()
+        * (excess_multiple >> usize::try_from(ADVANCE_DIFFICULTY_CORRECTION_WAIT.ilog2()).unwrap());
+    let effective_difficulty = previous_header.difficulty >> shift;
+
+    block_digest <= effective_difficulty.target()
+}
+
+/// Verify that a block header succeeds the previous one, which is assumed
+/// valid. Note that this function does **not** check that the block has enough
+/// proof-of-work; that must be done separately, by calling
+/// [`has_proof_of_work`].
+pub fn verify_header(
+    light_header: &LightBlockHeader,
+    previous: &LightBlockHeader,
+    now: Timestamp,
+    network_params: &NetworkParams,
+) -> Result<(), LightVerificationError> {
+    let header = &light_header.header;
+    let previous_header = &previous.header;
+
+    if previous_header.height.next() != header.height {
+        return Err(LightVerificationError::Height {
+            height: header.height,
+            previous_height: previous_header.height,
+        });
+    }
+
+    if previous.hash() != header.prev_block_digest {
+        return Err(LightVerificationError::PrevBlockDigest);
+    }
+
+    if previous_header.timestamp + MINIMUM_BLOCK_TIME > header.timestamp {
+        return Err(LightVerificationError::MinimumBlockTime {
+            timestamp: header.timestamp,
+            previous_timestamp: previous_header.timestamp,
+        });
+    }
+
+    let expected = expected_difficulty(header.timestamp, previous_header, None, network_params);
+    if header.difficulty != expected {
+        return Err(LightVerificationError::Difficulty {
+            actual: header.difficulty,
+            expected,
+        });
+    }
+
+    if header.cumulative_proof_of_work != expected_cumulative_proof_of_work(previous_header) {
+        return Err(LightVerificationError::CumulativeProofOfWork);
+    }
+
+    if header.timestamp >= now + FUTUREDATING_LIMIT {
+        return Err(LightVerificationError::FutureDating(header.timestamp));
+    }
+
+    Ok(())
+}
+
+/// Apply the inputs and outputs of a block's transaction to the mutator set
+/// accumulator of the previous block, resulting in that of the block.
+pub fn apply_mutator_set_update(
+    previous_mutator_set_accumulator: &MutatorSetAccumulator,
+    removals: Vec<RemovalRecord>,
+    additions: Vec<AdditionRecord>,
+) -> Result<MutatorSetAccumulator, LightVerificationError> {
+    let mut mutator_set_accumulator = previous_mutator_set_accumulator.clone();
+    MutatorSetUpdate::new(removals, additions)
+        .apply_to_accumulator_batched(&mut mutator_set_accumulator)
+        .map_err(|err| LightVerificationError::MutatorSetUpdate(err.to_string()))?;
+
+    Ok(mutator_set_accumulator)
+}
+
+#[cfg(test)]
+mod light_verification_tests {
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::blockchain::block::Block;
+    use crate::models::state::wallet::WalletSecret;
+    use crate::tests::shared::make_mock_block;
+
+    #[test]
+    fn light_headers_verify_like_blocks() {
+        let network = Network::RegTest;
+        let address = WalletSecret::new_random()
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+        let genesis = Block::genesis_block(network);
+        let (block_1, _, _) = make_mock_block(&genesis, None, address, rand::random());
+
+        let light_genesis = genesis.light_header();
+        let light_block_1 = block_1.light_header();
+        assert_eq!(genesis.hash(), light_genesis.hash());
+        assert_eq!(block_1.hash(), light_block_1.hash());
+
+        let now = block_1.header().timestamp;
+        let params = network.params();
+        assert_eq!(
+            Ok(()),
+            verify_header(&light_block_1, &light_genesis, now, params)
+        );
+
+        let mut wrong_parent = light_block_1.clone();
+        wrong_parent.header.prev_block_digest = Digest::default();
+        assert_eq!(
+            Err(LightVerificationError::PrevBlockDigest),
+            verify_header(&wrong_parent, &light_genesis, now, params)
+        );
+
+        let mut too_early = light_block_1.clone();
+        too_early.header.timestamp = genesis.header().timestamp;
+        assert!(matches!(
+            verify_header(&too_early, &light_genesis, now, params),
+            Err(LightVerificationError::MinimumBlockTime { .. })
+        ));
+
+        let transaction_kernel = &block_1.body().transaction_kernel;
+        assert_eq!(
+            Ok(block_1.body().mutator_set_accumulator.clone()),
+            apply_mutator_set_update(
+                &genesis.body().mutator_set_accumulator,
+                transaction_kernel.inputs.clone(),
+                transaction_kernel.outputs.clone(),
+            )
+        );
+    }
+}
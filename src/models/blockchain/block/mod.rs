@@ -5,8 +5,16 @@ pub mod block_height;
 pub mod block_info;
 pub mod block_kernel;
 pub mod block_selector;
+pub mod chain_params;
+pub mod coinbase_accounting;
 pub mod difficulty_control;
+#[cfg(any(test, feature = "difficulty-sim"))]
+pub mod difficulty_sim;
+pub mod fork_choice;
+pub mod light_verification;
 pub mod mutator_set_update;
+pub mod validation_checkpoints;
+pub mod validation_trace;
 pub mod validity;
 
 use std::sync::OnceLock;
@@ -14,20 +22,21 @@ use std::sync::OnceLock;
 use block_appendix::BlockAppendix;
 use block_body::BlockBody;
 use block_header::BlockHeader;
-use block_header::ADVANCE_DIFFICULTY_CORRECTION_FACTOR;
-use block_header::ADVANCE_DIFFICULTY_CORRECTION_WAIT;
 use block_header::BLOCK_HEADER_VERSION;
 use block_header::MINIMUM_BLOCK_TIME;
-use block_header::TARGET_BLOCK_INTERVAL;
 use block_height::BlockHeight;
 use block_kernel::BlockKernel;
+use coinbase_accounting::CoinbaseAccounting;
 use difficulty_control::Difficulty;
 use difficulty_control::ProofOfWork;
 use get_size::GetSize;
 use itertools::Itertools;
+use light_verification::LightBlockHeader;
 use mutator_set_update::MutatorSetUpdate;
 use num_traits::ConstZero;
 use num_traits::Zero;
+use rayon::iter::IntoParallelRefIterator;
+use rayon::iter::ParallelIterator;
 use serde::Deserialize;
 use serde::Serialize;
 use tasm_lib::triton_vm::prelude::*;
@@ -40,6 +49,8 @@ use twenty_first::math::b_field_element::BFieldElement;
 use twenty_first::math::bfield_codec::BFieldCodec;
 use twenty_first::math::digest::Digest;
 use twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
+use validation_checkpoints::ValidationCheckpoints;
+use validation_trace::ValidationTracer;
 use validity::appendix_witness::AppendixWitness;
 use validity::block_primitive_witness::BlockPrimitiveWitness;
 use validity::block_program::BlockProgram;
@@ -50,6 +61,7 @@ use super::transaction::Transaction;
 use super::type_scripts::neptune_coins::NeptuneCoins;
 use super::type_scripts::time_lock::TimeLock;
 use crate::config_models::network::Network;
+use crate::config_models::network_params::NetworkParams;
 use crate::models::blockchain::block::difficulty_control::difficulty_control;
 use crate::models::blockchain::shared::Hash;
 use crate::models::proof_abstractions::mast_hash::MastHash;
@@ -60,6 +72,7 @@ use crate::models::proof_abstractions::SecretWitness;
 use crate::models::state::wallet::address::ReceivingAddress;
 use crate::models::state::wallet::WalletSecret;
 use crate::prelude::twenty_first;
+use crate::util_types::fault_injection::BlockValidationStep;
 use crate::util_types::mutator_set::commit;
 use crate::util_types::mutator_set::mutator_set_accumulator::MutatorSetAccumulator;
 
@@ -71,6 +84,14 @@ use crate::util_types::mutator_set::mutator_set_accumulator::MutatorSetAccumulat
 /// blocks with many outputs.
 pub(crate) const MAX_BLOCK_SIZE: usize = 250_000;
 
+/// Blocks with a timestamp this far or further ahead of the validating node's
+/// clock are invalid.
+pub(crate) const FUTUREDATING_LIMIT: Timestamp = Timestamp::hours(2);
+
+/// Duration, counted from the network's launch date, during which premine
+/// UTXOs are time-locked.
+pub(crate) const PREMINE_TIME_LOCK_PERIOD: Timestamp = Timestamp::months(6);
+
 /// All blocks have proofs except the genesis block
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, BFieldCodec, GetSize, Default)]
 pub enum BlockProof {
@@ -173,6 +194,7 @@ impl Block {
             predecessor.header().difficulty,
             target_block_interval,
             predecessor.header().height,
+            NetworkParams::in_effect(),
         );
 
         let new_cumulative_proof_of_work: ProofOfWork =
@@ -195,8 +217,8 @@ impl Block {
 
     /// Create a block template with an invalid block proof.
     ///
-    /// To be used in tests where you don't care about block validity.
-    #[cfg(test)]
+    /// To be used in tests where you don't care about block validity, and for
+    /// blocks generated without proofs on RegTest.
     pub(crate) fn block_template_invalid_proof(
         predecessor: &Block,
         transaction: Transaction,
@@ -311,6 +333,16 @@ impl Block {
         &self.kernel.appendix
     }
 
+    /// The header along with the digests needed to compute the block digest
+    /// from it, for light clients.
+    pub fn light_header(&self) -> LightBlockHeader {
+        LightBlockHeader {
+            header: self.kernel.header.clone(),
+            body_mast_hash: self.kernel.body.mast_hash(),
+            appendix_digest: Hash::hash_varlen(&self.kernel.appendix.encode()),
+        }
+    }
+
     /// note: this causes block digest to change to that of the new block.
     #[inline]
     pub fn set_block(&mut self, block: Block) {
@@ -328,7 +360,7 @@ impl Block {
     }
 
     pub fn genesis_block(network: Network) -> Self {
-        let premine_distribution = Self::premine_distribution();
+        let premine_distribution = Self::premine_distribution(network);
         let total_premine_amount = premine_distribution
             .iter()
             .map(|(_receiving_address, amount)| *amount)
@@ -383,7 +415,7 @@ impl Block {
             // TODO: to be set to something difficult to predict ahead of time
             nonce: [bfe!(0), bfe!(0), bfe!(0)],
             cumulative_proof_of_work: ProofOfWork::zero(),
-            difficulty: Difficulty::MINIMUM,
+            difficulty: network.params().minimum_difficulty(),
         };
 
         let appendix = BlockAppendix::default();
@@ -396,10 +428,23 @@ impl Block {
     /// kernels. The net result is that broadcasting transaction on other
     /// networks invalidates the lock script proofs.
     pub(crate) fn premine_sender_randomness(network: Network) -> Digest {
-        Digest::new([bfe!(network as u64), bfe!(0), bfe!(0), bfe!(0), bfe!(0)])
+        Digest::new([
+            bfe!(network.genesis_seed()),
+            bfe!(0),
+            bfe!(0),
+            bfe!(0),
+            bfe!(0),
+        ])
     }
 
-    fn premine_distribution() -> Vec<(ReceivingAddress, NeptuneCoins)> {
+    fn premine_distribution(network: Network) -> Vec<(ReceivingAddress, NeptuneCoins)> {
+        if network == Network::Custom {
+            return network
+                .params()
+                .premine_distribution()
+                .expect("premine addresses are checked when network parameters are loaded");
+        }
+
         // The premine UTXOs can be hardcoded here.
         let authority_wallet = WalletSecret::devnet_wallet();
         let authority_receiving_address = authority_wallet
@@ -418,12 +463,12 @@ impl Block {
 
     pub fn premine_utxos(network: Network) -> Vec<Utxo> {
         let mut utxos = vec![];
-        for (receiving_address, amount) in Self::premine_distribution() {
+        for (receiving_address, amount) in Self::premine_distribution(network) {
             // generate utxo
             let mut utxo = Utxo::new_native_currency(receiving_address.lock_script(), amount);
-            let six_months = Timestamp::months(6);
-            utxo.coins
-                .push(TimeLock::until(network.launch_date() + six_months));
+            utxo.coins.push(TimeLock::until(
+                network.launch_date() + PREMINE_TIME_LOCK_PERIOD,
+            ));
             utxos.push(utxo);
         }
         utxos
@@ -513,66 +558,57 @@ impl Block {
         self.is_valid_extended(previous_block, now, None, None)
     }
 
-    /// Like `is_valid` but also allows specifying a custom
-    /// `target_block_interval` and `minimum_block_time`. If `None` is passed,
-    /// these variabes take the default values.
-    pub(crate) fn is_valid_extended(
+    /// Verify that the block follows `previous_block`: the checks of
+    /// [`Self::is_valid`] on the header and the block MMR, which do not
+    /// involve the block proof or the transaction. Returns the first check
+    /// that fails, if any.
+    fn validate_successor(
         &self,
         previous_block: &Block,
         now: Timestamp,
         target_block_interval: Option<Timestamp>,
         minimum_block_time: Option<Timestamp>,
-    ) -> bool {
-        // What belongs here are the things that would otherwise
-        // be verified by the block validity proof.
-
-        // 0. `previous_block` is consistent with current block
-        //   a) Block height is previous plus one
-        //   b) Block header points to previous block
-        //   c) Block mmr updated correctly
-        //   d) Block timestamp is greater than (or equal to) timestamp of
-        //      previous block plus minimum block time
-        //   e) Target difficulty and cumulative proof-of-work were updated correctly
-        //   f) Block timestamp is less than host-time (utc) + 2 hours.
-        // 1. Block proof is valid
-        //   a) Verify appendix contains required claims
-        //   b) Block proof is valid
-        //   c) Max block size is not exceeded
-        // 2. The transaction is valid.
-        //   a) Verify that MS removal records are valid, done against previous `mutator_set_accumulator`,
-        //   b) Verify that all removal records have unique index sets
-        //   c) verify that we can add `mutator_set_update` to previous `mutator_set_accumulator`,
-        //      and that it results in new block's `mutator_set_accumulator`
-        //   d) transaction timestamp <= block timestamp
-        //   e) transaction coinbase <= miner reward
-        //   f) transaction is valid (internally consistent)
-
+        network_params: &NetworkParams,
+        tracer: &mut ValidationTracer,
+    ) -> Result<(), BlockValidationStep> {
         // 0.a) Block height is previous plus one
+        if tracer.enter(BlockValidationStep::Height) {
+            return Err(BlockValidationStep::Height);
+        }
         if previous_block.kernel.header.height.next() != self.kernel.header.height {
             warn!(
                 "Block height ({}) does not match previous height plus one ({})",
                 self.kernel.header.height,
                 previous_block.kernel.header.height.next()
             );
-            return false;
+            return Err(BlockValidationStep::Height);
         }
 
         // 0.b) Block header points to previous block
+        if tracer.enter(BlockValidationStep::PrevBlockDigest) {
+            return Err(BlockValidationStep::PrevBlockDigest);
+        }
         if previous_block.hash() != self.kernel.header.prev_block_digest {
             warn!("Hash digest does not match previous digest");
-            return false;
+            return Err(BlockValidationStep::PrevBlockDigest);
         }
 
         // 0.c) Block mmr updated correctly
+        if tracer.enter(BlockValidationStep::BlockMmr) {
+            return Err(BlockValidationStep::BlockMmr);
+        }
         let mut mmra = previous_block.kernel.body.block_mmr_accumulator.clone();
         mmra.append(previous_block.hash());
         if mmra != self.kernel.body.block_mmr_accumulator {
             warn!("Block MMRA was not updated correctly");
-            return false;
+            return Err(BlockValidationStep::BlockMmr);
         }
 
         // 0.d) Block timestamp is greater than (or equal to) timestamp of
         //      previous block plus minimum block time
+        if tracer.enter(BlockValidationStep::MinimumBlockTime) {
+            return Err(BlockValidationStep::MinimumBlockTime);
+        }
         let minimum_block_time = minimum_block_time.unwrap_or(MINIMUM_BLOCK_TIME);
         if previous_block.kernel.header.timestamp + minimum_block_time
             > self.kernel.header.timestamp
@@ -584,85 +620,272 @@ impl Block {
                 minimum_block_time,
                 previous_block.kernel.header.timestamp + minimum_block_time <= self.kernel.header.timestamp
             );
-            return false;
+            return Err(BlockValidationStep::MinimumBlockTime);
         }
 
         // 0.e) Target difficulty and cumulative proof-of-work were updated correctly
-        let expected_difficulty = difficulty_control(
+        if tracer.enter(BlockValidationStep::Difficulty) {
+            return Err(BlockValidationStep::Difficulty);
+        }
+        let expected_difficulty = light_verification::expected_difficulty(
             self.header().timestamp,
-            previous_block.header().timestamp,
-            previous_block.header().difficulty,
+            previous_block.header(),
             target_block_interval,
-            previous_block.header().height,
+            network_params,
         );
         if self.kernel.header.difficulty != expected_difficulty {
             warn!(
                 "Value for new difficulty is incorrect.  actual: {},  expected: {expected_difficulty}",
                 self.kernel.header.difficulty,
             );
-            return false;
+            return Err(BlockValidationStep::Difficulty);
         }
         let expected_cumulative_proof_of_work =
-            previous_block.header().cumulative_proof_of_work + previous_block.header().difficulty;
+            light_verification::expected_cumulative_proof_of_work(previous_block.header());
         if self.header().cumulative_proof_of_work != expected_cumulative_proof_of_work {
             warn!("Block's cumulative proof-of-work number does not match with expectation.\n\nBlock's pow: {}\nexpectation: {}", self.header().cumulative_proof_of_work, expected_cumulative_proof_of_work);
-            return false;
+            return Err(BlockValidationStep::Difficulty);
         }
 
         // 0.f) Block timestamp is less than host-time (utc) + 2 hours.
-        const FUTUREDATING_LIMIT: Timestamp = Timestamp::hours(2);
+        if tracer.enter(BlockValidationStep::FutureDating) {
+            return Err(BlockValidationStep::FutureDating);
+        }
         let future_limit = now + FUTUREDATING_LIMIT;
         if self.kernel.header.timestamp >= future_limit {
             warn!(
                 "block time is too far in the future.\n\nBlock timestamp: {}\nThreshold is: {}",
                 self.kernel.header.timestamp, future_limit
             );
-            return false;
+            return Err(BlockValidationStep::FutureDating);
+        }
+
+        // 0.g) Lock-free MMR is unchanged. Transactions have no lock-free
+        //      inputs or outputs, so nothing may be added to the MMR.
+        if tracer.enter(BlockValidationStep::LockFreeMmr) {
+            return Err(BlockValidationStep::LockFreeMmr);
+        }
+        if previous_block.kernel.body.lock_free_mmr_accumulator
+            != self.kernel.body.lock_free_mmr_accumulator
+        {
+            warn!("Lock-free MMRA changed, but transactions cannot create lock-free UTXOs");
+            return Err(BlockValidationStep::LockFreeMmr);
+        }
+
+        Ok(())
+    }
+
+    /// Like [`Self::is_valid`], but also rejecting blocks that do not match
+    /// the checkpoint at their height. During the initial sync, blocks at or
+    /// below the highest of `checkpoints` are trusted to have a valid proof,
+    /// as they lead to a checkpointed block; all other rules are checked.
+    pub(crate) fn is_valid_with_checkpoints(
+        &self,
+        previous_block: &Block,
+        now: Timestamp,
+        checkpoints: &ValidationCheckpoints,
+        initial_sync: bool,
+    ) -> bool {
+        self.validate_with_checkpoints(previous_block, now, checkpoints, initial_sync)
+            .is_ok()
+    }
+
+    /// Like [`Self::is_valid_with_checkpoints`], but returning the first check
+    /// that fails, if any.
+    pub(crate) fn validate_with_checkpoints(
+        &self,
+        previous_block: &Block,
+        now: Timestamp,
+        checkpoints: &ValidationCheckpoints,
+        initial_sync: bool,
+    ) -> Result<(), BlockValidationStep> {
+        let mut tracer = ValidationTracer::new(self);
+        let result = self.validate_against_checkpoints(
+            previous_block,
+            now,
+            checkpoints,
+            initial_sync,
+            &mut tracer,
+        );
+        tracer.finish(result);
+        result
+    }
+
+    fn validate_against_checkpoints(
+        &self,
+        previous_block: &Block,
+        now: Timestamp,
+        checkpoints: &ValidationCheckpoints,
+        initial_sync: bool,
+        tracer: &mut ValidationTracer,
+    ) -> Result<(), BlockValidationStep> {
+        if tracer.enter(BlockValidationStep::Checkpoint) {
+            return Err(BlockValidationStep::Checkpoint);
+        }
+        if checkpoints.conflicts_with(self) {
+            warn!(
+                "Block {} at height {} does not match the checkpoint",
+                self.hash(),
+                self.header().height
+            );
+            return Err(BlockValidationStep::Checkpoint);
         }
 
+        let verify_proof = !(initial_sync && checkpoints.covers(self.header().height));
+        self.validate_rules(previous_block, now, None, None, verify_proof, tracer)
+    }
+
+    /// Like `is_valid` but also allows specifying a custom
+    /// `target_block_interval` and `minimum_block_time`. If `None` is passed,
+    /// these variabes take the default values.
+    pub(crate) fn is_valid_extended(
+        &self,
+        previous_block: &Block,
+        now: Timestamp,
+        target_block_interval: Option<Timestamp>,
+        minimum_block_time: Option<Timestamp>,
+    ) -> bool {
+        self.validate_extended(
+            previous_block,
+            now,
+            target_block_interval,
+            minimum_block_time,
+        )
+        .is_ok()
+    }
+
+    /// Like [`Self::is_valid_extended`], but returning the first check that
+    /// fails, if any.
+    pub(crate) fn validate_extended(
+        &self,
+        previous_block: &Block,
+        now: Timestamp,
+        target_block_interval: Option<Timestamp>,
+        minimum_block_time: Option<Timestamp>,
+    ) -> Result<(), BlockValidationStep> {
+        let mut tracer = ValidationTracer::new(self);
+        let result = self.validate_rules(
+            previous_block,
+            now,
+            target_block_interval,
+            minimum_block_time,
+            true,
+            &mut tracer,
+        );
+        tracer.finish(result);
+        result
+    }
+
+    fn validate_rules(
+        &self,
+        previous_block: &Block,
+        now: Timestamp,
+        target_block_interval: Option<Timestamp>,
+        minimum_block_time: Option<Timestamp>,
+        verify_proof: bool,
+        tracer: &mut ValidationTracer,
+    ) -> Result<(), BlockValidationStep> {
+        // What belongs here are the things that would otherwise
+        // be verified by the block validity proof.
+
+        // 0. `previous_block` is consistent with current block
+        //   a) Block height is previous plus one
+        //   b) Block header points to previous block
+        //   c) Block mmr updated correctly
+        //   d) Block timestamp is greater than (or equal to) timestamp of
+        //      previous block plus minimum block time
+        //   e) Target difficulty and cumulative proof-of-work were updated correctly
+        //   f) Block timestamp is less than host-time (utc) + 2 hours.
+        //   g) Lock-free MMR is unchanged
+        // 1. Block proof is valid
+        //   a) Verify appendix contains required claims
+        //   b) Block proof is valid
+        //   c) Max block size is not exceeded
+        // 2. The transaction is valid.
+        //   a) Verify that MS removal records are valid, done against previous `mutator_set_accumulator`,
+        //   b) Verify that all removal records have unique index sets
+        //   c) verify that we can add `mutator_set_update` to previous `mutator_set_accumulator`,
+        //      and that it results in new block's `mutator_set_accumulator`
+        //   d) transaction timestamp <= block timestamp
+        //   e) transaction coinbase <= miner reward + unburned fee, with non-negative fee
+        //   f) transaction is valid (internally consistent)
+
+        // Blocks do not name their network, so they follow the rules of the
+        // network the node runs on.
+        let network_params = NetworkParams::in_effect();
+
+        self.validate_successor(
+            previous_block,
+            now,
+            target_block_interval,
+            minimum_block_time,
+            network_params,
+            tracer,
+        )?;
+
         // 1.a) Verify appendix contains required claims
+        if tracer.enter(BlockValidationStep::AppendixClaims) {
+            return Err(BlockValidationStep::AppendixClaims);
+        }
         for required_claim in BlockAppendix::consensus_claims(self.body()) {
             if !self.appendix().contains(&required_claim) {
                 warn!("Block appendix does not contain required claim.\nRequired claim: {required_claim:?}");
-                return false;
+                return Err(BlockValidationStep::AppendixClaims);
             }
         }
 
-        // 1.b) Block proof is valid
-        let BlockProof::SingleProof(block_proof) = &self.proof else {
-            warn!("Can only verify block proofs, got {:?}", self.proof);
-            return false;
-        };
-        if !BlockProgram::verify(self.body(), self.appendix(), block_proof) {
-            warn!("Block proof invalid.");
-            return false;
+        // 1.b) Block proof is valid, unless the block is trusted to lead to
+        //      a checkpoint
+        if verify_proof {
+            if tracer.enter(BlockValidationStep::Proof) {
+                return Err(BlockValidationStep::Proof);
+            }
+            let BlockProof::SingleProof(block_proof) = &self.proof else {
+                warn!("Can only verify block proofs, got {:?}", self.proof);
+                return Err(BlockValidationStep::Proof);
+            };
+            if !BlockProgram::verify(self.body(), self.appendix(), block_proof) {
+                warn!("Block proof invalid.");
+                return Err(BlockValidationStep::Proof);
+            }
         }
 
         // 1.c) Max block size is not exceeded
-        if self.size() > MAX_BLOCK_SIZE {
+        if tracer.enter(BlockValidationStep::BlockSize) {
+            return Err(BlockValidationStep::BlockSize);
+        }
+        let max_block_size = network_params.max_block_size;
+        if self.size() > max_block_size {
             warn!(
                 "Block size exceeds limit.\n\nBlock size: {} bfes\nLimit: {} bfes",
                 self.size(),
-                MAX_BLOCK_SIZE
+                max_block_size
             );
-            return false;
+            return Err(BlockValidationStep::BlockSize);
         }
 
         // 2.a) Verify validity of removal records: That their MMR MPs match the SWBF, and
         // that at least one of their listed indices is absent.
-        for removal_record in self.kernel.body.transaction_kernel.inputs.iter() {
-            if !previous_block
-                .kernel
-                .body
-                .mutator_set_accumulator
-                .can_remove(removal_record)
-            {
-                warn!("Removal record cannot be removed from mutator set");
-                return false;
-            }
+        if tracer.enter(BlockValidationStep::RemovalRecords) {
+            return Err(BlockValidationStep::RemovalRecords);
+        }
+        let previous_mutator_set = &previous_block.kernel.body.mutator_set_accumulator;
+        if !self
+            .kernel
+            .body
+            .transaction_kernel
+            .inputs
+            .par_iter()
+            .all(|removal_record| previous_mutator_set.can_remove(removal_record))
+        {
+            warn!("Removal record cannot be removed from mutator set");
+            return Err(BlockValidationStep::RemovalRecords);
         }
 
         // 2.b) Verify that the removal records do not contain duplicate `AbsoluteIndexSet`s
+        if tracer.enter(BlockValidationStep::UniqueIndexSets) {
+            return Err(BlockValidationStep::UniqueIndexSets);
+        }
         let mut absolute_index_sets = self
             .kernel
             .body
@@ -675,20 +898,25 @@ impl Block {
         absolute_index_sets.dedup();
         if absolute_index_sets.len() != self.kernel.body.transaction_kernel.inputs.len() {
             warn!("Removal records contain duplicates");
-            return false;
+            return Err(BlockValidationStep::UniqueIndexSets);
         }
 
         // 2.c) Verify that the two mutator sets, the one from the current block and the
         // one from the previous, are consistent with the transactions.
-        let mutator_set_update = MutatorSetUpdate::new(
+        if tracer.enter(BlockValidationStep::MutatorSetUpdate) {
+            return Err(BlockValidationStep::MutatorSetUpdate);
+        }
+        let ms_update_result = light_verification::apply_mutator_set_update(
+            &previous_block.kernel.body.mutator_set_accumulator,
             self.kernel.body.transaction_kernel.inputs.clone(),
             self.kernel.body.transaction_kernel.outputs.clone(),
         );
-        let mut ms = previous_block.kernel.body.mutator_set_accumulator.clone();
-        let ms_update_result = mutator_set_update.apply_to_accumulator(&mut ms);
-        if let Err(err) = ms_update_result {
-            warn!("Failed to apply mutator set update: {}", err);
-            return false;
+        let ms = match ms_update_result {
+            Ok(ms) => ms,
+            Err(err) => {
+                warn!("Failed to apply mutator set update: {}", err);
+                return Err(BlockValidationStep::MutatorSetUpdate);
+            }
         };
         if ms.hash() != self.kernel.body.mutator_set_accumulator.hash() {
             warn!("Reported mutator set does not match calculated object.");
@@ -696,30 +924,40 @@ impl Block {
                 "From Block\n{:?}. \n\n\nCalculated\n{:?}",
                 self.kernel.body.mutator_set_accumulator, ms
             );
-            return false;
+            return Err(BlockValidationStep::MutatorSetUpdate);
         }
 
         // 2.d) verify that the transaction timestamp is less than or equal to the block's timestamp.
+        if tracer.enter(BlockValidationStep::TransactionTimestamp) {
+            return Err(BlockValidationStep::TransactionTimestamp);
+        }
         if self.kernel.body.transaction_kernel.timestamp > self.kernel.header.timestamp {
             warn!(
                 "Transaction timestamp ({}) is is larger than that of block ({})",
                 self.kernel.body.transaction_kernel.timestamp, self.kernel.header.timestamp
             );
-            return false;
+            return Err(BlockValidationStep::TransactionTimestamp);
         }
 
         // 2.e) Verify that the coinbase claimed by the transaction does not exceed
-        //      the allowed coinbase based on block height, epoch, etc., and fee
-        let expected_reward: NeptuneCoins = Self::get_mining_reward(self.kernel.header.height)
-            + self.kernel.body.transaction_kernel.fee;
-        if let Some(claimed_reward) = self.kernel.body.transaction_kernel.coinbase {
-            if claimed_reward > expected_reward {
-                warn!("Block is invalid because the claimed miner reward is too high relative to current network parameters.");
-                return false;
-            }
+        //      the allowed coinbase based on block height, epoch, etc., and the
+        //      unburned part of the fee
+        if tracer.enter(BlockValidationStep::Coinbase) {
+            return Err(BlockValidationStep::Coinbase);
+        }
+        let coinbase_check = CoinbaseAccounting::for_transaction(
+            self.kernel.header.height,
+            &self.kernel.body.transaction_kernel,
+        )
+        .and_then(|accounting| {
+            accounting.verify_coinbase(self.kernel.body.transaction_kernel.coinbase)
+        });
+        if let Err(err) = coinbase_check {
+            warn!("Block is invalid because the claimed miner reward is not covered by subsidy and fee: {err}");
+            return Err(BlockValidationStep::Coinbase);
         }
 
-        true
+        Ok(())
     }
 
     /// Determine whether the the proof-of-work puzzle was solved correctly.
@@ -731,66 +969,12 @@ impl Block {
     /// then the effective difficulty is reduced by a factor
     /// `ADVANCE_DIFFICULTY_CORRECTION_FACTOR`.
     pub fn has_proof_of_work(&self, previous_block: &Block) -> bool {
-        let hash = self.hash();
-        let threshold = previous_block.kernel.header.difficulty.target();
-        if hash <= threshold {
-            return true;
-        }
-
-        let delta_t = self.header().timestamp - previous_block.header().timestamp;
-        let excess_multiple = usize::try_from(
-            delta_t.to_millis() / TARGET_BLOCK_INTERVAL.to_millis(),
+        light_verification::has_proof_of_work(
+            self.hash(),
+            self.header(),
+            previous_block.header(),
+            NetworkParams::in_effect(),
         )
-        .expect("excessive timestamp on incoming block should have been caught by peer loop");
-        let shift = usize::try_from(ADVANCE_DIFFICULTY_CORRECTION_FACTOR.ilog2()).unwrap()
-            * (excess_multiple
-                >> usize::try_from(ADVANCE_DIFFICULTY_CORRECTION_WAIT.ilog2()).unwrap());
-        let effective_difficulty = previous_block.header().difficulty >> shift;
-        if hash <= effective_difficulty.target() {
-            return true;
-        }
-
-        false
-    }
-
-    /// Evaluate the fork choice rule.
-    ///
-    /// Given two blocks, determine which one is more canonical. This function
-    /// evaluates the following logic:
-    ///  - if the height is different, prefer the block with more accumulated
-    ///    proof-of-work;
-    ///  - otherwise, if exactly one of the blocks' transactions has no inputs,
-    ///    reject that one;
-    ///  - otherwise, prefer the current tip.
-    ///
-    /// This function assumes the blocks are valid and have the self-declared
-    /// accumulated proof-of-work.
-    ///
-    /// This function is called exclusively in
-    /// [`GlobalState::incoming_block_is_more_canonical`], which is in turn
-    /// called in two places:
-    ///  1. In `peer_loop`, when a peer sends a block. The `peer_loop` task only
-    ///     sends the incoming block to the `main_loop` if it is more canonical.
-    ///  2. In `main_loop`, when it receives a block from a `peer_loop` or from
-    ///     the `mine_loop`. It is possible that despite (1), race conditions
-    ///     arise and they must be solved here.
-    pub(crate) fn fork_choice_rule<'a>(
-        current_tip: &'a Self,
-        incoming_block: &'a Self,
-    ) -> &'a Self {
-        if current_tip.header().height != incoming_block.header().height {
-            if current_tip.header().cumulative_proof_of_work
-                >= incoming_block.header().cumulative_proof_of_work
-            {
-                current_tip
-            } else {
-                incoming_block
-            }
-        } else if current_tip.body().transaction_kernel.inputs.is_empty() {
-            incoming_block
-        } else {
-            current_tip
-        }
     }
 
     /// Size in number of BFieldElements of the block
@@ -809,8 +993,6 @@ mod block_tests {
 
     use rand::thread_rng;
     use rand::Rng;
-    use rayon::iter::IntoParallelRefIterator;
-    use rayon::iter::ParallelIterator;
     use strum::IntoEnumIterator;
     use tracing_test::traced_test;
 
@@ -869,6 +1051,7 @@ mod block_tests {
                         block_prev.header().difficulty,
                         None,
                         block_prev.header().height,
+                        network.params(),
                     );
                     assert_eq!(block.kernel.header.difficulty, control);
 
@@ -992,16 +1175,20 @@ mod block_tests {
         // where 42000000 is the asymptotical limit of the token supply
         // and 1.98% is the relative size of the premine
         let premine_max_size = NeptuneCoins::new(831600);
-        let total_premine = Block::premine_distribution()
-            .iter()
-            .map(|(_receiving_address, amount)| *amount)
-            .sum::<NeptuneCoins>();
+        for network in Network::iter() {
+            let total_premine = Block::premine_distribution(network)
+                .iter()
+                .map(|(_receiving_address, amount)| *amount)
+                .sum::<NeptuneCoins>();
 
-        assert!(total_premine <= premine_max_size);
+            assert!(total_premine <= premine_max_size);
+        }
     }
 
     mod block_is_valid {
         use super::*;
+        use crate::models::blockchain::block::validation_checkpoints::ValidationCheckpoint;
+        use crate::util_types::fault_injection;
 
         #[traced_test]
         #[tokio::test]
@@ -1048,6 +1235,148 @@ mod block_tests {
             block1.kernel.header.timestamp = future_time4;
             assert!(!block1.is_valid(&genesis_block, now));
         }
+
+        #[traced_test]
+        #[tokio::test]
+        async fn injected_fault_rejects_valid_block_once() {
+            let network = Network::Main;
+            let genesis_block = Block::genesis_block(network);
+            let now = genesis_block.kernel.header.timestamp + Timestamp::hours(2);
+            let wallet = WalletSecret::devnet_wallet();
+            let genesis_state = mock_genesis_global_state(network, 0, wallet).await;
+
+            let (block_tx, _expected_utxo) =
+                make_coinbase_transaction(&genesis_state, NeptuneCoins::zero(), now)
+                    .await
+                    .unwrap();
+            let block1 = Block::make_block_template_with_valid_proof(
+                &genesis_block,
+                block_tx,
+                now,
+                None,
+                &TritonProverSync::dummy(),
+            )
+            .await
+            .unwrap();
+            assert!(block1.is_valid(&genesis_block, now));
+
+            fault_injection::arm(
+                fault_injection::FaultPoint::BlockValidation {
+                    block_digest: block1.hash(),
+                    step: BlockValidationStep::MutatorSetUpdate,
+                },
+                0,
+            );
+            assert!(!block1.is_valid(&genesis_block, now));
+            assert!(block1.is_valid(&genesis_block, now));
+        }
+
+        #[traced_test]
+        #[tokio::test]
+        async fn block_changing_lock_free_mmr_is_invalid() {
+            let network = Network::Main;
+            let genesis_block = Block::genesis_block(network);
+            let now = genesis_block.kernel.header.timestamp + Timestamp::hours(2);
+            let wallet = WalletSecret::devnet_wallet();
+            let genesis_state = mock_genesis_global_state(network, 0, wallet).await;
+
+            let (block_tx, _expected_utxo) =
+                make_coinbase_transaction(&genesis_state, NeptuneCoins::zero(), now)
+                    .await
+                    .unwrap();
+            let mut block1 = Block::make_block_template_with_valid_proof(
+                &genesis_block,
+                block_tx,
+                now,
+                None,
+                &TritonProverSync::dummy(),
+            )
+            .await
+            .unwrap();
+            assert!(block1.is_valid(&genesis_block, now));
+
+            block1
+                .kernel
+                .body
+                .lock_free_mmr_accumulator
+                .append(Digest::default());
+            block1.unset_digest();
+            assert_eq!(
+                Err(BlockValidationStep::LockFreeMmr),
+                block1.validate_extended(&genesis_block, now, None, None)
+            );
+        }
+
+        #[traced_test]
+        #[tokio::test]
+        async fn checkpoints_skip_only_proof_and_only_during_initial_sync() {
+            let network = Network::Main;
+            let genesis_block = Block::genesis_block(network);
+            let now = genesis_block.kernel.header.timestamp + Timestamp::hours(2);
+            let wallet = WalletSecret::devnet_wallet();
+            let genesis_state = mock_genesis_global_state(network, 0, wallet).await;
+
+            let (block_tx, _expected_utxo) =
+                make_coinbase_transaction(&genesis_state, NeptuneCoins::zero(), now)
+                    .await
+                    .unwrap();
+            let block1 = Block::make_block_template_with_valid_proof(
+                &genesis_block,
+                block_tx,
+                now,
+                None,
+                &TritonProverSync::dummy(),
+            )
+            .await
+            .unwrap();
+            let checkpoints = ValidationCheckpoints::new(
+                network,
+                &[ValidationCheckpoint {
+                    height: block1.header().height,
+                    digest: block1.hash(),
+                }],
+            );
+            let fault = |step| fault_injection::FaultPoint::BlockValidation {
+                block_digest: block1.hash(),
+                step,
+            };
+
+            // an invalid proof is tolerated during the initial sync only
+            fault_injection::arm(fault(BlockValidationStep::Proof), 0);
+            assert_eq!(
+                Ok(()),
+                block1.validate_with_checkpoints(&genesis_block, now, &checkpoints, true)
+            );
+            assert_eq!(
+                Err(BlockValidationStep::Proof),
+                block1.validate_with_checkpoints(&genesis_block, now, &checkpoints, false)
+            );
+
+            // all other rules are checked below checkpoints
+            for step in [
+                BlockValidationStep::MutatorSetUpdate,
+                BlockValidationStep::Coinbase,
+            ] {
+                fault_injection::arm(fault(step), 0);
+                assert_eq!(
+                    Err(step),
+                    block1.validate_with_checkpoints(&genesis_block, now, &checkpoints, true)
+                );
+            }
+
+            // blocks must match the checkpoint at their height
+            let other_checkpoints = ValidationCheckpoints::new(
+                network,
+                &[ValidationCheckpoint {
+                    height: block1.header().height,
+                    digest: genesis_block.hash(),
+                }],
+            );
+            assert_eq!(
+                Err(BlockValidationStep::Checkpoint),
+                block1.validate_with_checkpoints(&genesis_block, now, &other_checkpoints, true)
+            );
+        }
     }
 
     /// This module has tests that verify a block's digest
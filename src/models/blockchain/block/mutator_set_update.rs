@@ -1,5 +1,10 @@
+use std::collections::HashSet;
+
 use anyhow::bail;
 use anyhow::Result;
+use itertools::Itertools;
+use rayon::iter::IntoParallelRefIterator;
+use rayon::iter::ParallelIterator;
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -31,6 +36,49 @@ impl MutatorSetUpdate {
         self.apply_to_accumulator_and_records(ms_accumulator, &mut [])
     }
 
+    /// Like [`Self::apply_to_accumulator`], but checks the removal records in
+    /// parallel and removes them in one batch. Gives the same result: each
+    /// removal record must be valid once the additions are applied, and have
+    /// an index set neither in the mutator set nor by the removal records
+    /// before it.
+    pub fn apply_to_accumulator_batched(
+        &self,
+        ms_accumulator: &mut MutatorSetAccumulator,
+    ) -> Result<()> {
+        let mut removals = self.removals.clone();
+        for addition_record in self.additions.iter() {
+            RemovalRecord::batch_update_from_addition(
+                &mut removals.iter_mut().collect_vec(),
+                ms_accumulator,
+            );
+            ms_accumulator.add(addition_record);
+        }
+
+        let absent_indices = removals
+            .par_iter()
+            .map(|removal_record| ms_accumulator.absent_indices(removal_record))
+            .collect::<Vec<_>>();
+        let mut set_by_earlier_removals = HashSet::new();
+        for (removal_record, absent_indices) in removals.iter().zip_eq(absent_indices) {
+            let Some(absent_indices) = absent_indices else {
+                bail!("Cannot remove item from mutator set.");
+            };
+            if absent_indices
+                .iter()
+                .all(|index| set_by_earlier_removals.contains(index))
+            {
+                bail!("Cannot remove item from mutator set.");
+            }
+            set_by_earlier_removals.extend(removal_record.absolute_indices.to_vec());
+        }
+
+        if !removals.is_empty() {
+            ms_accumulator.batch_remove(removals, &mut []);
+        }
+
+        Ok(())
+    }
+
     /// Apply a mutator-set-update to a mutator-set-accumulator and a bunch of
     /// removal records.
     ///
@@ -80,6 +128,62 @@ mod tests {
     use num_traits::Zero;
 
     use super::*;
+    use crate::util_types::mutator_set::commit;
+    use crate::util_types::mutator_set::ms_membership_proof::MsMembershipProof;
+    use crate::util_types::test_shared::mutator_set::mock_item_and_randomnesses;
+
+    #[test]
+    fn batched_application_matches_sequential_application() {
+        let mut accumulator = MutatorSetAccumulator::default();
+        let mut membership_proofs: Vec<MsMembershipProof> = vec![];
+        let mut items = vec![];
+        for _ in 0..20 {
+            let (item, sender_randomness, receiver_preimage) = mock_item_and_randomnesses();
+            let addition_record = commit(item, sender_randomness, receiver_preimage.hash());
+            let membership_proof = accumulator.prove(item, sender_randomness, receiver_preimage);
+            MsMembershipProof::batch_update_from_addition(
+                &mut membership_proofs.iter_mut().collect_vec(),
+                &items,
+                &accumulator,
+                &addition_record,
+            )
+            .unwrap();
+            accumulator.add(&addition_record);
+            membership_proofs.push(membership_proof);
+            items.push(item);
+        }
+
+        let removals = items
+            .iter()
+            .zip_eq(membership_proofs.iter())
+            .step_by(3)
+            .map(|(&item, membership_proof)| accumulator.drop(item, membership_proof))
+            .collect_vec();
+        let additions = (0..5)
+            .map(|_| {
+                let (item, sender_randomness, receiver_preimage) = mock_item_and_randomnesses();
+                commit(item, sender_randomness, receiver_preimage.hash())
+            })
+            .collect_vec();
+        let update = MutatorSetUpdate::new(removals.clone(), additions.clone());
+
+        let mut sequential = accumulator.clone();
+        update.apply_to_accumulator(&mut sequential).unwrap();
+        let mut batched = accumulator.clone();
+        update.apply_to_accumulator_batched(&mut batched).unwrap();
+        assert_eq!(sequential.hash(), batched.hash());
+
+        // Removing the same item twice in one update is a double spend.
+        let mut double_spend = removals;
+        double_spend.push(double_spend[0].clone());
+        let update = MutatorSetUpdate::new(double_spend, additions);
+        assert!(update
+            .apply_to_accumulator(&mut accumulator.clone())
+            .is_err());
+        assert!(update
+            .apply_to_accumulator_batched(&mut accumulator.clone())
+            .is_err());
+    }
 
     impl MutatorSetUpdate {
         /// Return the number of removal records
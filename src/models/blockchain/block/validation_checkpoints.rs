@@ -0,0 +1,123 @@
+//! Checkpoints that speed up the initial sync.
+//!
+//! A checkpoint is the digest of the canonical block at some height. Each
+//! network comes with hardcoded checkpoints, which can be extended or replaced
+//! with `--checkpoint`, or ignored with `--no-checkpoints`. Blocks at a
+//! checkpoint height must match it. During the initial sync, the proofs of
+//! blocks at or below the highest checkpoint are trusted, as they were
+//! verified by the nodes that vouched for the checkpoint. Verifying the proof
+//! is the expensive part of block validation; all other consensus rules,
+//! including those on the transaction and the mutator set, are still checked.
+
+use std::str::FromStr;
+
+use anyhow::bail;
+use anyhow::Context;
+use itertools::Itertools;
+
+use super::block_height::BlockHeight;
+use super::Block;
+use crate::config_models::network::Network;
+use crate::prelude::twenty_first::math::digest::Digest;
+
+/// The digest of the canonical block at a height, parsed from
+/// `<height>:<digest in hex>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationCheckpoint {
+    pub height: BlockHeight,
+    pub digest: Digest,
+}
+
+impl FromStr for ValidationCheckpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((height, digest)) = s.split_once(':') else {
+            bail!("checkpoint must be given as <height>:<digest>");
+        };
+        let height = height
+            .parse::<u64>()
+            .context("invalid checkpoint height")?
+            .into();
+        let digest = Digest::try_from_hex(digest).context("invalid checkpoint digest")?;
+
+        Ok(Self { height, digest })
+    }
+}
+
+/// The checkpoints in force, sorted by height.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationCheckpoints(Vec<ValidationCheckpoint>);
+
+impl ValidationCheckpoints {
+    /// The checkpoints hardcoded for `network`, with `overrides` taking
+    /// precedence at their heights. If several overrides are given for the
+    /// same height, the first one is used.
+    pub fn new(network: Network, overrides: &[ValidationCheckpoint]) -> Self {
+        let hardcoded = network
+            .validation_checkpoints()
+            .iter()
+            .map(|&(height, digest)| ValidationCheckpoint {
+                height: height.into(),
+                digest: Digest::try_from_hex(digest).expect("hardcoded checkpoint is valid hex"),
+            });
+
+        let checkpoints = overrides
+            .iter()
+            .copied()
+            .chain(hardcoded)
+            .unique_by(|checkpoint| checkpoint.height)
+            .sorted_by_key(|checkpoint| checkpoint.height)
+            .collect();
+        Self(checkpoints)
+    }
+
+    /// No checkpoints: every block proof is verified.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    pub fn highest(&self) -> Option<BlockHeight> {
+        self.0.last().map(|checkpoint| checkpoint.height)
+    }
+
+    /// Whether a block at `height` is trusted to have a valid proof during
+    /// the initial sync.
+    pub fn covers(&self, height: BlockHeight) -> bool {
+        self.highest().is_some_and(|highest| height <= highest)
+    }
+
+    /// Whether there is a checkpoint at the block's height with a different
+    /// digest.
+    pub fn conflicts_with(&self, block: &Block) -> bool {
+        self.0
+            .iter()
+            .find(|checkpoint| checkpoint.height == block.header().height)
+            .is_some_and(|checkpoint| checkpoint.digest != block.hash())
+    }
+}
+
+#[cfg(test)]
+mod validation_checkpoints_tests {
+    use super::*;
+
+    #[test]
+    fn overrides_take_precedence_and_cover_lower_heights() {
+        let genesis = Block::genesis_block(Network::RegTest);
+        let checkpoint: ValidationCheckpoint =
+            format!("0:{}", genesis.hash().to_hex()).parse().unwrap();
+        assert!("0".parse::<ValidationCheckpoint>().is_err());
+        assert!("zero:00".parse::<ValidationCheckpoint>().is_err());
+
+        let checkpoints = ValidationCheckpoints::new(Network::RegTest, &[checkpoint]);
+        assert_eq!(Some(BlockHeight::genesis()), checkpoints.highest());
+        assert!(checkpoints.covers(BlockHeight::genesis()));
+        assert!(!checkpoints.covers(BlockHeight::genesis().next()));
+        assert!(!checkpoints.conflicts_with(&genesis));
+
+        let other_genesis = Block::genesis_block(Network::Main);
+        assert!(checkpoints.conflicts_with(&other_genesis));
+
+        assert!(!ValidationCheckpoints::none().covers(BlockHeight::genesis()));
+    }
+}
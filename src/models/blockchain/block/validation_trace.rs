@@ -0,0 +1,217 @@
+//! Per-rule reports of block validation, recorded with `--trace-validation`.
+//!
+//! Block validation stops at the first consensus rule a block breaks, and
+//! only logs a warning for it. When tracing is enabled, every validation also
+//! records which rules were checked, whether each one passed, and how long it
+//! took. The report is logged, and the reports of the most recently validated
+//! blocks are kept for retrieval over RPC.
+
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::debug;
+use tracing::info;
+
+use super::block_height::BlockHeight;
+use super::Block;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::prelude::twenty_first::math::digest::Digest;
+use crate::util_types::fault_injection;
+use crate::util_types::fault_injection::BlockValidationStep;
+use crate::util_types::json_schema::DigestSchema;
+
+/// Number of most recent reports kept.
+pub const MAX_VALIDATION_REPORTS: usize = 100;
+
+static TRACE_VALIDATION: AtomicBool = AtomicBool::new(false);
+
+static RECENT_REPORTS: Mutex<VecDeque<ValidationReport>> = Mutex::new(VecDeque::new());
+
+/// Record a report of every block validation from now on.
+pub fn enable() {
+    TRACE_VALIDATION.store(true, Ordering::Relaxed);
+}
+
+/// A consensus rule checked while validating a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct RuleOutcome {
+    pub rule: BlockValidationStep,
+    pub passed: bool,
+    pub duration: Duration,
+}
+
+/// The rules checked in one validation of a block, in the order they were
+/// checked. Validation stops at the first rule that fails.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ValidationReport {
+    #[schemars(with = "DigestSchema")]
+    pub block_digest: Digest,
+    pub height: BlockHeight,
+    pub validated_at: Timestamp,
+    pub rules: Vec<RuleOutcome>,
+}
+
+impl ValidationReport {
+    /// The rule the block broke, if any.
+    pub fn failed_rule(&self) -> Option<BlockValidationStep> {
+        self.rules
+            .iter()
+            .find(|outcome| !outcome.passed)
+            .map(|outcome| outcome.rule)
+    }
+
+    pub fn total_duration(&self) -> Duration {
+        self.rules.iter().map(|outcome| outcome.duration).sum()
+    }
+}
+
+/// The most recent reports, at most `max_num`, oldest first.
+pub fn recent_reports(max_num: usize) -> Vec<ValidationReport> {
+    let reports = RECENT_REPORTS.lock().unwrap();
+    reports
+        .iter()
+        .skip(reports.len().saturating_sub(max_num))
+        .cloned()
+        .collect()
+}
+
+/// Follows a validation from rule to rule. Also the hook for fault injection,
+/// as every rule is entered through it.
+#[derive(Debug)]
+pub(crate) struct ValidationTracer {
+    block_digest: Digest,
+
+    /// `None` if tracing is disabled
+    report: Option<ValidationReport>,
+
+    /// The rule being checked, and when its check started
+    current: Option<(BlockValidationStep, Instant)>,
+}
+
+impl ValidationTracer {
+    pub(crate) fn new(block: &Block) -> Self {
+        let report = TRACE_VALIDATION
+            .load(Ordering::Relaxed)
+            .then(|| ValidationReport {
+                block_digest: block.hash(),
+                height: block.header().height,
+                validated_at: Timestamp::now(),
+                rules: vec![],
+            });
+
+        Self {
+            block_digest: block.hash(),
+            report,
+            current: None,
+        }
+    }
+
+    /// Start checking `rule`, after the previous one passed. Returns whether
+    /// an injected fault makes the rule fail.
+    pub(crate) fn enter(&mut self, rule: BlockValidationStep) -> bool {
+        if self.report.is_some() {
+            self.close_current(true);
+            self.current = Some((rule, Instant::now()));
+        }
+        fault_injection::block_validation_fails(self.block_digest, rule)
+    }
+
+    fn close_current(&mut self, passed: bool) {
+        let (Some(report), Some((rule, started))) = (self.report.as_mut(), self.current.take())
+        else {
+            return;
+        };
+        report.rules.push(RuleOutcome {
+            rule,
+            passed,
+            duration: started.elapsed(),
+        });
+    }
+
+    /// Conclude the validation with its result, and log and keep the report.
+    pub(crate) fn finish(mut self, result: Result<(), BlockValidationStep>) {
+        let failed = result
+            .is_err_and(|failed_rule| self.current.is_some_and(|(rule, _)| rule == failed_rule));
+        self.close_current(!failed);
+        let Some(report) = self.report else {
+            return;
+        };
+
+        let outcome = match result {
+            Ok(()) => "valid".to_owned(),
+            Err(rule) => format!("invalid, failing {rule:?}"),
+        };
+        info!(
+            "Validated block {} at height {}: {outcome}, after checking {} rules in {:?}",
+            report.block_digest,
+            report.height,
+            report.rules.len(),
+            report.total_duration()
+        );
+        debug!(
+            "Validation of block {}: {}",
+            report.block_digest,
+            report
+                .rules
+                .iter()
+                .map(|outcome| format!(
+                    "{:?} {} in {:?}",
+                    outcome.rule,
+                    if outcome.passed { "passed" } else { "failed" },
+                    outcome.duration
+                ))
+                .join(", ")
+        );
+
+        let mut reports = RECENT_REPORTS.lock().unwrap();
+        reports.push_back(report);
+        while reports.len() > MAX_VALIDATION_REPORTS {
+            reports.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod validation_trace_tests {
+
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::state::wallet::WalletSecret;
+    use crate::tests::shared::make_mock_block;
+
+    fn report_for(block: &Block) -> Option<ValidationReport> {
+        recent_reports(MAX_VALIDATION_REPORTS)
+            .into_iter()
+            .rev()
+            .find(|report| report.block_digest == block.hash())
+    }
+
+    #[test]
+    fn report_lists_rules_up_to_the_failing_one() {
+        enable();
+        let genesis = Block::genesis_block(Network::Main);
+        let address = WalletSecret::devnet_wallet()
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+        let (block, _, _) = make_mock_block(&genesis, None, address, rand::random());
+
+        // A mock block follows its predecessor, but lacks a valid appendix and
+        // block proof.
+        let now = block.header().timestamp;
+        assert!(!block.is_valid(&genesis, now));
+        let report = report_for(&block).unwrap();
+        let (failed, passed) = report.rules.split_last().unwrap();
+        assert_eq!(Some(failed.rule), report.failed_rule());
+        assert!(!failed.passed);
+        assert_eq!(BlockValidationStep::Height, passed[0].rule);
+        assert!(passed.iter().all(|outcome| outcome.passed));
+    }
+}
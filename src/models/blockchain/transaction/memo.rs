@@ -0,0 +1,109 @@
+//! Free-form notes attached to transaction outputs, e.g. invoice references.
+//!
+//! A memo travels inside the encrypted UTXO notification, so only the sender
+//! and the receiver can read it.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Metadata;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Maximum size of a memo, in bytes of UTF-8. Memos sent on-chain increase the
+/// size of the transaction, so they are kept short.
+pub const MAX_MEMO_SIZE_IN_BYTES: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("memo of {0} bytes exceeds limit of {MAX_MEMO_SIZE_IN_BYTES} bytes")]
+pub struct MemoTooLong(pub usize);
+
+/// A UTF-8 string of at most [`MAX_MEMO_SIZE_IN_BYTES`] bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Memo(String);
+
+impl Memo {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for Memo {
+    type Error = MemoTooLong;
+
+    fn try_from(memo: String) -> Result<Self, Self::Error> {
+        if memo.len() > MAX_MEMO_SIZE_IN_BYTES {
+            return Err(MemoTooLong(memo.len()));
+        }
+        Ok(Self(memo))
+    }
+}
+
+impl FromStr for Memo {
+    type Err = MemoTooLong;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s.to_string())
+    }
+}
+
+impl From<Memo> for String {
+    fn from(memo: Memo) -> Self {
+        memo.0
+    }
+}
+
+impl JsonSchema for Memo {
+    fn schema_name() -> String {
+        "Memo".to_owned()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let mut schema = String::json_schema(gen).into_object();
+        schema.metadata = Some(Box::new(Metadata {
+            description: Some(format!(
+                "UTF-8 string of at most {MAX_MEMO_SIZE_IN_BYTES} bytes"
+            )),
+            ..Default::default()
+        }));
+        schema.into()
+    }
+}
+
+impl Display for Memo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod memo_tests {
+    use super::*;
+
+    #[test]
+    fn memo_size_is_limited() {
+        let longest = "x".repeat(MAX_MEMO_SIZE_IN_BYTES);
+        assert!(longest.parse::<Memo>().is_ok());
+
+        let too_long = "x".repeat(MAX_MEMO_SIZE_IN_BYTES + 1);
+        assert_eq!(
+            Err(MemoTooLong(MAX_MEMO_SIZE_IN_BYTES + 1)),
+            too_long.parse::<Memo>()
+        );
+    }
+
+    #[test]
+    fn oversized_memo_is_rejected_on_deserialization() {
+        let too_long = "x".repeat(MAX_MEMO_SIZE_IN_BYTES + 1);
+        let encoded = bincode::serialize(&too_long).unwrap();
+        assert!(bincode::deserialize::<Memo>(&encoded).is_err());
+
+        let memo: Memo = "invoice #42".parse().unwrap();
+        let encoded = bincode::serialize(&memo).unwrap();
+        assert_eq!(memo, bincode::deserialize(&encoded).unwrap());
+    }
+}
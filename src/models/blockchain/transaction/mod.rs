@@ -3,11 +3,14 @@ use crate::models::peer::transfer_transaction::TransactionProofQuality;
 use crate::models::proof_abstractions::mast_hash::MastHash;
 use crate::models::proof_abstractions::tasm::program::ConsensusProgram;
 use crate::models::proof_abstractions::tasm::program::TritonProverSync;
+use crate::models::proof_abstractions::verifier_pool;
 use crate::models::proof_abstractions::SecretWitness;
 use crate::models::state::wallet::expected_utxo::ExpectedUtxo;
 use crate::prelude::twenty_first;
 
 pub mod lock_script;
+pub mod memo;
+pub mod partially_proved;
 pub mod primitive_witness;
 pub mod transaction_kernel;
 pub mod transaction_output;
@@ -22,13 +25,12 @@ use anyhow::Result;
 use arbitrary::Arbitrary;
 use get_size::GetSize;
 use itertools::Itertools;
+use memo::Memo;
 use num_bigint::BigInt;
 use num_rational::BigRational;
 use serde::Deserialize;
 use serde::Serialize;
 use tasm_lib::prelude::TasmObject;
-use tasm_lib::triton_vm;
-use tasm_lib::triton_vm::stark::Stark;
 use tasm_lib::twenty_first::util_types::mmr::mmr_successor_proof::MmrSuccessorProof;
 use tasm_lib::Digest;
 use tokio::sync::TryLockError;
@@ -70,6 +72,9 @@ pub struct AnnouncedUtxo {
     pub utxo: Utxo,
     pub sender_randomness: Digest,
     pub receiver_preimage: Digest,
+
+    /// memo the sender attached to the notification, if any
+    pub memo: Option<Memo>,
 }
 
 impl From<&ExpectedUtxo> for AnnouncedUtxo {
@@ -79,6 +84,7 @@ impl From<&ExpectedUtxo> for AnnouncedUtxo {
             utxo: eu.utxo.clone(),
             sender_randomness: eu.sender_randomness,
             receiver_preimage: eu.receiver_preimage,
+            memo: None,
         }
     }
 }
@@ -140,7 +146,7 @@ impl TransactionProof {
             }
             TransactionProof::SingleProof(single_proof) => {
                 let claim = SingleProof::claim(kernel_mast_hash);
-                triton_vm::verify(Stark::default(), &claim, single_proof)
+                verifier_pool::verify(claim, single_proof.clone()).await
             }
             TransactionProof::ProofCollection(proof_collection) => {
                 proof_collection.verify(kernel_mast_hash)
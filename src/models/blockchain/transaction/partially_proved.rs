@@ -0,0 +1,484 @@
+//! Partially proved transactions (PSNTs), for constructing a transaction
+//! across several machines or parties.
+//!
+//! A transaction may spend an input once the input's lock script is proven to
+//! halt on the transaction kernel. Only the holder of the secret unlocking an
+//! input can make that proof, but the proof reveals nothing about the secret,
+//! and all other proofs of the transaction need no secrets at all. A
+//! [`PartiallyProvedTransaction`] carries everything the transaction is made
+//! of except these secrets, plus the lock-script proofs made so far.
+//!
+//! Constructing a transaction takes three steps:
+//!  1. Each party creates a PSNT funding its share of the outputs and the fee
+//!     from its own inputs, and the PSNTs are combined into one. This fixes
+//!     the transaction kernel.
+//!  2. Each party proves the lock scripts of its inputs. Copies of the PSNT
+//!     proved in parallel are combined again, collecting their proofs.
+//!  3. Once every lock script is proven, the PSNT is finalized into a
+//!     transaction supported by a [`ProofCollection`].
+//!
+//! The inputs are spent relative to the mutator set the PSNT was created
+//! with, so a PSNT must be finalized before the next block is found.
+
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tasm_lib::triton_vm::prelude::PublicInput;
+use tasm_lib::triton_vm::proof::Claim;
+use tasm_lib::triton_vm::proof::Proof;
+use tasm_lib::Digest;
+use thiserror::Error;
+use tokio::sync::TryLockError;
+use twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
+
+use super::lock_script::LockScript;
+use super::lock_script::LockScriptAndWitness;
+use super::transaction_kernel::TransactionKernel;
+use super::utxo::Utxo;
+use super::validity::proof_collection::ProofCollection;
+use super::PublicAnnouncement;
+use super::Transaction;
+use super::TransactionProof;
+use crate::models::blockchain::shared::Hash;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use crate::models::proof_abstractions::mast_hash::MastHash;
+use crate::models::proof_abstractions::tasm::program::TritonProverSync;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::models::proof_abstractions::verifier_pool;
+use crate::models::state::mempool_admission::AdmissionRejection;
+use crate::models::state::transaction_details::TransactionDetails;
+use crate::models::state::transaction_kernel_id::TransactionKernelId;
+use crate::models::state::wallet::expected_utxo::ExpectedUtxo;
+use crate::models::state::wallet::expected_utxo::UtxoNotifier;
+use crate::models::state::wallet::unlocked_utxo::UnlockedUtxo;
+use crate::models::state::wallet::wallet_state::WalletState;
+use crate::models::state::GlobalState;
+use crate::prelude::twenty_first;
+use crate::util_types::mutator_set::commit;
+use crate::util_types::mutator_set::ms_membership_proof::MsMembershipProof;
+use crate::util_types::mutator_set::mutator_set_accumulator::MutatorSetAccumulator;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error, Serialize, Deserialize, JsonSchema)]
+pub enum PsntError {
+    #[error("PSNT could not be decoded")]
+    Malformed,
+
+    #[error("no PSNTs to combine")]
+    Empty,
+
+    #[error("PSNTs are built on different mutator sets")]
+    MutatorSetMismatch,
+
+    #[error("PSNTs spend the same input")]
+    DoubleSpend,
+
+    #[error("PSNTs of different transactions cannot be combined once lock scripts are proven")]
+    AlreadyProven,
+
+    #[error("lock scripts of {0} inputs are not proven")]
+    Incomplete(usize),
+
+    #[error("inputs do not add up to the outputs and the fee")]
+    Unbalanced,
+
+    #[error("lock-script proof of input {0} is invalid")]
+    InvalidLockScriptProof(usize),
+
+    #[error("proving failed")]
+    ProvingFailed,
+
+    #[error("wallet is locked, or spend passphrase is missing or wrong")]
+    SpendNotAuthorized,
+
+    #[error("wallet would spend {0} net, more than the confirmed {1}")]
+    NetSpendExceeded(NeptuneCoins, NeptuneCoins),
+
+    #[error("transaction rejected: {0}")]
+    Rejected(#[from] AdmissionRejection),
+}
+
+/// The outcome of finalizing a PSNT on a node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum PsntFinalization {
+    /// Some lock scripts remain to be proven by other parties. Holds the
+    /// encoded PSNT, with the proofs this node could make.
+    Incomplete {
+        psnt: Vec<u8>,
+        num_unproven_inputs: usize,
+    },
+
+    /// The transaction was completed and broadcast.
+    Broadcast(TransactionKernelId),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PsntInput {
+    pub utxo: Utxo,
+    pub lock_script: LockScript,
+    pub membership_proof: MsMembershipProof,
+
+    /// Proof that the lock script halts on the transaction kernel, once made
+    pub lock_script_proof: Option<Proof>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PsntOutput {
+    pub utxo: Utxo,
+    pub sender_randomness: Digest,
+    pub receiver_digest: Digest,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartiallyProvedTransaction {
+    pub inputs: Vec<PsntInput>,
+    pub outputs: Vec<PsntOutput>,
+    pub public_announcements: Vec<PublicAnnouncement>,
+    pub fee: NeptuneCoins,
+    pub timestamp: Timestamp,
+    pub mutator_set_accumulator: MutatorSetAccumulator,
+}
+
+impl PartiallyProvedTransaction {
+    /// The PSNT of a transaction without coinbase, with no lock script proven
+    /// yet. The secrets unlocking the inputs are left out.
+    pub(crate) fn from_details(details: TransactionDetails) -> Self {
+        debug_assert!(details.coinbase.is_none(), "PSNTs have no coinbase");
+
+        let inputs = details
+            .tx_inputs
+            .iter()
+            .map(|unlocked_utxo| PsntInput {
+                utxo: unlocked_utxo.utxo.clone(),
+                lock_script: unlocked_utxo.lock_script_and_witness().into(),
+                membership_proof: unlocked_utxo.mutator_set_mp().clone(),
+                lock_script_proof: None,
+            })
+            .collect();
+        let outputs = details
+            .tx_outputs
+            .iter()
+            .map(|tx_output| PsntOutput {
+                utxo: tx_output.utxo(),
+                sender_randomness: tx_output.sender_randomness(),
+                receiver_digest: tx_output.receiver_digest(),
+            })
+            .collect();
+
+        Self {
+            inputs,
+            outputs,
+            public_announcements: details.tx_outputs.public_announcements(),
+            fee: details.fee,
+            timestamp: details.timestamp,
+            mutator_set_accumulator: details.mutator_set_accumulator,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("PSNT should serialize")
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PsntError> {
+        bincode::deserialize(bytes).map_err(|_| PsntError::Malformed)
+    }
+
+    /// The kernel of the transaction, which the lock scripts are proven on.
+    pub fn kernel(&self) -> TransactionKernel {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|input| {
+                self.mutator_set_accumulator
+                    .drop(Hash::hash(&input.utxo), &input.membership_proof)
+            })
+            .collect();
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|output| {
+                commit(
+                    Hash::hash(&output.utxo),
+                    output.sender_randomness,
+                    output.receiver_digest,
+                )
+            })
+            .collect();
+
+        TransactionKernel {
+            inputs,
+            outputs,
+            public_announcements: self.public_announcements.clone(),
+            fee: self.fee,
+            coinbase: None,
+            timestamp: self.timestamp,
+            mutator_set_hash: self.mutator_set_accumulator.hash(),
+        }
+    }
+
+    pub fn num_unproven_inputs(&self) -> usize {
+        self.inputs
+            .iter()
+            .filter(|input| input.lock_script_proof.is_none())
+            .count()
+    }
+
+    /// The outputs that the wallet can claim, as UTXOs it expects. An output
+    /// locked to one of its keys counts only if it also carries the key's
+    /// receiver digest, as the wallet could not spend it otherwise.
+    pub(crate) fn own_outputs(&self, wallet_state: &WalletState) -> Vec<ExpectedUtxo> {
+        self.outputs
+            .iter()
+            .filter_map(|output| {
+                let receiver_preimage =
+                    wallet_state.find_receiver_preimage_for_utxo(&output.utxo)?;
+                (receiver_preimage.hash() == output.receiver_digest).then(|| {
+                    ExpectedUtxo::new(
+                        output.utxo.clone(),
+                        output.sender_randomness,
+                        receiver_preimage,
+                        UtxoNotifier::Myself,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// What the wallet spends on the transaction: the amount of the inputs it
+    /// can unlock, less that of the outputs it can claim.
+    pub(crate) fn net_spend(&self, wallet_state: &WalletState) -> NeptuneCoins {
+        let own_inputs: NeptuneCoins = self
+            .inputs
+            .iter()
+            .filter(|input| {
+                wallet_state
+                    .find_spending_key_for_utxo(&input.utxo)
+                    .is_some()
+            })
+            .map(|input| input.utxo.get_native_currency_amount())
+            .sum();
+        let own_outputs: NeptuneCoins = self
+            .own_outputs(wallet_state)
+            .iter()
+            .map(|expected_utxo| expected_utxo.utxo.get_native_currency_amount())
+            .sum();
+
+        own_inputs - own_outputs
+    }
+
+    fn has_lock_script_proofs(&self) -> bool {
+        self.num_unproven_inputs() < self.inputs.len()
+    }
+
+    /// Combine PSNTs. Copies of the same transaction are combined into one
+    /// carrying the lock-script proofs of all of them. PSNTs of different
+    /// transactions are joined into one spending all their inputs and paying
+    /// all their outputs and fees, which requires that no lock script is
+    /// proven yet, as joining changes the kernel.
+    pub fn combine(psnts: Vec<Self>) -> Result<Self, PsntError> {
+        let mut psnts = psnts.into_iter();
+        let mut combined = psnts.next().ok_or(PsntError::Empty)?;
+        let others = psnts.collect_vec();
+
+        let kernel = combined.kernel();
+        if others.iter().all(|psnt| psnt.kernel() == kernel) {
+            for psnt in others {
+                for (input, other_input) in combined.inputs.iter_mut().zip(psnt.inputs) {
+                    if input.lock_script_proof.is_none() {
+                        input.lock_script_proof = other_input.lock_script_proof;
+                    }
+                }
+            }
+            return Ok(combined);
+        }
+
+        if combined.has_lock_script_proofs() || others.iter().any(Self::has_lock_script_proofs) {
+            return Err(PsntError::AlreadyProven);
+        }
+        for psnt in others {
+            if psnt.mutator_set_accumulator != combined.mutator_set_accumulator {
+                return Err(PsntError::MutatorSetMismatch);
+            }
+            combined.inputs.extend(psnt.inputs);
+            combined.outputs.extend(psnt.outputs);
+            combined
+                .public_announcements
+                .extend(psnt.public_announcements);
+            combined.fee = combined.fee + psnt.fee;
+            combined.timestamp = combined.timestamp.max(psnt.timestamp);
+        }
+        if !combined
+            .inputs
+            .iter()
+            .map(|input| input.membership_proof.aocl_leaf_index)
+            .all_unique()
+        {
+            return Err(PsntError::DoubleSpend);
+        }
+
+        Ok(combined)
+    }
+
+    /// Prove that the lock script of the input at `index` halts, given the
+    /// secret that unlocks the input.
+    pub(crate) async fn prove_lock_script(
+        &mut self,
+        index: usize,
+        lock_script_and_witness: &LockScriptAndWitness,
+        sync_device: &TritonProverSync,
+    ) -> Result<(), TryLockError> {
+        let kernel_mast_hash = self.kernel().mast_hash();
+        let public_input = PublicInput::new(kernel_mast_hash.reversed().values().to_vec());
+        let proof = lock_script_and_witness
+            .prove(public_input, sync_device)
+            .await?;
+        self.inputs[index].lock_script_proof = Some(proof);
+
+        Ok(())
+    }
+
+    /// Complete the transaction, once every lock script is proven, by
+    /// producing the remaining proofs of its proof collection.
+    pub(crate) async fn finalize(
+        self,
+        sync_device: &TritonProverSync,
+    ) -> Result<Transaction, PsntError> {
+        let num_unproven_inputs = self.num_unproven_inputs();
+        if num_unproven_inputs > 0 {
+            return Err(PsntError::Incomplete(num_unproven_inputs));
+        }
+
+        let total_input: NeptuneCoins = self
+            .inputs
+            .iter()
+            .map(|input| input.utxo.get_native_currency_amount())
+            .sum();
+        let total_output: NeptuneCoins = self
+            .outputs
+            .iter()
+            .map(|output| output.utxo.get_native_currency_amount())
+            .sum();
+        if total_input != total_output + self.fee {
+            return Err(PsntError::Unbalanced);
+        }
+
+        let kernel = self.kernel();
+        let kernel_mast_hash = kernel.mast_hash();
+        let mut lock_scripts_halt = vec![];
+        let mut unlocked_utxos = vec![];
+        for (index, input) in self.inputs.into_iter().enumerate() {
+            let proof = input
+                .lock_script_proof
+                .expect("all lock scripts are proven");
+            let claim = Claim::new(input.lock_script.hash())
+                .with_input(kernel_mast_hash.reversed().values().to_vec());
+            if input.lock_script.hash() != input.utxo.lock_script_hash
+                || !verifier_pool::verify(claim, proof.clone()).await
+            {
+                return Err(PsntError::InvalidLockScriptProof(index));
+            }
+
+            lock_scripts_halt.push(proof);
+            unlocked_utxos.push(UnlockedUtxo::with_proven_lock_script(
+                input.utxo,
+                input.lock_script,
+                input.membership_proof,
+            ));
+        }
+
+        let (output_utxos, sender_randomnesses, receiver_digests) = self
+            .outputs
+            .into_iter()
+            .map(|output| {
+                (
+                    output.utxo,
+                    output.sender_randomness,
+                    output.receiver_digest,
+                )
+            })
+            .multiunzip();
+        let primitive_witness = GlobalState::generate_primitive_witness(
+            unlocked_utxos,
+            output_utxos,
+            sender_randomnesses,
+            receiver_digests,
+            kernel.clone(),
+            self.mutator_set_accumulator,
+        );
+        let proof_collection = ProofCollection::produce_with_lock_script_proofs(
+            &primitive_witness,
+            lock_scripts_halt,
+            sync_device,
+        )
+        .await
+        .map_err(|_| PsntError::ProvingFailed)?;
+
+        Ok(Transaction {
+            kernel,
+            proof: TransactionProof::ProofCollection(proof_collection),
+        })
+    }
+}
+
+#[cfg(test)]
+mod partially_proved_tests {
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::blockchain::transaction::transaction_output::UtxoNotificationMedium;
+    use crate::models::state::wallet::coin_selection::CoinSelectionPolicy;
+    use crate::models::state::wallet::WalletSecret;
+    use crate::tests::shared::mock_genesis_global_state;
+
+    async fn psnt_for_tests(network: Network) -> PartiallyProvedTransaction {
+        let wallet_secret = WalletSecret::devnet_wallet();
+        let alice_key = wallet_secret.nth_generation_spending_key_for_tests(0);
+        let alice = mock_genesis_global_state(network, 1, wallet_secret).await;
+        let alice = alice.lock_guard().await;
+        let in_seven_months = alice.chain.light_state().header().timestamp + Timestamp::months(7);
+        let (details, _) = alice
+            .create_transaction_details(
+                vec![].into(),
+                alice_key.into(),
+                UtxoNotificationMedium::OffChain,
+                NeptuneCoins::new(1),
+                in_seven_months,
+                CoinSelectionPolicy::default(),
+            )
+            .await
+            .unwrap();
+
+        PartiallyProvedTransaction::from_details(details)
+    }
+
+    #[tokio::test]
+    async fn combining_copies_collects_their_lock_script_proofs_only() {
+        let psnt = psnt_for_tests(Network::Main).await;
+        assert!(!psnt.inputs.is_empty());
+        assert_eq!(
+            psnt,
+            PartiallyProvedTransaction::from_bytes(&psnt.to_bytes()).unwrap()
+        );
+
+        let mut proven = psnt.clone();
+        proven.inputs[0].lock_script_proof = Some(Proof(vec![]));
+        let combined = PartiallyProvedTransaction::combine(vec![psnt.clone(), proven]).unwrap();
+        assert_eq!(psnt.kernel(), combined.kernel());
+        assert!(combined.inputs[0].lock_script_proof.is_some());
+
+        // Joining the PSNT with itself would spend its inputs twice.
+        let mut other = psnt.clone();
+        other.fee = NeptuneCoins::new(2);
+        assert_eq!(
+            Err(PsntError::DoubleSpend),
+            PartiallyProvedTransaction::combine(vec![psnt.clone(), other.clone()])
+        );
+        assert_eq!(
+            Err(PsntError::AlreadyProven),
+            PartiallyProvedTransaction::combine(vec![combined, other])
+        );
+        assert_eq!(
+            Err(PsntError::Empty),
+            PartiallyProvedTransaction::combine(vec![])
+        );
+    }
+}
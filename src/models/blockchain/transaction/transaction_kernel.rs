@@ -1,6 +1,9 @@
+use anyhow::bail;
+use anyhow::Result;
 use arbitrary::Arbitrary;
 use get_size::GetSize;
 use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use strum::EnumCount;
@@ -9,6 +12,8 @@ use tasm_lib::structure::tasm_object::TasmObject;
 use twenty_first::math::b_field_element::BFieldElement;
 use twenty_first::math::bfield_codec::BFieldCodec;
 use twenty_first::math::tip5::Digest;
+use twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
+use twenty_first::util_types::merkle_tree::MerkleTreeInclusionProof;
 
 use super::primitive_witness::PrimitiveWitness;
 use super::PublicAnnouncement;
@@ -17,8 +22,11 @@ use crate::models::proof_abstractions::mast_hash::HasDiscriminant;
 use crate::models::proof_abstractions::mast_hash::MastHash;
 use crate::models::proof_abstractions::timestamp::Timestamp;
 use crate::prelude::twenty_first;
+use crate::util_types::json_schema::BFieldElementSchema;
+use crate::util_types::json_schema::DigestSchema;
 use crate::util_types::mutator_set::addition_record::AdditionRecord;
 use crate::util_types::mutator_set::removal_record::RemovalRecord;
+use crate::Hash;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, GetSize, BFieldCodec, TasmObject)]
 pub struct TransactionKernel {
@@ -44,8 +52,22 @@ impl From<PrimitiveWitness> for TransactionKernel {
     }
 }
 
-#[derive(VariantArray, Debug, Clone, EnumCount, Copy, strum_macros::Display)]
+#[derive(
+    VariantArray,
+    Debug,
+    Clone,
+    EnumCount,
+    Copy,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
 #[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
 pub enum TransactionKernelField {
     Inputs,
     Outputs,
@@ -93,6 +115,67 @@ impl MastHash for TransactionKernel {
     }
 }
 
+impl TransactionKernel {
+    /// Disclose the value of one field, such that it can be verified against
+    /// the kernel's MAST hash without revealing the other fields.
+    pub fn disclose_field(
+        &self,
+        field: TransactionKernelField,
+    ) -> TransactionKernelFieldDisclosure {
+        TransactionKernelFieldDisclosure {
+            kernel_mast_hash: self.mast_hash(),
+            field,
+            value: self.mast_sequences().swap_remove(field.discriminant()),
+            authentication_path: self.mast_path(field),
+        }
+    }
+}
+
+/// The value of one field of a transaction kernel, with the authentication
+/// path of the field in the kernel's Merkle tree.
+///
+/// Proves the fee or the timestamp of a transaction, for example, to anyone
+/// who knows the transaction's kernel MAST hash, without revealing its inputs
+/// and outputs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TransactionKernelFieldDisclosure {
+    /// The MAST hash of the kernel, which the verifier must know to be that
+    /// of the transaction in question
+    #[schemars(with = "DigestSchema")]
+    pub kernel_mast_hash: Digest,
+
+    pub field: TransactionKernelField,
+
+    /// The encoding of the field's value, which is the preimage of its leaf
+    #[schemars(with = "Vec<BFieldElementSchema>")]
+    pub value: Vec<BFieldElement>,
+
+    #[schemars(with = "Vec<DigestSchema>")]
+    pub authentication_path: Vec<Digest>,
+}
+
+impl TransactionKernelFieldDisclosure {
+    /// Whether the disclosed value is that of the field in the kernel with the
+    /// given MAST hash.
+    pub fn verify(&self, kernel_mast_hash: Digest) -> bool {
+        self.kernel_mast_hash == kernel_mast_hash
+            && MerkleTreeInclusionProof {
+                tree_height: TransactionKernel::MAST_HEIGHT,
+                indexed_leafs: vec![(self.field.discriminant(), Hash::hash_varlen(&self.value))],
+                authentication_structure: self.authentication_path.clone(),
+            }
+            .verify(kernel_mast_hash)
+    }
+
+    /// The disclosed value, decoded as the field's type.
+    pub fn decode<T: BFieldCodec>(&self) -> Result<T> {
+        match T::decode(&self.value) {
+            Ok(value) => Ok(*value),
+            Err(e) => bail!("could not decode disclosed {}: {e}", self.field),
+        }
+    }
+}
+
 impl<'a> Arbitrary<'a> for TransactionKernel {
     fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
         let num_inputs = u.int_in_range(0..=4)?;
@@ -178,6 +261,40 @@ pub mod transaction_kernel_tests {
         }
     }
 
+    #[test]
+    fn disclosed_fields_verify_against_kernel_mast_hash() {
+        let kernel = pseudorandom_transaction_kernel(random(), 2, 2, 1);
+        let mast_hash = kernel.mast_hash();
+
+        let fee = kernel.disclose_field(TransactionKernelField::Fee);
+        assert!(fee.verify(mast_hash));
+        assert_eq!(kernel.fee, fee.decode::<NeptuneCoins>().unwrap());
+
+        let timestamp = kernel.disclose_field(TransactionKernelField::Timestamp);
+        assert!(timestamp.verify(mast_hash));
+        assert_eq!(kernel.timestamp, timestamp.decode::<Timestamp>().unwrap());
+
+        for field in TransactionKernelField::VARIANTS {
+            assert!(kernel.disclose_field(*field).verify(mast_hash));
+        }
+
+        let mut forged_fee = fee.clone();
+        forged_fee.value = (kernel.fee + NeptuneCoins::new(1)).encode();
+        assert!(!forged_fee.verify(mast_hash));
+
+        let mut other_field = fee.clone();
+        other_field.field = TransactionKernelField::Coinbase;
+        assert!(!other_field.verify(mast_hash));
+
+        let other_kernel = pseudorandom_transaction_kernel(random(), 2, 2, 1);
+        assert!(!fee.verify(other_kernel.mast_hash()));
+
+        assert_eq!(
+            TransactionKernelField::MutatorSetHash,
+            "mutator_set_hash".parse().unwrap()
+        );
+    }
+
     #[test]
     pub fn arbitrary_tx_kernel_is_deterministic() {
         use proptest::prelude::Strategy;
@@ -4,9 +4,11 @@ use std::ops::Deref;
 use std::ops::DerefMut;
 
 use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 
+use super::memo::Memo;
 use super::PublicAnnouncement;
 use crate::models::blockchain::shared::Hash;
 use crate::models::blockchain::transaction::utxo::Utxo;
@@ -19,7 +21,8 @@ use crate::util_types::mutator_set::addition_record::AdditionRecord;
 use crate::util_types::mutator_set::commit;
 
 /// Enumerates the medium of exchange for UTXO-notifications.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[non_exhaustive]
 pub enum UtxoNotificationMedium {
     /// The UTXO notification should be sent on-chain
     OnChain,
@@ -51,22 +54,27 @@ pub(crate) enum UtxoNotifyMethod {
 /// we should consider adding functionality that would facilitate passing
 /// these payloads from sender to receiver off-chain for lower-fee transfers
 /// between trusted parties or eg wallets owned by the same person/org.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct UtxoNotificationPayload {
     utxo: Utxo,
     sender_randomness: Digest,
+    memo: Option<Memo>,
 }
 
 impl UtxoNotificationPayload {
-    // TODO: Remove test flag when used in main code.
-    #[cfg(test)]
     pub(crate) fn new(utxo: Utxo, sender_randomness: Digest) -> Self {
         Self {
             utxo,
             sender_randomness,
+            memo: None,
         }
     }
 
+    pub(crate) fn with_memo(mut self, memo: Option<Memo>) -> Self {
+        self.memo = memo;
+        self
+    }
+
     pub(crate) fn utxo(&self) -> Utxo {
         self.utxo.clone()
     }
@@ -74,6 +82,37 @@ impl UtxoNotificationPayload {
     pub(crate) fn sender_randomness(&self) -> Digest {
         self.sender_randomness
     }
+
+    pub(crate) fn memo(&self) -> Option<&Memo> {
+        self.memo.as_ref()
+    }
+
+    /// Serialize for encryption. The memo, if any, is appended to the
+    /// encoding of `(utxo, sender_randomness)`, such that wallets unaware of
+    /// memos still decode the rest of the payload.
+    pub(crate) fn to_plaintext(&self) -> Vec<u8> {
+        let mut plaintext = bincode::serialize(&(&self.utxo, self.sender_randomness)).unwrap();
+        if let Some(memo) = &self.memo {
+            plaintext.extend(bincode::serialize(memo).unwrap());
+        }
+        plaintext
+    }
+
+    pub(crate) fn from_plaintext(plaintext: &[u8]) -> Result<Self, bincode::Error> {
+        let mut reader = plaintext;
+        let (utxo, sender_randomness) = bincode::deserialize_from(&mut reader)?;
+        let memo = if reader.is_empty() {
+            None
+        } else {
+            Some(bincode::deserialize_from(&mut reader)?)
+        };
+
+        Ok(Self {
+            utxo,
+            sender_randomness,
+            memo,
+        })
+    }
 }
 
 /// represents a transaction output, as accepted by
@@ -87,6 +126,7 @@ pub struct TxOutput {
     sender_randomness: Digest,
     receiver_digest: Digest,
     notification_method: UtxoNotifyMethod,
+    memo: Option<Memo>,
 }
 
 impl From<&TxOutput> for AdditionRecord {
@@ -169,6 +209,7 @@ impl TxOutput {
             sender_randomness,
             receiver_digest,
             notification_method,
+            memo: None,
         }
     }
 
@@ -187,6 +228,7 @@ impl TxOutput {
             sender_randomness,
             receiver_digest: privacy_digest,
             notification_method: UtxoNotifyMethod::None,
+            memo: None,
         }
     }
 
@@ -203,6 +245,7 @@ impl TxOutput {
             sender_randomness,
             receiver_digest: receiving_address.privacy_digest(),
             notification_method: UtxoNotifyMethod::OnChain(receiving_address),
+            memo: None,
         }
     }
 
@@ -219,9 +262,20 @@ impl TxOutput {
             sender_randomness,
             receiver_digest: receiving_address.privacy_digest(),
             notification_method: UtxoNotifyMethod::OffChain(receiving_address),
+            memo: None,
         }
     }
 
+    /// Attach a memo, readable only by sender and receiver.
+    pub(crate) fn with_memo(mut self, memo: Option<Memo>) -> Self {
+        self.memo = memo;
+        self
+    }
+
+    pub(crate) fn memo(&self) -> Option<&Memo> {
+        self.memo.as_ref()
+    }
+
     pub(crate) fn is_offchain(&self) -> bool {
         matches!(self.notification_method, UtxoNotifyMethod::OffChain(_))
     }
@@ -243,10 +297,9 @@ impl TxOutput {
             UtxoNotifyMethod::None => None,
             UtxoNotifyMethod::OffChain(_) => None,
             UtxoNotifyMethod::OnChain(receiving_address) => {
-                let notification_payload = UtxoNotificationPayload {
-                    utxo: self.utxo(),
-                    sender_randomness: self.sender_randomness(),
-                };
+                let notification_payload =
+                    UtxoNotificationPayload::new(self.utxo(), self.sender_randomness())
+                        .with_memo(self.memo.clone());
                 Some(receiving_address.generate_public_announcement(notification_payload))
             }
         }
@@ -357,7 +410,7 @@ impl TxOutputList {
     }
 
     /// Returns all public announcement for this TxOutputList
-    pub(crate) fn public_announcements(&self) -> Vec<PublicAnnouncement> {
+    pub fn public_announcements(&self) -> Vec<PublicAnnouncement> {
         let mut public_announcements = vec![];
         for tx_output in self.0.iter() {
             if let Some(pa) = tx_output.public_announcement() {
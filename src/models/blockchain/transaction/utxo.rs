@@ -10,6 +10,7 @@ use rand::rngs::StdRng;
 use rand::Rng;
 use rand::RngCore;
 use rand::SeedableRng;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use tasm_lib::prelude::TasmObject;
@@ -20,18 +21,32 @@ use twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
 
 use super::lock_script::LockScript;
 use crate::models::blockchain::shared::Hash;
+use crate::models::blockchain::type_scripts::known_type_scripts;
 use crate::models::blockchain::type_scripts::native_currency::NativeCurrency;
 use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
 use crate::models::blockchain::type_scripts::time_lock::TimeLock;
 use crate::models::proof_abstractions::tasm::program::ConsensusProgram;
 use crate::models::proof_abstractions::timestamp::Timestamp;
 use crate::prelude::twenty_first;
+use crate::util_types::json_schema::BFieldElementSchema;
+use crate::util_types::json_schema::DigestSchema;
 
 #[derive(
-    Clone, Debug, Serialize, Deserialize, PartialEq, Eq, BFieldCodec, TasmObject, Arbitrary,
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    BFieldCodec,
+    TasmObject,
+    Arbitrary,
+    JsonSchema,
 )]
 pub struct Coin {
+    #[schemars(with = "DigestSchema")]
     pub type_script_hash: Digest,
+    #[schemars(with = "Vec<BFieldElementSchema>")]
     pub state: Vec<BFieldElement>,
 }
 
@@ -64,8 +79,11 @@ impl Coin {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, BFieldCodec, TasmObject)]
+#[derive(
+    Clone, Debug, Serialize, Deserialize, PartialEq, Eq, BFieldCodec, TasmObject, JsonSchema,
+)]
 pub struct Utxo {
+    #[schemars(with = "DigestSchema")]
     pub lock_script_hash: Digest,
     pub coins: Vec<Coin>,
 }
@@ -148,13 +166,13 @@ impl Utxo {
     }
 
     /// Determine whether the UTXO has coins that contain only known type
-    /// scripts. If other type scripts are included, then we cannot spend
-    /// this UTXO.
+    /// scripts, including those of registered
+    /// [plugins](crate::models::blockchain::type_scripts::plugins). If other
+    /// type scripts are included, then we cannot spend this UTXO.
     pub fn has_known_type_scripts(&self) -> bool {
-        let known_type_script_hashes = [NativeCurrency.hash(), TimeLock.hash()];
         self.coins
             .iter()
-            .all(|c| known_type_script_hashes.contains(&c.type_script_hash))
+            .all(|c| known_type_scripts::is_known(c.type_script_hash))
     }
 
     /// Determine if the UTXO can be spent at a given date in the future,
@@ -3,10 +3,8 @@ use itertools::Itertools;
 use serde::Deserialize;
 use serde::Serialize;
 use tasm_lib::structure::tasm_object::TasmObject;
-use tasm_lib::triton_vm;
 use tasm_lib::triton_vm::prelude::*;
 use tasm_lib::triton_vm::proof::Claim;
-use tasm_lib::triton_vm::stark::Stark;
 use tasm_lib::twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
 use tasm_lib::Digest;
 use tokio::sync::TryLockError;
@@ -27,6 +25,7 @@ use crate::models::blockchain::transaction::BFieldCodec;
 use crate::models::proof_abstractions::mast_hash::MastHash;
 use crate::models::proof_abstractions::tasm::program::ConsensusProgram;
 use crate::models::proof_abstractions::tasm::program::TritonProverSync;
+use crate::models::proof_abstractions::verifier_pool;
 use crate::models::proof_abstractions::SecretWitness;
 use crate::triton_vm::proof::Proof;
 
@@ -138,6 +137,32 @@ impl ProofCollection {
     pub(crate) async fn produce(
         primitive_witness: &PrimitiveWitness,
         sync_device: &TritonProverSync,
+    ) -> Result<Self, TryLockError> {
+        let txk_mast_hash = primitive_witness.kernel.mast_hash();
+        let txk_mast_hash_as_input = PublicInput::new(txk_mast_hash.reversed().values().to_vec());
+
+        debug!("proving lock scripts");
+        let mut lock_scripts_halt = vec![];
+        for lock_script_and_witness in primitive_witness.lock_scripts_and_witnesses.iter() {
+            lock_scripts_halt.push(
+                lock_script_and_witness
+                    .prove(txk_mast_hash_as_input.clone(), sync_device)
+                    .await?,
+            );
+        }
+
+        Self::produce_with_lock_script_proofs(primitive_witness, lock_scripts_halt, sync_device)
+            .await
+    }
+
+    /// Like [`Self::produce`], with the proofs that the lock scripts halt made
+    /// elsewhere, one for each input. Only these proofs need the secrets that
+    /// unlock the inputs, so the lock scripts of the primitive witness may
+    /// come without witnesses.
+    pub(crate) async fn produce_with_lock_script_proofs(
+        primitive_witness: &PrimitiveWitness,
+        lock_scripts_halt: Vec<Proof>,
+        sync_device: &TritonProverSync,
     ) -> Result<Self, TryLockError> {
         let (
             removal_records_integrity_witness,
@@ -147,7 +172,6 @@ impl ProofCollection {
         ) = Self::extract_specific_witnesses(primitive_witness);
 
         let txk_mast_hash = primitive_witness.kernel.mast_hash();
-        let txk_mast_hash_as_input = PublicInput::new(txk_mast_hash.reversed().values().to_vec());
         let salted_inputs_hash = Hash::hash(&primitive_witness.input_utxos);
         let salted_outputs_hash = Hash::hash(&primitive_witness.output_utxos);
         debug!("proving, txk hash: {}", txk_mast_hash);
@@ -191,16 +215,6 @@ impl ProofCollection {
             )
             .await?;
 
-        debug!("proving lock scripts");
-        let mut lock_scripts_halt = vec![];
-        for lock_script_and_witness in primitive_witness.lock_scripts_and_witnesses.iter() {
-            lock_scripts_halt.push(
-                lock_script_and_witness
-                    .prove(txk_mast_hash_as_input.clone(), sync_device)
-                    .await?,
-            );
-        }
-
         debug!("proving type scripts");
         let mut type_scripts_halt = vec![];
         for (i, tsaw) in primitive_witness
@@ -325,44 +339,33 @@ impl ProofCollection {
 
         // verify
         debug!("verifying removal records integrity ...");
-        let rri = triton_vm::verify(
-            Stark::default(),
+        let rri = verifier_pool::verify_blocking(
             &removal_records_integrity_claim,
             &self.removal_records_integrity,
         );
         debug!("{rri}");
         debug!("verifying kernel to outputs ...");
-        let k2o = triton_vm::verify(
-            Stark::default(),
-            &kernel_to_outputs_claim,
-            &self.kernel_to_outputs,
-        );
+        let k2o = verifier_pool::verify_blocking(&kernel_to_outputs_claim, &self.kernel_to_outputs);
         debug!("{k2o}");
         debug!("verifying collect lock scripts ...");
-        let cls = triton_vm::verify(
-            Stark::default(),
-            &collect_lock_scripts_claim,
-            &self.collect_lock_scripts,
-        );
+        let cls =
+            verifier_pool::verify_blocking(&collect_lock_scripts_claim, &self.collect_lock_scripts);
         debug!("{cls}");
         debug!("verifying collect type scripts ...");
-        let cts = triton_vm::verify(
-            Stark::default(),
-            &collect_type_scripts_claim,
-            &self.collect_type_scripts,
-        );
+        let cts =
+            verifier_pool::verify_blocking(&collect_type_scripts_claim, &self.collect_type_scripts);
         debug!("{cts}");
         debug!("verifying that all lock scripts halt ...");
         let lsh = lock_script_claims
             .iter()
             .zip(self.lock_scripts_halt.iter())
-            .all(|(cl, pr)| triton_vm::verify(Stark::default(), cl, pr));
+            .all(|(cl, pr)| verifier_pool::verify_blocking(cl, pr));
         debug!("{lsh}");
         debug!("verifying that all type scripts halt ...");
         let tsh = type_script_claims
             .iter()
             .zip(self.type_scripts_halt.iter())
-            .all(|(cl, pr)| triton_vm::verify(Stark::default(), cl, pr));
+            .all(|(cl, pr)| verifier_pool::verify_blocking(cl, pr));
         debug!("{tsh}");
 
         // and all bits together and return
@@ -2,6 +2,7 @@ use tasm_lib::Digest;
 
 use super::native_currency::NativeCurrency;
 use super::native_currency::NativeCurrencyWitness;
+use super::plugins;
 use super::time_lock::TimeLock;
 use super::time_lock::TimeLockWitness;
 use super::TypeScriptAndWitness;
@@ -23,7 +24,23 @@ pub(crate) fn match_type_script_and_generate_witness(
         TimeLockWitness::new(transaction_kernel, salted_input_utxos, salted_output_utxos)
             .type_script_and_witness()
     } else {
-        return None;
+        return plugins::type_script_and_witness(
+            type_script_hash,
+            transaction_kernel,
+            salted_input_utxos,
+            salted_output_utxos,
+        );
     };
     Some(type_script_and_witness)
 }
+
+/// Whether the type script is one of the type scripts defined in this crate.
+pub(crate) fn is_built_in(type_script_hash: Digest) -> bool {
+    [NativeCurrency.hash(), TimeLock.hash()].contains(&type_script_hash)
+}
+
+/// Whether the wallet can generate witnesses for the type script: it is
+/// either built in or provided by a registered [plugin](plugins).
+pub(crate) fn is_known(type_script_hash: Digest) -> bool {
+    is_built_in(type_script_hash) || plugins::is_registered(type_script_hash)
+}
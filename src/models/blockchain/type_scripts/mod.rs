@@ -1,6 +1,7 @@
 pub mod known_type_scripts;
 pub mod native_currency;
 pub mod neptune_coins;
+pub mod plugins;
 pub mod time_lock;
 
 use std::collections::HashMap;
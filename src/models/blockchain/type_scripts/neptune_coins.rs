@@ -16,6 +16,7 @@ use num_traits::FromPrimitive;
 use num_traits::One;
 use num_traits::Zero;
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use tasm_lib::structure::tasm_object::TasmObject;
@@ -41,7 +42,7 @@ use crate::models::proof_abstractions::tasm::program::ConsensusProgram;
 /// program related to block validity, it is important to use `safe_add` rather than `+` as
 /// the latter operation does not care about overflow. Not testing for overflow can cause
 /// inflation bugs.
-#[derive(Clone, Copy, Serialize, Deserialize, Eq, BFieldCodec, TasmObject, Default)]
+#[derive(Clone, Copy, Serialize, Deserialize, Eq, BFieldCodec, TasmObject, Default, JsonSchema)]
 pub struct NeptuneCoins(u128);
 
 impl NeptuneCoins {
@@ -162,6 +163,36 @@ impl NeptuneCoins {
             None
         }
     }
+
+    /// Split a non-negative amount into `num_parts` amounts that add up to it
+    /// and differ by at most one nau, largest first.
+    pub fn split(&self, num_parts: usize) -> Vec<NeptuneCoins> {
+        assert!(num_parts > 0, "cannot split into zero parts");
+        assert!(!self.is_negative(), "cannot split negative amount");
+        let num_parts = num_parts as u128;
+        let (quotient, remainder) = (self.0 / num_parts, self.0 % num_parts);
+        (0..num_parts)
+            .map(|i| NeptuneCoins(quotient + u128::from(i < remainder)))
+            .collect()
+    }
+
+    /// The largest number of parts, at most `max_parts`, that [`Self::split`]
+    /// splits this non-negative amount into such that no part is smaller than
+    /// `min_part`. At least one, even if the amount is smaller than `min_part`.
+    pub fn max_split_parts(&self, min_part: NeptuneCoins, max_parts: usize) -> usize {
+        assert!(!self.is_negative(), "cannot split negative amount");
+        let max_parts = max_parts.max(1);
+        if min_part.is_negative() || min_part.0 == 0 {
+            return max_parts;
+        }
+
+        // The smallest part of a split into n parts is the amount divided by
+        // n, rounded down.
+        let num_parts = self.0 / min_part.0;
+        usize::try_from(num_parts)
+            .map_or(max_parts, |num_parts| num_parts.min(max_parts))
+            .max(1)
+    }
 }
 
 impl GetSize for NeptuneCoins {
@@ -390,6 +421,37 @@ mod amount_tests {
 
     use super::*;
 
+    #[proptest]
+    fn split_parts_add_up_and_differ_by_at_most_one_nau(
+        #[strategy(0u64..)] nau: u64,
+        #[strategy(1usize..20)] num_parts: usize,
+    ) {
+        let amount = NeptuneCoins::from_nau(nau.into()).unwrap();
+        let parts = amount.split(num_parts);
+        assert_eq!(num_parts, parts.len());
+        assert_eq!(amount, parts.iter().copied().sum());
+        assert!(parts
+            .iter()
+            .tuple_windows()
+            .all(|(larger, smaller)| larger >= smaller));
+        assert!(parts[0].to_nau() - parts[num_parts - 1].to_nau() <= BigInt::one());
+    }
+
+    #[proptest]
+    fn max_split_parts_is_largest_split_without_smaller_parts(
+        #[strategy(0u64..1_000_000)] nau: u64,
+        #[strategy(0u64..1_000)] min_nau: u64,
+        #[strategy(1usize..50)] max_parts: usize,
+    ) {
+        let amount = NeptuneCoins::from_nau(nau.into()).unwrap();
+        let min_part = NeptuneCoins::from_nau(min_nau.into()).unwrap();
+        let expected = (1..=max_parts)
+            .rev()
+            .find(|&num_parts| amount.split(num_parts).iter().all(|part| *part >= min_part))
+            .unwrap_or(1);
+        assert_eq!(expected, amount.max_split_parts(min_part, max_parts));
+    }
+
     #[test]
     fn test_slice_conversion() {
         let mut rng = thread_rng();
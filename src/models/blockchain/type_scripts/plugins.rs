@@ -0,0 +1,237 @@
+//! Registry of experimental type scripts, for contract experimentation on
+//! regtest without modifying the consensus programs of this crate.
+//!
+//! A [`TypeScriptPlugin`] provides a type script and generates its witness
+//! for a given transaction. Once registered, coins with that type script are
+//! considered spendable by the wallet, and primitive witnesses for
+//! transactions involving them include the plugin's type script and witness.
+//!
+//! Plugins are only available when the crate is built with the
+//! `type-script-plugins` feature, and in unit tests, and can only be
+//! registered on [`Network::RegTest`]. In all other builds the hooks compile
+//! to constant "unknown type script", so production code pays nothing for
+//! them.
+//!
+//! ```ignore
+//! plugins::register(Network::RegTest, Arc::new(MyTypeScript))?;
+//! ```
+
+use tasm_lib::triton_vm::prelude::Program;
+use tasm_lib::Digest;
+
+use super::TypeScriptAndWitness;
+use crate::config_models::network::Network;
+use crate::models::blockchain::transaction::primitive_witness::SaltedUtxos;
+use crate::models::blockchain::transaction::transaction_kernel::TransactionKernel;
+
+/// A type script along with its witness generator.
+pub trait TypeScriptPlugin: Send + Sync {
+    fn program(&self) -> Program;
+
+    /// Generate the witness of the type script for a transaction, in the
+    /// same way as [`TypeScriptWitness`](super::TypeScriptWitness) does for
+    /// built-in type scripts.
+    fn type_script_and_witness(
+        &self,
+        transaction_kernel: TransactionKernel,
+        salted_input_utxos: SaltedUtxos,
+        salted_output_utxos: SaltedUtxos,
+    ) -> TypeScriptAndWitness;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum PluginError {
+    #[error("type script plugins are only available on regtest, not on {0}")]
+    WrongNetwork(Network),
+
+    #[error("type script plugins are not enabled in this build")]
+    Disabled,
+
+    #[error("type script {0} is built in")]
+    BuiltIn(Digest),
+}
+
+#[cfg(any(test, feature = "type-script-plugins"))]
+mod registry {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::OnceLock;
+    use std::sync::RwLock;
+
+    use tasm_lib::Digest;
+
+    use super::TypeScriptPlugin;
+
+    pub(super) type Registry = RwLock<HashMap<Digest, Arc<dyn TypeScriptPlugin>>>;
+
+    pub(super) fn registered() -> &'static Registry {
+        static REGISTERED: OnceLock<Registry> = OnceLock::new();
+        REGISTERED.get_or_init(Default::default)
+    }
+
+    /// Remove a registered plugin.
+    pub fn unregister(type_script_hash: Digest) {
+        registered().write().unwrap().remove(&type_script_hash);
+    }
+
+    pub(super) fn get(type_script_hash: Digest) -> Option<Arc<dyn TypeScriptPlugin>> {
+        registered().read().unwrap().get(&type_script_hash).cloned()
+    }
+}
+
+#[cfg(any(test, feature = "type-script-plugins"))]
+pub use registry::unregister;
+
+/// Register a plugin, replacing any plugin with the same type script.
+/// Returns the hash of the type script.
+pub fn register(
+    network: Network,
+    plugin: std::sync::Arc<dyn TypeScriptPlugin>,
+) -> Result<Digest, PluginError> {
+    if !cfg!(any(test, feature = "type-script-plugins")) {
+        return Err(PluginError::Disabled);
+    }
+    if network != Network::RegTest {
+        return Err(PluginError::WrongNetwork(network));
+    }
+
+    let type_script_hash = plugin.program().hash();
+    if super::known_type_scripts::is_built_in(type_script_hash) {
+        return Err(PluginError::BuiltIn(type_script_hash));
+    }
+
+    #[cfg(any(test, feature = "type-script-plugins"))]
+    registry::registered()
+        .write()
+        .unwrap()
+        .insert(type_script_hash, plugin);
+
+    Ok(type_script_hash)
+}
+
+/// Whether a plugin for the given type script is registered.
+#[inline]
+pub(crate) fn is_registered(type_script_hash: Digest) -> bool {
+    generator(type_script_hash).is_some()
+}
+
+/// Generate the type script and witness with the registered plugin, if any.
+#[inline]
+pub(crate) fn type_script_and_witness(
+    type_script_hash: Digest,
+    transaction_kernel: TransactionKernel,
+    salted_input_utxos: SaltedUtxos,
+    salted_output_utxos: SaltedUtxos,
+) -> Option<TypeScriptAndWitness> {
+    generator(type_script_hash).map(|plugin| {
+        plugin.type_script_and_witness(transaction_kernel, salted_input_utxos, salted_output_utxos)
+    })
+}
+
+#[cfg(any(test, feature = "type-script-plugins"))]
+fn generator(type_script_hash: Digest) -> Option<std::sync::Arc<dyn TypeScriptPlugin>> {
+    registry::get(type_script_hash)
+}
+
+#[cfg(not(any(test, feature = "type-script-plugins")))]
+#[inline(always)]
+fn generator(_type_script_hash: Digest) -> Option<std::sync::Arc<dyn TypeScriptPlugin>> {
+    None
+}
+
+#[cfg(test)]
+mod plugins_tests {
+    use std::sync::Arc;
+
+    use rand::random;
+    use tasm_lib::triton_vm::prelude::*;
+
+    use super::*;
+    use crate::models::blockchain::transaction::lock_script::LockScript;
+    use crate::models::blockchain::transaction::utxo::Coin;
+    use crate::models::blockchain::transaction::utxo::Utxo;
+    use crate::models::blockchain::type_scripts::known_type_scripts::match_type_script_and_generate_witness;
+    use crate::models::blockchain::type_scripts::native_currency::NativeCurrency;
+    use crate::models::proof_abstractions::tasm::program::ConsensusProgram;
+    use crate::tests::shared::random_transaction_kernel;
+
+    /// Type script that accepts every transaction. The pushed constant makes
+    /// the program, and thus its hash, unique to each test.
+    struct AcceptAll(u32);
+
+    impl TypeScriptPlugin for AcceptAll {
+        fn program(&self) -> Program {
+            Program::new(&triton_asm!(push {self.0} pop 1 halt))
+        }
+
+        fn type_script_and_witness(
+            &self,
+            _transaction_kernel: TransactionKernel,
+            _salted_input_utxos: SaltedUtxos,
+            _salted_output_utxos: SaltedUtxos,
+        ) -> TypeScriptAndWitness {
+            TypeScriptAndWitness::new(self.program())
+        }
+    }
+
+    #[test]
+    fn plugins_are_honored_only_on_regtest() {
+        let plugin = Arc::new(AcceptAll(random()));
+        let type_script_hash = plugin.program().hash();
+        let utxo = Utxo::new(
+            LockScript::anyone_can_spend(),
+            vec![Coin {
+                type_script_hash,
+                state: vec![],
+            }],
+        );
+        assert!(!utxo.has_known_type_scripts());
+
+        assert_eq!(
+            Err(PluginError::WrongNetwork(Network::Main)),
+            register(Network::Main, plugin.clone())
+        );
+        assert!(!is_registered(type_script_hash));
+
+        assert_eq!(
+            Ok(type_script_hash),
+            register(Network::RegTest, plugin.clone())
+        );
+        assert!(utxo.has_known_type_scripts());
+        let type_script_and_witness = match_type_script_and_generate_witness(
+            type_script_hash,
+            random_transaction_kernel(),
+            SaltedUtxos::empty(),
+            SaltedUtxos::empty(),
+        )
+        .unwrap();
+        assert_eq!(plugin.program(), type_script_and_witness.program);
+
+        unregister(type_script_hash);
+        assert!(!utxo.has_known_type_scripts());
+    }
+
+    #[test]
+    fn built_in_type_scripts_cannot_be_replaced() {
+        struct FakeNativeCurrency;
+        impl TypeScriptPlugin for FakeNativeCurrency {
+            fn program(&self) -> Program {
+                NativeCurrency.program()
+            }
+
+            fn type_script_and_witness(
+                &self,
+                _transaction_kernel: TransactionKernel,
+                _salted_input_utxos: SaltedUtxos,
+                _salted_output_utxos: SaltedUtxos,
+            ) -> TypeScriptAndWitness {
+                TypeScriptAndWitness::new(self.program())
+            }
+        }
+
+        assert_eq!(
+            Err(PluginError::BuiltIn(NativeCurrency.hash())),
+            register(Network::RegTest, Arc::new(FakeNativeCurrency))
+        );
+    }
+}
@@ -1,12 +1,19 @@
 use std::net::SocketAddr;
+use std::sync::Arc;
 
 use tasm_lib::triton_vm::prelude::Digest;
+use tokio::sync::oneshot;
 
 use super::blockchain::block::block_height::BlockHeight;
 use super::blockchain::block::difficulty_control::ProofOfWork;
 use super::blockchain::block::Block;
 use super::blockchain::transaction::Transaction;
+use super::peer::own_block::OwnBlockAnnouncement;
 use super::peer::transaction_notification::TransactionNotification;
+use super::state::coinjoin::CoinJoinContribution;
+use super::state::coinjoin::CoinJoinError;
+use super::state::coinjoin::OwnCoinJoinContribution;
+use super::state::mining_log::BlockTemplateInfo;
 use super::state::wallet::expected_utxo::ExpectedUtxo;
 
 #[derive(Clone, Debug)]
@@ -32,6 +39,7 @@ pub enum MainToMiner {
 pub struct NewBlockFound {
     pub block: Box<Block>,
     pub coinbase_utxo_info: Box<ExpectedUtxo>,
+    pub template: BlockTemplateInfo,
 }
 
 #[derive(Clone, Debug)]
@@ -53,19 +61,43 @@ pub struct MainToPeerTaskBatchBlockRequest {
 #[derive(Clone, Debug)]
 pub(crate) enum MainToPeerTask {
     Block(Box<Block>),
+    OwnBlock(Arc<OwnBlockAnnouncement>), // Announce a block found by this node, ahead of other messages
     RequestBlockBatch(MainToPeerTaskBatchBlockRequest),
     PeerSynchronizationTimeout(SocketAddr), // sanction a peer for failing to respond to sync request
     MakePeerDiscoveryRequest,               // Request peer list from connected peers
     MakeSpecificPeerDiscoveryRequest(SocketAddr), // Request peers from a specific peer to get peers further away
     TransactionNotification(TransactionNotification), // Publish knowledge of a transaction
-    Disconnect(SocketAddr),                       // Disconnect from a specific peer
-    DisconnectAll(),                              // Disconnect from all peers
+    TransactionNotificationToPeers(TransactionNotification, Vec<SocketAddr>), // Publish knowledge of a transaction to specific peers only
+    Disconnect(SocketAddr),        // Disconnect from a specific peer
+    DisconnectAll(),               // Disconnect from all peers
+    RequestRendezvous(SocketAddr), // Ask a specific peer for an introduction to a NATed node
+    RendezvousIntroduction {
+        to: SocketAddr,
+        endpoint: SocketAddr,
+        initiator: bool,
+    }, // Introduce a specific peer to the node at `endpoint`
+    RequestReachabilityCheck(Vec<SocketAddr>), // Ask specific peers to connect back to us
+    RequestBlock {
+        block_digest: Digest,
+        except: SocketAddr,
+    }, // Ask all peers but one for a block
+    RequestCoinJoinSessions,       // Ask all peers for the coinjoin sessions they coordinate
+    CoinJoinContribute {
+        coordinator: SocketAddr,
+        contribution: Box<CoinJoinContribution>,
+    }, // Contribute to the coinjoin session of a specific peer
+    CoinJoinStatus {
+        contributors: Vec<SocketAddr>,
+        session_id: u64,
+        result: Result<(), CoinJoinError>,
+    }, // Inform the contributors to a coinjoin session coordinated by this node
 }
 
 impl MainToPeerTask {
     pub fn get_type(&self) -> String {
         match self {
             MainToPeerTask::Block(_) => "block".to_string(),
+            MainToPeerTask::OwnBlock(_) => "own block".to_string(),
             MainToPeerTask::RequestBlockBatch(_) => "req block batch".to_string(),
             MainToPeerTask::PeerSynchronizationTimeout(_) => "peer sync timeout".to_string(),
             MainToPeerTask::MakePeerDiscoveryRequest => "make peer discovery req".to_string(),
@@ -73,8 +105,18 @@ impl MainToPeerTask {
                 "make specific peer discovery req".to_string()
             }
             MainToPeerTask::TransactionNotification(_) => "transaction notification".to_string(),
+            MainToPeerTask::TransactionNotificationToPeers(..) => {
+                "transaction notification to peers".to_string()
+            }
             MainToPeerTask::Disconnect(_) => "disconnect".to_string(),
             MainToPeerTask::DisconnectAll() => "disconnect all".to_string(),
+            MainToPeerTask::RequestRendezvous(_) => "request rendezvous".to_string(),
+            MainToPeerTask::RendezvousIntroduction { .. } => "rendezvous introduction".to_string(),
+            MainToPeerTask::RequestReachabilityCheck(_) => "request reachability check".to_string(),
+            MainToPeerTask::RequestBlock { .. } => "request block".to_string(),
+            MainToPeerTask::RequestCoinJoinSessions => "request coinjoin sessions".to_string(),
+            MainToPeerTask::CoinJoinContribute { .. } => "coinjoin contribute".to_string(),
+            MainToPeerTask::CoinJoinStatus { .. } => "coinjoin status".to_string(),
         }
     }
 }
@@ -86,6 +128,21 @@ pub(crate) enum PeerTaskToMain {
     RemovePeerMaxBlockHeight(SocketAddr),
     PeerDiscoveryAnswer((Vec<(SocketAddr, u128)>, SocketAddr, u8)), // ([(peer_listen_address)], reported_by, distance)
     Transaction(Box<PeerTaskToMainTransaction>),
+    RendezvousRequest(SocketAddr), // requester, as observed by this node
+    RendezvousIntroduction {
+        endpoint: SocketAddr,
+        initiator: bool,
+    },
+    /// This node's contribution to a coinjoin session that failed, to be
+    /// broadcast on its own.
+    CoinJoinFailed(Box<Transaction>),
+
+    /// The parent of an orphan block, already requested from the peer that
+    /// sent the orphan, to be requested from all other peers.
+    OrphanParentMissing {
+        parent_digest: Digest,
+        requested_from: SocketAddr,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -104,25 +161,50 @@ impl PeerTaskToMain {
             }
             PeerTaskToMain::PeerDiscoveryAnswer(_) => "peer discovery answer".to_string(),
             PeerTaskToMain::Transaction(_) => "transaction".to_string(),
+            PeerTaskToMain::RendezvousRequest(_) => "rendezvous request".to_string(),
+            PeerTaskToMain::RendezvousIntroduction { .. } => "rendezvous introduction".to_string(),
+            PeerTaskToMain::CoinJoinFailed(_) => "coinjoin failed".to_string(),
+            PeerTaskToMain::OrphanParentMissing { .. } => "orphan parent missing".to_string(),
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub enum RPCServerToMain {
     BroadcastTx(Box<Transaction>),
+
+    /// A transaction constructed elsewhere, to be broadcast without the
+    /// wallet tracking it
+    BroadcastExternalTx(Box<Transaction>),
+
     Shutdown,
     PauseMiner,
     RestartMiner,
+    CheckReachability(Vec<SocketAddr>),
+    RequestCoinJoinSessions,
+    CoinJoinContribute(Box<OwnCoinJoinContribution>),
+
+    /// An external miner solved a published block template
+    BlockSolutionFound(Box<NewBlockFound>),
+
+    /// A block generated on RegTest, to be stored. Whether it became the tip
+    /// is sent back.
+    GeneratedBlock(Box<Block>, oneshot::Sender<bool>),
 }
 
 impl RPCServerToMain {
     pub fn get_type(&self) -> String {
         match self {
             RPCServerToMain::BroadcastTx(_) => "broadcast transaction".to_string(),
+            RPCServerToMain::BroadcastExternalTx(_) => "broadcast external transaction".to_string(),
             RPCServerToMain::Shutdown => "shutdown".to_string(),
             RPCServerToMain::PauseMiner => "pause miner".to_owned(),
             RPCServerToMain::RestartMiner => "restart miner".to_owned(),
+            RPCServerToMain::CheckReachability(_) => "check reachability".to_owned(),
+            RPCServerToMain::RequestCoinJoinSessions => "request coinjoin sessions".to_owned(),
+            RPCServerToMain::CoinJoinContribute(_) => "coinjoin contribute".to_owned(),
+            RPCServerToMain::BlockSolutionFound(_) => "block solution found".to_owned(),
+            RPCServerToMain::GeneratedBlock(..) => "generated block".to_owned(),
         }
     }
 }
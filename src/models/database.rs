@@ -85,6 +85,7 @@ pub enum BlockIndexKey {
     Height(BlockHeight), // Maps from block height to list of blocks
     LastFile,            // points to last file used
     BlockTipDigest,      // points to block digest of most canonical block known
+    FirstUnprunedFile,   // points to lowest index of a block file not deleted by pruning
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -94,6 +95,7 @@ pub enum BlockIndexValue {
     Height(Vec<Digest>),
     LastFile(LastFileRecord),
     BlockTipDigest(Digest),
+    FirstUnprunedFile(u32),
 }
 
 impl BlockIndexValue {
@@ -131,6 +133,13 @@ impl BlockIndexValue {
             _ => panic!("Requested BlockTipDigest, found {:?}", self),
         }
     }
+
+    pub fn as_first_unpruned_file(&self) -> u32 {
+        match self {
+            BlockIndexValue::FirstUnprunedFile(file_index) => *file_index,
+            _ => panic!("Requested FirstUnprunedFile, found {:?}", self),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -1,14 +1,32 @@
+pub mod anchor_peers;
+pub mod archival_scheduler;
+pub mod compact_block;
+pub mod digest_summary;
+pub mod message_codec;
+pub mod network_group;
+pub mod own_block;
+pub mod rate_limit;
 pub mod transaction_notification;
 pub mod transfer_block;
 pub mod transfer_transaction;
 
 use std::fmt::Display;
 use std::net::SocketAddr;
+use std::time::Duration;
+use std::time::Instant;
 use std::time::SystemTime;
 
+use compact_block::CompactBlock;
+use compact_block::PendingCompactBlock;
+use digest_summary::DigestSummary;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
+use strum::EnumDiscriminants;
+use strum::EnumIter;
+use strum::FromRepr;
 use transaction_notification::TransactionNotification;
+use transfer_transaction::AcceptedTransactionProofs;
 use transfer_transaction::TransferTransaction;
 use twenty_first::math::digest::Digest;
 use twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
@@ -18,10 +36,16 @@ use super::blockchain::block::block_height::BlockHeight;
 use super::blockchain::block::difficulty_control::ProofOfWork;
 use super::blockchain::block::Block;
 use super::blockchain::shared::Hash;
+use super::state::coinjoin::CoinJoinContribution;
+use super::state::coinjoin::CoinJoinError;
+use super::state::coinjoin::CoinJoinSessionInfo;
 use super::state::transaction_kernel_id::TransactionKernelId;
 use crate::config_models::network::Network;
 use crate::models::peer::transfer_block::TransferBlock;
 use crate::prelude::twenty_first;
+use crate::util_types::fault_injection::BlockValidationStep;
+use crate::util_types::json_schema::DigestSchema;
+use crate::util_types::mutator_set::removal_record::RemovalRecord;
 
 const BAD_BLOCK_BATCH_REQUEST_SEVERITY: u16 = 10;
 const INVALID_BLOCK_SEVERITY: u16 = 10;
@@ -34,10 +58,14 @@ const UNKNOWN_BLOCK_HEIGHT: u16 = 1;
 const INVALID_TRANSACTION: u16 = 10;
 const UNCONFIRMABLE_TRANSACTION: u16 = 2;
 const NO_STANDING_FOUND_MAYBE_CRASH: u16 = 10;
+const RATE_LIMIT_EXCEEDED_SEVERITY: u16 = 2;
+
+/// Number of most recent violations kept in a peer's standing.
+pub const MAX_PEER_VIOLATIONS: usize = 100;
 
 pub type InstanceId = u128;
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 pub struct PeerInfo {
     pub port_for_incoming_connections: Option<u16>,
     pub connected_address: SocketAddr,
@@ -47,6 +75,42 @@ pub struct PeerInfo {
     pub standing: PeerStanding,
     pub version: String,
     pub is_archival_node: bool,
+
+    /// Time from when this node found its latest own block until the peer
+    /// first asked for it. `None` if it has not asked for any.
+    pub own_block_latency: Option<Duration>,
+
+    /// Round-trip time of the latest ping the peer answered. `None` until it
+    /// answered one.
+    pub round_trip_time: Option<Duration>,
+}
+
+/// Outcome of asking a peer to connect back to this node's listen address.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct ReachabilityReport {
+    pub peer: SocketAddr,
+
+    /// Address the peer attempted to connect to: this node's IP as observed
+    /// by the peer, with the advertised listen port. `None` if the peer did not
+    /// answer.
+    pub address: Option<SocketAddr>,
+
+    /// `None` if the peer did not answer in time.
+    pub reachable: Option<bool>,
+}
+
+impl Display for ReachabilityReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.address, self.reachable) {
+            (Some(address), Some(true)) => {
+                write!(f, "{}: reached {address}", self.peer)
+            }
+            (Some(address), Some(false)) => {
+                write!(f, "{}: could not reach {address}", self.peer)
+            }
+            _ => write!(f, "{}: no answer", self.peer),
+        }
+    }
 }
 
 impl PeerInfo {
@@ -58,11 +122,13 @@ impl PeerInfo {
     }
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 pub enum PeerSanctionReason {
-    InvalidBlock((BlockHeight, Digest)),
+    InvalidBlock(#[schemars(with = "(BlockHeight, DigestSchema)")] (BlockHeight, Digest)),
     DifferentGenesis,
-    ForkResolutionError((BlockHeight, u16, Digest)),
+    ForkResolutionError(
+        #[schemars(with = "(BlockHeight, u16, DigestSchema)")] (BlockHeight, u16, Digest),
+    ),
     SynchronizationTimeout,
     FloodPeerListResponse,
     BlockRequestUnknownHeight,
@@ -70,6 +136,7 @@ pub enum PeerSanctionReason {
     InvalidMessage,
     NonMinedTransactionHasCoinbase,
     TooShortBlockBatch,
+    TooLongBlockBatch,
     ReceivedBatchBlocksOutsideOfSync,
     BatchBlocksInvalidStartHeight,
     BatchBlocksUnknownRequest,
@@ -77,6 +144,10 @@ pub enum PeerSanctionReason {
     UnconfirmableTransaction,
 
     NoStandingFoundMaybeCrash,
+
+    BlockRequestFlood,
+    TransactionFlood,
+    HandshakeFlood,
 }
 
 impl Display for PeerSanctionReason {
@@ -90,6 +161,7 @@ impl Display for PeerSanctionReason {
             PeerSanctionReason::BlockRequestUnknownHeight => "block request unknown height",
             PeerSanctionReason::InvalidMessage => "invalid message",
             PeerSanctionReason::TooShortBlockBatch => "too short block batch",
+            PeerSanctionReason::TooLongBlockBatch => "too long block batch",
             PeerSanctionReason::ReceivedBatchBlocksOutsideOfSync => {
                 "received block batch outside of sync"
             }
@@ -105,6 +177,9 @@ impl Display for PeerSanctionReason {
             PeerSanctionReason::NoStandingFoundMaybeCrash => {
                 "No standing found in map. Did peer task crash?"
             }
+            PeerSanctionReason::BlockRequestFlood => "block request flood",
+            PeerSanctionReason::TransactionFlood => "transaction flood",
+            PeerSanctionReason::HandshakeFlood => "handshake flood",
         };
         write!(f, "{string}")
     }
@@ -144,6 +219,7 @@ impl PeerSanctionReason {
             PeerSanctionReason::FloodPeerListResponse => FLOODED_PEER_LIST_RESPONSE_SEVERITY,
             PeerSanctionReason::InvalidMessage => INVALID_MESSAGE_SEVERITY,
             PeerSanctionReason::TooShortBlockBatch => INVALID_MESSAGE_SEVERITY,
+            PeerSanctionReason::TooLongBlockBatch => INVALID_MESSAGE_SEVERITY,
             PeerSanctionReason::ReceivedBatchBlocksOutsideOfSync => INVALID_MESSAGE_SEVERITY,
             PeerSanctionReason::BatchBlocksInvalidStartHeight => INVALID_MESSAGE_SEVERITY,
             PeerSanctionReason::BatchBlocksUnknownRequest => BAD_BLOCK_BATCH_REQUEST_SEVERITY,
@@ -152,25 +228,86 @@ impl PeerSanctionReason {
             PeerSanctionReason::UnconfirmableTransaction => UNCONFIRMABLE_TRANSACTION,
             PeerSanctionReason::NonMinedTransactionHasCoinbase => INVALID_TRANSACTION,
             PeerSanctionReason::NoStandingFoundMaybeCrash => NO_STANDING_FOUND_MAYBE_CRASH,
+            PeerSanctionReason::BlockRequestFlood => RATE_LIMIT_EXCEEDED_SEVERITY,
+            PeerSanctionReason::TransactionFlood => RATE_LIMIT_EXCEEDED_SEVERITY,
+            PeerSanctionReason::HandshakeFlood => RATE_LIMIT_EXCEEDED_SEVERITY,
+        }
+    }
+}
+
+/// A sanctioned violation, with the evidence for it, kept for operators to
+/// find out why a peer was sanctioned.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+pub struct PeerViolation {
+    pub reason: PeerSanctionReason,
+    pub timestamp: SystemTime,
+
+    /// The consensus rule broken by an invalid block. `None` for blocks with
+    /// insufficient proof-of-work, and for other violations.
+    pub failed_rule: Option<BlockValidationStep>,
+
+    /// Digests of the offending block and its predecessor, or of whatever
+    /// else the violation concerns
+    #[schemars(with = "Vec<DigestSchema>")]
+    pub digests: Vec<Digest>,
+}
+
+impl PeerViolation {
+    pub fn new(reason: PeerSanctionReason) -> Self {
+        Self {
+            reason,
+            timestamp: SystemTime::now(),
+            failed_rule: None,
+            digests: vec![],
+        }
+    }
+
+    /// A block that is invalid, or lacks proof-of-work if `failed_rule` is
+    /// `None`.
+    pub fn invalid_block(block: &Block, failed_rule: Option<BlockValidationStep>) -> Self {
+        Self {
+            failed_rule,
+            digests: vec![block.hash(), block.header().prev_block_digest],
+            ..Self::new(PeerSanctionReason::InvalidBlock((
+                block.header().height,
+                block.hash(),
+            )))
         }
     }
 }
 
 /// This is object that gets stored in the database to record how well a peer
 /// at a certain IP behaves. A lower number is better.
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Default, JsonSchema)]
 pub struct PeerStanding {
     pub standing: i32,
     pub latest_sanction: Option<PeerSanctionReason>,
     pub timestamp_of_latest_sanction: Option<SystemTime>,
+
+    /// The most recent violations, oldest first, at most
+    /// [`MAX_PEER_VIOLATIONS`] many
+    pub violations: Vec<PeerViolation>,
 }
 
 impl PeerStanding {
     /// Sanction peer and return latest standing score
     pub fn sanction(&mut self, reason: PeerSanctionReason) -> i32 {
-        self.standing = self.standing.saturating_sub(reason.to_severity().into());
-        self.latest_sanction = Some(reason);
-        self.timestamp_of_latest_sanction = Some(SystemTime::now());
+        self.record_violation(PeerViolation::new(reason))
+    }
+
+    /// Sanction peer for a violation, keeping its evidence, and return latest
+    /// standing score
+    pub fn record_violation(&mut self, violation: PeerViolation) -> i32 {
+        self.standing = self
+            .standing
+            .saturating_sub(violation.reason.to_severity().into());
+        self.latest_sanction = Some(violation.reason);
+        self.timestamp_of_latest_sanction = Some(violation.timestamp);
+
+        self.violations.push(violation);
+        let excess = self.violations.len().saturating_sub(MAX_PEER_VIOLATIONS);
+        self.violations.drain(..excess);
+
         self.standing
     }
 
@@ -184,11 +321,9 @@ impl PeerStanding {
     }
 
     pub fn new_on_no_standing_found_in_map() -> Self {
-        Self {
-            standing: -(NO_STANDING_FOUND_MAYBE_CRASH as i32),
-            latest_sanction: Some(PeerSanctionReason::NoStandingFoundMaybeCrash),
-            timestamp_of_latest_sanction: Some(SystemTime::now()),
-        }
+        let mut standing = Self::default();
+        standing.sanction(PeerSanctionReason::NoStandingFoundMaybeCrash);
+        standing
     }
 }
 
@@ -200,6 +335,17 @@ pub struct HandshakeData {
     pub instance_id: u128,
     pub version: String,
     pub is_archival_node: bool,
+
+    /// Address under which the sender's router forwards its listen port, if
+    /// the sender mapped the port through UPnP or NAT-PMP
+    pub external_address: Option<SocketAddr>,
+
+    /// Kinds of transaction proofs the sender wants to receive
+    pub accepted_tx_proofs: AcceptedTransactionProofs,
+
+    /// Whether the sender understands [`PeerMessage::Ping`] and
+    /// [`PeerMessage::Pong`]. Only peers that do are pinged.
+    pub supports_ping: bool,
 }
 
 /// Used to tell peers that a new block has been found without having to
@@ -263,11 +409,17 @@ pub struct BlockRequestBatch {
     /// canonical chain.
     pub(crate) known_blocks: Vec<Digest>,
 
-    /// Indicates the maximum allowed number of blocks in the response.
+    /// Indicates the maximum allowed number of blocks in the response. The
+    /// responding peer never sends more than `MAXIMUM_BLOCK_BATCH_SIZE`
+    /// blocks, regardless of this value.
     pub(crate) max_response_len: usize,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+/// Messages exchanged with peers. The type of a message, [`PeerMessageType`],
+/// determines its size limit, see
+/// [`MessageSizeLimits`](message_codec::MessageSizeLimits).
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, EnumDiscriminants)]
+#[strum_discriminants(name(PeerMessageType), derive(Hash, FromRepr, EnumIter))]
 pub(crate) enum PeerMessage {
     Handshake(Box<(Vec<u8>, HandshakeData)>),
     Block(Box<TransferBlock>),
@@ -276,9 +428,14 @@ pub(crate) enum PeerMessage {
     BlockRequestByHeight(BlockHeight),
     BlockRequestByHash(Digest),
 
-    /// A list of block digests containing the
-    BlockRequestBatch(BlockRequestBatch), // TODO: Consider restricting this in size
-    BlockResponseBatch(Vec<TransferBlock>), // TODO: Consider restricting this in size
+    /// Request a batch of canonical blocks, descending from the first known
+    /// block in the list.
+    BlockRequestBatch(BlockRequestBatch),
+
+    /// At most `MAXIMUM_BLOCK_BATCH_SIZE` consecutive blocks. Longer responses
+    /// are punished.
+    BlockResponseBatch(Vec<TransferBlock>),
+
     /// Send a full transaction object to a peer.
     Transaction(Box<TransferTransaction>),
     /// Send a notification to a peer, informing it that this node stores the
@@ -294,6 +451,55 @@ pub(crate) enum PeerMessage {
     /// Inform peer that we are disconnecting them.
     Bye,
     ConnectionStatus(ConnectionStatus),
+    /// Ask a public peer to introduce this node to another node that cannot
+    /// accept incoming connections, for hole punching.
+    RendezvousRequest,
+    /// Endpoint of a node to punch a hole to, as observed by the rendezvous
+    /// peer. Of the two introduced nodes, exactly one is the initiator, which
+    /// performs the handshake as the calling side.
+    RendezvousIntroduction {
+        endpoint: SocketAddr,
+        initiator: bool,
+    },
+    /// Ask a peer to attempt a connection to this node's listen address.
+    ReachabilityRequest,
+    /// Outcome of a connection attempt to the address of the node asking.
+    ReachabilityResponse {
+        address: Option<SocketAddr>,
+        reachable: bool,
+    },
+    /// Summary of the recent blocks and mempool transactions the sender
+    /// knows of, asking the receiver to send those the sender is missing.
+    DigestSummary(DigestSummary),
+    /// Ask for the block with this digest as a compact block.
+    CompactBlockRequest(Digest),
+    CompactBlock(Box<CompactBlock>),
+    /// Ask for the removal records at `indices` of a compact block's
+    /// transaction, that were not found in the mempool.
+    GetBlockTxn {
+        block_digest: Digest,
+        indices: Vec<usize>,
+    },
+    BlockTxn {
+        block_digest: Digest,
+        removal_records: Vec<RemovalRecord>,
+    },
+    /// Ask a peer for the coinjoin session it coordinates, if any.
+    CoinJoinSessionRequest,
+    CoinJoinSession(Option<CoinJoinSessionInfo>),
+    /// Contribute a transaction to a coinjoin session coordinated by the
+    /// receiver.
+    CoinJoinContribution(Box<CoinJoinContribution>),
+    /// Whether a contribution was accepted, or why it or the session failed.
+    /// Sent by the coordinator in response to a contribution, and again to all
+    /// contributors of a session that fails.
+    CoinJoinStatus {
+        session_id: u64,
+        result: Result<(), CoinJoinError>,
+    },
+    /// Ask the peer to answer with a `Pong` carrying the same nonce.
+    Ping(u64),
+    Pong(u64),
 }
 
 impl PeerMessage {
@@ -314,6 +520,21 @@ impl PeerMessage {
             PeerMessage::PeerListResponse(_) => "peer list resp".to_string(),
             PeerMessage::Bye => "bye".to_string(),
             PeerMessage::ConnectionStatus(_) => "connection status".to_string(),
+            PeerMessage::RendezvousRequest => "rendezvous request".to_string(),
+            PeerMessage::RendezvousIntroduction { .. } => "rendezvous introduction".to_string(),
+            PeerMessage::ReachabilityRequest => "reachability request".to_string(),
+            PeerMessage::ReachabilityResponse { .. } => "reachability response".to_string(),
+            PeerMessage::DigestSummary(_) => "digest summary".to_string(),
+            PeerMessage::CompactBlockRequest(_) => "compact block request".to_string(),
+            PeerMessage::CompactBlock(_) => "compact block".to_string(),
+            PeerMessage::GetBlockTxn { .. } => "get block txn".to_string(),
+            PeerMessage::BlockTxn { .. } => "block txn".to_string(),
+            PeerMessage::CoinJoinSessionRequest => "coinjoin session request".to_string(),
+            PeerMessage::CoinJoinSession(_) => "coinjoin session".to_string(),
+            PeerMessage::CoinJoinContribution(_) => "coinjoin contribution".to_string(),
+            PeerMessage::CoinJoinStatus { .. } => "coinjoin status".to_string(),
+            PeerMessage::Ping(_) => "ping".to_string(),
+            PeerMessage::Pong(_) => "pong".to_string(),
         }
     }
 
@@ -334,6 +555,21 @@ impl PeerMessage {
             PeerMessage::PeerListResponse(_) => false,
             PeerMessage::Bye => false,
             PeerMessage::ConnectionStatus(_) => false,
+            PeerMessage::RendezvousRequest => false,
+            PeerMessage::RendezvousIntroduction { .. } => false,
+            PeerMessage::ReachabilityRequest => false,
+            PeerMessage::ReachabilityResponse { .. } => false,
+            PeerMessage::DigestSummary(_) => false,
+            PeerMessage::CompactBlockRequest(_) => false,
+            PeerMessage::CompactBlock(_) => false,
+            PeerMessage::GetBlockTxn { .. } => false,
+            PeerMessage::BlockTxn { .. } => false,
+            PeerMessage::CoinJoinSessionRequest => false,
+            PeerMessage::CoinJoinSession(_) => false,
+            PeerMessage::CoinJoinContribution(_) => false,
+            PeerMessage::CoinJoinStatus { .. } => false,
+            PeerMessage::Ping(_) => false,
+            PeerMessage::Pong(_) => false,
         }
     }
 
@@ -355,6 +591,21 @@ impl PeerMessage {
             PeerMessage::PeerListResponse(_) => false,
             PeerMessage::Bye => false,
             PeerMessage::ConnectionStatus(_) => false,
+            PeerMessage::RendezvousRequest => false,
+            PeerMessage::RendezvousIntroduction { .. } => false,
+            PeerMessage::ReachabilityRequest => false,
+            PeerMessage::ReachabilityResponse { .. } => false,
+            PeerMessage::DigestSummary(_) => true,
+            PeerMessage::CompactBlockRequest(_) => false,
+            PeerMessage::CompactBlock(_) => true,
+            PeerMessage::GetBlockTxn { .. } => false,
+            PeerMessage::BlockTxn { .. } => true,
+            PeerMessage::CoinJoinSessionRequest => false,
+            PeerMessage::CoinJoinSession(_) => false,
+            PeerMessage::CoinJoinContribution(_) => true,
+            PeerMessage::CoinJoinStatus { .. } => false,
+            PeerMessage::Ping(_) => false,
+            PeerMessage::Pong(_) => false,
         }
     }
 }
@@ -364,6 +615,31 @@ impl PeerMessage {
 pub struct MutablePeerState {
     pub highest_shared_block_height: BlockHeight,
     pub fork_reconciliation_blocks: Vec<Block>,
+
+    /// Whether this node asked the peer for a reachability check, and awaits
+    /// the answer.
+    pub reachability_check_requested: bool,
+
+    /// When the peer last asked this node for a reachability check.
+    pub last_reachability_check: Option<SystemTime>,
+
+    /// Compact block received from the peer, awaiting the removal records
+    /// requested with `GetBlockTxn`.
+    pub pending_compact_block: Option<PendingCompactBlock>,
+
+    /// The latest own block the peer asked for, if any
+    pub own_block_requested: Option<Digest>,
+
+    /// When the peer last sent a message
+    pub last_message_received: Instant,
+
+    /// Nonce of the latest ping sent to the peer and not answered yet, and
+    /// when it was sent
+    pub pending_ping: Option<(u64, Instant)>,
+
+    /// When this node asked the peer for a rendezvous, if it awaits the
+    /// introduction
+    pub pending_rendezvous_request: Option<Instant>,
 }
 
 impl MutablePeerState {
@@ -371,6 +647,13 @@ impl MutablePeerState {
         Self {
             highest_shared_block_height: block_height,
             fork_reconciliation_blocks: vec![],
+            reachability_check_requested: false,
+            last_reachability_check: None,
+            pending_compact_block: None,
+            own_block_requested: None,
+            last_message_received: Instant::now(),
+            pending_ping: None,
+            pending_rendezvous_request: None,
         }
     }
 }
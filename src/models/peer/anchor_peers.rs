@@ -0,0 +1,111 @@
+//! Anchor peers: outbound peers that were connected at shutdown and that are
+//! connected to first at the next startup. An attacker trying to eclipse the
+//! node by filling its peer list on restart must then also displace
+//! connections that were known to be good.
+
+use std::net::SocketAddr;
+use std::path::Path;
+
+use anyhow::Context;
+use anyhow::Result;
+use itertools::Itertools;
+
+use super::network_group::NetworkGroup;
+use super::PeerInfo;
+
+pub const ANCHOR_PEERS_FILE_NAME: &str = "anchor_peers.json";
+
+/// Maximum number of anchor peers.
+pub const MAX_ANCHOR_PEERS: usize = 2;
+
+/// Read and delete the anchor peers file. The file is deleted so that a node
+/// that keeps crashing does not keep reconnecting to the same, possibly
+/// malicious, anchors.
+pub fn take(data_dir: &Path) -> Result<Vec<SocketAddr>> {
+    let path = data_dir.join(ANCHOR_PEERS_FILE_NAME);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let anchors = std::fs::read_to_string(&path)
+        .with_context(|| format!("could not read {}", path.display()))?;
+    std::fs::remove_file(&path)?;
+    let anchors: Vec<SocketAddr> = serde_json::from_str(&anchors)?;
+    Ok(anchors.into_iter().take(MAX_ANCHOR_PEERS).collect())
+}
+
+/// Persist the anchor peers chosen among the connected peers.
+pub fn store(data_dir: &Path, connected_peers: &[PeerInfo]) -> Result<()> {
+    let anchors = select(connected_peers);
+    let path = data_dir.join(ANCHOR_PEERS_FILE_NAME);
+    std::fs::write(&path, serde_json::to_string(&anchors)?)
+        .with_context(|| format!("could not write {}", path.display()))
+}
+
+/// Choose anchors among connected peers: outbound peers accepting incoming
+/// connections, in distinct network groups, best standing first.
+fn select(connected_peers: &[PeerInfo]) -> Vec<SocketAddr> {
+    connected_peers
+        .iter()
+        .filter(|peer| !peer.inbound)
+        .filter_map(|peer| peer.listen_address().map(|address| (peer, address)))
+        .sorted_by_key(|(peer, _)| -peer.standing.standing)
+        .unique_by(|(_, address)| match NetworkGroup::of(address.ip()) {
+            NetworkGroup::Local => Err(*address),
+            group => Ok(group),
+        })
+        .map(|(_, address)| address)
+        .take(MAX_ANCHOR_PEERS)
+        .collect()
+}
+
+#[cfg(test)]
+mod anchor_peers_tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::tests::shared::get_dummy_peer;
+
+    fn outbound_peer(address: &str, standing: i32) -> PeerInfo {
+        let address = SocketAddr::from_str(address).unwrap();
+        let mut peer = get_dummy_peer(address);
+        peer.inbound = false;
+        peer.port_for_incoming_connections = Some(address.port());
+        peer.standing.standing = standing;
+        peer
+    }
+
+    #[test]
+    fn anchors_are_outbound_peers_in_distinct_groups() {
+        let mut inbound = outbound_peer("1.1.1.1:9798", 100);
+        inbound.inbound = true;
+        let peers = vec![
+            inbound,
+            outbound_peer("2.2.1.1:9798", 1),
+            outbound_peer("2.2.2.2:9798", 5),
+            outbound_peer("3.3.3.3:9798", 0),
+        ];
+
+        assert_eq!(
+            vec![
+                SocketAddr::from_str("2.2.2.2:9798").unwrap(),
+                SocketAddr::from_str("3.3.3.3:9798").unwrap()
+            ],
+            select(&peers)
+        );
+    }
+
+    #[test]
+    fn anchors_are_read_once() {
+        let data_dir = std::env::temp_dir().join(format!("anchors-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&data_dir).unwrap();
+        let peers = vec![outbound_peer("4.4.4.4:9798", 0)];
+
+        store(&data_dir, &peers).unwrap();
+        assert_eq!(
+            vec![SocketAddr::from_str("4.4.4.4:9798").unwrap()],
+            take(&data_dir).unwrap()
+        );
+        assert!(take(&data_dir).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,226 @@
+//! Fair sharing of the archival node's disk and CPU between peers.
+//!
+//! Serving blocks out of the archival state is expensive: every block is read
+//! from disk and deserialized. The scheduler bounds how many such requests
+//! are served concurrently across all peers, and hands out free slots to the
+//! waiting peer with the fewest requests in progress, such that one peer
+//! issuing requests back to back cannot starve the others. The number of
+//! blocks each peer may be served per minute is limited separately, by its
+//! [`PeerRateLimiter`](super::rate_limit::PeerRateLimiter).
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use tokio::sync::Notify;
+
+#[derive(Debug, Default)]
+struct Slots {
+    /// Requests in progress, per peer
+    active: HashMap<SocketAddr, usize>,
+
+    /// Requests waiting for a slot, per peer
+    waiting: HashMap<SocketAddr, usize>,
+}
+
+impl Slots {
+    fn total_active(&self) -> usize {
+        self.active.values().sum()
+    }
+
+    fn active_for(&self, peer: SocketAddr) -> usize {
+        self.active.get(&peer).copied().unwrap_or_default()
+    }
+
+    /// A slot is granted to `peer` if one is free and no other waiting peer
+    /// has fewer requests in progress.
+    fn may_grant(&self, peer: SocketAddr, capacity: usize) -> bool {
+        if capacity != 0 && self.total_active() >= capacity {
+            return false;
+        }
+
+        let own_active = self.active_for(peer);
+        self.waiting
+            .keys()
+            .filter(|&&other| other != peer)
+            .all(|&other| self.active_for(other) >= own_active)
+    }
+
+    fn add(map: &mut HashMap<SocketAddr, usize>, peer: SocketAddr) {
+        *map.entry(peer).or_default() += 1;
+    }
+
+    fn remove(map: &mut HashMap<SocketAddr, usize>, peer: SocketAddr) {
+        if let Some(count) = map.get_mut(&peer) {
+            *count -= 1;
+            if *count == 0 {
+                map.remove(&peer);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    slots: Mutex<Slots>,
+    released: Notify,
+}
+
+/// Bounds the number of concurrently served archival requests, and shares
+/// them fairly between peers. Cheap to clone; all clones share state.
+#[derive(Debug, Clone, Default)]
+pub struct ArchivalScheduler {
+    shared: Arc<Shared>,
+}
+
+/// The right to serve one archival request. The slot is freed on drop.
+#[derive(Debug)]
+pub struct ArchivalPermit {
+    shared: Arc<Shared>,
+    peer: SocketAddr,
+}
+
+impl Drop for ArchivalPermit {
+    fn drop(&mut self) {
+        Slots::remove(&mut self.shared.slots.lock().unwrap().active, self.peer);
+        self.shared.released.notify_waiters();
+    }
+}
+
+/// Registration of a waiting request, withdrawn on drop such that a request
+/// that is cancelled while waiting does not hold back other peers.
+struct Waiting<'a> {
+    shared: &'a Shared,
+    peer: SocketAddr,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        Slots::remove(&mut self.shared.slots.lock().unwrap().waiting, self.peer);
+        self.shared.released.notify_waiters();
+    }
+}
+
+impl ArchivalScheduler {
+    /// Wait for a slot to serve an archival request of `peer`, with at most
+    /// `capacity` requests served concurrently across all peers, or any
+    /// number if `capacity` is zero.
+    ///
+    /// Must not be called while holding the global state lock.
+    pub async fn acquire(&self, peer: SocketAddr, capacity: usize) -> ArchivalPermit {
+        Slots::add(&mut self.shared.slots.lock().unwrap().waiting, peer);
+        let _waiting = Waiting {
+            shared: &self.shared,
+            peer,
+        };
+
+        loop {
+            // Register for wake-ups before checking, such that a slot freed
+            // between the check and the wait is not missed.
+            let released = self.shared.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            {
+                let mut slots = self.shared.slots.lock().unwrap();
+                if slots.may_grant(peer, capacity) {
+                    Slots::add(&mut slots.active, peer);
+                    return ArchivalPermit {
+                        shared: self.shared.clone(),
+                        peer,
+                    };
+                }
+            }
+
+            released.await;
+        }
+    }
+
+    /// Number of archival requests currently being served.
+    pub fn active_requests(&self) -> usize {
+        self.shared.slots.lock().unwrap().total_active()
+    }
+}
+
+#[cfg(test)]
+mod archival_scheduler_tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use super::*;
+
+    fn peer(i: u8) -> SocketAddr {
+        SocketAddr::from(([10, 0, 0, i], 9798))
+    }
+
+    #[tokio::test]
+    async fn capacity_is_shared_fairly_between_peers() {
+        let scheduler = ArchivalScheduler::default();
+        let greedy = peer(1);
+        let modest = peer(2);
+
+        let first = scheduler.acquire(greedy, 2).await;
+        let second = scheduler.acquire(greedy, 2).await;
+        assert_eq!(2, scheduler.active_requests());
+
+        // Both peers wait for a slot; the greedy peer asked first.
+        let greedy_waits = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.acquire(greedy, 2).await }
+        });
+        tokio::task::yield_now().await;
+        let modest_waits = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.acquire(modest, 2).await }
+        });
+        tokio::task::yield_now().await;
+
+        // The freed slot goes to the peer with fewer requests in progress.
+        drop(first);
+        let modest_permit = timeout(Duration::from_secs(1), modest_waits)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!greedy_waits.is_finished());
+
+        drop(second);
+        let greedy_permit = timeout(Duration::from_secs(1), greedy_waits)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(2, scheduler.active_requests());
+
+        drop(modest_permit);
+        drop(greedy_permit);
+        assert_eq!(0, scheduler.active_requests());
+    }
+
+    #[tokio::test]
+    async fn zero_capacity_is_unlimited() {
+        let scheduler = ArchivalScheduler::default();
+        let mut permits = vec![];
+        for _ in 0..100 {
+            permits.push(scheduler.acquire(peer(1), 0).await);
+        }
+        assert_eq!(100, scheduler.active_requests());
+    }
+
+    #[tokio::test]
+    async fn cancelled_waiter_does_not_block_others() {
+        let scheduler = ArchivalScheduler::default();
+        let held = scheduler.acquire(peer(1), 1).await;
+
+        // peer 2 has no request in progress, so while waiting it takes
+        // precedence over peer 1 ...
+        let cancelled = timeout(Duration::from_millis(10), scheduler.acquire(peer(2), 1)).await;
+        assert!(cancelled.is_err());
+
+        // ... but once it gave up, peer 1 may be served again.
+        drop(held);
+        timeout(Duration::from_secs(1), scheduler.acquire(peer(1), 1))
+            .await
+            .unwrap();
+    }
+}
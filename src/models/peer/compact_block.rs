@@ -0,0 +1,185 @@
+//! Compact blocks, for relaying a block without the removal records that the
+//! receiving peer already knows.
+//!
+//! A block contains one transaction, the merger of transactions that were
+//! broadcast before the block was mined, and its removal records make up most
+//! of the block's size. A compact block replaces them by short IDs, which the
+//! receiver looks up among the removal records of its mempool transactions.
+//! Missing removal records are requested with `PeerMessage::GetBlockTxn`.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use twenty_first::math::digest::Digest;
+
+use super::transfer_block::TransferBlock;
+use crate::models::blockchain::block::Block;
+use crate::models::blockchain::shared::Hash;
+use crate::prelude::twenty_first;
+use crate::prelude::twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
+use crate::util_types::mutator_set::removal_record::RemovalRecord;
+
+/// Identifies a removal record within a block. Salted with the block digest,
+/// such that collisions cannot be prepared before the block is mined.
+pub(crate) type ShortId = u64;
+
+pub(crate) fn short_id(block_digest: Digest, removal_record: &RemovalRecord) -> ShortId {
+    Hash::hash_pair(block_digest, Hash::hash(removal_record)).values()[0].value()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactBlock {
+    /// Digest of the full block
+    pub(crate) digest: Digest,
+
+    /// The block, without the removal records of its transaction
+    pub(crate) stripped_block: TransferBlock,
+
+    /// Short IDs of the removal records, in the order of the block
+    pub(crate) input_short_ids: Vec<ShortId>,
+}
+
+impl CompactBlock {
+    pub(crate) fn new(block: &Block) -> Result<Self> {
+        let digest = block.hash();
+        let mut stripped_block = TransferBlock::try_from(block)?;
+        let inputs = std::mem::take(&mut stripped_block.body.transaction_kernel.inputs);
+        let input_short_ids = inputs.iter().map(|rr| short_id(digest, rr)).collect();
+
+        Ok(Self {
+            digest,
+            stripped_block,
+            input_short_ids,
+        })
+    }
+}
+
+/// A compact block whose removal records are being collected.
+#[derive(Clone, Debug)]
+pub struct PendingCompactBlock {
+    compact_block: CompactBlock,
+    inputs: Vec<Option<RemovalRecord>>,
+}
+
+impl PendingCompactBlock {
+    /// Fill in the removal records among `known` that the block refers to.
+    pub(crate) fn new(
+        compact_block: CompactBlock,
+        known: impl IntoIterator<Item = RemovalRecord>,
+    ) -> Self {
+        let known_by_short_id: HashMap<_, _> = known
+            .into_iter()
+            .map(|rr| (short_id(compact_block.digest, &rr), rr))
+            .collect();
+        let inputs = compact_block
+            .input_short_ids
+            .iter()
+            .map(|id| known_by_short_id.get(id).cloned())
+            .collect();
+
+        Self {
+            compact_block,
+            inputs,
+        }
+    }
+
+    pub(crate) fn digest(&self) -> Digest {
+        self.compact_block.digest
+    }
+
+    /// Indices of the removal records still missing.
+    pub(crate) fn missing(&self) -> Vec<usize> {
+        self.inputs
+            .iter()
+            .enumerate()
+            .filter_map(|(i, input)| input.is_none().then_some(i))
+            .collect()
+    }
+
+    /// Fill in the removal records at `indices`. Returns false, and changes
+    /// nothing, if they do not match the short IDs of the block.
+    pub(crate) fn fill(&mut self, indices: &[usize], removal_records: Vec<RemovalRecord>) -> bool {
+        let matches = indices.len() == removal_records.len()
+            && indices.iter().zip(&removal_records).all(|(&i, rr)| {
+                self.compact_block
+                    .input_short_ids
+                    .get(i)
+                    .is_some_and(|&id| id == short_id(self.digest(), rr))
+            });
+        if !matches {
+            return false;
+        }
+
+        for (&i, rr) in indices.iter().zip(removal_records) {
+            self.inputs[i] = Some(rr);
+        }
+        true
+    }
+
+    /// The full block, if all removal records are known and the block has
+    /// the announced digest. A mismatch means that a short ID collided, and
+    /// the full block must be requested instead.
+    pub(crate) fn into_block(self) -> Option<TransferBlock> {
+        let inputs = self.inputs.into_iter().collect::<Option<Vec<_>>>()?;
+        let mut block = self.compact_block.stripped_block;
+        block.body.transaction_kernel.inputs = inputs;
+
+        (Block::from(block.clone()).hash() == self.compact_block.digest).then_some(block)
+    }
+}
+
+#[cfg(test)]
+mod compact_block_tests {
+    use rand::random;
+    use tasm_lib::triton_vm::proof::Proof;
+
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::util_types::test_shared::mutator_set::random_removal_record;
+
+    fn compact_block_with_inputs(inputs: &[RemovalRecord]) -> CompactBlock {
+        let genesis = Block::genesis_block(Network::Main);
+        let digest: Digest = random();
+        CompactBlock {
+            digest,
+            stripped_block: TransferBlock {
+                header: genesis.kernel.header.clone(),
+                body: genesis.kernel.body.clone(),
+                appendix: genesis.kernel.appendix.clone(),
+                proof: Proof(vec![]),
+            },
+            input_short_ids: inputs.iter().map(|rr| short_id(digest, rr)).collect(),
+        }
+    }
+
+    #[test]
+    fn missing_inputs_are_found_and_filled() {
+        let inputs = (0..4).map(|_| random_removal_record()).collect::<Vec<_>>();
+        let compact_block = compact_block_with_inputs(&inputs);
+
+        let known = vec![
+            inputs[0].clone(),
+            inputs[2].clone(),
+            random_removal_record(),
+        ];
+        let mut pending = PendingCompactBlock::new(compact_block, known);
+        assert_eq!(vec![1, 3], pending.missing());
+
+        assert!(!pending.fill(&[1, 3], vec![inputs[3].clone(), inputs[1].clone()]));
+        assert!(!pending.fill(&[1, 3], vec![inputs[1].clone()]));
+        assert_eq!(vec![1, 3], pending.missing());
+
+        assert!(pending.fill(&[1, 3], vec![inputs[1].clone(), inputs[3].clone()]));
+        assert!(pending.missing().is_empty());
+    }
+
+    #[test]
+    fn block_with_wrong_digest_is_not_reconstructed() {
+        let compact_block = compact_block_with_inputs(&[]);
+        assert!(PendingCompactBlock::new(compact_block, vec![])
+            .into_block()
+            .is_none());
+    }
+}
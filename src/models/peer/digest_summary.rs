@@ -0,0 +1,159 @@
+//! Compact summary of the recent blocks and mempool transactions a node knows
+//! of, for detecting what a peer is missing after a partition.
+//!
+//! On connecting, a node sends a summary holding bloom filters of the digests
+//! of its most recent canonical blocks and of the ids of the transactions in
+//! its mempool. The counterparty checks its own recent blocks and mempool
+//! transactions against the filters, and sends those the node is missing:
+//! blocks directly, oldest first, and transactions as notifications. A false
+//! positive only means an item is not sent; the node then picks it up through
+//! regular relay or synchronization.
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::models::state::transaction_kernel_id::TransactionKernelId;
+use crate::tasm_lib::Digest;
+
+/// Number of most recent canonical blocks, including the tip, summarized.
+pub(crate) const DIGEST_SUMMARY_BLOCK_WINDOW: usize = 32;
+
+/// Maximum number of mempool transactions summarized.
+pub(crate) const DIGEST_SUMMARY_MAX_TRANSACTIONS: usize = 4096;
+
+/// Maximum number of blocks sent in response to a summary. A peer missing
+/// more blocks is brought up to date by synchronization.
+pub(crate) const DIGEST_SUMMARY_MAX_BACKFILL_BLOCKS: usize = 8;
+
+/// Bits per item, giving a false-positive rate of about 1 %.
+const BITS_PER_ITEM: usize = 10;
+const NUM_HASHES: u32 = 7;
+
+/// Upper bound on the size of a received filter, larger than any filter an
+/// honest node builds.
+const MAX_FILTER_BITS: usize = 1 << 20;
+
+/// Bloom filter over digests.
+///
+/// Digests are uniformly distributed, so the bit indices of an item are
+/// derived from its elements by double hashing, without hashing again.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub(crate) struct DigestFilter {
+    bits: Vec<u64>,
+    num_hashes: u32,
+}
+
+impl DigestFilter {
+    pub(crate) fn new(digests: &[Digest]) -> Self {
+        let num_bits = (digests.len() * BITS_PER_ITEM).max(u64::BITS as usize);
+        let mut filter = Self {
+            bits: vec![0; num_bits.div_ceil(u64::BITS as usize)],
+            num_hashes: NUM_HASHES,
+        };
+        for digest in digests {
+            for index in filter.bit_indices(digest) {
+                filter.bits[index / 64] |= 1 << (index % 64);
+            }
+        }
+
+        filter
+    }
+
+    fn bit_indices(&self, digest: &Digest) -> impl Iterator<Item = usize> {
+        let num_bits = (self.bits.len() * 64) as u64;
+        let values = digest.values();
+        let h1 = values[0].value();
+        let h2 = values[1].value();
+        (0..u64::from(self.num_hashes))
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % num_bits) as usize)
+    }
+
+    /// Whether the digest may be in the set. `false` is definite.
+    pub(crate) fn contains(&self, digest: &Digest) -> bool {
+        self.bit_indices(digest)
+            .all(|index| self.bits[index / 64] & (1 << (index % 64)) != 0)
+    }
+
+    fn is_well_formed(&self) -> bool {
+        !self.bits.is_empty()
+            && self.bits.len() * 64 <= MAX_FILTER_BITS
+            && (1..=2 * NUM_HASHES).contains(&self.num_hashes)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub(crate) struct DigestSummary {
+    /// Digest of the sender's tip
+    pub(crate) tip: Digest,
+
+    /// Digests of the sender's most recent canonical blocks
+    pub(crate) blocks: DigestFilter,
+
+    /// Ids of the transactions in the sender's mempool
+    pub(crate) transactions: DigestFilter,
+}
+
+impl DigestSummary {
+    /// Summarize the given recent block digests, tip first, and mempool
+    /// transactions.
+    pub(crate) fn new(block_digests: &[Digest], txids: &[TransactionKernelId]) -> Self {
+        let txids = txids
+            .iter()
+            .take(DIGEST_SUMMARY_MAX_TRANSACTIONS)
+            .map(|txid| Digest::from(*txid))
+            .collect::<Vec<_>>();
+        Self {
+            tip: block_digests.first().copied().unwrap_or_default(),
+            blocks: DigestFilter::new(block_digests),
+            transactions: DigestFilter::new(&txids),
+        }
+    }
+
+    pub(crate) fn is_well_formed(&self) -> bool {
+        self.blocks.is_well_formed() && self.transactions.is_well_formed()
+    }
+
+    pub(crate) fn has_block(&self, block_digest: Digest) -> bool {
+        self.tip == block_digest || self.blocks.contains(&block_digest)
+    }
+
+    pub(crate) fn has_transaction(&self, txid: TransactionKernelId) -> bool {
+        self.transactions.contains(&Digest::from(txid))
+    }
+}
+
+#[cfg(test)]
+mod digest_summary_tests {
+    use rand::random;
+
+    use super::*;
+
+    #[test]
+    fn summary_has_no_false_negatives_and_few_false_positives() {
+        let block_digests: Vec<Digest> =
+            (0..DIGEST_SUMMARY_BLOCK_WINDOW).map(|_| random()).collect();
+        let summary = DigestSummary::new(&block_digests, &[]);
+        assert!(summary.is_well_formed());
+        assert_eq!(block_digests[0], summary.tip);
+        assert!(block_digests
+            .iter()
+            .all(|digest| summary.has_block(*digest)));
+
+        let num_false_positives = (0..1000).filter(|_| summary.has_block(random())).count();
+        assert!(num_false_positives < 50, "{num_false_positives}");
+    }
+
+    #[test]
+    fn oversized_or_empty_filters_are_malformed() {
+        let mut summary = DigestSummary::new(&[random()], &[]);
+        summary.transactions.bits = vec![];
+        assert!(!summary.is_well_formed());
+
+        summary.transactions.bits = vec![0; MAX_FILTER_BITS / 64 + 1];
+        assert!(!summary.is_well_formed());
+
+        summary.transactions = DigestFilter::new(&[]);
+        summary.transactions.num_hashes = 0;
+        assert!(!summary.is_well_formed());
+    }
+}
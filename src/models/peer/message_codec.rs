@@ -0,0 +1,396 @@
+//! Framing of peer messages, with size limits per message type.
+//!
+//! Messages are length-delimited frames holding the bincode encoding of a
+//! [`PeerMessage`](super::PeerMessage). The length prefix is checked against the limit for the
+//! message's type as soon as the first byte of the message arrives, before
+//! any buffer for the frame is reserved, so a peer cannot trigger large
+//! allocations by announcing a huge frame.
+//!
+//! Bincode encodes the variant index of an enum with fewer than 251 variants
+//! in the first byte, with both fixed-size and variable-size integer encoding.
+//! That byte identifies the type of the message, as the discriminant of the
+//! matching [`PeerMessageType`].
+
+use std::io;
+
+use bytes::Bytes;
+use bytes::BytesMut;
+use tokio_util::codec::Decoder;
+use tokio_util::codec::Encoder;
+use tokio_util::codec::LengthDelimitedCodec;
+
+use super::PeerMessageType;
+
+/// Length of the big-endian length prefix of [`LengthDelimitedCodec`].
+const LENGTH_FIELD_LEN: usize = 4;
+
+const KIB: usize = 1024;
+const MIB: usize = 1024 * KIB;
+
+/// Maximum sizes in bytes of encoded peer messages, by message type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageSizeLimits {
+    pub handshake: usize,
+
+    /// Blocks, and compact blocks and the removal records completing them,
+    /// which carry everything of a block but its inputs' removal records.
+    pub block: usize,
+    pub block_response_batch: usize,
+
+    /// Transactions, also those contributed to coinjoin sessions.
+    pub transaction: usize,
+
+    /// All other messages: notifications, requests, and peer lists.
+    pub other: usize,
+}
+
+impl MessageSizeLimits {
+    pub const STANDARD: Self = Self {
+        handshake: 64 * KIB,
+        block: 64 * MIB,
+        block_response_batch: 512 * MIB,
+        transaction: 64 * MIB,
+        other: MIB,
+    };
+
+    /// The largest message of any type.
+    pub fn max(&self) -> usize {
+        [
+            self.handshake,
+            self.block,
+            self.block_response_batch,
+            self.transaction,
+            self.other,
+        ]
+        .into_iter()
+        .max()
+        .unwrap()
+    }
+
+    /// The limit for messages of the given type.
+    fn for_type(&self, message_type: PeerMessageType) -> usize {
+        match message_type {
+            PeerMessageType::Handshake => self.handshake,
+            PeerMessageType::Block | PeerMessageType::CompactBlock | PeerMessageType::BlockTxn => {
+                self.block
+            }
+            PeerMessageType::BlockResponseBatch => self.block_response_batch,
+            PeerMessageType::Transaction | PeerMessageType::CoinJoinContribution => {
+                self.transaction
+            }
+            _ => self.other,
+        }
+    }
+
+    /// The limit for messages whose encoding starts with the given variant
+    /// index of [`PeerMessage`](super::PeerMessage). Unknown indices get the
+    /// limit of [`Self::other`].
+    fn for_variant_index(&self, variant_index: u8) -> usize {
+        PeerMessageType::from_repr(usize::from(variant_index))
+            .map_or(self.other, |message_type| self.for_type(message_type))
+    }
+}
+
+/// Length-delimited codec rejecting frames that exceed the size limit of
+/// their message type.
+#[derive(Debug)]
+pub(crate) struct PeerMessageCodec {
+    inner: LengthDelimitedCodec,
+    limits: MessageSizeLimits,
+
+    /// Whether the length prefix of the frame being received was checked.
+    frame_checked: bool,
+}
+
+impl PeerMessageCodec {
+    pub(crate) fn new(limits: MessageSizeLimits) -> Self {
+        let mut inner = LengthDelimitedCodec::new();
+        inner.set_max_frame_length(limits.max());
+        Self {
+            inner,
+            limits,
+            frame_checked: false,
+        }
+    }
+}
+
+impl Decoder for PeerMessageCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if !self.frame_checked {
+            let Some(length_field) = src.get(..LENGTH_FIELD_LEN) else {
+                return Ok(None);
+            };
+            let frame_length = u32::from_be_bytes(length_field.try_into().unwrap()) as usize;
+            if frame_length > 0 {
+                let Some(&variant_index) = src.get(LENGTH_FIELD_LEN) else {
+                    return Ok(None);
+                };
+                let limit = self.limits.for_variant_index(variant_index);
+                if frame_length > limit {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "peer message with variant index {variant_index} of {frame_length} \
+                            bytes exceeds limit of {limit} bytes"
+                        ),
+                    ));
+                }
+            }
+            self.frame_checked = true;
+        }
+
+        let frame = self.inner.decode(src)?;
+        if frame.is_some() {
+            self.frame_checked = false;
+        }
+
+        Ok(frame)
+    }
+}
+
+impl Encoder<Bytes> for PeerMessageCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Bytes, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.inner.encode(item, dst)
+    }
+}
+
+#[cfg(test)]
+mod message_codec_tests {
+    use std::collections::HashSet;
+
+    use strum::IntoEnumIterator;
+    use tasm_lib::triton_vm::proof::Proof;
+
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::blockchain::block::block_height::BlockHeight;
+    use crate::models::blockchain::block::Block;
+    use crate::models::blockchain::transaction::TransactionProof;
+    use crate::models::peer::compact_block::CompactBlock;
+    use crate::models::peer::digest_summary::DigestSummary;
+    use crate::models::peer::transfer_block::TransferBlock;
+    use crate::models::peer::transfer_transaction::TransferTransaction;
+    use crate::models::peer::BlockRequestBatch;
+    use crate::models::peer::ConnectionStatus;
+    use crate::models::peer::PeerMessage;
+    use crate::models::state::coinjoin::CoinJoinContribution;
+    use crate::models::state::coinjoin::DisclosedOutput;
+    use crate::tests::shared::get_dummy_handshake_data_for_genesis;
+    use crate::tests::shared::get_dummy_socket_address;
+    use crate::tests::shared::make_mock_transaction;
+    use crate::tests::shared::pseudorandom_utxo;
+    use crate::tests::shared::to_bytes;
+
+    fn variant_index(message: &PeerMessage) -> u8 {
+        to_bytes(message).unwrap()[LENGTH_FIELD_LEN]
+    }
+
+    /// One message of every type, with the limit it must get.
+    async fn one_message_of_every_type(limits: &MessageSizeLimits) -> Vec<(PeerMessage, usize)> {
+        let network = Network::RegTest;
+        let handshake = get_dummy_handshake_data_for_genesis(network).await;
+        let genesis = Block::genesis_block(network);
+        let transfer_block = TransferBlock {
+            header: genesis.kernel.header.clone(),
+            body: genesis.kernel.body.clone(),
+            appendix: genesis.kernel.appendix.clone(),
+            proof: Proof(vec![]),
+        };
+        let mut transaction = make_mock_transaction(vec![], vec![]);
+        transaction.proof = TransactionProof::SingleProof(Proof(vec![]));
+        let transfer_transaction = TransferTransaction::try_from(&transaction).unwrap();
+        let contribution = CoinJoinContribution {
+            session_id: 0,
+            transaction: transfer_transaction.clone(),
+            disclosed_output: DisclosedOutput {
+                utxo: pseudorandom_utxo(rand::random()),
+                sender_randomness: rand::random(),
+                receiver_digest: rand::random(),
+            },
+        };
+
+        vec![
+            (
+                PeerMessage::Handshake(Box::new((vec![], handshake))),
+                limits.handshake,
+            ),
+            (PeerMessage::Block(Box::new(transfer_block)), limits.block),
+            (PeerMessage::BlockNotificationRequest, limits.other),
+            (
+                PeerMessage::BlockNotification((&genesis).into()),
+                limits.other,
+            ),
+            (
+                PeerMessage::BlockRequestByHeight(BlockHeight::genesis()),
+                limits.other,
+            ),
+            (
+                PeerMessage::BlockRequestByHash(genesis.hash()),
+                limits.other,
+            ),
+            (
+                PeerMessage::BlockRequestBatch(BlockRequestBatch {
+                    known_blocks: vec![genesis.hash()],
+                    max_response_len: 1,
+                }),
+                limits.other,
+            ),
+            (
+                PeerMessage::BlockResponseBatch(vec![]),
+                limits.block_response_batch,
+            ),
+            (
+                PeerMessage::Transaction(Box::new(transfer_transaction)),
+                limits.transaction,
+            ),
+            (
+                PeerMessage::TransactionNotification((&transaction).try_into().unwrap()),
+                limits.other,
+            ),
+            (
+                PeerMessage::TransactionRequest(transaction.kernel.txid()),
+                limits.other,
+            ),
+            (PeerMessage::PeerListRequest, limits.other),
+            (PeerMessage::PeerListResponse(vec![]), limits.other),
+            (PeerMessage::Bye, limits.other),
+            (
+                PeerMessage::ConnectionStatus(ConnectionStatus::Accepted),
+                limits.other,
+            ),
+            (PeerMessage::RendezvousRequest, limits.other),
+            (
+                PeerMessage::RendezvousIntroduction {
+                    endpoint: get_dummy_socket_address(0),
+                    initiator: true,
+                },
+                limits.other,
+            ),
+            (PeerMessage::ReachabilityRequest, limits.other),
+            (
+                PeerMessage::ReachabilityResponse {
+                    address: None,
+                    reachable: false,
+                },
+                limits.other,
+            ),
+            (
+                PeerMessage::DigestSummary(DigestSummary::new(&[genesis.hash()], &[])),
+                limits.other,
+            ),
+            (
+                PeerMessage::CompactBlockRequest(genesis.hash()),
+                limits.other,
+            ),
+            (
+                PeerMessage::CompactBlock(Box::new(CompactBlock::new(&genesis).unwrap())),
+                limits.block,
+            ),
+            (
+                PeerMessage::GetBlockTxn {
+                    block_digest: genesis.hash(),
+                    indices: vec![0],
+                },
+                limits.other,
+            ),
+            (
+                PeerMessage::BlockTxn {
+                    block_digest: genesis.hash(),
+                    removal_records: vec![],
+                },
+                limits.block,
+            ),
+            (PeerMessage::CoinJoinSessionRequest, limits.other),
+            (PeerMessage::CoinJoinSession(None), limits.other),
+            (
+                PeerMessage::CoinJoinContribution(Box::new(contribution)),
+                limits.transaction,
+            ),
+            (
+                PeerMessage::CoinJoinStatus {
+                    session_id: 0,
+                    result: Ok(()),
+                },
+                limits.other,
+            ),
+            (PeerMessage::Ping(0), limits.other),
+            (PeerMessage::Pong(0), limits.other),
+        ]
+    }
+
+    #[tokio::test]
+    async fn variant_indices_match_message_types() {
+        let limits = MessageSizeLimits {
+            handshake: 1,
+            block: 2,
+            block_response_batch: 3,
+            transaction: 4,
+            other: 5,
+        };
+
+        let messages = one_message_of_every_type(&limits).await;
+        let message_types = messages
+            .iter()
+            .map(|(message, _)| PeerMessageType::from(message))
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            PeerMessageType::iter().count(),
+            message_types.len(),
+            "test must cover every message type"
+        );
+
+        for (message, limit) in messages {
+            let variant_index = variant_index(&message);
+            assert_eq!(
+                Some(PeerMessageType::from(&message)),
+                PeerMessageType::from_repr(usize::from(variant_index))
+            );
+            assert_eq!(
+                limit,
+                limits.for_variant_index(variant_index),
+                "limit of {}",
+                message.get_type()
+            );
+        }
+
+        let unknown_index = u8::try_from(PeerMessageType::iter().count()).unwrap();
+        assert_eq!(limits.other, limits.for_variant_index(unknown_index));
+    }
+
+    #[test]
+    fn oversized_frame_is_rejected_before_payload_arrives() {
+        let limits = MessageSizeLimits::STANDARD;
+        let mut codec = PeerMessageCodec::new(limits);
+
+        // Announce a peer list response larger than allowed, sending only
+        // the first byte of the payload.
+        let mut src = BytesMut::new();
+        src.extend_from_slice(&((limits.other + 1) as u32).to_be_bytes());
+        src.extend_from_slice(&[12]);
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            codec.decode(&mut src).unwrap_err().kind()
+        );
+        assert!(src.capacity() < limits.other);
+    }
+
+    #[test]
+    fn frames_within_limits_are_decoded() {
+        let mut codec = PeerMessageCodec::new(MessageSizeLimits::STANDARD);
+        let bye = to_bytes(&PeerMessage::Bye).unwrap();
+
+        // Deliver the frame byte by byte.
+        let mut src = BytesMut::new();
+        for (i, byte) in bye.iter().enumerate() {
+            src.extend_from_slice(&[*byte]);
+            let frame = codec.decode(&mut src).unwrap();
+            assert_eq!(i == bye.len() - 1, frame.is_some());
+        }
+        assert!(src.is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+use std::net::IpAddr;
+
+/// Coarse grouping of IP addresses by the network they likely belong to. An
+/// attacker controlling many addresses typically controls them within a few
+/// groups, so outbound connections are spread across distinct groups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NetworkGroup {
+    /// The /16 prefix of an IPv4 address.
+    Ipv4([u8; 2]),
+
+    /// The /32 prefix of an IPv6 address.
+    Ipv6([u16; 2]),
+
+    /// Loopback, private and otherwise non-routable addresses. Not subject to
+    /// diversity limits, as they are only reached deliberately.
+    Local,
+}
+
+impl NetworkGroup {
+    pub fn of(ip: IpAddr) -> Self {
+        match ip.to_canonical() {
+            IpAddr::V4(ip) => {
+                if ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified()
+                {
+                    Self::Local
+                } else {
+                    let [a, b, _, _] = ip.octets();
+                    Self::Ipv4([a, b])
+                }
+            }
+            IpAddr::V6(ip) => {
+                let segments = ip.segments();
+                let is_unique_local = segments[0] & 0xfe00 == 0xfc00;
+                let is_unicast_link_local = segments[0] & 0xffc0 == 0xfe80;
+                if ip.is_loopback()
+                    || ip.is_unspecified()
+                    || is_unique_local
+                    || is_unicast_link_local
+                {
+                    Self::Local
+                } else {
+                    Self::Ipv6([segments[0], segments[1]])
+                }
+            }
+        }
+    }
+
+    /// Returns true if at most one outbound connection should be made to this
+    /// group.
+    pub fn is_limited(&self) -> bool {
+        *self != Self::Local
+    }
+}
+
+#[cfg(test)]
+mod network_group_tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn group(ip: &str) -> NetworkGroup {
+        NetworkGroup::of(IpAddr::from_str(ip).unwrap())
+    }
+
+    #[test]
+    fn ipv4_addresses_are_grouped_by_16_bit_prefix() {
+        assert_eq!(group("139.162.1.1"), group("139.162.200.7"));
+        assert_ne!(group("139.162.1.1"), group("139.163.1.1"));
+        assert_eq!(NetworkGroup::Ipv4([139, 162]), group("139.162.1.1"));
+    }
+
+    #[test]
+    fn ipv6_addresses_are_grouped_by_32_bit_prefix() {
+        assert_eq!(group("2a01:7e00::1"), group("2a01:7e00:ffff::2"));
+        assert_ne!(group("2a01:7e00::1"), group("2a01:7e01::1"));
+    }
+
+    #[test]
+    fn ipv4_mapped_ipv6_addresses_are_grouped_as_ipv4() {
+        assert_eq!(group("139.162.1.1"), group("::ffff:139.162.9.9"));
+    }
+
+    #[test]
+    fn non_routable_addresses_are_local() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "192.168.0.1",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+        ] {
+            assert_eq!(NetworkGroup::Local, group(ip), "{ip}");
+            assert!(!group(ip).is_limited());
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Fast propagation of blocks found by this node.
+//!
+//! A block found by this node is at risk of being orphaned until its peers
+//! have it. It is therefore announced to all peers before it is stored, which
+//! takes a while as the wallet and mempool are updated along with it. Until
+//! then, peer tasks serve requests for the block from its announcement, which
+//! holds it in the form in which it goes over the wire, such that it is
+//! converted once rather than read and converted for every peer.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Result;
+use tracing::warn;
+
+use super::compact_block::CompactBlock;
+use super::transfer_block::TransferBlock;
+use super::PeerBlockNotification;
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::models::blockchain::block::Block;
+use crate::prelude::twenty_first::math::digest::Digest;
+
+#[derive(Debug, Clone)]
+pub struct OwnBlockAnnouncement {
+    /// When the block was handed to the main task
+    found_at: Instant,
+    parent_digest: Digest,
+    pub(crate) notification: PeerBlockNotification,
+    pub(crate) transfer_block: TransferBlock,
+
+    /// `None` if the block cannot be sent as a compact block, in which case
+    /// peers asking for one are served from the archival state
+    pub(crate) compact_block: Option<CompactBlock>,
+}
+
+impl OwnBlockAnnouncement {
+    pub(crate) fn new(block: &Block) -> Result<Self> {
+        let compact_block = match CompactBlock::new(block) {
+            Ok(compact_block) => Some(compact_block),
+            Err(err) => {
+                warn!("Cannot announce own block as compact block: {err}");
+                None
+            }
+        };
+
+        Ok(Self {
+            found_at: Instant::now(),
+            parent_digest: block.header().prev_block_digest,
+            notification: block.into(),
+            transfer_block: block.try_into()?,
+            compact_block,
+        })
+    }
+
+    pub fn digest(&self) -> Digest {
+        self.notification.hash
+    }
+
+    pub fn height(&self) -> BlockHeight {
+        self.notification.height
+    }
+
+    /// Whether requests for the block may be served from the announcement
+    /// while the tip is `tip_digest`: while the block is being stored, and
+    /// after it became the tip. Once the tip moved on, requests by height
+    /// could refer to another block.
+    pub fn is_current(&self, tip_digest: Digest) -> bool {
+        tip_digest == self.parent_digest || tip_digest == self.digest()
+    }
+
+    /// Time since the block was found
+    pub fn elapsed(&self) -> Duration {
+        self.found_at.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod own_block_tests {
+    use rand::rngs::StdRng;
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::proof_abstractions::timestamp::Timestamp;
+    use crate::tests::shared::valid_sequence_of_blocks_for_tests;
+
+    #[tokio::test]
+    async fn announcement_is_current_until_the_tip_moves_on() {
+        let genesis = Block::genesis_block(Network::Main);
+        let [block_1, block_2] = valid_sequence_of_blocks_for_tests(
+            &genesis,
+            Timestamp::hours(1),
+            StdRng::seed_from_u64(5550001).gen(),
+        )
+        .await;
+
+        let announcement = OwnBlockAnnouncement::new(&block_1).unwrap();
+        assert_eq!(block_1.hash(), announcement.digest());
+        assert_eq!(block_1.header().height, announcement.height());
+        assert!(announcement.compact_block.is_some());
+
+        assert!(announcement.is_current(genesis.hash()));
+        assert!(announcement.is_current(block_1.hash()));
+        assert!(!announcement.is_current(block_2.hash()));
+
+        assert!(OwnBlockAnnouncement::new(&genesis).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+//! Token-bucket rate limits on what a peer may ask of this node.
+//!
+//! Each limited kind of message draws a token from a bucket that refills at a
+//! fixed rate per minute and holds at most one minute's worth of tokens, so a
+//! peer may burst up to the per-minute limit but not sustain more than it.
+//! Messages beyond the limit are dropped and the peer is sanctioned, such that
+//! a peer that keeps flooding ends up banned. Incoming handshakes are limited
+//! per IP address, since the peer is not known until the handshake completes.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Instant;
+
+use super::PeerMessage;
+use super::PeerSanctionReason;
+use crate::config_models::cli_args;
+
+/// Above this many tracked IP addresses, the buckets of IPs that have not
+/// connected for a minute are forgotten.
+const MAX_TRACKED_HANDSHAKE_IPS: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens_per_second: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// A full bucket allowing `max_per_minute` events per minute on average,
+    /// or any number of events if `max_per_minute` is zero.
+    pub fn per_minute(max_per_minute: u32, now: Instant) -> Self {
+        let capacity = f64::from(max_per_minute);
+        Self {
+            capacity,
+            tokens_per_second: capacity / 60.0,
+            tokens: capacity,
+            last_refill: now,
+        }
+    }
+
+    fn is_unlimited(&self) -> bool {
+        self.capacity == 0.0
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.tokens_per_second).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Take a token if one is available. Returns false if the rate limit is
+    /// exceeded.
+    pub fn try_take(&mut self, now: Instant) -> bool {
+        if self.is_unlimited() {
+            return true;
+        }
+
+        self.refill(now);
+        if self.tokens < 1.0 {
+            return false;
+        }
+
+        self.tokens -= 1.0;
+        true
+    }
+
+    /// Take up to `wanted` tokens, as many as are available. Returns the
+    /// number of tokens taken.
+    pub fn take_up_to(&mut self, wanted: usize, now: Instant) -> usize {
+        if self.is_unlimited() {
+            return wanted;
+        }
+
+        self.refill(now);
+        let taken = (self.tokens.floor() as usize).min(wanted);
+        self.tokens -= taken as f64;
+        taken
+    }
+
+    fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.capacity
+    }
+}
+
+/// Rate limits on the messages of one connected peer.
+#[derive(Debug, Clone)]
+pub struct PeerRateLimiter {
+    block_requests: TokenBucket,
+    transactions: TokenBucket,
+    blocks_served: TokenBucket,
+}
+
+impl PeerRateLimiter {
+    pub fn new(cli: &cli_args::Args) -> Self {
+        let now = Instant::now();
+        Self {
+            block_requests: TokenBucket::per_minute(cli.max_peer_block_requests_per_minute, now),
+            transactions: TokenBucket::per_minute(cli.max_peer_transactions_per_minute, now),
+            blocks_served: TokenBucket::per_minute(cli.max_peer_blocks_served_per_minute, now),
+        }
+    }
+
+    /// Count a message received from the peer. Returns the reason to sanction
+    /// the peer if the message exceeds its rate limit and should be dropped.
+    pub fn exceeded_by(
+        &mut self,
+        message: &PeerMessage,
+        now: Instant,
+    ) -> Option<PeerSanctionReason> {
+        match message {
+            PeerMessage::BlockRequestByHeight(_)
+            | PeerMessage::BlockRequestByHash(_)
+            | PeerMessage::BlockRequestBatch(_)
+            | PeerMessage::CompactBlockRequest(_)
+            | PeerMessage::GetBlockTxn { .. } => (!self.block_requests.try_take(now))
+                .then_some(PeerSanctionReason::BlockRequestFlood),
+            PeerMessage::Transaction(_) | PeerMessage::TransactionNotification(_) => {
+                (!self.transactions.try_take(now)).then_some(PeerSanctionReason::TransactionFlood)
+            }
+            _ => None,
+        }
+    }
+
+    /// Count blocks about to be served to the peer. Returns how many of the
+    /// `wanted` blocks may be served without exceeding its quota.
+    pub fn blocks_to_serve(&mut self, wanted: usize, now: Instant) -> usize {
+        self.blocks_served.take_up_to(wanted, now)
+    }
+}
+
+/// Rate limits on incoming connection attempts, per IP address.
+#[derive(Debug, Clone, Default)]
+pub struct HandshakeRateLimiter {
+    buckets: HashMap<IpAddr, TokenBucket>,
+}
+
+impl HandshakeRateLimiter {
+    /// Count a connection attempt from `ip`. Returns false if the attempt
+    /// exceeds `max_per_minute` and should be refused.
+    pub fn try_admit(&mut self, ip: IpAddr, max_per_minute: u32, now: Instant) -> bool {
+        if max_per_minute == 0 {
+            return true;
+        }
+
+        if self.buckets.len() >= MAX_TRACKED_HANDSHAKE_IPS {
+            self.buckets.retain(|_, bucket| !bucket.is_full(now));
+        }
+
+        self.buckets
+            .entry(ip)
+            .or_insert_with(|| TokenBucket::per_minute(max_per_minute, now))
+            .try_take(now)
+    }
+}
+
+#[cfg(test)]
+mod rate_limit_tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn bucket_allows_bursts_up_to_limit_and_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::per_minute(6, start);
+        for _ in 0..6 {
+            assert!(bucket.try_take(start));
+        }
+        assert!(!bucket.try_take(start));
+
+        // one token per 10 seconds
+        assert!(!bucket.try_take(start + Duration::from_secs(9)));
+        assert!(bucket.try_take(start + Duration::from_secs(10)));
+        assert!(!bucket.try_take(start + Duration::from_secs(10)));
+
+        // refills to capacity, not beyond
+        let later = start + Duration::from_secs(3600);
+        for _ in 0..6 {
+            assert!(bucket.try_take(later));
+        }
+        assert!(!bucket.try_take(later));
+    }
+
+    #[test]
+    fn batches_are_cut_to_available_tokens() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::per_minute(60, start);
+        assert_eq!(50, bucket.take_up_to(50, start));
+        assert_eq!(10, bucket.take_up_to(50, start));
+        assert_eq!(0, bucket.take_up_to(50, start));
+        assert_eq!(5, bucket.take_up_to(50, start + Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn zero_limit_is_unlimited() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::per_minute(0, now);
+        assert!((0..1000).all(|_| bucket.try_take(now)));
+
+        let mut handshakes = HandshakeRateLimiter::default();
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        assert!((0..1000).all(|_| handshakes.try_admit(ip, 0, now)));
+    }
+
+    #[test]
+    fn handshakes_are_limited_per_ip() {
+        let now = Instant::now();
+        let mut handshakes = HandshakeRateLimiter::default();
+        let flooder = IpAddr::from([10, 0, 0, 1]);
+        let other = IpAddr::from([10, 0, 0, 2]);
+        assert!(handshakes.try_admit(flooder, 2, now));
+        assert!(handshakes.try_admit(flooder, 2, now));
+        assert!(!handshakes.try_admit(flooder, 2, now));
+        assert!(handshakes.try_admit(other, 2, now));
+    }
+}
@@ -4,6 +4,7 @@ use serde::Serialize;
 use strum::EnumIter;
 use tasm_lib::triton_vm::proof::Proof;
 
+use crate::config_models::network::Network;
 use crate::models::blockchain::transaction::transaction_kernel::TransactionKernel;
 use crate::models::blockchain::transaction::validity::proof_collection::ProofCollection;
 use crate::models::blockchain::transaction::Transaction;
@@ -23,6 +24,43 @@ pub(crate) enum TransactionProofQuality {
     SingleProof,
 }
 
+/// The kinds of transaction proofs a node is willing to receive, announced to
+/// peers in the handshake. Peers notify of and send only transactions with
+/// proofs of these kinds. A transaction with a proof the peer does not accept
+/// reaches it once upgraded, for instance from a proof collection to a single
+/// proof, as the upgraded transaction is announced anew.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AcceptedTransactionProofs {
+    /// Primitive witnesses reveal the secrets that unlock the spent UTXOs.
+    /// Asking for them is only tolerated on regtest, where those secrets are
+    /// worthless, and they are never relayed.
+    pub primitive_witness: bool,
+    pub proof_collection: bool,
+    pub single_proof: bool,
+}
+
+impl AcceptedTransactionProofs {
+    pub(crate) fn new(single_proofs_only: bool) -> Self {
+        Self {
+            primitive_witness: false,
+            proof_collection: !single_proofs_only,
+            single_proof: true,
+        }
+    }
+
+    pub(crate) fn accepts(&self, proof_quality: TransactionProofQuality) -> bool {
+        match proof_quality {
+            TransactionProofQuality::ProofCollection => self.proof_collection,
+            TransactionProofQuality::SingleProof => self.single_proof,
+        }
+    }
+
+    /// Whether a peer on `network` may announce these.
+    pub(crate) fn is_allowed_on(&self, network: Network) -> bool {
+        !self.primitive_witness || network == Network::RegTest
+    }
+}
+
 /// Enumerates the kind of proofs that can be transferred to peers without
 /// loss of funds.
 ///
@@ -90,6 +128,22 @@ impl From<TransferTransaction> for Transaction {
 mod tests {
     use super::*;
 
+    #[test]
+    fn witnesses_may_only_be_asked_for_on_regtest() {
+        let mut accepted = AcceptedTransactionProofs::new(false);
+        assert!(accepted.accepts(TransactionProofQuality::ProofCollection));
+        assert!(accepted.is_allowed_on(Network::Main));
+
+        accepted.primitive_witness = true;
+        assert!(!accepted.is_allowed_on(Network::Main));
+        assert!(!accepted.is_allowed_on(Network::Testnet));
+        assert!(accepted.is_allowed_on(Network::RegTest));
+
+        let single_proofs_only = AcceptedTransactionProofs::new(true);
+        assert!(!single_proofs_only.accepts(TransactionProofQuality::ProofCollection));
+        assert!(single_proofs_only.accepts(TransactionProofQuality::SingleProof));
+    }
+
     #[test]
     fn transaction_proof_quality_ordering() {
         assert!(TransactionProofQuality::ProofCollection < TransactionProofQuality::SingleProof);
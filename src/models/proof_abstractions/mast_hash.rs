@@ -1,4 +1,8 @@
-use itertools::Itertools;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use rayon::iter::IntoParallelIterator;
+use rayon::iter::ParallelIterator;
 use strum::EnumCount;
 use tasm_lib::twenty_first::math::b_field_element::BFieldElement;
 use tasm_lib::twenty_first::math::tip5::Digest;
@@ -9,6 +13,51 @@ use tasm_lib::twenty_first::util_types::merkle_tree::MerkleTree;
 
 use crate::models::blockchain::shared::Hash;
 
+/// Leaf sequences at least this long are hashed through [LEAF_DIGEST_CACHE].
+/// Shorter ones are cheaper to hash than to look up.
+const MIN_CACHED_SEQUENCE_LENGTH: usize = 1 << 12;
+
+/// Bound on the total length of the sequences in [LEAF_DIGEST_CACHE].
+const MAX_CACHED_ELEMENTS: usize = 1 << 20;
+
+/// Digests of recently hashed long leaf sequences, most recent last.
+///
+/// The same large inputs and outputs are hashed repeatedly, e.g., whenever a
+/// block template is rebuilt with a new timestamp, or a kernel is hashed again
+/// to sort the mempool. Entries are matched by their entire sequence, not by
+/// a fingerprint, since MAST hashes are consensus critical.
+static LEAF_DIGEST_CACHE: Mutex<VecDeque<(Vec<BFieldElement>, Digest)>> =
+    Mutex::new(VecDeque::new());
+
+fn hash_leaf(sequence: Vec<BFieldElement>) -> Digest {
+    if sequence.len() < MIN_CACHED_SEQUENCE_LENGTH {
+        return Hash::hash_varlen(&sequence);
+    }
+
+    let cached_digest = LEAF_DIGEST_CACHE
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(cached_sequence, _)| *cached_sequence == sequence)
+        .map(|(_, digest)| *digest);
+    if let Some(digest) = cached_digest {
+        return digest;
+    }
+
+    let digest = Hash::hash_varlen(&sequence);
+    if sequence.len() <= MAX_CACHED_ELEMENTS {
+        let mut cache = LEAF_DIGEST_CACHE.lock().unwrap();
+        let mut num_cached_elements: usize = cache.iter().map(|(seq, _)| seq.len()).sum();
+        while num_cached_elements + sequence.len() > MAX_CACHED_ELEMENTS {
+            let (evicted, _) = cache.pop_front().unwrap();
+            num_cached_elements -= evicted.len();
+        }
+        cache.push_back((sequence, digest));
+    }
+
+    digest
+}
+
 pub trait HasDiscriminant: Clone {
     fn discriminant(&self) -> usize;
     // {
@@ -23,12 +72,17 @@ pub trait MastHash {
 
     fn mast_sequences(&self) -> Vec<Vec<BFieldElement>>;
 
+    /// The leaves of the Merkle tree, i.e., the digests of the sequences,
+    /// hashed in parallel.
+    fn mast_leaves(&self) -> Vec<Digest> {
+        self.mast_sequences()
+            .into_par_iter()
+            .map(hash_leaf)
+            .collect()
+    }
+
     fn merkle_tree(&self) -> MerkleTree {
-        let mut digests = self
-            .mast_sequences()
-            .into_iter()
-            .map(|seq| Hash::hash_varlen(&seq))
-            .collect_vec();
+        let mut digests = self.mast_leaves();
 
         // pad until length is a power of two
         while digests.len() & (digests.len() - 1) != 0 {
@@ -51,10 +105,12 @@ pub trait MastHash {
 
 #[cfg(test)]
 mod test {
+    use itertools::Itertools;
+    use rand::random;
     use strum::EnumCount;
     use strum::FromRepr;
 
-    use super::HasDiscriminant;
+    use super::*;
 
     #[derive(Debug, Clone, FromRepr, EnumCount, PartialEq, Eq, PartialOrd, Ord)]
     enum TestEnum {
@@ -88,4 +144,22 @@ mod test {
         assert_eq!(variant_set.len(), TestEnum::COUNT);
         assert_eq!(uint_set.len(), TestEnum::COUNT);
     }
+
+    #[test]
+    fn cached_leaf_digests_match_uncached_ones() {
+        let long_sequence = (0..MIN_CACHED_SEQUENCE_LENGTH)
+            .map(|_| random::<BFieldElement>())
+            .collect_vec();
+        let expected = Hash::hash_varlen(&long_sequence);
+
+        assert_eq!(expected, hash_leaf(long_sequence.clone()));
+        assert_eq!(expected, hash_leaf(long_sequence.clone()));
+
+        let mut other_sequence = long_sequence;
+        other_sequence[0].increment();
+        assert_eq!(
+            Hash::hash_varlen(&other_sequence),
+            hash_leaf(other_sequence)
+        );
+    }
 }
@@ -2,3 +2,4 @@ pub(crate) mod audit_vm_end_state;
 pub mod builtins;
 mod environment;
 pub mod program;
+pub mod proving_progress;
@@ -13,6 +13,7 @@ use tracing::debug;
 use tracing::info;
 
 use super::environment;
+use super::proving_progress::ProvingJob;
 use crate::models::state::ProvingLock;
 
 #[derive(Debug, Clone)]
@@ -182,6 +183,8 @@ pub(crate) async fn prove_consensus_program(
     nondeterminism: NonDeterminism,
     priority: &TritonProverSync,
 ) -> Result<Proof, TryLockError> {
+    let job = ProvingJob::start(claim.program_digest);
+
     // Hold proving lock until this function has terminated to prevent multiple
     // tasks from attempting to produce proofs simultaneously -- as this will
     // crash most computers and since the prover is already heavily parallel.
@@ -205,26 +208,47 @@ pub(crate) async fn prove_consensus_program(
     let proof = {
         #[cfg(test)]
         {
+            job.tracing_execution();
             test::load_proof_or_produce_and_save(&claim, program.clone(), nondeterminism.clone())
         }
         #[cfg(not(test))]
         {
+            use tracing::Instrument;
+
             let claim_clone = claim.clone();
             let program_clone = program.clone();
             let nondeterminism_clone = nondeterminism.clone();
-            tokio::task::spawn_blocking(move || {
-                tasm_lib::triton_vm::prove(
-                    tasm_lib::triton_vm::stark::Stark::default(),
-                    &claim_clone,
+            let span = tracing::trace_span!(
+                target: crate::PROFILE_IO_TARGET,
+                "prove",
+                program = %claim.program_digest,
+            );
+            job.tracing_execution();
+            let (aet, _) = tokio::task::spawn_blocking(move || {
+                VM::trace_execution(
                     &program_clone,
+                    claim_clone.input.clone().into(),
                     nondeterminism_clone,
                 )
                 .unwrap()
             })
+            .instrument(span.clone())
+            .await
+            .unwrap();
+
+            job.proving(aet.padded_height());
+            let claim_clone = claim.clone();
+            tokio::task::spawn_blocking(move || {
+                tasm_lib::triton_vm::stark::Stark::default()
+                    .prove(&claim_clone, &aet)
+                    .unwrap()
+            })
+            .instrument(span)
             .await
             .unwrap()
         }
     };
+    job.finished();
 
     let vm_output = VM::run(&program, claim.input.clone().into(), nondeterminism);
     assert!(vm_output.is_ok());
@@ -0,0 +1,234 @@
+//! Progress of in-flight Triton VM proving jobs, for display by the dashboard.
+//!
+//! Triton VM does not report progress from inside the prover, so a job only
+//! advances through coarse [`ProvingStage`]s. Once execution has been traced,
+//! the padded height of the algebraic execution trace is known, and the
+//! remaining time is estimated from the time per row that previous jobs took.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::util_types::json_schema::DigestSchema;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tasm_lib::Digest;
+
+/// Time per row of the padded execution trace assumed until a job completes.
+const INITIAL_NANOS_PER_ROW: u64 = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum ProvingStage {
+    WaitingForProver,
+    TracingExecution,
+    Proving,
+}
+
+impl ProvingStage {
+    pub const NUM_STAGES: usize = 3;
+
+    /// The 1-indexed step of this stage.
+    pub fn step(&self) -> usize {
+        match self {
+            Self::WaitingForProver => 1,
+            Self::TracingExecution => 2,
+            Self::Proving => 3,
+        }
+    }
+}
+
+impl Display for ProvingStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let stage = match self {
+            Self::WaitingForProver => "waiting for prover",
+            Self::TracingExecution => "tracing execution",
+            Self::Proving => "proving",
+        };
+        write!(f, "{stage}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ProvingJobStatus {
+    #[schemars(with = "DigestSchema")]
+    pub program_digest: Digest,
+    pub stage: ProvingStage,
+
+    /// Padded height of the algebraic execution trace, once known
+    pub padded_height: Option<usize>,
+    pub elapsed: Duration,
+
+    /// Estimated time until the proof is done, once the padded height is known
+    pub eta: Option<Duration>,
+}
+
+impl Display for ProvingJobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: step {}/{}",
+            self.stage,
+            self.stage.step(),
+            ProvingStage::NUM_STAGES
+        )?;
+        if let Some(eta) = self.eta {
+            write!(f, ", ~{} min left", eta.as_secs().div_ceil(60))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct JobState {
+    program_digest: Digest,
+    stage: ProvingStage,
+    padded_height: Option<usize>,
+    started: Instant,
+    proving_started: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    jobs: HashMap<u64, JobState>,
+    nanos_per_row: Option<u64>,
+}
+
+fn registry() -> &'static Mutex<Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Handle of a registered proving job. The job is unregistered when the
+/// handle is dropped.
+#[derive(Debug)]
+pub(crate) struct ProvingJob(u64);
+
+impl ProvingJob {
+    pub(crate) fn start(program_digest: Digest) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let job = JobState {
+            program_digest,
+            stage: ProvingStage::WaitingForProver,
+            padded_height: None,
+            started: Instant::now(),
+            proving_started: None,
+        };
+        registry().lock().unwrap().jobs.insert(id, job);
+
+        Self(id)
+    }
+
+    fn update(&self, f: impl FnOnce(&mut JobState)) {
+        if let Some(job) = registry().lock().unwrap().jobs.get_mut(&self.0) {
+            f(job);
+        }
+    }
+
+    pub(crate) fn tracing_execution(&self) {
+        self.update(|job| job.stage = ProvingStage::TracingExecution);
+    }
+
+    pub(crate) fn proving(&self, padded_height: usize) {
+        self.update(|job| {
+            job.stage = ProvingStage::Proving;
+            job.padded_height = Some(padded_height);
+            job.proving_started = Some(Instant::now());
+        });
+    }
+
+    /// Record that the proof was produced, improving the estimates of later
+    /// jobs.
+    pub(crate) fn finished(self) {
+        let mut registry = registry().lock().unwrap();
+        let Some(job) = registry.jobs.remove(&self.0) else {
+            return;
+        };
+        let (Some(padded_height), Some(proving_started)) = (job.padded_height, job.proving_started)
+        else {
+            return;
+        };
+
+        let nanos_per_row = proving_started.elapsed().as_nanos() as u64 / padded_height as u64;
+        registry.nanos_per_row = Some(match registry.nanos_per_row {
+            None => nanos_per_row,
+            Some(previous) => (previous + nanos_per_row) / 2,
+        });
+    }
+}
+
+impl Drop for ProvingJob {
+    fn drop(&mut self) {
+        registry().lock().unwrap().jobs.remove(&self.0);
+    }
+}
+
+/// Status of all in-flight proving jobs, oldest first.
+pub fn in_flight() -> Vec<ProvingJobStatus> {
+    let registry = registry().lock().unwrap();
+    let nanos_per_row = registry.nanos_per_row.unwrap_or(INITIAL_NANOS_PER_ROW);
+
+    let mut jobs = registry.jobs.values().collect::<Vec<_>>();
+    jobs.sort_by_key(|job| job.started);
+    jobs.into_iter()
+        .map(|job| {
+            let eta = job.padded_height.zip(job.proving_started).map(
+                |(padded_height, proving_started)| {
+                    Duration::from_nanos(nanos_per_row.saturating_mul(padded_height as u64))
+                        .saturating_sub(proving_started.elapsed())
+                },
+            );
+            ProvingJobStatus {
+                program_digest: job.program_digest,
+                stage: job.stage,
+                padded_height: job.padded_height,
+                elapsed: job.started.elapsed(),
+                eta,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod proving_progress_tests {
+    use rand::random;
+
+    use super::*;
+
+    fn status_of(program_digest: Digest) -> Option<ProvingJobStatus> {
+        in_flight()
+            .into_iter()
+            .find(|status| status.program_digest == program_digest)
+    }
+
+    #[test]
+    fn job_advances_through_stages_and_is_removed_when_dropped() {
+        let program_digest: Digest = random();
+        let job = ProvingJob::start(program_digest);
+        assert_eq!(
+            ProvingStage::WaitingForProver,
+            status_of(program_digest).unwrap().stage
+        );
+
+        job.tracing_execution();
+        let status = status_of(program_digest).unwrap();
+        assert_eq!(ProvingStage::TracingExecution, status.stage);
+        assert!(status.eta.is_none());
+
+        job.proving(1 << 10);
+        let status = status_of(program_digest).unwrap();
+        assert_eq!(ProvingStage::Proving, status.stage);
+        assert_eq!(Some(1 << 10), status.padded_height);
+        assert!(status.eta.is_some());
+        assert!(status.to_string().starts_with("proving: step 3/3"));
+
+        drop(job);
+        assert!(status_of(program_digest).is_none());
+    }
+}
@@ -16,12 +16,15 @@ use proptest::strategy::BoxedStrategy;
 use proptest::strategy::Strategy;
 use rand::distributions::Distribution;
 use rand::distributions::Standard;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use tasm_lib::prelude::TasmObject;
 use tasm_lib::twenty_first::math::b_field_element::BFieldElement;
 use tasm_lib::twenty_first::math::bfield_codec::BFieldCodec;
 
+use crate::util_types::json_schema::BFieldElementSchema;
+
 /// Dedicated struct for timestamps (and durations). Counts the number of
 /// milliseconds elapsed since the Unix epoch (00:00 UTC on 1 Jan 1970) using
 /// a single BFieldElement.
@@ -38,8 +41,10 @@ use tasm_lib::twenty_first::math::bfield_codec::BFieldCodec;
     GetSize,
     Default,
     TasmObject,
+    JsonSchema,
 )]
-pub struct Timestamp(pub BFieldElement);
+#[non_exhaustive]
+pub struct Timestamp(#[schemars(with = "BFieldElementSchema")] pub BFieldElement);
 
 impl PartialOrd for Timestamp {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
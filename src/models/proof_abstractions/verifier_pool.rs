@@ -0,0 +1,298 @@
+//! Optional pool of worker subprocesses for STARK verification.
+//!
+//! Verifying a proof allocates a lot of memory for a short while. With a pool,
+//! proofs received from peers are verified in worker processes, which isolates
+//! these spikes from the node and lets verifications run in parallel outside
+//! of the tokio runtime.
+//!
+//! A worker is the node binary started with `--verifier-worker`. It reads
+//! [`VerificationRequest`]s from stdin and writes a `bool` verdict to stdout
+//! for each. Every message is a little-endian `u32` length followed by that
+//! many bytes of bincode.
+//!
+//! When no pool is installed, or a worker fails, proofs are verified in
+//! process.
+
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::OnceLock;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+use tasm_lib::triton_vm;
+use tasm_lib::triton_vm::proof::Claim;
+use tasm_lib::triton_vm::proof::Proof;
+use tasm_lib::triton_vm::stark::Stark;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Child;
+use tokio::process::ChildStdin;
+use tokio::process::ChildStdout;
+use tokio::process::Command;
+use tokio::runtime::RuntimeFlavor;
+use tokio::sync::Mutex;
+use tokio::sync::Semaphore;
+use tracing::debug;
+use tracing::warn;
+
+/// Command line flag that makes the node binary run as a verifier worker.
+pub const VERIFIER_WORKER_FLAG: &str = "--verifier-worker";
+
+/// Upper bound on the size of a single message, to protect both ends from
+/// allocating absurd amounts of memory on a corrupted stream.
+const MAX_FRAME_SIZE: usize = 1 << 30;
+
+static VERIFIER_POOL: OnceLock<VerifierPool> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationRequest {
+    pub claim: Claim,
+    pub proof: Proof,
+}
+
+impl VerificationRequest {
+    fn verify(&self) -> bool {
+        triton_vm::verify(Stark::default(), &self.claim, &self.proof)
+    }
+}
+
+fn encode_frame<T: Serialize>(message: &T) -> Result<Vec<u8>> {
+    let payload = bincode::serialize(message)?;
+    if payload.len() > MAX_FRAME_SIZE {
+        bail!("message of {} bytes exceeds frame limit", payload.len());
+    }
+    let length = u32::try_from(payload.len())?;
+    Ok([length.to_le_bytes().as_slice(), &payload].concat())
+}
+
+fn frame_length(header: [u8; 4]) -> Result<usize> {
+    let length = u32::from_le_bytes(header) as usize;
+    if length > MAX_FRAME_SIZE {
+        bail!("frame of {length} bytes exceeds frame limit");
+    }
+    Ok(length)
+}
+
+/// Read one frame. Returns `None` if the stream ended before a new frame.
+fn read_frame<T: DeserializeOwned>(reader: &mut impl Read) -> Result<Option<T>> {
+    let mut header = [0u8; 4];
+    match reader.read_exact(&mut header) {
+        Ok(()) => (),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let mut payload = vec![0u8; frame_length(header)?];
+    reader.read_exact(&mut payload)?;
+    Ok(Some(bincode::deserialize(&payload)?))
+}
+
+async fn read_frame_async<T: DeserializeOwned>(reader: &mut ChildStdout) -> Result<T> {
+    let mut header = [0u8; 4];
+    reader.read_exact(&mut header).await?;
+    let mut payload = vec![0u8; frame_length(header)?];
+    reader.read_exact(&mut payload).await?;
+    Ok(bincode::deserialize(&payload)?)
+}
+
+/// Entry point of a verifier worker process. Serves requests on stdin until
+/// it is closed.
+pub fn run_worker() -> Result<()> {
+    let mut stdin = std::io::stdin().lock();
+    let mut stdout = std::io::stdout().lock();
+    while let Some(request) = read_frame::<VerificationRequest>(&mut stdin)? {
+        stdout.write_all(&encode_frame(&request.verify())?)?;
+        stdout.flush()?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+struct Worker {
+    // Held so the process is killed when the worker is dropped.
+    _process: Child,
+    stdin: ChildStdin,
+    stdout: ChildStdout,
+}
+
+impl Worker {
+    fn spawn(binary: &Path) -> Result<Self> {
+        let mut process = Command::new(binary)
+            .arg(VERIFIER_WORKER_FLAG)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("could not start verifier worker {}", binary.display()))?;
+        let stdin = process.stdin.take().context("worker stdin must be piped")?;
+        let stdout = process
+            .stdout
+            .take()
+            .context("worker stdout must be piped")?;
+
+        Ok(Self {
+            _process: process,
+            stdin,
+            stdout,
+        })
+    }
+
+    async fn verify(&mut self, request: &VerificationRequest) -> Result<bool> {
+        self.stdin.write_all(&encode_frame(request)?).await?;
+        self.stdin.flush().await?;
+        read_frame_async(&mut self.stdout).await
+    }
+}
+
+/// A fixed number of verifier worker processes. Workers that fail are
+/// replaced on next use.
+#[derive(Debug)]
+pub struct VerifierPool {
+    binary: PathBuf,
+    idle: Mutex<Vec<Worker>>,
+    permits: Semaphore,
+}
+
+impl VerifierPool {
+    pub fn new(binary: PathBuf, num_workers: usize) -> Result<Self> {
+        let workers = (0..num_workers)
+            .map(|_| Worker::spawn(&binary))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            binary,
+            idle: Mutex::new(workers),
+            permits: Semaphore::new(num_workers),
+        })
+    }
+
+    /// Make this pool the one used by [`verify`] for the rest of the process'
+    /// lifetime.
+    pub fn install(self) -> Result<()> {
+        if VERIFIER_POOL.set(self).is_err() {
+            bail!("verifier pool already installed");
+        }
+        Ok(())
+    }
+
+    async fn verify(&self, request: VerificationRequest) -> bool {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("verifier pool semaphore is never closed");
+        let worker = self.idle.lock().await.pop();
+        let worker = match worker {
+            Some(worker) => Ok(worker),
+            None => Worker::spawn(&self.binary),
+        };
+
+        let verdict = match worker {
+            Ok(mut worker) => match worker.verify(&request).await {
+                Ok(verdict) => {
+                    self.idle.lock().await.push(worker);
+                    Some(verdict)
+                }
+                Err(e) => {
+                    warn!("Verifier worker failed: {e:#}");
+                    None
+                }
+            },
+            Err(e) => {
+                warn!("No verifier worker available: {e:#}");
+                None
+            }
+        };
+
+        match verdict {
+            Some(verdict) => verdict,
+            None => {
+                debug!("Verifying proof in process");
+                tokio::task::spawn_blocking(move || request.verify())
+                    .await
+                    .unwrap_or(false)
+            }
+        }
+    }
+}
+
+/// Verify a STARK proof, in a worker process if a [`VerifierPool`] is
+/// installed.
+pub async fn verify(claim: Claim, proof: Proof) -> bool {
+    let request = VerificationRequest { claim, proof };
+    match VERIFIER_POOL.get() {
+        Some(pool) => pool.verify(request).await,
+        None => request.verify(),
+    }
+}
+
+/// Like [`verify`], for synchronous callers. Uses the pool only when called
+/// from a multi-threaded tokio runtime, where the calling thread may block.
+pub fn verify_blocking(claim: &Claim, proof: &Proof) -> bool {
+    let Some(pool) = VERIFIER_POOL.get() else {
+        return triton_vm::verify(Stark::default(), claim, proof);
+    };
+    let handle = match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => handle,
+        _ => return triton_vm::verify(Stark::default(), claim, proof),
+    };
+
+    let request = VerificationRequest {
+        claim: claim.clone(),
+        proof: proof.clone(),
+    };
+    tokio::task::block_in_place(|| handle.block_on(pool.verify(request)))
+}
+
+#[cfg(test)]
+mod verifier_pool_tests {
+    use tasm_lib::twenty_first::prelude::BFieldElement;
+
+    use super::*;
+
+    #[test]
+    fn frames_roundtrip() {
+        let request = VerificationRequest {
+            claim: Claim::new(Default::default()).with_input(vec![BFieldElement::new(7)]),
+            proof: Proof(vec![BFieldElement::new(1), BFieldElement::new(2)]),
+        };
+        let mut stream = [
+            encode_frame(&request).unwrap(),
+            encode_frame(&true).unwrap(),
+        ]
+        .concat();
+        let mut reader = stream.as_slice();
+
+        let decoded: VerificationRequest = read_frame(&mut reader).unwrap().unwrap();
+        assert_eq!(request.claim, decoded.claim);
+        assert_eq!(request.proof, decoded.proof);
+        assert!(read_frame::<bool>(&mut reader).unwrap().unwrap());
+        assert!(read_frame::<bool>(&mut reader).unwrap().is_none());
+
+        // a truncated frame is an error, not a clean end of stream
+        stream.truncate(stream.len() - 1);
+        let mut reader = stream.as_slice();
+        let _: VerificationRequest = read_frame(&mut reader).unwrap().unwrap();
+        assert!(read_frame::<bool>(&mut reader).is_err());
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected() {
+        let header = u32::try_from(MAX_FRAME_SIZE + 1).unwrap().to_le_bytes();
+        let mut reader = header.as_slice();
+        assert!(read_frame::<bool>(&mut reader).is_err());
+    }
+
+    #[test]
+    fn in_process_verification_rejects_bogus_proof() {
+        let claim = Claim::new(Default::default());
+        assert!(!verify_blocking(&claim, &Proof(vec![])));
+    }
+}
@@ -1,6 +1,9 @@
 use std::ops::DerefMut;
 use std::path::PathBuf;
 
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
 use anyhow::Result;
 use memmap2::MmapOptions;
 use num_traits::Zero;
@@ -13,10 +16,16 @@ use tracing::warn;
 use twenty_first::math::digest::Digest;
 
 use super::shared::new_block_file_is_needed;
+use super::tx_index::TxIndex;
+use super::utxo_set_stats::AdditionRecordAge;
+use super::utxo_set_stats::SwbfIndexCounts;
+use super::utxo_set_stats::UtxoSetStats;
+use super::utxo_set_stats::ADDITION_RECORD_AGES_IN_DAYS;
 use crate::config_models::data_directory::DataDirectory;
 use crate::config_models::network::Network;
 use crate::database::create_db_if_missing;
 use crate::database::storage::storage_schema::traits::*;
+use crate::database::storage::storage_vec::traits::*;
 use crate::database::NeptuneLevelDb;
 use crate::database::WriteBatchAsync;
 use crate::models::blockchain::block::block_header::BlockHeader;
@@ -29,6 +38,7 @@ use crate::models::database::BlockIndexValue;
 use crate::models::database::BlockRecord;
 use crate::models::database::FileRecord;
 use crate::models::database::LastFileRecord;
+use crate::models::proof_abstractions::timestamp::Timestamp;
 use crate::prelude::twenty_first;
 use crate::util_types::mutator_set::addition_record::AdditionRecord;
 use crate::util_types::mutator_set::mutator_set_accumulator::MutatorSetAccumulator;
@@ -55,9 +65,10 @@ pub struct ArchivalState {
     ///   Height(BlockHeight)  -> Height(Vec<Digest>)
     ///   LastFile             -> LastFile(LastFileRecord)
     ///   BlockTipDigest       -> BlockTipDigest(Digest)
+    ///   FirstUnprunedFile    -> FirstUnprunedFile(u32)
     /// ```
     ///
-    /// So this is effectively 5 logical indexes.
+    /// So this is effectively 6 logical indexes.
     pub block_index_db: NeptuneLevelDb<BlockIndexKey, BlockIndexValue>,
 
     // The genesis block is stored on the heap, as we would otherwise get stack overflows whenever we instantiate
@@ -67,6 +78,10 @@ pub struct ArchivalState {
     // The archival mutator set is persisted to one database that also records a sync label,
     // which corresponds to the hash of the block to which the mutator set is synced.
     pub archival_mutator_set: RustyArchivalMutatorSet,
+
+    /// Index of announcements and received UTXOs, if enabled with
+    /// `--build-tx-index`.
+    tx_index: Option<TxIndex>,
 }
 
 // The only reason we have this `Debug` implementation is that it's required
@@ -77,6 +92,7 @@ impl core::fmt::Debug for ArchivalState {
             .field("data_dir", &self.data_dir)
             .field("block_index_db", &self.block_index_db)
             .field("genesis_block", &self.genesis_block)
+            .field("tx_index", &self.tx_index)
             .finish()
     }
 }
@@ -108,20 +124,12 @@ impl ArchivalState {
         let path = ms_db_dir_path.clone();
         let result = NeptuneLevelDb::new(&path, &create_db_if_missing()).await;
 
-        let db = match result {
-            Ok(db) => db,
-            Err(e) => {
-                tracing::error!(
-                    "Could not open mutator set database at {}: {e}",
-                    ms_db_dir_path.display()
-                );
-                panic!(
-                    "Could not open database; do not know how to proceed. Panicking.\n\
-                    If you suspect the database may be corrupted, consider renaming the directory {} or removing it altogether.",
-                    ms_db_dir_path.display()
-                );
-            }
-        };
+        let db = result.with_context(|| {
+            format!(
+                "Could not open mutator set database at {}",
+                ms_db_dir_path.display()
+            )
+        })?;
 
         let mut archival_set = RustyArchivalMutatorSet::connect(db).await;
         archival_set.restore_or_new().await;
@@ -219,9 +227,53 @@ impl ArchivalState {
             block_index_db,
             genesis_block,
             archival_mutator_set,
+            tx_index: None,
         }
     }
 
+    /// Start indexing blocks stored as tip, after indexing the canonical
+    /// blocks stored since the index was last updated.
+    pub async fn enable_tx_index(&mut self, mut tx_index: TxIndex) -> Result<()> {
+        let tip_digest = self.get_tip_digest().await;
+        let tip_height = self
+            .get_block_header(tip_digest)
+            .await
+            .map(|header| header.height)
+            .unwrap_or_default();
+        let first_unindexed_height = tx_index
+            .indexed_height()
+            .await
+            .map(|height| height.next())
+            .unwrap_or_default();
+
+        let mut height = first_unindexed_height;
+        while height <= tip_height {
+            let block = match self
+                .block_height_to_canonical_block_digest(height, tip_digest)
+                .await
+            {
+                Some(digest) => self.get_block(digest).await?,
+                None => None,
+            };
+            match block {
+                Some(block) => tx_index.index_block(&block).await,
+                None => warn!("Could not index block at height {height}: block not stored"),
+            }
+            height = height.next();
+        }
+
+        self.tx_index = Some(tx_index);
+        Ok(())
+    }
+
+    pub fn tx_index(&self) -> Option<&TxIndex> {
+        self.tx_index.as_ref()
+    }
+
+    pub(crate) fn tx_index_mut(&mut self) -> Option<&mut TxIndex> {
+        self.tx_index.as_mut()
+    }
+
     pub fn genesis_block(&self) -> &Block {
         &self.genesis_block
     }
@@ -377,6 +429,10 @@ impl ArchivalState {
 
         self.block_index_db.batch_write(batch).await;
 
+        if let Some(tx_index) = self.tx_index.as_mut() {
+            tx_index.index_block(new_block).await;
+        }
+
         Ok(())
     }
 
@@ -389,9 +445,20 @@ impl ArchivalState {
         // Open file as read-only
         let block_file: tokio::fs::File = tokio::fs::OpenOptions::new()
             .read(true)
-            .open(block_file_path)
-            .await
-            .unwrap();
+            .open(&block_file_path)
+            .await
+            .with_context(|| format!("could not open {}", block_file_path.display()))?;
+
+        // Mapping beyond the end of a truncated file would crash on access
+        let block_end =
+            block_record.file_location.offset + block_record.file_location.block_length as u64;
+        let file_length = block_file.metadata().await?.len();
+        if file_length < block_end {
+            bail!(
+                "{} is truncated: has {file_length} bytes, block ends at byte {block_end}",
+                block_file_path.display()
+            );
+        }
 
         // Read the file into memory, set the offset and length indicated in the block record
         // to avoid using more memory than needed
@@ -403,7 +470,12 @@ impl ArchivalState {
                     .len(block_record.file_location.block_length)
                     .map(&block_file)?
             };
-            let block: Block = bincode::deserialize(&mmap).unwrap();
+            let block: Block = bincode::deserialize(&mmap).with_context(|| {
+                format!(
+                    "could not deserialize block in {}",
+                    block_file_path.display()
+                )
+            })?;
             Ok(block)
         })
         .await?
@@ -422,7 +494,7 @@ impl ArchivalState {
             .block_index_db
             .get(BlockIndexKey::Block(tip_digest))
             .await
-            .unwrap()
+            .ok_or_else(|| anyhow!("no block record for tip {tip_digest}"))?
             .as_block_record();
 
         let block: Block = self.get_block_from_block_record(tip_block_record).await?;
@@ -430,6 +502,15 @@ impl ArchivalState {
         Ok(Some(block))
     }
 
+    /// Return the digest of the latest block from the database, without
+    /// reading the block itself.
+    pub async fn get_tip_digest(&self) -> Digest {
+        match self.block_index_db.get(BlockIndexKey::BlockTipDigest).await {
+            Some(digest) => digest.as_tip_digest(),
+            None => self.genesis_block.hash(),
+        }
+    }
+
     /// Return latest block from database, or genesis block if no other block
     /// is known.
     pub async fn get_tip(&self) -> Block {
@@ -444,6 +525,33 @@ impl ArchivalState {
         }
     }
 
+    /// The canonical blocks applied to the mutator set with the given hash,
+    /// oldest first, together with that mutator set. `None` if it is not the
+    /// mutator set of one of the last `max_depth` canonical blocks, or of the
+    /// tip.
+    pub(crate) async fn blocks_since_mutator_set(
+        &self,
+        mutator_set_hash: Digest,
+        max_depth: usize,
+    ) -> Result<Option<(MutatorSetAccumulator, Vec<Block>)>> {
+        let mut blocks = vec![];
+        let mut block = self.get_tip().await;
+        for _ in 0..=max_depth {
+            if block.body().mutator_set_accumulator.hash() == mutator_set_hash {
+                blocks.reverse();
+                return Ok(Some((block.body().mutator_set_accumulator.clone(), blocks)));
+            }
+
+            let Some(parent) = self.get_block(block.header().prev_block_digest).await? else {
+                return Ok(None);
+            };
+            blocks.push(block);
+            block = parent;
+        }
+
+        Ok(None)
+    }
+
     /// Return parent of tip block. Returns `None` iff tip is genesis block.
     pub async fn get_tip_parent(&self) -> Option<Block> {
         let tip_digest = self
@@ -499,12 +607,99 @@ impl ArchivalState {
             }
         };
 
+        // Headers of pruned blocks are kept, but the blocks themselves are not
+        if record.file_location.file_index < self.first_unpruned_block_file().await {
+            return Ok(None);
+        }
+
         // Fetch block from disk
         let block = self.get_block_from_block_record(record).await?;
 
         Ok(Some(block))
     }
 
+    /// Return the index of the first block file that has not been deleted by
+    /// pruning. All block files are present iff this is 0.
+    pub async fn first_unpruned_block_file(&self) -> u32 {
+        self.block_index_db
+            .get(BlockIndexKey::FirstUnprunedFile)
+            .await
+            .map(|x| x.as_first_unpruned_file())
+            .unwrap_or_default()
+    }
+
+    /// Delete the files of blocks that are more than `prune_depth` blocks
+    /// below `tip_height`, to bound disk usage.
+    ///
+    /// Block headers remain in the block index and the archival mutator set is
+    /// unaffected, but the pruned blocks can no longer be read:
+    /// [`Self::get_block`] returns `None` for them. Consequently, reorganizations
+    /// deeper than `prune_depth` cannot be handled.
+    ///
+    /// Files are deleted in the order they were written, and only once all
+    /// their blocks are below the retention depth. The file currently being
+    /// written to is never deleted. Returns the number of files deleted.
+    pub async fn prune_block_files(
+        &mut self,
+        tip_height: BlockHeight,
+        prune_depth: u64,
+    ) -> Result<u32> {
+        let Some(lowest_retained_height) = u64::from(tip_height).checked_sub(prune_depth) else {
+            return Ok(0);
+        };
+
+        let last_file = self
+            .block_index_db
+            .get(BlockIndexKey::LastFile)
+            .await
+            .map(|x| x.as_last_file_record())
+            .unwrap_or_default()
+            .last_file;
+        let first_unpruned_file = self.first_unpruned_block_file().await;
+
+        let mut new_first_unpruned_file = first_unpruned_file;
+        while new_first_unpruned_file < last_file {
+            let Some(file_record) = self
+                .block_index_db
+                .get(BlockIndexKey::File(new_first_unpruned_file))
+                .await
+                .map(|x| x.as_file_record())
+            else {
+                break;
+            };
+            if u64::from(file_record.max_block_height) >= lowest_retained_height {
+                break;
+            }
+            new_first_unpruned_file += 1;
+        }
+
+        if new_first_unpruned_file == first_unpruned_file {
+            return Ok(0);
+        }
+
+        // Persist the pruning boundary before deleting any file, such that the
+        // block index never points into a deleted file.
+        self.block_index_db
+            .put(
+                BlockIndexKey::FirstUnprunedFile,
+                BlockIndexValue::FirstUnprunedFile(new_first_unpruned_file),
+            )
+            .await;
+        self.block_index_db.flush().await;
+
+        for file_index in first_unpruned_file..new_first_unpruned_file {
+            let block_file_path = self.data_dir.block_file_path(file_index);
+            debug!("Pruning block file: {}", block_file_path.display());
+            match tokio::fs::remove_file(&block_file_path).await {
+                Ok(()) => (),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(new_first_unpruned_file - first_unpruned_file)
+    }
+
     /// Return the number of blocks with the given height
     async fn block_height_to_block_count(&self, height: BlockHeight) -> usize {
         match self
@@ -576,6 +771,41 @@ impl ArchivalState {
         None
     }
 
+    /// Return the digest of the last canonical block with a timestamp at or
+    /// before `timestamp`, or None if the genesis block is younger.
+    ///
+    /// Block timestamps increase along a chain, so this is a binary search
+    /// over heights.
+    pub async fn canonical_block_digest_at_or_before(
+        &self,
+        timestamp: Timestamp,
+        tip_digest: Digest,
+    ) -> Option<Digest> {
+        if self.genesis_block.header().timestamp > timestamp {
+            return None;
+        }
+
+        // invariant: the canonical block at height `low` is at or before
+        // `timestamp`, and those above `high` are after it.
+        let tip_header = self.get_block_header(tip_digest).await?;
+        let mut low = 0u64;
+        let mut high = u64::from(tip_header.height);
+        while low < high {
+            let mid = low + (high - low + 1) / 2;
+            let digest = self
+                .block_height_to_canonical_block_digest(mid.into(), tip_digest)
+                .await?;
+            if self.get_block_header(digest).await?.timestamp <= timestamp {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+
+        self.block_height_to_canonical_block_digest(low.into(), tip_digest)
+            .await
+    }
+
     pub async fn get_children_block_headers(
         &self,
         parent_block_digest: Digest,
@@ -764,6 +994,73 @@ impl ArchivalState {
         ))
     }
 
+    /// Number of addition records in the mutator set as of the given
+    /// canonical block, or `None` if that block was pruned and is not covered
+    /// by the mutator set history.
+    async fn addition_record_count_as_of(&self, block_digest: Digest) -> Option<u64> {
+        let height = self.get_block_header(block_digest).await?.height;
+        if let Some(snapshot) = self.archival_mutator_set.snapshot_as_of(height).await {
+            return Some(snapshot.aocl_leaf_count);
+        }
+
+        let block = self.get_block(block_digest).await.ok()??;
+        Some(block.body().mutator_set_accumulator.aocl.num_leafs())
+    }
+
+    /// Compute statistics of the UTXO set as of the tip, from the archival
+    /// mutator set. Reads all inactive chunks of the sliding-window Bloom
+    /// filter.
+    pub async fn utxo_set_stats(&self) -> UtxoSetStats {
+        let tip_digest = self.get_tip_digest().await;
+        let tip_header = self
+            .get_block_header(tip_digest)
+            .await
+            .expect("tip header must be known");
+
+        let ams = self.archival_mutator_set.ams();
+        let addition_record_count = ams.aocl.num_leafs().await;
+        let active_window = SwbfIndexCounts::of_sorted(&ams.swbf_active.sbf);
+        let chunks = ams.chunks.get_all().await;
+        let inactive_chunks = SwbfIndexCounts::of_chunks(&chunks);
+
+        let mut addition_record_ages = vec![];
+        for max_age_days in ADDITION_RECORD_AGES_IN_DAYS {
+            let max_age = Timestamp::days(max_age_days as usize);
+            let older_block = match tip_header
+                .timestamp
+                .to_millis()
+                .checked_sub(max_age.to_millis())
+            {
+                Some(cutoff) => {
+                    self.canonical_block_digest_at_or_before(Timestamp::millis(cutoff), tip_digest)
+                        .await
+                }
+                None => None,
+            };
+            let count = match older_block {
+                // all blocks are younger than the cutoff
+                None => Some(addition_record_count),
+                Some(digest) => self
+                    .addition_record_count_as_of(digest)
+                    .await
+                    .map(|older| addition_record_count - older),
+            };
+            addition_record_ages.push(AdditionRecordAge {
+                max_age_days,
+                count,
+            });
+        }
+
+        UtxoSetStats::new(
+            tip_header.height,
+            addition_record_count,
+            active_window,
+            inactive_chunks,
+            chunks.len() as u64,
+            addition_record_ages,
+        )
+    }
+
     /// Update the mutator set with a block after this block has been stored to the database.
     /// Handles rollback of the mutator set if needed but requires that all blocks that are
     /// rolled back are present in the DB. The input block is considered chain tip. All blocks
@@ -834,8 +1131,21 @@ impl ArchivalState {
                     .revert_remove(removal_record)
                     .await;
             }
+
+            let touched_chunks = RustyArchivalMutatorSet::touched_chunk_indices(
+                &roll_back_block.kernel.body.transaction_kernel.inputs,
+            );
+            self.archival_mutator_set
+                .revert_history(roll_back_block.kernel.header.height, &touched_chunks)
+                .await;
         }
 
+        // Start recording the SWBF history, if not recording already
+        let synced_height = u64::from(new_block.kernel.header.height) - forwards.len() as u64;
+        self.archival_mutator_set
+            .ensure_history(synced_height.into())
+            .await;
+
         for digest in forwards {
             // Add block to mutator set
             let apply_forward_block = if digest == new_block.hash() {
@@ -899,6 +1209,13 @@ impl ArchivalState {
                     .remove(removal_record)
                     .await;
             }
+
+            let touched_chunks = RustyArchivalMutatorSet::touched_chunk_indices(
+                &apply_forward_block.kernel.body.transaction_kernel.inputs,
+            );
+            self.archival_mutator_set
+                .record_history(apply_forward_block.kernel.header.height, &touched_chunks)
+                .await;
         }
 
         // Sanity check that archival mutator set has been updated consistently with the new block
@@ -924,6 +1241,9 @@ impl ArchivalState {
 
 #[cfg(test)]
 mod archival_state_tests {
+    use std::panic::AssertUnwindSafe;
+
+    use futures::FutureExt;
     use rand::rngs::StdRng;
     use rand::thread_rng;
     use rand::Rng;
@@ -947,6 +1267,8 @@ mod archival_state_tests {
     use crate::models::proof_abstractions::tasm::program::TritonProverSync;
     use crate::models::proof_abstractions::timestamp::Timestamp;
     use crate::models::state::archival_state::ArchivalState;
+    use crate::models::state::db_diagnostics::DbIssue;
+    use crate::models::state::db_diagnostics::STARTUP_CHECK_DEPTH;
     use crate::models::state::tx_proving_capability::TxProvingCapability;
     use crate::models::state::wallet::expected_utxo::UtxoNotifier;
     use crate::models::state::wallet::WalletSecret;
@@ -958,6 +1280,8 @@ mod archival_state_tests {
     use crate::tests::shared::mock_genesis_global_state;
     use crate::tests::shared::mock_genesis_wallet_state;
     use crate::tests::shared::unit_test_databases;
+    use crate::util_types::fault_injection;
+    use crate::util_types::fault_injection::FaultPoint;
     use crate::util_types::test_shared::mutator_set::mock_item_mp_rr_for_init_msa;
 
     async fn make_test_archival_state(network: Network) -> ArchivalState {
@@ -1088,6 +1412,139 @@ mod archival_state_tests {
         Ok(())
     }
 
+    /// Open the archival state stored in `data_dir`, as on a restart.
+    async fn reopen_archival_state(data_dir: &DataDirectory, network: Network) -> ArchivalState {
+        let block_index_db = ArchivalState::initialize_block_index_database(data_dir)
+            .await
+            .unwrap();
+        let ams = ArchivalState::initialize_mutator_set(data_dir)
+            .await
+            .unwrap();
+
+        ArchivalState::new(data_dir.to_owned(), block_index_db, ams, network).await
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn crash_before_mutator_set_write_is_detected_and_recovered() {
+        let network = Network::RegTest;
+        let (mut archival_state, _peer_db_lock, data_dir) =
+            mock_genesis_archival_state(network).await;
+        let genesis = archival_state.genesis_block().clone();
+        let address = WalletSecret::new_random()
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+        let block_1 = make_mock_block(&genesis, None, address, rand::random()).0;
+
+        // Crash after the block is stored as tip, but before the mutator set
+        // is updated.
+        fault_injection::arm(
+            FaultPoint::DbWrite {
+                db_path: data_dir.mutator_set_database_dir_path(),
+            },
+            0,
+        );
+        let crashed = AssertUnwindSafe(async {
+            archival_state.write_block_as_tip(&block_1).await.unwrap();
+            archival_state.update_mutator_set(&block_1).await.unwrap();
+        })
+        .catch_unwind()
+        .await;
+        assert!(crashed.is_err());
+        drop(archival_state);
+
+        let mut archival_state = reopen_archival_state(&data_dir, network).await;
+        assert_eq!(block_1.hash(), archival_state.get_tip_digest().await);
+        assert_eq!(
+            vec![DbIssue::MutatorSetNotSyncedToTip {
+                tip: block_1.hash(),
+                sync_label: genesis.hash(),
+            }],
+            archival_state.check_consistency(STARTUP_CHECK_DEPTH).await
+        );
+
+        // Replaying the block brings the mutator set back in sync with the tip.
+        archival_state.update_mutator_set(&block_1).await.unwrap();
+        assert!(archival_state
+            .check_consistency(STARTUP_CHECK_DEPTH)
+            .await
+            .is_empty());
+        assert_eq!(
+            block_1.kernel.body.mutator_set_accumulator.hash(),
+            archival_state
+                .archival_mutator_set
+                .ams()
+                .accumulator()
+                .await
+                .hash()
+        );
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn crash_before_block_index_write_leaves_previous_tip() {
+        let network = Network::RegTest;
+        let (mut archival_state, _peer_db_lock, data_dir) =
+            mock_genesis_archival_state(network).await;
+        let genesis = archival_state.genesis_block().clone();
+        let address = WalletSecret::new_random()
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+        let block_1 = make_mock_block(&genesis, None, address, rand::random()).0;
+        let block_2 = make_mock_block(&block_1, None, address, rand::random()).0;
+        add_block_to_archival_state(&mut archival_state, block_1.clone())
+            .await
+            .unwrap();
+
+        // Crash after block 2 is written to its block file, but before it is
+        // recorded in the block index.
+        fault_injection::arm(
+            FaultPoint::DbWrite {
+                db_path: data_dir.block_index_database_dir_path(),
+            },
+            0,
+        );
+        let crashed = AssertUnwindSafe(add_block_to_archival_state(
+            &mut archival_state,
+            block_2.clone(),
+        ))
+        .catch_unwind()
+        .await;
+        assert!(crashed.is_err());
+        drop(archival_state);
+
+        // The partially applied block is rolled back: the block index and
+        // the mutator set are both still at block 1.
+        let mut archival_state = reopen_archival_state(&data_dir, network).await;
+        assert_eq!(block_1.hash(), archival_state.get_tip_digest().await);
+        assert!(archival_state
+            .get_block_header(block_2.hash())
+            .await
+            .is_none());
+        assert!(archival_state
+            .check_consistency(STARTUP_CHECK_DEPTH)
+            .await
+            .is_empty());
+
+        // The block can be applied again after the restart.
+        add_block_to_archival_state(&mut archival_state, block_2.clone())
+            .await
+            .unwrap();
+        assert_eq!(block_2.hash(), archival_state.get_tip_digest().await);
+        assert_eq!(
+            block_2,
+            archival_state
+                .get_block(block_2.hash())
+                .await
+                .unwrap()
+                .unwrap()
+        );
+        assert!(archival_state
+            .check_consistency(STARTUP_CHECK_DEPTH)
+            .await
+            .is_empty());
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn update_mutator_set_db_write_test() -> Result<()> {
@@ -1224,6 +1681,52 @@ mod archival_state_tests {
         Ok(())
     }
 
+    #[traced_test]
+    #[tokio::test]
+    async fn mutator_set_history_matches_blocks_across_rollback() -> Result<()> {
+        let mut rng = thread_rng();
+        let network = Network::Alpha;
+        let (mut archival_state, _peer_db_lock, _data_dir) =
+            mock_genesis_archival_state(network).await;
+        let own_receiving_address = WalletSecret::new_random()
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+
+        let mut blocks = vec![archival_state.genesis_block.as_ref().clone()];
+        for _ in 0..3 {
+            let (block, _, _) = make_mock_block_with_valid_pow(
+                blocks.last().unwrap(),
+                None,
+                own_receiving_address,
+                rng.gen(),
+            );
+            archival_state.write_block_as_tip(&block).await?;
+            archival_state.update_mutator_set(&block).await?;
+            blocks.push(block);
+        }
+
+        // Fork off block 1, rolling back blocks 2 and 3
+        let (block_2b, _, _) =
+            make_mock_block_with_valid_pow(&blocks[1], None, own_receiving_address, rng.gen());
+        archival_state.write_block_as_tip(&block_2b).await?;
+        archival_state.update_mutator_set(&block_2b).await?;
+        blocks.truncate(2);
+        blocks.push(block_2b);
+
+        let ams = &archival_state.archival_mutator_set;
+        for block in &blocks {
+            let height = block.kernel.header.height;
+            assert_eq!(
+                block.kernel.body.mutator_set_accumulator.hash(),
+                ams.accumulator_as_of(height).await.unwrap().hash(),
+                "historical mutator set must match block of height {height}"
+            );
+        }
+        assert!(ams.accumulator_as_of(3u64.into()).await.is_none());
+
+        Ok(())
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn update_mutator_set_rollback_ms_block_sync_multiple_inputs_outputs_in_block_test() {
@@ -1501,7 +2004,7 @@ mod archival_state_tests {
             .wallet_state
             .wallet_secret
             .nth_symmetric_key_for_tests(0);
-        let (tx_to_alice_and_bob, change_utxo) = genesis
+        let (tx_to_alice_and_bob, change_utxos) = genesis
             .lock_guard()
             .await
             .create_transaction_with_prover_capability(
@@ -1563,7 +2066,7 @@ mod archival_state_tests {
             let mut genesis_state = genesis.lock_guard_mut().await;
             let expected_utxos = genesis_state
                 .wallet_state
-                .extract_expected_utxos(vec![change_utxo.unwrap()].into(), UtxoNotifier::Cli);
+                .extract_expected_utxos(change_utxos.into(), UtxoNotifier::Cli);
             genesis_state
                 .wallet_state
                 .add_expected_utxos(expected_utxos)
@@ -1686,7 +2189,7 @@ mod archival_state_tests {
             .await
             .unwrap();
         assert!(
-            alice_change.is_none(),
+            alice_change.is_empty(),
             "no change when consuming entire balance"
         );
         let outputs_from_bob: TxOutputList = vec![
@@ -1729,7 +2232,7 @@ mod archival_state_tests {
             .await
             .unwrap();
         assert!(
-            bob_change.is_none(),
+            bob_change.is_empty(),
             "no change when consuming entire balance"
         );
 
@@ -1933,6 +2436,57 @@ mod archival_state_tests {
         Ok(())
     }
 
+    #[traced_test]
+    #[tokio::test]
+    async fn canonical_block_digest_at_or_before_test() -> Result<()> {
+        let network = Network::RegTest;
+        let mut archival_state = make_test_archival_state(network).await;
+        let genesis = *archival_state.genesis_block.clone();
+        let mut rng = thread_rng();
+        let own_receiving_address = WalletSecret::new_random()
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+
+        let mut blocks = vec![genesis.clone()];
+        for _ in 0..5 {
+            let (block, _, _) = make_mock_block_with_valid_pow(
+                blocks.last().unwrap(),
+                None,
+                own_receiving_address,
+                rng.gen(),
+            );
+            add_block_to_archival_state(&mut archival_state, block.clone()).await?;
+            blocks.push(block);
+        }
+        let tip_digest = blocks.last().unwrap().hash();
+
+        let before_genesis = genesis.header().timestamp - Timestamp::millis(1);
+        assert!(archival_state
+            .canonical_block_digest_at_or_before(before_genesis, tip_digest)
+            .await
+            .is_none());
+        for block in &blocks {
+            let timestamp = block.header().timestamp;
+            assert_eq!(
+                Some(block.hash()),
+                archival_state
+                    .canonical_block_digest_at_or_before(timestamp, tip_digest)
+                    .await
+            );
+            assert_eq!(
+                Some(block.hash()),
+                archival_state
+                    .canonical_block_digest_at_or_before(
+                        timestamp + MINIMUM_BLOCK_TIME - Timestamp::millis(1),
+                        tip_digest
+                    )
+                    .await
+            );
+        }
+
+        Ok(())
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn get_block_test() -> Result<()> {
@@ -2017,6 +2571,128 @@ mod archival_state_tests {
         Ok(())
     }
 
+    #[traced_test]
+    #[tokio::test]
+    async fn blocks_since_mutator_set_test() -> Result<()> {
+        let mut rng = thread_rng();
+        let network = Network::Alpha;
+        let mut archival_state = make_test_archival_state(network).await;
+        let own_receiving_address = WalletSecret::new_random()
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+
+        let genesis = *archival_state.genesis_block.clone();
+        let (block_1, _, _) =
+            make_mock_block_with_valid_pow(&genesis, None, own_receiving_address, rng.gen());
+        let (block_2, _, _) =
+            make_mock_block_with_valid_pow(&block_1, None, own_receiving_address, rng.gen());
+        add_block_to_archival_state(&mut archival_state, block_1.clone()).await?;
+        add_block_to_archival_state(&mut archival_state, block_2.clone()).await?;
+
+        let genesis_msa = genesis.body().mutator_set_accumulator.clone();
+        assert_eq!(
+            Some((genesis_msa.clone(), vec![block_1.clone(), block_2.clone()])),
+            archival_state
+                .blocks_since_mutator_set(genesis_msa.hash(), 2)
+                .await?
+        );
+        assert_eq!(
+            None,
+            archival_state
+                .blocks_since_mutator_set(genesis_msa.hash(), 1)
+                .await?
+        );
+
+        let tip_msa = block_2.body().mutator_set_accumulator.clone();
+        assert_eq!(
+            Some((tip_msa.clone(), vec![])),
+            archival_state
+                .blocks_since_mutator_set(tip_msa.hash(), 0)
+                .await?
+        );
+        assert_eq!(
+            None,
+            archival_state
+                .blocks_since_mutator_set(Digest::default(), 10)
+                .await?
+        );
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn prune_block_files_test() -> Result<()> {
+        let mut rng = thread_rng();
+        let network = Network::Alpha;
+        let mut archival_state = make_test_archival_state(network).await;
+        let own_receiving_address = WalletSecret::new_random()
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+
+        let genesis = *archival_state.genesis_block.clone();
+        let (block_1, _, _) =
+            make_mock_block_with_valid_pow(&genesis, None, own_receiving_address, rng.gen());
+        let (block_2, _, _) =
+            make_mock_block_with_valid_pow(&block_1, None, own_receiving_address, rng.gen());
+        let (block_3, _, _) =
+            make_mock_block_with_valid_pow(&block_2, None, own_receiving_address, rng.gen());
+        add_block_to_archival_state(&mut archival_state, block_1.clone()).await?;
+        add_block_to_archival_state(&mut archival_state, block_2.clone()).await?;
+
+        // Store the next block in a new file, as if the first one were full
+        archival_state
+            .block_index_db
+            .put(
+                BlockIndexKey::LastFile,
+                BlockIndexValue::LastFile(LastFileRecord { last_file: 1 }),
+            )
+            .await;
+        add_block_to_archival_state(&mut archival_state, block_3.clone()).await?;
+
+        // Block 2 is within the retention depth, so its file is kept
+        assert_eq!(
+            0,
+            archival_state
+                .prune_block_files(block_3.header().height, 1)
+                .await?
+        );
+        assert!(archival_state.get_block(block_1.hash()).await?.is_some());
+
+        // Once all its blocks are below the retention depth, the file is pruned
+        assert_eq!(
+            1,
+            archival_state
+                .prune_block_files(block_3.header().height, 0)
+                .await?
+        );
+        assert_eq!(1, archival_state.first_unpruned_block_file().await);
+        assert!(!archival_state.data_dir.block_file_path(0).exists());
+        assert!(archival_state.get_block(block_1.hash()).await?.is_none());
+        assert!(archival_state.get_block(block_2.hash()).await?.is_none());
+        assert_eq!(
+            block_1.header(),
+            &archival_state
+                .get_block_header(block_1.hash())
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            block_3,
+            archival_state.get_block(block_3.hash()).await?.unwrap()
+        );
+
+        // Pruning again is a no-op
+        assert_eq!(
+            0,
+            archival_state
+                .prune_block_files(block_3.header().height, 0)
+                .await?
+        );
+
+        Ok(())
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn ms_update_to_tip_genesis() {
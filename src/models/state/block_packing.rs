@@ -0,0 +1,340 @@
+//! Selection of mempool transactions for a block template.
+//!
+//! The block transaction merges the selected transactions and the coinbase
+//! transaction into one transaction with a single proof. Transaction proofs
+//! are not part of the block, so a selected transaction takes up block space
+//! with its kernel only, while the block proof takes up a fixed amount. Every
+//! transaction not already merged into the previous template costs one merger,
+//! which produces a new single proof and delays mining the template, so the
+//! number of mergers per template can be capped.
+//!
+//! Maximizing the fees of the selection under the block size limit and the
+//! merger cap is a knapsack problem. It is solved exactly for the most
+//! fee-dense candidates, over kernel sizes rounded up to a fraction of the
+//! capacity, and any space left is filled greedily. Packing by fee density
+//! alone, as the mempool orders its transactions, is also tried, and the
+//! better selection wins, so packing never earns less than the greedy policy.
+
+use itertools::Itertools;
+use tasm_lib::twenty_first::math::bfield_codec::BFieldCodec;
+
+use crate::models::blockchain::block::Block;
+use crate::models::blockchain::block::BlockProof;
+use crate::models::blockchain::block::MAX_BLOCK_SIZE;
+use crate::models::blockchain::transaction::Transaction;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use crate::models::state::transaction_kernel_id::TransactionKernelId;
+
+/// Block space, in number of `BFieldElement`s, reserved for the kernel of the
+/// coinbase transaction, which is merged last.
+const COINBASE_KERNEL_RESERVE: usize = 2_000;
+
+/// Number of most fee-dense candidates for which the optimal selection is
+/// computed.
+const MAX_KNAPSACK_CANDIDATES: usize = 64;
+
+/// Number of units the capacity is divided into for the knapsack. Kernel sizes
+/// are rounded up to whole units.
+const KNAPSACK_CAPACITY_UNITS: usize = 1024;
+
+/// A mempool transaction considered for a block template.
+#[derive(Debug, Clone)]
+pub(crate) struct PackingCandidate {
+    pub(crate) txid: TransactionKernelId,
+    pub(crate) fee: NeptuneCoins,
+
+    /// Size of the transaction kernel, in number of `BFieldElement`s
+    pub(crate) kernel_size: usize,
+
+    /// Size of the transaction proof, in number of `BFieldElement`s
+    pub(crate) proof_size: usize,
+
+    /// Whether the transaction is merged into the template already, such that
+    /// including it takes no merger
+    pub(crate) merged: bool,
+}
+
+impl PackingCandidate {
+    pub(crate) fn new(transaction: &Transaction, merged: bool) -> Self {
+        Self {
+            txid: transaction.kernel.txid(),
+            fee: transaction.kernel.fee,
+            kernel_size: transaction.kernel.encode().len(),
+            proof_size: transaction.proof.encode().len(),
+            merged,
+        }
+    }
+
+    fn value(&self) -> f64 {
+        self.fee.to_nau_f64()
+    }
+
+    fn fee_density(&self) -> f64 {
+        self.value() / self.kernel_size.max(1) as f64
+    }
+
+    fn num_mergers(&self) -> usize {
+        usize::from(!self.merged)
+    }
+}
+
+/// Packs transactions into the template for a block on top of a given
+/// predecessor.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BlockPacker {
+    /// Size of the predecessor without its transaction kernel, as an estimate
+    /// of the size of everything but the kernel in the new block
+    overhead: usize,
+
+    /// Whether the overhead includes a block proof
+    overhead_includes_proof: bool,
+
+    max_mergers: Option<usize>,
+}
+
+impl BlockPacker {
+    pub(crate) fn new(predecessor: &Block, max_mergers: Option<usize>) -> Self {
+        let kernel_size = predecessor.body().transaction_kernel.encode().len();
+        Self {
+            overhead: predecessor.size().saturating_sub(kernel_size),
+            overhead_includes_proof: matches!(predecessor.proof, BlockProof::SingleProof(_)),
+            max_mergers,
+        }
+    }
+
+    /// Block space available to the kernels of the selected transactions. The
+    /// predecessor of a block without a block proof is the genesis block, in
+    /// which case the block proof is assumed to be as large as the largest
+    /// transaction proof.
+    fn capacity(&self, candidates: &[PackingCandidate]) -> usize {
+        let proof_reserve = if self.overhead_includes_proof {
+            0
+        } else {
+            candidates
+                .iter()
+                .map(|candidate| candidate.proof_size)
+                .max()
+                .unwrap_or_default()
+        };
+
+        MAX_BLOCK_SIZE.saturating_sub(self.overhead + COINBASE_KERNEL_RESERVE + proof_reserve)
+    }
+
+    /// Select the transactions to include, maximizing their total fee.
+    pub(crate) fn pack(&self, mut candidates: Vec<PackingCandidate>) -> Vec<TransactionKernelId> {
+        let capacity = self.capacity(&candidates);
+        candidates.sort_by(|a, b| b.fee_density().total_cmp(&a.fee_density()));
+
+        let greedy = fill_greedily(&candidates, vec![], capacity, self.max_mergers);
+        let knapsack = knapsack(&candidates, capacity, self.max_mergers);
+        let selection = if revenue(&candidates, &knapsack) >= revenue(&candidates, &greedy) {
+            knapsack
+        } else {
+            greedy
+        };
+
+        selection
+            .into_iter()
+            .map(|index| candidates[index].txid)
+            .collect()
+    }
+}
+
+fn revenue(candidates: &[PackingCandidate], selection: &[usize]) -> f64 {
+    selection
+        .iter()
+        .map(|&index| candidates[index].value())
+        .sum()
+}
+
+/// Add candidates, in order, to the selection as long as they fit.
+fn fill_greedily(
+    candidates: &[PackingCandidate],
+    mut selection: Vec<usize>,
+    capacity: usize,
+    max_mergers: Option<usize>,
+) -> Vec<usize> {
+    let mut size = selection
+        .iter()
+        .map(|&index| candidates[index].kernel_size)
+        .sum::<usize>();
+    let mut num_mergers = selection
+        .iter()
+        .map(|&index| candidates[index].num_mergers())
+        .sum::<usize>();
+    let max_mergers = max_mergers.unwrap_or(usize::MAX);
+
+    for (index, candidate) in candidates.iter().enumerate() {
+        if selection.contains(&index)
+            || size + candidate.kernel_size > capacity
+            || num_mergers + candidate.num_mergers() > max_mergers
+        {
+            continue;
+        }
+
+        size += candidate.kernel_size;
+        num_mergers += candidate.num_mergers();
+        selection.push(index);
+    }
+
+    selection
+}
+
+/// Select the most valuable subset of the first [`MAX_KNAPSACK_CANDIDATES`]
+/// candidates by dynamic programming over rounded-up sizes and number of
+/// mergers, then fill any space left with the remaining candidates.
+fn knapsack(
+    candidates: &[PackingCandidate],
+    capacity: usize,
+    max_mergers: Option<usize>,
+) -> Vec<usize> {
+    let exact = &candidates[..candidates.len().min(MAX_KNAPSACK_CANDIDATES)];
+    let unit = capacity.div_ceil(KNAPSACK_CAPACITY_UNITS).max(1);
+    let num_units = capacity / unit;
+    let weight = |candidate: &PackingCandidate| candidate.kernel_size.div_ceil(unit).max(1);
+    let merger_budget = exact
+        .iter()
+        .map(PackingCandidate::num_mergers)
+        .sum::<usize>()
+        .min(max_mergers.unwrap_or(usize::MAX));
+
+    // best[m][w] is the highest value of the candidates considered so far,
+    // using at most m mergers and w units
+    let mut best = vec![vec![0.0; num_units + 1]; merger_budget + 1];
+    let mut taken = vec![vec![vec![false; num_units + 1]; merger_budget + 1]; exact.len()];
+    for (index, candidate) in exact.iter().enumerate() {
+        let (weight, num_mergers) = (weight(candidate), candidate.num_mergers());
+        if weight > num_units {
+            continue;
+        }
+
+        for m in (num_mergers..=merger_budget).rev() {
+            for w in (weight..=num_units).rev() {
+                let with_candidate = best[m - num_mergers][w - weight] + candidate.value();
+                if with_candidate > best[m][w] {
+                    best[m][w] = with_candidate;
+                    taken[index][m][w] = true;
+                }
+            }
+        }
+    }
+
+    let (mut m, mut w) = (merger_budget, num_units);
+    let mut selection = vec![];
+    for (index, candidate) in exact.iter().enumerate().rev() {
+        if taken[index][m][w] {
+            selection.push(index);
+            m -= candidate.num_mergers();
+            w -= weight(candidate);
+        }
+    }
+    let selection = selection.into_iter().rev().collect_vec();
+
+    fill_greedily(candidates, selection, capacity, max_mergers)
+}
+
+#[cfg(test)]
+mod block_packing_tests {
+    use rand::thread_rng;
+    use rand::Rng;
+    use tasm_lib::twenty_first::math::b_field_element::BFieldElement;
+    use tasm_lib::twenty_first::math::digest::Digest;
+
+    use super::*;
+
+    fn candidate(id: u64, fee: u32, kernel_size: usize) -> PackingCandidate {
+        PackingCandidate {
+            txid: Digest::new([BFieldElement::new(id); Digest::LEN])
+                .to_hex()
+                .parse()
+                .unwrap(),
+            fee: NeptuneCoins::new(fee),
+            kernel_size,
+            proof_size: 10_000,
+            merged: false,
+        }
+    }
+
+    fn packer(capacity: usize, max_mergers: Option<usize>) -> BlockPacker {
+        BlockPacker {
+            overhead: MAX_BLOCK_SIZE - COINBASE_KERNEL_RESERVE - capacity,
+            overhead_includes_proof: true,
+            max_mergers,
+        }
+    }
+
+    /// Revenue of the current policy: fee-dense transactions first
+    fn greedy_revenue(candidates: &[PackingCandidate], packer: BlockPacker) -> f64 {
+        let mut candidates = candidates.to_vec();
+        candidates.sort_by(|a, b| b.fee_density().total_cmp(&a.fee_density()));
+        let selection = fill_greedily(
+            &candidates,
+            vec![],
+            packer.capacity(&candidates),
+            packer.max_mergers,
+        );
+        revenue(&candidates, &selection)
+    }
+
+    fn packed_revenue(candidates: &[PackingCandidate], packer: BlockPacker) -> f64 {
+        let selection = packer.pack(candidates.to_vec());
+        let selected = candidates
+            .iter()
+            .filter(|candidate| selection.contains(&candidate.txid))
+            .collect_vec();
+        assert_eq!(selection.len(), selected.len());
+
+        let size = selected.iter().map(|c| c.kernel_size).sum::<usize>();
+        assert!(size <= packer.capacity(candidates), "selection must fit");
+        let num_mergers = selected.iter().map(|c| c.num_mergers()).sum::<usize>();
+        assert!(num_mergers <= packer.max_mergers.unwrap_or(usize::MAX));
+
+        selected.iter().map(|c| c.value()).sum()
+    }
+
+    #[test]
+    fn packing_fills_space_fee_density_leaves_unused() {
+        // The densest transaction keeps out the two that fill the block.
+        let mut candidates = [
+            candidate(0, 60, 5_100),
+            candidate(1, 55, 5_000),
+            candidate(2, 55, 5_000),
+        ];
+        let coins = |amount| NeptuneCoins::new(amount).to_nau_f64();
+        let unlimited = packer(10_000, None);
+        assert_eq!(coins(60), greedy_revenue(&candidates, unlimited));
+        assert_eq!(coins(110), packed_revenue(&candidates, unlimited));
+
+        // with one merger, the most valuable transaction wins
+        let one_merger = packer(10_000, Some(1));
+        assert_eq!(coins(60), packed_revenue(&candidates, one_merger));
+
+        // transactions merged already take no merger
+        candidates[1].merged = true;
+        assert_eq!(coins(110), packed_revenue(&candidates, one_merger));
+    }
+
+    #[test]
+    fn packing_never_earns_less_than_fee_density_on_synthetic_mempools() {
+        let mut rng = thread_rng();
+        let mut total_gain = 0.0;
+        for _ in 0..50 {
+            let num_transactions = rng.gen_range(1..150);
+            let candidates = (0..num_transactions)
+                .map(|id| candidate(id, rng.gen_range(0..100), rng.gen_range(500..20_000)))
+                .collect_vec();
+            let max_mergers = rng.gen_bool(0.5).then(|| rng.gen_range(1..40));
+            let packer = packer(rng.gen_range(10_000..200_000), max_mergers);
+
+            let greedy = greedy_revenue(&candidates, packer);
+            let packed = packed_revenue(&candidates, packer);
+            assert!(packed >= greedy);
+            total_gain += packed - greedy;
+        }
+
+        assert!(
+            total_gain > 0.0,
+            "packing must beat fee density on some mempools"
+        );
+    }
+}
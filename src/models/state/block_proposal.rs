@@ -0,0 +1,440 @@
+//! Block templates served to external mining software.
+//!
+//! With `--external-mining`, the mining task builds block templates as usual
+//! but does not search for a nonce itself. Instead, each template is published
+//! here as a proposal, which external miners fetch over RPC, and a solution
+//! for it, a nonce and optionally a different timestamp, is submitted back
+//! over RPC. A few of the latest proposals are kept, such that a solution for
+//! a template that was just replaced, say because a transaction was added,
+//! is still accepted.
+//!
+//! Miners that already hold a template can poll for updates instead of whole
+//! templates. Proposal ids are sequence numbers: a miner quotes the id of the
+//! template it holds, and gets back only the header fields and digests that
+//! changed, if the node still knows that template and the newest one builds
+//! on the same block. Otherwise it gets the whole newest template.
+
+use std::collections::VecDeque;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tasm_lib::twenty_first::math::b_field_element::BFieldElement;
+use tasm_lib::twenty_first::math::bfield_codec::BFieldCodec;
+use tasm_lib::twenty_first::math::digest::Digest;
+use thiserror::Error;
+
+use super::mining_log::BlockTemplateInfo;
+use super::wallet::expected_utxo::ExpectedUtxo;
+use crate::config_models::network_params::NetworkParams;
+use crate::models::blockchain::block::block_header::BlockHeader;
+use crate::models::blockchain::block::block_header::MINIMUM_BLOCK_TIME;
+use crate::models::blockchain::block::difficulty_control::difficulty_control;
+use crate::models::blockchain::block::difficulty_control::Difficulty;
+use crate::models::blockchain::block::Block;
+use crate::models::blockchain::block::FUTUREDATING_LIMIT;
+use crate::models::channel::NewBlockFound;
+use crate::models::proof_abstractions::mast_hash::MastHash;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::util_types::json_schema::BFieldElementSchema;
+use crate::util_types::json_schema::DigestSchema;
+
+/// Number of latest proposals for which solutions are accepted.
+const MAX_NUM_PROPOSALS: usize = 4;
+
+/// A block template, as served to external mining software.
+///
+/// The block digest is the MAST hash of the header, the body, and the
+/// appendix, and must not exceed `target`. Miners vary the header's nonce,
+/// and may vary its timestamp within the given range, which also changes the
+/// header's difficulty field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct BlockTemplate {
+    pub proposal_id: u64,
+    pub header: BlockHeader,
+
+    /// `BFieldCodec` encoding of the block body
+    #[schemars(with = "Vec<BFieldElementSchema>")]
+    pub body: Vec<BFieldElement>,
+
+    /// `BFieldCodec` encoding of the block appendix
+    #[schemars(with = "Vec<BFieldElementSchema>")]
+    pub appendix: Vec<BFieldElement>,
+
+    /// The block digest must be less than or equal to this
+    #[schemars(with = "DigestSchema")]
+    pub target: Digest,
+
+    /// Earliest timestamp the block may have
+    pub min_timestamp: Timestamp,
+
+    /// Timestamp from which on peers reject the block as future-dated, as of
+    /// the time the template was served
+    pub max_timestamp: Timestamp,
+}
+
+/// The changes from one block template to a later one building on the same
+/// block. Within the block's MAST, only these leaves differ: the body digest
+/// because transactions were added or removed, the timestamp, and with it the
+/// difficulty. The appendix is included only if it changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct BlockTemplateDelta {
+    /// Proposal id of the template the changes apply to
+    pub base_proposal_id: u64,
+
+    /// Proposal id of the template after the changes
+    pub proposal_id: u64,
+
+    pub timestamp: Timestamp,
+    pub difficulty: Difficulty,
+
+    /// MAST hash of the block body
+    #[schemars(with = "DigestSchema")]
+    pub body_mast_hash: Digest,
+
+    /// `BFieldCodec` encoding of the block appendix, if it changed
+    #[schemars(with = "Option<Vec<BFieldElementSchema>>")]
+    pub appendix: Option<Vec<BFieldElement>>,
+
+    pub max_timestamp: Timestamp,
+}
+
+/// What a miner holding some template needs to know about the newest one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum BlockTemplateUpdate {
+    /// The held template is still the newest
+    Unchanged,
+
+    /// The newest template differs from the held one only in a few fields
+    Delta(BlockTemplateDelta),
+
+    /// The held template is unknown or outdated, so here is the newest one
+    Full(BlockTemplate),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error, Serialize, Deserialize, JsonSchema)]
+pub enum BlockSolutionError {
+    #[error("no proposal with this id, or it was superseded")]
+    UnknownProposal,
+
+    #[error("the block the proposal builds on is no longer known")]
+    UnknownPredecessor,
+
+    #[error("block digest exceeds the target")]
+    InsufficientProofOfWork,
+
+    #[error("block is invalid, for instance because its timestamp is out of range")]
+    InvalidBlock,
+}
+
+/// A template published for external miners.
+#[derive(Debug, Clone)]
+pub(crate) struct BlockProposal {
+    pub(crate) template: Block,
+    pub(crate) coinbase_utxo_info: ExpectedUtxo,
+    pub(crate) info: BlockTemplateInfo,
+}
+
+impl BlockProposal {
+    /// The template with the given nonce, and the given timestamp if any, set
+    /// in the header. The difficulty is updated to match the timestamp.
+    pub(crate) fn solve(
+        &self,
+        predecessor: &Block,
+        nonce: [BFieldElement; 3],
+        timestamp: Option<Timestamp>,
+    ) -> Block {
+        let mut block = self.template.clone();
+        let timestamp = timestamp.unwrap_or(self.template.header().timestamp);
+        set_solution(&mut block, predecessor.header(), nonce, timestamp);
+        block
+    }
+
+    /// Check that a solved template is a valid block, and package it for the
+    /// main loop.
+    pub(crate) fn verify_solution(
+        &self,
+        block: Block,
+        predecessor: &Block,
+        now: Timestamp,
+    ) -> Result<NewBlockFound, BlockSolutionError> {
+        if !block.has_proof_of_work(predecessor) {
+            return Err(BlockSolutionError::InsufficientProofOfWork);
+        }
+        if !block.is_valid(predecessor, now) {
+            return Err(BlockSolutionError::InvalidBlock);
+        }
+
+        Ok(NewBlockFound {
+            block: Box::new(block),
+            coinbase_utxo_info: Box::new(self.coinbase_utxo_info.clone()),
+            template: self.info,
+        })
+    }
+
+    fn to_template(
+        &self,
+        proposal_id: u64,
+        predecessor: &BlockHeader,
+        now: Timestamp,
+    ) -> BlockTemplate {
+        BlockTemplate::new(proposal_id, &self.template, predecessor, now)
+    }
+
+    /// The changes from `base` to this proposal, if both build on the same
+    /// block.
+    fn delta_from(
+        &self,
+        proposal_id: u64,
+        base_proposal_id: u64,
+        base: &BlockProposal,
+        now: Timestamp,
+    ) -> Option<BlockTemplateDelta> {
+        let header = self.template.header();
+        let base_header = base.template.header();
+        if header.prev_block_digest != base_header.prev_block_digest {
+            return None;
+        }
+
+        let appendix = self.template.appendix();
+        Some(BlockTemplateDelta {
+            base_proposal_id,
+            proposal_id,
+            timestamp: header.timestamp,
+            difficulty: header.difficulty,
+            body_mast_hash: self.template.body().mast_hash(),
+            appendix: (appendix != base.template.appendix()).then(|| appendix.encode()),
+            max_timestamp: now + FUTUREDATING_LIMIT,
+        })
+    }
+}
+
+impl BlockTemplate {
+    pub(crate) fn new(
+        proposal_id: u64,
+        template: &Block,
+        predecessor: &BlockHeader,
+        now: Timestamp,
+    ) -> Self {
+        Self {
+            proposal_id,
+            header: template.header().clone(),
+            body: template.body().encode(),
+            appendix: template.appendix().encode(),
+            target: predecessor.difficulty.target(),
+            min_timestamp: predecessor.timestamp + MINIMUM_BLOCK_TIME,
+            max_timestamp: now + FUTUREDATING_LIMIT,
+        }
+    }
+}
+
+/// Set nonce, timestamp, and the difficulty matching the timestamp in the
+/// header of a copy of a template, such that repeated guesses need not copy
+/// the template.
+pub(crate) fn set_solution(
+    block: &mut Block,
+    predecessor: &BlockHeader,
+    nonce: [BFieldElement; 3],
+    timestamp: Timestamp,
+) {
+    let difficulty = difficulty_control(
+        timestamp,
+        predecessor.timestamp,
+        predecessor.difficulty,
+        None,
+        predecessor.height,
+        NetworkParams::in_effect(),
+    );
+    block.set_header_timestamp_and_difficulty(timestamp, difficulty);
+    block.set_header_nonce(nonce);
+}
+
+/// The latest proposals, newest last.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BlockProposals {
+    proposals: VecDeque<(u64, BlockProposal)>,
+    next_id: u64,
+}
+
+impl BlockProposals {
+    /// Publish a proposal, superseding the oldest one if too many are kept.
+    pub(crate) fn publish(&mut self, proposal: BlockProposal) -> u64 {
+        let proposal_id = self.next_id;
+        self.next_id += 1;
+        self.proposals.push_back((proposal_id, proposal));
+        if self.proposals.len() > MAX_NUM_PROPOSALS {
+            self.proposals.pop_front();
+        }
+        proposal_id
+    }
+
+    /// Withdraw all proposals, for instance because mining stopped.
+    pub(crate) fn clear(&mut self) {
+        self.proposals.clear();
+    }
+
+    pub(crate) fn get(&self, proposal_id: u64) -> Option<&BlockProposal> {
+        self.proposals
+            .iter()
+            .find(|(id, _)| *id == proposal_id)
+            .map(|(_, proposal)| proposal)
+    }
+
+    /// The id of the newest proposal, and the proposal.
+    pub(crate) fn latest(&self) -> Option<(u64, &BlockProposal)> {
+        self.proposals
+            .back()
+            .map(|(proposal_id, proposal)| (*proposal_id, proposal))
+    }
+
+    /// The newest proposal, as a template for external miners.
+    pub(crate) fn latest_template(
+        &self,
+        predecessor: &BlockHeader,
+        now: Timestamp,
+    ) -> Option<BlockTemplate> {
+        let (proposal_id, proposal) = self.proposals.back()?;
+        Some(proposal.to_template(*proposal_id, predecessor, now))
+    }
+
+    /// The newest proposal, for a miner holding the template with id
+    /// `known_proposal_id`, if any.
+    pub(crate) fn latest_update(
+        &self,
+        known_proposal_id: Option<u64>,
+        predecessor: &BlockHeader,
+        now: Timestamp,
+    ) -> Option<BlockTemplateUpdate> {
+        let (proposal_id, proposal) = self.proposals.back()?;
+        if known_proposal_id == Some(*proposal_id) {
+            return Some(BlockTemplateUpdate::Unchanged);
+        }
+
+        let delta = known_proposal_id.and_then(|base_proposal_id| {
+            let base = self.get(base_proposal_id)?;
+            proposal.delta_from(*proposal_id, base_proposal_id, base, now)
+        });
+        let update = match delta {
+            Some(delta) => BlockTemplateUpdate::Delta(delta),
+            None => BlockTemplateUpdate::Full(proposal.to_template(*proposal_id, predecessor, now)),
+        };
+        Some(update)
+    }
+}
+
+#[cfg(test)]
+mod block_proposal_tests {
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::blockchain::transaction::Transaction;
+    use crate::tests::shared::dummy_expected_utxo;
+    use crate::tests::shared::make_mock_transaction;
+    use crate::util_types::test_shared::mutator_set::random_addition_record;
+
+    #[test]
+    fn only_latest_proposals_can_be_solved() {
+        let genesis = Block::genesis_block(Network::RegTest);
+        let transaction: Transaction = make_mock_transaction(vec![], vec![]);
+        let created = genesis.header().timestamp + Timestamp::minutes(10);
+        let proposal = BlockProposal {
+            template: Block::block_template_invalid_proof(&genesis, transaction, created, None),
+            coinbase_utxo_info: dummy_expected_utxo(),
+            info: BlockTemplateInfo {
+                created,
+                num_transactions: 0,
+            },
+        };
+
+        let mut proposals = BlockProposals::default();
+        let first = proposals.publish(proposal.clone());
+        for _ in 0..MAX_NUM_PROPOSALS {
+            proposals.publish(proposal.clone());
+        }
+        assert!(proposals.get(first).is_none());
+
+        let template = proposals
+            .latest_template(genesis.header(), created)
+            .unwrap();
+        assert_eq!(first + MAX_NUM_PROPOSALS as u64, template.proposal_id);
+        assert_eq!(genesis.header().difficulty.target(), template.target);
+
+        let nonce = [
+            BFieldElement::new(1),
+            BFieldElement::new(2),
+            BFieldElement::new(3),
+        ];
+        let later = created + Timestamp::minutes(1);
+        let solved =
+            proposals
+                .get(template.proposal_id)
+                .unwrap()
+                .solve(&genesis, nonce, Some(later));
+        assert_eq!(nonce, solved.header().nonce);
+        assert_eq!(later, solved.header().timestamp);
+        assert_eq!(
+            template.body,
+            solved.body().encode(),
+            "solving must not touch the body"
+        );
+
+        proposals.clear();
+        assert!(proposals
+            .latest_template(genesis.header(), created)
+            .is_none());
+    }
+
+    #[test]
+    fn updates_carry_only_changes_to_known_templates() {
+        let genesis = Block::genesis_block(Network::RegTest);
+        let proposal_at = |created: Timestamp, num_outputs: usize| {
+            let outputs = (0..num_outputs).map(|_| random_addition_record()).collect();
+            let transaction: Transaction = make_mock_transaction(vec![], outputs);
+            BlockProposal {
+                template: Block::block_template_invalid_proof(&genesis, transaction, created, None),
+                coinbase_utxo_info: dummy_expected_utxo(),
+                info: BlockTemplateInfo {
+                    created,
+                    num_transactions: num_outputs,
+                },
+            }
+        };
+        let created = genesis.header().timestamp + Timestamp::minutes(10);
+        let now = created;
+
+        let mut proposals = BlockProposals::default();
+        assert!(proposals
+            .latest_update(None, genesis.header(), now)
+            .is_none());
+
+        let first = proposals.publish(proposal_at(created, 0));
+        let Some(BlockTemplateUpdate::Full(template)) =
+            proposals.latest_update(None, genesis.header(), now)
+        else {
+            panic!("miners without a template get a whole one");
+        };
+        assert_eq!(first, template.proposal_id);
+        assert_eq!(
+            Some(BlockTemplateUpdate::Unchanged),
+            proposals.latest_update(Some(first), genesis.header(), now)
+        );
+
+        let later = created + Timestamp::minutes(1);
+        let second = proposals.publish(proposal_at(later, 2));
+        let Some(BlockTemplateUpdate::Delta(delta)) =
+            proposals.latest_update(Some(first), genesis.header(), now)
+        else {
+            panic!("miners with a known template get the changes");
+        };
+        let newest = &proposals.get(second).unwrap().template;
+        assert_eq!(first, delta.base_proposal_id);
+        assert_eq!(second, delta.proposal_id);
+        assert_eq!(later, delta.timestamp);
+        assert_eq!(newest.header().difficulty, delta.difficulty);
+        assert_eq!(newest.body().mast_hash(), delta.body_mast_hash);
+
+        assert!(matches!(
+            proposals.latest_update(Some(second + 1), genesis.header(), now),
+            Some(BlockTemplateUpdate::Full(_))
+        ));
+    }
+}
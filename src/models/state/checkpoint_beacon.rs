@@ -0,0 +1,361 @@
+//! Optional trusted feed of checkpoint headers.
+//!
+//! An operator can configure public keys of checkpoint signers it trusts. Signed
+//! checkpoints, i.e. a (height, block digest) pair, are then pushed to the node
+//! through RPC. They are used to prioritize sync targets and to detect that the
+//! node is being fed a different chain than the rest of the network, as in an
+//! eclipse attack. Checkpoints never override proof-of-work validity: blocks
+//! are validated and fork choice is made exactly as without a beacon.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::bail;
+use ed25519_dalek::Signature;
+use ed25519_dalek::Signer;
+use ed25519_dalek::SigningKey;
+use ed25519_dalek::Verifier;
+use ed25519_dalek::VerifyingKey;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::archival_state::ArchivalState;
+use crate::config_models::network::Network;
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::prelude::twenty_first::math::digest::Digest;
+use crate::util_types::json_schema::DigestSchema;
+
+/// Domain separator for checkpoint signatures.
+const SIGNATURE_DOMAIN: &[u8] = b"neptune-checkpoint-beacon";
+
+/// Public key of a trusted checkpoint signer, parsed from 64 hex characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BeaconKey(VerifyingKey);
+
+impl FromStr for BeaconKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = hex_decode(s)?;
+        let Ok(bytes) = <[u8; 32]>::try_from(bytes) else {
+            bail!("beacon key must be 32 bytes");
+        };
+        Ok(Self(VerifyingKey::from_bytes(&bytes)?))
+    }
+}
+
+impl Display for BeaconKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", hex_encode(self.0.as_bytes()))
+    }
+}
+
+impl From<&SigningKey> for BeaconKey {
+    fn from(signing_key: &SigningKey) -> Self {
+        Self(signing_key.verifying_key())
+    }
+}
+
+/// A block digest at a given height, signed by a checkpoint signer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SignedCheckpoint {
+    pub network: Network,
+    pub height: BlockHeight,
+    #[schemars(with = "DigestSchema")]
+    pub digest: Digest,
+    pub signer: [u8; 32],
+    pub signature: Vec<u8>,
+}
+
+impl SignedCheckpoint {
+    fn message(network: Network, height: BlockHeight, digest: Digest) -> Vec<u8> {
+        let payload = bincode::serialize(&(network, height, digest))
+            .expect("serializing checkpoint must succeed");
+        [SIGNATURE_DOMAIN, &payload].concat()
+    }
+
+    /// Sign a checkpoint. Used by checkpoint feeds, and in tests.
+    pub fn sign(
+        network: Network,
+        height: BlockHeight,
+        digest: Digest,
+        signing_key: &SigningKey,
+    ) -> Self {
+        let signature = signing_key.sign(&Self::message(network, height, digest));
+        Self {
+            network,
+            height,
+            digest,
+            signer: signing_key.verifying_key().to_bytes(),
+            signature: signature.to_bytes().to_vec(),
+        }
+    }
+
+    fn verify(&self, trusted_keys: &[BeaconKey]) -> Result<(), BeaconError> {
+        let Some(key) = trusted_keys
+            .iter()
+            .find(|key| key.0.as_bytes() == &self.signer)
+        else {
+            return Err(BeaconError::UntrustedSigner);
+        };
+        let signature =
+            Signature::from_slice(&self.signature).map_err(|_| BeaconError::InvalidSignature)?;
+        key.0
+            .verify(
+                &Self::message(self.network, self.height, self.digest),
+                &signature,
+            )
+            .map_err(|_| BeaconError::InvalidSignature)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum BeaconError {
+    #[error("beacon mode is not enabled")]
+    Disabled,
+
+    #[error("checkpoint is for network {0}")]
+    WrongNetwork(Network),
+
+    #[error("checkpoint is not signed by a trusted key")]
+    UntrustedSigner,
+
+    #[error("invalid checkpoint signature")]
+    InvalidSignature,
+
+    #[error("checkpoint height {0} is not above that of the latest checkpoint")]
+    Stale(BlockHeight),
+}
+
+/// How the canonical chain relates to the latest trusted checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum BeaconAssessment {
+    /// No trusted keys are configured.
+    Disabled,
+
+    /// No checkpoint has been received yet.
+    NoCheckpoint,
+
+    /// The checkpointed block is on the canonical chain.
+    Consistent { height: BlockHeight },
+
+    /// The checkpoint is above the tip.
+    Behind {
+        checkpoint_height: BlockHeight,
+        tip_height: BlockHeight,
+    },
+
+    /// The canonical chain has a different block at the checkpoint height.
+    Conflict {
+        height: BlockHeight,
+        #[schemars(with = "Option<DigestSchema>")]
+        canonical: Option<Digest>,
+        #[schemars(with = "DigestSchema")]
+        checkpoint: Digest,
+    },
+}
+
+impl Display for BeaconAssessment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Disabled => write!(f, "beacon disabled"),
+            Self::NoCheckpoint => write!(f, "no checkpoint received"),
+            Self::Consistent { height } => {
+                write!(
+                    f,
+                    "canonical chain agrees with checkpoint at height {height}"
+                )
+            }
+            Self::Behind {
+                checkpoint_height,
+                tip_height,
+            } => write!(
+                f,
+                "tip at height {tip_height} is behind checkpoint at height {checkpoint_height}"
+            ),
+            Self::Conflict {
+                height,
+                canonical,
+                checkpoint,
+            } => write!(
+                f,
+                "canonical block {} at height {height} conflicts with checkpoint {}",
+                canonical.map_or("none".to_string(), |d| d.to_hex()),
+                checkpoint.to_hex()
+            ),
+        }
+    }
+}
+
+/// Trusted signers and the latest checkpoint received from them.
+#[derive(Debug, Clone, Default)]
+pub struct CheckpointBeacon {
+    trusted_keys: Vec<BeaconKey>,
+    latest: Option<SignedCheckpoint>,
+}
+
+impl CheckpointBeacon {
+    pub fn new(trusted_keys: Vec<BeaconKey>) -> Self {
+        Self {
+            trusted_keys,
+            latest: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.trusted_keys.is_empty()
+    }
+
+    pub fn latest(&self) -> Option<&SignedCheckpoint> {
+        self.latest.as_ref()
+    }
+
+    /// Verify a checkpoint and make it the latest if it is above the current
+    /// one.
+    pub fn accept(
+        &mut self,
+        network: Network,
+        checkpoint: SignedCheckpoint,
+    ) -> Result<(), BeaconError> {
+        if !self.is_enabled() {
+            return Err(BeaconError::Disabled);
+        }
+        if checkpoint.network != network {
+            return Err(BeaconError::WrongNetwork(checkpoint.network));
+        }
+        checkpoint.verify(&self.trusted_keys)?;
+        if let Some(latest) = &self.latest {
+            if checkpoint.height <= latest.height {
+                return Err(BeaconError::Stale(checkpoint.height));
+            }
+        }
+
+        self.latest = Some(checkpoint);
+        Ok(())
+    }
+
+    /// Compare the canonical chain with the latest checkpoint.
+    pub async fn assess(
+        &self,
+        archival_state: &ArchivalState,
+        tip_digest: Digest,
+        tip_height: BlockHeight,
+    ) -> BeaconAssessment {
+        if !self.is_enabled() {
+            return BeaconAssessment::Disabled;
+        }
+        let Some(checkpoint) = &self.latest else {
+            return BeaconAssessment::NoCheckpoint;
+        };
+        if checkpoint.height > tip_height {
+            return BeaconAssessment::Behind {
+                checkpoint_height: checkpoint.height,
+                tip_height,
+            };
+        }
+
+        let canonical = archival_state
+            .block_height_to_canonical_block_digest(checkpoint.height, tip_digest)
+            .await;
+        if canonical == Some(checkpoint.digest) {
+            BeaconAssessment::Consistent {
+                height: checkpoint.height,
+            }
+        } else {
+            BeaconAssessment::Conflict {
+                height: checkpoint.height,
+                canonical,
+                checkpoint: checkpoint.digest,
+            }
+        }
+    }
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn hex_decode(s: &str) -> anyhow::Result<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        bail!("hex string must have even length");
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| Ok(u8::from_str_radix(&s[i..i + 2], 16)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod checkpoint_beacon_tests {
+    use rand::random;
+
+    use super::*;
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_bytes(&random())
+    }
+
+    #[test]
+    fn beacon_key_hex_roundtrip() {
+        let key = BeaconKey::from(&signing_key());
+        assert_eq!(key, key.to_string().parse().unwrap());
+        assert!("abcd".parse::<BeaconKey>().is_err());
+    }
+
+    #[test]
+    fn only_checkpoints_from_trusted_keys_are_accepted() {
+        let trusted = signing_key();
+        let untrusted = signing_key();
+        let network = Network::Main;
+        let mut beacon = CheckpointBeacon::new(vec![BeaconKey::from(&trusted)]);
+
+        let checkpoint = SignedCheckpoint::sign(network, 5u64.into(), random(), &untrusted);
+        assert_eq!(
+            Err(BeaconError::UntrustedSigner),
+            beacon.accept(network, checkpoint)
+        );
+
+        let mut checkpoint = SignedCheckpoint::sign(network, 5u64.into(), random(), &trusted);
+        checkpoint.height = 6u64.into();
+        assert_eq!(
+            Err(BeaconError::InvalidSignature),
+            beacon.accept(network, checkpoint)
+        );
+
+        let checkpoint = SignedCheckpoint::sign(network, 5u64.into(), random(), &trusted);
+        assert_eq!(
+            Err(BeaconError::WrongNetwork(network)),
+            beacon.accept(Network::Testnet, checkpoint.clone())
+        );
+        assert!(beacon.accept(network, checkpoint.clone()).is_ok());
+        assert_eq!(Some(&checkpoint), beacon.latest());
+    }
+
+    #[test]
+    fn stale_checkpoints_are_rejected() {
+        let key = signing_key();
+        let network = Network::Main;
+        let mut beacon = CheckpointBeacon::new(vec![BeaconKey::from(&key)]);
+
+        let checkpoint = SignedCheckpoint::sign(network, 10u64.into(), random(), &key);
+        beacon.accept(network, checkpoint).unwrap();
+
+        let older = SignedCheckpoint::sign(network, 10u64.into(), random(), &key);
+        assert_eq!(
+            Err(BeaconError::Stale(10u64.into())),
+            beacon.accept(network, older)
+        );
+    }
+
+    #[test]
+    fn disabled_beacon_rejects_checkpoints() {
+        let key = signing_key();
+        let network = Network::Main;
+        let checkpoint = SignedCheckpoint::sign(network, 1u64.into(), random(), &key);
+        assert_eq!(
+            Err(BeaconError::Disabled),
+            CheckpointBeacon::default().accept(network, checkpoint)
+        );
+    }
+}
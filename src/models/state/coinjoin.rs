@@ -0,0 +1,415 @@
+//! Opt-in coordination of coinjoin transactions.
+//!
+//! A coinjoin merges the transactions of several wallets into one, such that
+//! an observer of the chain cannot tell which inputs paid for which outputs.
+//! Transactions supported by single proofs are merged without the help of
+//! their authors, so a coordinator only has to collect them. Every participant
+//! contributes a transaction with an output of the session's denomination and
+//! discloses the opening of that output's addition record to the coordinator,
+//! which thereby enforces that the denomination outputs of all participants
+//! are equal. The coordinator learns which denomination output belongs to
+//! which contribution; the rest of the network does not. Change outputs are
+//! not equalized, and reveal as much as in any other transaction.
+//!
+//! A session collects contributions until it has the maximum number of
+//! participants, or until its deadline if it has the minimum number by then.
+//! Sessions that end short of participants, or whose contributions are
+//! outdated by a new block, fail, and their participants broadcast their
+//! contributions on their own.
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tasm_lib::twenty_first::math::digest::Digest;
+use tasm_lib::twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
+
+use crate::models::blockchain::shared::Hash;
+use crate::models::blockchain::transaction::utxo::Utxo;
+use crate::models::blockchain::transaction::Transaction;
+use crate::models::blockchain::transaction::TransactionProof;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use crate::models::peer::transfer_transaction::TransferTransaction;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::util_types::json_schema::DigestSchema;
+use crate::util_types::mutator_set::addition_record::AdditionRecord;
+use crate::util_types::mutator_set::commit;
+use crate::util_types::mutator_set::shared::NUM_TRIALS;
+
+/// A coinjoin session open for contributions, as announced by its
+/// coordinator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CoinJoinSessionInfo {
+    pub id: u64,
+
+    /// Amount of the output that every participant contributes
+    pub denomination: NeptuneCoins,
+
+    /// Mutator set that contributions must be synced to
+    #[schemars(with = "DigestSchema")]
+    pub mutator_set_hash: Digest,
+
+    pub num_participants: usize,
+    pub min_participants: usize,
+    pub max_participants: usize,
+
+    /// Time at which the session is merged if it has the minimum number of
+    /// participants, and fails otherwise
+    pub deadline: Timestamp,
+}
+
+/// Opening of the addition record of a contribution's denomination output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisclosedOutput {
+    pub(crate) utxo: Utxo,
+    pub(crate) sender_randomness: Digest,
+    pub(crate) receiver_digest: Digest,
+}
+
+impl DisclosedOutput {
+    pub(crate) fn addition_record(&self) -> AdditionRecord {
+        commit(
+            Hash::hash(&self.utxo),
+            self.sender_randomness,
+            self.receiver_digest,
+        )
+    }
+}
+
+/// A transaction contributed to a coinjoin session, sent to the coordinator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct CoinJoinContribution {
+    pub(crate) session_id: u64,
+    pub(crate) transaction: TransferTransaction,
+    pub(crate) disclosed_output: DisclosedOutput,
+}
+
+/// This node's contribution to a session coordinated by a peer, kept such
+/// that it can be broadcast on its own if the session fails.
+#[derive(Debug, Clone)]
+pub struct OwnCoinJoinContribution {
+    pub(crate) coordinator: SocketAddr,
+    pub(crate) session_id: u64,
+    pub(crate) transaction: Transaction,
+    pub(crate) disclosed_output: DisclosedOutput,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+pub(crate) enum CoinJoinError {
+    #[error("node does not coordinate coinjoin sessions")]
+    NotCoordinator,
+
+    #[error("no open session with id {0}")]
+    UnknownSession(u64),
+
+    #[error("session is full")]
+    SessionFull,
+
+    #[error("peer already contributed to the session")]
+    AlreadyContributed,
+
+    #[error("transaction is invalid")]
+    InvalidTransaction,
+
+    #[error("transaction is not supported by a single proof")]
+    NotSingleProof,
+
+    #[error("transaction is not synced to the session's mutator set")]
+    MutatorSetMismatch,
+
+    #[error("transaction has coinbase")]
+    HasCoinbase,
+
+    #[error("disclosed output of {0} does not match the denomination")]
+    WrongDenomination(NeptuneCoins),
+
+    #[error("disclosed output is not an output of the transaction")]
+    MissingDenominationOutput,
+
+    #[error("transaction spends inputs of another contribution")]
+    ConflictingInputs,
+
+    #[error("session failed short of participants, or was outdated by a new block")]
+    SessionFailed,
+}
+
+/// Limits of the sessions coordinated by this node, set on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CoinJoinConfig {
+    pub(crate) denomination: NeptuneCoins,
+    pub(crate) min_participants: usize,
+    pub(crate) max_participants: usize,
+    pub(crate) session_duration: Timestamp,
+}
+
+/// How a coordinated session ended.
+#[derive(Debug, Clone)]
+pub(crate) enum SessionOutcome {
+    /// The session has enough contributions to be merged.
+    Ready {
+        session_id: u64,
+        contributors: Vec<SocketAddr>,
+        transactions: Vec<Transaction>,
+    },
+
+    /// The session ended short of participants, or its contributions were
+    /// outdated by a new block.
+    Failed {
+        session_id: u64,
+        contributors: Vec<SocketAddr>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Session {
+    info: CoinJoinSessionInfo,
+    contributions: Vec<(SocketAddr, Transaction)>,
+
+    /// Absolute index sets of the inputs of all contributions
+    spent: HashSet<[u128; NUM_TRIALS as usize]>,
+}
+
+/// The session coordinated by this node. At most one session is open at a
+/// time, and a new one is opened when a peer asks for a session after the
+/// previous one ended.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CoinJoinCoordinator {
+    next_session_id: u64,
+    session: Option<Session>,
+}
+
+impl CoinJoinCoordinator {
+    /// The session open for contributions synced to the given mutator set,
+    /// opening one if none is. `None` if the open session is synced to
+    /// another mutator set and is about to fail.
+    pub(crate) fn session_info(
+        &mut self,
+        config: &CoinJoinConfig,
+        mutator_set_hash: Digest,
+        now: Timestamp,
+    ) -> Option<CoinJoinSessionInfo> {
+        let session = self.session.get_or_insert_with(|| {
+            let info = CoinJoinSessionInfo {
+                id: self.next_session_id,
+                denomination: config.denomination,
+                mutator_set_hash,
+                num_participants: 0,
+                min_participants: config.min_participants,
+                max_participants: config.max_participants,
+                deadline: now + config.session_duration,
+            };
+            self.next_session_id += 1;
+            Session {
+                info,
+                contributions: vec![],
+                spent: HashSet::new(),
+            }
+        });
+
+        (session.info.mutator_set_hash == mutator_set_hash).then_some(session.info)
+    }
+
+    /// Add a transaction, whose proof has been verified, to the open session.
+    pub(crate) fn contribute(
+        &mut self,
+        peer: SocketAddr,
+        session_id: u64,
+        transaction: Transaction,
+        disclosed_output: &DisclosedOutput,
+    ) -> Result<(), CoinJoinError> {
+        let Some(session) = self
+            .session
+            .as_mut()
+            .filter(|session| session.info.id == session_id)
+        else {
+            return Err(CoinJoinError::UnknownSession(session_id));
+        };
+
+        if session.contributions.len() >= session.info.max_participants {
+            return Err(CoinJoinError::SessionFull);
+        }
+        if session
+            .contributions
+            .iter()
+            .any(|(contributor, _)| *contributor == peer)
+        {
+            return Err(CoinJoinError::AlreadyContributed);
+        }
+
+        let kernel = &transaction.kernel;
+        if !matches!(transaction.proof, TransactionProof::SingleProof(_)) {
+            return Err(CoinJoinError::NotSingleProof);
+        }
+        if kernel.mutator_set_hash != session.info.mutator_set_hash {
+            return Err(CoinJoinError::MutatorSetMismatch);
+        }
+        if kernel.coinbase.is_some() {
+            return Err(CoinJoinError::HasCoinbase);
+        }
+
+        let amount = disclosed_output.utxo.get_native_currency_amount();
+        if amount != session.info.denomination {
+            return Err(CoinJoinError::WrongDenomination(amount));
+        }
+        if !kernel.outputs.contains(&disclosed_output.addition_record()) {
+            return Err(CoinJoinError::MissingDenominationOutput);
+        }
+
+        let inputs = kernel
+            .inputs
+            .iter()
+            .map(|removal_record| removal_record.absolute_indices.to_array())
+            .collect::<HashSet<_>>();
+        if !session.spent.is_disjoint(&inputs) {
+            return Err(CoinJoinError::ConflictingInputs);
+        }
+
+        session.spent.extend(inputs);
+        session.contributions.push((peer, transaction));
+        session.info.num_participants = session.contributions.len();
+
+        Ok(())
+    }
+
+    /// End the open session if it is full, past its deadline, or outdated by
+    /// a block that changed the mutator set.
+    pub(crate) fn poll(
+        &mut self,
+        mutator_set_hash: Digest,
+        now: Timestamp,
+    ) -> Option<SessionOutcome> {
+        let session = self.session.as_ref()?;
+        let num_contributions = session.contributions.len();
+        let is_outdated = session.info.mutator_set_hash != mutator_set_hash;
+        let is_full = num_contributions >= session.info.max_participants;
+        let is_due = now >= session.info.deadline;
+        if !is_outdated && !is_full && !is_due {
+            return None;
+        }
+
+        let session = self.session.take()?;
+        let session_id = session.info.id;
+        let (contributors, transactions): (Vec<_>, Vec<_>) =
+            session.contributions.into_iter().unzip();
+        if contributors.is_empty() {
+            return None;
+        }
+
+        if !is_outdated && num_contributions >= session.info.min_participants {
+            Some(SessionOutcome::Ready {
+                session_id,
+                contributors,
+                transactions,
+            })
+        } else {
+            Some(SessionOutcome::Failed {
+                session_id,
+                contributors,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod coinjoin_tests {
+    use tasm_lib::triton_vm::proof::Proof;
+
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::blockchain::block::Block;
+    use crate::models::blockchain::transaction::lock_script::LockScript;
+
+    fn contribution(denomination: NeptuneCoins) -> (Transaction, DisclosedOutput) {
+        let mut kernel = Block::genesis_block(Network::RegTest)
+            .body()
+            .transaction_kernel
+            .clone();
+        let disclosed_output = DisclosedOutput {
+            utxo: Utxo::new_native_currency(LockScript::anyone_can_spend(), denomination),
+            sender_randomness: rand::random(),
+            receiver_digest: rand::random(),
+        };
+        kernel.coinbase = None;
+        kernel.inputs = vec![];
+        kernel.outputs = vec![disclosed_output.addition_record()];
+
+        let transaction = Transaction {
+            kernel,
+            proof: TransactionProof::SingleProof(Proof(vec![])),
+        };
+        (transaction, disclosed_output)
+    }
+
+    #[test]
+    fn sessions_enforce_denomination_and_end_by_participants() {
+        let denomination = NeptuneCoins::new(10);
+        let config = CoinJoinConfig {
+            denomination,
+            min_participants: 2,
+            max_participants: 3,
+            session_duration: Timestamp::minutes(10),
+        };
+        let now = Timestamp::now();
+        let mutator_set_hash = Block::genesis_block(Network::RegTest)
+            .body()
+            .transaction_kernel
+            .mutator_set_hash;
+        let peer = |i: u8| SocketAddr::from(([127, 0, 0, i], 9798));
+
+        let mut coordinator = CoinJoinCoordinator::default();
+        let session = coordinator
+            .session_info(&config, mutator_set_hash, now)
+            .unwrap();
+        assert_eq!(0, session.num_participants);
+
+        let (transaction, disclosed_output) = contribution(NeptuneCoins::new(11));
+        assert_eq!(
+            Err(CoinJoinError::WrongDenomination(NeptuneCoins::new(11))),
+            coordinator.contribute(peer(1), session.id, transaction, &disclosed_output)
+        );
+
+        let (transaction, mut disclosed_output) = contribution(denomination);
+        disclosed_output.sender_randomness = rand::random();
+        assert_eq!(
+            Err(CoinJoinError::MissingDenominationOutput),
+            coordinator.contribute(peer(1), session.id, transaction, &disclosed_output)
+        );
+
+        let (transaction, disclosed_output) = contribution(denomination);
+        assert_eq!(
+            Ok(()),
+            coordinator.contribute(peer(1), session.id, transaction.clone(), &disclosed_output)
+        );
+        assert_eq!(
+            Err(CoinJoinError::AlreadyContributed),
+            coordinator.contribute(peer(1), session.id, transaction, &disclosed_output)
+        );
+
+        // one participant at the deadline is too few
+        assert!(coordinator.poll(mutator_set_hash, now).is_none());
+        let deadline = session.deadline;
+        assert!(matches!(
+            coordinator.poll(mutator_set_hash, deadline),
+            Some(SessionOutcome::Failed { .. })
+        ));
+
+        // a new session fills up
+        let session = coordinator
+            .session_info(&config, mutator_set_hash, deadline)
+            .unwrap();
+        for i in 1..=3 {
+            let (transaction, disclosed_output) = contribution(denomination);
+            coordinator
+                .contribute(peer(i), session.id, transaction, &disclosed_output)
+                .unwrap();
+        }
+        let Some(SessionOutcome::Ready { transactions, .. }) =
+            coordinator.poll(mutator_set_hash, deadline)
+        else {
+            panic!("full session must be ready");
+        };
+        assert_eq!(3, transactions.len());
+    }
+}
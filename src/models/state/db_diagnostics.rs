@@ -0,0 +1,387 @@
+//! Consistency checks of the databases, and quarantining of databases that
+//! fail them.
+//!
+//! The checks run at startup. If they find an issue, the node does not start
+//! normally but boots into [safe mode](crate::safe_mode), where the operator
+//! can inspect the databases, export the wallet files, and quarantine broken
+//! databases so that they are rebuilt on the next start.
+
+use std::fmt::Display;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::Context;
+use anyhow::Result;
+use itertools::Itertools;
+use serde::Deserialize;
+use serde::Serialize;
+use twenty_first::math::digest::Digest;
+
+use super::archival_state::ArchivalState;
+use crate::config_models::data_directory::DataDirectory;
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::prelude::twenty_first;
+
+/// Name of the directory, within the data directory, that quarantined
+/// databases are moved to
+pub const QUARANTINE_DIRECTORY_NAME: &str = "quarantine";
+
+/// Number of blocks below the tip that are read and checked at startup
+pub const STARTUP_CHECK_DEPTH: u64 = 10;
+
+/// A group of databases that must be consistent with each other, and are
+/// therefore quarantined together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DatabaseKind {
+    /// Block index, block files, and archival mutator set
+    Chain,
+
+    /// Peer standings
+    Peers,
+
+    /// Wallet database, which is restored from the wallet files in the
+    /// wallet directory. The wallet files themselves are never quarantined.
+    Wallet,
+}
+
+impl DatabaseKind {
+    pub fn paths(&self, data_dir: &DataDirectory) -> Vec<PathBuf> {
+        match self {
+            Self::Chain => vec![
+                data_dir.block_index_database_dir_path(),
+                data_dir.mutator_set_database_dir_path(),
+                data_dir.block_dir_path(),
+            ],
+            Self::Peers => vec![data_dir.banned_ips_database_dir_path()],
+            Self::Wallet => vec![
+                data_dir.wallet_database_dir_path(),
+                data_dir.wallet_output_count_database_dir_path(),
+            ],
+        }
+    }
+}
+
+impl Display for DatabaseKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Chain => "chain",
+            Self::Peers => "peers",
+            Self::Wallet => "wallet",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for DatabaseKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chain" => Ok(Self::Chain),
+            "peers" => Ok(Self::Peers),
+            "wallet" => Ok(Self::Wallet),
+            _ => Err(format!(
+                "unknown database `{s}`; expected `chain`, `peers` or `wallet`"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DbIssue {
+    Unopenable {
+        database: DatabaseKind,
+        error: String,
+    },
+    BlockRecordMissing {
+        digest: Digest,
+    },
+    BlockUnreadable {
+        height: BlockHeight,
+        digest: Digest,
+        error: String,
+    },
+    HeightMismatch {
+        digest: Digest,
+        recorded: BlockHeight,
+        actual: BlockHeight,
+    },
+    HeightIndexMissing {
+        height: BlockHeight,
+        digest: Digest,
+    },
+    MutatorSetNotSyncedToTip {
+        tip: Digest,
+        sync_label: Digest,
+    },
+}
+
+impl DbIssue {
+    /// The databases to quarantine to get rid of this issue.
+    pub fn database(&self) -> DatabaseKind {
+        match self {
+            Self::Unopenable { database, .. } => *database,
+            _ => DatabaseKind::Chain,
+        }
+    }
+}
+
+impl Display for DbIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unopenable { database, error } => {
+                write!(f, "{database} database cannot be opened: {error}")
+            }
+            Self::BlockRecordMissing { digest } => {
+                write!(f, "block {digest} is referenced but has no block record")
+            }
+            Self::BlockUnreadable {
+                height,
+                digest,
+                error,
+            } => write!(
+                f,
+                "block {digest} at height {height} cannot be read: {error}"
+            ),
+            Self::HeightMismatch {
+                digest,
+                recorded,
+                actual,
+            } => write!(
+                f,
+                "block {digest} is recorded at height {recorded} but has height {actual}"
+            ),
+            Self::HeightIndexMissing { height, digest } => {
+                write!(f, "height index {height} does not list block {digest}")
+            }
+            Self::MutatorSetNotSyncedToTip { tip, sync_label } => write!(
+                f,
+                "mutator set is synced to block {sync_label} instead of tip {tip}"
+            ),
+        }
+    }
+}
+
+/// Returned by node setup if the databases are inconsistent.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("inconsistent databases: {}", .issues.iter().join("; "))]
+pub struct DatabaseInconsistency {
+    pub issues: Vec<DbIssue>,
+}
+
+impl DatabaseInconsistency {
+    pub(crate) fn unopenable(database: DatabaseKind, error: anyhow::Error) -> Self {
+        Self {
+            issues: vec![DbIssue::Unopenable {
+                database,
+                error: format!("{error:#}"),
+            }],
+        }
+    }
+}
+
+impl ArchivalState {
+    /// Check the tip, the `depth` canonical blocks below it, and that the
+    /// mutator set is synced to the tip.
+    pub async fn check_consistency(&self, depth: u64) -> Vec<DbIssue> {
+        let tip_digest = self.get_tip_digest().await;
+        let Some(tip_header) = self.get_block_header(tip_digest).await else {
+            return vec![DbIssue::BlockRecordMissing { digest: tip_digest }];
+        };
+
+        let mut issues = vec![];
+        let sync_label = self.archival_mutator_set.get_sync_label().await;
+        if sync_label != tip_digest {
+            issues.push(DbIssue::MutatorSetNotSyncedToTip {
+                tip: tip_digest,
+                sync_label,
+            });
+        }
+
+        let from = u64::from(tip_header.height).saturating_sub(depth);
+        issues.extend(
+            self.verify_canonical_range(from.into(), tip_header.height)
+                .await,
+        );
+
+        issues
+    }
+
+    /// Check that the canonical blocks with heights in `from..=to` have block
+    /// records, are listed in the height index, and can be read from their
+    /// block files, unless pruned. Stops at the first missing block record,
+    /// since the chain cannot be followed past it.
+    pub async fn verify_canonical_range(&self, from: BlockHeight, to: BlockHeight) -> Vec<DbIssue> {
+        let mut digest = self.get_tip_digest().await;
+        let mut issues = vec![];
+        loop {
+            let Some(header) = self.get_block_header(digest).await else {
+                issues.push(DbIssue::BlockRecordMissing { digest });
+                return issues;
+            };
+            if header.height < from {
+                return issues;
+            }
+
+            if header.height <= to {
+                issues.extend(self.verify_block(header.height, digest).await);
+            }
+            if header.height.is_genesis() {
+                return issues;
+            }
+            digest = header.prev_block_digest;
+        }
+    }
+
+    async fn verify_block(&self, height: BlockHeight, digest: Digest) -> Vec<DbIssue> {
+        let mut issues = vec![];
+        if !height.is_genesis()
+            && !self
+                .block_height_to_block_digests(height)
+                .await
+                .contains(&digest)
+        {
+            issues.push(DbIssue::HeightIndexMissing { height, digest });
+        }
+
+        match self.get_block(digest).await {
+            Ok(Some(block)) if block.kernel.header.height != height => {
+                issues.push(DbIssue::HeightMismatch {
+                    digest,
+                    recorded: height,
+                    actual: block.kernel.header.height,
+                });
+            }
+            Ok(_) => (),
+            Err(err) => issues.push(DbIssue::BlockUnreadable {
+                height,
+                digest,
+                error: format!("{err:#}"),
+            }),
+        }
+
+        issues
+    }
+}
+
+/// Move the databases of the given kind into a new subdirectory of the
+/// quarantine directory, from where they can be inspected or restored by
+/// hand. They are recreated empty on the next start. Returns the
+/// subdirectory.
+///
+/// The databases must not be open.
+pub fn quarantine(data_dir: &DataDirectory, database: DatabaseKind) -> Result<PathBuf> {
+    let target_dir = data_dir
+        .root_dir_path()
+        .join(QUARANTINE_DIRECTORY_NAME)
+        .join(format!(
+            "{database}-{}",
+            Timestamp::now().to_millis() / 1000
+        ));
+    std::fs::create_dir_all(&target_dir)
+        .with_context(|| format!("could not create {}", target_dir.display()))?;
+
+    for path in database.paths(data_dir) {
+        if !path.exists() {
+            continue;
+        }
+        let file_name = path.file_name().unwrap_or(path.as_os_str());
+        let target = target_dir.join(file_name);
+        move_dir(&path, &target)?;
+    }
+
+    Ok(target_dir)
+}
+
+fn move_dir(from: &Path, to: &Path) -> Result<()> {
+    std::fs::rename(from, to)
+        .with_context(|| format!("could not move {} to {}", from.display(), to.display()))
+}
+
+#[cfg(test)]
+mod db_diagnostics_tests {
+    use tracing_test::traced_test;
+
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::database::BlockIndexKey;
+    use crate::models::state::wallet::WalletSecret;
+    use crate::tests::shared::add_block_to_archival_state;
+    use crate::tests::shared::make_mock_block;
+    use crate::tests::shared::mock_genesis_archival_state;
+    use crate::tests::shared::unit_test_data_directory;
+
+    #[traced_test]
+    #[tokio::test]
+    async fn consistency_check_finds_missing_and_truncated_blocks() {
+        let network = Network::RegTest;
+        let (mut archival_state, _peer_db_lock, data_dir) =
+            mock_genesis_archival_state(network).await;
+        assert!(archival_state
+            .check_consistency(STARTUP_CHECK_DEPTH)
+            .await
+            .is_empty());
+
+        let address = WalletSecret::new_random()
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+        let genesis = archival_state.genesis_block().clone();
+        let block_1 = make_mock_block(&genesis, None, address, rand::random()).0;
+        add_block_to_archival_state(&mut archival_state, block_1.clone())
+            .await
+            .unwrap();
+        assert!(archival_state
+            .check_consistency(STARTUP_CHECK_DEPTH)
+            .await
+            .is_empty());
+
+        // truncate the block file
+        let record = archival_state
+            .block_index_db
+            .get(BlockIndexKey::Block(block_1.hash()))
+            .await
+            .unwrap()
+            .as_block_record();
+        let block_file_path = data_dir.block_file_path(record.file_location.file_index);
+        let block_file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(block_file_path)
+            .unwrap();
+        block_file.set_len(record.file_location.offset).unwrap();
+
+        let issues = archival_state.check_consistency(STARTUP_CHECK_DEPTH).await;
+        assert!(matches!(
+            issues.as_slice(),
+            [DbIssue::BlockUnreadable { digest, .. }] if *digest == block_1.hash()
+        ));
+
+        // lose the block record
+        archival_state
+            .block_index_db
+            .delete(BlockIndexKey::Block(block_1.hash()))
+            .await;
+        assert_eq!(
+            vec![DbIssue::BlockRecordMissing {
+                digest: block_1.hash()
+            }],
+            archival_state.check_consistency(STARTUP_CHECK_DEPTH).await
+        );
+    }
+
+    #[test]
+    fn quarantine_moves_database_directories() {
+        let data_dir = unit_test_data_directory(Network::RegTest).unwrap();
+        let block_index_path = data_dir.block_index_database_dir_path();
+        std::fs::create_dir_all(&block_index_path).unwrap();
+        std::fs::write(block_index_path.join("CURRENT"), b"corrupt").unwrap();
+
+        let target_dir = quarantine(&data_dir, DatabaseKind::Chain).unwrap();
+        assert!(!block_index_path.exists());
+        assert!(target_dir
+            .join(block_index_path.file_name().unwrap())
+            .join("CURRENT")
+            .exists());
+    }
+}
@@ -0,0 +1,186 @@
+//! Append-only journal of state transitions, for operators to audit what the
+//! node did after the fact.
+//!
+//! Unlike [`NodeEvent`](super::node_event::NodeEvent)s, which are published to
+//! live subscribers and dropped if nobody listens, journal entries are written
+//! to a database in the data directory as the transitions happen, and are
+//! never modified or deleted by the node. Entries are keyed by the time they
+//! were recorded, so they can be queried by time range.
+
+use std::net::IpAddr;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use twenty_first::math::digest::Digest;
+
+use crate::config_models::data_directory::DataDirectory;
+use crate::database::create_db_if_missing;
+use crate::database::NeptuneLevelDb;
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::prelude::twenty_first;
+use crate::util_types::json_schema::DigestSchema;
+
+pub const EVENT_JOURNAL_DB_NAME: &str = "event_journal";
+
+/// A state transition recorded in the journal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum JournalEvent {
+    /// A block became the tip of the canonical chain
+    BlockAccepted {
+        #[schemars(with = "DigestSchema")]
+        digest: Digest,
+        height: BlockHeight,
+    },
+
+    /// The new tip does not extend the previous tip
+    Reorganization {
+        #[schemars(with = "DigestSchema")]
+        old_tip: Digest,
+        old_height: BlockHeight,
+        #[schemars(with = "DigestSchema")]
+        new_tip: Digest,
+        new_height: BlockHeight,
+    },
+
+    /// The wallet received a UTXO in a block
+    UtxoReceived {
+        amount: NeptuneCoins,
+        #[schemars(with = "DigestSchema")]
+        block: Digest,
+    },
+
+    /// A UTXO of the wallet was spent in a block
+    UtxoSpent {
+        amount: NeptuneCoins,
+        #[schemars(with = "DigestSchema")]
+        block: Digest,
+    },
+
+    /// A peer was disconnected for falling below the tolerated standing
+    PeerBanned { ip: IpAddr, reason: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct JournalEntry {
+    /// When the event was recorded
+    pub timestamp: Timestamp,
+    pub event: JournalEvent,
+}
+
+/// Key of a journal entry: the time it was recorded, in milliseconds since
+/// the Unix epoch, and a counter distinguishing entries recorded in the same
+/// millisecond.
+type JournalKey = (u64, u64);
+
+#[derive(Debug, Clone)]
+pub struct EventJournal {
+    db: NeptuneLevelDb<JournalKey, JournalEntry>,
+    counter: Arc<AtomicU64>,
+}
+
+impl EventJournal {
+    pub async fn open(data_dir: &DataDirectory) -> Result<Self> {
+        DataDirectory::create_dir_if_not_exists(&data_dir.database_dir_path()).await?;
+        let db = NeptuneLevelDb::new(
+            &data_dir.event_journal_database_dir_path(),
+            &create_db_if_missing(),
+        )
+        .await?;
+
+        Ok(Self {
+            db,
+            counter: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// Append an event, recorded now.
+    pub(crate) async fn record(&mut self, event: JournalEvent) {
+        let timestamp = Timestamp::now();
+        let key = (
+            timestamp.to_millis(),
+            self.counter.fetch_add(1, Ordering::Relaxed),
+        );
+        self.db.put(key, JournalEntry { timestamp, event }).await;
+    }
+
+    /// The entries recorded between `from` and `to`, both inclusive, oldest
+    /// first. At most `max_num`, the most recent ones if there are more.
+    pub(crate) async fn query(
+        &self,
+        from: Timestamp,
+        to: Timestamp,
+        max_num: usize,
+    ) -> Vec<JournalEntry> {
+        // The database iterator is synchronous and reads all keys.
+        let db = self.db.clone();
+        let mut entries = tokio::task::spawn_blocking(move || {
+            db.iter()
+                .filter(|((millis, _), _)| (from.to_millis()..=to.to_millis()).contains(millis))
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap();
+
+        entries.sort_by_key(|(key, _)| *key);
+        let num_skipped = entries.len().saturating_sub(max_num);
+        entries
+            .into_iter()
+            .skip(num_skipped)
+            .map(|(_, entry)| entry)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod event_journal_tests {
+    use super::*;
+    use crate::config_models::network::Network;
+
+    #[tokio::test]
+    async fn entries_are_queried_by_time_range() {
+        let data_dir = DataDirectory::get(
+            Some(std::env::temp_dir().join(format!("journal-{}", rand::random::<u64>()))),
+            Network::RegTest,
+        )
+        .unwrap();
+        let mut journal = EventJournal::open(&data_dir).await.unwrap();
+
+        let before = Timestamp::now();
+        for height in 1u64..=3 {
+            journal
+                .record(JournalEvent::BlockAccepted {
+                    digest: Digest::default(),
+                    height: height.into(),
+                })
+                .await;
+        }
+        journal
+            .record(JournalEvent::PeerBanned {
+                ip: IpAddr::from([10, 0, 0, 1]),
+                reason: "invalid block".to_owned(),
+            })
+            .await;
+        let after = Timestamp::now();
+
+        let all = journal.query(before, after, 100).await;
+        assert_eq!(4, all.len());
+        assert!(matches!(
+            all[0].event,
+            JournalEvent::BlockAccepted { height, .. } if height == 1u64.into()
+        ));
+        assert!(matches!(all[3].event, JournalEvent::PeerBanned { .. }));
+
+        let most_recent = journal.query(before, after, 2).await;
+        assert_eq!(all[2..], most_recent);
+
+        let earlier = before - Timestamp::hours(1);
+        assert!(journal.query(earlier, earlier, 100).await.is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+//! Histogram of the fee densities in the mempool, for wallets to show where a
+//! transaction with a given fee would queue.
+
+use num_rational::BigRational as FeeDensity;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::mempool::fee_per_kb;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+
+/// Maximum number of buckets of a fee histogram.
+pub const MAX_FEE_HISTOGRAM_BUCKETS: usize = 100;
+
+/// The mempool transactions within a range of fee densities.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct FeeHistogramBucket {
+    /// Lowest fee, per 1000 bytes, of the transactions in the bucket
+    pub min_fee_per_kb: NeptuneCoins,
+
+    /// Highest fee, per 1000 bytes, of the transactions in the bucket
+    pub max_fee_per_kb: NeptuneCoins,
+    pub num_transactions: usize,
+
+    /// Combined size in bytes of the transactions in the bucket
+    pub size: usize,
+
+    /// Combined size in bytes of the transactions in this bucket and in the
+    /// buckets of higher fee densities, i.e., of the transactions a miner
+    /// picks before any transaction with a lower fee than this bucket's
+    pub cumulative_size: usize,
+}
+
+/// Group transactions, given as fee density and size in order of descending
+/// fee density, into at most `bucket_count` buckets of about equal combined
+/// size. Buckets are in order of descending fee density as well.
+pub(crate) fn fee_histogram(
+    transactions: &[(FeeDensity, usize)],
+    bucket_count: usize,
+) -> Vec<FeeHistogramBucket> {
+    let bucket_count = bucket_count.clamp(1, MAX_FEE_HISTOGRAM_BUCKETS);
+    let total_size = transactions.iter().map(|(_, size)| size).sum::<usize>();
+
+    let mut buckets: Vec<(usize, FeeHistogramBucket)> = vec![];
+    let mut cumulative_size = 0;
+    for (fee_density, size) in transactions {
+        // A transaction belongs to the bucket in which it starts.
+        let bucket_index = (cumulative_size * bucket_count)
+            .checked_div(total_size)
+            .unwrap_or_default()
+            .min(bucket_count - 1);
+        cumulative_size += size;
+
+        let fee = fee_per_kb(fee_density);
+        match buckets.last_mut() {
+            Some((index, bucket)) if *index == bucket_index => {
+                bucket.min_fee_per_kb = fee;
+                bucket.num_transactions += 1;
+                bucket.size += size;
+                bucket.cumulative_size = cumulative_size;
+            }
+            _ => buckets.push((
+                bucket_index,
+                FeeHistogramBucket {
+                    min_fee_per_kb: fee,
+                    max_fee_per_kb: fee,
+                    num_transactions: 1,
+                    size: *size,
+                    cumulative_size,
+                },
+            )),
+        }
+    }
+
+    buckets.into_iter().map(|(_, bucket)| bucket).collect()
+}
+
+#[cfg(test)]
+mod fee_histogram_tests {
+    use num_bigint::BigInt;
+
+    use super::*;
+
+    fn fee_density(fee_per_kb: u32) -> FeeDensity {
+        FeeDensity::new(NeptuneCoins::new(fee_per_kb).to_nau(), BigInt::from(1000))
+    }
+
+    #[test]
+    fn buckets_split_size_evenly_in_order_of_fee_density() {
+        let transactions = [
+            (fee_density(5), 100),
+            (fee_density(4), 100),
+            (fee_density(3), 100),
+            (fee_density(2), 50),
+            (fee_density(1), 50),
+        ];
+
+        let histogram = fee_histogram(&transactions, 2);
+        assert_eq!(2, histogram.len());
+        assert_eq!(NeptuneCoins::new(5), histogram[0].max_fee_per_kb);
+        assert_eq!(NeptuneCoins::new(4), histogram[0].min_fee_per_kb);
+        assert_eq!(2, histogram[0].num_transactions);
+        assert_eq!(200, histogram[0].cumulative_size);
+        assert_eq!(NeptuneCoins::new(3), histogram[1].max_fee_per_kb);
+        assert_eq!(NeptuneCoins::new(1), histogram[1].min_fee_per_kb);
+        assert_eq!(200, histogram[1].size);
+        assert_eq!(400, histogram[1].cumulative_size);
+
+        assert_eq!(5, fee_histogram(&transactions, 1000).len());
+        assert_eq!(1, fee_histogram(&transactions, 0).len());
+        assert!(fee_histogram(&[], 10).is_empty());
+    }
+}
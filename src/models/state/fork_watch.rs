@@ -0,0 +1,173 @@
+//! Detection of consensus changes this binary does not know about.
+//!
+//! Every consensus change bumps the block header version from some activation
+//! height on. A node running an outdated binary rejects the blocks of the
+//! upgraded network and keeps extending its own, minority, chain without
+//! noticing. To warn the operator before that happens, the headers peers
+//! announce are checked against the activation table of this binary. Once
+//! enough peers announce chains that are heavier than the own chain and that
+//! carry header versions not known for their height, the node logs a warning
+//! and flags it over RPC, prompting an upgrade.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::models::blockchain::block::block_header::BlockHeader;
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+
+/// Header versions known to this binary, with the height from which each one
+/// is in effect, in order of activation. A release that changes consensus
+/// appends its version and activation height.
+const KNOWN_HEADER_VERSIONS: &[(u64, u64)] = &[(0, 0)];
+
+/// Number of distinct peers that must announce heavier chains under unknown
+/// rules before the node warns. One peer alone may simply be misbehaving.
+pub const UNKNOWN_RULES_PEER_THRESHOLD: usize = 2;
+
+/// Announcements older than this no longer count towards the threshold.
+const SIGHTING_EXPIRY: Timestamp = Timestamp::hours(24);
+
+/// The header version in effect at the given height, according to this
+/// binary.
+fn known_version_at(height: BlockHeight) -> u64 {
+    KNOWN_HEADER_VERSIONS
+        .iter()
+        .rev()
+        .find(|(_, activation_height)| u64::from(height) >= *activation_height)
+        .map(|(version, _)| *version)
+        .unwrap_or_default()
+}
+
+/// Whether the header follows the rules this binary knows for its height.
+pub(crate) fn rules_are_known(header: &BlockHeader) -> bool {
+    header.version.value() == known_version_at(header.height)
+}
+
+/// A header under unknown rules announced by a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Sighting {
+    version: u64,
+    height: BlockHeight,
+    seen_at: Timestamp,
+}
+
+/// Summary of the headers under unknown rules that peers announced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ForkWatchStatus {
+    /// Whether enough peers announced heavier chains under unknown rules for
+    /// the network to appear to have activated them
+    pub unknown_rules_active: bool,
+
+    /// Number of peers that recently announced such chains
+    pub num_peers: usize,
+
+    /// Highest unknown header version announced
+    pub highest_unknown_version: Option<u64>,
+
+    /// Highest block height announced under unknown rules
+    pub highest_height: Option<BlockHeight>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ForkWatch {
+    /// Latest header under unknown rules announced by each peer
+    sightings: HashMap<IpAddr, Sighting>,
+
+    /// Whether the warning was logged already
+    warned: bool,
+}
+
+impl ForkWatch {
+    /// Record a header a peer announced with more cumulative proof-of-work
+    /// than the own tip. Headers under known rules are ignored.
+    pub(crate) fn observe(&mut self, peer: IpAddr, header: &BlockHeader, now: Timestamp) {
+        if rules_are_known(header) {
+            return;
+        }
+
+        self.sightings.insert(
+            peer,
+            Sighting {
+                version: header.version.value(),
+                height: header.height,
+                seen_at: now,
+            },
+        );
+
+        let status = self.status(now);
+        if status.unknown_rules_active && !self.warned {
+            self.warned = true;
+            warn!(
+                "{} peers announce heavier chains with block header version {}, which this \
+                version of neptune-core does not know. The network has likely activated new \
+                consensus rules. Upgrade neptune-core to avoid following a minority chain.",
+                status.num_peers,
+                status.highest_unknown_version.unwrap_or_default(),
+            );
+        }
+    }
+
+    pub fn status(&self, now: Timestamp) -> ForkWatchStatus {
+        let recent = self
+            .sightings
+            .values()
+            .filter(|sighting| now - sighting.seen_at <= SIGHTING_EXPIRY)
+            .collect::<Vec<_>>();
+
+        ForkWatchStatus {
+            unknown_rules_active: recent.len() >= UNKNOWN_RULES_PEER_THRESHOLD,
+            num_peers: recent.len(),
+            highest_unknown_version: recent.iter().map(|sighting| sighting.version).max(),
+            highest_height: recent.iter().map(|sighting| sighting.height).max(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod fork_watch_tests {
+    use tasm_lib::twenty_first::math::b_field_element::BFieldElement;
+
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::blockchain::block::block_header::BLOCK_HEADER_VERSION;
+    use crate::models::blockchain::block::Block;
+
+    #[test]
+    fn unknown_rules_are_flagged_once_enough_peers_announce_them() {
+        let (latest_version, _) = KNOWN_HEADER_VERSIONS.last().unwrap();
+        assert_eq!(BLOCK_HEADER_VERSION.value(), *latest_version);
+
+        let mut header = Block::genesis_block(Network::RegTest).header().clone();
+        assert!(rules_are_known(&header));
+
+        let now = Timestamp::now();
+        let mut fork_watch = ForkWatch::default();
+        fork_watch.observe(IpAddr::from([10, 0, 0, 1]), &header, now);
+        assert_eq!(0, fork_watch.status(now).num_peers);
+
+        header.version = BFieldElement::new(1);
+        header.height = 500u64.into();
+        assert!(!rules_are_known(&header));
+
+        // the same peer counts once
+        fork_watch.observe(IpAddr::from([10, 0, 0, 2]), &header, now);
+        fork_watch.observe(IpAddr::from([10, 0, 0, 2]), &header, now);
+        assert!(!fork_watch.status(now).unknown_rules_active);
+
+        fork_watch.observe(IpAddr::from([10, 0, 0, 3]), &header, now);
+        let status = fork_watch.status(now);
+        assert!(status.unknown_rules_active);
+        assert_eq!(2, status.num_peers);
+        assert_eq!(Some(1), status.highest_unknown_version);
+        assert_eq!(Some(500u64.into()), status.highest_height);
+
+        let later = now + SIGHTING_EXPIRY + Timestamp::seconds(1);
+        assert!(!fork_watch.status(later).unknown_rules_active);
+    }
+}
@@ -9,6 +9,11 @@
 //! `queue` maintains transactions id's ordered by 'fee density'. Usually, we
 //! are interested in the transaction with either the highest or the lowest 'fee
 //! density'.
+//!
+//! When the mempool exceeds its maximum size, the transactions with the lowest
+//! fee density are evicted first. The combined size of all transactions is
+//! tracked as they are inserted and removed, such that enforcing the limit does
+//! not require measuring the whole mempool.
 
 use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
@@ -17,6 +22,7 @@ use std::iter::Rev;
 
 use bytesize::ByteSize;
 use get_size::GetSize;
+use itertools::Itertools;
 /// `FeeDensity` is a measure of 'Fee/Bytes' or 'reward per storage unit' for
 /// transactions.  Different strategies are possible for selecting transactions
 /// to mine, but a simple one is to pick transactions in descending order of
@@ -34,14 +40,18 @@ use get_size::GetSize;
 /// If available space is 4, then the greedy choice on `FeeDensity` would select
 /// the set { TransactionA } while the optimal solution is { TransactionB,
 /// TransactionC }.
+use num_bigint::BigInt;
 use num_rational::BigRational as FeeDensity;
 use num_traits::Zero;
 use priority_queue::double_priority_queue::iterators::IntoSortedIter;
 use priority_queue::DoublePriorityQueue;
 use tasm_lib::triton_vm::proof::Proof;
+use tracing::debug;
 use tracing::error;
 use twenty_first::math::digest::Digest;
 
+use super::fee_histogram::fee_histogram;
+use super::fee_histogram::FeeHistogramBucket;
 use super::transaction_kernel_id::TransactionKernelId;
 use super::ProvingLock;
 use crate::models::blockchain::block::Block;
@@ -53,6 +63,7 @@ use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
 use crate::models::peer::transfer_transaction::TransactionProofQuality;
 use crate::models::proof_abstractions::tasm::program::TritonProverSync;
 use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::models::shared::SIZE_20MB_IN_BYTES;
 use crate::prelude::twenty_first;
 use crate::util_types::mutator_set::mutator_set_accumulator::MutatorSetAccumulator;
 
@@ -64,8 +75,31 @@ pub const MEMPOOL_IGNORE_TRANSACTIONS_THIS_MANY_SECS_AHEAD: u64 = 5 * 60;
 
 pub const TRANSACTION_NOTIFICATION_AGE_LIMIT_IN_SECS: u64 = 60 * 60 * 24;
 
+/// Number of most recent blocks for which the transactions they removed from
+/// the mempool are kept, to be resurrected if the block is abandoned.
+///
+/// Transactions removed by a block are synced to that block's parent, and
+/// cannot be rolled back further. So only the transactions removed by the
+/// abandoned child of the fork point can be resurrected. Those removed by
+/// later abandoned blocks are synced to an abandoned block, and are lost.
+/// Keeping the transactions of only the most recent block covers
+/// reorganizations of depth one, which is by far the most common kind.
+pub const MEMPOOL_RESURRECTION_DEPTH: usize = 1;
+
+/// Number of most recent blocks whose inclusion of mempool transactions
+/// informs fee estimation.
+pub const FEE_ESTIMATION_HISTORY_DEPTH: usize = 20;
+
 type LookupItem<'a> = (TransactionKernelId, &'a Transaction);
 
+/// Fee per 1000 bytes of a transaction with the given fee density, rounded up.
+pub(crate) fn fee_per_kb(fee_density: &FeeDensity) -> NeptuneCoins {
+    let nau = (fee_density.clone() * BigInt::from(1000))
+        .ceil()
+        .to_integer();
+    NeptuneCoins::from_nau(nau).expect("fee density is bounded by the fees")
+}
+
 /// Represents a mempool state change.
 ///
 /// For purpose of notifying interested parties
@@ -83,11 +117,26 @@ pub enum MempoolEvent {
     UpdateTxMutatorSet(TransactionKernelId, Transaction),
 }
 
+/// Transactions that were removed from the mempool because a block spent
+/// their inputs.
+#[derive(Debug)]
+struct RemovedByBlock {
+    block_digest: Digest,
+
+    /// The parent of the block, which the transactions are synced to
+    parent_digest: Digest,
+
+    transactions: Vec<Transaction>,
+}
+
 #[derive(Debug, GetSize)]
 pub struct Mempool {
-    /// Maximum size this data structure may take up in memory.
+    /// Maximum combined size of the transactions in the mempool.
     max_total_size: usize,
 
+    /// Combined size of the transactions in `tx_dictionary`.
+    transactions_size: usize,
+
     /// If set, represents the maximum number of transactions allowed in the
     /// mempool. If None, mempool is only restricted by size.
     max_length: Option<usize>,
@@ -104,6 +153,17 @@ pub struct Mempool {
     /// Records the digest of the block that the transactions were synced to.
     /// Used to discover reorganizations.
     tip_digest: Digest,
+
+    /// Transactions removed by the most recent blocks, oldest first. Their
+    /// size is bounded by that of [`MEMPOOL_RESURRECTION_DEPTH`] blocks, and
+    /// does not count towards the mempool's max size.
+    #[get_size(ignore)]
+    removed_by_recent_blocks: Vec<RemovedByBlock>,
+
+    /// Lowest fee density of the mempool transactions included by each of
+    /// the most recent blocks that included any, oldest first.
+    #[get_size(ignore)]
+    recent_inclusion_fee_densities: Vec<FeeDensity>,
 }
 
 /// note that all methods that modify state and result in a MempoolEvent
@@ -124,10 +184,13 @@ impl Mempool {
         let max_total_size = max_total_size.0.try_into().unwrap();
         Self {
             max_total_size,
+            transactions_size: 0,
             max_length: max_num_transactions,
             tx_dictionary: table,
             queue,
             tip_digest,
+            removed_by_recent_blocks: vec![],
+            recent_inclusion_fee_densities: vec![],
         }
     }
 
@@ -253,6 +316,9 @@ impl Mempool {
     ///   1 event:  AddTx. tx does not replace an older one.
     ///   0 events: tx not added because an older matching tx has a higher fee.
     ///
+    /// followed by a RemoveTx event for every transaction evicted to keep the
+    /// mempool within its limits, which may be the inserted transaction itself.
+    ///
     /// # Panics
     ///
     /// Panics if the transaction's proof is of the wrong type.
@@ -292,6 +358,7 @@ impl Mempool {
         let txid = transaction.kernel.txid();
 
         self.queue.push(txid, transaction.fee_density());
+        self.transactions_size += transaction.get_size();
         self.tx_dictionary.insert(txid, transaction.to_owned());
         events.push(MempoolEvent::AddTx(transaction));
 
@@ -300,8 +367,8 @@ impl Mempool {
             self.queue.len(),
             "mempool's table and queue length must agree prior to shrink"
         );
-        self.shrink_to_max_size();
-        self.shrink_to_max_length();
+        events.extend(self.shrink_to_max_size());
+        events.extend(self.shrink_to_max_length());
         assert_eq!(
             self.tx_dictionary.len(),
             self.queue.len(),
@@ -315,6 +382,7 @@ impl Mempool {
     pub(super) fn remove(&mut self, transaction_id: TransactionKernelId) -> Option<MempoolEvent> {
         self.tx_dictionary.remove(&transaction_id).map(|tx| {
             self.queue.remove(&transaction_id);
+            self.transactions_size -= tx.get_size();
             debug_assert_eq!(self.tx_dictionary.len(), self.queue.len());
             MempoolEvent::RemoveTx(tx)
         })
@@ -395,6 +463,7 @@ impl Mempool {
         if let Some((transaction_digest, fee_density)) = self.queue.pop_max() {
             if let Some(transaction) = self.tx_dictionary.remove(&transaction_digest) {
                 debug_assert_eq!(self.tx_dictionary.len(), self.queue.len());
+                self.transactions_size -= transaction.get_size();
 
                 let event = MempoolEvent::RemoveTx(transaction);
 
@@ -412,6 +481,7 @@ impl Mempool {
         if let Some((transaction_digest, fee_density)) = self.queue.pop_min() {
             if let Some(transaction) = self.tx_dictionary.remove(&transaction_digest) {
                 debug_assert_eq!(self.tx_dictionary.len(), self.queue.len());
+                self.transactions_size -= transaction.get_size();
 
                 let event = MempoolEvent::RemoveTx(transaction);
 
@@ -475,13 +545,19 @@ impl Mempool {
         block: &Block,
         prover_lock: &ProvingLock,
     ) -> Vec<MempoolEvent> {
-        // If we discover a reorganization, we currently just clear the mempool,
-        // as we don't have the ability to roll transaction removal record integrity
-        // proofs back to previous blocks. It would be nice if we could handle a
-        // reorganization that's at least a few blocks deep though.
+        // If we discover a reorganization, we clear the mempool, as we don't
+        // have the ability to roll transaction removal record integrity proofs
+        // back to previous blocks. Transactions removed by abandoned children
+        // of the new block's parent are still synced to that parent though,
+        // so they are resurrected, and then handled like any other
+        // transaction. Transactions removed by deeper abandoned blocks are
+        // synced to abandoned blocks, and are not resurrected. See
+        // [`MEMPOOL_RESURRECTION_DEPTH`].
         let previous_block_digest = block.header().prev_block_digest;
+        let mut resurrection_events = vec![];
         if self.tip_digest != previous_block_digest {
             self.clear();
+            resurrection_events = self.resurrect_transactions_synced_to(previous_block_digest);
         }
 
         // The general strategy is to check whether the SWBF index set of a given
@@ -522,7 +598,10 @@ impl Mempool {
         };
 
         // Remove the transactions that become invalid with this block
-        let mut events = self.retain(keep);
+        let removal_events = self.retain(keep);
+        self.remember_removed_by_block(block, &removal_events);
+        self.record_inclusion_fee_density(&removal_events);
+        let mut events = [resurrection_events, removal_events].concat();
 
         // Update the remaining transactions so their mutator set data is still valid
         // But kick out those transactions that we were unable to update.
@@ -538,6 +617,8 @@ impl Mempool {
                 )
                 .await
             {
+                self.transactions_size -= tx.get_size();
+                self.transactions_size += new_tx.get_size();
                 *tx = new_tx;
                 events.push(MempoolEvent::UpdateTxMutatorSet(*tx_id, (*tx).clone()));
             } else {
@@ -552,7 +633,7 @@ impl Mempool {
         // Maintaining the mutator set data could have increased the size of the
         // transactions in the mempool. So we should shrink it to max size after
         // applying the block.
-        self.shrink_to_max_size();
+        events.extend(self.shrink_to_max_size());
 
         // Update the sync-label to keep track of reorganizations
         let current_block_digest = block.hash();
@@ -561,27 +642,200 @@ impl Mempool {
         events
     }
 
-    /// Shrink the memory pool to the value of its `max_size` field.
-    /// Likely computes in O(n).
-    fn shrink_to_max_size(&mut self) {
+    /// Keep the transactions removed by a block, such that they can be
+    /// resurrected if the block is abandoned.
+    fn remember_removed_by_block(&mut self, block: &Block, removal_events: &[MempoolEvent]) {
+        let transactions = removal_events
+            .iter()
+            .filter_map(|event| match event {
+                MempoolEvent::RemoveTx(transaction) => Some(transaction.to_owned()),
+                _ => None,
+            })
+            .collect_vec();
+        if transactions.is_empty() {
+            return;
+        }
+
+        self.removed_by_recent_blocks.push(RemovedByBlock {
+            block_digest: block.hash(),
+            parent_digest: block.header().prev_block_digest,
+            transactions,
+        });
+        let excess = self
+            .removed_by_recent_blocks
+            .len()
+            .saturating_sub(MEMPOOL_RESURRECTION_DEPTH);
+        self.removed_by_recent_blocks.drain(..excess);
+    }
+
+    /// Keep the lowest fee density of the transactions a block removed from
+    /// the mempool, which it included, for fee estimation.
+    fn record_inclusion_fee_density(&mut self, removal_events: &[MempoolEvent]) {
+        let lowest_fee_density = removal_events
+            .iter()
+            .filter_map(|event| match event {
+                MempoolEvent::RemoveTx(transaction) => Some(transaction.fee_density()),
+                _ => None,
+            })
+            .min();
+        let Some(lowest_fee_density) = lowest_fee_density else {
+            return;
+        };
+
+        self.recent_inclusion_fee_densities.push(lowest_fee_density);
+        let excess = self
+            .recent_inclusion_fee_densities
+            .len()
+            .saturating_sub(FEE_ESTIMATION_HISTORY_DEPTH);
+        self.recent_inclusion_fee_densities.drain(..excess);
+    }
+
+    /// Estimate the fee, per 1000 bytes of transaction, for a transaction to
+    /// be included within `target_blocks` blocks.
+    ///
+    /// The estimate is the larger of the fee density needed to outbid the
+    /// mempool transactions that do not fit into `target_blocks` blocks, and
+    /// a fee density that was enough for inclusion in recent blocks: the
+    /// median of the lowest included fee densities for a target of one block,
+    /// and lower quantiles for later targets.
+    pub fn estimate_fee(&self, target_blocks: usize) -> NeptuneCoins {
+        self.estimate_fee_with_block_capacity(target_blocks, SIZE_20MB_IN_BYTES)
+    }
+
+    fn estimate_fee_with_block_capacity(
+        &self,
+        target_blocks: usize,
+        block_capacity: usize,
+    ) -> NeptuneCoins {
+        let target_blocks = target_blocks.max(1);
+
+        // The fee density of the densest transaction that is not mined within
+        // the target, if any
+        let capacity = block_capacity.saturating_mul(target_blocks);
+        let mut cumulative_size = 0usize;
+        let mut mempool_estimate = None;
+        for (txid, fee_density) in self.get_sorted_iter() {
+            cumulative_size += self.get(txid).map(|tx| tx.get_size()).unwrap_or_default();
+            if cumulative_size > capacity {
+                mempool_estimate = Some(fee_density);
+                break;
+            }
+        }
+
+        let history_estimate = self
+            .recent_inclusion_fee_densities
+            .iter()
+            .sorted()
+            .nth(self.recent_inclusion_fee_densities.len() / (target_blocks + 1))
+            .cloned();
+
+        let fee_density = [mempool_estimate, history_estimate]
+            .into_iter()
+            .flatten()
+            .max()
+            .unwrap_or_else(FeeDensity::zero);
+        fee_per_kb(&fee_density)
+    }
+
+    /// Summarize the transactions in the mempool by fee density, in at most
+    /// `bucket_count` buckets of about equal combined size.
+    ///
+    /// Best effort: the mempool changes with every transaction and block.
+    pub fn fee_histogram(&self, bucket_count: usize) -> Vec<FeeHistogramBucket> {
+        let transactions = self
+            .get_sorted_iter()
+            .map(|(txid, fee_density)| {
+                let size = self.get(txid).map(|tx| tx.get_size()).unwrap_or_default();
+                (fee_density, size)
+            })
+            .collect_vec();
+
+        fee_histogram(&transactions, bucket_count)
+    }
+
+    /// Re-insert the transactions removed by blocks with the given parent,
+    /// which must be the new tip's parent such that those blocks are
+    /// abandoned. The transactions are synced to that parent, so their
+    /// mutator set data can be updated with the new tip.
+    ///
+    /// All other kept transactions were either removed by deeper abandoned
+    /// blocks, or confirmed by blocks that remain canonical, so they are
+    /// dropped.
+    fn resurrect_transactions_synced_to(&mut self, parent_digest: Digest) -> Vec<MempoolEvent> {
+        let mut events = vec![];
+        for removed in std::mem::take(&mut self.removed_by_recent_blocks) {
+            if removed.parent_digest != parent_digest {
+                debug!(
+                    "Dropping {} transactions of block {}, as they are not synced to the fork point",
+                    removed.transactions.len(),
+                    removed.block_digest
+                );
+                continue;
+            }
+
+            debug!(
+                "Resurrecting {} transactions of abandoned block {}",
+                removed.transactions.len(),
+                removed.block_digest
+            );
+            for transaction in removed.transactions {
+                events.extend(self.insert(transaction));
+            }
+        }
+
+        events
+    }
+
+    /// Combined size in bytes of the transactions in the mempool.
+    /// Computes in O(1)
+    pub fn transactions_size(&self) -> usize {
+        self.transactions_size
+    }
+
+    /// Shrink the memory pool to the value of its `max_total_size` field by
+    /// evicting the transactions with the lowest [`FeeDensity`].
+    ///
+    /// Computes in O(k lg N) for k evicted transactions.
+    fn shrink_to_max_size(&mut self) -> Vec<MempoolEvent> {
         // Repeately remove the least valuable transaction
-        while self.get_size() > self.max_total_size && self.pop_min().is_some() {
-            continue;
+        let mut events = vec![];
+        while self.transactions_size > self.max_total_size {
+            let Some((event, _)) = self.pop_min() else {
+                break;
+            };
+            events.push(event);
         }
 
-        self.shrink_to_fit();
+        if !events.is_empty() {
+            debug!(
+                "Evicted {} transactions to keep mempool within {} bytes",
+                events.len(),
+                self.max_total_size
+            );
+            self.shrink_to_fit();
+        }
+
+        events
     }
 
     /// Shrink the memory pool to the value of its `max_length` field,
     /// if that field is set.
-    fn shrink_to_max_length(&mut self) {
+    fn shrink_to_max_length(&mut self) -> Vec<MempoolEvent> {
+        let mut events = vec![];
         if let Some(max_length) = self.max_length {
-            while self.len() > max_length && self.pop_min().is_some() {
-                continue;
+            while self.len() > max_length {
+                let Some((event, _)) = self.pop_min() else {
+                    break;
+                };
+                events.push(event);
             }
         }
 
-        self.shrink_to_fit()
+        if !events.is_empty() {
+            self.shrink_to_fit();
+        }
+
+        events
     }
 
     /// Shrinks internal data structures as much as possible.
@@ -749,6 +1003,58 @@ mod tests {
         assert!(!mempool.is_empty())
     }
 
+    #[traced_test]
+    #[tokio::test]
+    async fn fee_estimate_outbids_transactions_that_do_not_fit() {
+        let mempool = setup_mock_mempool(10, Network::Main).await;
+        assert!(mempool.estimate_fee(1).is_zero());
+
+        // A block that fits only the five densest transactions
+        let sorted = mempool.get_sorted_iter().collect_vec();
+        let block_capacity = sorted[..5]
+            .iter()
+            .map(|(txid, _)| mempool.get(*txid).unwrap().get_size())
+            .sum();
+        let fee_per_kb = (sorted[5].1.clone() * BigInt::from(1000))
+            .ceil()
+            .to_integer();
+        assert_eq!(
+            NeptuneCoins::from_nau(fee_per_kb).unwrap(),
+            mempool.estimate_fee_with_block_capacity(1, block_capacity)
+        );
+        assert!(
+            mempool.estimate_fee_with_block_capacity(2, block_capacity)
+                <= mempool.estimate_fee_with_block_capacity(1, block_capacity)
+        );
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn fee_estimate_follows_recent_inclusions() {
+        let mut mempool = setup_mock_mempool(0, Network::Main).await;
+        assert!(mempool.estimate_fee(1).is_zero());
+
+        let txs =
+            make_plenty_mock_transaction_with_primitive_witness(FEE_ESTIMATION_HISTORY_DEPTH + 3);
+        for tx in &txs {
+            mempool.record_inclusion_fee_density(&[MempoolEvent::RemoveTx(tx.clone())]);
+        }
+        assert_eq!(
+            FEE_ESTIMATION_HISTORY_DEPTH,
+            mempool.recent_inclusion_fee_densities.len()
+        );
+
+        let mut densities = txs[3..].iter().map(|tx| tx.fee_density()).collect_vec();
+        densities.sort();
+        let median = densities[densities.len() / 2].clone();
+        let fee_per_kb = (median * BigInt::from(1000)).ceil().to_integer();
+        assert_eq!(
+            NeptuneCoins::from_nau(fee_per_kb).unwrap(),
+            mempool.estimate_fee(1)
+        );
+        assert!(mempool.estimate_fee(10) <= mempool.estimate_fee(1));
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn most_dense_proof_collection_test() {
@@ -912,7 +1218,7 @@ mod tests {
         let in_seven_months = now + Timestamp::months(7);
         let in_eight_months = now + Timestamp::months(8);
         let in_nine_months = now + Timestamp::months(9);
-        let (tx_by_bob, maybe_change_output) = bob
+        let (tx_by_bob, change_outputs) = bob
             .lock_guard()
             .await
             .create_transaction_with_prover_capability(
@@ -929,7 +1235,7 @@ mod tests {
 
         // inform wallet of any expected utxos from this tx.
         let expected_utxos = bob.lock_guard().await.wallet_state.extract_expected_utxos(
-            utxos_from_bob.concat_with(maybe_change_output),
+            utxos_from_bob.concat_with(change_outputs),
             UtxoNotifier::Myself,
         );
         bob.lock_guard_mut()
@@ -1281,6 +1587,162 @@ mod tests {
         );
     }
 
+    #[traced_test]
+    #[tokio::test]
+    async fn transactions_of_abandoned_block_are_resurrected() {
+        let network = Network::Main;
+        let bob_wallet = WalletSecret::devnet_wallet();
+        let bob_key = bob_wallet.nth_generation_spending_key_for_tests(0);
+        let bob = mock_genesis_global_state(network, 2, bob_wallet).await;
+        let mut rng: StdRng = StdRng::seed_from_u64(0x5e55u64);
+        let alice_address = WalletSecret::new_pseudorandom(rng.gen())
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+
+        let genesis_block = Block::genesis_block(network);
+        let in_seven_months = genesis_block.kernel.header.timestamp + Timestamp::months(7);
+        let (tx_by_bob, _maybe_change_output) = bob
+            .lock_guard()
+            .await
+            .create_transaction_with_prover_capability(
+                vec![TxOutput::onchain_native_currency(
+                    NeptuneCoins::new(1),
+                    rng.gen(),
+                    alice_address.into(),
+                )]
+                .into(),
+                bob_key.into(),
+                UtxoNotificationMedium::OffChain,
+                NeptuneCoins::new(1),
+                in_seven_months,
+                TxProvingCapability::PrimitiveWitness,
+                &TritonProverSync::dummy(),
+            )
+            .await
+            .unwrap();
+        let txid = tx_by_bob.kernel.txid();
+
+        let mut mempool = Mempool::new(ByteSize::gb(1), None, genesis_block.hash());
+        mempool.insert(tx_by_bob.clone());
+
+        // Block 1a confirms the transaction
+        let block_1a =
+            Block::block_template_invalid_proof(&genesis_block, tx_by_bob, in_seven_months, None);
+        mempool
+            .update_with_block(
+                genesis_block.kernel.body.mutator_set_accumulator.clone(),
+                &block_1a,
+                &AtomicMutex::from(()),
+            )
+            .await;
+        assert!(mempool.is_empty());
+
+        // Block 1b does not, so the transaction must return to the mempool
+        let (block_1b, _, _) = make_mock_block(
+            &genesis_block,
+            Some(in_seven_months),
+            alice_address,
+            rng.gen(),
+        );
+        let events = mempool
+            .update_with_block(
+                genesis_block.kernel.body.mutator_set_accumulator.clone(),
+                &block_1b,
+                &AtomicMutex::from(()),
+            )
+            .await;
+        assert!(mempool.contains(txid));
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, MempoolEvent::AddTx(tx) if tx.kernel.txid() == txid)));
+        assert!(mempool
+            .get(txid)
+            .unwrap()
+            .is_confirmable_relative_to(&block_1b.body().mutator_set_accumulator));
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn transactions_of_abandoned_block_are_resurrected_in_depth_two_reorg() {
+        let network = Network::Main;
+        let bob_wallet = WalletSecret::devnet_wallet();
+        let bob_key = bob_wallet.nth_generation_spending_key_for_tests(0);
+        let bob = mock_genesis_global_state(network, 2, bob_wallet).await;
+        let mut rng: StdRng = StdRng::seed_from_u64(0x5e56u64);
+        let alice_address = WalletSecret::new_pseudorandom(rng.gen())
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+
+        let genesis_block = Block::genesis_block(network);
+        let in_seven_months = genesis_block.kernel.header.timestamp + Timestamp::months(7);
+        let (tx_by_bob, _maybe_change_output) = bob
+            .lock_guard()
+            .await
+            .create_transaction_with_prover_capability(
+                vec![TxOutput::onchain_native_currency(
+                    NeptuneCoins::new(1),
+                    rng.gen(),
+                    alice_address.into(),
+                )]
+                .into(),
+                bob_key.into(),
+                UtxoNotificationMedium::OffChain,
+                NeptuneCoins::new(1),
+                in_seven_months,
+                TxProvingCapability::PrimitiveWitness,
+                &TritonProverSync::dummy(),
+            )
+            .await
+            .unwrap();
+        let txid = tx_by_bob.kernel.txid();
+
+        let mut mempool = Mempool::new(ByteSize::gb(1), None, genesis_block.hash());
+        mempool.insert(tx_by_bob.clone());
+
+        // Blocks 1a and 2a form the old branch, and 1a confirms the
+        // transaction
+        let block_1a =
+            Block::block_template_invalid_proof(&genesis_block, tx_by_bob, in_seven_months, None);
+        let (block_2a, _, _) =
+            make_mock_block(&block_1a, Some(in_seven_months), alice_address, rng.gen());
+        for (parent, block) in [(&genesis_block, &block_1a), (&block_1a, &block_2a)] {
+            mempool
+                .update_with_block(
+                    parent.kernel.body.mutator_set_accumulator.clone(),
+                    block,
+                    &AtomicMutex::from(()),
+                )
+                .await;
+        }
+        assert!(mempool.is_empty());
+
+        // Blocks 1b and 2b form the new branch, which abandons both 1a and 2a,
+        // so the transaction must return to the mempool and be kept synced
+        // along the new branch
+        let (block_1b, _, _) = make_mock_block(
+            &genesis_block,
+            Some(in_seven_months),
+            alice_address,
+            rng.gen(),
+        );
+        let (block_2b, _, _) =
+            make_mock_block(&block_1b, Some(in_seven_months), alice_address, rng.gen());
+        for (parent, block) in [(&genesis_block, &block_1b), (&block_1b, &block_2b)] {
+            mempool
+                .update_with_block(
+                    parent.kernel.body.mutator_set_accumulator.clone(),
+                    block,
+                    &AtomicMutex::from(()),
+                )
+                .await;
+            assert!(mempool.contains(txid));
+            assert!(mempool
+                .get(txid)
+                .unwrap()
+                .is_confirmable_relative_to(&block.body().mutator_set_accumulator));
+        }
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn conflicting_txs_preserve_highest_fee() {
@@ -1422,6 +1884,51 @@ mod tests {
         }
     }
 
+    #[traced_test]
+    #[tokio::test]
+    async fn max_size_evicts_lowest_fee_density_first() {
+        let network = Network::Main;
+        let genesis_block = Block::genesis_block(network);
+        let txs = make_plenty_mock_transaction_with_primitive_witness(10);
+        let max_size = txs.iter().map(|tx| tx.get_size()).max().unwrap() * 4;
+
+        let mut mempool = Mempool::new(
+            ByteSize::b(max_size.try_into().unwrap()),
+            None,
+            genesis_block.hash(),
+        );
+        let mut num_evicted = 0;
+        for tx in txs.clone() {
+            let evicted = mempool
+                .insert(tx)
+                .into_iter()
+                .filter_map(|event| match event {
+                    MempoolEvent::RemoveTx(tx) => Some(tx),
+                    _ => None,
+                })
+                .collect_vec();
+            assert!(mempool.transactions_size() <= max_size);
+
+            // evicted transactions are less dense than all those that remain
+            if let Some((_, min_kept_fee_density)) = mempool.get_sorted_iter().last() {
+                assert!(evicted
+                    .iter()
+                    .all(|tx| tx.fee_density() <= min_kept_fee_density));
+            }
+            num_evicted += evicted.len();
+        }
+
+        assert!(num_evicted > 0);
+        assert_eq!(txs.len(), mempool.len() + num_evicted);
+        assert_eq!(
+            mempool
+                .get_sorted_iter()
+                .map(|(txid, _)| mempool.get(txid).unwrap().get_size())
+                .sum::<usize>(),
+            mempool.transactions_size()
+        );
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn get_mempool_size() {
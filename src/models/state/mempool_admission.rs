@@ -0,0 +1,237 @@
+//! Admission of transactions received from peers into the mempool.
+//!
+//! Admission is split in two stages. Stateless checks depend only on the
+//! transaction and the current time, and run on the peer task without holding
+//! the global state lock, so transactions from different peers are verified in
+//! parallel. Stateful checks depend on the mempool and the tip, and run under a
+//! single acquisition of the global state lock.
+//!
+//! The number of transactions undergoing admission at any time is bounded by
+//! the [`AdmissionQueue`]. When it is full, further transactions are dropped
+//! instead of making peer tasks wait, as a burst of transactions would
+//! otherwise stall the peer tasks of all connections.
+
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use twenty_first::math::bfield_codec::BFieldCodec;
+use twenty_first::math::digest::Digest;
+
+use super::mempool::MEMPOOL_IGNORE_TRANSACTIONS_THIS_MANY_SECS_AHEAD;
+use super::mempool::MEMPOOL_TX_THRESHOLD_AGE_IN_SECS;
+use super::GlobalState;
+use crate::config_models::network::Network;
+use crate::models::blockchain::transaction::Transaction;
+use crate::models::peer::PeerSanctionReason;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::prelude::twenty_first;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, thiserror::Error, Serialize, Deserialize, JsonSchema,
+)]
+pub enum AdmissionRejection {
+    #[error("transaction has coinbase")]
+    HasCoinbase,
+
+    #[error("transaction has negative fee")]
+    NegativeFee,
+
+    #[error("transaction of {0} elements cannot fit in a block")]
+    TooLarge(usize),
+
+    #[error("transaction is too old")]
+    TooOld,
+
+    #[error("transaction timestamp is too far into the future")]
+    TooFarIntoFuture,
+
+    #[error("transaction proof is invalid")]
+    InvalidProof,
+
+    #[error("transaction with same or higher proof quality is already known")]
+    AlreadyKnown,
+
+    #[error("transaction is not confirmable relative to tip")]
+    Unconfirmable,
+
+    #[error("node is in maintenance mode")]
+    MaintenanceMode,
+}
+
+/// Why a transaction submitted over RPC was not broadcast.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, thiserror::Error, Serialize, Deserialize, JsonSchema,
+)]
+pub enum BroadcastTransactionError {
+    #[error("transaction could not be decoded")]
+    Malformed,
+
+    #[error(transparent)]
+    Rejected(#[from] AdmissionRejection),
+}
+
+impl AdmissionRejection {
+    /// The reason to sanction the peer that sent the transaction, if the
+    /// rejection is the peer's fault.
+    pub(crate) fn sanction(&self) -> Option<PeerSanctionReason> {
+        match self {
+            Self::HasCoinbase => Some(PeerSanctionReason::NonMinedTransactionHasCoinbase),
+            Self::NegativeFee | Self::TooLarge(_) | Self::InvalidProof => {
+                Some(PeerSanctionReason::InvalidTransaction)
+            }
+            Self::Unconfirmable => Some(PeerSanctionReason::UnconfirmableTransaction),
+
+            // TODO: Consider punishing for timestamps out of range
+            Self::TooOld | Self::TooFarIntoFuture | Self::AlreadyKnown => None,
+            Self::MaintenanceMode => None,
+        }
+    }
+}
+
+/// Checks that depend only on the transaction, the time and the network. The
+/// proof is verified last, such that it is only verified for transactions
+/// that pass the cheap checks.
+pub(crate) async fn check_stateless(
+    transaction: &Transaction,
+    now: Timestamp,
+    network: Network,
+) -> Result<(), AdmissionRejection> {
+    // Transactions received from peers have not been mined yet. Only the
+    // miner is allowed to produce transactions with non-empty coinbase fields.
+    if transaction.kernel.coinbase.is_some() {
+        return Err(AdmissionRejection::HasCoinbase);
+    }
+
+    if transaction.kernel.fee.is_negative() {
+        return Err(AdmissionRejection::NegativeFee);
+    }
+
+    let size = transaction.encode().len();
+    if size > network.params().max_block_size {
+        return Err(AdmissionRejection::TooLarge(size));
+    }
+
+    let tx_timestamp = transaction.kernel.timestamp;
+    if tx_timestamp < now - Timestamp::seconds(MEMPOOL_TX_THRESHOLD_AGE_IN_SECS) {
+        return Err(AdmissionRejection::TooOld);
+    }
+    if tx_timestamp > now + Timestamp::seconds(MEMPOOL_IGNORE_TRANSACTIONS_THIS_MANY_SECS_AHEAD) {
+        return Err(AdmissionRejection::TooFarIntoFuture);
+    }
+
+    if !transaction.is_valid().await {
+        return Err(AdmissionRejection::InvalidProof);
+    }
+
+    Ok(())
+}
+
+/// Checks against the mempool and the tip. Returns the digest of the tip the
+/// transaction is confirmable for. Conflicts with transactions in the mempool
+/// are resolved on insertion.
+pub(crate) fn check_stateful(
+    transaction: &Transaction,
+    state: &GlobalState,
+) -> Result<Digest, AdmissionRejection> {
+    if state.net.maintenance_mode {
+        return Err(AdmissionRejection::MaintenanceMode);
+    }
+
+    let proof_quality = transaction
+        .proof
+        .proof_quality()
+        .map_err(|_| AdmissionRejection::InvalidProof)?;
+    if state
+        .mempool
+        .contains_with_higher_proof_quality(transaction.kernel.txid(), proof_quality)
+    {
+        return Err(AdmissionRejection::AlreadyKnown);
+    }
+
+    let tip = state.chain.light_state();
+    if !transaction.is_confirmable_relative_to(&tip.body().mutator_set_accumulator) {
+        return Err(AdmissionRejection::Unconfirmable);
+    }
+
+    Ok(tip.hash())
+}
+
+/// Bounds the number of transactions undergoing admission.
+#[derive(Debug, Clone)]
+pub struct AdmissionQueue {
+    slots: Arc<Semaphore>,
+    capacity: usize,
+}
+
+impl AdmissionQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(capacity)),
+            capacity,
+        }
+    }
+
+    /// Reserve a slot in the queue, or `None` if it is full. The slot is
+    /// released when the returned permit is dropped.
+    pub fn try_enter(&self) -> Option<OwnedSemaphorePermit> {
+        self.slots.clone().try_acquire_owned().ok()
+    }
+
+    /// Wait until no transactions are undergoing admission. Transactions that
+    /// enter the queue after this returns are not waited for.
+    pub async fn drain(&self) {
+        let Ok(capacity) = u32::try_from(self.capacity) else {
+            return;
+        };
+        let _all_slots = self
+            .slots
+            .acquire_many(capacity)
+            .await
+            .expect("admission semaphore is never closed");
+    }
+}
+
+#[cfg(test)]
+mod mempool_admission_tests {
+    use super::*;
+
+    #[test]
+    fn full_admission_queue_rejects_until_slot_is_released() {
+        let queue = AdmissionQueue::new(2);
+        let first = queue.try_enter().unwrap();
+        let _second = queue.try_enter().unwrap();
+        assert!(queue.try_enter().is_none());
+
+        drop(first);
+        assert!(queue.try_enter().is_some());
+    }
+
+    #[tokio::test]
+    async fn drain_waits_for_transactions_in_admission() {
+        let queue = AdmissionQueue::new(2);
+        let in_admission = queue.try_enter().unwrap();
+
+        let draining = queue.clone();
+        let drain = tokio::spawn(async move { draining.drain().await });
+        tokio::task::yield_now().await;
+        assert!(!drain.is_finished());
+
+        drop(in_admission);
+        drain.await.unwrap();
+        assert!(queue.try_enter().is_some());
+    }
+
+    #[test]
+    fn only_faulty_transactions_are_sanctioned() {
+        assert_eq!(
+            Some(PeerSanctionReason::NonMinedTransactionHasCoinbase),
+            AdmissionRejection::HasCoinbase.sanction()
+        );
+        assert!(AdmissionRejection::AlreadyKnown.sanction().is_none());
+        assert!(AdmissionRejection::TooOld.sanction().is_none());
+    }
+}
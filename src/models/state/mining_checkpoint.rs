@@ -0,0 +1,201 @@
+//! Checkpoint of the block template being mined, such that a brief restart of
+//! the node does not discard it.
+//!
+//! Building a template proves the coinbase transaction, merges the selected
+//! mempool transactions into it, and proves the block, which takes minutes on
+//! slow machines. The template is written when it is built and again at
+//! shutdown, along with the nonces tried on it so far. After a restart, mining
+//! resumes on the stored template if it still extends the tip and pays this
+//! node's wallet.
+//!
+//! Nonces are a random salt, fixed per template, followed by a counter that
+//! the mining workers share. Each guess takes the next value of the counter,
+//! so no two workers ever try the same nonce, and the counter is the number of
+//! nonces tried.
+
+use std::path::Path;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use anyhow::Context;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use twenty_first::math::b_field_element::BFieldElement;
+use twenty_first::math::digest::Digest;
+
+use crate::config_models::network::Network;
+use crate::models::blockchain::block::Block;
+use crate::models::state::wallet::expected_utxo::ExpectedUtxo;
+use crate::prelude::twenty_first;
+
+pub const MINING_CHECKPOINT_FILE_NAME: &str = "mining_checkpoint.bin";
+
+/// How far mining on a template got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct NonceProgress {
+    salt: [BFieldElement; 2],
+
+    /// Number of nonces tried
+    num_tried: u64,
+}
+
+impl NonceProgress {
+    pub(crate) fn num_tried(&self) -> u64 {
+        self.num_tried
+    }
+}
+
+/// The nonces of one template, shared by its mining workers.
+#[derive(Debug)]
+pub(crate) struct NonceSequence {
+    salt: [BFieldElement; 2],
+    next: AtomicU64,
+}
+
+impl NonceSequence {
+    pub(crate) fn random() -> Self {
+        Self::resume(NonceProgress {
+            salt: rand::random(),
+            num_tried: 0,
+        })
+    }
+
+    pub(crate) fn resume(progress: NonceProgress) -> Self {
+        Self {
+            salt: progress.salt,
+            next: AtomicU64::new(progress.num_tried),
+        }
+    }
+
+    /// A nonce no worker has tried yet.
+    pub(crate) fn next(&self) -> [BFieldElement; 3] {
+        let counter = self.next.fetch_add(1, Ordering::Relaxed);
+        [self.salt[0], self.salt[1], BFieldElement::new(counter)]
+    }
+
+    pub(crate) fn progress(&self) -> NonceProgress {
+        NonceProgress {
+            salt: self.salt,
+            num_tried: self.next.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct MiningCheckpoint {
+    pub(crate) network: Network,
+    pub(crate) template: Block,
+    pub(crate) coinbase_utxo_info: ExpectedUtxo,
+
+    /// Number of mempool transactions merged into the template
+    pub(crate) num_transactions: usize,
+    pub(crate) nonce_progress: NonceProgress,
+}
+
+impl MiningCheckpoint {
+    /// Write the checkpoint, replacing the previous one atomically.
+    pub(crate) fn store(&self, data_dir: &Path) -> Result<()> {
+        let path = data_dir.join(MINING_CHECKPOINT_FILE_NAME);
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, bincode::serialize(self)?)
+            .with_context(|| format!("could not write {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("could not write {}", path.display()))
+    }
+
+    /// Read and delete the checkpoint, if one was written for a template on top
+    /// of `predecessor` on the given network. The file is deleted so that a
+    /// template is resumed at most once, unless stored again.
+    pub(crate) fn take(
+        data_dir: &Path,
+        network: Network,
+        predecessor: Digest,
+    ) -> Result<Option<Self>> {
+        let path = data_dir.join(MINING_CHECKPOINT_FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let bytes =
+            std::fs::read(&path).with_context(|| format!("could not read {}", path.display()))?;
+        std::fs::remove_file(&path)?;
+        let checkpoint: Self = bincode::deserialize(&bytes)?;
+        Ok(Some(checkpoint).filter(|checkpoint| {
+            checkpoint.network == network
+                && checkpoint.template.header().prev_block_digest == predecessor
+        }))
+    }
+}
+
+#[cfg(test)]
+mod mining_checkpoint_tests {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::models::blockchain::transaction::Transaction;
+    use crate::models::proof_abstractions::timestamp::Timestamp;
+    use crate::tests::shared::dummy_expected_utxo;
+    use crate::tests::shared::make_mock_transaction;
+
+    #[test]
+    fn workers_never_share_nonces_and_progress_resumes() {
+        let nonces = Arc::new(NonceSequence::random());
+        let workers = (0..4)
+            .map(|_| {
+                let nonces = nonces.clone();
+                std::thread::spawn(move || (0..1000).map(|_| nonces.next()).collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+        let tried = workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect::<HashSet<_>>();
+        assert_eq!(4000, tried.len());
+        assert_eq!(4000, nonces.progress().num_tried());
+
+        let resumed = NonceSequence::resume(nonces.progress());
+        assert!(!tried.contains(&resumed.next()));
+    }
+
+    #[test]
+    fn checkpoint_is_taken_once_for_its_predecessor() {
+        let network = Network::RegTest;
+        let data_dir = std::env::temp_dir().join(format!("mining-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&data_dir).unwrap();
+
+        let genesis = Block::genesis_block(network);
+        let transaction: Transaction = make_mock_transaction(vec![], vec![]);
+        let template = Block::block_template_invalid_proof(
+            &genesis,
+            transaction,
+            genesis.header().timestamp + Timestamp::minutes(10),
+            None,
+        );
+        let checkpoint = MiningCheckpoint {
+            network,
+            template,
+            coinbase_utxo_info: dummy_expected_utxo(),
+            num_transactions: 0,
+            nonce_progress: NonceSequence::random().progress(),
+        };
+
+        checkpoint.store(&data_dir).unwrap();
+        assert!(
+            MiningCheckpoint::take(&data_dir, network, Digest::default())
+                .unwrap()
+                .is_none()
+        );
+
+        checkpoint.store(&data_dir).unwrap();
+        let taken = MiningCheckpoint::take(&data_dir, network, genesis.hash())
+            .unwrap()
+            .unwrap();
+        assert_eq!(checkpoint.template, taken.template);
+        assert_eq!(checkpoint.nonce_progress, taken.nonce_progress);
+        assert!(MiningCheckpoint::take(&data_dir, network, genesis.hash())
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,156 @@
+//! Log of the blocks mined by this node, with the revenue of each block and
+//! the age of the template it was mined on, for miners to evaluate their fee
+//! revenue and template refresh policy.
+//!
+//! The log is a file of JSON lines in the data directory, appended to whenever
+//! a self-mined block becomes the tip. Blocks that are later reorganized away
+//! stay in the log.
+
+use std::fs::OpenOptions;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Context;
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use twenty_first::math::digest::Digest;
+
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::models::blockchain::block::Block;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::prelude::twenty_first;
+use crate::util_types::json_schema::DigestSchema;
+
+pub const MINING_LOG_FILE_NAME: &str = "mined_blocks.jsonl";
+
+/// The block template a block was mined on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockTemplateInfo {
+    /// When the template was built
+    pub created: Timestamp,
+
+    /// Number of mempool transactions merged into the template
+    pub num_transactions: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct MinedBlockReport {
+    pub height: BlockHeight,
+    #[schemars(with = "DigestSchema")]
+    pub digest: Digest,
+
+    /// When the block was found
+    pub timestamp: Timestamp,
+
+    /// Amount paid to this node: the block subsidy plus the fees
+    pub coinbase: NeptuneCoins,
+    pub fees: NeptuneCoins,
+    pub num_transactions: usize,
+
+    /// Time from building the template to finding the block
+    pub template_age: Timestamp,
+}
+
+impl MinedBlockReport {
+    pub fn new(block: &Block, template: BlockTemplateInfo) -> Self {
+        let kernel = &block.body().transaction_kernel;
+        let timestamp = block.header().timestamp;
+        Self {
+            height: block.header().height,
+            digest: block.hash(),
+            timestamp,
+            coinbase: kernel.coinbase.unwrap_or_default(),
+            fees: kernel.fee,
+            num_transactions: template.num_transactions,
+            template_age: if timestamp > template.created {
+                timestamp - template.created
+            } else {
+                Timestamp::default()
+            },
+        }
+    }
+
+    /// Append the report to the log in `data_dir`.
+    pub fn append_to_log(&self, data_dir: &Path) -> Result<()> {
+        let path = data_dir.join(MINING_LOG_FILE_NAME);
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&path)
+            .with_context(|| format!("could not open {}", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(self)?)
+            .with_context(|| format!("could not write {}", path.display()))
+    }
+
+    /// The last `max_num` reports in the log in `data_dir`, oldest first.
+    pub fn read_log(data_dir: &Path, max_num: usize) -> Result<Vec<Self>> {
+        let path = data_dir.join(MINING_LOG_FILE_NAME);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .open(&path)
+            .with_context(|| format!("could not open {}", path.display()))?;
+        let mut reports = vec![];
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            reports.push(
+                serde_json::from_str(&line)
+                    .with_context(|| format!("could not parse {}", path.display()))?,
+            );
+        }
+
+        let skip = reports.len().saturating_sub(max_num);
+        Ok(reports.split_off(skip))
+    }
+}
+
+#[cfg(test)]
+mod mining_log_tests {
+    use super::*;
+    use crate::config_models::network::Network;
+
+    #[test]
+    fn log_returns_most_recent_reports() {
+        let data_dir = std::env::temp_dir().join(format!("mining-log-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&data_dir).unwrap();
+        assert!(MinedBlockReport::read_log(&data_dir, 10)
+            .unwrap()
+            .is_empty());
+
+        let genesis = Block::genesis_block(Network::RegTest);
+        let reports = (0..3)
+            .map(|num_transactions| {
+                MinedBlockReport::new(
+                    &genesis,
+                    BlockTemplateInfo {
+                        created: genesis.header().timestamp - Timestamp::seconds(5),
+                        num_transactions,
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        for report in &reports {
+            report.append_to_log(&data_dir).unwrap();
+        }
+
+        assert_eq!(Timestamp::seconds(5), reports[0].template_age);
+        assert_eq!(reports, MinedBlockReport::read_log(&data_dir, 10).unwrap());
+        assert_eq!(
+            reports[1..],
+            MinedBlockReport::read_log(&data_dir, 2).unwrap()
+        );
+
+        std::fs::remove_dir_all(data_dir).unwrap();
+    }
+}
@@ -1,12 +1,30 @@
 pub mod archival_state;
+pub(crate) mod block_packing;
+pub mod block_proposal;
 pub mod blockchain_state;
+pub mod checkpoint_beacon;
+pub mod coinjoin;
+pub mod db_diagnostics;
+pub mod event_journal;
+pub mod fee_histogram;
+pub mod fork_watch;
 pub mod light_state;
 pub mod mempool;
+pub mod mempool_admission;
+pub(crate) mod mining_checkpoint;
+pub mod mining_log;
 pub mod networking_state;
+pub mod node_event;
+pub(crate) mod orphan_blocks;
 pub mod shared;
+pub mod spend_audit_log;
+pub(crate) mod state_checkpoint;
+pub mod sync_progress;
 pub(crate) mod transaction_details;
 pub(crate) mod transaction_kernel_id;
+pub mod tx_index;
 pub mod tx_proving_capability;
+pub mod utxo_set_stats;
 pub mod wallet;
 
 use std::cmp::max;
@@ -15,14 +33,23 @@ use std::ops::DerefMut;
 
 use anyhow::bail;
 use anyhow::Result;
+use block_proposal::BlockProposals;
 use blockchain_state::BlockchainState;
+use event_journal::EventJournal;
+use event_journal::JournalEvent;
 use itertools::Itertools;
 use mempool::Mempool;
+use mempool::MempoolEvent;
+use mempool_admission::AdmissionQueue;
 use networking_state::NetworkingState;
+use node_event::NodeEvent;
 use num_traits::CheckedSub;
 use rand::rngs::StdRng;
+use rand::thread_rng;
+use rand::Rng;
 use rand::SeedableRng;
 use tasm_lib::triton_vm::prelude::*;
+use tokio::sync::broadcast;
 use tokio::sync::TryLockError;
 use tracing::debug;
 use tracing::info;
@@ -33,12 +60,15 @@ use twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
 use tx_proving_capability::TxProvingCapability;
 use wallet::address::ReceivingAddress;
 use wallet::address::SpendingKey;
+use wallet::coin_selection::CoinSelectionPolicy;
+use wallet::coinbase_address_rotation::CoinbaseAddressRotation;
 use wallet::expected_utxo::UtxoNotifier;
 use wallet::unlocked_utxo::UnlockedUtxo;
 use wallet::wallet_state::WalletState;
 use wallet::wallet_status::WalletStatus;
 
 use super::blockchain::block::block_height::BlockHeight;
+use super::blockchain::block::fork_choice;
 use super::blockchain::block::Block;
 use super::blockchain::transaction::primitive_witness::PrimitiveWitness;
 use super::blockchain::transaction::primitive_witness::SaltedUtxos;
@@ -58,8 +88,10 @@ use crate::database::storage::storage_vec::Index;
 use crate::locks::tokio as sync_tokio;
 use crate::models::blockchain::transaction::validity::proof_collection::ProofCollection;
 use crate::models::blockchain::transaction::validity::single_proof::SingleProof;
+use crate::models::blockchain::transaction::AnnouncedUtxo;
 use crate::models::blockchain::transaction::TransactionProof;
 use crate::models::blockchain::type_scripts::known_type_scripts::match_type_script_and_generate_witness;
+use crate::models::peer::transfer_transaction::AcceptedTransactionProofs;
 use crate::models::peer::HandshakeData;
 use crate::models::state::wallet::expected_utxo::ExpectedUtxo;
 use crate::models::state::wallet::monitored_utxo::MonitoredUtxo;
@@ -136,6 +168,10 @@ pub struct GlobalStateLock {
     /// to ensure that only one proof is produced at a time. All calls to
     /// Triton VM's prover (except tests) must acquire this lock.
     pub(crate) proving_lock: ProvingLock,
+
+    /// Bounds the number of transactions from peers undergoing admission to
+    /// the mempool.
+    pub(crate) mempool_admission: AdmissionQueue,
 }
 
 impl GlobalStateLock {
@@ -159,10 +195,13 @@ impl GlobalStateLock {
             Some(crate::LOG_TOKIO_LOCK_EVENT_CB),
         ));
 
+        let mempool_admission = AdmissionQueue::new(cli.mempool_admission_queue_size);
+
         Self {
             global_state_lock,
             cli,
             proving_lock,
+            mempool_admission,
         }
     }
 
@@ -282,6 +321,17 @@ pub struct GlobalState {
 
     // Only the mining task should write to this, anyone can read.
     pub mining: bool,
+
+    /// Block templates for external miners. Only published by the mining task
+    /// with `--external-mining`.
+    pub(crate) block_proposals: BlockProposals,
+
+    /// Publishes [`NodeEvent`]s to subscribers. Sending never blocks and
+    /// events are dropped if nobody is listening.
+    events: broadcast::Sender<NodeEvent>,
+
+    /// Records state transitions, if opened. See [`EventJournal`].
+    event_journal: Option<EventJournal>,
 }
 
 impl GlobalState {
@@ -300,11 +350,77 @@ impl GlobalState {
             cli,
             mempool,
             mining,
+            block_proposals: BlockProposals::default(),
+            events: node_event::node_event_channel(),
+            event_journal: None,
+        }
+    }
+
+    /// Start recording state transitions in the given journal.
+    pub fn set_event_journal(&mut self, event_journal: EventJournal) {
+        self.event_journal = Some(event_journal);
+    }
+
+    pub(crate) fn event_journal(&self) -> Option<&EventJournal> {
+        self.event_journal.as_ref()
+    }
+
+    /// Record an event in the journal. Does nothing if no journal is open.
+    pub(crate) async fn record_journal_event(&mut self, event: JournalEvent) {
+        if let Some(journal) = self.event_journal.as_mut() {
+            journal.record(event).await;
+        }
+    }
+
+    /// The wallet's UTXOs received and spent, respectively, in the given block.
+    async fn wallet_utxos_in_block(&self, block_digest: Digest) -> (Vec<Utxo>, Vec<Utxo>) {
+        let monitored_utxos = self.wallet_state.wallet_db.monitored_utxos();
+
+        let (mut received, mut spent) = (vec![], vec![]);
+        let stream = monitored_utxos.stream_values().await;
+        pin_mut!(stream); // needed for iteration
+        while let Some(monitored_utxo) = stream.next().await {
+            if monitored_utxo
+                .confirmed_in_block
+                .is_some_and(|(digest, _, _)| digest == block_digest)
+            {
+                received.push(monitored_utxo.utxo.clone());
+            }
+            if monitored_utxo
+                .spent_in_block
+                .is_some_and(|(digest, _, _)| digest == block_digest)
+            {
+                spent.push(monitored_utxo.utxo);
+            }
+        }
+
+        (received, spent)
+    }
+
+    /// Subscribe to [`NodeEvent`]s published by this node.
+    pub fn subscribe_to_events(&self) -> broadcast::Receiver<NodeEvent> {
+        self.events.subscribe()
+    }
+
+    /// Publish an event to all subscribers. Events are silently dropped if
+    /// there are no subscribers.
+    pub(crate) fn publish_event(&self, event: NodeEvent) {
+        let _ = self.events.send(event);
+    }
+
+    fn publish_mempool_events(&self, events: &[MempoolEvent]) {
+        for event in events.iter().filter_map(NodeEvent::from_mempool_event) {
+            self.publish_event(event);
         }
     }
 
     /// Return a seed used to randomize shuffling.
     pub(crate) fn shuffle_seed(&self) -> [u8; 32] {
+        // A locked wallet has no secret to derive the seed from.
+        if self.wallet_state.is_locked() {
+            return thread_rng().gen();
+        }
+
         let next_block_height = self.chain.light_state().header().height.next();
         let secure_seed_from_wallet = self
             .wallet_state
@@ -337,8 +453,7 @@ impl GlobalState {
     /// If the incoming block equals the current tip, this function returns
     /// false.
     pub fn incoming_block_is_more_canonical(&self, incoming_block: &Block) -> bool {
-        let winner = Block::fork_choice_rule(self.chain.light_state(), incoming_block);
-        winner.hash() != self.chain.light_state().hash()
+        fork_choice::incoming_block_wins(self.chain.light_state(), incoming_block)
     }
 
     /// Retrieve block height of last change to wallet balance.
@@ -466,20 +581,64 @@ impl GlobalState {
             receiver_digest,
         );
 
-        let change_output = match change_utxo_notify_method {
-            UtxoNotificationMedium::OnChain => TxOutput::onchain_native_currency(
-                change_amount,
-                change_sender_randomness,
-                own_receiving_address,
-            ),
-            UtxoNotificationMedium::OffChain => TxOutput::offchain_native_currency(
-                change_amount,
-                change_sender_randomness,
-                own_receiving_address,
-            ),
-        };
+        Ok(Self::change_output(
+            change_amount,
+            change_sender_randomness,
+            own_receiving_address,
+            change_utxo_notify_method,
+        ))
+    }
+
+    /// Like [Self::create_change_output], split into as many outputs of about
+    /// equal amounts as configured with `--change-outputs`, but no more than
+    /// keep every output at or above `--change-dust-threshold`. Returns the
+    /// outputs, largest first.
+    pub fn create_change_outputs(
+        &self,
+        change_amount: NeptuneCoins,
+        change_key: SpendingKey,
+        change_utxo_notify_method: UtxoNotificationMedium,
+    ) -> Result<Vec<TxOutput>> {
+        let num_outputs = change_amount
+            .max_split_parts(self.cli().change_dust_threshold, self.cli().change_outputs);
+        let amounts = change_amount.split(num_outputs);
+
+        let first_output =
+            self.create_change_output(amounts[0], change_key, change_utxo_notify_method)?;
+
+        // The outputs go to the same address, so they need distinct sender
+        // randomness for their addition records to differ.
+        let mut change_outputs = vec![first_output.clone()];
+        for (i, amount) in amounts.into_iter().enumerate().skip(1) {
+            let sender_randomness = Hash::hash_pair(
+                first_output.sender_randomness(),
+                Digest::new([BFieldElement::new(i as u64); Digest::LEN]),
+            );
+            change_outputs.push(Self::change_output(
+                amount,
+                sender_randomness,
+                change_key.to_address(),
+                change_utxo_notify_method,
+            ));
+        }
 
-        Ok(change_output)
+        Ok(change_outputs)
+    }
+
+    fn change_output(
+        amount: NeptuneCoins,
+        sender_randomness: Digest,
+        own_receiving_address: ReceivingAddress,
+        change_utxo_notify_method: UtxoNotificationMedium,
+    ) -> TxOutput {
+        match change_utxo_notify_method {
+            UtxoNotificationMedium::OnChain => {
+                TxOutput::onchain_native_currency(amount, sender_randomness, own_receiving_address)
+            }
+            UtxoNotificationMedium::OffChain => {
+                TxOutput::offchain_native_currency(amount, sender_randomness, own_receiving_address)
+            }
+        }
     }
 
     /// Generate a primitive witness for a transaction from various disparate witness data.
@@ -625,10 +784,11 @@ impl GlobalState {
     /// [Self::generate_tx_outputs()] which determines which outputs should be
     /// `OnChain` or `OffChain`.
     ///
-    /// The return value is the created transaction and some change UTXO with
-    /// associated data or none if the transaction is already balanced. The
+    /// The return value is the created transaction and the change UTXOs with
+    /// associated data, none if the transaction is already balanced. The
     /// associated data allows the caller to expect and later claim the change
-    /// UTXO.
+    /// UTXOs. Change is split into several UTXOs if so configured with
+    /// `--change-outputs`.
     ///
     /// After this call returns, it is the caller's responsibility to inform the
     /// wallet of any returned [ExpectedUtxo], ie `OffChain` secret
@@ -668,7 +828,7 @@ impl GlobalState {
     /// let mut tx_outputs = state.generate_tx_outputs(outputs, change_notify_medium)?;
     ///
     /// // Create the transaction
-    /// let (transaction, maybe_change_utxo) = state
+    /// let (transaction, change_utxos) = state
     ///     .create_transaction(
     ///         tx_outputs,                   // all outputs except `change`
     ///         change_key,                   // send `change` to this key
@@ -682,7 +842,7 @@ impl GlobalState {
     /// drop(state);
     ///
     /// // Inform wallet of any expected incoming utxos.
-    /// if let Some(change_utxo) = maybe_change_utxo {
+    /// for change_utxo in change_utxos {
     ///     state
     ///         .lock_guard_mut()
     ///         .await
@@ -699,7 +859,7 @@ impl GlobalState {
         fee: NeptuneCoins,
         timestamp: Timestamp,
         sync_device: &TritonProverSync,
-    ) -> Result<(Transaction, Option<TxOutput>)> {
+    ) -> Result<(Transaction, Vec<TxOutput>)> {
         // TODO: function not used because all callers got through its
         // equivalent method `create_transaction_with_prover_capability`,
         // for testing purposes. Consider deleting or fixing this somehow.
@@ -721,16 +881,79 @@ impl GlobalState {
     #[allow(clippy::too_many_arguments)]
     pub(crate) async fn create_transaction_with_prover_capability(
         &self,
-        mut tx_outputs: TxOutputList,
+        tx_outputs: TxOutputList,
         change_key: SpendingKey,
         change_utxo_notify_medium: UtxoNotificationMedium,
         fee: NeptuneCoins,
         timestamp: Timestamp,
         prover_capability: TxProvingCapability,
         sync_device: &TritonProverSync,
-    ) -> Result<(Transaction, Option<TxOutput>)> {
-        // TODO: Attempt to simplify method interface somehow, maybe by moving
-        // it to GlobalStateLock?
+    ) -> Result<(Transaction, Vec<TxOutput>)> {
+        self.create_transaction_with_coin_selection(
+            tx_outputs,
+            change_key,
+            change_utxo_notify_medium,
+            fee,
+            timestamp,
+            prover_capability,
+            self.cli().coin_selection,
+            sync_device,
+        )
+        .await
+    }
+
+    /// Variant of [Self::create_transaction_with_prover_capability] that
+    /// allows caller to specify how inputs are selected, instead of using the
+    /// policy configured on the command line.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn create_transaction_with_coin_selection(
+        &self,
+        tx_outputs: TxOutputList,
+        change_key: SpendingKey,
+        change_utxo_notify_medium: UtxoNotificationMedium,
+        fee: NeptuneCoins,
+        timestamp: Timestamp,
+        prover_capability: TxProvingCapability,
+        coin_selection: CoinSelectionPolicy,
+        sync_device: &TritonProverSync,
+    ) -> Result<(Transaction, Vec<TxOutput>)> {
+        let (transaction_details, change_outputs) = self
+            .create_transaction_details(
+                tx_outputs,
+                change_key,
+                change_utxo_notify_medium,
+                fee,
+                timestamp,
+                coin_selection,
+            )
+            .await?;
+
+        // 2. Create the transaction
+        let transaction =
+            Self::create_raw_transaction(transaction_details, prover_capability, sync_device)
+                .await?;
+
+        Ok((transaction, change_outputs))
+    }
+
+    /// Select the inputs of a transaction with the given outputs, and add a
+    /// change output if needed, without proving the transaction.
+    ///
+    /// This snapshots the spendable UTXOs at the time of the call. Callers
+    /// that hold a lock on the global state should release it before proving
+    /// the transaction with [Self::create_raw_transaction], such that block
+    /// processing is not blocked for the duration of the proof. The inputs
+    /// may be spent meanwhile, which [WalletState::spends_spent_inputs] checks
+    /// before broadcasting.
+    pub(crate) async fn create_transaction_details(
+        &self,
+        mut tx_outputs: TxOutputList,
+        change_key: SpendingKey,
+        change_utxo_notify_medium: UtxoNotificationMedium,
+        fee: NeptuneCoins,
+        timestamp: Timestamp,
+        coin_selection: CoinSelectionPolicy,
+    ) -> Result<(TransactionDetails, Vec<TxOutput>)> {
         let tip = self.chain.light_state();
         let tip_mutator_set_accumulator = tip.kernel.body.mutator_set_accumulator.clone();
         let tip_digest = tip.hash();
@@ -738,11 +961,38 @@ impl GlobalState {
         // 1. create/add change output if necessary.
         let total_spend = tx_outputs.total_native_coins() + fee;
 
-        // collect spendable inputs
-        let tx_inputs = self
+        // collect spendable inputs, resorting to unconfirmed change if allowed
+        let allocation = self
             .wallet_state
-            .allocate_sufficient_input_funds(total_spend, tip_digest, timestamp)
-            .await?;
+            .allocate_sufficient_input_funds_with_policy(
+                total_spend,
+                tip_digest,
+                timestamp,
+                coin_selection,
+            )
+            .await;
+        let (tx_inputs, mutator_set_accumulator) = match allocation {
+            Ok(tx_inputs) => (tx_inputs, tip_mutator_set_accumulator),
+            Err(err) if self.cli().spend_unconfirmed => {
+                debug!("{err}. Trying to include unconfirmed change.");
+                let (tx_inputs, projected_mutator_set_accumulator) = self
+                    .wallet_state
+                    .allocate_sufficient_input_funds_spending_unconfirmed(
+                        total_spend,
+                        tip_digest,
+                        &tip_mutator_set_accumulator,
+                        timestamp,
+                    )
+                    .await?;
+                warn!(
+                    "Spending unconfirmed change. The transaction is held back until the \
+                    transactions paying the change are confirmed, and is invalidated if any of \
+                    them is dropped from the mempool."
+                );
+                (tx_inputs, projected_mutator_set_accumulator)
+            }
+            Err(err) => return Err(err),
+        };
 
         let total_spendable = tx_inputs
             .iter()
@@ -750,16 +1000,17 @@ impl GlobalState {
             .sum();
 
         // Add change, if required to balance tx.
-        let mut maybe_change_output = None;
+        let mut change_outputs = vec![];
         if total_spend < total_spendable {
             let amount = total_spendable.checked_sub(&total_spend).ok_or_else(|| {
                 anyhow::anyhow!("overflow subtracting total_spend from input_amount")
             })?;
 
-            let change_utxo =
-                self.create_change_output(amount, change_key, change_utxo_notify_medium)?;
-            tx_outputs.push(change_utxo.clone());
-            maybe_change_output = Some(change_utxo);
+            change_outputs =
+                self.create_change_outputs(amount, change_key, change_utxo_notify_medium)?;
+            for change_output in &change_outputs {
+                tx_outputs.push(change_output.clone());
+            }
         }
 
         let transaction_details = TransactionDetails::new_without_coinbase(
@@ -767,15 +1018,10 @@ impl GlobalState {
             tx_outputs.to_owned(),
             fee,
             timestamp,
-            tip_mutator_set_accumulator,
+            mutator_set_accumulator,
         )?;
 
-        // 2. Create the transaction
-        let transaction =
-            Self::create_raw_transaction(transaction_details, prover_capability, sync_device)
-                .await?;
-
-        Ok((transaction, maybe_change_output))
+        Ok((transaction_details, change_outputs))
     }
 
     /// creates a Transaction.
@@ -894,8 +1140,11 @@ impl GlobalState {
             network: self.cli().network,
             instance_id: self.net.instance_id,
             version: VERSION.to_string(),
-            // For now, all nodes are archival nodes
-            is_archival_node: self.chain.is_archival_node(),
+            // Pruned nodes cannot serve the blocks of a sync
+            is_archival_node: self.chain.is_archival_node() && self.cli().prune_depth.is_none(),
+            external_address: self.net.external_address,
+            accepted_tx_proofs: AcceptedTransactionProofs::new(self.cli().single_proofs_only),
+            supports_ping: true,
         }
     }
 
@@ -910,6 +1159,17 @@ impl GlobalState {
     ///
     /// Panics if the mutator set is not synced to current tip.
     pub(crate) async fn restore_monitored_utxos_from_recovery_data(&mut self) -> Result<()> {
+        self.restore_monitored_utxos_from_recovery_data_since(0)
+            .await
+    }
+
+    /// Like [`Self::restore_monitored_utxos_from_recovery_data`], but only
+    /// checks recovery data written after the first `verified_len` bytes,
+    /// which are known to be reflected in the wallet database.
+    pub(crate) async fn restore_monitored_utxos_from_recovery_data_since(
+        &mut self,
+        verified_len: u64,
+    ) -> Result<()> {
         let tip_hash = self.chain.light_state().hash();
         let ams_ref = &self.chain.archival_state().archival_mutator_set;
 
@@ -921,7 +1181,10 @@ impl GlobalState {
         );
 
         // Fetch all incoming UTXOs from recovery data
-        let incoming_utxos = self.wallet_state.read_utxo_ms_recovery_data().await?;
+        let incoming_utxos = self
+            .wallet_state
+            .read_utxo_ms_recovery_data_from(verified_len)
+            .await?;
         let incoming_utxo_count = incoming_utxos.len();
         info!("Checking {} incoming UTXOs", incoming_utxo_count);
 
@@ -968,6 +1231,17 @@ impl GlobalState {
             return Ok(());
         }
 
+        // Rewards of rotating coinbase addresses may have been paid to keys the
+        // wallet database does not know of, e.g. when it was restored from the
+        // seed. Rediscover those keys, such that the UTXOs stay spendable.
+        let missing_utxos = recovery_data_for_missing_mutxos
+            .iter()
+            .map(|recovery_data| recovery_data.utxo.clone())
+            .collect_vec();
+        self.wallet_state
+            .discover_generation_keys(&missing_utxos)
+            .await;
+
         // For all recovery data where we did not find a matching monitored UTXO,
         // recover the MS membership proof, and insert a new monitored UTXO into the
         // wallet database.
@@ -1006,8 +1280,10 @@ impl GlobalState {
                 Err(err) => bail!("Could not restore MS membership proof. Got: {err}"),
             };
 
-            let mut restored_mutxo =
-                MonitoredUtxo::new(incoming_utxo.utxo, self.wallet_state.number_of_mps_per_utxo);
+            let mut restored_mutxo = MonitoredUtxo::new(
+                incoming_utxo.utxo,
+                self.wallet_state.number_of_mps_per_new_utxo(),
+            );
             restored_mutxo.add_membership_proof_for_tip(tip_hash, restored_msmp);
 
             self.wallet_state
@@ -1065,9 +1341,23 @@ impl GlobalState {
                     }
                 };
 
-            // try latest (block hash, membership proof) entry
-            let (block_hash, mut membership_proof) = monitored_utxo
-                .get_latest_membership_proof_entry()
+            // Prefer the latest (block hash, membership proof) entry on the
+            // canonical chain, such as the one for the anchor block, as it
+            // only needs to be walked forwards. Otherwise, try the latest.
+            let mut canonical_entry = None;
+            for (block_hash, membership_proof) in &monitored_utxo.blockhash_to_membership_proof {
+                if self
+                    .chain
+                    .archival_state()
+                    .block_belongs_to_canonical_chain(*block_hash, tip_hash)
+                    .await
+                {
+                    canonical_entry = Some((*block_hash, membership_proof.clone()));
+                    break;
+                }
+            }
+            let (block_hash, mut membership_proof) = canonical_entry
+                .or_else(|| monitored_utxo.get_latest_membership_proof_entry())
                 .expect("Database not in consistent state. Monitored UTXO must have at least one membership proof.");
 
             // request path-to-tip
@@ -1308,10 +1598,37 @@ impl GlobalState {
     /// Update client's state with a new block. Block is assumed to be valid, also wrt. to PoW.
     /// The received block will be set as the new tip, regardless of its accumulated PoW.
     pub async fn set_new_tip(&mut self, new_block: Block, prover_lock: &ProvingLock) -> Result<()> {
-        self.set_new_tip_internal(new_block, None, prover_lock)
+        self.set_new_tip_internal(new_block, None, None, prover_lock)
             .await
     }
 
+    /// Update client's state with a batch of new blocks, in order. Blocks are assumed to be valid,
+    /// also wrt. to PoW, and each block must be the child of the one before it. The last block
+    /// will be set as the new tip, regardless of its accumulated PoW.
+    ///
+    /// The wallet scans all blocks of the batch for announced UTXOs in parallel before the blocks
+    /// are applied one by one.
+    pub async fn set_new_tips(
+        &mut self,
+        new_blocks: Vec<Block>,
+        prover_lock: &ProvingLock,
+    ) -> Result<()> {
+        let tx_kernels = new_blocks
+            .iter()
+            .map(|block| &block.kernel.body.transaction_kernel)
+            .collect_vec();
+        let announced_utxos = self
+            .wallet_state
+            .scan_batch_for_announced_utxos(&tx_kernels);
+
+        for (new_block, announced_utxos) in new_blocks.into_iter().zip_eq(announced_utxos) {
+            self.set_new_tip_internal(new_block, None, Some(announced_utxos), prover_lock)
+                .await?;
+        }
+
+        Ok(())
+    }
+
     /// Update client's state with a new block that was mined locally. Block is assumed to be valid,
     /// also wrt. to PoW. The received block will be set as the new tip, regardless of its
     /// accumulated PoW.
@@ -1321,8 +1638,16 @@ impl GlobalState {
         coinbase_utxo_info: ExpectedUtxo,
         prover_lock: &ProvingLock,
     ) -> Result<()> {
-        self.set_new_tip_internal(new_block, Some(coinbase_utxo_info), prover_lock)
-            .await
+        self.set_new_tip_internal(new_block, Some(coinbase_utxo_info), None, prover_lock)
+            .await?;
+
+        // The key just paid to has received funds, so the next block is paid
+        // to a fresh one.
+        if self.cli().coinbase_address_rotation == CoinbaseAddressRotation::Fresh {
+            self.wallet_state.derive_new_generation_key().await;
+        }
+
+        Ok(())
     }
 
     /// Update client's state with a new block. Block is assumed to be valid, also wrt. to PoW.
@@ -1332,6 +1657,7 @@ impl GlobalState {
         &mut self,
         new_block: Block,
         coinbase_utxo_info: Option<ExpectedUtxo>,
+        announced_utxos: Option<Vec<AnnouncedUtxo>>,
         prover_lock: &ProvingLock,
     ) -> Result<()> {
         // note: we make this fn internal so we can log its duration and ensure it will
@@ -1340,6 +1666,7 @@ impl GlobalState {
             myself: &mut GlobalState,
             new_block: Block,
             coinbase_utxo_info: Option<ExpectedUtxo>,
+            announced_utxos: Option<Vec<AnnouncedUtxo>>,
             prover_lock: &ProvingLock,
         ) -> Result<()> {
             // Apply the updates
@@ -1357,6 +1684,14 @@ impl GlobalState {
                 .await
                 .expect("Updating mutator set must succeed");
 
+            if let Some(prune_depth) = myself.cli().prune_depth {
+                myself
+                    .chain
+                    .archival_state_mut()
+                    .prune_block_files(new_block.header().height, prune_depth)
+                    .await?;
+            }
+
             if let Some(coinbase_info) = coinbase_utxo_info {
                 // Notify wallet to expect the coinbase UTXO, as we mined this block
                 myself
@@ -1390,24 +1725,83 @@ impl GlobalState {
             let previous_ms_accumulator = tip_parent.body().mutator_set_accumulator.clone();
 
             // update wallet state with relevant UTXOs from this block
+            match announced_utxos {
+                Some(announced_utxos) => {
+                    myself
+                        .wallet_state
+                        .update_wallet_state_with_scanned_block(
+                            &previous_ms_accumulator,
+                            &new_block,
+                            announced_utxos,
+                        )
+                        .await?
+                }
+                None => {
+                    myself
+                        .wallet_state
+                        .update_wallet_state_with_new_block(&previous_ms_accumulator, &new_block)
+                        .await?
+                }
+            }
             myself
                 .wallet_state
-                .update_wallet_state_with_new_block(&previous_ms_accumulator, &new_block)
-                .await?;
+                .own_transactions
+                .update_with_block(&new_block);
 
             // Update mempool with UTXOs from this block. This is done by removing all transaction
             // that became invalid/was mined by this block.
 
-            myself
+            let mempool_events = myself
                 .mempool
                 .update_with_block(previous_ms_accumulator, &new_block, prover_lock)
                 .await;
+            myself.publish_mempool_events(&mempool_events);
+
+            // Only scan the wallet for the UTXOs of this block if they are journaled or indexed
+            let tx_index_enabled = myself.chain.archival_state().tx_index().is_some();
+            let (received, spent) = if myself.event_journal.is_some() || tx_index_enabled {
+                myself.wallet_utxos_in_block(new_block.hash()).await
+            } else {
+                (vec![], vec![])
+            };
+            if let Some(tx_index) = myself.chain.archival_state_mut().tx_index_mut() {
+                tx_index.index_utxos(&new_block, &received).await;
+            }
 
+            if myself.event_journal.is_some() {
+                let old_tip = myself.chain.light_state();
+                let (digest, height) = (new_block.hash(), new_block.header().height);
+                let mut journal_events = vec![];
+                if new_block.header().prev_block_digest != old_tip.hash() {
+                    journal_events.push(JournalEvent::Reorganization {
+                        old_tip: old_tip.hash(),
+                        old_height: old_tip.header().height,
+                        new_tip: digest,
+                        new_height: height,
+                    });
+                }
+                journal_events.push(JournalEvent::BlockAccepted { digest, height });
+                journal_events.extend(received.iter().map(|utxo| JournalEvent::UtxoReceived {
+                    amount: utxo.get_native_currency_amount(),
+                    block: digest,
+                }));
+                journal_events.extend(spent.iter().map(|utxo| JournalEvent::UtxoSpent {
+                    amount: utxo.get_native_currency_amount(),
+                    block: digest,
+                }));
+                for event in journal_events {
+                    myself.record_journal_event(event).await;
+                }
+            }
+
+            let new_tip_event = NodeEvent::new_tip(&new_block);
             myself.chain.light_state_mut().set_block(new_block);
 
             // Flush databases
             myself.flush_databases().await?;
 
+            myself.publish_event(new_tip_event);
+
             Ok(())
         }
 
@@ -1415,6 +1809,7 @@ impl GlobalState {
             self,
             new_block,
             coinbase_utxo_info,
+            announced_utxos,
             prover_lock
         ))
     }
@@ -1448,6 +1843,139 @@ impl GlobalState {
         // Ok(())
     }
 
+    /// Rebuild the wallet's own transactions that are neither in the mempool
+    /// nor confirmed on the canonical chain, e.g. because a reorganization
+    /// un-confirmed them. Each transaction is re-validated against the tip's
+    /// mutator set using the wallet's updated membership proofs.
+    ///
+    /// Transactions spending unconfirmed change are rebuilt the same way once
+    /// the transactions paying the change have left the mempool.
+    ///
+    /// Returns the rebuilt transactions, which the caller should insert into
+    /// the mempool and relay. Transactions that conflict with the new chain,
+    /// or whose inputs are no longer on it, are forgotten.
+    ///
+    /// Locking:
+    ///   * acquires `monitored_utxos_lock` for write
+    pub(crate) async fn resurrect_own_transactions(&mut self) -> Result<Vec<Transaction>> {
+        if self.wallet_state.own_transactions.txids().is_empty() {
+            return Ok(vec![]);
+        }
+
+        let tip_digest = self.chain.light_state().hash();
+        if !self.wallet_state.is_synced_to(tip_digest).await {
+            self.resync_membership_proofs_from_stored_blocks(tip_digest)
+                .await?;
+        }
+
+        let msa = self
+            .chain
+            .light_state()
+            .body()
+            .mutator_set_accumulator
+            .clone();
+        let monitored_utxos = self
+            .wallet_state
+            .wallet_db
+            .monitored_utxos()
+            .get_all()
+            .await;
+
+        let mut resurrected = vec![];
+        for txid in self.wallet_state.own_transactions.txids() {
+            if self.mempool.contains(txid) {
+                continue;
+            }
+
+            // A transaction spending unconfirmed change waits for its parents.
+            // A parent that left the mempool unconfirmed invalidates it.
+            let parents = self.wallet_state.own_transactions.parents(txid);
+            if parents.iter().any(|parent| self.mempool.contains(*parent)) {
+                continue;
+            }
+            let spends_unconfirmed_change = !parents.is_empty();
+
+            let Some(primitive_witness) =
+                self.wallet_state.own_transactions.primitive_witness(txid)
+            else {
+                continue;
+            };
+
+            // Look up the membership proof for the new tip of every input.
+            let new_membership_proofs = primitive_witness
+                .input_utxos
+                .utxos
+                .iter()
+                .zip(primitive_witness.input_membership_proofs.iter())
+                .map(|(utxo, old_mp)| {
+                    monitored_utxos
+                        .iter()
+                        .filter(|mutxo| mutxo.utxo == *utxo)
+                        .filter_map(|mutxo| mutxo.get_membership_proof_for_block(tip_digest))
+                        .find(|mp| {
+                            mp.sender_randomness == old_mp.sender_randomness
+                                && mp.receiver_preimage == old_mp.receiver_preimage
+                        })
+                })
+                .collect::<Option<Vec<_>>>();
+            let Some(new_membership_proofs) = new_membership_proofs else {
+                if spends_unconfirmed_change {
+                    warn!(
+                        "Forgetting own transaction {txid}: spent change was dropped unconfirmed"
+                    );
+                } else {
+                    warn!(
+                        "Forgetting own transaction {txid}: inputs are not on the canonical chain"
+                    );
+                }
+                self.wallet_state.own_transactions.forget(txid);
+                continue;
+            };
+
+            let num_unspent = primitive_witness
+                .input_utxos
+                .utxos
+                .iter()
+                .zip(new_membership_proofs.iter())
+                .filter(|(utxo, mp)| msa.verify(Hash::hash(*utxo), mp))
+                .count();
+            if num_unspent == 0 {
+                // Confirmed on the canonical chain.
+                continue;
+            }
+            if num_unspent != new_membership_proofs.len() {
+                warn!(
+                    "Forgetting own transaction {txid}: inputs were spent by another transaction"
+                );
+                self.wallet_state.own_transactions.forget(txid);
+                continue;
+            }
+
+            let Some(transaction) =
+                self.wallet_state
+                    .own_transactions
+                    .rebuild(txid, new_membership_proofs, &msa)
+            else {
+                continue;
+            };
+            if !transaction.is_confirmable_relative_to(&msa) {
+                warn!("Forgetting own transaction {txid}: not confirmable after rebuild");
+                self.wallet_state.own_transactions.forget(txid);
+                continue;
+            }
+
+            if spends_unconfirmed_change {
+                info!("Releasing own transaction {txid}: spent change was confirmed");
+            } else {
+                info!("Resurrecting own transaction {txid} un-confirmed by reorganization");
+            }
+            self.publish_event(NodeEvent::OwnTransactionResurrected(txid));
+            resurrected.push(transaction);
+        }
+
+        Ok(resurrected)
+    }
+
     #[inline]
     pub fn cli(&self) -> &cli_args::Args {
         &self.cli
@@ -1456,18 +1984,21 @@ impl GlobalState {
     /// clears all Tx from mempool and notifies wallet of changes.
     pub async fn mempool_clear(&mut self) {
         let events = self.mempool.clear();
+        self.publish_mempool_events(&events);
         self.wallet_state.handle_mempool_events(events).await
     }
 
     /// adds Tx to mempool and notifies wallet of change.
     pub async fn mempool_insert(&mut self, transaction: Transaction) {
         let events = self.mempool.insert(transaction);
+        self.publish_mempool_events(&events);
         self.wallet_state.handle_mempool_events(events).await
     }
 
     /// prunes stale tx in mempool and notifies wallet of changes.
     pub async fn mempool_prune_stale_transactions(&mut self) {
         let events = self.mempool.prune_stale_transactions();
+        self.publish_mempool_events(&events);
         self.wallet_state.handle_mempool_events(events).await
     }
 }
@@ -1556,6 +2087,128 @@ mod global_state_tests {
         assert!(handshake_data.listen_port.is_none());
     }
 
+    /// The change of a transaction spending the whole balance but the fee,
+    /// with the given change configuration.
+    async fn change_outputs_with(
+        state_lock: &mut GlobalStateLock,
+        change_outputs: usize,
+        change_dust_threshold: NeptuneCoins,
+    ) -> Vec<TxOutput> {
+        state_lock
+            .set_cli(cli_args::Args {
+                change_outputs,
+                change_dust_threshold,
+                ..Default::default()
+            })
+            .await;
+        let state = state_lock.lock_guard().await;
+        let change_key = state
+            .wallet_state
+            .wallet_secret
+            .nth_generation_spending_key_for_tests(0);
+        let in_seven_months = state.chain.light_state().header().timestamp + Timestamp::months(7);
+        let (_, change_outputs) = state
+            .create_transaction_details(
+                vec![].into(),
+                change_key.into(),
+                UtxoNotificationMedium::OffChain,
+                NeptuneCoins::one(),
+                in_seven_months,
+                CoinSelectionPolicy::default(),
+            )
+            .await
+            .unwrap();
+
+        change_outputs
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn change_is_split_into_outputs_above_dust_threshold() {
+        let network = Network::Main;
+        let mut alice = mock_genesis_global_state(network, 2, WalletSecret::devnet_wallet()).await;
+
+        let unsplit = change_outputs_with(&mut alice, 1, NeptuneCoins::zero()).await;
+        assert_eq!(1, unsplit.len());
+        let change = unsplit[0].utxo().get_native_currency_amount();
+
+        let split = change_outputs_with(&mut alice, 4, NeptuneCoins::zero()).await;
+        assert_eq!(4, split.len());
+        assert_eq!(
+            change,
+            split
+                .iter()
+                .map(|output| output.utxo().get_native_currency_amount())
+                .sum()
+        );
+        assert!(split
+            .iter()
+            .all(|output| output.utxo().lock_script_hash == unsplit[0].utxo().lock_script_hash));
+        assert!(split
+            .iter()
+            .map(|output| output.sender_randomness())
+            .all_unique());
+
+        // Four outputs would be below the threshold, three are not.
+        let dust_threshold = change.split(3)[2];
+        let split = change_outputs_with(&mut alice, 4, dust_threshold).await;
+        assert_eq!(3, split.len());
+        assert!(split
+            .iter()
+            .all(|output| output.utxo().get_native_currency_amount() >= dust_threshold));
+
+        // Change below the threshold is not split at all.
+        let split = change_outputs_with(&mut alice, 4, change + NeptuneCoins::one()).await;
+        assert_eq!(1, split.len());
+        assert_eq!(unsplit[0].utxo(), split[0].utxo());
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn handshakes_listen_port_is_none_when_not_listening() {
+        let network = Network::Main;
+        let mut bob = mock_genesis_global_state(network, 2, WalletSecret::devnet_wallet()).await;
+        let outbound_only = cli_args::Args {
+            no_listen: true,
+            ..Default::default()
+        };
+        bob.set_cli(outbound_only).await;
+
+        let handshake_data = bob
+            .global_state_lock
+            .lock_guard()
+            .await
+            .get_own_handshakedata()
+            .await;
+        assert!(handshake_data.listen_port.is_none());
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn new_tip_is_published_to_event_subscribers() {
+        let network = Network::Main;
+        let mut rng = thread_rng();
+        let mut global_state_lock =
+            mock_genesis_global_state(network, 2, WalletSecret::new_random()).await;
+        let mut events = global_state_lock.lock_guard().await.subscribe_to_events();
+
+        let genesis_block = Block::genesis_block(network);
+        let address = GenerationReceivingAddress::derive_from_seed(rng.gen());
+        let (block_1, _, _) = make_mock_block(&genesis_block, None, address, rng.gen());
+        global_state_lock
+            .set_new_tip(block_1.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            NodeEvent::NewTip {
+                digest: block_1.hash(),
+                height: block_1.header().height,
+            },
+            events.try_recv().unwrap()
+        );
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn premine_recipient_cannot_spend_premine_before_and_can_after_release_date() {
@@ -2181,7 +2834,7 @@ mod global_state_tests {
             .await
             .wallet_state
             .next_unused_spending_key(KeyType::Generation);
-        let (tx_to_alice_and_bob, maybe_change_output) = premine_receiver
+        let (tx_to_alice_and_bob, change_outputs) = premine_receiver
             .lock_guard()
             .await
             .create_transaction_with_prover_capability(
@@ -2197,7 +2850,7 @@ mod global_state_tests {
             )
             .await
             .unwrap();
-        let Some(change_output) = maybe_change_output else {
+        let Some(change_output) = change_outputs.into_iter().next() else {
             panic!("Expected change output to genesis receiver");
         };
 
@@ -2345,7 +2998,7 @@ mod global_state_tests {
         // state is being updated correctly with new blocks; not the
         // use-`ProofCollection`-instead-of-`SingleProof` functionality.
         // Weaker machines need to use the proof server.
-        let (tx_from_alice, maybe_change_for_alice) = alice
+        let (tx_from_alice, change_for_alice) = alice
             .lock_guard()
             .await
             .create_transaction_with_prover_capability(
@@ -2360,7 +3013,7 @@ mod global_state_tests {
             .await
             .unwrap();
         assert!(
-            maybe_change_for_alice.is_none(),
+            change_for_alice.is_empty(),
             "No change for Alice as she spent it all"
         );
 
@@ -2382,7 +3035,7 @@ mod global_state_tests {
                 genesis_spending_key.to_address().into(),
             ),
         ];
-        let (tx_from_bob, maybe_change_for_bob) = bob
+        let (tx_from_bob, change_for_bob) = bob
             .lock_guard()
             .await
             .create_transaction_with_prover_capability(
@@ -2398,7 +3051,7 @@ mod global_state_tests {
             .unwrap();
 
         assert!(
-            maybe_change_for_bob.is_none(),
+            change_for_bob.is_empty(),
             "No change for Bob as he spent it all"
         );
 
@@ -2981,7 +3634,7 @@ mod global_state_tests {
                     alice_state_mut.generate_tx_outputs(outputs, change_notification_medium);
 
                 // create tx.  utxo_notify_method is a test param.
-                let (alice_to_bob_tx, maybe_change_utxo) = alice_state_mut
+                let (alice_to_bob_tx, change_utxos) = alice_state_mut
                     .create_transaction_with_prover_capability(
                         tx_outputs.clone(),
                         alice_change_key,
@@ -2993,7 +3646,7 @@ mod global_state_tests {
                     )
                     .await
                     .unwrap();
-                let Some(change_utxo) = maybe_change_utxo else {
+                let Some(change_utxo) = change_utxos.into_iter().next() else {
                     panic!("A change Tx-output was expected");
                 };
 
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
 use std::time::SystemTime;
 
 use anyhow::Result;
@@ -8,14 +10,28 @@ use num_traits::Zero;
 use sysinfo::System;
 use tracing::info;
 
+use super::checkpoint_beacon::CheckpointBeacon;
+use super::coinjoin::CoinJoinCoordinator;
+use super::coinjoin::CoinJoinSessionInfo;
+use super::coinjoin::OwnCoinJoinContribution;
+use super::fork_watch::ForkWatch;
+use super::orphan_blocks::OrphanBlocks;
+use super::sync_progress::SyncProgress;
+use super::transaction_kernel_id::TransactionKernelId;
 use super::tx_proving_capability::TxProvingCapability;
 use crate::config_models::data_directory::DataDirectory;
 use crate::database::create_db_if_missing;
 use crate::database::NeptuneLevelDb;
 use crate::database::WriteBatchAsync;
+use crate::models::blockchain::block::block_height::BlockHeight;
 use crate::models::database::PeerDatabases;
 use crate::models::peer;
+use crate::models::peer::archival_scheduler::ArchivalScheduler;
+use crate::models::peer::own_block::OwnBlockAnnouncement;
+use crate::models::peer::rate_limit::HandshakeRateLimiter;
+use crate::models::peer::PeerSanctionReason;
 use crate::models::peer::PeerStanding;
+use crate::models::proof_abstractions::timestamp::Timestamp;
 
 pub const BANNED_IPS_DB_NAME: &str = "banned_ips";
 
@@ -38,6 +54,11 @@ pub struct NetworkingState {
     // Only the main task may update this flag
     pub syncing: bool,
 
+    /// Progress of the ongoing synchronization, if any. Set and cleared along
+    /// with `syncing` by the main task; peer tasks record the blocks they
+    /// receive and validate.
+    pub(crate) sync_progress: Option<SyncProgress>,
+
     // Read-only value set during startup
     pub instance_id: u128,
 
@@ -48,6 +69,56 @@ pub struct NetworkingState {
     /// record latest successful upgrade, merely latest attempt. This is to
     /// prevent excessive runs of the proof-upgrade functionality.
     pub last_tx_proof_upgrade_attempt: std::time::SystemTime,
+
+    /// Trusted checkpoint feed, used to prioritize sync targets and detect
+    /// eclipse attacks. Disabled unless trusted keys are configured.
+    pub beacon: CheckpointBeacon,
+
+    /// Outbound peers that were connected at last shutdown and were connected
+    /// to first at startup. Exempt from peer rotation.
+    pub anchor_peers: Vec<SocketAddr>,
+
+    /// Address under which the router forwards the peer port to this node, if
+    /// port mapping is enabled and succeeded. Announced in the handshake.
+    pub external_address: Option<SocketAddr>,
+
+    /// Latest answers of peers asked to connect back to this node.
+    pub reachability_reports: HashMap<SocketAddr, peer::ReachabilityReport>,
+
+    /// Transactions that were in the mempool at the last state checkpoint,
+    /// to be requested from the first peer to connect after startup.
+    pub(crate) checkpointed_mempool_txids: Vec<TransactionKernelId>,
+
+    /// When set, no new peer connections are accepted and no transactions are
+    /// admitted to the mempool. Set by the operator through RPC.
+    pub maintenance_mode: bool,
+
+    /// Blocks received before their parent, from any peer
+    pub(crate) orphan_blocks: OrphanBlocks,
+
+    /// Incoming connection attempts per IP address
+    handshake_rate_limiter: HandshakeRateLimiter,
+
+    /// Shares the serving of blocks from the archival state between peers
+    pub archival_scheduler: ArchivalScheduler,
+
+    /// The coinjoin session coordinated by this node. Only used if enabled on
+    /// the command line.
+    pub(crate) coinjoin_coordinator: CoinJoinCoordinator,
+
+    /// Latest coinjoin sessions announced by connected peers
+    pub(crate) coinjoin_sessions: HashMap<SocketAddr, CoinJoinSessionInfo>,
+
+    /// This node's contribution to a coinjoin session coordinated by a peer,
+    /// until the session is merged or fails
+    pub(crate) own_coinjoin_contribution: Option<OwnCoinJoinContribution>,
+
+    /// Headers announced by peers under consensus rules this binary does not
+    /// know
+    pub fork_watch: ForkWatch,
+
+    /// The latest block found by this node, ready to be sent to peers
+    pub(crate) own_block_announcement: Option<Arc<OwnBlockAnnouncement>>,
 }
 
 impl NetworkingState {
@@ -56,6 +127,7 @@ impl NetworkingState {
         peer_databases: PeerDatabases,
         syncing: bool,
         tx_proving_capability: Option<TxProvingCapability>,
+        beacon: CheckpointBeacon,
     ) -> Self {
         let tx_proving_capability =
             tx_proving_capability.unwrap_or_else(Self::estimate_proving_power);
@@ -64,15 +136,47 @@ impl NetworkingState {
             peer_map,
             peer_databases,
             syncing,
+            sync_progress: None,
             instance_id: rand::random(),
             tx_proving_capability,
 
             // Initialize to now to prevent tx proof upgrade to run immediately
             // after startup of the client.
             last_tx_proof_upgrade_attempt: SystemTime::now(),
+            beacon,
+            anchor_peers: vec![],
+            external_address: None,
+            reachability_reports: HashMap::new(),
+            checkpointed_mempool_txids: vec![],
+            maintenance_mode: false,
+            orphan_blocks: OrphanBlocks::default(),
+            handshake_rate_limiter: HandshakeRateLimiter::default(),
+            archival_scheduler: ArchivalScheduler::default(),
+            coinjoin_coordinator: CoinJoinCoordinator::default(),
+            coinjoin_sessions: HashMap::new(),
+            own_coinjoin_contribution: None,
+            fork_watch: ForkWatch::default(),
+            own_block_announcement: None,
         }
     }
 
+    /// Enter synchronization mode, tracking progress from the current tip
+    /// height towards the highest tip height claimed by peers.
+    pub(crate) fn start_syncing(
+        &mut self,
+        tip_height: BlockHeight,
+        target_height: BlockHeight,
+        now: Timestamp,
+    ) {
+        self.syncing = true;
+        self.sync_progress = Some(SyncProgress::new(tip_height, target_height, now));
+    }
+
+    pub(crate) fn stop_syncing(&mut self) {
+        self.syncing = false;
+        self.sync_progress = None;
+    }
+
     pub(crate) fn estimate_proving_power() -> TxProvingCapability {
         const SINGLE_PROOF_CORE_REQ: usize = 19;
         const SINGLE_PROOF_MEMORY_USAGE: u64 = (1u64 << 30) * 128;
@@ -158,6 +262,30 @@ impl NetworkingState {
         self.peer_databases.peer_standings.batch_write(batch).await
     }
 
+    /// Count an incoming connection attempt from `ip`. Returns false if the
+    /// attempt exceeds `max_per_minute` and should be refused, in which case
+    /// the stored standing of `ip` is lowered.
+    pub(crate) async fn admit_handshake_attempt(
+        &mut self,
+        ip: IpAddr,
+        max_per_minute: u32,
+    ) -> bool {
+        if self
+            .handshake_rate_limiter
+            .try_admit(ip, max_per_minute, Instant::now())
+        {
+            return true;
+        }
+
+        let mut standing = self
+            .get_peer_standing_from_database(ip)
+            .await
+            .unwrap_or_default();
+        standing.sanction(PeerSanctionReason::HandshakeFlood);
+        self.write_peer_standing_on_decrease(ip, standing).await;
+        false
+    }
+
     // Storing IP addresses is, according to this answer, not a violation of GDPR:
     // https://law.stackexchange.com/a/28609/45846
     // Wayback machine: https://web.archive.org/web/20220708143841/https://law.stackexchange.com/questions/28603/how-to-satisfy-gdprs-consent-requirement-for-ip-logging/28609
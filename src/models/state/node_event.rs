@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use tokio::sync::broadcast;
+
+use super::mempool::MempoolEvent;
+use super::transaction_kernel_id::TransactionKernelId;
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::models::blockchain::block::Block;
+use crate::prelude::twenty_first::math::digest::Digest;
+
+/// Number of events buffered per subscriber before the slowest subscriber
+/// starts missing events.
+pub(crate) const NODE_EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// A state transition of the node that external observers may subscribe to.
+///
+/// Events are best-effort: a subscriber that falls more than
+/// [`NODE_EVENT_CHANNEL_CAPACITY`] events behind will observe a
+/// [`broadcast::error::RecvError::Lagged`] and must resynchronize by querying
+/// the node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NodeEvent {
+    /// A new block was set as tip of the canonical chain.
+    NewTip { digest: Digest, height: BlockHeight },
+
+    /// A transaction was added to the mempool.
+    MempoolTransactionAdded(TransactionKernelId),
+
+    /// A transaction was removed from the mempool.
+    MempoolTransactionRemoved(TransactionKernelId),
+
+    /// A transaction sent by this wallet was un-confirmed by a reorganization
+    /// and has been rebuilt against the new tip for re-broadcasting.
+    OwnTransactionResurrected(TransactionKernelId),
+
+    /// The tip has not advanced for several target block intervals although
+    /// peers claim more proof-of-work. Peers are rotated and synchronization
+    /// is restarted.
+    TipStale {
+        digest: Digest,
+        height: BlockHeight,
+        age: Duration,
+    },
+}
+
+impl NodeEvent {
+    pub(crate) fn new_tip(block: &Block) -> Self {
+        Self::NewTip {
+            digest: block.hash(),
+            height: block.header().height,
+        }
+    }
+
+    /// Translate a mempool event into a node event, if it is of interest to
+    /// external observers.
+    pub(crate) fn from_mempool_event(event: &MempoolEvent) -> Option<Self> {
+        match event {
+            MempoolEvent::AddTx(tx) => Some(Self::MempoolTransactionAdded(tx.kernel.txid())),
+            MempoolEvent::RemoveTx(tx) => Some(Self::MempoolTransactionRemoved(tx.kernel.txid())),
+            MempoolEvent::UpdateTxMutatorSet(..) => None,
+        }
+    }
+}
+
+/// Create the broadcast channel over which [`NodeEvent`]s are published.
+pub(crate) fn node_event_channel() -> broadcast::Sender<NodeEvent> {
+    broadcast::channel(NODE_EVENT_CHANNEL_CAPACITY).0
+}
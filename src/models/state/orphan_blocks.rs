@@ -0,0 +1,112 @@
+//! Blocks received before their parent, kept until the parent arrives.
+//!
+//! Peers may deliver blocks out of order, for example when two blocks are
+//! found in quick succession. Instead of discarding such a block and later
+//! requesting it again, it is kept in a bounded pool keyed by its parent's
+//! digest while the parent is requested from the peer that sent it. Once the
+//! parent has been validated, the block is taken from the pool and handled as
+//! if it had just been received.
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+use tasm_lib::triton_vm::prelude::Digest;
+
+use crate::models::blockchain::block::Block;
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OrphanBlocks {
+    by_parent: HashMap<Digest, Vec<Block>>,
+
+    /// The parent digest of every orphan, in the order the orphans were
+    /// inserted, for evicting the oldest first
+    insertion_order: VecDeque<Digest>,
+}
+
+impl OrphanBlocks {
+    pub(crate) fn len(&self) -> usize {
+        self.insertion_order.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.insertion_order.is_empty()
+    }
+
+    pub(crate) fn contains(&self, block_digest: Digest) -> bool {
+        self.by_parent
+            .values()
+            .flatten()
+            .any(|block| block.hash() == block_digest)
+    }
+
+    /// Keep a block until its parent arrives, evicting the oldest orphans
+    /// beyond `max_num`. Returns false if the block was not kept, because it
+    /// already was or because `max_num` is zero.
+    pub(crate) fn insert(&mut self, block: Block, max_num: usize) -> bool {
+        if max_num == 0 || self.contains(block.hash()) {
+            return false;
+        }
+
+        let parent_digest = block.header().prev_block_digest;
+        self.by_parent.entry(parent_digest).or_default().push(block);
+        self.insertion_order.push_back(parent_digest);
+
+        while self.len() > max_num {
+            let oldest_parent = self.insertion_order.pop_front().unwrap();
+            let siblings = self.by_parent.get_mut(&oldest_parent).unwrap();
+            siblings.remove(0);
+            if siblings.is_empty() {
+                self.by_parent.remove(&oldest_parent);
+            }
+        }
+
+        true
+    }
+
+    /// Remove and return the orphans whose parent is the given block.
+    pub(crate) fn take_children(&mut self, parent_digest: Digest) -> Vec<Block> {
+        let children = self.by_parent.remove(&parent_digest).unwrap_or_default();
+        if !children.is_empty() {
+            self.insertion_order
+                .retain(|digest| *digest != parent_digest);
+        }
+
+        children
+    }
+}
+
+#[cfg(test)]
+mod orphan_blocks_tests {
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::state::wallet::WalletSecret;
+    use crate::tests::shared::make_mock_block;
+
+    #[test]
+    fn orphans_are_taken_by_parent_and_evicted_oldest_first() {
+        let network = Network::RegTest;
+        let address = WalletSecret::new_random()
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+        let genesis = Block::genesis_block(network);
+        let (block_1, _, _) = make_mock_block(&genesis, None, address, rand::random());
+        let (block_2, _, _) = make_mock_block(&block_1, None, address, rand::random());
+        let (block_3, _, _) = make_mock_block(&block_2, None, address, rand::random());
+        let (fork_2, _, _) = make_mock_block(&block_1, None, address, rand::random());
+
+        let mut orphans = OrphanBlocks::default();
+        assert!(!orphans.insert(block_2.clone(), 0));
+        assert!(orphans.insert(block_2.clone(), 2));
+        assert!(!orphans.insert(block_2.clone(), 2));
+        assert!(orphans.insert(block_3.clone(), 2));
+        assert!(orphans.insert(fork_2.clone(), 2));
+
+        // block 2 was evicted to make room for its sibling
+        assert_eq!(2, orphans.len());
+        assert!(!orphans.contains(block_2.hash()));
+        assert_eq!(vec![fork_2], orphans.take_children(block_1.hash()));
+        assert!(orphans.take_children(block_1.hash()).is_empty());
+        assert_eq!(vec![block_3], orphans.take_children(block_2.hash()));
+        assert!(orphans.is_empty());
+    }
+}
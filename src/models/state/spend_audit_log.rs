@@ -0,0 +1,216 @@
+//! Tamper-evident log of the spend-capable RPC calls made to this node, for
+//! operators who must keep records of every attempt to move funds.
+//!
+//! The log is a file of JSON lines in the data directory, only ever appended
+//! to. Each entry holds a digest of the call's parameters rather than the
+//! parameters themselves, and commits to the previous entry through its hash,
+//! such that editing, reordering or removing an entry other than the last
+//! breaks the chain. Keeping a copy of the latest hash elsewhere also guards
+//! the end of the log.
+
+use std::fs::OpenOptions;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::transaction_kernel_id::TransactionKernelId;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+
+pub const SPEND_AUDIT_LOG_FILE_NAME: &str = "spend_audit.jsonl";
+
+/// Hash the first entry commits to in place of a previous entry.
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Serializes appends, such that concurrent calls cannot both chain to the
+/// same entry.
+static APPEND_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SpendOutcome {
+    /// The wallet was locked, or the spend passphrase was wrong.
+    Unauthorized,
+
+    /// The transaction could not be built, or was not broadcast.
+    Failed,
+    Sent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SpendAuditEntry {
+    /// Position in the log, starting at zero
+    pub sequence: u64,
+    pub timestamp: Timestamp,
+
+    /// Name of the RPC method called
+    pub method: String,
+
+    /// Hex-encoded BLAKE3 hash of the JSON-encoded parameters, leaving out
+    /// the spend passphrase
+    pub params_digest: String,
+    pub outcome: SpendOutcome,
+    pub txid: Option<TransactionKernelId>,
+
+    /// `hash` of the previous entry
+    pub previous_hash: String,
+
+    /// Hex-encoded BLAKE3 hash of all the other fields
+    pub hash: String,
+}
+
+impl SpendAuditEntry {
+    /// Hex-encoded BLAKE3 hash of the JSON encoding of `params`.
+    pub fn params_digest(params: &impl Serialize) -> Result<String> {
+        let encoded = serde_json::to_vec(params)?;
+        Ok(blake3::hash(&encoded).to_hex().to_string())
+    }
+
+    fn compute_hash(&self) -> Result<String> {
+        let unhashed = Self {
+            hash: String::default(),
+            ..self.clone()
+        };
+        let encoded = serde_json::to_vec(&unhashed)?;
+        Ok(blake3::hash(&encoded).to_hex().to_string())
+    }
+
+    /// Append an entry for a call of `method` to the log in `data_dir`, and
+    /// return it.
+    pub fn append_to_log(
+        data_dir: &Path,
+        method: &str,
+        params_digest: String,
+        outcome: SpendOutcome,
+        txid: Option<TransactionKernelId>,
+        now: Timestamp,
+    ) -> Result<Self> {
+        let _guard = APPEND_LOCK.lock().unwrap();
+
+        let previous = Self::read_log(data_dir)?.pop();
+        let mut entry = Self {
+            sequence: previous
+                .as_ref()
+                .map_or(0, |previous| previous.sequence + 1),
+            timestamp: now,
+            method: method.to_string(),
+            params_digest,
+            outcome,
+            txid,
+            previous_hash: previous.map_or(GENESIS_HASH.to_string(), |previous| previous.hash),
+            hash: String::default(),
+        };
+        entry.hash = entry.compute_hash()?;
+
+        let path = data_dir.join(SPEND_AUDIT_LOG_FILE_NAME);
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&path)
+            .with_context(|| format!("could not open {}", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)
+            .with_context(|| format!("could not write {}", path.display()))?;
+        file.sync_data()
+            .with_context(|| format!("could not sync {}", path.display()))?;
+
+        Ok(entry)
+    }
+
+    /// All entries in the log in `data_dir`, oldest first.
+    pub fn read_log(data_dir: &Path) -> Result<Vec<Self>> {
+        let path = data_dir.join(SPEND_AUDIT_LOG_FILE_NAME);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .open(&path)
+            .with_context(|| format!("could not open {}", path.display()))?;
+        let mut entries = vec![];
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            entries.push(
+                serde_json::from_str(&line)
+                    .with_context(|| format!("could not parse {}", path.display()))?,
+            );
+        }
+
+        Ok(entries)
+    }
+
+    /// Check that `entries` form an unbroken chain, starting with the first
+    /// entry of a log.
+    pub fn verify_chain(entries: &[Self]) -> Result<()> {
+        let mut previous_hash = GENESIS_HASH.to_string();
+        for (sequence, entry) in (0..).zip(entries) {
+            if entry.sequence != sequence {
+                bail!("entry {sequence} has sequence number {}", entry.sequence);
+            }
+            if entry.previous_hash != previous_hash {
+                bail!("entry {sequence} does not follow the previous entry");
+            }
+            if entry.hash != entry.compute_hash()? {
+                bail!("entry {sequence} does not match its hash");
+            }
+            previous_hash = entry.hash.clone();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod spend_audit_log_tests {
+    use super::*;
+
+    #[test]
+    fn tampering_breaks_the_chain() {
+        let data_dir =
+            std::env::temp_dir().join(format!("spend-audit-log-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&data_dir).unwrap();
+        assert!(SpendAuditEntry::read_log(&data_dir).unwrap().is_empty());
+
+        let now = Timestamp::now();
+        for (method, outcome) in [
+            ("send", SpendOutcome::Unauthorized),
+            ("send", SpendOutcome::Sent),
+            ("send_to_many", SpendOutcome::Failed),
+        ] {
+            let params_digest = SpendAuditEntry::params_digest(&(method, 1)).unwrap();
+            SpendAuditEntry::append_to_log(&data_dir, method, params_digest, outcome, None, now)
+                .unwrap();
+        }
+
+        let entries = SpendAuditEntry::read_log(&data_dir).unwrap();
+        assert_eq!(3, entries.len());
+        assert_eq!(2, entries[2].sequence);
+        SpendAuditEntry::verify_chain(&entries).unwrap();
+
+        let mut edited = entries.clone();
+        edited[1].outcome = SpendOutcome::Failed;
+        assert!(SpendAuditEntry::verify_chain(&edited).is_err());
+
+        let mut removed = entries.clone();
+        removed.remove(1);
+        assert!(SpendAuditEntry::verify_chain(&removed).is_err());
+
+        let mut rehashed = entries;
+        rehashed[1].outcome = SpendOutcome::Failed;
+        rehashed[1].hash = rehashed[1].compute_hash().unwrap();
+        assert!(SpendAuditEntry::verify_chain(&rehashed).is_err());
+
+        std::fs::remove_dir_all(data_dir).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+//! Checkpoint of the global state, written periodically and at shutdown, such
+//! that a restarted node does not have to re-derive state from the databases
+//! from scratch.
+//!
+//! The checkpoint holds the tip block, from which the light state is built
+//! without reading the block files, the ids of the transactions in the
+//! mempool, which are requested from the first peer to connect, and the
+//! amount of wallet recovery data already checked against the wallet
+//! database. On startup, only recovery data written since the checkpoint is
+//! checked, unless the wallet database changed in the meantime.
+//!
+//! A checkpoint is only an optimization. If it is missing, stale, or cannot be
+//! read, the node starts as if there were none.
+
+use std::path::Path;
+
+use anyhow::Context;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use twenty_first::math::digest::Digest;
+
+use super::transaction_kernel_id::TransactionKernelId;
+use super::GlobalState;
+use crate::config_models::network::Network;
+use crate::database::storage::storage_vec::traits::*;
+use crate::models::blockchain::block::Block;
+use crate::prelude::twenty_first;
+
+pub const STATE_CHECKPOINT_FILE_NAME: &str = "state_checkpoint.bin";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct StateCheckpoint {
+    pub(crate) network: Network,
+    pub(crate) tip: Block,
+    pub(crate) mempool_txids: Vec<TransactionKernelId>,
+
+    /// Block the wallet was synced to
+    pub(crate) wallet_sync_label: Digest,
+    pub(crate) num_monitored_utxos: u64,
+
+    /// Length in bytes of the wallet's incoming UTXO recovery data that has
+    /// been checked against the wallet database
+    pub(crate) verified_recovery_data_len: u64,
+}
+
+impl StateCheckpoint {
+    pub(crate) async fn capture(global_state: &GlobalState) -> Self {
+        let wallet_db = &global_state.wallet_state.wallet_db;
+        Self {
+            network: global_state.cli().network,
+            tip: global_state.chain.light_state().clone(),
+            mempool_txids: global_state
+                .mempool
+                .get_sorted_iter()
+                .map(|(txid, _fee_density)| txid)
+                .collect(),
+            wallet_sync_label: wallet_db.get_sync_label().await,
+            num_monitored_utxos: wallet_db.monitored_utxos().len().await,
+            verified_recovery_data_len: global_state.wallet_state.recovery_data_len().await,
+        }
+    }
+
+    /// Write the checkpoint, replacing the previous one atomically.
+    pub(crate) fn store(&self, data_dir: &Path) -> Result<()> {
+        let path = data_dir.join(STATE_CHECKPOINT_FILE_NAME);
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, bincode::serialize(self)?)
+            .with_context(|| format!("could not write {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("could not write {}", path.display()))
+    }
+
+    /// Read the checkpoint, if one was written for the given network.
+    pub(crate) fn load(data_dir: &Path, network: Network) -> Result<Option<Self>> {
+        let path = data_dir.join(STATE_CHECKPOINT_FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let bytes =
+            std::fs::read(&path).with_context(|| format!("could not read {}", path.display()))?;
+        let checkpoint: Self = bincode::deserialize(&bytes)?;
+        Ok(Some(checkpoint).filter(|checkpoint| checkpoint.network == network))
+    }
+
+    /// The tip block, if it is still the tip.
+    pub(crate) fn tip_if_current(&self, tip_digest: Digest) -> Option<Block> {
+        (self.tip.hash() == tip_digest).then(|| self.tip.clone())
+    }
+
+    /// Length of the recovery data that need not be checked again, given the
+    /// state of the wallet database on startup. Zero if the wallet database
+    /// changed since the checkpoint was taken.
+    pub(crate) async fn verified_recovery_data_len(&self, global_state: &GlobalState) -> u64 {
+        let wallet_db = &global_state.wallet_state.wallet_db;
+        let wallet_unchanged = wallet_db.get_sync_label().await == self.wallet_sync_label
+            && wallet_db.monitored_utxos().len().await == self.num_monitored_utxos;
+        if wallet_unchanged {
+            self.verified_recovery_data_len
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod state_checkpoint_tests {
+    use super::*;
+    use crate::models::state::wallet::WalletSecret;
+    use crate::tests::shared::mock_genesis_global_state;
+
+    #[tokio::test]
+    async fn checkpoint_round_trips_and_is_invalidated_by_wallet_changes() {
+        let network = Network::RegTest;
+        let data_dir = std::env::temp_dir().join(format!("checkpoint-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&data_dir).unwrap();
+
+        let global_state_lock =
+            mock_genesis_global_state(network, 2, WalletSecret::devnet_wallet()).await;
+        let global_state = global_state_lock.lock_guard().await;
+        let checkpoint = StateCheckpoint::capture(&global_state).await;
+        checkpoint.store(&data_dir).unwrap();
+
+        assert!(StateCheckpoint::load(&data_dir, Network::Main)
+            .unwrap()
+            .is_none());
+        let mut loaded = StateCheckpoint::load(&data_dir, network).unwrap().unwrap();
+        let tip_digest = global_state.chain.light_state().hash();
+        assert_eq!(
+            tip_digest,
+            loaded.tip_if_current(tip_digest).unwrap().hash()
+        );
+        assert_eq!(
+            checkpoint.verified_recovery_data_len,
+            loaded.verified_recovery_data_len(&global_state).await
+        );
+
+        loaded.num_monitored_utxos += 1;
+        assert_eq!(0, loaded.verified_recovery_data_len(&global_state).await);
+    }
+}
@@ -0,0 +1,230 @@
+//! Progress of synchronization, broken down into the stages that each block
+//! passes through.
+//!
+//! While syncing, blocks are downloaded in batches, validated by the peer task
+//! that received them, and then applied to the archival state and mutator set
+//! by the main loop. Headers are transferred along with their blocks, so
+//! header and block download progress coincide and are reported as a single
+//! stage. The heights reached by each stage tell how far along the sync is,
+//! and where blocks are piling up.
+
+use std::fmt::Display;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SyncProgress {
+    started: Timestamp,
+    start_height: BlockHeight,
+
+    /// Highest tip height claimed by a peer
+    target_height: BlockHeight,
+
+    downloaded_height: BlockHeight,
+    validated_height: BlockHeight,
+    applied_height: BlockHeight,
+}
+
+impl SyncProgress {
+    pub(crate) fn new(tip_height: BlockHeight, target_height: BlockHeight, now: Timestamp) -> Self {
+        Self {
+            started: now,
+            start_height: tip_height,
+            target_height: target_height.max(tip_height),
+            downloaded_height: tip_height,
+            validated_height: tip_height,
+            applied_height: tip_height,
+        }
+    }
+
+    pub(crate) fn raise_target_height(&mut self, height: BlockHeight) {
+        self.target_height = self.target_height.max(height);
+    }
+
+    pub(crate) fn record_downloaded(&mut self, height: BlockHeight) {
+        self.downloaded_height = self.downloaded_height.max(height);
+        self.raise_target_height(height);
+    }
+
+    pub(crate) fn record_validated(&mut self, height: BlockHeight) {
+        self.validated_height = self.validated_height.max(height);
+        self.record_downloaded(height);
+    }
+
+    pub(crate) fn record_applied(&mut self, height: BlockHeight) {
+        self.applied_height = self.applied_height.max(height);
+        self.record_validated(height);
+    }
+
+    pub(crate) fn report(&self, now: Timestamp) -> SyncProgressReport {
+        let elapsed = if now > self.started {
+            now - self.started
+        } else {
+            Timestamp::default()
+        };
+        let stage = |reached_height: BlockHeight| {
+            SyncStage::new(
+                blocks_between(self.start_height, reached_height),
+                blocks_between(self.start_height, self.target_height),
+                elapsed,
+            )
+        };
+
+        SyncProgressReport {
+            start_height: self.start_height,
+            target_height: self.target_height,
+            elapsed,
+            download: stage(self.downloaded_height),
+            validation_backlog: blocks_between(self.validated_height, self.downloaded_height),
+            application: stage(self.applied_height),
+            application_backlog: blocks_between(self.applied_height, self.validated_height),
+        }
+    }
+}
+
+fn blocks_between(from: BlockHeight, to: BlockHeight) -> u64 {
+    u64::from(to).saturating_sub(u64::from(from))
+}
+
+/// Progress of one stage of synchronization, in blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SyncStage {
+    pub blocks_done: u64,
+    pub blocks_total: u64,
+    pub percent: f64,
+
+    /// Average since synchronization started
+    pub blocks_per_second: f64,
+
+    /// Estimated time left at the average rate. `None` if no block has passed
+    /// the stage yet.
+    pub eta: Option<Timestamp>,
+}
+
+impl SyncStage {
+    fn new(blocks_done: u64, blocks_total: u64, elapsed: Timestamp) -> Self {
+        let percent = if blocks_total == 0 {
+            100.0
+        } else {
+            100.0 * blocks_done as f64 / blocks_total as f64
+        };
+        let blocks_per_second = if elapsed.to_millis() == 0 {
+            0.0
+        } else {
+            blocks_done as f64 * 1000.0 / elapsed.to_millis() as f64
+        };
+        let eta = (blocks_per_second > 0.0).then(|| {
+            let blocks_left = blocks_total.saturating_sub(blocks_done);
+            Timestamp::millis((blocks_left as f64 * 1000.0 / blocks_per_second) as u64)
+        });
+
+        Self {
+            blocks_done,
+            blocks_total,
+            percent,
+            blocks_per_second,
+            eta,
+        }
+    }
+}
+
+impl Display for SyncStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{} blocks ({:.1}%, {:.2} blocks/s",
+            self.blocks_done, self.blocks_total, self.percent, self.blocks_per_second
+        )?;
+        match self.eta {
+            Some(eta) => write!(f, ", ETA {}s)", eta.to_millis() / 1000),
+            None => write!(f, ")"),
+        }
+    }
+}
+
+/// Progress of synchronization, as reported through RPC and the log.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SyncProgressReport {
+    /// Tip height when synchronization started
+    pub start_height: BlockHeight,
+
+    /// Highest tip height claimed by a peer
+    pub target_height: BlockHeight,
+
+    /// Time since synchronization started
+    pub elapsed: Timestamp,
+
+    /// Blocks received from peers, along with their headers
+    pub download: SyncStage,
+
+    /// Number of received blocks not yet validated
+    pub validation_backlog: u64,
+
+    /// Blocks applied to the archival state and mutator set
+    pub application: SyncStage,
+
+    /// Number of validated blocks not yet applied
+    pub application_backlog: u64,
+}
+
+impl Display for SyncProgressReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "syncing from height {} to {}; downloaded {}; validation backlog: {} blocks; \
+            applied {}; application backlog: {} blocks",
+            self.start_height,
+            self.target_height,
+            self.download,
+            self.validation_backlog,
+            self.application,
+            self.application_backlog
+        )
+    }
+}
+
+#[cfg(test)]
+mod sync_progress_tests {
+    use super::*;
+
+    #[test]
+    fn stages_report_backlogs_and_eta() {
+        let started = Timestamp::now();
+        let mut progress = SyncProgress::new(100u64.into(), 300u64.into(), started);
+        progress.record_downloaded(200u64.into());
+        progress.record_validated(150u64.into());
+        progress.record_applied(120u64.into());
+
+        let report = progress.report(started + Timestamp::seconds(10));
+        assert_eq!(100, report.download.blocks_done);
+        assert_eq!(200, report.download.blocks_total);
+        assert_eq!(50.0, report.download.percent);
+        assert_eq!(10.0, report.download.blocks_per_second);
+        assert_eq!(Some(Timestamp::seconds(10)), report.download.eta);
+        assert_eq!(50, report.validation_backlog);
+        assert_eq!(30, report.application_backlog);
+        assert_eq!(2.0, report.application.blocks_per_second);
+
+        // later stages never overtake earlier ones
+        progress.record_applied(400u64.into());
+        let report = progress.report(started + Timestamp::seconds(20));
+        assert_eq!(BlockHeight::from(400u64), report.target_height);
+        assert_eq!(0, report.validation_backlog);
+        assert_eq!(0, report.application_backlog);
+        assert_eq!(100.0, report.application.percent);
+        assert_eq!(Some(Timestamp::default()), report.application.eta);
+    }
+
+    #[test]
+    fn nothing_done_has_no_eta() {
+        let now = Timestamp::now();
+        let report = SyncProgress::new(5u64.into(), 10u64.into(), now).report(now);
+        assert_eq!(0.0, report.download.percent);
+        assert_eq!(None, report.download.eta);
+    }
+}
@@ -1,7 +1,9 @@
 use std::fmt::Display;
+use std::str::FromStr;
 
 use get_size::GetSize;
 use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use tasm_lib::triton_vm::prelude::Digest;
@@ -11,11 +13,12 @@ use tasm_lib::twenty_first::prelude::CpuParallel;
 use tasm_lib::twenty_first::prelude::MerkleTreeMaker;
 
 use crate::models::blockchain::transaction::transaction_kernel::TransactionKernel;
+use crate::util_types::json_schema::DigestSchema;
 
 /// A unique identifier of a transaction whose value is unaffected by a
 /// transaction update.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, GetSize, Hash, Serialize, Deserialize)]
-pub struct TransactionKernelId(Digest);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GetSize, Hash, Serialize, Deserialize, JsonSchema)]
+pub struct TransactionKernelId(#[schemars(with = "DigestSchema")] Digest);
 
 impl Display for TransactionKernelId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -23,6 +26,25 @@ impl Display for TransactionKernelId {
     }
 }
 
+impl FromStr for TransactionKernelId {
+    type Err = anyhow::Error;
+
+    /// Parse the display format, or hex.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match Digest::from_str(s) {
+            Ok(digest) => Ok(Self(digest)),
+            Err(_) => Ok(Self(Digest::try_from_hex(s)?)),
+        }
+    }
+}
+
+impl From<TransactionKernelId> for Digest {
+    fn from(txid: TransactionKernelId) -> Self {
+        txid.0
+    }
+}
+
 impl TransactionKernel {
     // Return a digest that is unchanged by transaction updates.
     ///
@@ -0,0 +1,221 @@
+//! Optional index for block explorer style queries, enabled with
+//! `--build-tx-index`.
+//!
+//! Public announcements carry the receiver identifier of the address they
+//! notify in the clear, so the blocks containing announcements for a given
+//! receiver identifier can be indexed for every block. UTXOs, on the other
+//! hand, are only committed to on chain, and their lock script hashes are
+//! known only to those who can decrypt the announcements. The lock script
+//! hash index therefore only covers UTXOs received by this node's wallet.
+//!
+//! Blocks are indexed when they are stored, whether they end up canonical or
+//! not, and entries are never removed. Queries filter out blocks that are not
+//! in the canonical chain.
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use twenty_first::math::b_field_element::BFieldElement;
+use twenty_first::math::digest::Digest;
+
+use crate::config_models::data_directory::DataDirectory;
+use crate::database::create_db_if_missing;
+use crate::database::NeptuneLevelDb;
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::models::blockchain::block::Block;
+use crate::models::blockchain::transaction::utxo::Utxo;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use crate::models::state::wallet::address::common::receiver_identifier_from_public_announcement;
+use crate::prelude::twenty_first;
+use crate::util_types::json_schema::DigestSchema;
+
+pub const TX_INDEX_DB_NAME: &str = "tx_index";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum TxIndexKey {
+    /// Receiver identifier of public announcements
+    Announcement(BFieldElement),
+    LockScriptHash(Digest),
+
+    /// Height of the tip when the last block was indexed
+    IndexedHeight,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum TxIndexValue {
+    Blocks(Vec<Digest>),
+    Utxos(Vec<IndexedUtxo>),
+    IndexedHeight(BlockHeight),
+}
+
+/// A UTXO received in a block, as found in the lock script hash index.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct IndexedUtxo {
+    #[schemars(with = "DigestSchema")]
+    pub block: Digest,
+    pub height: BlockHeight,
+    pub amount: NeptuneCoins,
+}
+
+#[derive(Debug, Clone)]
+pub struct TxIndex {
+    db: NeptuneLevelDb<TxIndexKey, TxIndexValue>,
+}
+
+impl TxIndex {
+    pub async fn open(data_dir: &DataDirectory) -> Result<Self> {
+        let tx_index_db_dir_path = data_dir.tx_index_database_dir_path();
+        DataDirectory::create_dir_if_not_exists(&tx_index_db_dir_path).await?;
+        let db = NeptuneLevelDb::new(&tx_index_db_dir_path, &create_db_if_missing()).await?;
+
+        Ok(Self { db })
+    }
+
+    /// Height of the tip when the last block was indexed, or `None` if no
+    /// block was indexed yet.
+    pub(crate) async fn indexed_height(&self) -> Option<BlockHeight> {
+        match self.db.get(TxIndexKey::IndexedHeight).await {
+            Some(TxIndexValue::IndexedHeight(height)) => Some(height),
+            _ => None,
+        }
+    }
+
+    /// Index the public announcements of a block that became the tip.
+    pub(crate) async fn index_block(&mut self, block: &Block) {
+        let block_digest = block.hash();
+        for announcement in &block.body().transaction_kernel.public_announcements {
+            let Ok(receiver_identifier) =
+                receiver_identifier_from_public_announcement(announcement)
+            else {
+                continue;
+            };
+
+            let key = TxIndexKey::Announcement(receiver_identifier);
+            let mut blocks = self.blocks(key).await;
+            if !blocks.contains(&block_digest) {
+                blocks.push(block_digest);
+                self.db.put(key, TxIndexValue::Blocks(blocks)).await;
+            }
+        }
+
+        self.db
+            .put(
+                TxIndexKey::IndexedHeight,
+                TxIndexValue::IndexedHeight(block.header().height),
+            )
+            .await;
+    }
+
+    /// Index UTXOs received by the wallet in the given block.
+    pub(crate) async fn index_utxos(&mut self, block: &Block, utxos: &[Utxo]) {
+        for utxo in utxos {
+            let key = TxIndexKey::LockScriptHash(utxo.lock_script_hash);
+            let indexed_utxo = IndexedUtxo {
+                block: block.hash(),
+                height: block.header().height,
+                amount: utxo.get_native_currency_amount(),
+            };
+            let mut indexed_utxos = self.utxos(key).await;
+            if !indexed_utxos.contains(&indexed_utxo) {
+                indexed_utxos.push(indexed_utxo);
+                self.db.put(key, TxIndexValue::Utxos(indexed_utxos)).await;
+            }
+        }
+    }
+
+    /// Digests of the blocks containing a public announcement for the given
+    /// receiver identifier, canonical or not, in the order they were stored.
+    pub(crate) async fn blocks_for_announcement(
+        &self,
+        receiver_identifier: BFieldElement,
+    ) -> Vec<Digest> {
+        self.blocks(TxIndexKey::Announcement(receiver_identifier))
+            .await
+    }
+
+    /// UTXOs received by the wallet with the given lock script hash, in blocks
+    /// canonical or not, in the order they were stored.
+    pub(crate) async fn utxos_for_lock_script_hash(
+        &self,
+        lock_script_hash: Digest,
+    ) -> Vec<IndexedUtxo> {
+        self.utxos(TxIndexKey::LockScriptHash(lock_script_hash))
+            .await
+    }
+
+    async fn blocks(&self, key: TxIndexKey) -> Vec<Digest> {
+        match self.db.get(key).await {
+            Some(TxIndexValue::Blocks(blocks)) => blocks,
+            _ => vec![],
+        }
+    }
+
+    async fn utxos(&self, key: TxIndexKey) -> Vec<IndexedUtxo> {
+        match self.db.get(key).await {
+            Some(TxIndexValue::Utxos(utxos)) => utxos,
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tx_index_tests {
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::blockchain::transaction::lock_script::LockScript;
+    use crate::models::blockchain::transaction::PublicAnnouncement;
+    use crate::models::blockchain::transaction::Transaction;
+    use crate::models::proof_abstractions::timestamp::Timestamp;
+    use crate::tests::shared::make_mock_transaction;
+
+    #[tokio::test]
+    async fn announcements_and_utxos_are_indexed_once_per_block() {
+        let network = Network::RegTest;
+        let data_dir = DataDirectory::get(
+            Some(std::env::temp_dir().join(format!("tx-index-{}", rand::random::<u64>()))),
+            network,
+        )
+        .unwrap();
+        let mut tx_index = TxIndex::open(&data_dir).await.unwrap();
+        assert_eq!(None, tx_index.indexed_height().await);
+
+        let receiver_identifier: BFieldElement = rand::random();
+        let announcement = PublicAnnouncement::new(vec![
+            BFieldElement::new(79),
+            receiver_identifier,
+            BFieldElement::new(1),
+        ]);
+        let mut transaction: Transaction = make_mock_transaction(vec![], vec![]);
+        transaction.kernel.public_announcements = vec![announcement.clone(), announcement];
+
+        let genesis = Block::genesis_block(network);
+        let block = Block::block_template_invalid_proof(
+            &genesis,
+            transaction,
+            genesis.header().timestamp + Timestamp::minutes(10),
+            None,
+        );
+        tx_index.index_block(&block).await;
+        tx_index.index_block(&block).await;
+        assert_eq!(
+            vec![block.hash()],
+            tx_index.blocks_for_announcement(receiver_identifier).await
+        );
+        assert!(tx_index
+            .blocks_for_announcement(BFieldElement::new(1))
+            .await
+            .is_empty());
+        assert_eq!(Some(block.header().height), tx_index.indexed_height().await);
+
+        let utxo = Utxo::new_native_currency(LockScript::anyone_can_spend(), NeptuneCoins::new(5));
+        tx_index.index_utxos(&block, &[utxo.clone()]).await;
+        tx_index.index_utxos(&block, &[utxo.clone()]).await;
+        let indexed_utxos = tx_index
+            .utxos_for_lock_script_hash(utxo.lock_script_hash)
+            .await;
+        assert_eq!(1, indexed_utxos.len());
+        assert_eq!(NeptuneCoins::new(5), indexed_utxos[0].amount);
+        assert_eq!(block.hash(), indexed_utxos[0].block);
+    }
+}
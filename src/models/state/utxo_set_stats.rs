@@ -0,0 +1,158 @@
+//! Statistics of the UTXO set, for monitoring the growth of the chain state.
+
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::util_types::mutator_set::chunk::Chunk;
+use crate::util_types::mutator_set::shared::CHUNK_SIZE;
+use crate::util_types::mutator_set::shared::NUM_TRIALS;
+use crate::util_types::mutator_set::shared::WINDOW_SIZE;
+
+/// The ages, in days, for which the number of younger addition records is
+/// reported.
+pub(crate) const ADDITION_RECORD_AGES_IN_DAYS: [u64; 4] = [1, 7, 30, 365];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AdditionRecordAge {
+    pub max_age_days: u64,
+
+    /// Number of addition records of blocks at most `max_age_days` older than
+    /// the tip. `None` if unknown because the block at that age was pruned.
+    pub count: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct UtxoSetStats {
+    pub tip_height: BlockHeight,
+
+    /// Number of addition records in the mutator set, i.e., the number of
+    /// UTXOs ever created
+    pub addition_record_count: u64,
+
+    /// Number of removal records applied to the mutator set, i.e., the number
+    /// of UTXOs ever spent
+    pub removal_record_count: u64,
+
+    /// Number of unspent UTXOs
+    pub utxo_count: u64,
+
+    /// Cumulative distribution of the ages of addition records, for the ages
+    /// in [`ADDITION_RECORD_AGES_IN_DAYS`]
+    pub addition_record_ages: Vec<AdditionRecordAge>,
+
+    /// Fraction of the indices of the active window of the sliding-window
+    /// Bloom filter (SWBF) that are set
+    pub swbf_active_density: f64,
+
+    /// Fraction of the indices of the inactive chunks of the SWBF that are set
+    pub swbf_inactive_density: f64,
+
+    pub average_outputs_per_block: f64,
+}
+
+/// Counts of the indices of a part of the SWBF.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct SwbfIndexCounts {
+    /// Number of insertions, counting repeated insertions of the same index
+    pub inserted: u64,
+
+    /// Number of distinct indices set
+    pub distinct: u64,
+}
+
+impl SwbfIndexCounts {
+    /// Counts of the given indices, which the SWBF stores as a sorted
+    /// multiset.
+    pub(crate) fn of_sorted(indices: &[u32]) -> Self {
+        Self {
+            inserted: indices.len() as u64,
+            distinct: indices.iter().dedup().count() as u64,
+        }
+    }
+
+    pub(crate) fn of_chunks(chunks: &[Chunk]) -> Self {
+        chunks
+            .iter()
+            .map(|chunk| Self::of_sorted(&chunk.relative_indices))
+            .fold(Self::default(), |acc, counts| Self {
+                inserted: acc.inserted + counts.inserted,
+                distinct: acc.distinct + counts.distinct,
+            })
+    }
+}
+
+impl UtxoSetStats {
+    pub(crate) fn new(
+        tip_height: BlockHeight,
+        addition_record_count: u64,
+        active_window: SwbfIndexCounts,
+        inactive_chunks: SwbfIndexCounts,
+        num_chunks: u64,
+        addition_record_ages: Vec<AdditionRecordAge>,
+    ) -> Self {
+        // Every removal record inserts exactly `NUM_TRIALS` indices.
+        let removal_record_count =
+            (active_window.inserted + inactive_chunks.inserted) / u64::from(NUM_TRIALS);
+
+        let swbf_inactive_density = match num_chunks {
+            0 => 0.0,
+            n => inactive_chunks.distinct as f64 / (n * u64::from(CHUNK_SIZE)) as f64,
+        };
+
+        Self {
+            tip_height,
+            addition_record_count,
+            removal_record_count,
+            utxo_count: addition_record_count.saturating_sub(removal_record_count),
+            addition_record_ages,
+            swbf_active_density: active_window.distinct as f64 / f64::from(WINDOW_SIZE),
+            swbf_inactive_density,
+            average_outputs_per_block: addition_record_count as f64
+                / (u64::from(tip_height) + 1) as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod utxo_set_stats_tests {
+    use super::*;
+
+    #[test]
+    fn repeated_indices_count_as_removals_but_not_density() {
+        let removal_indices = (0..NUM_TRIALS).map(|i| i / 9).collect_vec();
+        let active_window = SwbfIndexCounts::of_sorted(&removal_indices);
+        assert_eq!(
+            SwbfIndexCounts {
+                inserted: u64::from(NUM_TRIALS),
+                distinct: 5,
+            },
+            active_window
+        );
+
+        let chunks = vec![
+            Chunk::from_indices(&(0..NUM_TRIALS).collect_vec()),
+            Chunk::empty_chunk(),
+        ];
+        let inactive_chunks = SwbfIndexCounts::of_chunks(&chunks);
+
+        let stats = UtxoSetStats::new(
+            BlockHeight::from(9u64),
+            30,
+            active_window,
+            inactive_chunks,
+            chunks.len() as u64,
+            vec![],
+        );
+        assert_eq!(2, stats.removal_record_count);
+        assert_eq!(28, stats.utxo_count);
+        assert_eq!(3.0, stats.average_outputs_per_block);
+        assert_eq!(5.0 / f64::from(WINDOW_SIZE), stats.swbf_active_density);
+        assert_eq!(
+            f64::from(NUM_TRIALS) / f64::from(2 * CHUNK_SIZE),
+            stats.swbf_inactive_density
+        );
+    }
+}
@@ -1,5 +1,5 @@
 mod address_type;
-mod common;
+pub(crate) mod common;
 
 pub mod generation_address;
 pub mod symmetric_key;
@@ -12,3 +12,6 @@ pub use address_type::ReceivingAddress;
 /// SpendingKey abstracts over any spending key type and should be used
 /// wherever possible.
 pub use address_type::SpendingKey;
+/// ViewingKey recognizes the UTXOs sent to a SpendingKey, without being able
+/// to unlock them.
+pub(crate) use address_type::ViewingKey;
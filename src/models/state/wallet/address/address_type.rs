@@ -1,11 +1,16 @@
 //! provides an abstraction over key and address types.
 
+use aead::Key;
+use aes_gcm::Aes256Gcm;
 use anyhow::bail;
 use anyhow::Result;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
+use strum::IntoEnumIterator;
 use tasm_lib::triton_vm::prelude::Digest;
 use tracing::warn;
+use twenty_first::math::lattice;
 use twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
 
 use super::common;
@@ -32,8 +37,9 @@ use crate::BFieldElement;
 // actually stored in PublicAnnouncement.
 
 /// enumerates available cryptographic key implementations for sending and receiving funds.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[repr(u8)]
+#[non_exhaustive]
 pub enum KeyType {
     /// [generation_address] built on [twenty_first::math::lattice::kem]
     ///
@@ -92,7 +98,8 @@ impl KeyType {
 /// This enum provides an abstraction API for Address types, so that
 /// a method or struct may simply accept a `ReceivingAddress` and be
 /// forward-compatible with new types of Address as they are implemented.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[non_exhaustive]
 pub enum ReceivingAddress {
     /// a [generation_address]
     Generation(Box<generation_address::GenerationReceivingAddress>),
@@ -185,11 +192,12 @@ impl ReceivingAddress {
         }
     }
 
-    /// encrypts a [Utxo] and `sender_randomness` secret for purpose of transferring to payment recipient
-    pub fn encrypt(&self, utxo: &Utxo, sender_randomness: Digest) -> Vec<BFieldElement> {
+    /// encrypts a [Utxo], `sender_randomness` secret, and memo for purpose of
+    /// transferring to payment recipient
+    pub(crate) fn encrypt(&self, payload: &UtxoNotificationPayload) -> Vec<BFieldElement> {
         match self {
-            Self::Generation(a) => a.encrypt(utxo, sender_randomness),
-            Self::Symmetric(a) => a.encrypt(utxo, sender_randomness),
+            Self::Generation(a) => a.encrypt(payload),
+            Self::Symmetric(a) => a.encrypt(payload),
         }
     }
 
@@ -220,6 +228,40 @@ impl ReceivingAddress {
         // note: not implemented for SymmetricKey (yet?)
     }
 
+    /// parses an address to pay on `network` from its bech32m encoding
+    ///
+    /// Unlike [Self::from_bech32m], an address encoded for another network is
+    /// recognized as such, and refused unless `allow_other_network` is set.
+    /// Main, Alpha and Beta share their address prefix, so addresses of these
+    /// networks cannot be told apart.
+    pub fn from_bech32m_for_network(
+        encoded: &str,
+        network: Network,
+        allow_other_network: bool,
+    ) -> Result<Self> {
+        let own_network_error = match Self::from_bech32m(encoded, network) {
+            Ok(address) => return Ok(address),
+            Err(err) => err,
+        };
+
+        let Some((other_network, address)) = Network::iter().find_map(|other_network| {
+            Self::from_bech32m(encoded, other_network)
+                .ok()
+                .map(|address| (other_network, address))
+        }) else {
+            return Err(own_network_error);
+        };
+
+        if !allow_other_network {
+            bail!(
+                "Address is for network {other_network}, not {network}. Paying it here \
+                could make the funds unreachable for the recipient."
+            );
+        }
+        warn!("Paying address of network {other_network} on {network}");
+        Ok(address)
+    }
+
     /// generates a lock script from the spending lock.
     ///
     /// Satisfaction of this lock script establishes the UTXO owner's assent to
@@ -235,6 +277,16 @@ impl ReceivingAddress {
     pub fn matches_public_announcement_key_type(&self, pa: &PublicAnnouncement) -> bool {
         matches!(KeyType::try_from(pa), Ok(kt) if kt == KeyType::from(self))
     }
+
+    /// returns true if the [PublicAnnouncement] is a UTXO notification for
+    /// this address. Only the holder of the spending key can decrypt it.
+    pub fn is_recipient_of(&self, pa: &PublicAnnouncement) -> bool {
+        self.matches_public_announcement_key_type(pa)
+            && matches!(
+                common::receiver_identifier_from_public_announcement(pa),
+                Ok(r) if r == self.receiver_identifier()
+            )
+    }
 }
 
 /// Represents any type of Neptune spending key.
@@ -301,8 +353,12 @@ impl SpendingKey {
         }
     }
 
-    /// decrypts an array of BFieldElement into a [Utxo] and [Digest] representing `sender_randomness`.
-    pub fn decrypt(&self, ciphertext_bfes: &[BFieldElement]) -> Result<(Utxo, Digest)> {
+    /// decrypts an array of BFieldElement into a [Utxo], a [Digest]
+    /// representing `sender_randomness`, and an optional memo.
+    pub(crate) fn decrypt(
+        &self,
+        ciphertext_bfes: &[BFieldElement],
+    ) -> Result<UtxoNotificationPayload> {
         match self {
             Self::Generation(k) => k.decrypt(ciphertext_bfes),
             Self::Symmetric(k) => Ok(k.decrypt(ciphertext_bfes)?),
@@ -321,57 +377,140 @@ impl SpendingKey {
         &'a self,
         tx_kernel: &'a TransactionKernel,
     ) -> impl Iterator<Item = AnnouncedUtxo> + 'a {
-        // pre-compute some fields.
-        let receiver_identifier = self.receiver_identifier();
-        let receiver_preimage = self.privacy_preimage();
-        let receiver_digest = receiver_preimage.hash();
-
-        // for all public announcements
-        tx_kernel
-            .public_announcements
-            .iter()
-
-            // ... that are marked as encrypted to our key type
-            .filter(|pa| self.matches_public_announcement_key_type(pa))
-
-            // ... that match the receiver_id of this key
-            .filter(move |pa| {
-                matches!(common::receiver_identifier_from_public_announcement(pa), Ok(r) if r == receiver_identifier)
-            })
-
-            // ... that have a ciphertext field
-            .filter_map(|pa| self.ok_warn(common::ciphertext_from_public_announcement(pa)))
-
-            // ... which can be decrypted with this key
-            .filter_map(|c| self.ok_warn(self.decrypt(&c)))
-
-            // ... map to AnnouncedUtxo
-            .map(move |(utxo, sender_randomness)| {
-                // and join those with the receiver digest to get a commitment
-                // Note: the commitment is computed in the same way as in the mutator set.
-                AnnouncedUtxo {
-                    addition_record: commit(Hash::hash(&utxo), sender_randomness, receiver_digest),
-                    utxo,
-                    sender_randomness,
-                    receiver_preimage,
-                }
-            })
-    }
-
-    /// converts a result into an Option and logs a warning on any error
-    fn ok_warn<T>(&self, result: Result<T>) -> Option<T> {
-        match result {
-            Ok(v) => Some(v),
-            Err(e) => {
-                warn!("possible loss of funds! skipping public announcement for symmetric key with receiver_identifier: {}.  error: {}", self.receiver_identifier(), e.to_string());
-                None
-            }
+        scan_for_announced_utxos(
+            KeyType::from(self),
+            self.receiver_identifier(),
+            self.privacy_preimage(),
+            move |ciphertext| self.decrypt(ciphertext),
+            tx_kernel,
+        )
+    }
+}
+
+/// The part of a [SpendingKey] that recognizes the UTXOs sent to it, but
+/// cannot unlock them. A locked wallet keeps only these, see
+/// [encrypted_secret](crate::models::state::wallet::encrypted_secret).
+#[derive(Debug, Clone)]
+pub(crate) struct ViewingKey {
+    address: ReceivingAddress,
+    privacy_preimage: Digest,
+    decryption_key: DecryptionKey,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DecryptionKey {
+    Generation(lattice::kem::SecretKey),
+    Symmetric(Key<Aes256Gcm>),
+}
+
+impl From<SpendingKey> for ViewingKey {
+    fn from(key: SpendingKey) -> Self {
+        let decryption_key = match key {
+            SpendingKey::Generation(k) => DecryptionKey::Generation(k.decryption_key),
+            SpendingKey::Symmetric(k) => DecryptionKey::Symmetric(k.secret_key()),
+        };
+        Self {
+            address: key.to_address(),
+            privacy_preimage: key.privacy_preimage(),
+            decryption_key,
         }
     }
+}
 
-    /// returns true if the [PublicAnnouncement] has a type-flag that matches the type of this key
-    fn matches_public_announcement_key_type(&self, pa: &PublicAnnouncement) -> bool {
-        matches!(KeyType::try_from(pa), Ok(kt) if kt == KeyType::from(self))
+impl ViewingKey {
+    /// returns the address of the spending key this key was made from.
+    pub(crate) fn address(&self) -> &ReceivingAddress {
+        &self.address
+    }
+
+    /// returns the privacy preimage.
+    pub(crate) fn privacy_preimage(&self) -> Digest {
+        self.privacy_preimage
+    }
+
+    /// decrypts an array of BFieldElement into a [UtxoNotificationPayload].
+    pub(crate) fn decrypt(
+        &self,
+        ciphertext_bfes: &[BFieldElement],
+    ) -> Result<UtxoNotificationPayload> {
+        match &self.decryption_key {
+            DecryptionKey::Generation(sk) => generation_address::decrypt(*sk, ciphertext_bfes),
+            DecryptionKey::Symmetric(sk) => Ok(symmetric_key::decrypt(sk, ciphertext_bfes)?),
+        }
+    }
+
+    /// scans public announcements in a [Transaction] and finds any that match
+    /// this key, like [SpendingKey::scan_for_announced_utxos()].
+    pub(crate) fn scan_for_announced_utxos<'a>(
+        &'a self,
+        tx_kernel: &'a TransactionKernel,
+    ) -> impl Iterator<Item = AnnouncedUtxo> + 'a {
+        scan_for_announced_utxos(
+            KeyType::from(&self.address),
+            self.address.receiver_identifier(),
+            self.privacy_preimage,
+            move |ciphertext| self.decrypt(ciphertext),
+            tx_kernel,
+        )
+    }
+}
+
+/// scans the public announcements of a transaction for UTXOs announced to the
+/// key of the given type, receiver identifier and privacy preimage, decrypting
+/// them with `decrypt`.
+fn scan_for_announced_utxos<'a>(
+    key_type: KeyType,
+    receiver_identifier: BFieldElement,
+    receiver_preimage: Digest,
+    decrypt: impl Fn(&[BFieldElement]) -> Result<UtxoNotificationPayload> + 'a,
+    tx_kernel: &'a TransactionKernel,
+) -> impl Iterator<Item = AnnouncedUtxo> + 'a {
+    // pre-compute some fields.
+    let receiver_digest = receiver_preimage.hash();
+
+    // for all public announcements
+    tx_kernel
+        .public_announcements
+        .iter()
+
+        // ... that are marked as encrypted to our key type
+        .filter(move |pa| matches!(KeyType::try_from(*pa), Ok(kt) if kt == key_type))
+
+        // ... that match the receiver_id of this key
+        .filter(move |pa| {
+            matches!(common::receiver_identifier_from_public_announcement(pa), Ok(r) if r == receiver_identifier)
+        })
+
+        // ... that have a ciphertext field
+        .filter_map(move |pa| ok_warn(receiver_identifier, common::ciphertext_from_public_announcement(pa)))
+
+        // ... which can be decrypted with this key
+        .filter_map(move |c| ok_warn(receiver_identifier, decrypt(&c)))
+
+        // ... map to AnnouncedUtxo
+        .map(move |payload| {
+            // and join those with the receiver digest to get a commitment
+            // Note: the commitment is computed in the same way as in the mutator set.
+            let utxo = payload.utxo();
+            let sender_randomness = payload.sender_randomness();
+            AnnouncedUtxo {
+                addition_record: commit(Hash::hash(&utxo), sender_randomness, receiver_digest),
+                utxo,
+                sender_randomness,
+                receiver_preimage,
+                memo: payload.memo().cloned(),
+            }
+        })
+}
+
+/// converts a result into an Option and logs a warning on any error
+fn ok_warn<T>(receiver_identifier: BFieldElement, result: Result<T>) -> Option<T> {
+    match result {
+        Ok(v) => Some(v),
+        Err(e) => {
+            warn!("possible loss of funds! skipping public announcement for symmetric key with receiver_identifier: {}.  error: {}", receiver_identifier, e.to_string());
+            None
+        }
     }
 }
 
@@ -388,6 +527,7 @@ mod test {
     use test_strategy::proptest;
 
     use super::*;
+    use crate::models::blockchain::transaction::memo::Memo;
     use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
     use crate::tests::shared::make_mock_transaction;
 
@@ -446,6 +586,34 @@ mod test {
         worker::test_bech32m_conversion(GenerationReceivingAddress::derive_from_seed(seed).into());
     }
 
+    #[test]
+    fn addresses_of_other_networks_are_refused_unless_allowed() {
+        let address: ReceivingAddress =
+            GenerationReceivingAddress::derive_from_seed(random()).into();
+        for address_network in Network::iter() {
+            let encoded = address.to_bech32m(address_network).unwrap();
+            for node_network in Network::iter() {
+                let same_prefix =
+                    address_network.address_network_byte() == node_network.address_network_byte();
+                let strict =
+                    ReceivingAddress::from_bech32m_for_network(&encoded, node_network, false);
+                assert_eq!(
+                    same_prefix,
+                    strict.is_ok(),
+                    "address of {address_network} on {node_network}"
+                );
+
+                let allowed =
+                    ReceivingAddress::from_bech32m_for_network(&encoded, node_network, true);
+                assert_eq!(address, allowed.unwrap());
+            }
+        }
+
+        assert!(
+            ReceivingAddress::from_bech32m_for_network("nolgam1xyz", Network::Main, true).is_err()
+        );
+    }
+
     mod worker {
         use super::*;
 
@@ -487,7 +655,10 @@ mod test {
                 .generate_public_announcement(utxo_notification_payload);
 
             // 7. verify that the public_announcement is marked as our key type.
-            assert!(key.matches_public_announcement_key_type(&public_announcement));
+            assert_eq!(
+                KeyType::from(&key),
+                KeyType::try_from(&public_announcement).unwrap()
+            );
 
             // 8. add the public announcement to the mock tx.
             mock_tx
@@ -507,6 +678,15 @@ mod test {
             assert_eq!(expected_addition_record, announced_utxo.addition_record);
             assert_eq!(sender_randomness, announced_utxo.sender_randomness);
             assert_eq!(key.privacy_preimage(), announced_utxo.receiver_preimage);
+
+            // 12. verify that the viewing key of this key finds the same utxo.
+            let viewing_key = ViewingKey::from(key);
+            let viewed_utxos = viewing_key
+                .scan_for_announced_utxos(&mock_tx.kernel)
+                .collect_vec();
+            assert_eq!(1, viewed_utxos.len());
+            assert_eq!(expected_addition_record, viewed_utxos[0].addition_record);
+            assert_eq!(key.privacy_preimage(), viewed_utxos[0].receiver_preimage);
         }
 
         /// This tests encrypting and decrypting with a [SpendingKey]
@@ -520,16 +700,20 @@ mod test {
             // 2. generate sender randomness
             let sender_randomness: Digest = random();
 
-            // 3. encrypt secrets (utxo, sender_randomness)
-            let ciphertext = key.to_address().encrypt(&utxo, sender_randomness);
+            // 3. encrypt secrets (utxo, sender_randomness), with and without memo
+            let payload = UtxoNotificationPayload::new(utxo, sender_randomness);
+            let memo: Memo = "invoice #42".parse().unwrap();
+            let payload_with_memo = payload.clone().with_memo(Some(memo));
+            let ciphertext = key.to_address().encrypt(&payload);
             println!("ciphertext.get_size() = {}", ciphertext.len() * 8);
+            let ciphertext_with_memo = key.to_address().encrypt(&payload_with_memo);
 
-            // 4. decrypt secrets
-            let (utxo_again, sender_randomness_again) = key.decrypt(&ciphertext).unwrap();
-
-            // 5. verify that decrypted secrets match original secrets
-            assert_eq!(utxo, utxo_again);
-            assert_eq!(sender_randomness, sender_randomness_again);
+            // 4. decrypt secrets and verify that they match the original ones
+            assert_eq!(payload, key.decrypt(&ciphertext).unwrap());
+            assert_eq!(
+                payload_with_memo,
+                key.decrypt(&ciphertext_with_memo).unwrap()
+            );
         }
 
         /// tests key generation, signing, and decrypting with a [SpendingKey]
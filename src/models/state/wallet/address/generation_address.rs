@@ -1,4 +1,4 @@
-//! provides an asymmetric key interface for sending and claiming [Utxo].
+//! provides an asymmetric key interface for sending and claiming [Utxo](crate::models::blockchain::transaction::utxo::Utxo).
 //!
 //! The asymmetric key is based on [lattice::kem] and encrypts a symmetric key
 //! based on [aes_gcm::Aes256Gcm] which encrypts the actual payload.
@@ -21,6 +21,7 @@ use anyhow::Result;
 use bech32::FromBase32;
 use bech32::ToBase32;
 use bech32::Variant;
+use schemars::JsonSchema;
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
 use twenty_first::math::b_field_element::BFieldElement;
@@ -36,13 +37,19 @@ use crate::models::blockchain::shared::Hash;
 use crate::models::blockchain::transaction::lock_script::LockScript;
 use crate::models::blockchain::transaction::lock_script::LockScriptAndWitness;
 use crate::models::blockchain::transaction::transaction_output::UtxoNotificationPayload;
-use crate::models::blockchain::transaction::utxo::Utxo;
 use crate::models::blockchain::transaction::PublicAnnouncement;
 use crate::prelude::twenty_first;
+use crate::util_types::json_schema::BFieldElementSchema;
+use crate::util_types::json_schema::DigestSchema;
+use crate::util_types::json_schema::KemPublicKeySchema;
 
 pub(super) const GENERATION_FLAG_U8: u8 = 79;
 pub const GENERATION_FLAG: BFieldElement = BFieldElement::new(GENERATION_FLAG_U8 as u64);
 
+fn derive_privacy_preimage(seed: Digest) -> Digest {
+    Hash::hash_varlen(&[seed.values().to_vec(), vec![BFieldElement::new(0)]].concat())
+}
+
 #[derive(Clone, Debug, Copy)]
 pub struct GenerationSpendingKey {
     pub receiver_identifier: BFieldElement,
@@ -52,11 +59,15 @@ pub struct GenerationSpendingKey {
     pub seed: Digest,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub struct GenerationReceivingAddress {
+    #[schemars(with = "BFieldElementSchema")]
     pub receiver_identifier: BFieldElement,
+    #[schemars(with = "KemPublicKeySchema")]
     pub encryption_key: lattice::kem::PublicKey,
+    #[schemars(with = "DigestSchema")]
     pub privacy_digest: Digest,
+    #[schemars(with = "DigestSchema")]
     pub spending_lock: Digest,
 }
 
@@ -78,10 +89,15 @@ impl GenerationSpendingKey {
     }
 
     pub fn derive_from_seed(seed: Digest) -> Self {
-        let privacy_preimage =
-            Hash::hash_varlen(&[seed.values().to_vec(), vec![BFieldElement::new(0)]].concat());
         let unlock_key =
             Hash::hash_varlen(&[seed.values().to_vec(), vec![BFieldElement::new(1)]].concat());
+        Self::derive_from_seed_and_unlock_key(seed, unlock_key)
+    }
+
+    /// Derive everything but the unlock key from the seed. Used for keys that
+    /// share their unlock key, see [hd_derivation](crate::models::state::wallet::hd_derivation).
+    pub(crate) fn derive_from_seed_and_unlock_key(seed: Digest, unlock_key: Digest) -> Self {
+        let privacy_preimage = derive_privacy_preimage(seed);
         let randomness: [u8; 32] = common::shake256::<32>(&bincode::serialize(&seed).unwrap());
         let (sk, _pk) = lattice::kem::keygen(randomness);
         let receiver_identifier = common::derive_receiver_id(seed);
@@ -109,34 +125,8 @@ impl GenerationSpendingKey {
     }
 
     /// Decrypt a Generation Address ciphertext
-    pub(super) fn decrypt(&self, ciphertext: &[BFieldElement]) -> Result<(Utxo, Digest)> {
-        // parse ciphertext
-        if ciphertext.len() <= CIPHERTEXT_SIZE_IN_BFES {
-            bail!("Ciphertext does not have nonce.");
-        }
-        let (kem_ctxt, remainder_ctxt) = ciphertext.split_at(CIPHERTEXT_SIZE_IN_BFES);
-        if remainder_ctxt.len() <= 1 {
-            bail!("Ciphertext does not have payload.")
-        }
-        let (nonce_ctxt, dem_ctxt) = remainder_ctxt.split_at(1);
-        let kem_ctxt_array: [BFieldElement; CIPHERTEXT_SIZE_IN_BFES] = kem_ctxt.try_into().unwrap();
-
-        // decrypt
-        let shared_key = match lattice::kem::dec(self.decryption_key, kem_ctxt_array.into()) {
-            Some(sk) => sk,
-            None => bail!("Could not establish shared secret key."),
-        };
-        let cipher = Aes256Gcm::new(&shared_key.into());
-        let nonce_as_bytes = [nonce_ctxt[0].value().to_be_bytes().to_vec(), vec![0u8; 4]].concat();
-        let nonce = Nonce::from_slice(&nonce_as_bytes); // almost 64 bits; unique per message
-        let ciphertext_bytes = common::bfes_to_bytes(dem_ctxt)?;
-        let plaintext = match cipher.decrypt(nonce, ciphertext_bytes.as_ref()) {
-            Ok(ptxt) => ptxt,
-            Err(_) => bail!("Failed to decrypt symmetric payload."),
-        };
-
-        // convert plaintext to utxo and digest
-        Ok(bincode::deserialize(&plaintext)?)
+    pub(super) fn decrypt(&self, ciphertext: &[BFieldElement]) -> Result<UtxoNotificationPayload> {
+        decrypt(self.decryption_key, ciphertext)
     }
 
     fn generate_spending_lock(&self) -> Digest {
@@ -144,6 +134,41 @@ impl GenerationSpendingKey {
     }
 }
 
+/// Decrypt a Generation Address ciphertext with the decryption key alone,
+/// which cannot unlock the UTXOs sent to the address.
+pub(super) fn decrypt(
+    decryption_key: lattice::kem::SecretKey,
+    ciphertext: &[BFieldElement],
+) -> Result<UtxoNotificationPayload> {
+    // parse ciphertext
+    if ciphertext.len() <= CIPHERTEXT_SIZE_IN_BFES {
+        bail!("Ciphertext does not have nonce.");
+    }
+    let (kem_ctxt, remainder_ctxt) = ciphertext.split_at(CIPHERTEXT_SIZE_IN_BFES);
+    if remainder_ctxt.len() <= 1 {
+        bail!("Ciphertext does not have payload.")
+    }
+    let (nonce_ctxt, dem_ctxt) = remainder_ctxt.split_at(1);
+    let kem_ctxt_array: [BFieldElement; CIPHERTEXT_SIZE_IN_BFES] = kem_ctxt.try_into().unwrap();
+
+    // decrypt
+    let shared_key = match lattice::kem::dec(decryption_key, kem_ctxt_array.into()) {
+        Some(sk) => sk,
+        None => bail!("Could not establish shared secret key."),
+    };
+    let cipher = Aes256Gcm::new(&shared_key.into());
+    let nonce_as_bytes = [nonce_ctxt[0].value().to_be_bytes().to_vec(), vec![0u8; 4]].concat();
+    let nonce = Nonce::from_slice(&nonce_as_bytes); // almost 64 bits; unique per message
+    let ciphertext_bytes = common::bfes_to_bytes(dem_ctxt)?;
+    let plaintext = match cipher.decrypt(nonce, ciphertext_bytes.as_ref()) {
+        Ok(ptxt) => ptxt,
+        Err(_) => bail!("Failed to decrypt symmetric payload."),
+    };
+
+    // convert plaintext to utxo, digest, and memo
+    Ok(UtxoNotificationPayload::from_plaintext(&plaintext)?)
+}
+
 impl GenerationReceivingAddress {
     pub fn from_spending_key(spending_key: &GenerationSpendingKey) -> Self {
         let seed = spending_key.seed;
@@ -164,6 +189,19 @@ impl GenerationReceivingAddress {
         Self::from_spending_key(&spending_key)
     }
 
+    /// The address of the key derived from the seed and an unlock key with
+    /// the given hash, without knowing the unlock key.
+    pub(crate) fn derive_from_seed_and_spending_lock(seed: Digest, spending_lock: Digest) -> Self {
+        let randomness: [u8; 32] = common::shake256::<32>(&bincode::serialize(&seed).unwrap());
+        let (_sk, pk) = lattice::kem::keygen(randomness);
+        Self {
+            receiver_identifier: common::derive_receiver_id(seed),
+            encryption_key: pk,
+            privacy_digest: derive_privacy_preimage(seed).hash(),
+            spending_lock,
+        }
+    }
+
     /// Determine whether the given witness unlocks the lock defined by this receiving
     /// address.
     pub fn can_unlock_with(&self, witness: &[BFieldElement]) -> bool {
@@ -173,13 +211,13 @@ impl GenerationReceivingAddress {
         }
     }
 
-    pub fn encrypt(&self, utxo: &Utxo, sender_randomness: Digest) -> Vec<BFieldElement> {
+    pub(crate) fn encrypt(&self, payload: &UtxoNotificationPayload) -> Vec<BFieldElement> {
         let (randomness, nonce_bfe) =
-            deterministically_derive_seed_and_nonce(utxo, sender_randomness);
+            deterministically_derive_seed_and_nonce(&payload.utxo(), payload.sender_randomness());
         let (shared_key, kem_ctxt) = lattice::kem::enc(self.encryption_key, randomness);
 
         // convert payload to bytes
-        let plaintext = bincode::serialize(&(utxo, sender_randomness)).unwrap();
+        let plaintext = payload.to_plaintext();
 
         // generate symmetric ciphertext
         let cipher = Aes256Gcm::new(&shared_key.into());
@@ -201,12 +239,7 @@ impl GenerationReceivingAddress {
     fn get_hrp(network: Network) -> String {
         // NOLGA: Neptune lattice-based generation address
         let mut hrp = "nolga".to_string();
-        let network_byte: char = match network {
-            Network::Alpha | Network::Beta | Network::Main => 'm',
-            Network::Testnet => 't',
-            Network::RegTest => 'r',
-        };
-        hrp.push(network_byte);
+        hrp.push(network.address_network_byte());
         hrp
     }
 
@@ -258,11 +291,7 @@ impl GenerationReceivingAddress {
     ) -> PublicAnnouncement {
         let ciphertext = [
             &[GENERATION_FLAG_U8.into(), self.receiver_identifier],
-            self.encrypt(
-                &utxo_notification_payload.utxo(),
-                utxo_notification_payload.sender_randomness(),
-            )
-            .as_slice(),
+            self.encrypt(&utxo_notification_payload).as_slice(),
         ]
         .concat();
 
@@ -1,4 +1,4 @@
-//! provides a symmetric key interface based on aes-256-gcm for sending and claiming [Utxo]
+//! provides a symmetric key interface based on aes-256-gcm for sending and claiming [Utxo](crate::models::blockchain::transaction::utxo::Utxo)
 
 use aead::Aead;
 use aead::Key;
@@ -6,6 +6,7 @@ use aead::KeyInit;
 use aes_gcm::Aes256Gcm;
 use aes_gcm::Nonce;
 use anyhow::Result;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use twenty_first::math::b_field_element::BFieldElement;
@@ -18,9 +19,9 @@ use crate::models::blockchain::shared::Hash;
 use crate::models::blockchain::transaction::lock_script::LockScript;
 use crate::models::blockchain::transaction::lock_script::LockScriptAndWitness;
 use crate::models::blockchain::transaction::transaction_output::UtxoNotificationPayload;
-use crate::models::blockchain::transaction::utxo::Utxo;
 use crate::models::blockchain::transaction::PublicAnnouncement;
 use crate::prelude::twenty_first;
+use crate::util_types::json_schema::DigestSchema;
 
 /// represents a symmetric key decryption error
 #[derive(Debug, thiserror::Error)]
@@ -74,8 +75,9 @@ pub const SYMMETRIC_KEY_FLAG: BFieldElement = BFieldElement::new(SYMMETRIC_KEY_F
 ///
 /// The implementation can be easily changed later if needed as the type is
 /// opaque.
-#[derive(Clone, Debug, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub struct SymmetricKey {
+    #[schemars(with = "DigestSchema")]
     seed: Digest,
 }
 
@@ -108,51 +110,34 @@ impl SymmetricKey {
         common::derive_receiver_id(self.seed)
     }
 
-    /// decrypt a ciphertext into utxo secrets (utxo, sender_randomness)
+    /// decrypt a ciphertext into utxo secrets (utxo, sender_randomness) and
+    /// memo
     ///
     /// The ciphertext_bfes param must contain the nonce in the first
     /// field and the ciphertext in the remaining fields.
     ///
     /// The output of `encrypt()` should be used as the input to `decrypt()`.
-    pub fn decrypt(
+    pub(crate) fn decrypt(
         &self,
         ciphertext_bfes: &[BFieldElement],
-    ) -> Result<(Utxo, Digest), DecryptError> {
-        const NONCE_LEN: usize = 1;
-
-        // 1. separate nonce from ciphertext.
-        let (nonce_ctxt, ciphertext) = match ciphertext_bfes.len() > NONCE_LEN {
-            true => ciphertext_bfes.split_at(NONCE_LEN),
-            false => return Err(DecryptError::MissingNonce),
-        };
-
-        // 2. generate Nonce and cyphertext_bytes
-        let nonce_as_bytes = [&nonce_ctxt[0].value().to_be_bytes(), [0u8; 4].as_slice()].concat();
-        let nonce = Nonce::from_slice(&nonce_as_bytes); // almost 64 bits; unique per message
-        let ciphertext_bytes = common::bfes_to_bytes(ciphertext)?;
-
-        // 3. decypt ciphertext to plaintext
-        let cipher = Aes256Gcm::new(&self.secret_key());
-        let plaintext = cipher.decrypt(nonce, ciphertext_bytes.as_ref())?;
-
-        // 4. deserialize plaintext into (utxo, sender_randomness)
-        Ok(bincode::deserialize(&plaintext)?)
+    ) -> Result<UtxoNotificationPayload, DecryptError> {
+        decrypt(&self.secret_key(), ciphertext_bfes)
     }
 
-    /// encrypts utxo secrets (utxo, sender_randomness) into ciphertext
+    /// encrypts utxo secrets (utxo, sender_randomness) and memo into ciphertext
     ///
     /// The output of `encrypt()` should be used as the input to `decrypt()`.
-    pub fn encrypt(&self, utxo: &Utxo, sender_randomness: Digest) -> Vec<BFieldElement> {
+    pub(crate) fn encrypt(&self, payload: &UtxoNotificationPayload) -> Vec<BFieldElement> {
         // 1. init randomness
         let (_randomness, nonce_bfe) =
-            deterministically_derive_seed_and_nonce(utxo, sender_randomness);
+            deterministically_derive_seed_and_nonce(&payload.utxo(), payload.sender_randomness());
 
         // 2. generate random nonce
         let nonce_as_bytes = [&nonce_bfe.value().to_be_bytes(), [0u8; 4].as_slice()].concat();
         let nonce = Nonce::from_slice(&nonce_as_bytes); // almost 64 bits; unique per message
 
         // 3. convert secrets to plaintext bytes
-        let plaintext = bincode::serialize(&(utxo, sender_randomness)).unwrap();
+        let plaintext = payload.to_plaintext();
 
         // 4. encrypt plaintext to symmetric ciphertext bytes
         let cipher = Aes256Gcm::new(&self.secret_key());
@@ -193,14 +178,39 @@ impl SymmetricKey {
     ) -> PublicAnnouncement {
         let ciphertext = [
             &[SYMMETRIC_KEY_FLAG_U8.into(), self.receiver_identifier()],
-            self.encrypt(
-                &utxo_notification_payload.utxo(),
-                utxo_notification_payload.sender_randomness(),
-            )
-            .as_slice(),
+            self.encrypt(&utxo_notification_payload).as_slice(),
         ]
         .concat();
 
         PublicAnnouncement::new(ciphertext)
     }
 }
+
+/// decrypt a ciphertext into utxo secrets (utxo, sender_randomness) and memo
+/// with the secret key alone, which cannot unlock the UTXOs sent to the key.
+///
+/// See [SymmetricKey::decrypt()].
+pub(super) fn decrypt(
+    secret_key: &Key<Aes256Gcm>,
+    ciphertext_bfes: &[BFieldElement],
+) -> Result<UtxoNotificationPayload, DecryptError> {
+    const NONCE_LEN: usize = 1;
+
+    // 1. separate nonce from ciphertext.
+    let (nonce_ctxt, ciphertext) = match ciphertext_bfes.len() > NONCE_LEN {
+        true => ciphertext_bfes.split_at(NONCE_LEN),
+        false => return Err(DecryptError::MissingNonce),
+    };
+
+    // 2. generate Nonce and cyphertext_bytes
+    let nonce_as_bytes = [&nonce_ctxt[0].value().to_be_bytes(), [0u8; 4].as_slice()].concat();
+    let nonce = Nonce::from_slice(&nonce_as_bytes); // almost 64 bits; unique per message
+    let ciphertext_bytes = common::bfes_to_bytes(ciphertext)?;
+
+    // 3. decypt ciphertext to plaintext
+    let cipher = Aes256Gcm::new(secret_key);
+    let plaintext = cipher.decrypt(nonce, ciphertext_bytes.as_ref())?;
+
+    // 4. deserialize plaintext into (utxo, sender_randomness, memo)
+    Ok(UtxoNotificationPayload::from_plaintext(&plaintext)?)
+}
@@ -0,0 +1,158 @@
+//! Export of wallet contents for third-party audit tooling.
+//!
+//! The export lists every monitored UTXO along with the data needed to
+//! locate it on chain and to re-derive the key that controls it. It is
+//! serialized as JSON with the following schema, version
+//! [`AUDIT_EXPORT_SCHEMA_VERSION`]:
+//!
+//! ```text
+//! {
+//!   "schema_version": 1,
+//!   "network": "main",                  // network name, as on the command line
+//!   "tip": "<hex>",                     // digest of the tip the export was made at
+//!   "utxos": [
+//!     {
+//!       "utxo_digest": "<hex>",         // Tip5 hash of the UTXO
+//!       "amount": "<decimal>",          // native currency amount
+//!       "key_type": "Generation",       // null if no wallet key unlocks the UTXO
+//!       "derivation_index": 0,          // index of the key for its key type, or null
+//!       "receiver_identifier": 123,     // of that key, as a u64, or null
+//!       "aocl_leaf_index": 456,         // null if no membership proof is known
+//!       "confirmation_block": "<hex>",  // null if unconfirmed
+//!       "confirmation_height": 7,       // null if unconfirmed
+//!       "spent_in_block": "<hex>",      // null if unspent
+//!       "abandoned": false              // confirmed on a fork that was abandoned
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Digests are encoded with [`Digest::to_hex`]. Fields may be added in later
+//! versions of the same schema; removing or changing a field bumps the
+//! version.
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use twenty_first::math::tip5::Digest;
+use twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
+
+use super::address::KeyType;
+use super::address::ViewingKey;
+use super::monitored_utxo::MonitoredUtxo;
+use crate::config_models::network::Network;
+use crate::models::blockchain::shared::Hash;
+use crate::prelude::twenty_first;
+
+pub const AUDIT_EXPORT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AuditedUtxo {
+    pub utxo_digest: String,
+    pub amount: String,
+    pub key_type: Option<KeyType>,
+    pub derivation_index: Option<u64>,
+    pub receiver_identifier: Option<u64>,
+    pub aocl_leaf_index: Option<u64>,
+    pub confirmation_block: Option<String>,
+    pub confirmation_height: Option<u64>,
+    pub spent_in_block: Option<String>,
+    pub abandoned: bool,
+}
+
+impl AuditedUtxo {
+    /// Describe a monitored UTXO. `known_keys` lists the wallet's keys along
+    /// with their type and derivation index.
+    pub(crate) fn new(mutxo: &MonitoredUtxo, known_keys: &[(KeyType, u64, ViewingKey)]) -> Self {
+        let key = known_keys
+            .iter()
+            .find(|(_, _, key)| key.address().lock_script().hash() == mutxo.utxo.lock_script_hash);
+
+        Self {
+            utxo_digest: Hash::hash(&mutxo.utxo).to_hex(),
+            amount: mutxo.utxo.get_native_currency_amount().to_string(),
+            key_type: key.map(|(key_type, _, _)| key_type.clone()),
+            derivation_index: key.map(|(_, index, _)| *index),
+            receiver_identifier: key.map(|(_, _, key)| key.address().receiver_identifier().value()),
+            aocl_leaf_index: mutxo
+                .get_latest_membership_proof_entry()
+                .map(|(_, mp)| mp.aocl_leaf_index),
+            confirmation_block: mutxo
+                .confirmed_in_block
+                .map(|(digest, _, _)| digest.to_hex()),
+            confirmation_height: mutxo.confirmed_in_block.map(|(_, _, height)| height.into()),
+            spent_in_block: mutxo.spent_in_block.map(|(digest, _, _)| digest.to_hex()),
+            abandoned: mutxo.abandoned_at.is_some(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletAuditExport {
+    pub schema_version: u32,
+    pub network: String,
+    pub tip: String,
+    pub utxos: Vec<AuditedUtxo>,
+}
+
+impl WalletAuditExport {
+    pub(crate) fn new(network: Network, tip: Digest, utxos: Vec<AuditedUtxo>) -> Self {
+        Self {
+            schema_version: AUDIT_EXPORT_SCHEMA_VERSION,
+            network: network.to_string(),
+            tip: tip.to_hex(),
+            utxos,
+        }
+    }
+}
+
+#[cfg(test)]
+mod audit_export_tests {
+    use rand::random;
+
+    use super::*;
+    use crate::models::blockchain::transaction::utxo::Utxo;
+    use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+    use crate::models::state::wallet::address::SpendingKey;
+    use crate::models::state::wallet::WalletSecret;
+
+    #[test]
+    fn utxo_is_attributed_to_the_key_that_unlocks_it() {
+        let wallet_secret = WalletSecret::new_random();
+        let known_keys = vec![
+            (
+                KeyType::Generation,
+                0,
+                SpendingKey::from(wallet_secret.nth_generation_spending_key(0)).into(),
+            ),
+            (
+                KeyType::Symmetric,
+                0,
+                SpendingKey::from(wallet_secret.nth_symmetric_key(0)).into(),
+            ),
+        ];
+        let (_, _, symmetric_key): &(KeyType, u64, ViewingKey) = &known_keys[1];
+        let utxo =
+            Utxo::new_native_currency(symmetric_key.address().lock_script(), NeptuneCoins::new(3));
+        let mut mutxo = MonitoredUtxo::new(utxo.clone(), 1);
+        let block_digest: Digest = random();
+        mutxo.confirmed_in_block = Some((block_digest, Default::default(), 4u64.into()));
+
+        let audited = AuditedUtxo::new(&mutxo, &known_keys);
+        assert_eq!(Some(KeyType::Symmetric), audited.key_type);
+        assert_eq!(Some(0), audited.derivation_index);
+        assert_eq!(
+            Some(symmetric_key.address().receiver_identifier().value()),
+            audited.receiver_identifier
+        );
+        assert_eq!(Some(block_digest.to_hex()), audited.confirmation_block);
+        assert_eq!(Some(4), audited.confirmation_height);
+        assert_eq!(Hash::hash(&utxo).to_hex(), audited.utxo_digest);
+        assert!(audited.aocl_leaf_index.is_none());
+        assert!(audited.spent_in_block.is_none());
+
+        let export = WalletAuditExport::new(Network::Main, block_digest, vec![audited]);
+        let json = serde_json::to_string(&export).unwrap();
+        assert_eq!(export, serde_json::from_str(&json).unwrap());
+    }
+}
@@ -0,0 +1,307 @@
+//! Policies for selecting the UTXOs that fund a transaction.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use itertools::Itertools;
+use num_traits::Zero;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use twenty_first::math::digest::Digest;
+
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use crate::prelude::twenty_first;
+
+/// Maximum number of inclusion/exclusion steps of the branch-and-bound
+/// search, after which it gives up on finding an exact match.
+const MAX_BRANCH_AND_BOUND_STEPS: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum CoinSelectionPolicy {
+    /// Spend UTXOs in the order they were received.
+    #[default]
+    OldestFirst,
+
+    /// Spend as few UTXOs as possible.
+    LargestFirst,
+
+    /// Consolidate small UTXOs.
+    SmallestFirst,
+
+    /// Search for UTXOs adding up to exactly the amount spent, such that no
+    /// change output is needed. Falls back to largest-first.
+    BranchAndBound,
+
+    /// Spend UTXOs received by a single address if possible, such that the
+    /// transaction does not link this wallet's addresses. Falls back to
+    /// largest-first.
+    PrivacyPreferring,
+}
+
+impl Display for CoinSelectionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::OldestFirst => "oldest-first",
+            Self::LargestFirst => "largest-first",
+            Self::SmallestFirst => "smallest-first",
+            Self::BranchAndBound => "branch-and-bound",
+            Self::PrivacyPreferring => "privacy-preferring",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for CoinSelectionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "oldest-first" => Ok(Self::OldestFirst),
+            "largest-first" => Ok(Self::LargestFirst),
+            "smallest-first" => Ok(Self::SmallestFirst),
+            "branch-and-bound" => Ok(Self::BranchAndBound),
+            "privacy-preferring" => Ok(Self::PrivacyPreferring),
+            _ => Err(format!(
+                "unknown coin selection policy `{s}`; expected one of `oldest-first`, \
+                `largest-first`, `smallest-first`, `branch-and-bound`, `privacy-preferring`"
+            )),
+        }
+    }
+}
+
+/// A spendable UTXO, as seen by coin selection.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CoinCandidate {
+    pub amount: NeptuneCoins,
+
+    /// Hash of the lock script, which identifies the receiving address
+    pub lock_script_hash: Digest,
+}
+
+impl CoinSelectionPolicy {
+    /// Indices of the candidates to spend for a total of at least `target`,
+    /// in the order to spend them. `None` if all candidates together do not
+    /// suffice.
+    pub(crate) fn select(
+        &self,
+        candidates: &[CoinCandidate],
+        target: NeptuneCoins,
+    ) -> Option<Vec<usize>> {
+        match self {
+            Self::OldestFirst => accumulate(candidates, 0..candidates.len(), target),
+            Self::LargestFirst => accumulate(candidates, largest_first(candidates), target),
+            Self::SmallestFirst => accumulate(
+                candidates,
+                largest_first(candidates).into_iter().rev(),
+                target,
+            ),
+            Self::BranchAndBound => exact_match(candidates, target)
+                .or_else(|| Self::LargestFirst.select(candidates, target)),
+            Self::PrivacyPreferring => single_address(candidates, target)
+                .or_else(|| Self::LargestFirst.select(candidates, target)),
+        }
+    }
+}
+
+fn largest_first(candidates: &[CoinCandidate]) -> Vec<usize> {
+    (0..candidates.len())
+        .sorted_by_key(|&i| std::cmp::Reverse(candidates[i].amount))
+        .collect()
+}
+
+/// Take candidates in the given order until their total reaches `target`.
+fn accumulate(
+    candidates: &[CoinCandidate],
+    order: impl IntoIterator<Item = usize>,
+    target: NeptuneCoins,
+) -> Option<Vec<usize>> {
+    let mut selected = vec![];
+    let mut total = NeptuneCoins::zero();
+    for i in order {
+        if total >= target {
+            break;
+        }
+        total = total + candidates[i].amount;
+        selected.push(i);
+    }
+
+    (total >= target).then_some(selected)
+}
+
+/// Depth-first search for a subset of the candidates adding up to exactly
+/// `target`, trying larger candidates first.
+fn exact_match(candidates: &[CoinCandidate], target: NeptuneCoins) -> Option<Vec<usize>> {
+    let order = largest_first(candidates);
+    let mut suffix_sums = vec![NeptuneCoins::zero(); order.len() + 1];
+    for (position, &i) in order.iter().enumerate().rev() {
+        suffix_sums[position] = suffix_sums[position + 1] + candidates[i].amount;
+    }
+
+    let mut steps = 0;
+    let mut selected = vec![];
+    search(
+        candidates,
+        &order,
+        &suffix_sums,
+        target,
+        0,
+        NeptuneCoins::zero(),
+        &mut selected,
+        &mut steps,
+    )
+    .then_some(selected)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn search(
+    candidates: &[CoinCandidate],
+    order: &[usize],
+    suffix_sums: &[NeptuneCoins],
+    target: NeptuneCoins,
+    position: usize,
+    total: NeptuneCoins,
+    selected: &mut Vec<usize>,
+    steps: &mut usize,
+) -> bool {
+    if total == target {
+        return true;
+    }
+    *steps += 1;
+    if position == order.len()
+        || total + suffix_sums[position] < target
+        || *steps > MAX_BRANCH_AND_BOUND_STEPS
+    {
+        return false;
+    }
+
+    let i = order[position];
+    let with_candidate = total + candidates[i].amount;
+    if with_candidate <= target {
+        selected.push(i);
+        if search(
+            candidates,
+            order,
+            suffix_sums,
+            target,
+            position + 1,
+            with_candidate,
+            selected,
+            steps,
+        ) {
+            return true;
+        }
+        selected.pop();
+    }
+
+    search(
+        candidates,
+        order,
+        suffix_sums,
+        target,
+        position + 1,
+        total,
+        selected,
+        steps,
+    )
+}
+
+/// The fewest candidates received by one address that add up to `target`.
+fn single_address(candidates: &[CoinCandidate], target: NeptuneCoins) -> Option<Vec<usize>> {
+    let mut by_address: HashMap<Digest, Vec<usize>> = HashMap::new();
+    for i in largest_first(candidates) {
+        by_address
+            .entry(candidates[i].lock_script_hash)
+            .or_default()
+            .push(i);
+    }
+
+    by_address
+        .into_values()
+        .filter_map(|indices| accumulate(candidates, indices, target))
+        .min_by_key(|selected| selected.len())
+}
+
+#[cfg(test)]
+mod coin_selection_tests {
+    use rand::random;
+
+    use super::*;
+
+    fn candidates(amounts: &[u32], lock_script_hashes: &[Digest]) -> Vec<CoinCandidate> {
+        amounts
+            .iter()
+            .zip(lock_script_hashes.iter().cycle())
+            .map(|(&amount, &lock_script_hash)| CoinCandidate {
+                amount: NeptuneCoins::new(amount),
+                lock_script_hash,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn policies_select_expected_candidates() {
+        let candidates = candidates(&[5, 1, 8, 3, 4], &[random()]);
+        let target = NeptuneCoins::new(7);
+
+        assert_eq!(
+            Some(vec![0, 1, 2]),
+            CoinSelectionPolicy::OldestFirst.select(&candidates, target)
+        );
+        assert_eq!(
+            Some(vec![2]),
+            CoinSelectionPolicy::LargestFirst.select(&candidates, target)
+        );
+        assert_eq!(
+            Some(vec![1, 3, 4]),
+            CoinSelectionPolicy::SmallestFirst.select(&candidates, target)
+        );
+        assert_eq!(
+            Some(vec![0, 1]),
+            CoinSelectionPolicy::BranchAndBound
+                .select(&candidates, NeptuneCoins::new(6))
+                .map(|selected| selected.into_iter().sorted().collect())
+        );
+
+        for policy in [
+            CoinSelectionPolicy::OldestFirst,
+            CoinSelectionPolicy::LargestFirst,
+            CoinSelectionPolicy::SmallestFirst,
+            CoinSelectionPolicy::BranchAndBound,
+            CoinSelectionPolicy::PrivacyPreferring,
+        ] {
+            assert_eq!(None, policy.select(&candidates, NeptuneCoins::new(22)));
+            assert_eq!(
+                Some(vec![]),
+                policy.select(&candidates, NeptuneCoins::zero())
+            );
+            assert_eq!(policy, policy.to_string().parse().unwrap());
+        }
+    }
+
+    #[test]
+    fn branch_and_bound_falls_back_to_largest_first() {
+        let candidates = candidates(&[4, 4, 4], &[random()]);
+        assert_eq!(
+            Some(vec![0, 1]),
+            CoinSelectionPolicy::BranchAndBound.select(&candidates, NeptuneCoins::new(6))
+        );
+    }
+
+    #[test]
+    fn privacy_preferring_spends_from_one_address() {
+        let (a, b) = (random(), random());
+        let candidates = candidates(&[6, 2, 3, 2, 1], &[a, b]);
+
+        // address `a` holds 6 + 3 + 1, address `b` holds 2 + 2
+        assert_eq!(
+            Some(vec![0, 2]),
+            CoinSelectionPolicy::PrivacyPreferring.select(&candidates, NeptuneCoins::new(8))
+        );
+        assert_eq!(
+            Some(vec![0, 2, 1]),
+            CoinSelectionPolicy::PrivacyPreferring.select(&candidates, NeptuneCoins::new(11))
+        );
+    }
+}
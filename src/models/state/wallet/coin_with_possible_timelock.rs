@@ -2,6 +2,7 @@ use std::fmt::Display;
 
 use itertools::Itertools;
 use num_traits::Zero;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -10,7 +11,7 @@ use crate::models::proof_abstractions::timestamp::Timestamp;
 
 /// An amount of Neptune coins, with confirmation timestamp and (if time-locked) its
 /// release date. For reporting purposes.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CoinWithPossibleTimeLock {
     pub amount: NeptuneCoins,
     pub confirmed: Timestamp,
@@ -0,0 +1,243 @@
+//! Wallet secret encrypted at rest, and the in-memory lock of a node running
+//! such a wallet.
+//!
+//! The encrypted secret is the JSON of the [WalletSecret], encrypted with
+//! AES-256-GCM under a key derived from a passphrase with Argon2id. It is
+//! created from a plaintext wallet with `neptune-cli encrypt-wallet` and used
+//! by starting neptune-core with `--encrypted-wallet`.
+//!
+//! The node decrypts the secret once at startup, derives the keys it needs to
+//! recognize incoming UTXOs, and locks the wallet: the secret seed is zeroized
+//! in memory and the derived spending keys are dropped. Only their viewing
+//! keys stay, which recognize incoming UTXOs but cannot unlock them. While
+//! locked, the wallet can receive but cannot send, mine, or derive new keys.
+//! The `wallet_unlock` RPC decrypts the secret again and derives the spending
+//! keys, for a limited time if requested, and `wallet_lock` locks it.
+//!
+//! Note that the files of incoming and outgoing randomness are not encrypted.
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+use aead::Aead;
+use aead::KeyInit;
+use aes_gcm::Aes256Gcm;
+use aes_gcm::Nonce;
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::rand_core::RngCore;
+use argon2::Argon2;
+use num_traits::Zero;
+use serde::Deserialize;
+use serde::Serialize;
+use zeroize::Zeroizing;
+
+use super::SecretKeyMaterial;
+use super::WalletSecret;
+use crate::prelude::twenty_first::math::x_field_element::XFieldElement;
+
+pub const ENCRYPTED_WALLET_SECRET_FILE_NAME: &str = "wallet.encrypted.json";
+
+const ENCRYPTED_WALLET_SECRET_VERSION: u8 = 0;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptedWalletSecret {
+    version: u8,
+
+    /// Salt of the Argon2id key derivation
+    salt: Vec<u8>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Zeroizing<[u8; 32]>> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key[..])
+        .map_err(|e| anyhow!("could not derive key from wallet passphrase: {e}"))?;
+    Ok(key)
+}
+
+fn cipher(key: &[u8; 32]) -> Result<Aes256Gcm> {
+    Aes256Gcm::new_from_slice(key).map_err(|e| anyhow!("invalid wallet encryption key: {e}"))
+}
+
+impl EncryptedWalletSecret {
+    pub fn encrypt(wallet_secret: &WalletSecret, passphrase: &str) -> Result<Self> {
+        let mut salt = vec![0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let mut nonce = vec![0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+
+        let key = derive_key(passphrase, &salt)?;
+        let plaintext = Zeroizing::new(serde_json::to_vec(wallet_secret)?);
+        let ciphertext = cipher(&key)?
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| anyhow!("could not encrypt wallet secret"))?;
+
+        Ok(Self {
+            version: ENCRYPTED_WALLET_SECRET_VERSION,
+            salt,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    pub fn decrypt(&self, passphrase: &str) -> Result<WalletSecret> {
+        if self.version != ENCRYPTED_WALLET_SECRET_VERSION {
+            bail!("unknown encrypted wallet version {}", self.version);
+        }
+        if self.nonce.len() != NONCE_LEN {
+            bail!("invalid nonce in encrypted wallet");
+        }
+
+        let key = derive_key(passphrase, &self.salt)?;
+        let plaintext = cipher(&key)?
+            .decrypt(Nonce::from_slice(&self.nonce), self.ciphertext.as_slice())
+            .map(Zeroizing::new)
+            .map_err(|_| anyhow!("wrong wallet passphrase"))?;
+        serde_json::from_slice(&plaintext).context("could not decode decrypted wallet secret")
+    }
+
+    pub fn file_path(wallet_directory_path: &Path) -> PathBuf {
+        wallet_directory_path.join(ENCRYPTED_WALLET_SECRET_FILE_NAME)
+    }
+
+    pub fn read_from_wallet_dir(wallet_directory_path: &Path) -> Result<Self> {
+        let path = Self::file_path(wallet_directory_path);
+        let json = fs::read_to_string(&path).with_context(|| {
+            format!(
+                "could not read {}; create it with `neptune-cli encrypt-wallet`",
+                path.display()
+            )
+        })?;
+        serde_json::from_str(&json).with_context(|| format!("could not parse {}", path.display()))
+    }
+
+    /// Write the encrypted secret, replacing any previous one.
+    pub fn store(&self, wallet_directory_path: &Path) -> Result<()> {
+        let path = Self::file_path(wallet_directory_path);
+        let mut options = fs::OpenOptions::new();
+        options.create(true).truncate(true).write(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::prelude::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+            .open(&path)
+            .with_context(|| format!("could not create {}", path.display()))?;
+        fs::write(&path, serde_json::to_string(self)?)
+            .with_context(|| format!("could not write {}", path.display()))
+    }
+}
+
+/// Lock state of a wallet whose secret is encrypted at rest.
+#[derive(Debug, Clone)]
+pub(crate) struct WalletEncryption {
+    encrypted: EncryptedWalletSecret,
+    locked: bool,
+
+    /// When an unlocked wallet locks itself again, if ever
+    relock_at: Option<Instant>,
+}
+
+impl WalletEncryption {
+    /// The lock state of a wallet that was just decrypted.
+    pub(crate) fn unlocked(encrypted: EncryptedWalletSecret) -> Self {
+        Self {
+            encrypted,
+            locked: false,
+            relock_at: None,
+        }
+    }
+
+    pub(crate) fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Zeroize the wallet secret.
+    pub(crate) fn lock(&mut self, wallet_secret: &mut WalletSecret) {
+        // the replaced secret is zeroized on drop
+        *wallet_secret = WalletSecret::new(SecretKeyMaterial(XFieldElement::zero()));
+        self.locked = true;
+        self.relock_at = None;
+    }
+
+    /// Decrypt the wallet secret, until `duration` has passed if given.
+    pub(crate) fn unlock(
+        &mut self,
+        passphrase: &str,
+        duration: Option<Duration>,
+    ) -> Result<WalletSecret> {
+        let wallet_secret = self.encrypted.decrypt(passphrase)?;
+        self.locked = false;
+        self.relock_at = duration.map(|duration| Instant::now() + duration);
+        Ok(wallet_secret)
+    }
+
+    /// Whether an unlocked wallet should be locked again at `now`.
+    pub(crate) fn relock_due(&self, now: Instant) -> bool {
+        !self.locked && self.relock_at.is_some_and(|relock_at| relock_at <= now)
+    }
+}
+
+#[cfg(test)]
+mod encrypted_secret_tests {
+    use super::*;
+
+    #[test]
+    fn only_the_passphrase_decrypts_after_round_trip() {
+        let wallet_dir =
+            std::env::temp_dir().join(format!("encrypted-wallet-{}", rand::random::<u64>()));
+        fs::create_dir_all(&wallet_dir).unwrap();
+        assert!(EncryptedWalletSecret::read_from_wallet_dir(&wallet_dir).is_err());
+
+        let wallet_secret = WalletSecret::new_random();
+        EncryptedWalletSecret::encrypt(&wallet_secret, "correct horse")
+            .unwrap()
+            .store(&wallet_dir)
+            .unwrap();
+        let encrypted = EncryptedWalletSecret::read_from_wallet_dir(&wallet_dir).unwrap();
+
+        assert_eq!(wallet_secret, encrypted.decrypt("correct horse").unwrap());
+        assert!(encrypted.decrypt("battery staple").is_err());
+
+        let mut tampered = encrypted.clone();
+        tampered.ciphertext[0] ^= 1;
+        assert!(tampered.decrypt("correct horse").is_err());
+
+        fs::remove_dir_all(wallet_dir).unwrap();
+    }
+
+    #[test]
+    fn unlocking_expires() {
+        let wallet_secret = WalletSecret::new_random();
+        let encrypted = EncryptedWalletSecret::encrypt(&wallet_secret, "pw").unwrap();
+        let mut encryption = WalletEncryption::unlocked(encrypted);
+
+        let mut in_memory = wallet_secret.clone();
+        encryption.lock(&mut in_memory);
+        assert!(encryption.is_locked());
+        assert_ne!(wallet_secret, in_memory);
+
+        assert!(encryption.unlock("wrong", None).is_err());
+        assert!(encryption.is_locked());
+
+        let unlocked = encryption
+            .unlock("pw", Some(Duration::from_secs(60)))
+            .unwrap();
+        assert_eq!(wallet_secret, unlocked);
+        assert!(!encryption.is_locked());
+        assert!(!encryption.relock_due(Instant::now()));
+        assert!(encryption.relock_due(Instant::now() + Duration::from_secs(61)));
+    }
+}
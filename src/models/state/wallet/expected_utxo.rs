@@ -86,4 +86,7 @@ pub enum UtxoNotifier {
     Cli,
     Myself,
     Premine,
+
+    /// Imported from a bundle signed by a payment processor
+    Imported,
 }
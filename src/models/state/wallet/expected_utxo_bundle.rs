@@ -0,0 +1,300 @@
+//! Bundles of expected UTXOs, signed by a payment processor.
+//!
+//! A payment processor, e.g. a custodian migrating its users' funds to their
+//! own wallets, can pay many users without announcing each UTXO on chain. It
+//! hands each user a bundle of the data needed to claim the UTXOs paid to the
+//! user's addresses, signed with the processor's ed25519 key. Importing the
+//! bundle adds its UTXOs to the wallet as [ExpectedUtxo]s, such that they are
+//! claimed when confirmed.
+//!
+//! Like other expected UTXOs, bundled UTXOs are only recognized in blocks
+//! applied after the import, so a bundle must be imported before its UTXOs
+//! are confirmed.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use ed25519_dalek::Signature;
+use ed25519_dalek::Signer;
+use ed25519_dalek::SigningKey;
+use ed25519_dalek::Verifier;
+use ed25519_dalek::VerifyingKey;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use twenty_first::math::digest::Digest;
+
+use super::address::ViewingKey;
+use super::expected_utxo::ExpectedUtxo;
+use super::expected_utxo::UtxoNotifier;
+use crate::config_models::network::Network;
+use crate::models::blockchain::transaction::utxo::Utxo;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use crate::prelude::twenty_first;
+use crate::util_types::json_schema::DigestSchema;
+use crate::util_types::mutator_set::addition_record::AdditionRecord;
+
+/// Domain separator for bundle signatures.
+const SIGNATURE_DOMAIN: &[u8] = b"neptune-expected-utxo-bundle";
+
+/// A native-currency UTXO paid to the holder of the preimage of
+/// `receiver_digest`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct BundledUtxo {
+    pub amount: NeptuneCoins,
+    #[schemars(with = "DigestSchema")]
+    pub sender_randomness: Digest,
+
+    /// The privacy digest of the receiving address
+    #[schemars(with = "DigestSchema")]
+    pub receiver_digest: Digest,
+
+    pub addition_record: AdditionRecord,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ExpectedUtxoBundle {
+    pub network: Network,
+    pub utxos: Vec<BundledUtxo>,
+    pub signer: [u8; 32],
+    pub signature: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, Serialize, Deserialize, JsonSchema)]
+pub enum ExpectedUtxoBundleError {
+    #[error("bundle is for network {0}")]
+    WrongNetwork(Network),
+
+    #[error("bundle is not signed by the trusted signer")]
+    UntrustedSigner,
+
+    #[error("invalid bundle signature")]
+    InvalidSignature,
+
+    #[error("UTXO {0} of bundle has a negative amount")]
+    NegativeAmount(usize),
+
+    #[error("UTXO {0} of bundle is not paid to this wallet")]
+    UnknownReceiver(usize),
+
+    #[error("UTXO {0} of bundle does not match its addition record")]
+    AdditionRecordMismatch(usize),
+}
+
+/// The outcome of importing a bundle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ExpectedUtxoImport {
+    /// Number of UTXOs added to the wallet
+    pub imported: usize,
+
+    /// Number of UTXOs that the wallet already expected, or that occurred
+    /// more than once in the bundle
+    pub duplicates: usize,
+}
+
+impl ExpectedUtxoBundle {
+    fn message(network: Network, utxos: &[BundledUtxo]) -> Vec<u8> {
+        let payload =
+            bincode::serialize(&(network, utxos)).expect("serializing bundle must succeed");
+        [SIGNATURE_DOMAIN, &payload].concat()
+    }
+
+    /// Sign a bundle. Used by payment processors, and in tests.
+    pub fn sign(network: Network, utxos: Vec<BundledUtxo>, signing_key: &SigningKey) -> Self {
+        let signature = signing_key.sign(&Self::message(network, &utxos));
+        Self {
+            network,
+            utxos,
+            signer: signing_key.verifying_key().to_bytes(),
+            signature: signature.to_bytes().to_vec(),
+        }
+    }
+
+    /// The public key of the signer, as 64 hex characters.
+    pub fn signer_hex(&self) -> String {
+        self.signer.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// Verify that the bundle is for `network` and validly signed, by
+    /// `trusted_signer` if given as hex.
+    pub(crate) fn verify(
+        &self,
+        network: Network,
+        trusted_signer: Option<&str>,
+    ) -> Result<(), ExpectedUtxoBundleError> {
+        if self.network != network {
+            return Err(ExpectedUtxoBundleError::WrongNetwork(self.network));
+        }
+        if trusted_signer.is_some_and(|signer| !signer.eq_ignore_ascii_case(&self.signer_hex())) {
+            return Err(ExpectedUtxoBundleError::UntrustedSigner);
+        }
+
+        let key = VerifyingKey::from_bytes(&self.signer)
+            .map_err(|_| ExpectedUtxoBundleError::InvalidSignature)?;
+        let signature = Signature::from_slice(&self.signature)
+            .map_err(|_| ExpectedUtxoBundleError::InvalidSignature)?;
+        key.verify(&Self::message(self.network, &self.utxos), &signature)
+            .map_err(|_| ExpectedUtxoBundleError::InvalidSignature)
+    }
+
+    /// Match every UTXO of the bundle to one of `keys`, and check
+    /// that it is committed to by its addition record. Returns the expected
+    /// UTXOs, in the order of the bundle, or the first invalid UTXO.
+    pub(crate) fn expected_utxos(
+        &self,
+        keys: &[ViewingKey],
+    ) -> Result<Vec<ExpectedUtxo>, ExpectedUtxoBundleError> {
+        let keys_by_receiver_digest: HashMap<_, _> = keys
+            .iter()
+            .map(|key| (key.address().privacy_digest(), key))
+            .collect();
+
+        self.utxos
+            .iter()
+            .enumerate()
+            .map(|(i, bundled)| {
+                if bundled.amount.is_negative() {
+                    return Err(ExpectedUtxoBundleError::NegativeAmount(i));
+                }
+                let key = keys_by_receiver_digest
+                    .get(&bundled.receiver_digest)
+                    .ok_or(ExpectedUtxoBundleError::UnknownReceiver(i))?;
+                let utxo = Utxo::new_native_currency(key.address().lock_script(), bundled.amount);
+                let expected_utxo = ExpectedUtxo::new(
+                    utxo,
+                    bundled.sender_randomness,
+                    key.privacy_preimage(),
+                    UtxoNotifier::Imported,
+                );
+                if expected_utxo.addition_record != bundled.addition_record {
+                    return Err(ExpectedUtxoBundleError::AdditionRecordMismatch(i));
+                }
+
+                Ok(expected_utxo)
+            })
+            .collect()
+    }
+}
+
+/// Remove the expected UTXOs whose addition records are in `known`, or occur
+/// earlier in the list. Returns the remaining ones and the number removed.
+pub(crate) fn dedup_expected_utxos(
+    expected_utxos: Vec<ExpectedUtxo>,
+    known: &HashSet<AdditionRecord>,
+) -> (Vec<ExpectedUtxo>, usize) {
+    let num_expected_utxos = expected_utxos.len();
+    let mut seen = HashSet::new();
+    let new_expected_utxos: Vec<_> = expected_utxos
+        .into_iter()
+        .filter(|eu| !known.contains(&eu.addition_record) && seen.insert(eu.addition_record))
+        .collect();
+    let num_duplicates = num_expected_utxos - new_expected_utxos.len();
+
+    (new_expected_utxos, num_duplicates)
+}
+
+#[cfg(test)]
+mod expected_utxo_bundle_tests {
+    use rand::random;
+
+    use super::*;
+    use crate::models::blockchain::shared::Hash;
+    use crate::models::state::wallet::address::SpendingKey;
+    use crate::models::state::wallet::WalletSecret;
+    use crate::prelude::twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
+    use crate::util_types::mutator_set::commit;
+
+    fn random_key() -> ViewingKey {
+        SpendingKey::from(WalletSecret::new_random().nth_generation_spending_key_for_tests(0))
+            .into()
+    }
+
+    fn bundled_utxo(key: &ViewingKey, amount: NeptuneCoins) -> BundledUtxo {
+        let address = key.address();
+        let utxo = Utxo::new_native_currency(address.lock_script(), amount);
+        let sender_randomness: Digest = random();
+        BundledUtxo {
+            amount,
+            sender_randomness,
+            receiver_digest: address.privacy_digest(),
+            addition_record: commit(
+                Hash::hash(&utxo),
+                sender_randomness,
+                address.privacy_digest(),
+            ),
+        }
+    }
+
+    #[test]
+    fn bundle_is_verified_and_matched_to_keys() {
+        let network = Network::Main;
+        let signing_key = SigningKey::from_bytes(&random());
+        let key = random_key();
+        let utxos = vec![
+            bundled_utxo(&key, NeptuneCoins::new(3)),
+            bundled_utxo(&key, NeptuneCoins::new(4)),
+        ];
+        let bundle = ExpectedUtxoBundle::sign(network, utxos.clone(), &signing_key);
+
+        assert!(bundle.verify(network, None).is_ok());
+        assert!(bundle.verify(network, Some(&bundle.signer_hex())).is_ok());
+        assert_eq!(
+            Err(ExpectedUtxoBundleError::WrongNetwork(network)),
+            bundle.verify(Network::Testnet, None)
+        );
+        assert_eq!(
+            Err(ExpectedUtxoBundleError::UntrustedSigner),
+            bundle.verify(network, Some(&"00".repeat(32)))
+        );
+
+        let mut tampered = bundle.clone();
+        tampered.utxos[0].amount = NeptuneCoins::new(300);
+        assert_eq!(
+            Err(ExpectedUtxoBundleError::InvalidSignature),
+            tampered.verify(network, None)
+        );
+        assert_eq!(
+            Err(ExpectedUtxoBundleError::AdditionRecordMismatch(0)),
+            tampered.expected_utxos(std::slice::from_ref(&key))
+        );
+
+        let expected_utxos = bundle.expected_utxos(std::slice::from_ref(&key)).unwrap();
+        assert_eq!(
+            utxos.iter().map(|u| u.addition_record).collect::<Vec<_>>(),
+            expected_utxos
+                .iter()
+                .map(|eu| eu.addition_record)
+                .collect::<Vec<_>>()
+        );
+
+        let other_key = random_key();
+        assert_eq!(
+            Err(ExpectedUtxoBundleError::UnknownReceiver(0)),
+            bundle.expected_utxos(&[other_key])
+        );
+    }
+
+    #[test]
+    fn known_and_repeated_utxos_are_duplicates() {
+        let key = random_key();
+        let utxos = vec![
+            bundled_utxo(&key, NeptuneCoins::new(1)),
+            bundled_utxo(&key, NeptuneCoins::new(2)),
+        ];
+        let bundle = ExpectedUtxoBundle::sign(
+            Network::Main,
+            vec![utxos[0].clone(), utxos[1].clone(), utxos[1].clone()],
+            &SigningKey::from_bytes(&random()),
+        );
+        let expected_utxos = bundle.expected_utxos(std::slice::from_ref(&key)).unwrap();
+
+        let known = HashSet::from([utxos[0].addition_record]);
+        let (new_expected_utxos, num_duplicates) = dedup_expected_utxos(expected_utxos, &known);
+        assert_eq!(2, num_duplicates);
+        assert_eq!(1, new_expected_utxos.len());
+        assert_eq!(
+            utxos[1].addition_record,
+            new_expected_utxos[0].addition_record
+        );
+    }
+}
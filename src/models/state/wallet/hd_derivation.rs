@@ -0,0 +1,278 @@
+//! Hierarchical derivation of generation keys along `account/chain/index`
+//! paths, and account descriptors that let external tools derive the
+//! receiving addresses of an account offline.
+//!
+//! Each account has a seed derived from the wallet secret. The account seed
+//! splits into a *view seed*, from which the per-address seeds are derived,
+//! and an *unlock key*, which is shared by all addresses of the account. An
+//! [AccountDescriptor] holds the view seed and the hash of the unlock key, so
+//! it suffices to derive every receiving address of the account but not to
+//! spend from any of them.
+//!
+//! The lattice-based KEM has no public derivation, so the descriptor also
+//! reveals the decryption keys and receiver preimages of the account: whoever
+//! holds it can read the account's incoming UTXO notifications. Treat it like
+//! a viewing key.
+//!
+//! Since the addresses of an account share their spending lock, two addresses
+//! of the same account can be recognized as such. Use separate accounts for
+//! addresses that must not be linkable.
+//!
+//! Keys derived along paths are separate from the keys returned by
+//! [WalletSecret::nth_generation_spending_key](super::WalletSecret::nth_generation_spending_key),
+//! which remain the wallet's default keys.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::bail;
+use anyhow::Result;
+use bech32::FromBase32;
+use bech32::ToBase32;
+use bech32::Variant;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use twenty_first::math::b_field_element::BFieldElement;
+use twenty_first::math::digest::Digest;
+use twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
+
+use super::address::generation_address::GenerationReceivingAddress;
+use super::address::generation_address::GenerationSpendingKey;
+use crate::config_models::network::Network;
+use crate::prelude::twenty_first;
+use crate::util_types::json_schema::DigestSchema;
+use crate::Hash;
+
+pub(super) const HD_DERIVATION_FLAG: BFieldElement = BFieldElement::new(0x48443031);
+
+/// Number of indices per chain of an exported account for which the wallet
+/// recognizes incoming UTXOs.
+pub const ACCOUNT_KEY_LOOKAHEAD: u16 = 100;
+
+/// The chain of a derivation path: addresses handed out to payers, or
+/// addresses the wallet pays change to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum DerivationChain {
+    Receiving,
+    Change,
+}
+
+impl DerivationChain {
+    pub fn all() -> [Self; 2] {
+        [Self::Receiving, Self::Change]
+    }
+
+    fn index(&self) -> u32 {
+        match self {
+            Self::Receiving => 0,
+            Self::Change => 1,
+        }
+    }
+}
+
+/// Location of a generation key in the hierarchy: `m/<account>/<chain>/<index>`
+/// where chain is 0 for receiving and 1 for change addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct DerivationPath {
+    pub account: u32,
+    pub chain: DerivationChain,
+
+    /// Limited to 2^16 like the wallet's default keys, such that all indices
+    /// can be scanned.
+    pub index: u16,
+}
+
+impl Display for DerivationPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "m/{}/{}/{}",
+            self.account,
+            self.chain.index(),
+            self.index
+        )
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<&str> = s.trim().split('/').collect();
+        let [root, account, chain, index] = parts[..] else {
+            bail!("derivation path must have the form m/<account>/<chain>/<index>");
+        };
+        if root != "m" {
+            bail!("derivation path must start at the root m");
+        }
+
+        let chain = match chain {
+            "0" => DerivationChain::Receiving,
+            "1" => DerivationChain::Change,
+            _ => bail!("chain must be 0 (receiving) or 1 (change), not {chain}"),
+        };
+
+        Ok(Self {
+            account: account.parse()?,
+            chain,
+            index: index.parse()?,
+        })
+    }
+}
+
+fn view_seed(account_seed: Digest) -> Digest {
+    Hash::hash_varlen(&[account_seed.values().to_vec(), vec![BFieldElement::new(0)]].concat())
+}
+
+fn unlock_key(account_seed: Digest) -> Digest {
+    Hash::hash_varlen(&[account_seed.values().to_vec(), vec![BFieldElement::new(1)]].concat())
+}
+
+fn address_seed(view_seed: Digest, chain: DerivationChain, index: u16) -> Digest {
+    Hash::hash_varlen(
+        &[
+            view_seed.values().to_vec(),
+            vec![
+                BFieldElement::new(chain.index().into()),
+                BFieldElement::new(index.into()),
+            ],
+        ]
+        .concat(),
+    )
+}
+
+/// The spending key at the given path, under the seed of the path's account.
+pub(super) fn spending_key(account_seed: Digest, path: DerivationPath) -> GenerationSpendingKey {
+    let seed = address_seed(view_seed(account_seed), path.chain, path.index);
+    GenerationSpendingKey::derive_from_seed_and_unlock_key(seed, unlock_key(account_seed))
+}
+
+/// Everything needed to derive the receiving addresses of one account, and
+/// nothing more. See the [module documentation](self) for what it reveals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AccountDescriptor {
+    pub network: Network,
+    pub account: u32,
+    #[schemars(with = "DigestSchema")]
+    view_seed: Digest,
+    #[schemars(with = "DigestSchema")]
+    spending_lock: Digest,
+}
+
+impl AccountDescriptor {
+    pub(super) fn from_account_seed(network: Network, account: u32, account_seed: Digest) -> Self {
+        Self {
+            network,
+            account,
+            view_seed: view_seed(account_seed),
+            spending_lock: unlock_key(account_seed).hash(),
+        }
+    }
+
+    /// The address at the given chain and index of this account.
+    pub fn derive_address(&self, chain: DerivationChain, index: u16) -> GenerationReceivingAddress {
+        let seed = address_seed(self.view_seed, chain, index);
+        GenerationReceivingAddress::derive_from_seed_and_spending_lock(seed, self.spending_lock)
+    }
+
+    /// returns human readable prefix (hrp) of a descriptor.
+    fn get_hrp(network: Network) -> String {
+        // NACCT: Neptune account descriptor
+        let mut hrp = "nacct".to_string();
+        hrp.push(network.address_network_byte());
+        hrp
+    }
+
+    pub fn to_bech32m(&self) -> Result<String> {
+        let hrp = Self::get_hrp(self.network);
+        let payload = bincode::serialize(self)?;
+        match bech32::encode(&hrp, payload.to_base32(), Variant::Bech32m) {
+            Ok(enc) => Ok(enc),
+            Err(e) => bail!("Could not encode account descriptor as bech32m because error: {e}"),
+        }
+    }
+
+    pub fn from_bech32m(encoded: &str) -> Result<Self> {
+        let (hrp, data, variant) = bech32::decode(encoded.trim())?;
+
+        if variant != Variant::Bech32m {
+            bail!("Can only decode bech32m account descriptors.");
+        }
+
+        let payload = Vec::<u8>::from_base32(&data)?;
+        let descriptor: Self = match bincode::deserialize(&payload) {
+            Ok(descriptor) => descriptor,
+            Err(e) => bail!("Could not decode bech32m account descriptor because of error: {e}"),
+        };
+
+        if hrp != Self::get_hrp(descriptor.network) {
+            bail!("Could not decode bech32m account descriptor because of invalid prefix");
+        }
+
+        Ok(descriptor)
+    }
+}
+
+#[cfg(test)]
+mod hd_derivation_tests {
+    use rand::random;
+
+    use super::*;
+    use crate::models::state::wallet::WalletSecret;
+
+    #[test]
+    fn derivation_path_round_trips() {
+        let path = DerivationPath {
+            account: 7,
+            chain: DerivationChain::Change,
+            index: 42,
+        };
+        assert_eq!("m/7/1/42", path.to_string());
+        assert_eq!(path, "m/7/1/42".parse().unwrap());
+
+        for invalid in ["7/1/42", "m/7/2/42", "m/7/0", "m/7/0/65536", "m/-1/0/0"] {
+            assert!(invalid.parse::<DerivationPath>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn descriptor_derives_the_wallets_addresses() {
+        let wallet_secret = WalletSecret::new_random();
+        let network = Network::RegTest;
+        let account = random::<u32>() >> 1;
+        let descriptor = wallet_secret.account_descriptor(network, account);
+
+        let encoded = descriptor.to_bech32m().unwrap();
+        assert_eq!(
+            descriptor,
+            AccountDescriptor::from_bech32m(&encoded).unwrap()
+        );
+
+        for chain in DerivationChain::all() {
+            for index in [0, 1, u16::MAX] {
+                let path = DerivationPath {
+                    account,
+                    chain,
+                    index,
+                };
+                let key = wallet_secret.generation_spending_key_at(path);
+                assert_eq!(key.to_address(), descriptor.derive_address(chain, index));
+            }
+        }
+
+        let receiving = descriptor.derive_address(DerivationChain::Receiving, 0);
+        let change = descriptor.derive_address(DerivationChain::Change, 0);
+        assert_ne!(receiving, change);
+
+        let other_account = wallet_secret.account_descriptor(network, account + 1);
+        assert_ne!(
+            receiving,
+            other_account.derive_address(DerivationChain::Receiving, 0)
+        );
+        assert_ne!(
+            receiving,
+            wallet_secret.nth_generation_spending_key(0).to_address()
+        );
+    }
+}
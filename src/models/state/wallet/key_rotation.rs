@@ -0,0 +1,395 @@
+//! Guided rotation of the wallet secret to a freshly generated seed.
+//!
+//! A rotation proceeds in steps, each of which can be repeated and resumed
+//! after a restart, since the progress is stored in the wallet directory:
+//!
+//!  1. *start*: a new seed is generated and stored next to the current wallet
+//!     secret. Funds are swept to the first generation address of the new
+//!     seed.
+//!  2. *sweep*: all UTXOs of the old seed that are spendable now are spent in a
+//!     single transaction to the new address. UTXOs that are still time-locked
+//!     are left alone; sweep again once their time lock has expired.
+//!  3. *finish*: once no funds remain under the old seed, the old wallet secret
+//!     is archived and the new one takes its place.
+//!
+//! The outputs of sweep transactions are registered as expected UTXOs in the
+//! wallet database, so their membership proofs are maintained by the wallet
+//! before the new seed is installed.
+//!
+//! The new seed is stored in plaintext, so wallets encrypted at rest cannot be
+//! rotated, and a wallet cannot be encrypted while a rotation is in progress.
+
+use std::fmt::Display;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use num_traits::Zero;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::address::ReceivingAddress;
+use super::address::SpendingKey;
+use super::wallet_state::WalletState;
+use super::wallet_status::WalletStatus;
+use super::WalletSecret;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::models::state::transaction_kernel_id::TransactionKernelId;
+
+pub const KEY_ROTATION_FILE_NAME: &str = "key_rotation.json";
+pub const NEXT_WALLET_SECRET_FILE_NAME: &str = "wallet.next.dat";
+
+/// A transaction that moved funds from the old seed to the new one.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct KeyRotationSweep {
+    pub txid: TransactionKernelId,
+    pub amount: NeptuneCoins,
+    pub fee: NeptuneCoins,
+    pub timestamp: Timestamp,
+}
+
+/// What the user must do next to advance a key rotation.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub enum KeyRotationStep {
+    /// Spendable funds remain under the old seed.
+    Sweep,
+
+    /// A sweep transaction, or an incoming transaction to the old seed, has not
+    /// been confirmed yet.
+    AwaitConfirmation,
+
+    /// The only funds remaining under the old seed are time-locked.
+    AwaitTimelocks,
+
+    /// No funds remain under the old seed.
+    Finish,
+}
+
+impl Display for KeyRotationStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let step = match self {
+            KeyRotationStep::Sweep => "sweep spendable funds",
+            KeyRotationStep::AwaitConfirmation => "wait for pending transactions to confirm",
+            KeyRotationStep::AwaitTimelocks => "wait for time locks to expire, then sweep",
+            KeyRotationStep::Finish => "finish the rotation",
+        };
+        write!(f, "{step}")
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct KeyRotationStatus {
+    pub next_step: KeyRotationStep,
+    pub destination: ReceivingAddress,
+
+    /// Old-seed funds that can be swept now.
+    pub sweepable: NeptuneCoins,
+
+    /// Old-seed funds spent by unconfirmed transactions, or not yet synced.
+    pub unconfirmed: NeptuneCoins,
+
+    /// Old-seed funds that cannot be swept before their time lock expires.
+    pub timelocked: NeptuneCoins,
+
+    /// Confirmed funds under the new seed.
+    pub received: NeptuneCoins,
+    pub sweeps: Vec<KeyRotationSweep>,
+}
+
+impl Display for KeyRotationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let buf = String::new()
+            + &format!("next step: {}\n", self.next_step)
+            + &format!("sweepable: {}\n", self.sweepable)
+            + &format!("unconfirmed: {}\n", self.unconfirmed)
+            + &format!("timelocked: {}\n", self.timelocked)
+            + &format!("received by new seed: {}\n", self.received)
+            + &format!("sweep transactions: {}\n", self.sweeps.len());
+
+        write!(f, "{}", buf)
+    }
+}
+
+/// Persisted progress of a key rotation.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KeyRotation {
+    pub started: Timestamp,
+    pub destination: ReceivingAddress,
+    pub sweeps: Vec<KeyRotationSweep>,
+}
+
+impl KeyRotation {
+    fn path(wallet_directory_path: &Path) -> PathBuf {
+        wallet_directory_path.join(KEY_ROTATION_FILE_NAME)
+    }
+
+    fn next_wallet_secret_path(wallet_directory_path: &Path) -> PathBuf {
+        wallet_directory_path.join(NEXT_WALLET_SECRET_FILE_NAME)
+    }
+
+    /// The key that receives swept funds.
+    fn destination_key(next_wallet_secret: &WalletSecret) -> SpendingKey {
+        next_wallet_secret.nth_generation_spending_key(0).into()
+    }
+
+    /// Read the rotation in progress, if any.
+    pub fn read(wallet_directory_path: &Path) -> Result<Option<Self>> {
+        let path = Self::path(wallet_directory_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read key rotation from {}", path.display()))?;
+        let rotation = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to decode key rotation from {}", path.display()))?;
+        Ok(Some(rotation))
+    }
+
+    /// Write progress, replacing the previous file atomically.
+    pub fn write(&self, wallet_directory_path: &Path) -> Result<()> {
+        let path = Self::path(wallet_directory_path);
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write key rotation to {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to write key rotation to {}", path.display()))
+    }
+
+    /// Start a rotation, or return the one in progress.
+    ///
+    /// If a new seed exists without a rotation file, because a previous start
+    /// was interrupted, that seed is reused rather than replaced.
+    pub fn start(wallet_directory_path: &Path, now: Timestamp) -> Result<Self> {
+        if let Some(rotation) = Self::read(wallet_directory_path)? {
+            return Ok(rotation);
+        }
+
+        let next_wallet_secret_path = Self::next_wallet_secret_path(wallet_directory_path);
+        let next_wallet_secret = if next_wallet_secret_path.exists() {
+            WalletSecret::read_from_file(&next_wallet_secret_path)?
+        } else {
+            let next_wallet_secret = WalletSecret::new_random();
+            next_wallet_secret.save_to_disk(&next_wallet_secret_path)?;
+            next_wallet_secret
+        };
+
+        let rotation = Self {
+            started: now,
+            destination: Self::destination_key(&next_wallet_secret).to_address(),
+            sweeps: vec![],
+        };
+        rotation.write(wallet_directory_path)?;
+
+        Ok(rotation)
+    }
+
+    /// The seed that the wallet is rotated to.
+    pub fn next_wallet_secret(wallet_directory_path: &Path) -> Result<WalletSecret> {
+        WalletSecret::read_from_file(&Self::next_wallet_secret_path(wallet_directory_path))
+    }
+
+    /// The spending key that receives swept funds.
+    pub fn next_spending_key(wallet_directory_path: &Path) -> Result<SpendingKey> {
+        Ok(Self::destination_key(&Self::next_wallet_secret(
+            wallet_directory_path,
+        )?))
+    }
+
+    /// Classify the wallet's funds into those still held by the old seed and
+    /// those already received by the new one.
+    pub fn status(
+        &self,
+        wallet_state: &WalletState,
+        wallet_status: &WalletStatus,
+        now: Timestamp,
+    ) -> KeyRotationStatus {
+        let destination_lock_script_hash = self.destination.lock_script().hash();
+        let mempool_spent = wallet_state.mempool_spent_utxos_iter().collect::<Vec<_>>();
+
+        let mut sweepable = NeptuneCoins::zero();
+        let mut unconfirmed = NeptuneCoins::zero();
+        let mut timelocked = NeptuneCoins::zero();
+        let mut received = NeptuneCoins::zero();
+        for (element, _) in &wallet_status.synced_unspent {
+            let utxo = &element.utxo;
+            let amount = utxo.get_native_currency_amount();
+            if utxo.lock_script_hash == destination_lock_script_hash {
+                received = received + amount;
+            } else if !wallet_state.can_unlock(utxo) {
+                continue;
+            } else if mempool_spent.contains(&utxo) {
+                unconfirmed = unconfirmed + amount;
+            } else if utxo.can_spend_at(now) {
+                sweepable = sweepable + amount;
+            } else {
+                timelocked = timelocked + amount;
+            }
+        }
+        for element in &wallet_status.unsynced_unspent {
+            if wallet_state.can_unlock(&element.utxo) {
+                unconfirmed = unconfirmed + element.utxo.get_native_currency_amount();
+            }
+        }
+
+        let next_step = if !unconfirmed.is_zero() {
+            KeyRotationStep::AwaitConfirmation
+        } else if !sweepable.is_zero() {
+            KeyRotationStep::Sweep
+        } else if !timelocked.is_zero() {
+            KeyRotationStep::AwaitTimelocks
+        } else {
+            KeyRotationStep::Finish
+        };
+
+        KeyRotationStatus {
+            next_step,
+            destination: self.destination.clone(),
+            sweepable,
+            unconfirmed,
+            timelocked,
+            received,
+            sweeps: self.sweeps.clone(),
+        }
+    }
+
+    /// Install the new seed as the wallet secret. The old secret is kept,
+    /// renamed, next to it.
+    ///
+    /// Fails unless the rotation is ready to be finished.
+    pub fn finish(
+        &self,
+        wallet_directory_path: &Path,
+        status: &KeyRotationStatus,
+        now: Timestamp,
+    ) -> Result<WalletSecret> {
+        if status.next_step != KeyRotationStep::Finish {
+            bail!(
+                "Key rotation cannot be finished yet; next step: {}",
+                status.next_step
+            );
+        }
+
+        let next_wallet_secret = Self::next_wallet_secret(wallet_directory_path)?;
+        let wallet_secret_path = WalletSecret::wallet_secret_path(wallet_directory_path);
+        let archived_path =
+            wallet_secret_path.with_extension(format!("dat.pre-rotation-{}", now.to_millis()));
+        std::fs::rename(&wallet_secret_path, &archived_path).with_context(|| {
+            format!(
+                "Failed to archive old wallet secret to {}",
+                archived_path.display()
+            )
+        })?;
+        std::fs::rename(
+            Self::next_wallet_secret_path(wallet_directory_path),
+            &wallet_secret_path,
+        )
+        .context("Failed to install new wallet secret")?;
+        std::fs::remove_file(Self::path(wallet_directory_path))
+            .context("Failed to remove key rotation file")?;
+
+        Ok(next_wallet_secret)
+    }
+}
+
+#[cfg(test)]
+mod key_rotation_tests {
+    use super::*;
+    use crate::config_models::data_directory::DataDirectory;
+    use crate::config_models::network::Network;
+    use crate::tests::shared::mock_genesis_wallet_state_with_data_dir;
+    use crate::tests::shared::unit_test_data_directory;
+
+    async fn devnet_wallet_state() -> (WalletState, DataDirectory) {
+        let network = Network::Main;
+        let data_dir = unit_test_data_directory(network).unwrap();
+        let wallet_state = mock_genesis_wallet_state_with_data_dir(
+            WalletSecret::devnet_wallet(),
+            network,
+            &data_dir,
+        )
+        .await;
+        DataDirectory::create_dir_if_not_exists(&data_dir.wallet_directory_path())
+            .await
+            .unwrap();
+        WalletSecret::devnet_wallet()
+            .save_to_disk(&WalletSecret::wallet_secret_path(
+                &data_dir.wallet_directory_path(),
+            ))
+            .unwrap();
+        (wallet_state, data_dir)
+    }
+
+    #[tokio::test]
+    async fn start_is_resumable() {
+        let (_, data_dir) = devnet_wallet_state().await;
+        let wallet_dir = data_dir.wallet_directory_path();
+        let now = Timestamp::now();
+
+        let rotation = KeyRotation::start(&wallet_dir, now).unwrap();
+        assert_eq!(
+            Some(rotation.clone()),
+            KeyRotation::read(&wallet_dir).unwrap()
+        );
+        assert_eq!(
+            rotation,
+            KeyRotation::start(&wallet_dir, now + Timestamp::hours(1)).unwrap()
+        );
+
+        // interrupted after the seed was stored, but before progress was
+        std::fs::remove_file(KeyRotation::path(&wallet_dir)).unwrap();
+        let restarted = KeyRotation::start(&wallet_dir, now).unwrap();
+        assert_eq!(rotation.destination, restarted.destination);
+    }
+
+    #[tokio::test]
+    async fn timelocked_premine_is_not_sweepable() {
+        let (wallet_state, data_dir) = devnet_wallet_state().await;
+        let wallet_dir = data_dir.wallet_directory_path();
+        let launch = Network::Main.launch_date();
+        let rotation = KeyRotation::start(&wallet_dir, launch).unwrap();
+
+        let tip_digest = wallet_state.wallet_db.get_sync_label().await;
+        let wallet_status = wallet_state.get_wallet_status_from_lock(tip_digest).await;
+        let status = rotation.status(&wallet_state, &wallet_status, launch);
+        assert!(status.sweepable.is_zero());
+        assert!(!status.timelocked.is_zero());
+        assert_eq!(KeyRotationStep::AwaitTimelocks, status.next_step);
+        assert!(rotation.finish(&wallet_dir, &status, launch).is_err());
+
+        let released = launch + Timestamp::months(12);
+        let status = rotation.status(&wallet_state, &wallet_status, released);
+        assert!(!status.sweepable.is_zero());
+        assert_eq!(KeyRotationStep::Sweep, status.next_step);
+    }
+
+    #[tokio::test]
+    async fn finish_installs_new_seed() {
+        let (_, data_dir) = devnet_wallet_state().await;
+        let wallet_dir = data_dir.wallet_directory_path();
+        let now = Timestamp::now();
+        let rotation = KeyRotation::start(&wallet_dir, now).unwrap();
+        let next_wallet_secret = KeyRotation::next_wallet_secret(&wallet_dir).unwrap();
+
+        let status = KeyRotationStatus {
+            next_step: KeyRotationStep::Finish,
+            destination: rotation.destination.clone(),
+            sweepable: NeptuneCoins::zero(),
+            unconfirmed: NeptuneCoins::zero(),
+            timelocked: NeptuneCoins::zero(),
+            received: NeptuneCoins::zero(),
+            sweeps: vec![],
+        };
+        let installed = rotation.finish(&wallet_dir, &status, now).unwrap();
+        assert_eq!(next_wallet_secret, installed);
+        assert_eq!(
+            next_wallet_secret,
+            WalletSecret::read_from_file(&WalletSecret::wallet_secret_path(&wallet_dir)).unwrap()
+        );
+        assert_eq!(None, KeyRotation::read(&wallet_dir).unwrap());
+    }
+}
@@ -0,0 +1,363 @@
+//! Storage format for the membership proofs of a monitored UTXO.
+//!
+//! A monitored UTXO keeps a membership proof for each of the most recent
+//! blocks. Successive proofs differ only in the few authentication path nodes
+//! and target chunks that were touched by a block, so storing every proof in
+//! full mostly repeats data. Here, the newest proof is stored in full and each
+//! older proof is stored as a delta against its successor: only the
+//! authentication path nodes and target chunks that differ are kept.
+//!
+//! The format is applied with `#[serde(with = ...)]` on
+//! [`MonitoredUtxo::blockhash_to_membership_proof`](super::monitored_utxo::MonitoredUtxo::blockhash_to_membership_proof),
+//! so decompression is transparent to code reading monitored UTXOs.
+
+use std::collections::VecDeque;
+
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use thiserror::Error;
+use twenty_first::math::tip5::Digest;
+use twenty_first::util_types::mmr::mmr_membership_proof::MmrMembershipProof;
+
+use crate::prelude::twenty_first;
+use crate::util_types::mutator_set::chunk::Chunk;
+use crate::util_types::mutator_set::ms_membership_proof::MsMembershipProof;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DecompressionError {
+    #[error("first stored membership proof is a delta; it must be stored in full")]
+    MissingReference,
+
+    #[error("delta refers to target chunk {0}, which is absent from its reference proof")]
+    MissingTargetChunk(u64),
+
+    #[error("delta changes authentication path node {0}, which is out of bounds")]
+    AuthPathNodeOutOfBounds(u32),
+}
+
+/// Difference between two MMR authentication paths.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct AuthPathDelta {
+    length: u32,
+
+    /// Nodes that differ from the reference path, or that lie beyond its end.
+    changed_nodes: Vec<(u32, Digest)>,
+}
+
+impl AuthPathDelta {
+    fn new(reference: &MmrMembershipProof, proof: &MmrMembershipProof) -> Self {
+        let changed_nodes = proof
+            .authentication_path
+            .iter()
+            .enumerate()
+            .filter(|(i, node)| reference.authentication_path.get(*i) != Some(node))
+            .map(|(i, node)| (i as u32, *node))
+            .collect();
+
+        Self {
+            length: proof.authentication_path.len() as u32,
+            changed_nodes,
+        }
+    }
+
+    fn is_empty_against(&self, reference: &MmrMembershipProof) -> bool {
+        self.changed_nodes.is_empty() && self.length as usize == reference.authentication_path.len()
+    }
+
+    fn apply(
+        &self,
+        reference: &MmrMembershipProof,
+    ) -> Result<MmrMembershipProof, DecompressionError> {
+        let mut authentication_path = reference.authentication_path.clone();
+        authentication_path.resize(self.length as usize, Digest::default());
+        for &(i, node) in &self.changed_nodes {
+            *authentication_path
+                .get_mut(i as usize)
+                .ok_or(DecompressionError::AuthPathNodeOutOfBounds(i))? = node;
+        }
+
+        Ok(MmrMembershipProof::new(authentication_path))
+    }
+}
+
+/// A target chunk that differs from the reference proof.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum TargetChunkDelta {
+    /// Chunk index is absent from the reference proof.
+    New(MmrMembershipProof, Chunk),
+
+    /// Chunk index is present in the reference proof but its authentication
+    /// path or, if `Some`, its chunk differs.
+    Changed(AuthPathDelta, Option<Chunk>),
+}
+
+/// Difference between a membership proof and a reference proof for the same
+/// item.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct MembershipProofDelta {
+    auth_path_aocl: AuthPathDelta,
+    changed_target_chunks: Vec<(u64, TargetChunkDelta)>,
+    removed_target_chunks: Vec<u64>,
+}
+
+impl MembershipProofDelta {
+    /// The delta of `proof` against `reference`, or `None` if the two proofs
+    /// are not for the same item, in which case a delta is meaningless.
+    fn new(reference: &MsMembershipProof, proof: &MsMembershipProof) -> Option<Self> {
+        if reference.sender_randomness != proof.sender_randomness
+            || reference.receiver_preimage != proof.receiver_preimage
+            || reference.aocl_leaf_index != proof.aocl_leaf_index
+        {
+            return None;
+        }
+
+        let mut changed_target_chunks = vec![];
+        for (chunk_index, (chunk_mp, chunk)) in proof.target_chunks.iter() {
+            match reference.target_chunks.get(chunk_index) {
+                None => changed_target_chunks.push((
+                    *chunk_index,
+                    TargetChunkDelta::New(chunk_mp.clone(), chunk.clone()),
+                )),
+                Some((reference_mp, reference_chunk)) => {
+                    let auth_path_delta = AuthPathDelta::new(reference_mp, chunk_mp);
+                    let chunk_delta = (chunk != reference_chunk).then(|| chunk.clone());
+                    if !auth_path_delta.is_empty_against(reference_mp) || chunk_delta.is_some() {
+                        changed_target_chunks.push((
+                            *chunk_index,
+                            TargetChunkDelta::Changed(auth_path_delta, chunk_delta),
+                        ));
+                    }
+                }
+            }
+        }
+
+        let removed_target_chunks = reference
+            .target_chunks
+            .iter()
+            .map(|(chunk_index, _)| *chunk_index)
+            .filter(|chunk_index| !proof.target_chunks.contains_key(chunk_index))
+            .collect();
+
+        Some(Self {
+            auth_path_aocl: AuthPathDelta::new(&reference.auth_path_aocl, &proof.auth_path_aocl),
+            changed_target_chunks,
+            removed_target_chunks,
+        })
+    }
+
+    fn apply(
+        &self,
+        reference: &MsMembershipProof,
+    ) -> Result<MsMembershipProof, DecompressionError> {
+        let mut target_chunks = reference.target_chunks.clone();
+        for chunk_index in &self.removed_target_chunks {
+            target_chunks.remove(chunk_index);
+        }
+        for (chunk_index, chunk_delta) in &self.changed_target_chunks {
+            let authenticated_chunk = match chunk_delta {
+                TargetChunkDelta::New(chunk_mp, chunk) => (chunk_mp.clone(), chunk.clone()),
+                TargetChunkDelta::Changed(auth_path_delta, chunk) => {
+                    let (reference_mp, reference_chunk) = reference
+                        .target_chunks
+                        .get(chunk_index)
+                        .ok_or(DecompressionError::MissingTargetChunk(*chunk_index))?;
+                    (
+                        auth_path_delta.apply(reference_mp)?,
+                        chunk.clone().unwrap_or_else(|| reference_chunk.clone()),
+                    )
+                }
+            };
+            target_chunks.insert(*chunk_index, authenticated_chunk);
+        }
+
+        Ok(MsMembershipProof {
+            sender_randomness: reference.sender_randomness,
+            receiver_preimage: reference.receiver_preimage,
+            auth_path_aocl: self.auth_path_aocl.apply(&reference.auth_path_aocl)?,
+            aocl_leaf_index: reference.aocl_leaf_index,
+            target_chunks,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum StoredMembershipProof {
+    Full(MsMembershipProof),
+
+    /// Delta against the proof stored immediately before this one.
+    Delta(MembershipProofDelta),
+}
+
+/// The membership proofs of a monitored UTXO, newest first, in their storage
+/// format.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressedMembershipProofs(Vec<(Digest, StoredMembershipProof)>);
+
+impl CompressedMembershipProofs {
+    /// Compress a list of (block digest, membership proof) pairs, newest first.
+    pub fn compress(proofs: &VecDeque<(Digest, MsMembershipProof)>) -> Self {
+        let mut reference: Option<&MsMembershipProof> = None;
+        let mut stored = Vec::with_capacity(proofs.len());
+        for (block_digest, proof) in proofs {
+            let delta = reference.and_then(|reference| MembershipProofDelta::new(reference, proof));
+            let stored_proof = match delta {
+                Some(delta) => StoredMembershipProof::Delta(delta),
+                None => StoredMembershipProof::Full(proof.clone()),
+            };
+            stored.push((*block_digest, stored_proof));
+            reference = Some(proof);
+        }
+
+        Self(stored)
+    }
+
+    pub fn decompress(&self) -> Result<VecDeque<(Digest, MsMembershipProof)>, DecompressionError> {
+        let mut proofs: VecDeque<(Digest, MsMembershipProof)> =
+            VecDeque::with_capacity(self.0.len());
+        for (block_digest, stored_proof) in &self.0 {
+            let proof = match stored_proof {
+                StoredMembershipProof::Full(proof) => proof.clone(),
+                StoredMembershipProof::Delta(delta) => {
+                    let (_, reference) =
+                        proofs.back().ok_or(DecompressionError::MissingReference)?;
+                    delta.apply(reference)?
+                }
+            };
+            proofs.push_back((*block_digest, proof));
+        }
+
+        Ok(proofs)
+    }
+}
+
+pub(crate) fn serialize<S: Serializer>(
+    proofs: &VecDeque<(Digest, MsMembershipProof)>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    CompressedMembershipProofs::compress(proofs).serialize(serializer)
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<VecDeque<(Digest, MsMembershipProof)>, D::Error> {
+    CompressedMembershipProofs::deserialize(deserializer)?
+        .decompress()
+        .map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod membership_proof_compression_tests {
+    use rand::random;
+
+    use super::*;
+    use crate::util_types::mutator_set::commit;
+    use crate::util_types::mutator_set::ms_membership_proof::pseudorandom_mutator_set_membership_proof;
+    use crate::util_types::mutator_set::mutator_set_accumulator::MutatorSetAccumulator;
+
+    /// Membership proofs of one item across a sequence of blocks that each add
+    /// one item to the mutator set, newest first.
+    fn successive_proofs(num_blocks: usize) -> VecDeque<(Digest, MsMembershipProof)> {
+        let mut msa = MutatorSetAccumulator::default();
+        let item: Digest = random();
+        let sender_randomness: Digest = random();
+        let receiver_preimage: Digest = random();
+        let mut mp = msa.prove(item, sender_randomness, receiver_preimage);
+        msa.add(&commit(item, sender_randomness, receiver_preimage.hash()));
+
+        let mut proofs = VecDeque::new();
+        for _ in 0..num_blocks {
+            let addition_record = commit(random(), random(), random());
+            mp.update_from_addition(item, &msa, &addition_record)
+                .unwrap();
+            msa.add(&addition_record);
+            proofs.push_front((random(), mp.clone()));
+        }
+
+        proofs
+    }
+
+    #[test]
+    fn successive_proofs_round_trip() {
+        let proofs = successive_proofs(20);
+        let compressed = CompressedMembershipProofs::compress(&proofs);
+        assert_eq!(proofs, compressed.decompress().unwrap());
+        assert!(matches!(compressed.0[0].1, StoredMembershipProof::Full(_)));
+        assert!(compressed.0[1..]
+            .iter()
+            .all(|(_, stored)| matches!(stored, StoredMembershipProof::Delta(_))));
+    }
+
+    #[test]
+    fn unrelated_proofs_round_trip() {
+        let proofs: VecDeque<_> = (0..5)
+            .map(|_| {
+                (
+                    random(),
+                    pseudorandom_mutator_set_membership_proof(random()),
+                )
+            })
+            .collect();
+        let compressed = CompressedMembershipProofs::compress(&proofs);
+        assert_eq!(proofs, compressed.decompress().unwrap());
+    }
+
+    #[test]
+    fn empty_round_trip() {
+        let proofs = VecDeque::new();
+        let compressed = CompressedMembershipProofs::compress(&proofs);
+        assert_eq!(proofs, compressed.decompress().unwrap());
+    }
+
+    #[test]
+    fn target_chunk_changes_round_trip() {
+        let mut proofs = successive_proofs(1);
+        let (_, newest) = proofs[0].clone();
+
+        let mut with_chunks = newest.clone();
+        let chunks = pseudorandom_mutator_set_membership_proof(random()).target_chunks;
+        with_chunks.target_chunks = chunks.clone();
+        let mut with_changed_chunks = with_chunks.clone();
+        let some_index = *chunks.all_chunk_indices().first().unwrap();
+        with_changed_chunks.target_chunks.remove(&some_index);
+        with_changed_chunks.target_chunks.insert(
+            some_index + 1_000_000,
+            (
+                MmrMembershipProof::new(vec![random()]),
+                Chunk::empty_chunk(),
+            ),
+        );
+
+        proofs.push_back((random(), with_chunks));
+        proofs.push_back((random(), with_changed_chunks));
+        proofs.push_back((random(), newest));
+
+        let compressed = CompressedMembershipProofs::compress(&proofs);
+        assert_eq!(proofs, compressed.decompress().unwrap());
+    }
+
+    #[test]
+    fn compressed_proofs_are_smaller() {
+        let proofs = successive_proofs(20);
+        let uncompressed_size = bincode::serialize(&proofs).unwrap().len();
+        let compressed_size = bincode::serialize(&CompressedMembershipProofs::compress(&proofs))
+            .unwrap()
+            .len();
+        assert!(
+            2 * compressed_size < uncompressed_size,
+            "compressed: {compressed_size}, uncompressed: {uncompressed_size}"
+        );
+    }
+
+    #[test]
+    fn delta_without_reference_is_rejected() {
+        let proofs = successive_proofs(2);
+        let mut compressed = CompressedMembershipProofs::compress(&proofs);
+        compressed.0.remove(0);
+        assert_eq!(
+            Err(DecompressionError::MissingReference),
+            compressed.decompress()
+        );
+    }
+}
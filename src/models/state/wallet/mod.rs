@@ -1,11 +1,28 @@
 pub mod address;
+pub mod audit_export;
+pub mod coin_selection;
 pub mod coin_with_possible_timelock;
+pub mod coinbase_address_rotation;
+pub mod encrypted_secret;
 pub mod expected_utxo;
+pub mod expected_utxo_bundle;
+pub mod hd_derivation;
+pub mod key_rotation;
+pub mod membership_proof_compression;
 pub mod monitored_utxo;
+pub mod ms_update_window;
+pub mod own_transactions;
 pub mod rusty_wallet_database;
+pub mod spend_authorization;
+pub mod spent_utxo_index;
+pub mod unconfirmed_change;
 pub mod unlocked_utxo;
+pub mod wallet_fsync_policy;
+pub mod wallet_memo;
 pub mod wallet_state;
 pub mod wallet_status;
+pub mod wallet_summary;
+pub mod watch_only;
 
 use std::fs;
 use std::path::Path;
@@ -17,6 +34,8 @@ use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use bip39::Mnemonic;
+use hd_derivation::AccountDescriptor;
+use hd_derivation::DerivationPath;
 use itertools::Itertools;
 use num_traits::Zero;
 use rand::rngs::StdRng;
@@ -34,6 +53,7 @@ use twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
 use zeroize::Zeroize;
 use zeroize::ZeroizeOnDrop;
 
+use crate::config_models::network::Network;
 use crate::models::blockchain::block::block_height::BlockHeight;
 use crate::prelude::twenty_first;
 use crate::Hash;
@@ -202,11 +222,6 @@ impl WalletSecret {
         &self,
         index: u16,
     ) -> generation_address::GenerationSpendingKey {
-        assert!(
-            index.is_zero(),
-            "For now we only support one generation address per wallet"
-        );
-
         // We keep n between 0 and 2^16 as this makes it possible to scan all possible addresses
         // in case you don't know with what counter you made the address
         let key_seed = Hash::hash_varlen(
@@ -222,6 +237,37 @@ impl WalletSecret {
         generation_address::GenerationSpendingKey::derive_from_seed(key_seed)
     }
 
+    /// seed of the given account of the hierarchical derivation, from which
+    /// all keys along the account's paths are derived.
+    fn account_seed(&self, account: u32) -> Digest {
+        Hash::hash_varlen(
+            &[
+                self.secret_seed.0.encode(),
+                vec![
+                    hd_derivation::HD_DERIVATION_FLAG,
+                    BFieldElement::new(account.into()),
+                ],
+            ]
+            .concat(),
+        )
+    }
+
+    /// derives the generation spending key at the given derivation path. See
+    /// [hd_derivation] for how these keys relate to the wallet's default
+    /// keys.
+    pub fn generation_spending_key_at(
+        &self,
+        path: DerivationPath,
+    ) -> generation_address::GenerationSpendingKey {
+        hd_derivation::spending_key(self.account_seed(path.account), path)
+    }
+
+    /// the descriptor from which the receiving addresses of the given account
+    /// can be derived without this secret.
+    pub fn account_descriptor(&self, network: Network, account: u32) -> AccountDescriptor {
+        AccountDescriptor::from_account_seed(network, account, self.account_seed(account))
+    }
+
     /// derives a symmetric key at `index`
     ///
     /// note: this is a read-only method and does not modify wallet state.  When
@@ -15,7 +15,8 @@ use crate::util_types::mutator_set::ms_membership_proof::MsMembershipProof;
 pub struct MonitoredUtxo {
     pub utxo: Utxo,
 
-    // Mapping from block digest to membership proof
+    // Mapping from block digest to membership proof, newest first
+    #[serde(with = "super::membership_proof_compression")]
     pub blockhash_to_membership_proof: VecDeque<(Digest, MsMembershipProof)>,
 
     pub number_of_mps_per_utxo: usize,
@@ -53,14 +54,59 @@ impl MonitoredUtxo {
         block_digest: Digest,
         updated_membership_proof: MsMembershipProof,
     ) {
-        while self.blockhash_to_membership_proof.len() >= self.number_of_mps_per_utxo {
-            self.blockhash_to_membership_proof.pop_back();
+        self.add_membership_proof_for_tip_keeping_anchor(
+            block_digest,
+            updated_membership_proof,
+            None,
+        );
+    }
+
+    /// Like [`Self::add_membership_proof_for_tip`], but never evicts the
+    /// membership proof for the anchor block, which does not count towards
+    /// the number of stored membership proofs.
+    pub fn add_membership_proof_for_tip_keeping_anchor(
+        &mut self,
+        block_digest: Digest,
+        updated_membership_proof: MsMembershipProof,
+        anchor: Option<Digest>,
+    ) {
+        let is_anchor = |digest: &Digest| Some(*digest) == anchor;
+        while self
+            .blockhash_to_membership_proof
+            .iter()
+            .filter(|(digest, _)| !is_anchor(digest))
+            .count()
+            >= self.number_of_mps_per_utxo
+        {
+            let Some(oldest) = self
+                .blockhash_to_membership_proof
+                .iter()
+                .rposition(|(digest, _)| !is_anchor(digest))
+            else {
+                break;
+            };
+            self.blockhash_to_membership_proof.remove(oldest);
         }
 
         self.blockhash_to_membership_proof
             .push_front((block_digest, updated_membership_proof));
     }
 
+    /// Replace the membership proof for the previous anchor block with the
+    /// membership proof for the new anchor block. The anchor proof is stored
+    /// last, as it is older than all proofs synced to blocks above it.
+    pub fn set_anchor_membership_proof(
+        &mut self,
+        previous_anchor: Option<Digest>,
+        anchor: Digest,
+        membership_proof: MsMembershipProof,
+    ) {
+        self.blockhash_to_membership_proof
+            .retain(|(digest, _)| *digest != anchor && Some(*digest) != previous_anchor);
+        self.blockhash_to_membership_proof
+            .push_back((anchor, membership_proof));
+    }
+
     pub fn get_membership_proof_for_block(
         &self,
         block_digest: Digest,
@@ -0,0 +1,221 @@
+//! Window of recent mutator set updates, used to keep the wallet's membership
+//! proofs synced to a block some depth below the tip, the anchor.
+//!
+//! A membership proof synced to the tip is invalidated by any reorganization.
+//! A proof synced to the anchor survives reorganizations up to the anchor
+//! depth, and can be brought to the new tip by applying the blocks of the new
+//! chain, without reverting any. To advance the anchor one block at a time,
+//! the window holds the mutator set updates of the blocks between the anchor
+//! and the tip.
+
+use std::collections::VecDeque;
+
+use anyhow::bail;
+use anyhow::Result;
+use itertools::Itertools;
+use twenty_first::math::tip5::Digest;
+
+use crate::models::blockchain::block::mutator_set_update::MutatorSetUpdate;
+use crate::models::blockchain::block::Block;
+use crate::prelude::twenty_first;
+use crate::util_types::mutator_set::ms_membership_proof::MsMembershipProof;
+use crate::util_types::mutator_set::mutator_set_accumulator::MutatorSetAccumulator;
+use crate::util_types::mutator_set::removal_record::RemovalRecord;
+
+#[derive(Debug, Clone)]
+struct WindowEntry {
+    block_digest: Digest,
+    previous_mutator_set_accumulator: MutatorSetAccumulator,
+    update: MutatorSetUpdate,
+}
+
+/// Advancement of the anchor by one block.
+#[derive(Debug, Clone)]
+pub(crate) struct AnchorAdvance {
+    /// The previous anchor, `None` if none was set
+    pub from: Option<Digest>,
+    pub to: Digest,
+    previous_mutator_set_accumulator: MutatorSetAccumulator,
+    update: MutatorSetUpdate,
+}
+
+impl AnchorAdvance {
+    /// Update membership proofs synced to the previous anchor to the new
+    /// anchor. `item_digests` lists the items of the proofs, in the same order.
+    pub(crate) fn update_membership_proofs(
+        &self,
+        membership_proofs: &mut [MsMembershipProof],
+        item_digests: &[Digest],
+    ) -> Result<()> {
+        let mut msa_state = self.previous_mutator_set_accumulator.clone();
+        let mut removal_records = self.update.removals.clone();
+        removal_records.reverse();
+        let mut removal_records = removal_records.iter_mut().collect_vec();
+
+        for addition_record in &self.update.additions {
+            if MsMembershipProof::batch_update_from_addition(
+                &mut membership_proofs.iter_mut().collect_vec(),
+                item_digests,
+                &msa_state,
+                addition_record,
+            )
+            .is_err()
+            {
+                bail!("Failed to update anchored membership proofs with addition record");
+            }
+            RemovalRecord::batch_update_from_addition(&mut removal_records, &msa_state);
+            msa_state.add(addition_record);
+        }
+
+        while let Some(removal_record) = removal_records.pop() {
+            if MsMembershipProof::batch_update_from_remove(
+                &mut membership_proofs.iter_mut().collect_vec(),
+                removal_record,
+            )
+            .is_err()
+            {
+                bail!("Failed to update anchored membership proofs with removal record");
+            }
+            RemovalRecord::batch_update_from_remove(&mut removal_records, removal_record);
+            msa_state.remove(removal_record);
+        }
+
+        Ok(())
+    }
+}
+
+/// The mutator set updates of the blocks between the anchor and the tip.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MsUpdateWindow {
+    depth: usize,
+    anchor: Option<Digest>,
+
+    /// oldest first
+    entries: VecDeque<WindowEntry>,
+}
+
+impl MsUpdateWindow {
+    /// A window anchoring proofs `depth` blocks below the tip. Depth 0
+    /// disables anchoring.
+    pub(crate) fn new(depth: usize) -> Self {
+        Self {
+            depth,
+            anchor: None,
+            entries: VecDeque::with_capacity(depth + 1),
+        }
+    }
+
+    pub(crate) fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub(crate) fn anchor(&self) -> Option<Digest> {
+        self.anchor
+    }
+
+    /// Register a new tip, given the mutator set accumulator of its parent.
+    /// Returns the advancement of the anchor, if any.
+    ///
+    /// If the new tip is not a child of the old tip, the updates of the
+    /// abandoned blocks are dropped. If the new tip forks off below the
+    /// anchor, the anchor is reset to the new tip's parent.
+    pub(crate) fn push(
+        &mut self,
+        previous_mutator_set_accumulator: &MutatorSetAccumulator,
+        block: &Block,
+    ) -> Option<AnchorAdvance> {
+        if self.depth == 0 {
+            return None;
+        }
+
+        let parent = block.header().prev_block_digest;
+        while self
+            .entries
+            .back()
+            .is_some_and(|entry| entry.block_digest != parent)
+        {
+            self.entries.pop_back();
+        }
+        if self.entries.is_empty() && self.anchor != Some(parent) {
+            self.anchor = Some(parent);
+        }
+
+        self.entries.push_back(WindowEntry {
+            block_digest: block.hash(),
+            previous_mutator_set_accumulator: previous_mutator_set_accumulator.clone(),
+            update: MutatorSetUpdate::new(
+                block.body().transaction_kernel.inputs.clone(),
+                block.body().transaction_kernel.outputs.clone(),
+            ),
+        });
+        if self.entries.len() <= self.depth {
+            return None;
+        }
+
+        let new_anchor = self.entries.pop_front().unwrap();
+        let from = self.anchor.replace(new_anchor.block_digest);
+        Some(AnchorAdvance {
+            from,
+            to: new_anchor.block_digest,
+            previous_mutator_set_accumulator: new_anchor.previous_mutator_set_accumulator,
+            update: new_anchor.update,
+        })
+    }
+}
+
+#[cfg(test)]
+mod ms_update_window_tests {
+    use rand::random;
+
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::state::wallet::WalletSecret;
+    use crate::tests::shared::make_mock_block;
+
+    fn mock_chain(parent: &Block, length: usize) -> Vec<Block> {
+        let address = WalletSecret::new_random()
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+        let mut chain: Vec<Block> = vec![];
+        for _ in 0..length {
+            let parent = chain.last().unwrap_or(parent);
+            let (block, _, _) = make_mock_block(parent, None, address, random());
+            chain.push(block);
+        }
+        chain
+    }
+
+    #[test]
+    fn anchor_trails_tip_and_survives_shallow_reorganization() {
+        let genesis = Block::genesis_block(Network::RegTest);
+        let chain = mock_chain(&genesis, 4);
+        let mut window = MsUpdateWindow::new(2);
+
+        let mut parent = &genesis;
+        let mut advances = vec![];
+        for block in &chain {
+            advances.push(window.push(&parent.body().mutator_set_accumulator, block));
+            parent = block;
+        }
+        assert!(advances[0].is_none());
+        assert!(advances[1].is_none());
+        let advance = advances[2].as_ref().unwrap();
+        assert_eq!(Some(genesis.hash()), advance.from);
+        assert_eq!(chain[0].hash(), advance.to);
+        assert_eq!(Some(chain[1].hash()), window.anchor());
+
+        // Fork off the anchor, abandoning the two blocks above it
+        let fork = mock_chain(&chain[1], 1);
+        assert!(window
+            .push(&chain[1].body().mutator_set_accumulator, &fork[0])
+            .is_none());
+        assert_eq!(Some(chain[1].hash()), window.anchor());
+
+        // Fork off below the anchor
+        let deep_fork = mock_chain(&genesis, 1);
+        assert!(window
+            .push(&genesis.body().mutator_set_accumulator, &deep_fork[0])
+            .is_none());
+        assert_eq!(Some(genesis.hash()), window.anchor());
+    }
+}
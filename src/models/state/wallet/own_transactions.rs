@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use tasm_lib::twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
+
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::models::blockchain::block::Block;
+use crate::models::blockchain::transaction::primitive_witness::PrimitiveWitness;
+use crate::models::blockchain::transaction::transaction_kernel::TransactionKernel;
+use crate::models::blockchain::transaction::Transaction;
+use crate::models::blockchain::transaction::TransactionProof;
+use crate::models::state::transaction_kernel_id::TransactionKernelId;
+use crate::models::state::wallet::unconfirmed_change::UnconfirmedSpend;
+use crate::util_types::mutator_set::ms_membership_proof::MsMembershipProof;
+use crate::util_types::mutator_set::mutator_set_accumulator::MutatorSetAccumulator;
+use crate::util_types::mutator_set::removal_record::AbsoluteIndexSet;
+use crate::util_types::mutator_set::removal_record::RemovalRecord;
+use crate::Hash;
+
+/// Number of blocks a confirmed transaction is remembered for. Reorganizations
+/// deeper than this cannot resurrect the transaction.
+pub(crate) const OWN_TRANSACTION_RETENTION_DEPTH: u64 = 100;
+
+#[derive(Debug, Clone)]
+struct OwnTransaction {
+    primitive_witness: PrimitiveWitness,
+
+    /// Height of the block that confirmed the transaction on the canonical
+    /// chain, if any.
+    confirmed_at: Option<BlockHeight>,
+
+    /// Unconfirmed transactions whose change this transaction spends. The
+    /// transaction is held until they are all confirmed.
+    parents: Vec<TransactionKernelId>,
+}
+
+impl OwnTransaction {
+    fn input_index_sets(&self) -> impl Iterator<Item = &AbsoluteIndexSet> {
+        self.primitive_witness
+            .kernel
+            .inputs
+            .iter()
+            .map(|rr| &rr.absolute_indices)
+    }
+}
+
+/// Transactions initiated by this wallet, along with the secret data needed
+/// to rebuild them against a new mutator set.
+///
+/// A reorganization can un-confirm a transaction that the wallet sent. The
+/// mempool is cleared on reorganizations, so without these records the
+/// transaction would silently disappear from both chain and mempool.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OwnTransactions(HashMap<TransactionKernelId, OwnTransaction>);
+
+impl OwnTransactions {
+    /// Start tracking a transaction initiated by this wallet.
+    pub(crate) fn track(&mut self, primitive_witness: PrimitiveWitness) {
+        self.track_unconfirmed_spend(primitive_witness, vec![]);
+    }
+
+    /// Start tracking a transaction spending the change of the given
+    /// unconfirmed transactions.
+    pub(crate) fn track_unconfirmed_spend(
+        &mut self,
+        primitive_witness: PrimitiveWitness,
+        parents: Vec<TransactionKernelId>,
+    ) {
+        self.0.insert(
+            primitive_witness.kernel.txid(),
+            OwnTransaction {
+                primitive_witness,
+                confirmed_at: None,
+                parents,
+            },
+        );
+    }
+
+    /// The transactions held until the transactions whose change they spend
+    /// are confirmed.
+    pub(crate) fn unconfirmed_spends(&self) -> Vec<UnconfirmedSpend> {
+        self.0
+            .iter()
+            .filter(|(_, tx)| !tx.parents.is_empty())
+            .map(|(txid, tx)| UnconfirmedSpend {
+                txid: *txid,
+                parents: tx.parents.clone(),
+            })
+            .collect()
+    }
+
+    pub(crate) fn parents(&self, txid: TransactionKernelId) -> &[TransactionKernelId] {
+        self.0.get(&txid).map_or(&[], |tx| &tx.parents)
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[cfg(test)]
+    fn is_confirmed(&self, txid: TransactionKernelId) -> Option<BlockHeight> {
+        self.0.get(&txid).and_then(|tx| tx.confirmed_at)
+    }
+
+    pub(crate) fn txids(&self) -> Vec<TransactionKernelId> {
+        self.0.keys().copied().collect()
+    }
+
+    pub(crate) fn primitive_witness(&self, txid: TransactionKernelId) -> Option<&PrimitiveWitness> {
+        self.0.get(&txid).map(|tx| &tx.primitive_witness)
+    }
+
+    pub(crate) fn forget(&mut self, txid: TransactionKernelId) {
+        self.0.remove(&txid);
+    }
+
+    /// Register a new tip: mark transactions whose inputs are all spent by
+    /// the block as confirmed, and forget transactions confirmed deeper than
+    /// [`OWN_TRANSACTION_RETENTION_DEPTH`].
+    pub(crate) fn update_with_block(&mut self, block: &Block) {
+        let height = block.header().height;
+        self.mark_confirmed(&block.kernel.body.transaction_kernel.inputs, height);
+        self.prune(height);
+    }
+
+    fn mark_confirmed(&mut self, block_inputs: &[RemovalRecord], height: BlockHeight) {
+        let spent = block_inputs
+            .iter()
+            .map(|rr| &rr.absolute_indices)
+            .collect_vec();
+        for tx in self.0.values_mut() {
+            let mut inputs = tx.input_index_sets().peekable();
+            if inputs.peek().is_some() && inputs.all(|index_set| spent.contains(&index_set)) {
+                tx.confirmed_at = Some(height);
+            }
+        }
+    }
+
+    fn prune(&mut self, tip_height: BlockHeight) {
+        self.0.retain(|_, tx| {
+            tx.confirmed_at.map_or(true, |confirmed_at| {
+                u64::from(tip_height) < u64::from(confirmed_at) + OWN_TRANSACTION_RETENTION_DEPTH
+            })
+        });
+    }
+
+    /// Rebuild a tracked transaction against the given mutator set, using
+    /// fresh membership proofs for its inputs, listed in input order. The
+    /// transaction is marked as unconfirmed.
+    pub(crate) fn rebuild(
+        &mut self,
+        txid: TransactionKernelId,
+        membership_proofs: Vec<MsMembershipProof>,
+        mutator_set_accumulator: &MutatorSetAccumulator,
+    ) -> Option<Transaction> {
+        let tx = self.0.get_mut(&txid)?;
+        let witness = &mut tx.primitive_witness;
+        let inputs = witness
+            .input_utxos
+            .utxos
+            .iter()
+            .zip(membership_proofs.iter())
+            .map(|(utxo, mp)| mutator_set_accumulator.drop(Hash::hash(utxo), mp))
+            .collect();
+
+        witness.kernel = TransactionKernel {
+            inputs,
+            mutator_set_hash: mutator_set_accumulator.hash(),
+            ..witness.kernel.clone()
+        };
+        witness.input_membership_proofs = membership_proofs;
+        witness.mutator_set_accumulator = mutator_set_accumulator.clone();
+        tx.confirmed_at = None;
+        tx.parents.clear();
+
+        Some(Transaction {
+            kernel: witness.kernel.clone(),
+            proof: TransactionProof::Witness(witness.clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod own_transactions_tests {
+    use proptest::arbitrary::Arbitrary;
+    use proptest::strategy::Strategy;
+    use proptest::strategy::ValueTree;
+    use proptest::test_runner::TestRunner;
+
+    use super::*;
+
+    fn primitive_witness(num_inputs: usize) -> PrimitiveWitness {
+        let mut test_runner = TestRunner::deterministic();
+        PrimitiveWitness::arbitrary_with((num_inputs, 2, 0))
+            .new_tree(&mut test_runner)
+            .unwrap()
+            .current()
+    }
+
+    #[test]
+    fn transaction_is_confirmed_only_when_all_inputs_are_spent() {
+        let witness = primitive_witness(2);
+        let txid = witness.kernel.txid();
+        let inputs = witness.kernel.inputs.clone();
+        let mut own_transactions = OwnTransactions::default();
+        own_transactions.track(witness);
+
+        own_transactions.mark_confirmed(&inputs[..1], 5u64.into());
+        assert!(own_transactions.is_confirmed(txid).is_none());
+
+        own_transactions.mark_confirmed(&inputs, 6u64.into());
+        assert_eq!(Some(6u64.into()), own_transactions.is_confirmed(txid));
+    }
+
+    #[test]
+    fn deeply_confirmed_transactions_are_forgotten() {
+        let witness = primitive_witness(1);
+        let inputs = witness.kernel.inputs.clone();
+        let mut own_transactions = OwnTransactions::default();
+        own_transactions.track(witness);
+        own_transactions.mark_confirmed(&inputs, 10u64.into());
+
+        own_transactions.prune((10 + OWN_TRANSACTION_RETENTION_DEPTH - 1).into());
+        assert_eq!(1, own_transactions.len());
+
+        own_transactions.prune((10 + OWN_TRANSACTION_RETENTION_DEPTH).into());
+        assert_eq!(0, own_transactions.len());
+    }
+
+    #[test]
+    fn rebuild_preserves_txid_and_unconfirms() {
+        let witness = primitive_witness(2);
+        let txid = witness.kernel.txid();
+        let inputs = witness.kernel.inputs.clone();
+        let membership_proofs = witness.input_membership_proofs.clone();
+        let msa = witness.mutator_set_accumulator.clone();
+        let mut own_transactions = OwnTransactions::default();
+        own_transactions.track(witness);
+        own_transactions.mark_confirmed(&inputs, 3u64.into());
+
+        let rebuilt = own_transactions
+            .rebuild(txid, membership_proofs, &msa)
+            .unwrap();
+        assert_eq!(txid, rebuilt.kernel.txid());
+        assert!(rebuilt.is_confirmable_relative_to(&msa));
+        assert!(own_transactions.is_confirmed(txid).is_none());
+    }
+
+    #[test]
+    fn unconfirmed_spend_is_released_by_rebuild() {
+        let parent = primitive_witness(2).kernel.txid();
+        let witness = primitive_witness(1);
+        let txid = witness.kernel.txid();
+        let membership_proofs = witness.input_membership_proofs.clone();
+        let msa = witness.mutator_set_accumulator.clone();
+        let mut own_transactions = OwnTransactions::default();
+        own_transactions.track_unconfirmed_spend(witness, vec![parent]);
+        assert_eq!(
+            vec![UnconfirmedSpend {
+                txid,
+                parents: vec![parent]
+            }],
+            own_transactions.unconfirmed_spends()
+        );
+
+        own_transactions.rebuild(txid, membership_proofs, &msa);
+        assert!(own_transactions.unconfirmed_spends().is_empty());
+        assert!(own_transactions.parents(txid).is_empty());
+    }
+}
@@ -1,19 +1,60 @@
+use std::collections::VecDeque;
+
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
 use twenty_first::math::tip5::Digest;
 
 use super::expected_utxo::ExpectedUtxo;
 use super::monitored_utxo::MonitoredUtxo;
+use super::wallet_fsync_policy::WalletFsyncPolicy;
+use super::wallet_memo::WalletMemo;
 use crate::database::storage::storage_schema::traits::*;
 use crate::database::storage::storage_schema::DbtSingleton;
 use crate::database::storage::storage_schema::DbtVec;
 use crate::database::storage::storage_schema::RustyKey;
 use crate::database::storage::storage_schema::RustyValue;
 use crate::database::storage::storage_schema::SimpleRustyStorage;
+use crate::database::storage::storage_vec::traits::*;
 use crate::database::NeptuneLevelDb;
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::models::blockchain::transaction::utxo::Utxo;
+use crate::models::proof_abstractions::timestamp::Timestamp;
 use crate::prelude::twenty_first;
+use crate::util_types::mutator_set::ms_membership_proof::MsMembershipProof;
+
+/// [`MonitoredUtxo`] as stored before its membership proofs were
+/// delta-compressed. Only read, to migrate old wallet databases.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LegacyMonitoredUtxo {
+    utxo: Utxo,
+    blockhash_to_membership_proof: VecDeque<(Digest, MsMembershipProof)>,
+    number_of_mps_per_utxo: usize,
+    spent_in_block: Option<(Digest, Timestamp, BlockHeight)>,
+    confirmed_in_block: Option<(Digest, Timestamp, BlockHeight)>,
+    abandoned_at: Option<(Digest, Timestamp, BlockHeight)>,
+}
+
+impl From<LegacyMonitoredUtxo> for MonitoredUtxo {
+    fn from(legacy: LegacyMonitoredUtxo) -> Self {
+        Self {
+            utxo: legacy.utxo,
+            blockhash_to_membership_proof: legacy.blockhash_to_membership_proof,
+            number_of_mps_per_utxo: legacy.number_of_mps_per_utxo,
+            spent_in_block: legacy.spent_in_block,
+            confirmed_in_block: legacy.confirmed_in_block,
+            abandoned_at: legacy.abandoned_at,
+        }
+    }
+}
 
 pub struct RustyWalletDatabase {
     storage: SimpleRustyStorage,
 
+    // monitored utxos in the format without proof compression. Emptied on
+    // connect.
+    legacy_monitored_utxos: DbtVec<LegacyMonitoredUtxo>,
+
     // list of utxos we have already received in a block
     monitored_utxos: DbtVec<MonitoredUtxo>,
 
@@ -25,6 +66,22 @@ pub struct RustyWalletDatabase {
 
     // counts the number of output UTXOs generated by this wallet
     counter: DbtSingleton<u64>,
+
+    // memos attached to utxos sent or received by this wallet
+    memos: DbtVec<WalletMemo>,
+
+    // number of generation keys handed out by this wallet
+    generation_key_counter: DbtSingleton<u64>,
+
+    // accounts of the hierarchical derivation whose descriptors were exported
+    exported_accounts: DbtVec<u32>,
+
+    // in-memory only. bumped on every mutable access to the monitored or
+    // expected utxos.
+    utxo_generation: u64,
+
+    // in-memory only. when writes are synced to disk.
+    fsync_policy: WalletFsyncPolicy,
 }
 
 impl RustyWalletDatabase {
@@ -35,9 +92,11 @@ impl RustyWalletDatabase {
             crate::LOG_LOCK_EVENT_CB,
         );
 
-        let monitored_utxos = storage
+        // Tables are identified by the order in which they are created, so
+        // new tables must be created last.
+        let legacy_monitored_utxos = storage
             .schema
-            .new_vec::<MonitoredUtxo>("monitored_utxos")
+            .new_vec::<LegacyMonitoredUtxo>("monitored_utxos")
             .await;
 
         let expected_utxos = storage
@@ -47,14 +106,55 @@ impl RustyWalletDatabase {
 
         let sync_label = storage.schema.new_singleton::<Digest>("sync_label").await;
         let counter = storage.schema.new_singleton::<u64>("counter").await;
+        let monitored_utxos = storage
+            .schema
+            .new_vec::<MonitoredUtxo>("monitored_utxos_compressed")
+            .await;
+        let memos = storage.schema.new_vec::<WalletMemo>("memos").await;
+        let generation_key_counter = storage
+            .schema
+            .new_singleton::<u64>("generation_key_counter")
+            .await;
+        let exported_accounts = storage.schema.new_vec::<u32>("exported_accounts").await;
 
-        Self {
+        let mut wallet_db = Self {
             storage,
+            legacy_monitored_utxos,
             monitored_utxos,
             expected_utxos,
             sync_label,
             counter,
+            memos,
+            generation_key_counter,
+            exported_accounts,
+            utxo_generation: 0,
+            fsync_policy: WalletFsyncPolicy::default(),
+        };
+        wallet_db.migrate_legacy_monitored_utxos().await;
+
+        wallet_db
+    }
+
+    /// Move monitored UTXOs stored in the legacy format to the table that
+    /// stores their membership proofs compressed. Atomic, since both tables
+    /// are written in the same batch.
+    async fn migrate_legacy_monitored_utxos(&mut self) {
+        if self.legacy_monitored_utxos.is_empty().await {
+            return;
         }
+
+        let legacy_monitored_utxos = self.legacy_monitored_utxos.get_all().await;
+        info!(
+            "Migrating {} monitored UTXOs to compressed membership proof storage",
+            legacy_monitored_utxos.len()
+        );
+        for legacy_monitored_utxo in legacy_monitored_utxos {
+            self.monitored_utxos
+                .push(legacy_monitored_utxo.into())
+                .await;
+        }
+        self.legacy_monitored_utxos.clear().await;
+        self.persist().await;
     }
 
     /// get monitored_utxos.
@@ -64,6 +164,7 @@ impl RustyWalletDatabase {
 
     /// get mutable monitored_utxos.
     pub fn monitored_utxos_mut(&mut self) -> &mut DbtVec<MonitoredUtxo> {
+        self.utxo_generation += 1;
         &mut self.monitored_utxos
     }
 
@@ -74,9 +175,27 @@ impl RustyWalletDatabase {
 
     /// get mutable expected_utxos.
     pub fn expected_utxos_mut(&mut self) -> &mut DbtVec<ExpectedUtxo> {
+        self.utxo_generation += 1;
         &mut self.expected_utxos
     }
 
+    /// Changes whenever the monitored or expected utxos may have been
+    /// modified since the last call, for invalidating aggregates computed
+    /// from them.
+    pub(crate) fn utxo_generation(&self) -> u64 {
+        self.utxo_generation
+    }
+
+    /// get memos.
+    pub fn memos(&self) -> &DbtVec<WalletMemo> {
+        &self.memos
+    }
+
+    /// get mutable memos.
+    pub fn memos_mut(&mut self) -> &mut DbtVec<WalletMemo> {
+        &mut self.memos
+    }
+
     /// Get the hash of the block to which this database is synced.
     pub async fn get_sync_label(&self) -> Digest {
         self.sync_label.get().await
@@ -93,10 +212,113 @@ impl RustyWalletDatabase {
     pub async fn set_counter(&mut self, counter: u64) {
         self.counter.set(counter).await;
     }
+
+    /// Get the number of generation keys handed out. Zero for databases
+    /// created before the counter was introduced, which only used the first
+    /// key.
+    pub async fn get_generation_key_counter(&self) -> u64 {
+        self.generation_key_counter.get().await
+    }
+
+    pub async fn set_generation_key_counter(&mut self, counter: u64) {
+        self.generation_key_counter.set(counter).await;
+    }
+
+    /// The accounts whose descriptors were exported, in order of export.
+    pub fn exported_accounts(&self) -> &DbtVec<u32> {
+        &self.exported_accounts
+    }
+
+    pub fn exported_accounts_mut(&mut self) -> &mut DbtVec<u32> {
+        &mut self.exported_accounts
+    }
+
+    pub fn set_fsync_policy(&mut self, fsync_policy: WalletFsyncPolicy) {
+        self.fsync_policy = fsync_policy;
+    }
+
+    pub fn fsync_policy(&self) -> WalletFsyncPolicy {
+        self.fsync_policy
+    }
+
+    /// Write all changes caused by applying a block in one atomic batch,
+    /// synced to disk only if the fsync policy syncs every write. Otherwise,
+    /// the batch is synced by the next call to [`Self::sync`] or, unless the
+    /// policy is periodic, to [`StorageWriter::persist`].
+    pub async fn persist_block_update(&mut self) {
+        if self.fsync_policy.syncs_block_updates() {
+            self.storage.persist().await
+        } else {
+            self.storage.persist_unsynced().await
+        }
+    }
+
+    /// Write all pending changes and sync them, and all earlier unsynced
+    /// writes, to disk, regardless of the fsync policy.
+    pub async fn sync(&mut self) {
+        self.storage.persist().await
+    }
 }
 
 impl StorageWriter for RustyWalletDatabase {
+    /// Write all pending changes in one atomic batch, synced to disk unless
+    /// the fsync policy is periodic.
     async fn persist(&mut self) {
-        self.storage.persist().await
+        if self.fsync_policy.syncs_every_commit() {
+            self.storage.persist().await
+        } else {
+            self.storage.persist_unsynced().await
+        }
+    }
+}
+
+#[cfg(test)]
+mod rusty_wallet_database_tests {
+    use rand::random;
+
+    use super::*;
+    use crate::models::blockchain::transaction::lock_script::LockScript;
+    use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+    use crate::util_types::mutator_set::ms_membership_proof::pseudorandom_mutator_set_membership_proof;
+
+    #[tokio::test]
+    async fn legacy_monitored_utxos_are_migrated() {
+        let db = NeptuneLevelDb::open_new_test_database(true, None, None, None)
+            .await
+            .unwrap();
+
+        let legacy_monitored_utxo = LegacyMonitoredUtxo {
+            utxo: Utxo::new_native_currency(LockScript::anyone_can_spend(), NeptuneCoins::new(1)),
+            blockhash_to_membership_proof: (0..3)
+                .map(|_| {
+                    (
+                        random(),
+                        pseudorandom_mutator_set_membership_proof(random()),
+                    )
+                })
+                .collect(),
+            number_of_mps_per_utxo: 3,
+            spent_in_block: None,
+            confirmed_in_block: None,
+            abandoned_at: None,
+        };
+        {
+            let mut wallet_db = RustyWalletDatabase::connect(db.clone()).await;
+            wallet_db
+                .legacy_monitored_utxos
+                .push(legacy_monitored_utxo.clone())
+                .await;
+            wallet_db.persist().await;
+        }
+
+        let wallet_db = RustyWalletDatabase::connect(db).await;
+        assert!(wallet_db.legacy_monitored_utxos.is_empty().await);
+        assert_eq!(1, wallet_db.monitored_utxos().len().await);
+        let migrated = wallet_db.monitored_utxos().get(0).await;
+        assert_eq!(legacy_monitored_utxo.utxo, migrated.utxo);
+        assert_eq!(
+            legacy_monitored_utxo.blockhash_to_membership_proof,
+            migrated.blockhash_to_membership_proof
+        );
     }
 }
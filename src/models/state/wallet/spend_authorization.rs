@@ -0,0 +1,130 @@
+//! Passphrase authorizing spends requested over RPC.
+//!
+//! Anyone holding the RPC credentials of a node can spend the funds of its
+//! wallet. A spend passphrase is a second factor against leaked credentials:
+//! when one is set, the send RPCs only create a transaction if the call
+//! carries the passphrase. Only an Argon2id hash of the passphrase is stored,
+//! in the wallet directory.
+//!
+//! The passphrase is set with `neptune-cli set-spend-passphrase`, which works
+//! on the data directory rather than over RPC, so it cannot be changed with
+//! the RPC credentials alone. The node reads it on startup.
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::SaltString;
+use argon2::Argon2;
+use argon2::PasswordHash;
+use argon2::PasswordHasher;
+use argon2::PasswordVerifier;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub const SPEND_AUTHORIZATION_FILE_NAME: &str = "spend_authorization.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendAuthorization {
+    /// Argon2id hash of the passphrase, in PHC string format
+    passphrase_hash: String,
+}
+
+impl SpendAuthorization {
+    pub fn new(passphrase: &str) -> Result<Self> {
+        let salt = SaltString::generate(&mut OsRng);
+        let passphrase_hash = Argon2::default()
+            .hash_password(passphrase.as_bytes(), &salt)
+            .map_err(|e| anyhow!("could not hash spend passphrase: {e}"))?
+            .to_string();
+        Ok(Self { passphrase_hash })
+    }
+
+    pub fn verify(&self, passphrase: &str) -> bool {
+        PasswordHash::new(&self.passphrase_hash).is_ok_and(|hash| {
+            Argon2::default()
+                .verify_password(passphrase.as_bytes(), &hash)
+                .is_ok()
+        })
+    }
+
+    pub fn file_path(wallet_directory_path: &Path) -> PathBuf {
+        wallet_directory_path.join(SPEND_AUTHORIZATION_FILE_NAME)
+    }
+
+    /// Read the spend authorization, if one is set.
+    pub fn read_from_wallet_dir(wallet_directory_path: &Path) -> Result<Option<Self>> {
+        let path = Self::file_path(wallet_directory_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let json = fs::read_to_string(&path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        let authorization = serde_json::from_str(&json)
+            .with_context(|| format!("could not parse {}", path.display()))?;
+        Ok(Some(authorization))
+    }
+
+    /// Write the spend authorization, replacing any previous one.
+    pub fn store(&self, wallet_directory_path: &Path) -> Result<()> {
+        let path = Self::file_path(wallet_directory_path);
+        let mut options = fs::OpenOptions::new();
+        options.create(true).truncate(true).write(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::prelude::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+            .open(&path)
+            .with_context(|| format!("could not create {}", path.display()))?;
+        fs::write(&path, serde_json::to_string(self)?)
+            .with_context(|| format!("could not write {}", path.display()))
+    }
+
+    /// Remove the spend authorization, such that spends need no passphrase.
+    pub fn remove(wallet_directory_path: &Path) -> Result<()> {
+        let path = Self::file_path(wallet_directory_path);
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("could not remove {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod spend_authorization_tests {
+    use super::*;
+
+    #[test]
+    fn only_the_set_passphrase_is_accepted_after_round_trip() {
+        let wallet_dir =
+            std::env::temp_dir().join(format!("spend-authorization-{}", rand::random::<u64>()));
+        fs::create_dir_all(&wallet_dir).unwrap();
+        assert!(SpendAuthorization::read_from_wallet_dir(&wallet_dir)
+            .unwrap()
+            .is_none());
+
+        SpendAuthorization::new("correct horse")
+            .unwrap()
+            .store(&wallet_dir)
+            .unwrap();
+        let authorization = SpendAuthorization::read_from_wallet_dir(&wallet_dir)
+            .unwrap()
+            .unwrap();
+        assert!(authorization.verify("correct horse"));
+        assert!(!authorization.verify("battery staple"));
+        assert!(!authorization.verify(""));
+
+        SpendAuthorization::remove(&wallet_dir).unwrap();
+        assert!(SpendAuthorization::read_from_wallet_dir(&wallet_dir)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,58 @@
+//! Index from the absolute index sets of monitored UTXOs to their position in
+//! the wallet database.
+//!
+//! A removal record spends a monitored UTXO iff its absolute index set is that
+//! of the UTXO. The index set of a UTXO is fixed once it is confirmed, so it is
+//! computed once per monitored UTXO rather than for every scanned transaction.
+//! Monitored UTXOs are only ever appended, such that the index is brought up to
+//! date by indexing the ones added since the last scan.
+
+use std::collections::HashMap;
+
+use super::monitored_utxo::MonitoredUtxo;
+use crate::database::storage::storage_schema::DbtVec;
+use crate::database::storage::storage_vec::traits::*;
+use crate::database::storage::storage_vec::Index;
+use crate::util_types::mutator_set::removal_record::AbsoluteIndexSet;
+use crate::Hash;
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SpentUtxoIndex {
+    positions: HashMap<AbsoluteIndexSet, Index>,
+
+    /// Number of monitored UTXOs indexed so far
+    num_indexed: Index,
+}
+
+impl SpentUtxoIndex {
+    /// Index the monitored UTXOs added since the last call. If the list
+    /// shrank, which only happens when it is edited by hand, start over.
+    pub(crate) async fn sync(&mut self, monitored_utxos: &DbtVec<MonitoredUtxo>) {
+        let len = monitored_utxos.len().await;
+        if len < self.num_indexed {
+            *self = Self::default();
+        }
+
+        let new_positions = (self.num_indexed..len).collect::<Vec<_>>();
+        let new_mutxos = monitored_utxos.get_many(&new_positions).await;
+        for (position, mutxo) in new_positions.into_iter().zip(new_mutxos) {
+            self.insert(position, &mutxo);
+        }
+        self.num_indexed = len;
+    }
+
+    /// Index a monitored UTXO. UTXOs without membership proof have no index
+    /// set and are left out.
+    fn insert(&mut self, position: Index, mutxo: &MonitoredUtxo) {
+        if let Some((_, msmp)) = mutxo.get_latest_membership_proof_entry() {
+            let index_set = msmp.compute_indices(Hash::hash(&mutxo.utxo));
+            self.positions.insert(index_set, position);
+        }
+    }
+
+    /// Position of the monitored UTXO spent by a removal record with the
+    /// given index set, if any.
+    pub(crate) fn position_of(&self, index_set: &AbsoluteIndexSet) -> Option<Index> {
+        self.positions.get(index_set).copied()
+    }
+}
@@ -0,0 +1,261 @@
+//! Spending of change from the wallet's own unconfirmed transactions.
+//!
+//! Inputs of a transaction are proven to be in the mutator set, so an output
+//! can only be spent once it is confirmed. To chain payments without waiting
+//! for a block, the wallet projects the mutator set that results from applying
+//! its pending transactions to the tip, and proves its unconfirmed change
+//! against that projection.
+//!
+//! The projection is a guess: the block confirming the parents also contains
+//! other transactions, so a transaction built against the projection is not
+//! confirmable as is. Such a transaction is therefore held by the wallet, not
+//! relayed, and rebuilt against the tip once all its parents are confirmed. If
+//! a parent is dropped from the mempool without being confirmed, the
+//! transaction is invalid and is forgotten.
+
+use std::collections::HashMap;
+
+use anyhow::bail;
+use anyhow::Result;
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use twenty_first::math::digest::Digest;
+
+use crate::models::blockchain::transaction::transaction_kernel::TransactionKernel;
+use crate::models::blockchain::transaction::utxo::Utxo;
+use crate::models::blockchain::transaction::AnnouncedUtxo;
+use crate::models::state::transaction_kernel_id::TransactionKernelId;
+use crate::prelude::twenty_first;
+use crate::util_types::mutator_set::ms_membership_proof::MsMembershipProof;
+use crate::util_types::mutator_set::mutator_set_accumulator::MutatorSetAccumulator;
+use crate::util_types::mutator_set::removal_record::RemovalRecord;
+use crate::Hash;
+
+/// A transaction held by the wallet because it spends unconfirmed change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct UnconfirmedSpend {
+    pub txid: TransactionKernelId,
+
+    /// Transactions whose change is spent. All must be confirmed before this
+    /// transaction is relayed. If one is dropped instead, this transaction is
+    /// invalidated.
+    pub parents: Vec<TransactionKernelId>,
+}
+
+#[derive(Debug, Clone)]
+struct PendingParent {
+    kernel: TransactionKernel,
+    change: Vec<AnnouncedUtxo>,
+}
+
+/// The wallet's own transactions in the mempool that pay change to the wallet.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct UnconfirmedChange(HashMap<TransactionKernelId, PendingParent>);
+
+/// The mutator set after the wallet's pending transactions, along with
+/// membership proofs against it.
+#[derive(Debug, Clone)]
+pub(crate) struct Projection {
+    pub mutator_set_accumulator: MutatorSetAccumulator,
+
+    /// confirmed UTXOs not spent by the pending transactions
+    pub confirmed: Vec<(Utxo, MsMembershipProof)>,
+
+    /// unconfirmed change, along with the transaction paying it
+    pub change: Vec<(TransactionKernelId, Utxo, MsMembershipProof)>,
+}
+
+impl UnconfirmedChange {
+    /// Register an own transaction entering the mempool, with the outputs
+    /// that it pays to this wallet.
+    pub(crate) fn insert(&mut self, kernel: TransactionKernel, change: Vec<AnnouncedUtxo>) {
+        if change.is_empty() {
+            return;
+        }
+        self.0
+            .insert(kernel.txid(), PendingParent { kernel, change });
+    }
+
+    pub(crate) fn remove(&mut self, txid: TransactionKernelId) {
+        self.0.remove(&txid);
+    }
+
+    /// Track the mutator set update of a transaction in the mempool.
+    pub(crate) fn update_kernel(&mut self, kernel: TransactionKernel) {
+        if let Some(parent) = self.0.get_mut(&kernel.txid()) {
+            parent.kernel = kernel;
+        }
+    }
+
+    /// The pending transactions paying any of the given UTXOs.
+    pub(crate) fn parents_of(&self, utxos: &[Utxo]) -> Vec<TransactionKernelId> {
+        self.0
+            .iter()
+            .filter(|(_, parent)| {
+                parent
+                    .change
+                    .iter()
+                    .any(|change| utxos.contains(&change.utxo))
+            })
+            .map(|(txid, _)| *txid)
+            .sorted_by_key(|txid| txid.to_string())
+            .collect()
+    }
+
+    /// Apply the pending transactions synced to `tip_msa` to it, in the order
+    /// of their ids, and bring the given membership proofs of confirmed UTXOs
+    /// along.
+    pub(crate) fn project(
+        &self,
+        tip_msa: &MutatorSetAccumulator,
+        confirmed: Vec<(Utxo, MsMembershipProof)>,
+    ) -> Result<Projection> {
+        let tip_msa_hash = tip_msa.hash();
+        let parents = self
+            .0
+            .iter()
+            .filter(|(_, parent)| parent.kernel.mutator_set_hash == tip_msa_hash)
+            .sorted_by_key(|(txid, _)| txid.to_string())
+            .collect_vec();
+
+        let mut msa = tip_msa.clone();
+        let (mut utxos, mut membership_proofs): (Vec<_>, Vec<_>) = confirmed.into_iter().unzip();
+        let mut paid_by = vec![None; utxos.len()];
+        let mut removal_records = parents
+            .iter()
+            .flat_map(|(_, parent)| parent.kernel.inputs.clone())
+            .collect_vec();
+
+        for (txid, parent) in &parents {
+            for addition_record in &parent.kernel.outputs {
+                let items = utxos.iter().map(Hash::hash).collect_vec();
+                if MsMembershipProof::batch_update_from_addition(
+                    &mut membership_proofs.iter_mut().collect_vec(),
+                    &items,
+                    &msa,
+                    addition_record,
+                )
+                .is_err()
+                {
+                    bail!("Failed to project membership proofs over addition record");
+                }
+                RemovalRecord::batch_update_from_addition(
+                    &mut removal_records.iter_mut().collect_vec(),
+                    &msa,
+                );
+
+                if let Some(change) = parent
+                    .change
+                    .iter()
+                    .find(|change| change.addition_record == *addition_record)
+                {
+                    membership_proofs.push(msa.prove(
+                        Hash::hash(&change.utxo),
+                        change.sender_randomness,
+                        change.receiver_preimage,
+                    ));
+                    utxos.push(change.utxo.clone());
+                    paid_by.push(Some(**txid));
+                }
+                msa.add(addition_record);
+            }
+        }
+
+        removal_records.reverse();
+        while let Some(removal_record) = removal_records.pop() {
+            if MsMembershipProof::batch_update_from_remove(
+                &mut membership_proofs.iter_mut().collect_vec(),
+                &removal_record,
+            )
+            .is_err()
+            {
+                bail!("Failed to project membership proofs over removal record");
+            }
+            RemovalRecord::batch_update_from_remove(
+                &mut removal_records.iter_mut().collect_vec(),
+                &removal_record,
+            );
+            if !msa.can_remove(&removal_record) {
+                bail!("Pending transactions conflict with each other");
+            }
+            msa.remove(&removal_record);
+        }
+
+        let mut projection = Projection {
+            mutator_set_accumulator: msa,
+            confirmed: vec![],
+            change: vec![],
+        };
+        for ((utxo, membership_proof), paid_by) in
+            utxos.into_iter().zip(membership_proofs).zip(paid_by)
+        {
+            // UTXOs spent by a pending transaction
+            if !projection
+                .mutator_set_accumulator
+                .verify(Hash::hash(&utxo), &membership_proof)
+            {
+                continue;
+            }
+
+            match paid_by {
+                Some(txid) => projection.change.push((txid, utxo, membership_proof)),
+                None => projection.confirmed.push((utxo, membership_proof)),
+            }
+        }
+
+        Ok(projection)
+    }
+}
+
+#[cfg(test)]
+mod unconfirmed_change_tests {
+    use rand::random;
+
+    use super::*;
+    use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+    use crate::models::state::wallet::WalletSecret;
+    use crate::tests::shared::make_mock_transaction;
+    use crate::util_types::mutator_set::commit;
+
+    #[test]
+    fn change_of_pending_transaction_is_provable_against_projection() {
+        let key = WalletSecret::new_random().nth_generation_spending_key_for_tests(0);
+        let utxo = Utxo::new_native_currency(key.to_address().lock_script(), NeptuneCoins::new(1));
+        let sender_randomness: Digest = random();
+        let addition_record = commit(
+            Hash::hash(&utxo),
+            sender_randomness,
+            key.to_address().privacy_digest,
+        );
+
+        let tip_msa = MutatorSetAccumulator::default();
+        let mut parent = make_mock_transaction(vec![], vec![addition_record]);
+        parent.kernel.mutator_set_hash = tip_msa.hash();
+        let txid = parent.kernel.txid();
+
+        let mut unconfirmed_change = UnconfirmedChange::default();
+        unconfirmed_change.insert(
+            parent.kernel.clone(),
+            vec![AnnouncedUtxo {
+                addition_record,
+                utxo: utxo.clone(),
+                sender_randomness,
+                receiver_preimage: key.privacy_preimage,
+                memo: None,
+            }],
+        );
+        assert_eq!(vec![txid], unconfirmed_change.parents_of(&[utxo.clone()]));
+
+        let projection = unconfirmed_change.project(&tip_msa, vec![]).unwrap();
+        assert_eq!(1, projection.change.len());
+        assert_eq!(txid, projection.change[0].0);
+        assert!(projection
+            .mutator_set_accumulator
+            .verify(Hash::hash(&utxo), &projection.change[0].2));
+
+        unconfirmed_change.remove(txid);
+        assert!(unconfirmed_change.parents_of(&[utxo]).is_empty());
+    }
+}
@@ -2,6 +2,7 @@ use tasm_lib::triton_vm::prelude::Tip5;
 use tasm_lib::twenty_first::prelude::AlgebraicHasher;
 
 use super::address::SpendingKey;
+use crate::models::blockchain::transaction::lock_script::LockScript;
 use crate::models::blockchain::transaction::lock_script::LockScriptAndWitness;
 use crate::models::blockchain::transaction::utxo::Utxo;
 use crate::tasm_lib::Digest;
@@ -29,6 +30,20 @@ impl UnlockedUtxo {
         }
     }
 
+    /// A UTXO whose lock script is proven to halt elsewhere, without the
+    /// secret that unlocks it.
+    pub(crate) fn with_proven_lock_script(
+        utxo: Utxo,
+        lock_script: LockScript,
+        membership_proof: MsMembershipProof,
+    ) -> Self {
+        Self {
+            utxo,
+            lock_script_and_witness: LockScriptAndWitness::new(lock_script.program),
+            membership_proof,
+        }
+    }
+
     /// Return the `item` from the perspective of the mutator set
     pub fn mutator_set_item(&self) -> Digest {
         Tip5::hash(&self.utxo)
@@ -0,0 +1,83 @@
+//! Policy for when wallet database writes are synced to disk.
+//!
+//! Every write to the wallet database is atomic, whatever the policy: a crash
+//! never leaves a block half-applied to the wallet. The policy only decides how
+//! many of the most recent writes a power loss or operating system crash may
+//! lose, trading durability for fewer fsyncs when processing blocks with many
+//! own UTXOs. A crash of the node alone loses no writes under any policy.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WalletFsyncPolicy {
+    /// Sync every write.
+    #[default]
+    Always,
+
+    /// Sync once a block has been fully applied, instead of after each of
+    /// the writes it causes. Other writes are synced immediately.
+    PerBlock,
+
+    /// Sync every `--wallet-fsync-interval` seconds and on shutdown.
+    Periodic,
+}
+
+impl WalletFsyncPolicy {
+    /// Whether writes outside of block processing are synced immediately.
+    pub(crate) fn syncs_every_commit(&self) -> bool {
+        matches!(self, Self::Always | Self::PerBlock)
+    }
+
+    /// Whether each write made while applying a block is synced immediately.
+    pub(crate) fn syncs_block_updates(&self) -> bool {
+        matches!(self, Self::Always)
+    }
+}
+
+impl Display for WalletFsyncPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Always => "always",
+            Self::PerBlock => "per-block",
+            Self::Periodic => "periodic",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for WalletFsyncPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "always" => Ok(Self::Always),
+            "per-block" => Ok(Self::PerBlock),
+            "periodic" => Ok(Self::Periodic),
+            _ => Err(format!(
+                "unknown wallet fsync policy `{s}`; expected one of `always`, `per-block`, \
+                `periodic`"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod wallet_fsync_policy_tests {
+    use super::*;
+
+    #[test]
+    fn display_and_from_str_agree() {
+        for policy in [
+            WalletFsyncPolicy::Always,
+            WalletFsyncPolicy::PerBlock,
+            WalletFsyncPolicy::Periodic,
+        ] {
+            assert_eq!(Ok(policy), policy.to_string().parse());
+        }
+        assert!("sometimes".parse::<WalletFsyncPolicy>().is_err());
+    }
+}
@@ -0,0 +1,30 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use twenty_first::math::tip5::Digest;
+
+use crate::models::blockchain::transaction::memo::Memo;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::prelude::twenty_first;
+use crate::util_types::json_schema::DigestSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum MemoDirection {
+    Sent,
+    Received,
+}
+
+/// A memo attached to a UTXO this wallet sent or received.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletMemo {
+    #[schemars(with = "DigestSchema")]
+    pub utxo_digest: Digest,
+    pub amount: NeptuneCoins,
+    pub direction: MemoDirection,
+
+    /// time the transaction was sent, or time of the block the UTXO was
+    /// received in
+    pub timestamp: Timestamp,
+    pub memo: Memo,
+}
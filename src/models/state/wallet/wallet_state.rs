@@ -2,17 +2,24 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use std::error::Error;
 use std::fmt::Debug;
+use std::path::Path;
 use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
 
 use anyhow::bail;
 use anyhow::Result;
 use itertools::Itertools;
 use num_traits::CheckedSub;
 use num_traits::Zero;
+use rayon::iter::IntoParallelIterator;
+use rayon::iter::IntoParallelRefIterator;
+use rayon::iter::ParallelIterator;
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
 use tokio::fs::OpenOptions;
 use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncSeekExt;
 use tokio::io::AsyncWriteExt;
 use tokio::io::BufReader;
 use tokio::io::BufWriter;
@@ -28,24 +35,52 @@ use twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
 use super::address::generation_address;
 use super::address::symmetric_key;
 use super::address::KeyType;
+use super::address::ReceivingAddress;
 use super::address::SpendingKey;
+use super::address::ViewingKey;
+use super::audit_export::AuditedUtxo;
+use super::audit_export::WalletAuditExport;
+use super::coin_selection::CoinCandidate;
+use super::coin_selection::CoinSelectionPolicy;
 use super::coin_with_possible_timelock::CoinWithPossibleTimeLock;
+use super::encrypted_secret::EncryptedWalletSecret;
+use super::encrypted_secret::WalletEncryption;
 use super::expected_utxo::ExpectedUtxo;
 use super::expected_utxo::UtxoNotifier;
+use super::expected_utxo_bundle::dedup_expected_utxos;
+use super::expected_utxo_bundle::ExpectedUtxoBundle;
+use super::expected_utxo_bundle::ExpectedUtxoBundleError;
+use super::expected_utxo_bundle::ExpectedUtxoImport;
+use super::hd_derivation::AccountDescriptor;
+use super::hd_derivation::DerivationChain;
+use super::hd_derivation::DerivationPath;
+use super::hd_derivation::ACCOUNT_KEY_LOOKAHEAD;
+use super::own_transactions::OwnTransactions;
 use super::rusty_wallet_database::RustyWalletDatabase;
+use super::spend_authorization::SpendAuthorization;
+use super::spent_utxo_index::SpentUtxoIndex;
+use super::unconfirmed_change::UnconfirmedChange;
 use super::unlocked_utxo::UnlockedUtxo;
+use super::wallet_memo::MemoDirection;
+use super::wallet_memo::WalletMemo;
 use super::wallet_status::WalletStatus;
 use super::wallet_status::WalletStatusElement;
+use super::wallet_summary::WalletAggregates;
+use super::wallet_summary::WalletSummary;
+use super::watch_only::WatchOnlyAddresses;
 use super::WalletSecret;
 use super::WALLET_INCOMING_SECRETS_FILE_NAME;
 use crate::config_models::cli_args::Args;
 use crate::config_models::data_directory::DataDirectory;
+use crate::config_models::network::Network;
 use crate::database::storage::storage_schema::traits::*;
 use crate::database::storage::storage_schema::DbtVec;
 use crate::database::storage::storage_vec::traits::*;
 use crate::database::storage::storage_vec::Index;
 use crate::database::NeptuneLevelDb;
+use crate::models::blockchain::block::block_height::BlockHeight;
 use crate::models::blockchain::block::Block;
+use crate::models::blockchain::transaction::memo::Memo;
 use crate::models::blockchain::transaction::transaction_kernel::TransactionKernel;
 use crate::models::blockchain::transaction::transaction_output::TxOutputList;
 use crate::models::blockchain::transaction::utxo::Utxo;
@@ -55,7 +90,10 @@ use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
 use crate::models::proof_abstractions::tasm::program::ConsensusProgram;
 use crate::models::proof_abstractions::timestamp::Timestamp;
 use crate::models::state::mempool::MempoolEvent;
+use crate::models::state::wallet::coinbase_address_rotation::CoinbaseAddressRotation;
 use crate::models::state::wallet::monitored_utxo::MonitoredUtxo;
+use crate::models::state::wallet::ms_update_window::AnchorAdvance;
+use crate::models::state::wallet::ms_update_window::MsUpdateWindow;
 use crate::prelude::twenty_first;
 use crate::util_types::mutator_set::addition_record::AdditionRecord;
 use crate::util_types::mutator_set::ms_membership_proof::MsMembershipProof;
@@ -64,6 +102,10 @@ use crate::util_types::mutator_set::removal_record::AbsoluteIndexSet;
 use crate::util_types::mutator_set::removal_record::RemovalRecord;
 use crate::Hash;
 
+/// Number of consecutive unused derivation indices after which the scan for
+/// generation keys that received funds stops.
+const GENERATION_KEY_GAP_LIMIT: u64 = 1000;
+
 pub struct WalletState {
     pub wallet_db: RustyWalletDatabase,
     pub wallet_secret: WalletSecret,
@@ -74,6 +116,55 @@ pub struct WalletState {
     /// key is Tx hash.  for removing watched utxos when a tx is removed from mempool.
     mempool_spent_utxos: HashMap<Digest, Vec<(Utxo, AbsoluteIndexSet, u64)>>,
     mempool_unspent_utxos: HashMap<Digest, Vec<AnnouncedUtxo>>,
+
+    /// monitored utxos by absolute index set, to find those spent by a
+    /// transaction without scanning them all.
+    spent_utxo_index: SpentUtxoIndex,
+
+    /// transactions sent by this wallet, kept so they can be rebuilt and
+    /// re-broadcast if a reorganization un-confirms them.
+    pub(crate) own_transactions: OwnTransactions,
+
+    /// mutator set updates of the blocks above the anchor, the block to which
+    /// membership proofs are kept synced in addition to the tip.
+    pub(crate) ms_update_window: MsUpdateWindow,
+
+    /// generation keys handed out by this wallet, by derivation index. The
+    /// number of keys is persisted as the generation key counter. Empty while
+    /// the wallet is locked.
+    generation_spending_keys: Vec<generation_address::GenerationSpendingKey>,
+
+    /// generation keys of the accounts whose descriptors were exported, up to
+    /// [`ACCOUNT_KEY_LOOKAHEAD`] keys per chain. Empty while the wallet is
+    /// locked.
+    account_spending_keys: Vec<generation_address::GenerationSpendingKey>,
+
+    /// own transactions in the mempool that pay change to this wallet, which
+    /// may be spent before they are confirmed.
+    pub(crate) unconfirmed_change: UnconfirmedChange,
+
+    /// passphrase required to spend over RPC, if set
+    pub(crate) spend_authorization: Option<SpendAuthorization>,
+
+    /// aggregates for the wallet summary, computed on demand and kept until
+    /// the tip or the wallet's utxos change.
+    summary_aggregates: std::sync::Mutex<Option<WalletAggregates>>,
+
+    /// the addresses watched by a watch-only wallet, which holds no keys and
+    /// cannot spend. See [watch_only](super::watch_only).
+    watch_only: Option<WatchOnlyAddresses>,
+
+    /// the first symmetric key, derived once. None while the wallet is locked
+    /// and for a watch-only wallet.
+    symmetric_key: Option<symmetric_key::SymmetricKey>,
+
+    /// the viewing keys of all known keys, with which a locked wallet keeps
+    /// recognizing incoming UTXOs. Empty while the wallet is unlocked.
+    viewing_keys: Vec<ViewingKey>,
+
+    /// lock state of a wallet whose secret is encrypted at rest. See
+    /// [encrypted_secret](super::encrypted_secret).
+    encryption: Option<WalletEncryption>,
 }
 
 /// Contains the cryptographic (non-public) data that is needed to recover the mutator set
@@ -107,11 +198,17 @@ impl Debug for WalletState {
             .field("wallet_secret", &self.wallet_secret)
             .field("number_of_mps_per_utxo", &self.number_of_mps_per_utxo)
             .field("wallet_directory_path", &self.wallet_directory_path)
+            .field("watch_only", &self.watch_only)
+            .field("encryption", &self.encryption)
             .finish()
     }
 }
 
 impl WalletState {
+    pub(crate) fn wallet_directory_path(&self) -> &Path {
+        &self.wallet_directory_path
+    }
+
     fn incoming_secrets_path(&self) -> PathBuf {
         self.wallet_directory_path
             .join(WALLET_INCOMING_SECRETS_FILE_NAME)
@@ -160,11 +257,23 @@ impl WalletState {
     ///
     /// Uses non-blocking I/O via tokio.
     pub(crate) async fn read_utxo_ms_recovery_data(&self) -> Result<Vec<IncomingUtxoRecoveryData>> {
-        let incoming_secrets_file = OpenOptions::new()
+        self.read_utxo_ms_recovery_data_from(0).await
+    }
+
+    /// Read the recovery-information written after the first `offset` bytes
+    /// of the file.
+    pub(crate) async fn read_utxo_ms_recovery_data_from(
+        &self,
+        offset: u64,
+    ) -> Result<Vec<IncomingUtxoRecoveryData>> {
+        let mut incoming_secrets_file = OpenOptions::new()
             .read(true)
             .write(false)
             .open(self.incoming_secrets_path())
             .await?;
+        incoming_secrets_file
+            .seek(std::io::SeekFrom::Start(offset))
+            .await?;
 
         let file_reader = BufReader::new(incoming_secrets_file);
         let mut ret = vec![];
@@ -178,10 +287,55 @@ impl WalletState {
         Ok(ret)
     }
 
+    /// Length in bytes of the recovery-information written so far.
+    pub(crate) async fn recovery_data_len(&self) -> u64 {
+        tokio::fs::metadata(self.incoming_secrets_path())
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or_default()
+    }
+
     pub async fn new_from_wallet_secret(
         data_dir: &DataDirectory,
         wallet_secret: WalletSecret,
         cli_args: &Args,
+    ) -> Self {
+        Self::new(data_dir, wallet_secret, None, cli_args).await
+    }
+
+    /// A wallet whose secret is encrypted at rest. The secret is decrypted to
+    /// derive the keys needed to receive, after which the wallet is locked.
+    pub async fn new_from_encrypted_wallet_secret(
+        data_dir: &DataDirectory,
+        encrypted: EncryptedWalletSecret,
+        passphrase: &str,
+        cli_args: &Args,
+    ) -> Result<Self> {
+        let wallet_secret = encrypted.decrypt(passphrase)?;
+        let mut wallet_state = Self::new(data_dir, wallet_secret, None, cli_args).await;
+        wallet_state.enable_encryption(encrypted);
+        Ok(wallet_state)
+    }
+
+    /// A wallet that watches the given addresses, but holds none of their
+    /// keys and refuses to spend.
+    pub async fn new_watch_only(
+        data_dir: &DataDirectory,
+        addresses: WatchOnlyAddresses,
+        cli_args: &Args,
+    ) -> Self {
+        // The secret is never persisted and no key derived from it is ever
+        // handed out. It only serves as a source of randomness, e.g. for
+        // shuffling.
+        let wallet_secret = WalletSecret::new_random();
+        Self::new(data_dir, wallet_secret, Some(addresses), cli_args).await
+    }
+
+    async fn new(
+        data_dir: &DataDirectory,
+        wallet_secret: WalletSecret,
+        watch_only: Option<WatchOnlyAddresses>,
+        cli_args: &Args,
     ) -> Self {
         // Create or connect to wallet block DB
 
@@ -201,8 +355,12 @@ impl WalletState {
             }
         };
 
-        let rusty_wallet_database = RustyWalletDatabase::connect(wallet_db).await;
+        let mut rusty_wallet_database = RustyWalletDatabase::connect(wallet_db).await;
+        rusty_wallet_database.set_fsync_policy(cli_args.wallet_fsync);
         let sync_label = rusty_wallet_database.get_sync_label().await;
+        let symmetric_key = watch_only
+            .is_none()
+            .then(|| wallet_secret.nth_symmetric_key(0));
 
         let mut wallet_state = Self {
             wallet_db: rusty_wallet_database,
@@ -211,8 +369,41 @@ impl WalletState {
             wallet_directory_path: data_dir.wallet_directory_path(),
             mempool_spent_utxos: Default::default(),
             mempool_unspent_utxos: Default::default(),
+            spent_utxo_index: Default::default(),
+            own_transactions: Default::default(),
+            ms_update_window: MsUpdateWindow::new(cli_args.membership_proof_anchor_depth),
+            generation_spending_keys: vec![],
+            account_spending_keys: vec![],
+            unconfirmed_change: Default::default(),
+            spend_authorization: SpendAuthorization::read_from_wallet_dir(
+                &data_dir.wallet_directory_path(),
+            )
+            .expect("Spend authorization file must be readable"),
+            summary_aggregates: Default::default(),
+            watch_only,
+            symmetric_key,
+            viewing_keys: vec![],
+            encryption: None,
         };
 
+        if wallet_state.is_watch_only() {
+            if sync_label == Digest::default() {
+                wallet_state
+                    .update_wallet_state_with_new_block(
+                        &MutatorSetAccumulator::default(),
+                        &Block::genesis_block(cli_args.network),
+                    )
+                    .await
+                    .expect("Updating wallet state with genesis block must succeed");
+            }
+
+            return wallet_state;
+        }
+
+        wallet_state
+            .derive_known_keys(cli_args.coinbase_address_rotation.min_num_generation_keys())
+            .await;
+
         // Wallet state has to be initialized with the genesis block, otherwise the outputs
         // from genesis would be unspendable. This should only be done *once* though.
         // This also ensures that any premine outputs are added to the file containing the
@@ -259,14 +450,14 @@ impl WalletState {
             .iter()
             .filter(|txo| txo.is_offchain())
             .filter_map(|txo| {
-                self.find_spending_key_for_utxo(&txo.utxo())
-                    .map(|sk| (txo, sk))
+                self.find_receiver_preimage_for_utxo(&txo.utxo())
+                    .map(|preimage| (txo, preimage))
             })
-            .map(|(tx_output, spending_key)| {
+            .map(|(tx_output, receiver_preimage)| {
                 ExpectedUtxo::new(
                     tx_output.utxo(),
                     tx_output.sender_randomness(),
-                    spending_key.privacy_preimage(),
+                    receiver_preimage,
                     notifier,
                 )
             })
@@ -301,6 +492,12 @@ impl WalletState {
                     .chain(self.scan_for_expected_utxos(&tx.kernel).await)
                     .collect_vec();
 
+                // Change of own transactions may be spent before confirmation.
+                if !spent_utxos.is_empty() {
+                    self.unconfirmed_change
+                        .insert(tx.kernel.clone(), announced_utxos.clone());
+                }
+
                 let tx_hash = Hash::hash(&tx);
                 self.mempool_spent_utxos.insert(tx_hash, spent_utxos);
                 self.mempool_unspent_utxos.insert(tx_hash, announced_utxos);
@@ -310,9 +507,12 @@ impl WalletState {
                 let tx_hash = Hash::hash(&tx);
                 self.mempool_spent_utxos.remove(&tx_hash);
                 self.mempool_unspent_utxos.remove(&tx_hash);
+                self.unconfirmed_change.remove(tx.kernel.txid());
             }
-            MempoolEvent::UpdateTxMutatorSet(_tx_hash_pre_update, _tx_post_update) => {
-                // Utxos are not affected by MutatorSet update, so this is a no-op.
+            MempoolEvent::UpdateTxMutatorSet(_tx_hash_pre_update, tx_post_update) => {
+                // Utxos are not affected by MutatorSet update, but the
+                // removal records of unconfirmed change are.
+                self.unconfirmed_change.update_kernel(tx_post_update.kernel);
             }
         }
     }
@@ -324,6 +524,29 @@ impl WalletState {
             .map(|(utxo, ..)| utxo)
     }
 
+    /// Whether an own transaction spends a UTXO that was spent after its
+    /// inputs were selected, on chain or by another own transaction in the
+    /// mempool. Inputs spending unconfirmed change are only checked against
+    /// the mempool, as they are not in the tip's mutator set yet.
+    pub(crate) fn spends_spent_inputs(
+        &self,
+        kernel: &TransactionKernel,
+        tip_mutator_set_accumulator: &MutatorSetAccumulator,
+    ) -> bool {
+        let spent_in_mempool: Vec<&AbsoluteIndexSet> = self
+            .mempool_spent_utxos
+            .values()
+            .flatten()
+            .map(|(_, absolute_index_set, _)| absolute_index_set)
+            .collect();
+
+        kernel.inputs.iter().any(|removal_record| {
+            spent_in_mempool.contains(&removal_record.absolute_indices)
+                || (removal_record.validate(tip_mutator_set_accumulator)
+                    && !tip_mutator_set_accumulator.can_remove(removal_record))
+        })
+    }
+
     pub fn mempool_unspent_utxos_iter(&self) -> impl Iterator<Item = &Utxo> {
         self.mempool_unspent_utxos
             .values()
@@ -371,6 +594,51 @@ impl WalletState {
             .await;
     }
 
+    /// Import the UTXOs of a verified bundle as expected UTXOs, skipping those
+    /// already expected. Imports nothing if any UTXO is not paid to a known
+    /// key or does not match its addition record.
+    /// Add those of `expected_utxos` that the wallet does not expect yet.
+    pub(crate) async fn add_new_expected_utxos(&mut self, expected_utxos: Vec<ExpectedUtxo>) {
+        let known = self
+            .wallet_db
+            .expected_utxos()
+            .get_all()
+            .await
+            .into_iter()
+            .map(|eu| eu.addition_record)
+            .collect();
+        let (new_expected_utxos, _duplicates) = dedup_expected_utxos(expected_utxos, &known);
+        for expected_utxo in new_expected_utxos {
+            self.add_expected_utxo(expected_utxo).await;
+        }
+    }
+
+    pub(crate) async fn import_expected_utxo_bundle(
+        &mut self,
+        bundle: &ExpectedUtxoBundle,
+    ) -> Result<ExpectedUtxoImport, ExpectedUtxoBundleError> {
+        let expected_utxos = bundle.expected_utxos(&self.get_all_known_viewing_keys())?;
+        let known = self
+            .wallet_db
+            .expected_utxos()
+            .get_all()
+            .await
+            .into_iter()
+            .map(|eu| eu.addition_record)
+            .collect();
+        let (new_expected_utxos, duplicates) = dedup_expected_utxos(expected_utxos, &known);
+
+        let imported = new_expected_utxos.len();
+        for expected_utxo in new_expected_utxos {
+            self.add_expected_utxo(expected_utxo).await;
+        }
+
+        Ok(ExpectedUtxoImport {
+            imported,
+            duplicates,
+        })
+    }
+
     // If any output UTXO(s) are going back to our wallet (eg change utxo)
     // we add them to pool of expected incoming UTXOs so that we can
     // synchronize them after the Tx is confirmed.
@@ -393,30 +661,26 @@ impl WalletState {
 
     /// Return a list of UTXOs spent by this wallet in the transaction
     async fn scan_for_spent_utxos(
-        &self,
+        &mut self,
         transaction_kernel: &TransactionKernel,
     ) -> Vec<(Utxo, AbsoluteIndexSet, u64)> {
-        let confirmed_absolute_index_sets = transaction_kernel
+        let monitored_utxos = self.wallet_db.monitored_utxos();
+        self.spent_utxo_index.sync(monitored_utxos).await;
+
+        let spent_positions = transaction_kernel
             .inputs
             .iter()
-            .map(|rr| rr.absolute_indices)
+            .filter_map(|rr| {
+                self.spent_utxo_index
+                    .position_of(&rr.absolute_indices)
+                    .map(|i| (rr.absolute_indices, i))
+            })
             .collect_vec();
 
-        let monitored_utxos = self.wallet_db.monitored_utxos();
         let mut spent_own_utxos = vec![];
-
-        let stream = monitored_utxos.stream().await;
-        pin_mut!(stream); // needed for iteration
-
-        while let Some((i, monitored_utxo)) = stream.next().await {
-            let abs_i = match monitored_utxo.get_latest_membership_proof_entry() {
-                Some(msmp) => msmp.1.compute_indices(Hash::hash(&monitored_utxo.utxo)),
-                None => continue,
-            };
-
-            if confirmed_absolute_index_sets.contains(&abs_i) {
-                spent_own_utxos.push((monitored_utxo.utxo, abs_i, i));
-            }
+        for (abs_i, i) in spent_positions {
+            let monitored_utxo = monitored_utxos.get(i).await;
+            spent_own_utxos.push((monitored_utxo.utxo, abs_i, i));
         }
         spent_own_utxos
     }
@@ -428,22 +692,45 @@ impl WalletState {
         &'a self,
         tx_kernel: &'a TransactionKernel,
     ) -> impl Iterator<Item = AnnouncedUtxo> + 'a {
-        // scan for announced utxos for every known key of every key type.
-        self.get_all_known_spending_keys()
+        self.scan_batch_for_announced_utxos(&[tx_kernel])
+            .pop()
+            .unwrap_or_default()
             .into_iter()
-            .flat_map(|key| key.scan_for_announced_utxos(tx_kernel).collect_vec())
-
-            // filter for presence in transaction
-            //
-            // note: this is a nice sanity check, but probably is un-necessary
-            //       work that can eventually be removed.
-            .filter(|au| match tx_kernel.outputs.contains(&au.addition_record) {
-                true => true,
-                false => {
-                    warn!("Transaction does not contain announced UTXO encrypted to own receiving address. Announced UTXO was: {:#?}", au.utxo);
-                    false
-                }
+    }
+
+    /// Like [Self::scan_for_announced_utxos], for a batch of transactions, e.g.
+    /// those of the blocks of a sync batch.
+    ///
+    /// Returns the announced UTXOs of each transaction, in the order of
+    /// `tx_kernels`.
+    pub fn scan_batch_for_announced_utxos(
+        &self,
+        tx_kernels: &[&TransactionKernel],
+    ) -> Vec<Vec<AnnouncedUtxo>> {
+        // scan for announced utxos for every known key of every key type.
+        //
+        // Trial decryption dominates the cost of scanning a block, so
+        // transactions and keys are scanned in parallel. Results are collected
+        // in transaction order and, per transaction, in key order, such that
+        // the outcome does not depend on thread scheduling.
+        //
+        // Only the scan runs in parallel. Applying a block updates the
+        // membership proofs of the UTXOs found in earlier blocks, so blocks are
+        // still applied in order.
+        //
+        // A locked wallet holds no spending keys, and scans with the viewing
+        // keys it kept instead.
+        if self.is_locked() {
+            scan_batch_with_keys(&self.viewing_keys, tx_kernels, |key, tx_kernel| {
+                key.scan_for_announced_utxos(tx_kernel).collect_vec()
             })
+        } else {
+            scan_batch_with_keys(
+                &self.get_all_known_spending_keys(),
+                tx_kernels,
+                |key, tx_kernel| key.scan_for_announced_utxos(tx_kernel).collect_vec(),
+            )
+        }
     }
 
     /// Scan the transaction for outputs that match with list of expected
@@ -528,20 +815,50 @@ impl WalletState {
     }
 
     // returns true if the utxo can be unlocked by one of the
-    // known wallet keys.
+    // known wallet keys, also while the wallet is locked.
     pub fn can_unlock(&self, utxo: &Utxo) -> bool {
-        self.find_spending_key_for_utxo(utxo).is_some()
+        self.find_receiver_preimage_for_utxo(utxo).is_some()
+    }
+
+    // returns the privacy preimage of the known wallet key that can unlock the
+    // utxo, if any. Known also while the wallet is locked.
+    pub(crate) fn find_receiver_preimage_for_utxo(&self, utxo: &Utxo) -> Option<Digest> {
+        if self.is_locked() {
+            return self
+                .viewing_keys
+                .par_iter()
+                .find_first(|k| k.address().lock_script().hash() == utxo.lock_script_hash)
+                .map(|k| k.privacy_preimage());
+        }
+
+        self.find_spending_key_for_utxo(utxo)
+            .map(|k| k.privacy_preimage())
     }
 
     // returns Some(SpendingKey) if the utxo can be unlocked by one of the known
-    // wallet keys.
+    // wallet keys. Keys are matched in parallel; if several match, the first
+    // known key is returned. Always None while the wallet is locked.
     pub fn find_spending_key_for_utxo(&self, utxo: &Utxo) -> Option<SpendingKey> {
         self.get_all_known_spending_keys()
-            .into_iter()
-            .find(|k| k.to_address().lock_script().hash() == utxo.lock_script_hash)
+            .into_par_iter()
+            .find_first(|k| k.to_address().lock_script().hash() == utxo.lock_script_hash)
+    }
+
+    /// returns the viewing keys of all key types: those kept while the wallet
+    /// is locked, or else those of all known spending keys.
+    pub(crate) fn get_all_known_viewing_keys(&self) -> Vec<ViewingKey> {
+        if self.is_locked() {
+            return self.viewing_keys.clone();
+        }
+
+        self.get_all_known_spending_keys()
+            .into_par_iter()
+            .map(ViewingKey::from)
+            .collect()
     }
 
-    /// returns all spending keys of all key types with derivation index less than current counter
+    /// returns all spending keys of all key types with derivation index less
+    /// than current counter. Empty while the wallet is locked.
     pub fn get_all_known_spending_keys(&self) -> Vec<SpendingKey> {
         KeyType::all_types()
             .into_iter()
@@ -557,18 +874,272 @@ impl WalletState {
         }
     }
 
-    // TODO: These spending keys should probably be derived dynamically from some
-    // state in the wallet. And we should allow for other types than just generation
-    // addresses.
-    //
-    // Probably the wallet should keep track of index of latest derived key
-    // that has been requested by the user for purpose of receiving
-    // funds.  We could also perform a sequential scan at startup (or import)
-    // of keys that have received funds, up to some "gap".  In bitcoin/bip32
-    // this gap is defined as 20 keys in a row that have never received funds.
+    /// returns all generation keys handed out by this wallet. Keys are derived
+    /// once, when they are handed out or when the wallet is loaded.
     fn get_known_generation_spending_keys(&self) -> Vec<SpendingKey> {
-        // for now we always return just the 1st key.
-        vec![self.wallet_secret.nth_generation_spending_key(0).into()]
+        self.generation_spending_keys
+            .iter()
+            .chain(&self.account_spending_keys)
+            .copied()
+            .map(SpendingKey::from)
+            .collect()
+    }
+
+    /// Derive the generation keys up to the persisted counter, at least
+    /// `min_num_generation_keys`, and the keys of the exported accounts.
+    async fn derive_known_keys(&mut self, min_num_generation_keys: u64) {
+        let num_generation_keys = self
+            .wallet_db
+            .get_generation_key_counter()
+            .await
+            .max(min_num_generation_keys);
+        self.ensure_generation_keys(num_generation_keys).await;
+        for account in self.wallet_db.exported_accounts().get_all().await {
+            self.derive_account_keys(account);
+        }
+    }
+
+    /// Derive and persist generation keys until at least `num_keys` keys are
+    /// known. At least the first key is always known.
+    async fn ensure_generation_keys(&mut self, num_keys: u64) {
+        let num_keys = num_keys.clamp(1, u64::from(u16::MAX) + 1);
+        if self.is_locked() && (self.generation_spending_keys.len() as u64) < num_keys {
+            warn!("Cannot derive generation keys while the wallet is locked");
+            return;
+        }
+
+        while (self.generation_spending_keys.len() as u64) < num_keys {
+            let index = self.generation_spending_keys.len() as u16;
+            self.generation_spending_keys
+                .push(self.wallet_secret.nth_generation_spending_key(index));
+        }
+
+        if self.wallet_db.get_generation_key_counter().await < num_keys {
+            self.wallet_db.set_generation_key_counter(num_keys).await;
+            self.wallet_db.persist().await;
+        }
+    }
+
+    /// Derive the keys of an account for which incoming UTXOs are recognized.
+    fn derive_account_keys(&mut self, account: u32) {
+        let keys = DerivationChain::all().into_iter().flat_map(|chain| {
+            (0..ACCOUNT_KEY_LOOKAHEAD).map(move |index| DerivationPath {
+                account,
+                chain,
+                index,
+            })
+        });
+        let keys = keys
+            .collect_vec()
+            .into_par_iter()
+            .map(|path| self.wallet_secret.generation_spending_key_at(path))
+            .collect::<Vec<_>>();
+        self.account_spending_keys.extend(keys);
+    }
+
+    /// The descriptor of the given account, from which external tools can
+    /// derive the account's receiving addresses. From then on, the wallet
+    /// recognizes UTXOs sent to the first [`ACCOUNT_KEY_LOOKAHEAD`] addresses
+    /// of each chain of the account.
+    pub(crate) async fn export_account_descriptor(
+        &mut self,
+        network: Network,
+        account: u32,
+    ) -> Result<AccountDescriptor> {
+        if self.is_watch_only() {
+            bail!("watch-only wallet has no accounts to export");
+        }
+        if self.is_locked() {
+            bail!("wallet is locked");
+        }
+
+        let exported_accounts = self.wallet_db.exported_accounts().get_all().await;
+        if !exported_accounts.contains(&account) {
+            self.derive_account_keys(account);
+            self.wallet_db.exported_accounts_mut().push(account).await;
+            self.wallet_db.persist().await;
+        }
+
+        Ok(self.wallet_secret.account_descriptor(network, account))
+    }
+
+    /// Hand out a new generation key, persisting the updated counter such that
+    /// the wallet keeps recognizing UTXOs sent to it. None while the wallet is
+    /// locked.
+    pub(crate) async fn derive_new_generation_key(
+        &mut self,
+    ) -> Option<generation_address::GenerationSpendingKey> {
+        if self.is_locked() {
+            warn!("Cannot derive generation keys while the wallet is locked");
+            return None;
+        }
+
+        let num_keys = self.generation_spending_keys.len() as u64 + 1;
+        self.ensure_generation_keys(num_keys).await;
+        self.generation_spending_keys.last().copied()
+    }
+
+    /// The generation key that the coinbase of the block at the given height
+    /// is paid to, under the given rotation policy.
+    pub(crate) fn coinbase_spending_key(
+        &self,
+        rotation: CoinbaseAddressRotation,
+        block_height: BlockHeight,
+    ) -> generation_address::GenerationSpendingKey {
+        let index = rotation.key_index(self.generation_spending_keys.len() as u64, block_height);
+        self.generation_spending_keys
+            .get(usize::from(index))
+            .copied()
+            .unwrap_or_else(|| self.wallet_secret.nth_generation_spending_key(index))
+    }
+
+    /// Replace the wallet secret, e.g. when a key rotation finishes.
+    pub(crate) fn set_wallet_secret(&mut self, wallet_secret: WalletSecret) {
+        self.symmetric_key = Some(wallet_secret.nth_symmetric_key(0));
+        self.wallet_secret = wallet_secret;
+    }
+
+    /// Whether the wallet secret is encrypted at rest.
+    pub fn is_encrypted(&self) -> bool {
+        self.encryption.is_some()
+    }
+
+    /// Whether the wallet secret is encrypted and currently not in memory.
+    pub fn is_locked(&self) -> bool {
+        self.encryption
+            .as_ref()
+            .is_some_and(|encryption| encryption.is_locked())
+    }
+
+    /// Keep the wallet secret encrypted at rest, and lock the wallet.
+    pub(crate) fn enable_encryption(&mut self, encrypted: EncryptedWalletSecret) {
+        self.encryption = Some(WalletEncryption::unlocked(encrypted));
+        self.lock();
+    }
+
+    /// Zeroize the wallet secret in memory and drop all spending keys derived
+    /// from it, keeping only their viewing keys. Returns false if the wallet is
+    /// not encrypted, in which case the secret stays.
+    pub(crate) fn lock(&mut self) -> bool {
+        if self.encryption.is_none() {
+            return false;
+        }
+
+        if !self.is_locked() {
+            self.viewing_keys = self.get_all_known_viewing_keys();
+        }
+        self.generation_spending_keys.clear();
+        self.account_spending_keys.clear();
+        self.symmetric_key = None;
+        if let Some(encryption) = &mut self.encryption {
+            encryption.lock(&mut self.wallet_secret);
+        }
+        true
+    }
+
+    /// Decrypt the wallet secret, until `duration` has passed if given, and
+    /// derive the known spending keys again.
+    pub(crate) async fn unlock(
+        &mut self,
+        passphrase: &str,
+        duration: Option<Duration>,
+    ) -> Result<()> {
+        let Some(encryption) = &mut self.encryption else {
+            bail!("wallet is not encrypted");
+        };
+        let was_locked = encryption.is_locked();
+        self.wallet_secret = encryption.unlock(passphrase, duration)?;
+
+        if was_locked {
+            self.symmetric_key = Some(self.wallet_secret.nth_symmetric_key(0));
+            self.derive_known_keys(0).await;
+            self.viewing_keys.clear();
+        }
+        Ok(())
+    }
+
+    /// Lock the wallet if it was unlocked for a duration that has passed.
+    pub(crate) fn relock_if_due(&mut self) {
+        if self
+            .encryption
+            .as_ref()
+            .is_some_and(|encryption| encryption.relock_due(Instant::now()))
+        {
+            info!("Wallet unlock expired; locking wallet");
+            self.lock();
+        }
+    }
+
+    /// Whether this wallet only watches addresses, without holding their keys.
+    pub fn is_watch_only(&self) -> bool {
+        self.watch_only.is_some()
+    }
+
+    /// The addresses watched by a watch-only wallet.
+    pub fn watched_addresses(&self) -> Option<&WatchOnlyAddresses> {
+        self.watch_only.as_ref()
+    }
+
+    /// Whether spends requested over RPC must carry a passphrase.
+    pub fn spend_passphrase_required(&self) -> bool {
+        self.spend_authorization.is_some()
+    }
+
+    /// Whether a spend requested over RPC with the given passphrase is
+    /// authorized. Any spend is, if no spend passphrase is set.
+    pub(crate) fn spend_authorized(&self, passphrase: Option<&str>) -> bool {
+        match &self.spend_authorization {
+            None => true,
+            Some(authorization) => passphrase.is_some_and(|p| authorization.verify(p)),
+        }
+    }
+
+    /// Scan derivation indices beyond the known generation keys for keys that
+    /// can unlock any of the given UTXOs, and mark the keys up to the last
+    /// match as known. The scan stops after [`GENERATION_KEY_GAP_LIMIT`]
+    /// consecutive indices without a match. Returns the number of keys
+    /// discovered.
+    pub(crate) async fn discover_generation_keys(&mut self, utxos: &[Utxo]) -> u64 {
+        if self.is_watch_only() {
+            return 0;
+        }
+        if self.is_locked() {
+            warn!("Not scanning for generation keys while the wallet is locked");
+            return 0;
+        }
+
+        let mut unmatched: HashSet<Digest> = utxos
+            .iter()
+            .filter(|utxo| !self.can_unlock(utxo))
+            .map(|utxo| utxo.lock_script_hash)
+            .collect();
+        if unmatched.is_empty() {
+            return 0;
+        }
+
+        let mut num_keys = self.generation_spending_keys.len() as u64;
+        let mut index = num_keys;
+        while !unmatched.is_empty()
+            && index <= u64::from(u16::MAX)
+            && index < num_keys + GENERATION_KEY_GAP_LIMIT
+        {
+            let key = self.wallet_secret.nth_generation_spending_key(index as u16);
+            if unmatched.remove(&key.to_address().lock_script().hash()) {
+                num_keys = index + 1;
+            }
+            index += 1;
+        }
+
+        let num_known_keys = self.generation_spending_keys.len() as u64;
+        if num_keys > num_known_keys {
+            info!(
+                "Discovered {} generation keys beyond the {num_known_keys} known ones",
+                num_keys - num_known_keys
+            );
+            self.ensure_generation_keys(num_keys).await;
+        }
+
+        num_keys - num_known_keys
     }
 
     // TODO: These spending keys should probably be derived dynamically from some
@@ -582,7 +1153,10 @@ impl WalletState {
     // this gap is defined as 20 keys in a row that have never received funds.
     fn get_known_symmetric_keys(&self) -> Vec<SpendingKey> {
         // for now we always return just the 1st key.
-        vec![self.wallet_secret.nth_symmetric_key(0).into()]
+        self.symmetric_key
+            .into_iter()
+            .map(SpendingKey::from)
+            .collect()
     }
 
     /// Get the next unused spending key of a given type.
@@ -594,7 +1168,11 @@ impl WalletState {
     ///
     /// Note that incrementing the counter modifies wallet state.  It is
     /// important to write to disk afterward to avoid possible funds loss.
+    ///
+    /// Panics if the wallet is locked, in which case it holds no spending
+    /// keys. See [Self::next_unused_receiving_address()].
     pub fn next_unused_spending_key(&mut self, key_type: KeyType) -> SpendingKey {
+        assert!(!self.is_locked(), "a locked wallet holds no spending keys");
         match key_type {
             KeyType::Generation => self.next_unused_generation_spending_key().into(),
             KeyType::Symmetric => self.next_unused_symmetric_key().into(),
@@ -609,7 +1187,10 @@ impl WalletState {
     /// Note that incrementing the counter modifies wallet state.  It is
     /// important to write to disk afterward to avoid possible funds loss.
     fn next_unused_generation_spending_key(&mut self) -> generation_address::GenerationSpendingKey {
-        self.wallet_secret.nth_generation_spending_key(0)
+        self.generation_spending_keys
+            .first()
+            .copied()
+            .unwrap_or_else(|| self.wallet_secret.nth_generation_spending_key(0))
     }
 
     /// Get the next unused symmetric key.
@@ -619,8 +1200,84 @@ impl WalletState {
     ///
     /// Note that incrementing the counter modifies wallet state.  It is
     /// important to write to disk afterward to avoid possible funds loss.
+    ///
+    /// Panics if the wallet is locked.
     pub fn next_unused_symmetric_key(&mut self) -> symmetric_key::SymmetricKey {
-        self.wallet_secret.nth_symmetric_key(0)
+        assert!(!self.is_locked(), "a locked wallet holds no spending keys");
+        self.symmetric_key
+            .unwrap_or_else(|| self.wallet_secret.nth_symmetric_key(0))
+    }
+
+    /// Get the address of the next unused spending key of a given type. Unlike
+    /// [Self::next_unused_spending_key()], this also works while the wallet is
+    /// locked.
+    pub fn next_unused_receiving_address(&mut self, key_type: KeyType) -> ReceivingAddress {
+        if !self.is_locked() {
+            return self.next_unused_spending_key(key_type).to_address();
+        }
+
+        self.viewing_keys
+            .iter()
+            .map(ViewingKey::address)
+            .find(|address| KeyType::from(*address) == key_type)
+            .cloned()
+            .expect("a locked wallet keeps the viewing key of the first key of every type")
+    }
+
+    /// Number of membership proofs to store for a new monitored UTXO. Must
+    /// exceed the anchor depth, such that the proof for the block in which the
+    /// UTXO was confirmed is still around when the anchor reaches that block.
+    pub(crate) fn number_of_mps_per_new_utxo(&self) -> usize {
+        self.number_of_mps_per_utxo
+            .max(self.ms_update_window.depth() + 1)
+    }
+
+    /// Bring the membership proofs synced to the previous anchor to the new
+    /// anchor. UTXOs confirmed after the previous anchor use their stored
+    /// proof for the new anchor, if any. Returns the number of anchored proofs.
+    async fn advance_membership_proof_anchor(&mut self, advance: AnchorAdvance) -> Result<usize> {
+        let monitored_utxos = self.wallet_db.monitored_utxos_mut();
+
+        let mut to_update = vec![];
+        let mut already_synced = vec![];
+        for (i, mutxo) in monitored_utxos.get_all().await.into_iter().enumerate() {
+            if mutxo.abandoned_at.is_some() {
+                continue;
+            }
+            let previous_anchor_mp = advance
+                .from
+                .and_then(|from| mutxo.get_membership_proof_for_block(from));
+            match previous_anchor_mp {
+                Some(mp) => to_update.push((i as Index, mutxo, mp)),
+                None => {
+                    if let Some(mp) = mutxo.get_membership_proof_for_block(advance.to) {
+                        already_synced.push((i as Index, mutxo, mp));
+                    }
+                }
+            }
+        }
+
+        let item_digests = to_update
+            .iter()
+            .map(|(_, mutxo, _)| Hash::hash(&mutxo.utxo))
+            .collect_vec();
+        let mut membership_proofs = to_update.iter().map(|(_, _, mp)| mp.clone()).collect_vec();
+        advance.update_membership_proofs(&mut membership_proofs, &item_digests)?;
+
+        let updated = to_update
+            .into_iter()
+            .zip(membership_proofs)
+            .map(|((i, mutxo, _), mp)| (i, mutxo, mp))
+            .chain(already_synced)
+            .map(|(i, mut mutxo, mp)| {
+                mutxo.set_anchor_membership_proof(advance.from, advance.to, mp);
+                (i, mutxo)
+            })
+            .collect_vec();
+        let num_anchored = updated.len();
+        monitored_utxos.set_many(updated).await;
+
+        Ok(num_anchored)
     }
 
     /// Update wallet state with new block. Assume the given block
@@ -629,6 +1286,26 @@ impl WalletState {
         &mut self,
         current_mutator_set_accumulator: &MutatorSetAccumulator,
         new_block: &Block,
+    ) -> Result<()> {
+        let onchain_received_outputs = self
+            .scan_for_announced_utxos(&new_block.kernel.body.transaction_kernel)
+            .collect_vec();
+        self.update_wallet_state_with_scanned_block(
+            current_mutator_set_accumulator,
+            new_block,
+            onchain_received_outputs,
+        )
+        .await
+    }
+
+    /// Like [Self::update_wallet_state_with_new_block], for a block whose
+    /// announced UTXOs were already found by
+    /// [Self::scan_batch_for_announced_utxos].
+    pub(crate) async fn update_wallet_state_with_scanned_block(
+        &mut self,
+        current_mutator_set_accumulator: &MutatorSetAccumulator,
+        new_block: &Block,
+        onchain_received_outputs: Vec<AnnouncedUtxo>,
     ) -> Result<()> {
         /// Preprocess all own monitored UTXOs prior to processing of the block.
         ///
@@ -707,28 +1384,50 @@ impl WalletState {
             (valid_membership_proofs_and_own_utxo_count, double_counted)
         }
 
+        // The window must see every block, also those that do not affect
+        // this wallet.
+        let previous_anchor = self.ms_update_window.anchor();
+        let anchor_advance = self
+            .ms_update_window
+            .push(current_mutator_set_accumulator, new_block);
+
         let tx_kernel = new_block.kernel.body.transaction_kernel.clone();
 
         let spent_inputs: Vec<(Utxo, AbsoluteIndexSet, u64)> =
             self.scan_for_spent_utxos(&tx_kernel).await;
+        let spent_input_positions: HashMap<AbsoluteIndexSet, u64> = spent_inputs
+            .iter()
+            .map(|(_, abs_i, mutxo_list_index)| (*abs_i, *mutxo_list_index))
+            .collect();
 
-        let onchain_received_outputs = self.scan_for_announced_utxos(&tx_kernel);
+        if let Some(watched) = &self.watch_only {
+            for (i, count) in watched.count_notifications(&tx_kernel) {
+                warn!(
+                    "Block {} holds {count} UTXO notification(s) for watched address #{i}. \
+                    Import them as expected UTXOs to track them.",
+                    new_block.kernel.header.height
+                );
+            }
+        }
 
         let offchain_received_outputs =
             self.scan_for_expected_utxos(&tx_kernel).await.collect_vec();
 
-        let all_received_outputs =
-            onchain_received_outputs.chain(offchain_received_outputs.iter().cloned());
-
-        let addition_record_to_utxo_info: HashMap<AdditionRecord, (Utxo, Digest, Digest)> =
-            all_received_outputs
-                .map(|au| {
-                    (
-                        au.addition_record,
-                        (au.utxo, au.sender_randomness, au.receiver_preimage),
-                    )
-                })
-                .collect();
+        let all_received_outputs = onchain_received_outputs
+            .into_iter()
+            .chain(offchain_received_outputs.iter().cloned());
+
+        let addition_record_to_utxo_info: HashMap<
+            AdditionRecord,
+            (Utxo, Digest, Digest, Option<Memo>),
+        > = all_received_outputs
+            .map(|au| {
+                (
+                    au.addition_record,
+                    (au.utxo, au.sender_randomness, au.receiver_preimage, au.memo),
+                )
+            })
+            .collect();
 
         debug!(
             "announced outputs received: onchain: {}, offchain: {}, total: {}",
@@ -743,6 +1442,7 @@ impl WalletState {
 
         let monitored_utxos = self.wallet_db.monitored_utxos_mut();
         let mut incoming_utxo_recovery_data_list = vec![];
+        let mut received_memos = vec![];
 
         // return early if there are no monitored utxos and this
         // block does not affect our balance
@@ -806,16 +1506,20 @@ impl WalletState {
                 let utxo = addition_record_to_utxo_info[addition_record].0.clone();
                 let sender_randomness = addition_record_to_utxo_info[addition_record].1;
                 let receiver_preimage = addition_record_to_utxo_info[addition_record].2;
+                let utxo_amount = utxo
+                    .coins
+                    .iter()
+                    .filter(|coin| coin.type_script_hash == NativeCurrency.hash())
+                    .map(|coin| {
+                        *NeptuneCoins::decode(&coin.state)
+                            .expect("Failed to decode coin state as amount")
+                    })
+                    .sum::<NeptuneCoins>();
                 info!(
                     "Received UTXO in block {}, height {}: value = {}",
                     new_block.hash(),
                     new_block.kernel.header.height,
-                    utxo.coins
-                        .iter()
-                        .filter(|coin| coin.type_script_hash == NativeCurrency.hash())
-                        .map(|coin| *NeptuneCoins::decode(&coin.state)
-                            .expect("Failed to decode coin state as amount"))
-                        .sum::<NeptuneCoins>(),
+                    utxo_amount,
                 );
                 let utxo_digest = Hash::hash(&utxo);
                 let new_own_membership_proof =
@@ -832,7 +1536,7 @@ impl WalletState {
                 incoming_utxo_recovery_data_list.push(utxo_ms_recovery_data);
 
                 // Add the new UTXO to the list of monitored UTXOs
-                let mut mutxo = MonitoredUtxo::new(utxo, self.number_of_mps_per_utxo);
+                let mut mutxo = MonitoredUtxo::new(utxo, self.number_of_mps_per_new_utxo());
                 mutxo.confirmed_in_block = Some((
                     new_block.hash(),
                     new_block.kernel.header.timestamp,
@@ -848,6 +1552,15 @@ impl WalletState {
                     valid_membership_proofs_and_own_utxo_count
                         .insert(strong_key, (new_own_membership_proof, mutxos_len));
                     monitored_utxos.push(mutxo).await;
+                    if let Some(memo) = addition_record_to_utxo_info[addition_record].3.clone() {
+                        received_memos.push(WalletMemo {
+                            utxo_digest,
+                            amount: utxo_amount,
+                            direction: MemoDirection::Received,
+                            timestamp: new_block.kernel.header.timestamp,
+                            memo,
+                        });
+                    }
                 }
             }
 
@@ -904,12 +1617,9 @@ impl WalletState {
             // how do we ensure that we can recover them in case of a fork? For now we maintain
             // them even if the are spent, and then, later, we can add logic to remove these
             // membership proofs of spent UTXOs once they have been spent for M blocks.
-            match spent_inputs
-                .iter()
-                .find(|(_, abs_i, _mutxo_list_index)| *abs_i == removal_record.absolute_indices)
-            {
+            match spent_input_positions.get(&removal_record.absolute_indices) {
                 None => (),
-                Some((_spent_utxo, _abs_i, mutxo_list_index)) => {
+                Some(mutxo_list_index) => {
                     debug!(
                         "Discovered own input at input {}, marking UTXO as spent.",
                         block_tx_input_count
@@ -959,7 +1669,11 @@ impl WalletState {
         {
             let StrongUtxoKey { utxo_digest, .. } = strong_utxo_key;
             let mut monitored_utxo = monitored_utxos.get(*own_utxo_index).await;
-            monitored_utxo.add_membership_proof_for_tip(new_block.hash(), updated_ms_mp.to_owned());
+            monitored_utxo.add_membership_proof_for_tip_keeping_anchor(
+                new_block.hash(),
+                updated_ms_mp.to_owned(),
+                previous_anchor,
+            );
 
             // Sanity check that membership proofs of non-spent transactions are still valid
             assert!(
@@ -975,10 +1689,22 @@ impl WalletState {
             // Another option is to attempt to mark those abandoned monitored UTXOs as reorganized.
         }
 
+        if let Some(advance) = anchor_advance {
+            let start = Instant::now();
+            let num_anchored = self.advance_membership_proof_anchor(advance).await?;
+            debug!(
+                "Advanced anchor of {num_anchored} membership proofs in {:?}",
+                start.elapsed()
+            );
+        }
+
         // write these to disk.
         for item in incoming_utxo_recovery_data_list.into_iter() {
             self.store_utxo_ms_recovery_data(item).await?;
         }
+        for memo in received_memos {
+            self.wallet_db.memos_mut().push(memo).await;
+        }
 
         // Mark all expected UTXOs that were received in this block as received
         let updates = self
@@ -999,8 +1725,9 @@ impl WalletState {
             });
         self.wallet_db.expected_utxos_mut().set_many(updates).await;
 
+        // All changes caused by the block are committed in one batch
         self.wallet_db.set_sync_label(new_block.hash()).await;
-        self.wallet_db.persist().await;
+        self.wallet_db.persist_block_update().await;
 
         Ok(())
     }
@@ -1064,6 +1791,60 @@ impl WalletState {
         }
     }
 
+    /// Aggregate statistics of the wallet, see [WalletSummary].
+    ///
+    /// Only iterates over the monitored and expected UTXOs if they, or the
+    /// tip, changed since the previous call.
+    pub async fn summary(
+        &self,
+        tip_digest: Digest,
+        now: Timestamp,
+        last_scan_height: Option<BlockHeight>,
+    ) -> WalletSummary {
+        let utxo_generation = self.wallet_db.utxo_generation();
+        let is_cached = self
+            .summary_aggregates
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|aggregates| aggregates.is_current(tip_digest, utxo_generation));
+        if !is_cached {
+            let wallet_status = self.get_wallet_status_from_lock(tip_digest).await;
+            let stream = self.wallet_db.expected_utxos().stream_values().await;
+            pin_mut!(stream); // needed for iteration
+            let expected_utxos_pending = stream
+                .filter(|eu| futures::future::ready(eu.mined_in_block.is_none()))
+                .count()
+                .await;
+            *self.summary_aggregates.lock().unwrap() = Some(WalletAggregates::new(
+                tip_digest,
+                utxo_generation,
+                &wallet_status,
+                expected_utxos_pending,
+            ));
+        }
+
+        let unconfirmed_incoming = self
+            .mempool_unspent_utxos_iter()
+            .map(|utxo| utxo.get_native_currency_amount())
+            .sum();
+        let unconfirmed_outgoing = self
+            .mempool_spent_utxos_iter()
+            .map(|utxo| utxo.get_native_currency_amount())
+            .sum();
+        self.summary_aggregates
+            .lock()
+            .unwrap()
+            .as_ref()
+            .expect("aggregates were just computed")
+            .summary(
+                now,
+                unconfirmed_incoming,
+                unconfirmed_outgoing,
+                last_scan_height,
+            )
+    }
+
     /// Allocate sufficient UTXOs to generate a transaction. Requested amount
     /// must include fees that are paid in the transaction.
     pub(crate) async fn allocate_sufficient_input_funds(
@@ -1072,6 +1853,31 @@ impl WalletState {
         tip_digest: Digest,
         timestamp: Timestamp,
     ) -> Result<Vec<UnlockedUtxo>> {
+        self.allocate_sufficient_input_funds_with_policy(
+            total_spend,
+            tip_digest,
+            timestamp,
+            CoinSelectionPolicy::default(),
+        )
+        .await
+    }
+
+    /// Like [`Self::allocate_sufficient_input_funds`], but selects the inputs
+    /// according to `coin_selection`.
+    pub(crate) async fn allocate_sufficient_input_funds_with_policy(
+        &self,
+        total_spend: NeptuneCoins,
+        tip_digest: Digest,
+        timestamp: Timestamp,
+        coin_selection: CoinSelectionPolicy,
+    ) -> Result<Vec<UnlockedUtxo>> {
+        if self.is_watch_only() {
+            bail!("watch-only wallet cannot spend");
+        }
+        if self.is_locked() {
+            bail!("locked wallet cannot spend");
+        }
+
         // We only attempt to generate a transaction using those UTXOs that have up-to-date
         // membership proofs.
         let wallet_status = self.get_wallet_status_from_lock(tip_digest).await;
@@ -1090,8 +1896,7 @@ impl WalletState {
                 tip_digest);
         }
 
-        let mut input_funds = vec![];
-        let mut allocated_amount = NeptuneCoins::zero();
+        let mut spendable = vec![];
         for (wallet_status_element, membership_proof) in wallet_status.synced_unspent.iter() {
             // Don't attempt to use UTXOs that are still timelocked.
             if !wallet_status_element.utxo.can_spend_at(timestamp) {
@@ -1110,21 +1915,101 @@ impl WalletState {
                 }
             };
 
-            input_funds.push(UnlockedUtxo::unlock(
-                wallet_status_element.utxo.clone(),
-                spending_key,
-                membership_proof.clone(),
-            ));
-            allocated_amount =
-                allocated_amount + wallet_status_element.utxo.get_native_currency_amount();
+            spendable.push((wallet_status_element, spending_key, membership_proof));
+        }
+
+        let candidates = spendable
+            .iter()
+            .map(|(wallet_status_element, _, _)| CoinCandidate {
+                amount: wallet_status_element.utxo.get_native_currency_amount(),
+                lock_script_hash: wallet_status_element.utxo.lock_script_hash,
+            })
+            .collect_vec();
+        let Some(selected) = coin_selection.select(&candidates, total_spend) else {
+            bail!("Insufficient spendable amount to create transaction. Requested: {total_spend}");
+        };
+
+        let input_funds = selected
+            .into_iter()
+            .map(|i| {
+                let (wallet_status_element, spending_key, membership_proof) = &spendable[i];
+                UnlockedUtxo::unlock(
+                    wallet_status_element.utxo.clone(),
+                    *spending_key,
+                    (*membership_proof).clone(),
+                )
+            })
+            .collect();
+
+        Ok(input_funds)
+    }
+
+    /// Like [`Self::allocate_sufficient_input_funds`], but also allocates the
+    /// unconfirmed change of own transactions in the mempool, if confirmed
+    /// funds do not suffice. Inputs are proven against the mutator set
+    /// projected from the tip's mutator set by applying the pending
+    /// transactions, which is returned along with the inputs.
+    ///
+    /// A transaction built from these inputs must not be relayed before its
+    /// parents are confirmed. See [`super::unconfirmed_change`].
+    pub(crate) async fn allocate_sufficient_input_funds_spending_unconfirmed(
+        &self,
+        total_spend: NeptuneCoins,
+        tip_digest: Digest,
+        tip_msa: &MutatorSetAccumulator,
+        timestamp: Timestamp,
+    ) -> Result<(Vec<UnlockedUtxo>, MutatorSetAccumulator)> {
+        if self.is_watch_only() {
+            bail!("watch-only wallet cannot spend");
+        }
+        if self.is_locked() {
+            bail!("locked wallet cannot spend");
+        }
+
+        let wallet_status = self.get_wallet_status_from_lock(tip_digest).await;
+        let confirmed = wallet_status
+            .synced_unspent
+            .into_iter()
+            .map(|(wallet_status_element, membership_proof)| {
+                (wallet_status_element.utxo, membership_proof)
+            })
+            .collect_vec();
+        let projection = self.unconfirmed_change.project(tip_msa, confirmed)?;
+
+        let candidates = projection.confirmed.into_iter().chain(
+            projection
+                .change
+                .into_iter()
+                .map(|(_parent, utxo, membership_proof)| (utxo, membership_proof)),
+        );
 
-            // Don't allocate more than needed
+        let mut input_funds = vec![];
+        let mut allocated_amount = NeptuneCoins::zero();
+        for (utxo, membership_proof) in candidates {
             if allocated_amount >= total_spend {
                 break;
             }
+            if !utxo.can_spend_at(timestamp) {
+                continue;
+            }
+            let Some(spending_key) = self.find_spending_key_for_utxo(&utxo) else {
+                warn!("spending key not found for utxo: {:?}", utxo);
+                continue;
+            };
+
+            allocated_amount = allocated_amount + utxo.get_native_currency_amount();
+            input_funds.push(UnlockedUtxo::unlock(utxo, spending_key, membership_proof));
         }
 
-        Ok(input_funds)
+        if allocated_amount < total_spend {
+            bail!(
+                "Insufficient amount to create transaction, including unconfirmed change. Requested: {}, available: {}",
+                total_spend,
+                allocated_amount
+            );
+        }
+
+        Ok((input_funds, projection.mutator_set_accumulator))
     }
 
     pub async fn get_all_own_coins_with_possible_timelocks(&self) -> Vec<CoinWithPossibleTimeLock> {
@@ -1151,6 +2036,93 @@ impl WalletState {
         }
         own_coins
     }
+
+    /// Describe all monitored UTXOs for audit tooling. See
+    /// [`audit_export`](super::audit_export) for the format.
+    pub async fn audit_export(&self, network: Network, tip: Digest) -> WalletAuditExport {
+        // viewing keys, such that UTXOs are attributed while the wallet is
+        // locked too
+        let viewing_keys = self.get_all_known_viewing_keys();
+        let known_keys = KeyType::all_types()
+            .into_iter()
+            .flat_map(|key_type| {
+                let keys = viewing_keys
+                    .iter()
+                    .filter(|key| KeyType::from(key.address()) == key_type)
+                    .cloned()
+                    .collect_vec();
+                keys.into_iter()
+                    .zip(0u64..)
+                    .map(move |(key, index)| (key_type.clone(), index, key))
+            })
+            .collect_vec();
+
+        let stream = self.wallet_db.monitored_utxos().stream_values().await;
+        pin_mut!(stream); // needed for iteration
+
+        let mut utxos = vec![];
+        while let Some(mutxo) = stream.next().await {
+            utxos.push(AuditedUtxo::new(&mutxo, &known_keys));
+        }
+
+        WalletAuditExport::new(network, tip, utxos)
+    }
+
+    /// Record the memos of outputs sent by this wallet. Outputs without a
+    /// memo are skipped.
+    pub(crate) async fn add_sent_memos(&mut self, outputs: &TxOutputList, timestamp: Timestamp) {
+        for output in outputs.iter() {
+            let Some(memo) = output.memo() else {
+                continue;
+            };
+            let utxo = output.utxo();
+            let memo = WalletMemo {
+                utxo_digest: Hash::hash(&utxo),
+                amount: utxo.get_native_currency_amount(),
+                direction: MemoDirection::Sent,
+                timestamp,
+                memo: memo.clone(),
+            };
+            self.wallet_db.memos_mut().push(memo).await;
+        }
+    }
+
+    /// All memos sent or received by this wallet, oldest first.
+    pub async fn memos(&self) -> Vec<WalletMemo> {
+        self.wallet_db.memos().get_all().await
+    }
+}
+
+/// Scan each of `tx_kernels` with each of `keys`, in parallel, and keep the
+/// announced UTXOs that are present in the transaction they were announced in.
+fn scan_batch_with_keys<K: Sync>(
+    keys: &[K],
+    tx_kernels: &[&TransactionKernel],
+    scan: impl Fn(&K, &TransactionKernel) -> Vec<AnnouncedUtxo> + Sync,
+) -> Vec<Vec<AnnouncedUtxo>> {
+    tx_kernels
+        .par_iter()
+        .map(|tx_kernel| {
+            let announced_utxos: Vec<Vec<AnnouncedUtxo>> =
+                keys.par_iter().map(|key| scan(key, *tx_kernel)).collect();
+            announced_utxos
+                .into_iter()
+                .flatten()
+
+                // filter for presence in transaction
+                //
+                // note: this is a nice sanity check, but probably is un-necessary
+                //       work that can eventually be removed.
+                .filter(|au| match tx_kernel.outputs.contains(&au.addition_record) {
+                    true => true,
+                    false => {
+                        warn!("Transaction does not contain announced UTXO encrypted to own receiving address. Announced UTXO was: {:#?}", au.utxo);
+                        false
+                    }
+                })
+                .collect_vec()
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -1162,10 +2134,112 @@ mod tests {
 
     use super::*;
     use crate::config_models::network::Network;
+    use crate::models::blockchain::transaction::transaction_output::UtxoNotificationPayload;
     use crate::models::state::wallet::expected_utxo::ExpectedUtxo;
     use crate::tests::shared::make_mock_block;
+    use crate::tests::shared::make_mock_transaction;
     use crate::tests::shared::mock_genesis_global_state;
     use crate::tests::shared::mock_genesis_wallet_state;
+    use crate::tests::shared::unit_test_data_directory;
+    use crate::util_types::mutator_set::commit;
+
+    #[tokio::test]
+    #[traced_test]
+    async fn announced_utxos_are_found_in_key_order() {
+        let network = Network::RegTest;
+        let wallet = mock_genesis_wallet_state(WalletSecret::new_random(), network).await;
+        let keys = wallet.get_all_known_spending_keys();
+        assert!(keys.len() > 1);
+
+        // announce in reverse key order, so that an order-preserving scan
+        // must reorder
+        let mut tx = make_mock_transaction(vec![], vec![]);
+        for key in keys.iter().rev() {
+            let address = key.to_address();
+            let utxo = Utxo::new_native_currency(address.lock_script(), NeptuneCoins::new(1));
+            let sender_randomness: Digest = rand::random();
+            tx.kernel.outputs.push(commit(
+                Hash::hash(&utxo),
+                sender_randomness,
+                address.privacy_digest(),
+            ));
+            let payload = UtxoNotificationPayload::new(utxo, sender_randomness);
+            let announcement = address.generate_public_announcement(payload);
+            tx.kernel.public_announcements.push(announcement);
+        }
+
+        for _ in 0..10 {
+            let receiver_preimages = wallet
+                .scan_for_announced_utxos(&tx.kernel)
+                .map(|au| au.receiver_preimage)
+                .collect_vec();
+            let expected = keys.iter().map(|k| k.privacy_preimage()).collect_vec();
+            assert_eq!(expected, receiver_preimages);
+        }
+
+        for key in &keys {
+            let utxo =
+                Utxo::new_native_currency(key.to_address().lock_script(), NeptuneCoins::new(1));
+            assert_eq!(
+                key.privacy_preimage(),
+                wallet
+                    .find_spending_key_for_utxo(&utxo)
+                    .unwrap()
+                    .privacy_preimage()
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn batch_scan_finds_announced_utxos_in_block_order() {
+        let network = Network::RegTest;
+        let wallet = mock_genesis_wallet_state(WalletSecret::new_random(), network).await;
+        let keys = wallet.get_all_known_spending_keys();
+
+        // block i announces one UTXO to key i, such that each block finds a
+        // different UTXO
+        let kernels = (0..8)
+            .map(|i| {
+                let mut tx = make_mock_transaction(vec![], vec![]);
+                let address = keys[i % keys.len()].to_address();
+                let utxo = Utxo::new_native_currency(address.lock_script(), NeptuneCoins::new(1));
+                let sender_randomness: Digest = rand::random();
+                tx.kernel.outputs.push(commit(
+                    Hash::hash(&utxo),
+                    sender_randomness,
+                    address.privacy_digest(),
+                ));
+                let payload = UtxoNotificationPayload::new(utxo, sender_randomness);
+                tx.kernel
+                    .public_announcements
+                    .push(address.generate_public_announcement(payload));
+                tx.kernel
+            })
+            .collect_vec();
+
+        let addition_records = |announced_utxos: Vec<Vec<AnnouncedUtxo>>| {
+            announced_utxos
+                .into_iter()
+                .map(|aus| aus.into_iter().map(|au| au.addition_record).collect_vec())
+                .collect_vec()
+        };
+        let expected = kernels
+            .iter()
+            .map(|kernel| kernel.outputs.clone())
+            .collect_vec();
+        let block_by_block = kernels
+            .iter()
+            .map(|kernel| wallet.scan_for_announced_utxos(kernel).collect_vec())
+            .collect_vec();
+        assert_eq!(expected, addition_records(block_by_block));
+
+        let kernel_refs = kernels.iter().collect_vec();
+        for _ in 0..10 {
+            let batch = wallet.scan_batch_for_announced_utxos(&kernel_refs);
+            assert_eq!(expected, addition_records(batch));
+        }
+    }
 
     #[tokio::test]
     #[traced_test]
@@ -1258,6 +2332,175 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn watch_only_wallet_tracks_imported_utxos_but_cannot_spend() {
+        let network = Network::RegTest;
+        let cold_key = WalletSecret::new_random().nth_generation_spending_key_for_tests(0);
+        let watched = WatchOnlyAddresses::new(vec![cold_key.to_address().into()]).unwrap();
+        let cli_args = Args {
+            network,
+            ..Default::default()
+        };
+        let data_dir = unit_test_data_directory(network).unwrap();
+        let mut wallet = WalletState::new_watch_only(&data_dir, watched, &cli_args).await;
+        assert!(wallet.is_watch_only());
+        assert!(wallet.get_all_known_spending_keys().is_empty());
+
+        let genesis = Block::genesis_block(network);
+        let (block_1, cb_utxo, cb_sender_randomness) =
+            make_mock_block(&genesis, None, cold_key.to_address(), rand::random());
+
+        // the key holder exports the expected UTXO to the watch-only wallet
+        wallet
+            .add_expected_utxo(ExpectedUtxo::new(
+                cb_utxo.clone(),
+                cb_sender_randomness,
+                cold_key.privacy_preimage,
+                UtxoNotifier::Imported,
+            ))
+            .await;
+        wallet
+            .update_wallet_state_with_new_block(
+                &genesis.kernel.body.mutator_set_accumulator,
+                &block_1,
+            )
+            .await
+            .unwrap();
+
+        let wallet_status = wallet.get_wallet_status_from_lock(block_1.hash()).await;
+        assert_eq!(
+            cb_utxo.get_native_currency_amount(),
+            wallet_status
+                .synced_unspent
+                .iter()
+                .map(|(wse, _)| wse.utxo.get_native_currency_amount())
+                .sum::<NeptuneCoins>()
+        );
+
+        let timestamp = block_1.kernel.header.timestamp + Timestamp::months(12);
+        assert!(wallet
+            .allocate_sufficient_input_funds(NeptuneCoins::new(1), block_1.hash(), timestamp)
+            .await
+            .is_err());
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn locked_wallet_receives_but_cannot_spend_until_unlocked() {
+        let network = Network::RegTest;
+        let wallet_secret = WalletSecret::new_random();
+        let mut wallet = mock_genesis_wallet_state(wallet_secret.clone(), network).await;
+        let own_key = wallet.next_unused_spending_key(KeyType::Generation);
+        let symmetric_key = wallet.next_unused_symmetric_key();
+        assert!(!wallet.is_encrypted());
+        assert!(!wallet.lock());
+
+        let encrypted = EncryptedWalletSecret::encrypt(&wallet_secret, "passphrase").unwrap();
+        wallet.enable_encryption(encrypted);
+        assert!(wallet.is_locked());
+        assert_ne!(wallet_secret, wallet.wallet_secret);
+
+        // Keys needed to receive are still known
+        let utxo =
+            Utxo::new_native_currency(own_key.to_address().lock_script(), NeptuneCoins::new(1));
+        assert!(wallet.can_unlock(&utxo));
+        assert_eq!(
+            own_key.to_address(),
+            wallet.next_unused_receiving_address(KeyType::Generation)
+        );
+        assert_eq!(
+            ReceivingAddress::from(symmetric_key),
+            wallet.next_unused_receiving_address(KeyType::Symmetric)
+        );
+
+        let genesis_digest = Block::genesis_block(network).hash();
+        assert!(wallet
+            .allocate_sufficient_input_funds(NeptuneCoins::new(1), genesis_digest, Timestamp::now())
+            .await
+            .is_err());
+        assert!(wallet.export_account_descriptor(network, 0).await.is_err());
+        assert!(wallet.derive_new_generation_key().await.is_none());
+
+        assert!(wallet.unlock("wrong", None).await.is_err());
+        assert!(wallet.is_locked());
+        wallet
+            .unlock("passphrase", Some(Duration::from_secs(0)))
+            .await
+            .unwrap();
+        assert!(!wallet.is_locked());
+        assert_eq!(wallet_secret, wallet.wallet_secret);
+        assert_eq!(1, wallet.generation_spending_keys.len());
+        assert_eq!(symmetric_key, wallet.next_unused_symmetric_key());
+        assert!(wallet.export_account_descriptor(network, 0).await.is_ok());
+
+        wallet.relock_if_due();
+        assert!(wallet.is_locked());
+        assert_ne!(wallet_secret, wallet.wallet_secret);
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn locked_wallet_holds_no_spending_keys() {
+        let network = Network::RegTest;
+        let wallet_secret = WalletSecret::new_random();
+        let mut wallet = mock_genesis_wallet_state(wallet_secret.clone(), network).await;
+        wallet.derive_new_generation_key().await.unwrap();
+        wallet.export_account_descriptor(network, 0).await.unwrap();
+        let keys = wallet.get_all_known_spending_keys();
+
+        let mut tx = make_mock_transaction(vec![], vec![]);
+        let mut utxos = vec![];
+        for key in &keys {
+            let address = key.to_address();
+            let utxo = Utxo::new_native_currency(address.lock_script(), NeptuneCoins::new(1));
+            let sender_randomness: Digest = rand::random();
+            let payload = UtxoNotificationPayload::new(utxo.clone(), sender_randomness);
+            tx.kernel
+                .public_announcements
+                .push(address.generate_public_announcement(payload));
+            tx.kernel.outputs.push(commit(
+                Hash::hash(&utxo),
+                sender_randomness,
+                address.privacy_digest(),
+            ));
+            utxos.push(utxo);
+        }
+
+        let encrypted = EncryptedWalletSecret::encrypt(&wallet_secret, "passphrase").unwrap();
+        wallet.enable_encryption(encrypted);
+        assert!(wallet.is_locked());
+        assert!(wallet.get_all_known_spending_keys().is_empty());
+        assert!(wallet.generation_spending_keys.is_empty());
+        assert!(wallet.account_spending_keys.is_empty());
+        assert!(wallet.symmetric_key.is_none());
+
+        // every UTXO is still recognized, but none can be unlocked
+        for utxo in &utxos {
+            assert!(wallet.can_unlock(utxo));
+            assert!(wallet.find_spending_key_for_utxo(utxo).is_none());
+        }
+        let receiver_preimages = wallet
+            .scan_for_announced_utxos(&tx.kernel)
+            .map(|au| au.receiver_preimage)
+            .collect_vec();
+        let expected = keys.iter().map(|k| k.privacy_preimage()).collect_vec();
+        assert_eq!(expected, receiver_preimages);
+
+        wallet.unlock("passphrase", None).await.unwrap();
+        assert_eq!(keys.len(), wallet.get_all_known_spending_keys().len());
+        for utxo in &utxos {
+            assert!(wallet.find_spending_key_for_utxo(utxo).is_some());
+        }
+
+        assert!(wallet.lock());
+        assert!(wallet.get_all_known_spending_keys().is_empty());
+        assert_eq!(
+            expected.len(),
+            wallet.scan_for_announced_utxos(&tx.kernel).count()
+        );
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn wallet_state_prune_abandoned_mutxos() {
@@ -1470,6 +2713,82 @@ mod tests {
         );
     }
 
+    #[traced_test]
+    #[tokio::test]
+    async fn coinbase_keys_rotate_and_are_rediscovered() {
+        let network = Network::RegTest;
+        let wallet_secret = WalletSecret::new_random();
+        let mut wallet = mock_genesis_wallet_state(wallet_secret.clone(), network).await;
+        let height = BlockHeight::from(1u64);
+
+        let first_key = wallet.coinbase_spending_key(CoinbaseAddressRotation::Fresh, height);
+        let fresh_key = wallet.derive_new_generation_key().await.unwrap();
+        assert_ne!(
+            first_key.to_address(),
+            fresh_key.to_address(),
+            "fresh key must differ from the first key"
+        );
+        assert_eq!(
+            fresh_key.to_address(),
+            wallet
+                .coinbase_spending_key(CoinbaseAddressRotation::Fresh, height)
+                .to_address()
+        );
+        assert_eq!(
+            first_key.to_address(),
+            wallet
+                .coinbase_spending_key(CoinbaseAddressRotation::Fixed, height)
+                .to_address()
+        );
+        assert_eq!(2, wallet.wallet_db.get_generation_key_counter().await);
+
+        // A wallet that only knows the first key rediscovers keys that
+        // received funds, within the gap limit.
+        let mut restored = mock_genesis_wallet_state(wallet_secret.clone(), network).await;
+        let distant_key = wallet_secret.nth_generation_spending_key(7);
+        let utxo =
+            Utxo::new_native_currency(distant_key.to_address().lock_script(), NeptuneCoins::new(1));
+        assert!(!restored.can_unlock(&utxo));
+        assert_eq!(7, restored.discover_generation_keys(&[utxo.clone()]).await);
+        assert!(restored.can_unlock(&utxo));
+        assert_eq!(8, restored.wallet_db.get_generation_key_counter().await);
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn utxos_to_exported_accounts_are_recognized() {
+        let network = Network::RegTest;
+        let mut wallet = mock_genesis_wallet_state(WalletSecret::new_random(), network).await;
+
+        let account = 3;
+        let descriptor = wallet.wallet_secret.account_descriptor(network, account);
+        let address = descriptor.derive_address(DerivationChain::Receiving, 5);
+        let utxo = Utxo::new_native_currency(address.lock_script(), NeptuneCoins::new(1));
+        assert!(!wallet.can_unlock(&utxo));
+
+        let exported = wallet
+            .export_account_descriptor(network, account)
+            .await
+            .unwrap();
+        assert_eq!(descriptor, exported);
+        assert!(wallet.can_unlock(&utxo));
+        assert_eq!(
+            vec![account],
+            wallet.wallet_db.exported_accounts().get_all().await
+        );
+
+        // exporting again changes nothing
+        wallet
+            .export_account_descriptor(network, account)
+            .await
+            .unwrap();
+        assert_eq!(1, wallet.wallet_db.exported_accounts().len().await);
+        assert_eq!(
+            2 * usize::from(ACCOUNT_KEY_LOOKAHEAD),
+            wallet.account_spending_keys.len()
+        );
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn mock_wallet_state_is_synchronized_to_genesis_block() {
@@ -0,0 +1,121 @@
+//! Aggregate wallet statistics, for dashboards that would otherwise combine
+//! several calls that each iterate over all monitored UTXOs.
+
+use num_traits::Zero;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use twenty_first::math::digest::Digest;
+
+use super::wallet_status::WalletStatus;
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::models::blockchain::transaction::utxo::Utxo;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::prelude::twenty_first;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletSummary {
+    /// Number of unspent monitored UTXOs
+    pub utxo_count: usize,
+
+    /// Amount of all unspent monitored UTXOs, including those whose
+    /// membership proofs are not synced to the tip
+    pub total_balance: NeptuneCoins,
+
+    /// Amount that can be spent now
+    pub spendable_balance: NeptuneCoins,
+
+    /// Amount that can be spent once its timelocks expire
+    pub timelocked_balance: NeptuneCoins,
+
+    /// Number of expected UTXOs not yet confirmed in a block
+    pub expected_utxos_pending: usize,
+
+    /// Amount paid to this wallet by transactions in the mempool
+    pub unconfirmed_incoming: NeptuneCoins,
+
+    /// Amount spent from this wallet by transactions in the mempool
+    pub unconfirmed_outgoing: NeptuneCoins,
+
+    /// Height of the block the wallet was last updated with, `None` if that
+    /// block is not stored
+    pub last_scan_height: Option<BlockHeight>,
+
+    /// Number of unspent UTXOs whose membership proofs are not synced to the
+    /// tip, and hence cannot be spent until they are
+    pub proof_maintenance_backlog: usize,
+}
+
+/// The data the summary is computed from that requires iterating over the
+/// wallet database. Cached for as long as the tip and the wallet's UTXOs are
+/// unchanged. Balances are computed from it on every call, since timelocks
+/// depend on the time.
+#[derive(Clone, Debug)]
+pub(crate) struct WalletAggregates {
+    tip_digest: Digest,
+    utxo_generation: u64,
+    synced_unspent: Vec<Utxo>,
+    unsynced_unspent_amount: NeptuneCoins,
+    unsynced_unspent_count: usize,
+    expected_utxos_pending: usize,
+}
+
+impl WalletAggregates {
+    pub(crate) fn new(
+        tip_digest: Digest,
+        utxo_generation: u64,
+        wallet_status: &WalletStatus,
+        expected_utxos_pending: usize,
+    ) -> Self {
+        Self {
+            tip_digest,
+            utxo_generation,
+            synced_unspent: wallet_status
+                .synced_unspent
+                .iter()
+                .map(|(wse, _msmp)| wse.utxo.clone())
+                .collect(),
+            unsynced_unspent_amount: wallet_status.unsynced_unspent_amount(),
+            unsynced_unspent_count: wallet_status.unsynced_unspent.len(),
+            expected_utxos_pending,
+        }
+    }
+
+    pub(crate) fn is_current(&self, tip_digest: Digest, utxo_generation: u64) -> bool {
+        self.tip_digest == tip_digest && self.utxo_generation == utxo_generation
+    }
+
+    pub(crate) fn summary(
+        &self,
+        now: Timestamp,
+        unconfirmed_incoming: NeptuneCoins,
+        unconfirmed_outgoing: NeptuneCoins,
+        last_scan_height: Option<BlockHeight>,
+    ) -> WalletSummary {
+        let mut spendable_balance = NeptuneCoins::zero();
+        let mut timelocked_balance = NeptuneCoins::zero();
+        let mut synced_balance = NeptuneCoins::zero();
+        for utxo in &self.synced_unspent {
+            let amount = utxo.get_native_currency_amount();
+            synced_balance = synced_balance + amount;
+            if utxo.can_spend_at(now) {
+                spendable_balance = spendable_balance + amount;
+            } else if utxo.is_timelocked_but_otherwise_spendable_at(now) {
+                timelocked_balance = timelocked_balance + amount;
+            }
+        }
+
+        WalletSummary {
+            utxo_count: self.synced_unspent.len() + self.unsynced_unspent_count,
+            total_balance: synced_balance + self.unsynced_unspent_amount,
+            spendable_balance,
+            timelocked_balance,
+            expected_utxos_pending: self.expected_utxos_pending,
+            unconfirmed_incoming,
+            unconfirmed_outgoing,
+            last_scan_height,
+            proof_maintenance_backlog: self.unsynced_unspent_count,
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Watch-only wallets, which track the funds of addresses whose spending keys
+//! never touch the node, e.g. in a cold-storage setup.
+//!
+//! Without the spending keys, the node cannot decrypt the on-chain UTXO
+//! notifications for its addresses, so it only counts and logs them. The
+//! UTXOs themselves are tracked once the key holder exports them as expected
+//! UTXOs, e.g. in an [expected UTXO bundle](super::expected_utxo_bundle),
+//! since an expected UTXO carries what is needed to recognize the UTXO when
+//! it is confirmed and to notice when it is spent. Balances are then reported
+//! as for any other wallet.
+//!
+//! A watch-only wallet refuses to construct transactions.
+
+use std::path::Path;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use twenty_first::math::digest::Digest;
+
+use super::address::KeyType;
+use super::address::ReceivingAddress;
+use crate::config_models::network::Network;
+use crate::models::blockchain::transaction::transaction_kernel::TransactionKernel;
+use crate::prelude::twenty_first;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchOnlyAddresses {
+    addresses: Vec<ReceivingAddress>,
+}
+
+impl WatchOnlyAddresses {
+    pub fn new(addresses: Vec<ReceivingAddress>) -> Result<Self> {
+        if addresses.is_empty() {
+            bail!("a watch-only wallet needs at least one address");
+        }
+
+        let mut unique: Vec<ReceivingAddress> = vec![];
+        for address in addresses {
+            if !unique.contains(&address) {
+                unique.push(address);
+            }
+        }
+
+        Ok(Self { addresses: unique })
+    }
+
+    /// Read bech32m-encoded addresses, one per line. Empty lines and lines
+    /// starting with `#` are ignored.
+    pub fn read_from_file(path: &Path, network: Network) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        let addresses = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                ReceivingAddress::from_bech32m(line, network)
+                    .with_context(|| format!("invalid address in {}: {line}", path.display()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Self::new(addresses)
+    }
+
+    pub fn addresses(&self) -> &[ReceivingAddress] {
+        &self.addresses
+    }
+
+    /// The first watched address of the given type, if any.
+    pub fn address_of_type(&self, key_type: KeyType) -> Option<&ReceivingAddress> {
+        self.addresses
+            .iter()
+            .find(|address| KeyType::from(*address) == key_type)
+    }
+
+    /// Whether UTXOs with the given lock script hash are paid to a watched
+    /// address.
+    pub fn watches_lock_script_hash(&self, lock_script_hash: Digest) -> bool {
+        self.addresses
+            .iter()
+            .any(|address| address.lock_script().hash() == lock_script_hash)
+    }
+
+    /// The number of UTXO notifications in the transaction for each watched
+    /// address that received any, by index into [Self::addresses].
+    pub fn count_notifications(&self, tx_kernel: &TransactionKernel) -> Vec<(usize, usize)> {
+        self.addresses
+            .iter()
+            .enumerate()
+            .map(|(i, address)| {
+                let count = tx_kernel
+                    .public_announcements
+                    .iter()
+                    .filter(|pa| address.is_recipient_of(pa))
+                    .count();
+                (i, count)
+            })
+            .filter(|&(_, count)| count > 0)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod watch_only_tests {
+    use rand::random;
+
+    use super::*;
+    use crate::models::state::wallet::address::generation_address::GenerationReceivingAddress;
+    use crate::models::state::wallet::address::symmetric_key::SymmetricKey;
+
+    #[test]
+    fn addresses_are_read_from_file() {
+        let network = Network::RegTest;
+        let generation: ReceivingAddress =
+            GenerationReceivingAddress::derive_from_seed(random()).into();
+        let symmetric: ReceivingAddress = SymmetricKey::from_seed(random()).into();
+
+        let path = std::env::temp_dir().join(format!("watch-only-{}.txt", random::<u64>()));
+        let contents = format!(
+            "# cold storage\n{}\n\n  {}  \n{}\n",
+            generation.to_bech32m(network).unwrap(),
+            symmetric.to_bech32m(network).unwrap(),
+            generation.to_bech32m(network).unwrap(),
+        );
+        std::fs::write(&path, contents).unwrap();
+
+        let watched = WatchOnlyAddresses::read_from_file(&path, network).unwrap();
+        assert_eq!(vec![generation, symmetric], watched.addresses());
+        assert_eq!(
+            Some(&symmetric),
+            watched.address_of_type(KeyType::Symmetric)
+        );
+        assert!(watched.watches_lock_script_hash(generation.lock_script().hash()));
+
+        std::fs::write(&path, "# no addresses\n").unwrap();
+        assert!(WatchOnlyAddresses::read_from_file(&path, network).is_err());
+        std::fs::write(&path, "not-an-address\n").unwrap();
+        assert!(WatchOnlyAddresses::read_from_file(&path, network).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,6 +1,9 @@
 use std::cmp;
 use std::marker::Unpin;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 use std::time::SystemTime;
 
 use anyhow::bail;
@@ -13,20 +16,38 @@ use itertools::Itertools;
 use tasm_lib::triton_vm::prelude::Digest;
 use tokio::select;
 use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::TryRecvError;
 use tokio::sync::mpsc;
+use tokio::time;
+use tokio::time::MissedTickBehavior;
 use tracing::debug;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
 
 use crate::connect_to_peers::close_peer_connected_callback;
+use crate::connect_to_peers::probe_reachability;
+use crate::models::blockchain::block::block_header::BlockHeader;
 use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::models::blockchain::block::light_verification;
 use crate::models::blockchain::block::Block;
+use crate::models::blockchain::block::FUTUREDATING_LIMIT;
 use crate::models::blockchain::transaction::Transaction;
 use crate::models::channel::MainToPeerTask;
 use crate::models::channel::PeerTaskToMain;
 use crate::models::channel::PeerTaskToMainTransaction;
+use crate::models::peer::archival_scheduler::ArchivalPermit;
+use crate::models::peer::compact_block::CompactBlock;
+use crate::models::peer::compact_block::PendingCompactBlock;
+use crate::models::peer::digest_summary::DigestSummary;
+use crate::models::peer::digest_summary::DIGEST_SUMMARY_BLOCK_WINDOW;
+use crate::models::peer::digest_summary::DIGEST_SUMMARY_MAX_BACKFILL_BLOCKS;
+use crate::models::peer::digest_summary::DIGEST_SUMMARY_MAX_TRANSACTIONS;
+use crate::models::peer::own_block::OwnBlockAnnouncement;
+use crate::models::peer::rate_limit::PeerRateLimiter;
+use crate::models::peer::transaction_notification::TransactionNotification;
 use crate::models::peer::transfer_block::TransferBlock;
+use crate::models::peer::transfer_transaction::AcceptedTransactionProofs;
 use crate::models::peer::BlockRequestBatch;
 use crate::models::peer::HandshakeData;
 use crate::models::peer::MutablePeerState;
@@ -34,15 +55,56 @@ use crate::models::peer::PeerInfo;
 use crate::models::peer::PeerMessage;
 use crate::models::peer::PeerSanctionReason;
 use crate::models::peer::PeerStanding;
+use crate::models::peer::PeerViolation;
+use crate::models::peer::ReachabilityReport;
 use crate::models::proof_abstractions::timestamp::Timestamp;
-use crate::models::state::mempool::MEMPOOL_IGNORE_TRANSACTIONS_THIS_MANY_SECS_AHEAD;
-use crate::models::state::mempool::MEMPOOL_TX_THRESHOLD_AGE_IN_SECS;
+use crate::models::state::coinjoin::CoinJoinContribution;
+use crate::models::state::coinjoin::CoinJoinError;
+use crate::models::state::event_journal::JournalEvent;
+use crate::models::state::fork_watch;
+use crate::models::state::mempool_admission;
 use crate::models::state::GlobalStateLock;
 
 const STANDARD_BLOCK_BATCH_SIZE: usize = 50;
 const MAX_PEER_LIST_LENGTH: usize = 10;
+
+/// How far above the tip a block with an unknown parent may be and still be
+/// kept as an orphan. Blocks further ahead are only fetched by syncing.
+const MAX_ORPHAN_HEIGHT_ABOVE_TIP: u64 = 10;
+
+/// Why a block whose parent is unknown is not kept as an orphan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImplausibleOrphan {
+    /// Not within [`MAX_ORPHAN_HEIGHT_ABOVE_TIP`] blocks above the tip, or
+    /// timestamped too far into the future. Honest peers on a competing fork
+    /// or far ahead send such blocks.
+    OutOfReach,
+
+    /// Not enough proof-of-work relative to any plausible parent.
+    InsufficientProofOfWork,
+}
 const MINIMUM_BLOCK_BATCH_SIZE: usize = 2;
 
+/// Upper bound on the number of blocks in a batch response, regardless of the
+/// requested length, to bound the memory needed to receive one.
+pub(crate) const MAXIMUM_BLOCK_BATCH_SIZE: usize = 500;
+
+/// Minimum time between reachability checks performed for the same peer.
+const MIN_REACHABILITY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often peers are pinged, to measure their round-trip time and to notice
+/// connections that died without being closed.
+const PING_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Connections to peers that sent nothing for this long, despite being pinged,
+/// are closed, such that half-open connections do not count toward the peer
+/// limits.
+const PEER_IDLE_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// How long this node awaits the introduction after asking a peer for a
+/// rendezvous. The peer pairs requests made within a minute of each other.
+const RENDEZVOUS_INTRODUCTION_TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
 const KEEP_CONNECTION_ALIVE: bool = false;
 const DISCONNECT_CONNECTION: bool = true;
 
@@ -59,6 +121,7 @@ pub struct PeerLoopHandler {
     peer_handshake_data: HandshakeData,
     inbound_connection: bool,
     distance: u8,
+    rate_limiter: PeerRateLimiter,
     #[cfg(test)]
     mock_now: Option<Timestamp>,
 }
@@ -72,6 +135,7 @@ impl PeerLoopHandler {
         inbound_connection: bool,
         distance: u8,
     ) -> Self {
+        let rate_limiter = PeerRateLimiter::new(global_state_lock.cli());
         Self {
             to_main_tx,
             global_state_lock,
@@ -79,6 +143,7 @@ impl PeerLoopHandler {
             peer_handshake_data,
             inbound_connection,
             distance,
+            rate_limiter,
             #[cfg(test)]
             mock_now: None,
         }
@@ -95,6 +160,7 @@ impl PeerLoopHandler {
         distance: u8,
         mocked_time: Timestamp,
     ) -> Self {
+        let rate_limiter = PeerRateLimiter::new(global_state_lock.cli());
         Self {
             to_main_tx,
             global_state_lock,
@@ -102,6 +168,7 @@ impl PeerLoopHandler {
             peer_handshake_data,
             inbound_connection,
             distance,
+            rate_limiter,
             mock_now: Some(mocked_time),
         }
     }
@@ -117,32 +184,140 @@ impl PeerLoopHandler {
         }
     }
 
+    /// Whether the peer announced in its handshake that it wants transactions
+    /// with the notified kind of proof. Transactions it declined reach it
+    /// once they are upgraded to a kind of proof it accepts.
+    fn peer_accepts(&self, transaction_notification: &TransactionNotification) -> bool {
+        self.peer_handshake_data
+            .accepted_tx_proofs
+            .accepts(transaction_notification.proof_quality)
+    }
+
+    /// Wait for a slot to serve a block out of the archival state, shared
+    /// fairly with the other peers. Returns `None` if the peer has used up its
+    /// quota of served blocks.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for read
+    async fn archival_permit(&mut self) -> Option<ArchivalPermit> {
+        if self.rate_limiter.blocks_to_serve(1, Instant::now()) == 0 {
+            debug!(
+                "Not serving blocks to peer {}: quota used up",
+                self.peer_address
+            );
+            return None;
+        }
+
+        Some(self.archival_slot().await)
+    }
+
+    /// Wait for a slot to serve blocks out of the archival state, shared
+    /// fairly with the other peers, regardless of the peer's quota.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for read
+    async fn archival_slot(&self) -> ArchivalPermit {
+        let scheduler = self
+            .global_state_lock
+            .lock_guard()
+            .await
+            .net
+            .archival_scheduler
+            .clone();
+        let capacity = self
+            .global_state_lock
+            .cli()
+            .max_concurrent_archival_requests;
+        scheduler.acquire(self.peer_address, capacity).await
+    }
+
     // TODO: Add a reward function that mutates the peer status
 
     /// Locking:
     ///   * acquires `global_state_lock` for write
     async fn punish(&mut self, reason: PeerSanctionReason) -> Result<()> {
+        self.punish_with_evidence(PeerViolation::new(reason)).await
+    }
+
+    /// Like [`Self::punish`], but keeps the evidence for the violation in the
+    /// peer's standing.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for write
+    async fn punish_with_evidence(&mut self, violation: PeerViolation) -> Result<()> {
         let mut global_state_mut = self.global_state_lock.lock_guard_mut().await;
+        let reason = violation.reason;
         warn!(
             "Sanctioning peer {} for {:?}",
             self.peer_address.ip(),
-            reason
+            violation
         );
         let new_standing = global_state_mut
             .net
             .peer_map
             .get_mut(&self.peer_address)
-            .map(|p| p.standing.sanction(reason))
+            .map(|p| p.standing.record_violation(violation))
             .unwrap_or(0);
 
         if new_standing < -(global_state_mut.cli().peer_tolerance as PeerStandingNumber) {
             warn!("Banning peer");
+            global_state_mut
+                .record_journal_event(JournalEvent::PeerBanned {
+                    ip: self.peer_address.ip(),
+                    reason: reason.to_string(),
+                })
+                .await;
             bail!("Banning peer");
         }
 
         Ok(())
     }
 
+    /// The announcement of the latest block found by this node, if it is
+    /// what the peer asks for and may still be served from there. Records the
+    /// time it took the peer to ask for it.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for write
+    async fn own_block_requested(
+        &mut self,
+        is_requested: impl FnOnce(&OwnBlockAnnouncement) -> bool,
+        peer_state_info: &mut MutablePeerState,
+    ) -> Option<Arc<OwnBlockAnnouncement>> {
+        let own_block = {
+            let global_state = self.global_state_lock.lock_guard().await;
+            let tip_digest = global_state.chain.light_state().hash();
+            global_state
+                .net
+                .own_block_announcement
+                .clone()
+                .filter(|own_block| own_block.is_current(tip_digest))
+                .filter(|own_block| is_requested(own_block))?
+        };
+
+        if peer_state_info.own_block_requested != Some(own_block.digest()) {
+            peer_state_info.own_block_requested = Some(own_block.digest());
+            let latency = own_block.elapsed();
+            debug!(
+                "Peer {} asked for own block {} after {latency:?}",
+                self.peer_address,
+                own_block.digest()
+            );
+            if let Some(peer_info) = self
+                .global_state_lock
+                .lock_guard_mut()
+                .await
+                .net
+                .peer_map
+                .get_mut(&self.peer_address)
+            {
+                peer_info.own_block_latency = Some(latency);
+            }
+        }
+
+        Some(own_block)
+    }
+
     /// Handle validation and send all blocks to the main task if they're all
     /// valid. Use with a list of blocks or a single block. When the
     /// `received_blocks` is a list, the parent of the `i+1`th block in the
@@ -175,6 +350,8 @@ impl PeerLoopHandler {
             }
         );
         let now = self.now();
+        let checkpoints = self.global_state_lock.cli().validation_checkpoints();
+        let initial_sync = self.global_state_lock.lock(|s| s.net.syncing).await;
         let mut previous_block = &parent_of_first_block;
         for new_block in received_blocks.iter() {
             if !new_block.has_proof_of_work(previous_block) {
@@ -188,22 +365,21 @@ impl PeerLoopHandler {
                     previous_block.kernel.header.difficulty.target(),
                     new_block.hash().values().iter().join(", ")
                 );
-                self.punish(PeerSanctionReason::InvalidBlock((
-                    new_block.kernel.header.height,
-                    new_block.hash(),
-                )))
-                .await?;
+                self.punish_with_evidence(PeerViolation::invalid_block(new_block, None))
+                    .await?;
                 warn!("Failed to validate block due to insufficient PoW");
                 return Ok(None);
-            } else if !new_block.is_valid(previous_block, now) {
+            } else if let Err(failed_rule) =
+                new_block.validate_with_checkpoints(previous_block, now, &checkpoints, initial_sync)
+            {
                 warn!(
-                    "Received invalid block of height {} from peer with IP {}",
-                    new_block.kernel.header.height, self.peer_address
+                    "Received invalid block of height {} from peer with IP {}: failed {:?}",
+                    new_block.kernel.header.height, self.peer_address, failed_rule
                 );
-                self.punish(PeerSanctionReason::InvalidBlock((
-                    new_block.kernel.header.height,
-                    new_block.hash(),
-                )))
+                self.punish_with_evidence(PeerViolation::invalid_block(
+                    new_block,
+                    Some(failed_rule),
+                ))
                 .await?;
                 warn!("Failed to validate block: invalid block");
                 return Ok(None);
@@ -258,7 +434,14 @@ impl PeerLoopHandler {
     ///    handling by this function.
     ///
     /// If the parent is stored, the block and any fork reconciliation blocks
-    /// are passed down the pipeline.
+    /// are passed down the pipeline, followed by any orphans descending from
+    /// them.
+    ///
+    /// A block that neither has a known parent nor continues the fork
+    /// reconciliation list is kept as an orphan if it plausibly extends the
+    /// tip, and its parent is requested from this peer and, through the main
+    /// task, from all other peers. The peer is punished for an orphan without
+    /// enough proof-of-work.
     ///
     /// Locking:
     ///   * acquires `global_state_lock` for write via Self::punish()
@@ -320,9 +503,67 @@ impl PeerLoopHandler {
                             .max_number_of_blocks_before_syncing
             {
                 peer_state.fork_reconciliation_blocks.push(*received_block);
+            } else if self
+                .global_state_lock
+                .lock_guard()
+                .await
+                .net
+                .orphan_blocks
+                .contains(received_block.hash())
+            {
+                // Already kept, and its parent already requested.
+                debug!(
+                    "Block of height {} is already kept as an orphan",
+                    received_block.kernel.header.height
+                );
+                return Ok(());
+            } else if self.global_state_lock.cli().max_orphan_blocks > 0 {
+                match self.check_orphan_plausibility(&received_block).await {
+                    Ok(()) => (),
+                    Err(ImplausibleOrphan::InsufficientProofOfWork) => {
+                        warn!(
+                            "Received orphan block of height {} without enough proof-of-work",
+                            received_block.kernel.header.height
+                        );
+                        self.punish(PeerSanctionReason::InvalidBlock((
+                            received_block.kernel.header.height,
+                            received_block.hash(),
+                        )))
+                        .await?;
+                        return Ok(());
+                    }
+                    Err(ImplausibleOrphan::OutOfReach) => {
+                        // The peer may be on a competing fork, or far ahead.
+                        debug!(
+                            "Ignoring block of height {} with unknown parent, out of reach of \
+                            the tip",
+                            received_block.kernel.header.height
+                        );
+                        return Ok(());
+                    }
+                }
+
+                if self.keep_orphan(&received_block).await {
+                    // Blocks received out of order. Handled once the parent
+                    // arrives, from this or any other peer.
+                    info!(
+                        "Keeping block of height {} until its parent arrives",
+                        received_block.kernel.header.height
+                    );
+
+                    // The parent may be known to other peers even if this one
+                    // fails to deliver it.
+                    self.to_main_tx
+                        .send(PeerTaskToMain::OrphanParentMissing {
+                            parent_digest,
+                            requested_from: self.peer_address,
+                        })
+                        .await?;
+                }
             } else {
-                // Blocks received out of order. Or more than allowed received without
-                // going into sync mode. Give up on block resolution attempt.
+                // More blocks received than allowed without going into sync
+                // mode, or no room for orphans. Give up on block resolution
+                // attempt.
                 self.punish(PeerSanctionReason::ForkResolutionError((
                     received_block.kernel.header.height,
                     peer_state.fork_reconciliation_blocks.len() as u16,
@@ -379,10 +620,13 @@ impl PeerLoopHandler {
 
         // Parent block is guaranteed to be set here. Because: either it was fetched from the
         // database, or it's the genesis block.
+        let last_new_block = new_blocks.last().unwrap().clone();
         if let Some(new_block_height) = self
             .handle_blocks(new_blocks, parent_block.unwrap())
             .await?
         {
+            self.handle_orphans_of(last_new_block).await?;
+
             // If `BlockNotification` was received during a block reconciliation
             // event, then the peer might have one (or more (unlikely)) blocks
             // that we do not have. We should thus request those blocks.
@@ -399,6 +643,292 @@ impl PeerLoopHandler {
         Ok(())
     }
 
+    /// Check that a block whose parent is unknown could plausibly extend the
+    /// tip. It must be higher than the tip, by at most
+    /// [`MAX_ORPHAN_HEIGHT_ABOVE_TIP`], must not be timestamped too far into
+    /// the future, and must have enough proof-of-work relative to a parent
+    /// with the tip's difficulty, halved once for every block from the tip up
+    /// to the orphan. The first halving accounts for the orphan's parent
+    /// competing with the tip.
+    ///
+    /// The parent is unknown, so this is no substitute for validation, but it
+    /// makes keeping orphans and requesting their parents cost proof-of-work.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for read
+    async fn check_orphan_plausibility(&self, block: &Block) -> Result<(), ImplausibleOrphan> {
+        let tip_header = self
+            .global_state_lock
+            .lock_guard()
+            .await
+            .chain
+            .light_state()
+            .header()
+            .clone();
+        let header = block.header();
+
+        let height_above_tip = header.height - tip_header.height;
+        if !(1..=i128::from(MAX_ORPHAN_HEIGHT_ABOVE_TIP)).contains(&height_above_tip) {
+            return Err(ImplausibleOrphan::OutOfReach);
+        }
+
+        if header.timestamp > self.now() + FUTUREDATING_LIMIT {
+            return Err(ImplausibleOrphan::OutOfReach);
+        }
+
+        let mut plausible_parent = tip_header;
+        plausible_parent.difficulty >>= height_above_tip as usize;
+
+        // Timestamps are field elements, so the parent must not be younger
+        // than the orphan for the time difference not to wrap around.
+        if header.timestamp < plausible_parent.timestamp {
+            plausible_parent.timestamp = header.timestamp;
+        }
+
+        let network_params = self.global_state_lock.cli().network.params();
+        if !light_verification::has_proof_of_work(
+            block.hash(),
+            header,
+            &plausible_parent,
+            network_params,
+        ) {
+            return Err(ImplausibleOrphan::InsufficientProofOfWork);
+        }
+
+        Ok(())
+    }
+
+    /// Keep a block whose parent is unknown, unless too many blocks are
+    /// already pending in fork reconciliation. Returns true if it was kept.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for write
+    async fn keep_orphan(&self, block: &Block) -> bool {
+        let max_orphan_blocks = self.global_state_lock.cli().max_orphan_blocks;
+        self.global_state_lock
+            .lock_guard_mut()
+            .await
+            .net
+            .orphan_blocks
+            .insert(block.clone(), max_orphan_blocks)
+    }
+
+    /// Validate the orphans descending from `parent`, which was just passed
+    /// on to the main task, and pass on those that are valid and canonical,
+    /// parents first.
+    ///
+    /// Orphans may have been sent by other peers, so invalid ones are dropped
+    /// without punishing this peer.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for write
+    async fn handle_orphans_of(&mut self, parent: Block) -> Result<()> {
+        let now = self.now();
+        let checkpoints = self.global_state_lock.cli().validation_checkpoints();
+        let initial_sync = self.global_state_lock.lock(|s| s.net.syncing).await;
+        let mut parents = vec![parent];
+        while let Some(parent) = parents.pop() {
+            let children = self
+                .global_state_lock
+                .lock_guard_mut()
+                .await
+                .net
+                .orphan_blocks
+                .take_children(parent.hash());
+            for child in children {
+                if !child.has_proof_of_work(&parent)
+                    || !child.is_valid_with_checkpoints(&parent, now, &checkpoints, initial_sync)
+                {
+                    warn!(
+                        "Dropping invalid orphan block of height {}",
+                        child.kernel.header.height
+                    );
+                    continue;
+                }
+                if !self
+                    .global_state_lock
+                    .lock_guard()
+                    .await
+                    .incoming_block_is_more_canonical(&child)
+                {
+                    debug!(
+                        "Dropping orphan block of height {} that is not canonical",
+                        child.kernel.header.height
+                    );
+                    continue;
+                }
+
+                info!(
+                    "Parent of orphan block of height {} arrived",
+                    child.kernel.header.height
+                );
+                self.to_main_tx
+                    .send(PeerTaskToMain::NewBlocks(vec![child.clone()]))
+                    .await?;
+                parents.push(child);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Summary of this node's recent canonical blocks and mempool
+    /// transactions, for the peer to send what this node is missing.
+    async fn digest_summary(&self) -> DigestSummary {
+        let global_state = self.global_state_lock.lock_guard().await;
+        let tip_digest = global_state.chain.light_state().hash();
+        let mut block_digests = vec![tip_digest];
+        block_digests.extend(
+            global_state
+                .chain
+                .archival_state()
+                .get_ancestor_block_digests(tip_digest, DIGEST_SUMMARY_BLOCK_WINDOW - 1)
+                .await,
+        );
+        let txids = global_state
+            .mempool
+            .get_sorted_iter()
+            .map(|(txid, _fee_density)| txid)
+            .take(DIGEST_SUMMARY_MAX_TRANSACTIONS)
+            .collect_vec();
+
+        DigestSummary::new(&block_digests, &txids)
+    }
+
+    /// The recent canonical blocks, oldest first, and the mempool
+    /// transactions that are not in the peer's summary.
+    ///
+    /// No blocks are returned if the peer is missing more than
+    /// [`DIGEST_SUMMARY_MAX_BACKFILL_BLOCKS`], since the peer then catches
+    /// up by synchronization.
+    async fn missing_from(
+        &self,
+        summary: &DigestSummary,
+    ) -> Result<(Vec<Block>, Vec<TransactionNotification>)> {
+        let global_state = self.global_state_lock.lock_guard().await;
+        let archival_state = global_state.chain.archival_state();
+        let genesis_digest = archival_state.genesis_block().hash();
+        let tip_digest = global_state.chain.light_state().hash();
+
+        let mut recent_digests = archival_state
+            .get_ancestor_block_digests(tip_digest, DIGEST_SUMMARY_BLOCK_WINDOW - 1)
+            .await;
+        recent_digests.reverse();
+        recent_digests.push(tip_digest);
+        let missing_digests = recent_digests
+            .into_iter()
+            .filter(|digest| *digest != genesis_digest && !summary.has_block(*digest))
+            .collect_vec();
+
+        let mut missing_blocks = vec![];
+        if missing_digests.len() <= DIGEST_SUMMARY_MAX_BACKFILL_BLOCKS {
+            for digest in missing_digests {
+                if let Some(block) = archival_state.get_block(digest).await? {
+                    missing_blocks.push(block);
+                }
+            }
+        }
+
+        let missing_transactions = global_state
+            .mempool
+            .get_sorted_iter()
+            .filter(|(txid, _fee_density)| !summary.has_transaction(*txid))
+            .filter_map(|(txid, _fee_density)| global_state.mempool.get(txid))
+            .filter_map(|transaction| TransactionNotification::try_from(transaction).ok())
+            .take(DIGEST_SUMMARY_MAX_TRANSACTIONS)
+            .collect_vec();
+
+        Ok((missing_blocks, missing_transactions))
+    }
+
+    /// Handle a block received from the peer, either in full or reconstructed
+    /// from a compact block.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for write via Self::try_ensure_path()
+    async fn handle_received_block<S>(
+        &mut self,
+        t_block: TransferBlock,
+        peer: &mut S,
+        peer_state_info: &mut MutablePeerState,
+    ) -> Result<()>
+    where
+        S: Sink<PeerMessage> + TryStream<Ok = PeerMessage> + Unpin,
+        <S as Sink<PeerMessage>>::Error: std::error::Error + Sync + Send + 'static,
+        <S as TryStream>::Error: std::error::Error,
+    {
+        info!(
+            "Got new block from peer {}, height {}, mined {}",
+            self.peer_address,
+            t_block.header.height,
+            t_block.header.timestamp.standard_format()
+        );
+        let new_block_height = t_block.header.height;
+        self.watch_for_unknown_rules(&t_block.header).await;
+
+        let block: Box<Block> = Box::new(t_block.into());
+
+        // Update the value for the highest known height that peer possesses iff
+        // we are not in a fork reconciliation state.
+        if peer_state_info.fork_reconciliation_blocks.is_empty() {
+            peer_state_info.highest_shared_block_height = new_block_height;
+        }
+
+        self.try_ensure_path(block, peer, peer_state_info).await
+    }
+
+    /// Report a header announced by the peer to the fork watch if it follows
+    /// consensus rules this binary does not know and is heavier than the own
+    /// tip.
+    async fn watch_for_unknown_rules(&self, header: &BlockHeader) {
+        if fork_watch::rules_are_known(header) {
+            return;
+        }
+
+        let now = self.now();
+        let mut global_state_mut = self.global_state_lock.lock_guard_mut().await;
+        let own_proof_of_work = global_state_mut
+            .chain
+            .light_state()
+            .header()
+            .cumulative_proof_of_work;
+        if header.cumulative_proof_of_work > own_proof_of_work {
+            global_state_mut
+                .net
+                .fork_watch
+                .observe(self.peer_address.ip(), header, now);
+        }
+    }
+
+    /// Handle a compact block of which all removal records are known. If the
+    /// reconstructed block does not have the announced digest, because short
+    /// IDs collided, the full block is requested instead.
+    async fn complete_compact_block<S>(
+        &mut self,
+        pending: PendingCompactBlock,
+        peer: &mut S,
+        peer_state_info: &mut MutablePeerState,
+    ) -> Result<()>
+    where
+        S: Sink<PeerMessage> + TryStream<Ok = PeerMessage> + Unpin,
+        <S as Sink<PeerMessage>>::Error: std::error::Error + Sync + Send + 'static,
+        <S as TryStream>::Error: std::error::Error,
+    {
+        let block_digest = pending.digest();
+        match pending.into_block() {
+            Some(t_block) => {
+                self.handle_received_block(t_block, peer, peer_state_info)
+                    .await
+            }
+            None => {
+                debug!("Could not reconstruct compact block {block_digest}, requesting it in full");
+                peer.send(PeerMessage::BlockRequestByHash(block_digest))
+                    .await?;
+                Ok(())
+            }
+        }
+    }
+
     /// Handle peer messages and returns Ok(true) if connection should be closed.
     /// Connection should also be closed if an error is returned.
     /// Otherwise returns OK(false).
@@ -422,6 +952,16 @@ impl PeerLoopHandler {
             msg.get_type(),
             self.peer_address
         );
+        if let Some(reason) = self.rate_limiter.exceeded_by(&msg, Instant::now()) {
+            warn!(
+                "Ignoring {} from peer {}: rate limit exceeded",
+                msg.get_type(),
+                self.peer_address
+            );
+            self.punish(reason).await?;
+            return Ok(KEEP_CONNECTION_ALIVE);
+        }
+
         match msg {
             PeerMessage::Bye => {
                 // Note that the current peer is not removed from the global_state.peer_map here
@@ -474,47 +1014,169 @@ impl PeerLoopHandler {
                 Ok(KEEP_CONNECTION_ALIVE)
             }
             PeerMessage::Block(t_block) => {
-                info!(
-                    "Got new block from peer {}, height {}, mined {}",
-                    self.peer_address,
-                    t_block.header.height,
-                    t_block.header.timestamp.standard_format()
-                );
-                let new_block_height = t_block.header.height;
-
-                let block: Box<Block> = Box::new((*t_block).into());
+                self.handle_received_block(*t_block, peer, peer_state_info)
+                    .await?;
 
-                // Update the value for the highest known height that peer possesses iff
-                // we are not in a fork reconciliation state.
-                if peer_state_info.fork_reconciliation_blocks.is_empty() {
-                    peer_state_info.highest_shared_block_height = new_block_height;
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            PeerMessage::CompactBlockRequest(block_digest) => {
+                let own_block = self
+                    .own_block_requested(
+                        |own_block| {
+                            own_block.digest() == block_digest && own_block.compact_block.is_some()
+                        },
+                        peer_state_info,
+                    )
+                    .await;
+                if let Some(compact_block) = own_block.and_then(|b| b.compact_block.clone()) {
+                    peer.send(PeerMessage::CompactBlock(Box::new(compact_block)))
+                        .await?;
+                    return Ok(KEEP_CONNECTION_ALIVE);
                 }
 
-                self.try_ensure_path(block, peer, peer_state_info).await?;
+                let Some(_permit) = self.archival_permit().await else {
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                };
+                let block = self
+                    .global_state_lock
+                    .lock_guard()
+                    .await
+                    .chain
+                    .archival_state()
+                    .get_block(block_digest)
+                    .await?;
+                match block.map(|block| CompactBlock::new(&block)) {
+                    Some(Ok(compact_block)) => {
+                        peer.send(PeerMessage::CompactBlock(Box::new(compact_block)))
+                            .await?;
+                    }
+                    Some(Err(err)) => {
+                        warn!("Cannot send block {block_digest} as compact block: {err}")
+                    }
+                    None => warn!("Peer requested unknown compact block with hash {block_digest}"),
+                }
 
                 Ok(KEEP_CONNECTION_ALIVE)
             }
-            PeerMessage::BlockRequestBatch(BlockRequestBatch {
-                known_blocks,
-                max_response_len,
-            }) => {
-                // Find the block that the peer is requesting to start from
-                let mut peers_preferred_canonical_block: Option<Block> = None;
+            PeerMessage::CompactBlock(compact_block) => {
+                let pending = {
+                    let global_state = self.global_state_lock.lock_guard().await;
+                    let mempool_removal_records = global_state
+                        .mempool
+                        .get_sorted_iter()
+                        .filter_map(|(txid, _fee_density)| global_state.mempool.get(txid))
+                        .flat_map(|transaction| transaction.kernel.inputs.clone());
+                    PendingCompactBlock::new(*compact_block, mempool_removal_records)
+                };
 
-                let tip_digest = self
+                let missing = pending.missing();
+                if missing.is_empty() {
+                    self.complete_compact_block(pending, peer, peer_state_info)
+                        .await?;
+                } else {
+                    debug!(
+                        "Requesting {} removal records of compact block {}",
+                        missing.len(),
+                        pending.digest()
+                    );
+                    peer.send(PeerMessage::GetBlockTxn {
+                        block_digest: pending.digest(),
+                        indices: missing,
+                    })
+                    .await?;
+                    peer_state_info.pending_compact_block = Some(pending);
+                }
+
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            PeerMessage::GetBlockTxn {
+                block_digest,
+                indices,
+            } => {
+                let Some(_permit) = self.archival_permit().await else {
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                };
+                let block = self
                     .global_state_lock
                     .lock_guard()
                     .await
                     .chain
-                    .light_state()
-                    .hash();
-                {
-                    let global_state = self.global_state_lock.lock_guard().await;
-                    // Find the 1st match (known and canonical block) of the
-                    // request. break as soon as a match is found.
-                    for digest in known_blocks {
-                        debug!("Looking up block {} in batch request", digest);
-                        let block_candidate = global_state
+                    .archival_state()
+                    .get_block(block_digest)
+                    .await?;
+                let Some(block) = block else {
+                    warn!("Peer requested removal records of unknown block {block_digest}");
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                };
+
+                let inputs = &block.kernel.body.transaction_kernel.inputs;
+                let Some(removal_records) = indices
+                    .iter()
+                    .map(|&i| inputs.get(i).cloned())
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    self.punish(PeerSanctionReason::InvalidMessage).await?;
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                };
+
+                peer.send(PeerMessage::BlockTxn {
+                    block_digest,
+                    removal_records,
+                })
+                .await?;
+
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            PeerMessage::BlockTxn {
+                block_digest,
+                removal_records,
+            } => {
+                let Some(mut pending) = peer_state_info
+                    .pending_compact_block
+                    .take()
+                    .filter(|pending| pending.digest() == block_digest)
+                else {
+                    warn!("Got unrequested removal records of block {block_digest}");
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                };
+
+                let missing = pending.missing();
+                if !pending.fill(&missing, removal_records) {
+                    warn!("Got removal records not matching compact block {block_digest}");
+                    self.punish(PeerSanctionReason::InvalidMessage).await?;
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                }
+                self.complete_compact_block(pending, peer, peer_state_info)
+                    .await?;
+
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            PeerMessage::BlockRequestBatch(BlockRequestBatch {
+                known_blocks,
+                max_response_len,
+            }) => {
+                // A syncing peer punishes missing and short responses, so a
+                // batch is served also once the quota is used up. The batch
+                // size is then cut to the minimum.
+                let _permit = self.archival_slot().await;
+
+                // Find the block that the peer is requesting to start from
+                let mut peers_preferred_canonical_block: Option<Block> = None;
+
+                let tip_digest = self
+                    .global_state_lock
+                    .lock_guard()
+                    .await
+                    .chain
+                    .light_state()
+                    .hash();
+                {
+                    let global_state = self.global_state_lock.lock_guard().await;
+                    // Find the 1st match (known and canonical block) of the
+                    // request. break as soon as a match is found.
+                    for digest in known_blocks {
+                        debug!("Looking up block {} in batch request", digest);
+                        let block_candidate = global_state
                             .chain
                             .archival_state()
                             .get_block(digest)
@@ -560,7 +1222,13 @@ impl PeerLoopHandler {
                         / 2,
                 );
 
-                let responded_batch_size = cmp::max(len_of_response, MINIMUM_BLOCK_BATCH_SIZE);
+                let responded_batch_size = cmp::max(len_of_response, MINIMUM_BLOCK_BATCH_SIZE)
+                    .min(MAXIMUM_BLOCK_BATCH_SIZE);
+
+                let responded_batch_size = self
+                    .rate_limiter
+                    .blocks_to_serve(responded_batch_size, Instant::now())
+                    .max(MINIMUM_BLOCK_BATCH_SIZE);
                 let mut returned_blocks: Vec<TransferBlock> =
                     Vec::with_capacity(responded_batch_size);
 
@@ -596,13 +1264,15 @@ impl PeerLoopHandler {
                         canonical
                     };
 
-                    // get block and append to list
-                    let canonical_child: Block = global_state
+                    // get block and append to list, unless it was pruned
+                    let Some(canonical_child) = global_state
                         .chain
                         .archival_state()
                         .get_block(canonical_child_digest)
                         .await?
-                        .unwrap();
+                    else {
+                        break;
+                    };
                     returned_blocks.push(canonical_child.try_into().unwrap());
 
                     // prepare for next iteration
@@ -625,10 +1295,21 @@ impl PeerLoopHandler {
                     t_blocks.len()
                 );
                 if t_blocks.len() < MINIMUM_BLOCK_BATCH_SIZE {
+                    // A pruned peer lacks the blocks below its prune point. It
+                    // is not asked for batches, but may answer an old request.
+                    if !self.peer_handshake_data.is_archival_node {
+                        debug!("Ignoring short batch response from pruned peer");
+                        return Ok(KEEP_CONNECTION_ALIVE);
+                    }
                     warn!("Got smaller batch response than allowed");
                     self.punish(PeerSanctionReason::TooShortBlockBatch).await?;
                     return Ok(KEEP_CONNECTION_ALIVE);
                 }
+                if t_blocks.len() > MAXIMUM_BLOCK_BATCH_SIZE {
+                    warn!("Got larger batch response than allowed");
+                    self.punish(PeerSanctionReason::TooLongBlockBatch).await?;
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                }
 
                 // Verify that we are in fact in syncing mode
                 // TODO: Seperate peer messages into those allowed under syncing
@@ -669,10 +1350,30 @@ impl PeerLoopHandler {
                     most_canonical_own_block_match.kernel.header.height
                 );
                 let received_blocks: Vec<Block> = t_blocks.into_iter().map(|x| x.into()).collect();
+                let last_received_block = received_blocks.last().unwrap().clone();
+                let received_height = last_received_block.kernel.header.height;
+                self.global_state_lock
+                    .lock_mut(|s| {
+                        if let Some(sync_progress) = s.net.sync_progress.as_mut() {
+                            sync_progress.record_downloaded(received_height);
+                        }
+                    })
+                    .await;
 
                 // Get the latest block that we know of and handle all received blocks
-                self.handle_blocks(received_blocks, most_canonical_own_block_match)
+                let validated_height = self
+                    .handle_blocks(received_blocks, most_canonical_own_block_match)
                     .await?;
+                if let Some(validated_height) = validated_height {
+                    self.global_state_lock
+                        .lock_mut(|s| {
+                            if let Some(sync_progress) = s.net.sync_progress.as_mut() {
+                                sync_progress.record_validated(validated_height);
+                            }
+                        })
+                        .await;
+                    self.handle_orphans_of(last_received_block).await?;
+                }
 
                 Ok(KEEP_CONNECTION_ALIVE)
             }
@@ -734,12 +1435,21 @@ impl PeerLoopHandler {
                             .expect("Sending to main task must succeed");
                     } else if block_is_new && peer_state_info.fork_reconciliation_blocks.is_empty()
                     {
-                        debug!(
-                            "sending BlockRequestByHeight to peer for block with height {}",
-                            block_notification.height
-                        );
-                        peer.send(PeerMessage::BlockRequestByHeight(block_notification.height))
-                            .await?;
+                        if self.global_state_lock.cli().compact_block_relay {
+                            debug!(
+                                "sending CompactBlockRequest to peer for block with height {}",
+                                block_notification.height
+                            );
+                            peer.send(PeerMessage::CompactBlockRequest(block_notification.hash))
+                                .await?;
+                        } else {
+                            debug!(
+                                "sending BlockRequestByHeight to peer for block with height {}",
+                                block_notification.height
+                            );
+                            peer.send(PeerMessage::BlockRequestByHeight(block_notification.height))
+                                .await?;
+                        }
                     } else {
                         debug!(
                             "ignoring peer block. height {}. new: {}, reconciling_fork: {}",
@@ -753,6 +1463,20 @@ impl PeerLoopHandler {
                 Ok(KEEP_CONNECTION_ALIVE)
             }
             PeerMessage::BlockRequestByHash(block_digest) => {
+                if let Some(own_block) = self
+                    .own_block_requested(|b| b.digest() == block_digest, peer_state_info)
+                    .await
+                {
+                    peer.send(PeerMessage::Block(Box::new(
+                        own_block.transfer_block.clone(),
+                    )))
+                    .await?;
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                }
+
+                let Some(_permit) = self.archival_permit().await else {
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                };
                 match self
                     .global_state_lock
                     .lock_guard()
@@ -777,6 +1501,18 @@ impl PeerLoopHandler {
             PeerMessage::BlockRequestByHeight(block_height) => {
                 debug!("Got BlockRequestByHeight of height {}", block_height);
 
+                // The own block may not be stored yet
+                if let Some(own_block) = self
+                    .own_block_requested(|b| b.height() == block_height, peer_state_info)
+                    .await
+                {
+                    peer.send(PeerMessage::Block(Box::new(
+                        own_block.transfer_block.clone(),
+                    )))
+                    .await?;
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                }
+
                 let block_digests = self
                     .global_state_lock
                     .lock_guard()
@@ -811,7 +1547,10 @@ impl PeerLoopHandler {
                     }
                 }
 
-                let canonical_chain_block: Block = self
+                let Some(_permit) = self.archival_permit().await else {
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                };
+                let Some(canonical_chain_block) = self
                     .global_state_lock
                     .lock_guard()
                     .await
@@ -819,7 +1558,10 @@ impl PeerLoopHandler {
                     .archival_state()
                     .get_block(canonical_chain_block_digest)
                     .await?
-                    .unwrap();
+                else {
+                    debug!("Requested block at height {block_height} was pruned");
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                };
                 let block_response: PeerMessage =
                     PeerMessage::Block(Box::new(canonical_chain_block.try_into().unwrap()));
 
@@ -836,99 +1578,170 @@ impl PeerLoopHandler {
                 self.punish(PeerSanctionReason::InvalidMessage).await?;
                 Ok(KEEP_CONNECTION_ALIVE)
             }
-            PeerMessage::Transaction(transaction) => {
-                debug!(
-                    "`peer_loop` received following transaction from peer. {} inputs, {} outputs. Synced to mutator set hash: {}",
-                    transaction.kernel.inputs.len(),
-                    transaction.kernel.outputs.len(),
-                    transaction.kernel.mutator_set_hash
-                );
-
-                let transaction: Transaction = (*transaction).into();
-
-                // 1. If transaction is invalid, punish.
-                if !transaction.is_valid().await {
-                    warn!("Received invalid tx");
-                    self.punish(PeerSanctionReason::InvalidTransaction).await?;
+            PeerMessage::RendezvousRequest => {
+                // Only peers that connected to us have an endpoint worth
+                // introducing; it is the NAT mapping of their connection.
+                if self.global_state_lock.cli().hole_punching && self.inbound_connection {
+                    self.to_main_tx
+                        .send(PeerTaskToMain::RendezvousRequest(self.peer_address))
+                        .await?;
+                }
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            PeerMessage::RendezvousIntroduction {
+                endpoint,
+                initiator,
+            } => {
+                // Only accept one introduction per rendezvous asked for.
+                let Some(requested_at) = peer_state_info.pending_rendezvous_request.take() else {
+                    self.punish(PeerSanctionReason::InvalidMessage).await?;
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                };
+                if requested_at.elapsed() > RENDEZVOUS_INTRODUCTION_TIMEOUT {
+                    debug!(
+                        "Ignoring late rendezvous introduction from {}",
+                        self.peer_address
+                    );
                     return Ok(KEEP_CONNECTION_ALIVE);
                 }
 
-                // 2. If transaction has coinbase, punish.
-                // Transactions received from peers have not been mined yet.
-                // Only the miner is allowed to produce transactions with non-empty coinbase fields.
-                if transaction.kernel.coinbase.is_some() {
-                    warn!("Received non-mined transaction with coinbase.");
-                    self.punish(PeerSanctionReason::NonMinedTransactionHasCoinbase)
-                        .await?;
+                self.to_main_tx
+                    .send(PeerTaskToMain::RendezvousIntroduction {
+                        endpoint,
+                        initiator,
+                    })
+                    .await?;
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            PeerMessage::ReachabilityRequest => {
+                let now = SystemTime::now();
+                let too_soon = peer_state_info.last_reachability_check.is_some_and(|last| {
+                    now.duration_since(last)
+                        .is_ok_and(|age| age < MIN_REACHABILITY_CHECK_INTERVAL)
+                });
+                if too_soon {
+                    debug!(
+                        "Ignoring repeated reachability request from {}",
+                        self.peer_address
+                    );
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                }
+                peer_state_info.last_reachability_check = Some(now);
+
+                // Only connect back to the peer's own IP, such that this node
+                // cannot be used to probe third parties.
+                let address = self
+                    .peer_handshake_data
+                    .listen_port
+                    .map(|port| SocketAddr::new(self.peer_address.ip(), port));
+                let reachable = match address {
+                    Some(address) => probe_reachability(address).await,
+                    None => false,
+                };
+                peer.send(PeerMessage::ReachabilityResponse { address, reachable })
+                    .await?;
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            PeerMessage::ReachabilityResponse { address, reachable } => {
+                if !std::mem::take(&mut peer_state_info.reachability_check_requested) {
+                    self.punish(PeerSanctionReason::InvalidMessage).await?;
                     return Ok(KEEP_CONNECTION_ALIVE);
                 }
 
-                // 3. If transaction is already known, ignore.
-                if self
-                    .global_state_lock
-                    .lock_guard()
+                let report = ReachabilityReport {
+                    peer: self.peer_address,
+                    address,
+                    reachable: Some(reachable),
+                };
+                info!("Reachability check: {report}");
+                self.global_state_lock
+                    .lock_guard_mut()
                     .await
-                    .mempool
-                    .contains_with_higher_proof_quality(
-                        transaction.kernel.txid(),
-                        transaction.proof.proof_quality()?,
-                    )
-                {
-                    warn!("Received transaction that was already known");
-
-                    // We received a transaction that we *probably* haven't requested.
-                    // Consider punishing here, if this is abused.
+                    .net
+                    .reachability_reports
+                    .insert(self.peer_address, report);
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            PeerMessage::DigestSummary(summary) => {
+                if !summary.is_well_formed() {
+                    warn!("Got malformed digest summary");
+                    self.punish(PeerSanctionReason::InvalidMessage).await?;
                     return Ok(KEEP_CONNECTION_ALIVE);
                 }
 
-                // 4 if transaction is not confirmable, punish.
-                let confirmable = transaction.is_confirmable_relative_to(
-                    &self
-                        .global_state_lock
-                        .lock_guard()
-                        .await
-                        .chain
-                        .light_state()
-                        .kernel
-                        .body
-                        .mutator_set_accumulator,
+                let (missing_blocks, missing_transactions) = self.missing_from(&summary).await?;
+                debug!(
+                    "Peer {} is missing {} blocks and {} transactions",
+                    self.peer_address,
+                    missing_blocks.len(),
+                    missing_transactions.len()
                 );
-                if !confirmable {
-                    warn!("Received unconfirmable tx");
-                    self.punish(PeerSanctionReason::UnconfirmableTransaction)
-                        .await?;
-                    return Ok(KEEP_CONNECTION_ALIVE);
+                for block in missing_blocks {
+                    if let Ok(transfer_block) = TransferBlock::try_from(block) {
+                        peer.send(PeerMessage::Block(Box::new(transfer_block)))
+                            .await?;
+                    }
+                }
+                for transaction_notification in missing_transactions {
+                    if !self.peer_accepts(&transaction_notification) {
+                        continue;
+                    }
+                    peer.send(PeerMessage::TransactionNotification(
+                        transaction_notification,
+                    ))
+                    .await?;
                 }
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            PeerMessage::Transaction(transaction) => {
+                debug!(
+                    "`peer_loop` received following transaction from peer. {} inputs, {} outputs. Synced to mutator set hash: {}",
+                    transaction.kernel.inputs.len(),
+                    transaction.kernel.outputs.len(),
+                    transaction.kernel.mutator_set_hash
+                );
 
-                let tx_timestamp = transaction.kernel.timestamp;
+                let transaction: Transaction = (*transaction).into();
 
-                // 5. Ignore if transaction is too old
-                let now = self.now();
-                if tx_timestamp < now - Timestamp::seconds(MEMPOOL_TX_THRESHOLD_AGE_IN_SECS) {
-                    // TODO: Consider punishing here
-                    warn!("Received too old tx");
+                // Drop the transaction if too many are already being admitted.
+                // The slot is held until the transaction is handed to main.
+                let Some(_admission_slot) = self.global_state_lock.mempool_admission.try_enter()
+                else {
+                    debug!("Mempool admission queue is full; dropping transaction");
                     return Ok(KEEP_CONNECTION_ALIVE);
-                }
+                };
 
-                // 6. Ignore if transaction is too far into the future
-                if tx_timestamp
-                    > now + Timestamp::seconds(MEMPOOL_IGNORE_TRANSACTIONS_THIS_MANY_SECS_AHEAD)
+                // 1. Stateless checks, without holding the state lock
+                let admission = match mempool_admission::check_stateless(
+                    &transaction,
+                    self.now(),
+                    self.global_state_lock.cli().network,
+                )
+                .await
                 {
-                    // TODO: Consider punishing here
-                    warn!("Received tx too far into the future. Got timestamp: {tx_timestamp:?}");
-                    return Ok(KEEP_CONNECTION_ALIVE);
-                }
+                    // 2. Stateful checks, under a single acquisition of the lock
+                    Ok(()) => mempool_admission::check_stateful(
+                        &transaction,
+                        &*self.global_state_lock.lock_guard().await,
+                    ),
+                    Err(rejection) => Err(rejection),
+                };
+
+                let confirmable_for_block = match admission {
+                    Ok(tip_digest) => tip_digest,
+                    Err(rejection) => {
+                        warn!("Rejected transaction from peer: {rejection}");
+                        if let Some(reason) = rejection.sanction() {
+                            self.punish(reason).await?;
+                        }
+                        return Ok(KEEP_CONNECTION_ALIVE);
+                    }
+                };
 
                 // Otherwise relay to main
                 let pt2m_transaction = PeerTaskToMainTransaction {
                     transaction,
-                    confirmable_for_block: self
-                        .global_state_lock
-                        .lock_guard()
-                        .await
-                        .chain
-                        .light_state()
-                        .hash(),
+                    confirmable_for_block,
                 };
                 self.to_main_tx
                     .send(PeerTaskToMain::Transaction(Box::new(pt2m_transaction)))
@@ -950,6 +1763,15 @@ impl PeerLoopHandler {
                     return Ok(KEEP_CONNECTION_ALIVE);
                 }
 
+                // Do not ask for proofs this node told the peer it does not
+                // want.
+                let own_accepted_proofs =
+                    AcceptedTransactionProofs::new(self.global_state_lock.cli().single_proofs_only);
+                if !own_accepted_proofs.accepts(tx_notification.proof_quality) {
+                    debug!("transaction has a proof of a kind this node does not accept");
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                }
+
                 // Only accept transactions that do not require executing
                 // `update`.
                 if state
@@ -979,7 +1801,14 @@ impl PeerLoopHandler {
                     .mempool
                     .get(transaction_identifier)
                 {
-                    if let Ok(transfer_transaction) = transaction.try_into() {
+                    let accepted = transaction.proof_quality().is_ok_and(|proof_quality| {
+                        self.peer_handshake_data
+                            .accepted_tx_proofs
+                            .accepts(proof_quality)
+                    });
+                    if !accepted {
+                        warn!("Peer requested transaction with a proof it does not accept");
+                    } else if let Ok(transfer_transaction) = transaction.try_into() {
                         peer.send(PeerMessage::Transaction(Box::new(transfer_transaction)))
                             .await?;
                     } else {
@@ -989,6 +1818,179 @@ impl PeerLoopHandler {
 
                 Ok(KEEP_CONNECTION_ALIVE)
             }
+            PeerMessage::CoinJoinSessionRequest => {
+                let session = match self.global_state_lock.cli().coinjoin_config() {
+                    Some(config) => {
+                        let now = self.now();
+                        let mut state = self.global_state_lock.lock_guard_mut().await;
+                        let mutator_set_hash = state
+                            .chain
+                            .light_state()
+                            .body()
+                            .mutator_set_accumulator
+                            .hash();
+                        state
+                            .net
+                            .coinjoin_coordinator
+                            .session_info(&config, mutator_set_hash, now)
+                    }
+                    None => None,
+                };
+                peer.send(PeerMessage::CoinJoinSession(session)).await?;
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            PeerMessage::CoinJoinSession(session) => {
+                let mut state = self.global_state_lock.lock_guard_mut().await;
+                match session {
+                    Some(session) => {
+                        debug!(
+                            "Peer {} coordinates coinjoin session {} of {}",
+                            self.peer_address, session.id, session.denomination
+                        );
+                        state
+                            .net
+                            .coinjoin_sessions
+                            .insert(self.peer_address, session);
+                    }
+                    None => {
+                        state.net.coinjoin_sessions.remove(&self.peer_address);
+                    }
+                }
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            PeerMessage::CoinJoinContribution(contribution) => {
+                let session_id = contribution.session_id;
+                if self.global_state_lock.cli().coinjoin_config().is_none() {
+                    peer.send(PeerMessage::CoinJoinStatus {
+                        session_id,
+                        result: Err(CoinJoinError::NotCoordinator),
+                    })
+                    .await?;
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                }
+
+                let CoinJoinContribution {
+                    transaction,
+                    disclosed_output,
+                    ..
+                } = *contribution;
+                let transaction: Transaction = transaction.into();
+
+                // Contributions are verified like transactions entering the
+                // mempool, since they end up there once merged.
+                let result = match mempool_admission::check_stateless(
+                    &transaction,
+                    self.now(),
+                    self.global_state_lock.cli().network,
+                )
+                .await
+                {
+                    Ok(()) => self
+                        .global_state_lock
+                        .lock_guard_mut()
+                        .await
+                        .net
+                        .coinjoin_coordinator
+                        .contribute(
+                            self.peer_address,
+                            session_id,
+                            transaction,
+                            &disclosed_output,
+                        ),
+                    Err(rejection) => {
+                        warn!("Rejected coinjoin contribution from peer: {rejection}");
+                        if let Some(reason) = rejection.sanction() {
+                            self.punish(reason).await?;
+                        }
+                        Err(CoinJoinError::InvalidTransaction)
+                    }
+                };
+
+                match &result {
+                    Ok(()) => info!(
+                        "Accepted contribution of {} to coinjoin session {session_id}",
+                        self.peer_address
+                    ),
+                    Err(err) => debug!(
+                        "Refused contribution of {} to coinjoin session {session_id}: {err}",
+                        self.peer_address
+                    ),
+                }
+                peer.send(PeerMessage::CoinJoinStatus { session_id, result })
+                    .await?;
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            PeerMessage::Ping(nonce) => {
+                peer.send(PeerMessage::Pong(nonce)).await?;
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            PeerMessage::Pong(nonce) => {
+                let Some((ping_nonce, sent_at)) = peer_state_info.pending_ping else {
+                    debug!("Ignoring unsolicited pong from {}", self.peer_address);
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                };
+                if nonce != ping_nonce {
+                    debug!(
+                        "Ignoring pong with unknown nonce from {}",
+                        self.peer_address
+                    );
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                }
+                peer_state_info.pending_ping = None;
+
+                let round_trip_time = sent_at.elapsed();
+                debug!(
+                    "Round-trip time to {} is {round_trip_time:?}",
+                    self.peer_address
+                );
+                if let Some(peer_info) = self
+                    .global_state_lock
+                    .lock_guard_mut()
+                    .await
+                    .net
+                    .peer_map
+                    .get_mut(&self.peer_address)
+                {
+                    peer_info.round_trip_time = Some(round_trip_time);
+                }
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            PeerMessage::CoinJoinStatus { session_id, result } => {
+                let mut state = self.global_state_lock.lock_guard_mut().await;
+                let is_own_session =
+                    state
+                        .net
+                        .own_coinjoin_contribution
+                        .as_ref()
+                        .is_some_and(|contribution| {
+                            contribution.coordinator == self.peer_address
+                                && contribution.session_id == session_id
+                        });
+                if !is_own_session {
+                    debug!("Ignoring status of coinjoin session this node did not contribute to");
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                }
+
+                match result {
+                    Ok(()) => {
+                        info!(
+                            "Coordinator {} accepted contribution to coinjoin session {session_id}",
+                            self.peer_address
+                        );
+                    }
+                    Err(err) => {
+                        let contribution = state.net.own_coinjoin_contribution.take().unwrap();
+                        drop(state);
+                        warn!("Coinjoin session {session_id} failed: {err}");
+                        self.to_main_tx
+                            .send(PeerTaskToMain::CoinJoinFailed(Box::new(
+                                contribution.transaction,
+                            )))
+                            .await?;
+                    }
+                }
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
         }
     }
 
@@ -1023,6 +2025,17 @@ impl PeerLoopHandler {
                 }
                 Ok(KEEP_CONNECTION_ALIVE)
             }
+            MainToPeerTask::OwnBlock(own_block) => {
+                if own_block.height() > peer_state_info.highest_shared_block_height {
+                    debug!("Announcing own block");
+                    peer_state_info.highest_shared_block_height = own_block.height();
+                    peer.send(PeerMessage::BlockNotification(
+                        own_block.notification.clone(),
+                    ))
+                    .await?;
+                }
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
             MainToPeerTask::RequestBlockBatch(batch_block_request) => {
                 // Only ask one of the peers about the batch of blocks
                 if batch_block_request.peer_addr_target != self.peer_address {
@@ -1074,6 +2087,9 @@ impl PeerLoopHandler {
                 Ok(KEEP_CONNECTION_ALIVE)
             }
             MainToPeerTask::TransactionNotification(transaction_notification) => {
+                if !self.peer_accepts(&transaction_notification) {
+                    return Ok(KEEP_CONNECTION_ALIVE);
+                }
                 debug!("Sending PeerMessage::TransactionNotification");
                 peer.send(PeerMessage::TransactionNotification(
                     transaction_notification,
@@ -1082,23 +2098,101 @@ impl PeerLoopHandler {
                 debug!("Sent PeerMessage::TransactionNotification");
                 Ok(KEEP_CONNECTION_ALIVE)
             }
-        }
-    }
-
-    /// Loop for the peer tasks. Awaits either a message from the peer over TCP,
-    /// or a message from main over the main-to-peer-tasks broadcast channel.
-    async fn run<S>(
-        &mut self,
-        mut peer: S,
-        mut from_main_rx: broadcast::Receiver<MainToPeerTask>,
-        peer_state_info: &mut MutablePeerState,
-    ) -> Result<()>
-    where
-        S: Sink<PeerMessage> + TryStream<Ok = PeerMessage> + Unpin,
-        <S as Sink<PeerMessage>>::Error: std::error::Error + Sync + Send + 'static,
-        <S as TryStream>::Error: std::error::Error,
-    {
-        loop {
+            MainToPeerTask::RequestBlock {
+                block_digest,
+                except,
+            } => {
+                if except != self.peer_address {
+                    peer.send(PeerMessage::BlockRequestByHash(block_digest))
+                        .await?;
+                }
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            MainToPeerTask::RequestRendezvous(target_socket_addr) => {
+                if target_socket_addr == self.peer_address {
+                    peer_state_info.pending_rendezvous_request = Some(Instant::now());
+                    peer.send(PeerMessage::RendezvousRequest).await?;
+                }
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            MainToPeerTask::RendezvousIntroduction {
+                to,
+                endpoint,
+                initiator,
+            } => {
+                if to == self.peer_address {
+                    peer.send(PeerMessage::RendezvousIntroduction {
+                        endpoint,
+                        initiator,
+                    })
+                    .await?;
+                }
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            MainToPeerTask::TransactionNotificationToPeers(transaction_notification, peers) => {
+                if peers.contains(&self.peer_address)
+                    && self.peer_accepts(&transaction_notification)
+                {
+                    peer.send(PeerMessage::TransactionNotification(
+                        transaction_notification,
+                    ))
+                    .await?;
+                }
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            MainToPeerTask::RequestReachabilityCheck(peers) => {
+                if peers.contains(&self.peer_address) {
+                    peer_state_info.reachability_check_requested = true;
+                    peer.send(PeerMessage::ReachabilityRequest).await?;
+                }
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            MainToPeerTask::RequestCoinJoinSessions => {
+                peer.send(PeerMessage::CoinJoinSessionRequest).await?;
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            MainToPeerTask::CoinJoinContribute {
+                coordinator,
+                contribution,
+            } => {
+                if coordinator == self.peer_address {
+                    peer.send(PeerMessage::CoinJoinContribution(contribution))
+                        .await?;
+                }
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+            MainToPeerTask::CoinJoinStatus {
+                contributors,
+                session_id,
+                result,
+            } => {
+                if contributors.contains(&self.peer_address) {
+                    peer.send(PeerMessage::CoinJoinStatus { session_id, result })
+                        .await?;
+                }
+                Ok(KEEP_CONNECTION_ALIVE)
+            }
+        }
+    }
+
+    /// Loop for the peer tasks. Awaits either a message from the peer over TCP,
+    /// or a message from main over the main-to-peer-tasks broadcast channel.
+    async fn run<S>(
+        &mut self,
+        mut peer: S,
+        mut from_main_rx: broadcast::Receiver<MainToPeerTask>,
+        peer_state_info: &mut MutablePeerState,
+    ) -> Result<()>
+    where
+        S: Sink<PeerMessage> + TryStream<Ok = PeerMessage> + Unpin,
+        <S as Sink<PeerMessage>>::Error: std::error::Error + Sync + Send + 'static,
+        <S as TryStream>::Error: std::error::Error,
+    {
+        let mut ping_interval =
+            time::interval_at(time::Instant::now() + PING_INTERVAL, PING_INTERVAL);
+        ping_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
             select! {
                 // Handle peer messages
                 peer_message = peer.try_next() => {
@@ -1110,6 +2204,7 @@ impl PeerLoopHandler {
                                     break;
                                 }
                                 Some(peer_msg) => {
+                                    peer_state_info.last_message_received = Instant::now();
                                     let syncing = self.global_state_lock.lock(|s| s.net.syncing).await;
                                     if peer_msg.ignore_during_sync() && syncing {
                                         debug!("Ignoring {} message during syncing, from {}", peer_msg.get_type(), self.peer_address);
@@ -1143,8 +2238,14 @@ impl PeerLoopHandler {
 
                 // Handle messages from main task
                 main_msg_res = from_main_rx.recv() => {
-                    let close_connection = match main_msg_res {
-                        Ok(main_msg) => match self.handle_main_task_message(main_msg, &mut peer, peer_state_info).await {
+                    let main_msgs = match main_msg_res {
+                        Ok(main_msg) => with_own_blocks_first(main_msg, &mut from_main_rx),
+                        Err(e) => panic!("Failed to read from main loop: {}", e),
+                    };
+
+                    let mut close_connection = false;
+                    for main_msg in main_msgs {
+                        close_connection = match self.handle_main_task_message(main_msg, &mut peer, peer_state_info).await {
                             Ok(close) => close,
 
                             // If the handler of main-task messages returns error, the connection is closed.
@@ -1153,15 +2254,34 @@ impl PeerLoopHandler {
                                 warn!("handle_main_task_message returned an eror: {}", err);
                                 true
                             },
+                        };
+                        if close_connection {
+                            break;
                         }
-                        Err(e) => panic!("Failed to read from main loop: {}", e),
-                    };
+                    }
 
                     if close_connection {
                         info!("handle_main_task_message is closing the connection to {}", self.peer_address);
                         break;
                     }
                 }
+
+                // Ping the peer, and close the connection if it went silent.
+                // Peers that do not understand pings are left alone, since
+                // they may stay silent for longer without being gone.
+                _ = ping_interval.tick(), if self.peer_handshake_data.supports_ping => {
+                    if peer_state_info.last_message_received.elapsed() > PEER_IDLE_TIMEOUT {
+                        info!(
+                            "Peer {} sent nothing for {PEER_IDLE_TIMEOUT:?}. Closing connection.",
+                            self.peer_address
+                        );
+                        break;
+                    }
+
+                    let nonce = rand::random();
+                    peer_state_info.pending_ping = Some((nonce, Instant::now()));
+                    peer.send(PeerMessage::Ping(nonce)).await?;
+                }
             }
         }
         Ok(())
@@ -1195,9 +2315,22 @@ impl PeerLoopHandler {
             .await
             .unwrap_or_default();
 
+        // The router of a peer behind NAT may forward another port than the
+        // peer listens on. The announced external address is only trusted if
+        // the peer connects from it.
+        let port_for_incoming_connections = match self.peer_handshake_data.external_address {
+            Some(external_address)
+                if external_address.ip().to_canonical()
+                    == self.peer_address.ip().to_canonical() =>
+            {
+                Some(external_address.port())
+            }
+            _ => self.peer_handshake_data.listen_port,
+        };
+
         // Add peer to peer map
         let new_peer = PeerInfo {
-            port_for_incoming_connections: self.peer_handshake_data.listen_port,
+            port_for_incoming_connections,
             connected_address: self.peer_address,
             inbound: self.inbound_connection,
             instance_id: self.peer_handshake_data.instance_id,
@@ -1205,6 +2338,8 @@ impl PeerLoopHandler {
             standing,
             version: self.peer_handshake_data.version.clone(),
             is_archival_node: self.peer_handshake_data.is_archival_node,
+            own_block_latency: None,
+            round_trip_time: None,
         };
 
         // There is potential for a race-condition in the peer_map here, as we've previously
@@ -1244,6 +2379,9 @@ impl PeerLoopHandler {
             )))
             .await?;
 
+        let peer_tip_header = self.peer_handshake_data.tip_header.clone();
+        self.watch_for_unknown_rules(&peer_tip_header).await;
+
         // `MutablePeerState` contains the part of the peer-loop's state that is mutable
         let mut peer_state = MutablePeerState::new(self.peer_handshake_data.tip_header.height);
 
@@ -1262,6 +2400,41 @@ impl PeerLoopHandler {
             peer.send(PeerMessage::BlockNotificationRequest).await?;
         }
 
+        // Recover the mempool from before the restart from the first peer
+        // to connect.
+        let checkpointed_mempool_txids = self
+            .global_state_lock
+            .lock_mut(|s| std::mem::take(&mut s.net.checkpointed_mempool_txids))
+            .await;
+        if !checkpointed_mempool_txids.is_empty() {
+            debug!(
+                "Requesting {} checkpointed mempool transactions from {}",
+                checkpointed_mempool_txids.len(),
+                self.peer_address
+            );
+        }
+        for txid in checkpointed_mempool_txids {
+            peer.send(PeerMessage::TransactionRequest(txid)).await?;
+        }
+
+        // A peer whose tip is unknown to this node may have been partitioned
+        // from it. Ask the peer for the recent blocks and transactions this
+        // node is missing.
+        let peer_tip_header = &self.peer_handshake_data.tip_header;
+        let peer_tip_is_known = self
+            .global_state_lock
+            .lock_guard()
+            .await
+            .chain
+            .archival_state()
+            .block_height_to_block_headers(peer_tip_header.height)
+            .await
+            .contains(peer_tip_header);
+        if !peer_tip_is_known {
+            let summary = self.digest_summary().await;
+            peer.send(PeerMessage::DigestSummary(summary)).await?;
+        }
+
         let res = self.run(peer, from_main_rx, &mut peer_state).await;
         debug!("Exited peer loop for {}", self.peer_address);
 
@@ -1280,6 +2453,26 @@ impl PeerLoopHandler {
     }
 }
 
+/// Take the messages queued behind `first` off the channel, and put the
+/// announcements of blocks found by this node first, such that they are not
+/// held up by other messages.
+fn with_own_blocks_first(
+    first: MainToPeerTask,
+    from_main_rx: &mut broadcast::Receiver<MainToPeerTask>,
+) -> Vec<MainToPeerTask> {
+    let mut messages = vec![first];
+    loop {
+        match from_main_rx.try_recv() {
+            Ok(message) => messages.push(message),
+            Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+            Err(e @ TryRecvError::Lagged(_)) => panic!("Failed to read from main loop: {}", e),
+        }
+    }
+
+    messages.sort_by_key(|message| !matches!(message, MainToPeerTask::OwnBlock(_)));
+    messages
+}
+
 #[cfg(test)]
 mod peer_loop_tests {
     use num_traits::Zero;
@@ -1287,17 +2480,26 @@ mod peer_loop_tests {
     use rand::Rng;
     use rand::SeedableRng;
     use tasm_lib::twenty_first::bfe;
+    use tokio::io::DuplexStream;
     use tokio::sync::mpsc::error::TryRecvError;
+    use tokio_serde::formats::SymmetricalBincode;
+    use tokio_serde::SymmetricallyFramed;
+    use tokio_util::codec::Framed;
     use tracing_test::traced_test;
 
     use super::*;
+    use crate::config_models::cli_args;
     use crate::config_models::network::Network;
+    use crate::models::blockchain::block::validation_checkpoints::ValidationCheckpoint;
     use crate::models::blockchain::transaction::transaction_output::UtxoNotificationMedium;
     use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+    use crate::models::channel::MainToPeerTaskBatchBlockRequest;
+    use crate::models::peer::message_codec::PeerMessageCodec;
     use crate::models::peer::transaction_notification::TransactionNotification;
     use crate::models::proof_abstractions::tasm::program::TritonProverSync;
     use crate::models::state::tx_proving_capability::TxProvingCapability;
     use crate::models::state::wallet::WalletSecret;
+    use crate::tests::shared::get_dummy_peer;
     use crate::tests::shared::get_dummy_peer_connection_data_genesis;
     use crate::tests::shared::get_dummy_socket_address;
     use crate::tests::shared::get_test_genesis_setup;
@@ -1305,6 +2507,9 @@ mod peer_loop_tests {
     use crate::tests::shared::valid_sequence_of_blocks_for_tests;
     use crate::tests::shared::Action;
     use crate::tests::shared::Mock;
+    use crate::util_types::fault_injection;
+    use crate::util_types::fault_injection::BlockValidationStep;
+    use crate::util_types::fault_injection::FaultPoint;
     use crate::BFieldElement;
 
     #[traced_test]
@@ -1332,6 +2537,89 @@ mod peer_loop_tests {
         Ok(())
     }
 
+    #[traced_test]
+    #[tokio::test]
+    async fn pings_are_answered_and_pongs_measure_round_trip_time() -> Result<()> {
+        let network = Network::Alpha;
+        let (_peer_broadcast_tx, from_main_rx_clone, to_main_tx, _to_main_rx1, state_lock, hsd) =
+            get_test_genesis_setup(network, 1).await?;
+        let peer_address = get_dummy_socket_address(0);
+
+        let mock = Mock::new(vec![
+            Action::Read(PeerMessage::Ping(7)),
+            Action::Write(PeerMessage::Pong(7)),
+            Action::Read(PeerMessage::Pong(8)),
+            Action::Read(PeerMessage::Pong(9)),
+            Action::Read(PeerMessage::Bye),
+        ]);
+
+        let mut peer_loop_handler =
+            PeerLoopHandler::new(to_main_tx, state_lock.clone(), peer_address, hsd, false, 1);
+        let mut peer_state = MutablePeerState::new(BlockHeight::genesis());
+        peer_state.pending_ping = Some((9, Instant::now()));
+        peer_loop_handler
+            .run(mock, from_main_rx_clone, &mut peer_state)
+            .await?;
+
+        assert!(peer_state.pending_ping.is_none());
+        assert!(state_lock.lock_guard().await.net.peer_map[&peer_address]
+            .round_trip_time
+            .is_some());
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn only_one_rendezvous_introduction_per_request_is_accepted() -> Result<()> {
+        let network = Network::Alpha;
+        let (_peer_broadcast_tx, from_main_rx_clone, to_main_tx, mut to_main_rx1, state_lock, hsd) =
+            get_test_genesis_setup(network, 0).await?;
+        let peer_address = get_dummy_socket_address(0);
+        state_lock
+            .lock_guard_mut()
+            .await
+            .net
+            .peer_map
+            .insert(peer_address, get_dummy_peer(peer_address));
+
+        let introduction = PeerMessage::RendezvousIntroduction {
+            endpoint: get_dummy_socket_address(1),
+            initiator: true,
+        };
+        let mock = Mock::new(vec![
+            Action::Read(introduction.clone()),
+            Action::Read(introduction),
+            Action::Read(PeerMessage::Bye),
+        ]);
+
+        let mut peer_loop_handler =
+            PeerLoopHandler::new(to_main_tx, state_lock.clone(), peer_address, hsd, false, 1);
+        let mut peer_state = MutablePeerState::new(BlockHeight::genesis());
+        peer_state.pending_rendezvous_request = Some(Instant::now());
+        peer_loop_handler
+            .run(mock, from_main_rx_clone, &mut peer_state)
+            .await?;
+
+        assert!(matches!(
+            to_main_rx1.try_recv(),
+            Ok(PeerTaskToMain::RendezvousIntroduction { .. })
+        ));
+        assert!(
+            to_main_rx1.try_recv().is_err(),
+            "Unsolicited introduction must not be forwarded"
+        );
+        assert!(peer_state.pending_rendezvous_request.is_none());
+        assert_eq!(
+            Some(PeerSanctionReason::InvalidMessage),
+            state_lock.lock_guard().await.net.peer_map[&peer_address]
+                .standing
+                .latest_sanction
+        );
+
+        Ok(())
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn test_peer_loop_peer_list() {
@@ -1380,6 +2668,47 @@ mod peer_loop_tests {
         );
     }
 
+    #[traced_test]
+    #[tokio::test]
+    async fn reachability_is_checked_at_most_once_per_interval() -> Result<()> {
+        let (peer_broadcast_tx, _from_main_rx_clone, to_main_tx, _to_main_rx1, state_lock, _hsd) =
+            get_test_genesis_setup(Network::Alpha, 2).await?;
+
+        // A peer that does not listen cannot be reached. The repeated request
+        // goes unanswered, as does the unsolicited response.
+        let (mut hsd2, sa2) = get_dummy_peer_connection_data_genesis(Network::Alpha, 2).await;
+        hsd2.listen_port = None;
+        let mock = Mock::new(vec![
+            Action::Read(PeerMessage::ReachabilityRequest),
+            Action::Write(PeerMessage::ReachabilityResponse {
+                address: None,
+                reachable: false,
+            }),
+            Action::Read(PeerMessage::ReachabilityRequest),
+            Action::Read(PeerMessage::ReachabilityResponse {
+                address: Some(sa2),
+                reachable: true,
+            }),
+            Action::Read(PeerMessage::Bye),
+        ]);
+
+        let from_main_rx_clone = peer_broadcast_tx.subscribe();
+        let mut peer_loop_handler =
+            PeerLoopHandler::new(to_main_tx, state_lock.clone(), sa2, hsd2, true, 0);
+        peer_loop_handler
+            .run_wrapper(mock, from_main_rx_clone)
+            .await?;
+
+        assert!(state_lock
+            .lock_guard()
+            .await
+            .net
+            .reachability_reports
+            .is_empty());
+
+        Ok(())
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn different_genesis_test() -> Result<()> {
@@ -1455,10 +2784,10 @@ mod peer_loop_tests {
             .net
             .get_peer_standing_from_database(peer_address.ip())
             .await;
-        assert_eq!(-(u16::MAX as i32), peer_standing.unwrap().standing);
+        assert_eq!(-(u16::MAX as i32), peer_standing.as_ref().unwrap().standing);
         assert_eq!(
             PeerSanctionReason::DifferentGenesis,
-            peer_standing.unwrap().latest_sanction.unwrap()
+            peer_standing.as_ref().unwrap().latest_sanction.unwrap()
         );
 
         Ok(())
@@ -1557,6 +2886,16 @@ mod peer_loop_tests {
             "Peer must be sanctioned for sending a bad block"
         );
 
+        // Verify that the evidence was stored with it
+        let [violation] = standing.violations.as_slice() else {
+            bail!("Exactly one violation must be recorded");
+        };
+        assert_eq!(None, violation.failed_rule);
+        assert_eq!(
+            vec![block_without_valid_pow.hash(), genesis_block.hash()],
+            violation.digests
+        );
+
         Ok(())
     }
 
@@ -1785,124 +3124,611 @@ mod peer_loop_tests {
 
     #[traced_test]
     #[tokio::test]
-    async fn find_canonical_chain_when_multiple_blocks_at_same_height_test() -> Result<()> {
-        // Scenario: A fork began at block 2, node knows two blocks of height 2 and two of height 3.
-        // A peer requests a block at height 2. Verify that the correct block at height 2 is
-        // returned.
-
+    async fn too_long_block_batch_response_is_punished() -> Result<()> {
         let network = Network::Main;
-        let (_peer_broadcast_tx, from_main_rx_clone, to_main_tx, _to_main_rx1, mut state_lock, hsd) =
+        let (_peer_broadcast_tx, from_main_rx_clone, to_main_tx, _to_main_rx1, state_lock, hsd) =
             get_test_genesis_setup(network, 0).await?;
-        let genesis_block = Block::genesis_block(network);
         let peer_address = get_dummy_socket_address(0);
-
-        let [block_1, block_2_a, block_3_a] = valid_sequence_of_blocks_for_tests(
-            &genesis_block,
-            Timestamp::hours(1),
-            StdRng::seed_from_u64(5550001).gen(),
-        )
-        .await;
-        let [block_2_b, block_3_b] = valid_sequence_of_blocks_for_tests(
-            &block_1,
+        let [block_1] = valid_sequence_of_blocks_for_tests(
+            &Block::genesis_block(network),
             Timestamp::hours(1),
-            StdRng::seed_from_u64(5550002).gen(),
+            rand::random(),
         )
         .await;
-        assert_ne!(block_2_a.hash(), block_2_b.hash());
-
-        state_lock.set_new_tip(block_1.clone()).await?;
-        state_lock.set_new_tip(block_2_a.clone()).await?;
-        state_lock.set_new_tip(block_2_b.clone()).await?;
-        state_lock.set_new_tip(block_3_b.clone()).await?;
-        state_lock.set_new_tip(block_3_a.clone()).await?;
+        let transfer_block: TransferBlock = block_1.try_into().unwrap();
 
         let mock = Mock::new(vec![
-            Action::Read(PeerMessage::BlockRequestByHeight(2.into())),
-            Action::Write(PeerMessage::Block(Box::new(block_2_a.try_into().unwrap()))),
-            Action::Read(PeerMessage::BlockRequestByHeight(3.into())),
-            Action::Write(PeerMessage::Block(Box::new(
-                block_3_a.clone().try_into().unwrap(),
-            ))),
+            Action::Read(PeerMessage::BlockResponseBatch(vec![
+                transfer_block;
+                MAXIMUM_BLOCK_BATCH_SIZE
+                    + 1
+            ])),
             Action::Read(PeerMessage::Bye),
         ]);
-
-        let mut peer_loop_handler = PeerLoopHandler::with_mocked_time(
+        let mut peer_loop_handler = PeerLoopHandler::new(
             to_main_tx.clone(),
             state_lock.clone(),
             peer_address,
             hsd,
             false,
             1,
-            block_3_a.header().timestamp,
         );
-
-        // This will return error if seen read/write order does not match that of the
-        // mocked object.
         peer_loop_handler
             .run_wrapper(mock, from_main_rx_clone)
             .await?;
 
+        let peer_standing = state_lock
+            .lock_guard()
+            .await
+            .net
+            .get_peer_standing_from_database(peer_address.ip())
+            .await
+            .unwrap();
+        assert_eq!(
+            PeerSanctionReason::TooLongBlockBatch,
+            peer_standing.latest_sanction.unwrap()
+        );
+
         Ok(())
     }
 
     #[traced_test]
     #[tokio::test]
-    async fn test_peer_loop_receival_of_first_block() -> Result<()> {
-        // Scenario: client only knows genesis block. Then receives block 1.
-
+    async fn short_block_batch_response_from_pruned_peer_is_not_punished() -> Result<()> {
         let network = Network::Main;
-        let mut rng = StdRng::seed_from_u64(5550001);
-        let (_peer_broadcast_tx, from_main_rx_clone, to_main_tx, mut to_main_rx1, state_lock, hsd) =
+        let (_peer_broadcast_tx, from_main_rx_clone, to_main_tx, _to_main_rx1, state_lock, hsd) =
             get_test_genesis_setup(network, 0).await?;
         let peer_address = get_dummy_socket_address(0);
-        let genesis_block: Block = state_lock
-            .lock_guard()
-            .await
-            .chain
-            .archival_state()
-            .get_tip()
-            .await;
-
-        let now = genesis_block.header().timestamp + Timestamp::hours(2);
-        let fee = NeptuneCoins::zero();
-        let block_1 = valid_block_for_tests(&state_lock, fee, now, rng.gen()).await;
+        let [block_1] = valid_sequence_of_blocks_for_tests(
+            &Block::genesis_block(network),
+            Timestamp::hours(1),
+            rand::random(),
+        )
+        .await;
+        let transfer_block: TransferBlock = block_1.try_into().unwrap();
 
         let mock = Mock::new(vec![
-            Action::Read(PeerMessage::Block(Box::new(
-                block_1.clone().try_into().unwrap(),
-            ))),
+            Action::Read(PeerMessage::BlockResponseBatch(vec![transfer_block])),
             Action::Read(PeerMessage::Bye),
         ]);
-
-        let mut peer_loop_handler = PeerLoopHandler::with_mocked_time(
+        let pruned_hsd = HandshakeData {
+            is_archival_node: false,
+            ..hsd
+        };
+        let mut peer_loop_handler = PeerLoopHandler::new(
             to_main_tx.clone(),
             state_lock.clone(),
             peer_address,
-            hsd,
+            pruned_hsd,
             false,
             1,
-            block_1.header().timestamp,
         );
         peer_loop_handler
             .run_wrapper(mock, from_main_rx_clone)
             .await?;
 
-        // Verify that peer max block height was sent
-        match to_main_rx1.recv().await {
-            Some(PeerTaskToMain::AddPeerMaxBlockHeight(_)) => (),
-            _ => bail!("Must receive add of peer block max height"),
-        }
+        assert!(!state_lock
+            .lock_guard()
+            .await
+            .net
+            .get_peer_standing_from_database(peer_address.ip())
+            .await
+            .is_some_and(|standing| standing.latest_sanction.is_some()));
 
-        // Verify that a block was sent to `main_loop`
-        match to_main_rx1.recv().await {
-            Some(PeerTaskToMain::NewBlocks(_block)) => (),
-            _ => bail!("Did not find msg sent to main task"),
-        };
+        Ok(())
+    }
 
-        match to_main_rx1.recv().await {
-            Some(PeerTaskToMain::RemovePeerMaxBlockHeight(_)) => (),
-            _ => bail!("Must receive remove of peer block max height"),
-        }
+    /// Both ends of a connection between two peer loops, framed as by
+    /// [`connect_to_peers`](crate::connect_to_peers).
+    fn connected_streams_for_tests(
+        network: Network,
+    ) -> [SymmetricallyFramed<
+        Framed<DuplexStream, PeerMessageCodec>,
+        PeerMessage,
+        SymmetricalBincode<PeerMessage>,
+    >; 2] {
+        let (stream_a, stream_b) = tokio::io::duplex(1 << 24);
+        [stream_a, stream_b].map(|stream| {
+            SymmetricallyFramed::new(
+                Framed::new(stream, PeerMessageCodec::new(network.message_size_limits())),
+                SymmetricalBincode::default(),
+            )
+        })
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn sync_from_peer_with_used_up_quota_gets_minimum_batches() -> Result<()> {
+        let network = Network::Main;
+        let genesis_block = Block::genesis_block(network);
+        let blocks: [Block; 3] = valid_sequence_of_blocks_for_tests(
+            &genesis_block,
+            Timestamp::hours(1),
+            StdRng::seed_from_u64(5550001).gen(),
+        )
+        .await;
+        let now = blocks[2].header().timestamp;
+
+        // The responder may serve one block per minute.
+        let (
+            _responder_main_tx,
+            responder_from_main_rx,
+            responder_to_main_tx,
+            _responder_to_main_rx,
+            mut responder_state,
+            responder_hsd,
+        ) = get_test_genesis_setup(network, 0).await?;
+        for block in &blocks {
+            responder_state.set_new_tip(block.clone()).await?;
+        }
+        responder_state
+            .set_cli(cli_args::Args {
+                network,
+                max_peer_blocks_served_per_minute: 1,
+                ..Default::default()
+            })
+            .await;
+        let responder_hsd = HandshakeData {
+            tip_header: blocks[2].header().clone(),
+            ..responder_hsd
+        };
+
+        let (
+            requester_main_tx,
+            requester_from_main_rx,
+            requester_to_main_tx,
+            mut requester_to_main_rx,
+            requester_state,
+            requester_hsd,
+        ) = get_test_genesis_setup(network, 0).await?;
+        requester_state.lock_guard_mut().await.net.syncing = true;
+
+        let requester_address = get_dummy_socket_address(1);
+        let responder_address = get_dummy_socket_address(2);
+        let [requester_stream, responder_stream] = connected_streams_for_tests(network);
+        let mut responder = PeerLoopHandler::with_mocked_time(
+            responder_to_main_tx,
+            responder_state,
+            requester_address,
+            requester_hsd,
+            true,
+            1,
+            now,
+        );
+        let mut requester = PeerLoopHandler::with_mocked_time(
+            requester_to_main_tx,
+            requester_state.clone(),
+            responder_address,
+            responder_hsd,
+            false,
+            1,
+            now,
+        );
+        tokio::spawn(async move {
+            responder
+                .run_wrapper(responder_stream, responder_from_main_rx)
+                .await
+        });
+        tokio::spawn(async move {
+            requester
+                .run_wrapper(requester_stream, requester_from_main_rx)
+                .await
+        });
+
+        // Every batch is served, at the minimum size once the quota is used up.
+        for _ in 0..3 {
+            requester_main_tx.send(MainToPeerTask::RequestBlockBatch(
+                MainToPeerTaskBatchBlockRequest {
+                    peer_addr_target: responder_address,
+                    known_blocks: vec![genesis_block.hash()],
+                },
+            ))?;
+            let received_blocks = loop {
+                match requester_to_main_rx.recv().await {
+                    Some(PeerTaskToMain::NewBlocks(received_blocks)) => break received_blocks,
+                    Some(_) => continue,
+                    None => bail!("Requester must stay connected"),
+                }
+            };
+            assert_eq!(
+                vec![blocks[0].hash(), blocks[1].hash()],
+                received_blocks
+                    .iter()
+                    .map(|block| block.hash())
+                    .collect_vec()
+            );
+        }
+        assert!(requester_state
+            .lock_guard()
+            .await
+            .net
+            .peer_map
+            .get(&responder_address)
+            .is_some_and(|peer_info| peer_info.standing.latest_sanction.is_none()));
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn peer_is_sanctioned_for_block_failing_injected_validation_fault() -> Result<()> {
+        let network = Network::Main;
+        let (_peer_broadcast_tx, from_main_rx_clone, to_main_tx, mut to_main_rx1, state_lock, hsd) =
+            get_test_genesis_setup(network, 0).await?;
+        let peer_address = get_dummy_socket_address(0);
+        let [block_1] = valid_sequence_of_blocks_for_tests(
+            &Block::genesis_block(network),
+            Timestamp::hours(1),
+            rand::random(),
+        )
+        .await;
+
+        fault_injection::arm(
+            FaultPoint::BlockValidation {
+                block_digest: block_1.hash(),
+                step: BlockValidationStep::Coinbase,
+            },
+            0,
+        );
+        let mock = Mock::new(vec![
+            Action::Read(PeerMessage::Block(Box::new(
+                block_1.clone().try_into().unwrap(),
+            ))),
+            Action::Read(PeerMessage::Bye),
+        ]);
+        let mut peer_loop_handler = PeerLoopHandler::new(
+            to_main_tx.clone(),
+            state_lock.clone(),
+            peer_address,
+            hsd,
+            false,
+            1,
+        );
+        peer_loop_handler
+            .run_wrapper(mock, from_main_rx_clone)
+            .await?;
+
+        // The block must not be passed on to the main loop
+        match to_main_rx1.recv().await {
+            Some(PeerTaskToMain::AddPeerMaxBlockHeight(_)) => (),
+            _ => bail!("Must receive add of peer block max height"),
+        }
+        match to_main_rx1.recv().await {
+            Some(PeerTaskToMain::RemovePeerMaxBlockHeight(_)) => (),
+            _ => bail!("Block failing validation must not be sent to main loop"),
+        }
+
+        let peer_standing = state_lock
+            .lock_guard()
+            .await
+            .net
+            .get_peer_standing_from_database(peer_address.ip())
+            .await
+            .unwrap();
+        assert_eq!(
+            PeerSanctionReason::InvalidBlock((block_1.header().height, block_1.hash())),
+            peer_standing.latest_sanction.unwrap()
+        );
+        assert_eq!(
+            Some(BlockValidationStep::Coinbase),
+            peer_standing.violations.last().unwrap().failed_rule
+        );
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn peer_is_not_sanctioned_for_injected_proof_fault_below_checkpoint_during_sync(
+    ) -> Result<()> {
+        let network = Network::Main;
+        let (
+            _peer_broadcast_tx,
+            from_main_rx_clone,
+            to_main_tx,
+            mut to_main_rx1,
+            mut state_lock,
+            hsd,
+        ) = get_test_genesis_setup(network, 0).await?;
+        let peer_address = get_dummy_socket_address(0);
+        let [block_1, block_2] = valid_sequence_of_blocks_for_tests(
+            &Block::genesis_block(network),
+            Timestamp::hours(1),
+            rand::random(),
+        )
+        .await;
+
+        let mut cli = state_lock.cli().clone();
+        cli.checkpoint = vec![ValidationCheckpoint {
+            height: block_2.header().height,
+            digest: block_2.hash(),
+        }];
+        state_lock.set_cli(cli).await;
+        state_lock.lock_guard_mut().await.net.syncing = true;
+
+        // Block proofs below the checkpoint are not verified during the
+        // initial sync, so this fault is never hit.
+        let proof_fault = FaultPoint::BlockValidation {
+            block_digest: block_1.hash(),
+            step: BlockValidationStep::Proof,
+        };
+        fault_injection::arm(proof_fault.clone(), 0);
+        let mock = Mock::new(vec![
+            Action::Read(PeerMessage::BlockResponseBatch(vec![
+                block_1.clone().try_into().unwrap(),
+                block_2.clone().try_into().unwrap(),
+            ])),
+            Action::Read(PeerMessage::Bye),
+        ]);
+        let mut peer_loop_handler = PeerLoopHandler::new(
+            to_main_tx.clone(),
+            state_lock.clone(),
+            peer_address,
+            hsd,
+            false,
+            1,
+        );
+        peer_loop_handler
+            .run_wrapper(mock, from_main_rx_clone)
+            .await?;
+        fault_injection::disarm(&proof_fault);
+
+        let mut blocks_sent_to_main = vec![];
+        while let Ok(message) = to_main_rx1.try_recv() {
+            if let PeerTaskToMain::NewBlocks(blocks) = message {
+                blocks_sent_to_main.extend(blocks);
+            }
+        }
+        assert_eq!(vec![block_1, block_2], blocks_sent_to_main);
+
+        let peer_standing = state_lock
+            .lock_guard()
+            .await
+            .net
+            .get_peer_standing_from_database(peer_address.ip())
+            .await;
+        assert!(peer_standing
+            .and_then(|standing| standing.latest_sanction)
+            .is_none());
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn compact_block_request_and_missing_removal_records_are_served() -> Result<()> {
+        let network = Network::Main;
+        let (_peer_broadcast_tx, from_main_rx_clone, to_main_tx, _to_main_rx1, mut state_lock, hsd) =
+            get_test_genesis_setup(network, 0).await?;
+        let peer_address = get_dummy_socket_address(0);
+        let [block_1] = valid_sequence_of_blocks_for_tests(
+            &Block::genesis_block(network),
+            Timestamp::hours(1),
+            StdRng::seed_from_u64(5550001).gen(),
+        )
+        .await;
+        state_lock.set_new_tip(block_1.clone()).await?;
+
+        let compact_block = CompactBlock::new(&block_1)?;
+        let num_inputs = block_1.kernel.body.transaction_kernel.inputs.len();
+        let mock = Mock::new(vec![
+            Action::Read(PeerMessage::CompactBlockRequest(block_1.hash())),
+            Action::Write(PeerMessage::CompactBlock(Box::new(compact_block))),
+            Action::Read(PeerMessage::GetBlockTxn {
+                block_digest: block_1.hash(),
+                indices: (0..num_inputs).collect(),
+            }),
+            Action::Write(PeerMessage::BlockTxn {
+                block_digest: block_1.hash(),
+                removal_records: block_1.kernel.body.transaction_kernel.inputs.clone(),
+            }),
+            Action::Read(PeerMessage::Bye),
+        ]);
+
+        let mut peer_loop_handler = PeerLoopHandler::new(
+            to_main_tx.clone(),
+            state_lock.clone(),
+            peer_address,
+            hsd,
+            false,
+            1,
+        );
+        peer_loop_handler
+            .run_wrapper(mock, from_main_rx_clone)
+            .await?;
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn find_canonical_chain_when_multiple_blocks_at_same_height_test() -> Result<()> {
+        // Scenario: A fork began at block 2, node knows two blocks of height 2 and two of height 3.
+        // A peer requests a block at height 2. Verify that the correct block at height 2 is
+        // returned.
+
+        let network = Network::Main;
+        let (_peer_broadcast_tx, from_main_rx_clone, to_main_tx, _to_main_rx1, mut state_lock, hsd) =
+            get_test_genesis_setup(network, 0).await?;
+        let genesis_block = Block::genesis_block(network);
+        let peer_address = get_dummy_socket_address(0);
+
+        let [block_1, block_2_a, block_3_a] = valid_sequence_of_blocks_for_tests(
+            &genesis_block,
+            Timestamp::hours(1),
+            StdRng::seed_from_u64(5550001).gen(),
+        )
+        .await;
+        let [block_2_b, block_3_b] = valid_sequence_of_blocks_for_tests(
+            &block_1,
+            Timestamp::hours(1),
+            StdRng::seed_from_u64(5550002).gen(),
+        )
+        .await;
+        assert_ne!(block_2_a.hash(), block_2_b.hash());
+
+        state_lock.set_new_tip(block_1.clone()).await?;
+        state_lock.set_new_tip(block_2_a.clone()).await?;
+        state_lock.set_new_tip(block_2_b.clone()).await?;
+        state_lock.set_new_tip(block_3_b.clone()).await?;
+        state_lock.set_new_tip(block_3_a.clone()).await?;
+
+        let mock = Mock::new(vec![
+            Action::Read(PeerMessage::BlockRequestByHeight(2.into())),
+            Action::Write(PeerMessage::Block(Box::new(block_2_a.try_into().unwrap()))),
+            Action::Read(PeerMessage::BlockRequestByHeight(3.into())),
+            Action::Write(PeerMessage::Block(Box::new(
+                block_3_a.clone().try_into().unwrap(),
+            ))),
+            Action::Read(PeerMessage::Bye),
+        ]);
+
+        let mut peer_loop_handler = PeerLoopHandler::with_mocked_time(
+            to_main_tx.clone(),
+            state_lock.clone(),
+            peer_address,
+            hsd,
+            false,
+            1,
+            block_3_a.header().timestamp,
+        );
+
+        // This will return error if seen read/write order does not match that of the
+        // mocked object.
+        peer_loop_handler
+            .run_wrapper(mock, from_main_rx_clone)
+            .await?;
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn own_block_is_served_before_it_is_stored() -> Result<()> {
+        let network = Network::Main;
+        let (_peer_broadcast_tx, from_main_rx_clone, to_main_tx, _to_main_rx1, state_lock, hsd) =
+            get_test_genesis_setup(network, 0).await?;
+        let genesis_block = Block::genesis_block(network);
+        let peer_address = get_dummy_socket_address(0);
+
+        let [block_1] = valid_sequence_of_blocks_for_tests(
+            &genesis_block,
+            Timestamp::hours(1),
+            StdRng::seed_from_u64(5550001).gen(),
+        )
+        .await;
+        let own_block = Arc::new(OwnBlockAnnouncement::new(&block_1)?);
+        state_lock.lock_guard_mut().await.net.own_block_announcement = Some(own_block.clone());
+
+        let mock = Mock::new(vec![
+            Action::Read(PeerMessage::BlockRequestByHeight(1.into())),
+            Action::Write(PeerMessage::Block(Box::new(
+                block_1.clone().try_into().unwrap(),
+            ))),
+            Action::Read(PeerMessage::CompactBlockRequest(block_1.hash())),
+            Action::Write(PeerMessage::CompactBlock(Box::new(
+                own_block.compact_block.clone().unwrap(),
+            ))),
+            Action::Read(PeerMessage::Bye),
+        ]);
+
+        let mut peer_loop_handler = PeerLoopHandler::with_mocked_time(
+            to_main_tx.clone(),
+            state_lock.clone(),
+            peer_address,
+            hsd,
+            false,
+            1,
+            block_1.header().timestamp,
+        );
+        peer_loop_handler
+            .run_wrapper(mock, from_main_rx_clone)
+            .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn own_blocks_are_handled_before_queued_messages() {
+        let genesis_block = Block::genesis_block(Network::Main);
+        let [block_1] = valid_sequence_of_blocks_for_tests(
+            &genesis_block,
+            Timestamp::hours(1),
+            StdRng::seed_from_u64(5550001).gen(),
+        )
+        .await;
+        let own_block = Arc::new(OwnBlockAnnouncement::new(&block_1).unwrap());
+
+        let (tx, mut rx) = broadcast::channel(10);
+        tx.send(MainToPeerTask::MakePeerDiscoveryRequest).unwrap();
+        tx.send(MainToPeerTask::OwnBlock(own_block)).unwrap();
+        let first = rx.recv().await.unwrap();
+
+        let types = with_own_blocks_first(first, &mut rx)
+            .iter()
+            .map(|message| message.get_type())
+            .collect_vec();
+        assert_eq!(vec!["own block", "make peer discovery req"], types);
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_peer_loop_receival_of_first_block() -> Result<()> {
+        // Scenario: client only knows genesis block. Then receives block 1.
+
+        let network = Network::Main;
+        let mut rng = StdRng::seed_from_u64(5550001);
+        let (_peer_broadcast_tx, from_main_rx_clone, to_main_tx, mut to_main_rx1, state_lock, hsd) =
+            get_test_genesis_setup(network, 0).await?;
+        let peer_address = get_dummy_socket_address(0);
+        let genesis_block: Block = state_lock
+            .lock_guard()
+            .await
+            .chain
+            .archival_state()
+            .get_tip()
+            .await;
+
+        let now = genesis_block.header().timestamp + Timestamp::hours(2);
+        let fee = NeptuneCoins::zero();
+        let block_1 = valid_block_for_tests(&state_lock, fee, now, rng.gen()).await;
+
+        let mock = Mock::new(vec![
+            Action::Read(PeerMessage::Block(Box::new(
+                block_1.clone().try_into().unwrap(),
+            ))),
+            Action::Read(PeerMessage::Bye),
+        ]);
+
+        let mut peer_loop_handler = PeerLoopHandler::with_mocked_time(
+            to_main_tx.clone(),
+            state_lock.clone(),
+            peer_address,
+            hsd,
+            false,
+            1,
+            block_1.header().timestamp,
+        );
+        peer_loop_handler
+            .run_wrapper(mock, from_main_rx_clone)
+            .await?;
+
+        // Verify that peer max block height was sent
+        match to_main_rx1.recv().await {
+            Some(PeerTaskToMain::AddPeerMaxBlockHeight(_)) => (),
+            _ => bail!("Must receive add of peer block max height"),
+        }
+
+        // Verify that a block was sent to `main_loop`
+        match to_main_rx1.recv().await {
+            Some(PeerTaskToMain::NewBlocks(_block)) => (),
+            _ => bail!("Did not find msg sent to main task"),
+        };
+
+        match to_main_rx1.recv().await {
+            Some(PeerTaskToMain::RemovePeerMaxBlockHeight(_)) => (),
+            _ => bail!("Must receive remove of peer block max height"),
+        }
 
         if !state_lock.lock_guard().await.net.peer_map.is_empty() {
             bail!("peer map must be empty after closing connection gracefully");
@@ -1992,8 +3818,9 @@ mod peer_loop_tests {
     #[tokio::test]
     async fn prevent_ram_exhaustion_test() -> Result<()> {
         // In this scenario the peer sends more blocks than the client allows to store in the
-        // fork-reconciliation field. This should result in abandonment of the fork-reconciliation
-        // process as the alternative is that the program will crash because it runs out of RAM.
+        // fork-reconciliation field. The block exceeding that limit is kept as an orphan instead,
+        // in a pool of bounded size, as the alternative is that the program will crash because
+        // it runs out of RAM.
 
         let network = Network::Main;
         let mut rng = StdRng::seed_from_u64(5550001);
@@ -2013,7 +3840,7 @@ mod peer_loop_tests {
         state_lock.set_cli(cli).await;
 
         let (hsd1, peer_address1) = get_dummy_peer_connection_data_genesis(Network::Alpha, 1).await;
-        let [block_1, _block_2, block_3, block_4] =
+        let [block_1, block_2, block_3, block_4] =
             valid_sequence_of_blocks_for_tests(&genesis_block, Timestamp::hours(1), rng.gen())
                 .await;
         state_lock.set_new_tip(block_1.clone()).await?;
@@ -2026,6 +3853,7 @@ mod peer_loop_tests {
             Action::Read(PeerMessage::Block(Box::new(
                 block_3.clone().try_into().unwrap(),
             ))),
+            Action::Write(PeerMessage::BlockRequestByHash(block_2.hash())),
             Action::Read(PeerMessage::Bye),
         ]);
 
@@ -2047,6 +3875,18 @@ mod peer_loop_tests {
             Some(PeerTaskToMain::AddPeerMaxBlockHeight(_)) => (),
             _ => bail!("Must receive add of peer block max height"),
         }
+
+        // Verify that the parent of the orphan is requested from all peers
+        match to_main_rx1.recv().await {
+            Some(PeerTaskToMain::OrphanParentMissing {
+                parent_digest,
+                requested_from,
+            }) => {
+                assert_eq!(block_2.hash(), parent_digest);
+                assert_eq!(peer_address1, requested_from);
+            }
+            _ => bail!("Must ask main task to request parent of orphan"),
+        }
         match to_main_rx1.recv().await {
             Some(PeerTaskToMain::RemovePeerMaxBlockHeight(_)) => (),
             _ => bail!("Must receive remove of peer block max height"),
@@ -2059,16 +3899,160 @@ mod peer_loop_tests {
         };
         drop(to_main_tx);
 
-        // Verify that peer is sanctioned for failed fork reconciliation attempt
+        // Verify that the block beyond the limit is kept as an orphan, and
+        // that the peer is not sanctioned for sending it
         assert!(state_lock
+            .lock_guard()
+            .await
+            .net
+            .orphan_blocks
+            .contains(block_3.hash()));
+        assert!(!state_lock
             .lock_guard()
             .await
             .net
             .get_peer_standing_from_database(peer_address1.ip())
             .await
-            .unwrap()
-            .standing
-            .is_negative());
+            .is_some_and(|standing| standing.standing.is_negative()));
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn parent_of_orphan_is_requested_from_all_peers() -> Result<()> {
+        // The peer sends block 4 and then block 2, which neither has a known
+        // parent nor continues the fork reconciliation. Block 2 is kept as an
+        // orphan, and its parent is requested from this and all other peers.
+        let network = Network::Main;
+        let (_peer_broadcast_tx, from_main_rx_clone, to_main_tx, mut to_main_rx1, state_lock, hsd) =
+            get_test_genesis_setup(network, 0).await?;
+        let peer_address = get_dummy_socket_address(0);
+        let genesis_block = Block::genesis_block(network);
+        let [block_1, block_2, block_3, block_4] =
+            valid_sequence_of_blocks_for_tests(&genesis_block, Timestamp::hours(1), rand::random())
+                .await;
+
+        let mock = Mock::new(vec![
+            Action::Read(PeerMessage::Block(Box::new(
+                block_4.clone().try_into().unwrap(),
+            ))),
+            Action::Write(PeerMessage::BlockRequestByHash(block_3.hash())),
+            Action::Read(PeerMessage::Block(Box::new(
+                block_2.clone().try_into().unwrap(),
+            ))),
+            Action::Write(PeerMessage::BlockRequestByHash(block_1.hash())),
+            Action::Read(PeerMessage::Bye),
+        ]);
+
+        let mut peer_loop_handler = PeerLoopHandler::with_mocked_time(
+            to_main_tx.clone(),
+            state_lock.clone(),
+            peer_address,
+            hsd,
+            false,
+            1,
+            block_4.header().timestamp,
+        );
+        peer_loop_handler
+            .run_wrapper(mock, from_main_rx_clone)
+            .await?;
+
+        match to_main_rx1.recv().await {
+            Some(PeerTaskToMain::AddPeerMaxBlockHeight(_)) => (),
+            _ => bail!("Must receive add of peer block max height"),
+        }
+        match to_main_rx1.recv().await {
+            Some(PeerTaskToMain::OrphanParentMissing {
+                parent_digest,
+                requested_from,
+            }) => {
+                assert_eq!(block_1.hash(), parent_digest);
+                assert_eq!(peer_address, requested_from);
+            }
+            _ => bail!("Must ask main task to request parent of orphan"),
+        }
+        match to_main_rx1.recv().await {
+            Some(PeerTaskToMain::RemovePeerMaxBlockHeight(_)) => (),
+            _ => bail!("Must receive remove of peer block max height"),
+        }
+        assert!(state_lock
+            .lock_guard()
+            .await
+            .net
+            .orphan_blocks
+            .contains(block_2.hash()));
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn orphan_out_of_reach_is_dropped_without_sanction() -> Result<()> {
+        // The peer sends block 4 and then a block 2 timestamped too far into
+        // the future. The latter is not kept as an orphan and its parent is
+        // not requested, but the peer is not sanctioned, since it did not
+        // send an invalid block.
+        let network = Network::Main;
+        let (_peer_broadcast_tx, from_main_rx_clone, to_main_tx, mut to_main_rx1, state_lock, hsd) =
+            get_test_genesis_setup(network, 0).await?;
+        let peer_address = get_dummy_socket_address(0);
+        let genesis_block = Block::genesis_block(network);
+        let [_block_1, mut block_2, block_3, block_4] =
+            valid_sequence_of_blocks_for_tests(&genesis_block, Timestamp::hours(1), rand::random())
+                .await;
+        let now = block_4.header().timestamp;
+        let difficulty = block_2.header().difficulty;
+        block_2.set_header_timestamp_and_difficulty(
+            now + FUTUREDATING_LIMIT + Timestamp::hours(1),
+            difficulty,
+        );
+
+        let mock = Mock::new(vec![
+            Action::Read(PeerMessage::Block(Box::new(
+                block_4.clone().try_into().unwrap(),
+            ))),
+            Action::Write(PeerMessage::BlockRequestByHash(block_3.hash())),
+            Action::Read(PeerMessage::Block(Box::new(
+                block_2.clone().try_into().unwrap(),
+            ))),
+            Action::Read(PeerMessage::Bye),
+        ]);
+
+        let mut peer_loop_handler = PeerLoopHandler::with_mocked_time(
+            to_main_tx.clone(),
+            state_lock.clone(),
+            peer_address,
+            hsd,
+            false,
+            1,
+            now,
+        );
+        peer_loop_handler
+            .run_wrapper(mock, from_main_rx_clone)
+            .await?;
+
+        match to_main_rx1.recv().await {
+            Some(PeerTaskToMain::AddPeerMaxBlockHeight(_)) => (),
+            _ => bail!("Must receive add of peer block max height"),
+        }
+        match to_main_rx1.recv().await {
+            Some(PeerTaskToMain::RemovePeerMaxBlockHeight(_)) => (),
+            _ => bail!("Parent of implausible orphan must not be requested"),
+        }
+        assert!(!state_lock
+            .lock_guard()
+            .await
+            .net
+            .orphan_blocks
+            .contains(block_2.hash()));
+        assert!(!state_lock
+            .lock_guard()
+            .await
+            .net
+            .get_peer_standing_from_database(peer_address.ip())
+            .await
+            .is_some_and(|standing| standing.latest_sanction.is_some()));
 
         Ok(())
     }
@@ -0,0 +1,560 @@
+//! Forwarding of the peer listen port by the router, for nodes behind NAT.
+//!
+//! Home routers drop incoming connections unless told to forward them. With
+//! `--port-mapping`, the node asks the router at startup to forward its listen
+//! port, through UPnP (IGD) if the router answers SSDP discovery and through
+//! NAT-PMP otherwise. The external address the router reports is announced in
+//! the handshake, so peers can connect back and share the address with others.
+//! Mappings are leased for [`MAPPING_LEASE`] and renewed while the node runs;
+//! the router drops them on its own once the node is gone.
+
+use std::fmt::Display;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::net::SocketAddrV4;
+use std::time::Duration;
+
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use anyhow::Result;
+use regex::Regex;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::net::UdpSocket;
+use tracing::debug;
+use tracing::info;
+use tracing::warn;
+
+use crate::models::state::GlobalStateLock;
+
+/// How long the router keeps a mapping without renewal.
+pub const MAPPING_LEASE: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// How long to wait for the router to answer discovery and requests.
+const GATEWAY_TIMEOUT: Duration = Duration::from_secs(3);
+
+const SSDP_MULTICAST_ADDRESS: SocketAddrV4 =
+    SocketAddrV4::new(Ipv4Addr::new(239, 255, 255, 250), 1900);
+const NAT_PMP_PORT: u16 = 5351;
+
+/// WAN connection services of an internet gateway device that can forward
+/// ports, in order of preference.
+const UPNP_WAN_SERVICES: [&str; 3] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:2",
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortMappingProtocol {
+    Upnp,
+    NatPmp,
+}
+
+impl Display for PortMappingProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortMappingProtocol::Upnp => write!(f, "UPnP"),
+            PortMappingProtocol::NatPmp => write!(f, "NAT-PMP"),
+        }
+    }
+}
+
+/// A router that forwards ports on request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Gateway {
+    Upnp {
+        /// Address of the HTTP server of the device
+        host: SocketAddr,
+
+        /// Path of the control endpoint of the WAN connection service
+        control_path: String,
+        service_type: String,
+
+        /// Address of this machine on the router's network
+        local_ip: IpAddr,
+    },
+    NatPmp(SocketAddrV4),
+}
+
+/// A port forwarded by the router to this node's listen port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortMapping {
+    pub protocol: PortMappingProtocol,
+
+    /// Address under which peers outside the router's network reach this node
+    pub external_address: SocketAddr,
+    listen_port: u16,
+    gateway: Gateway,
+}
+
+impl PortMapping {
+    /// Ask the router to forward `listen_port`, trying UPnP first and NAT-PMP
+    /// second.
+    pub async fn create(listen_port: u16) -> Result<Self> {
+        let upnp_error = match Self::create_with_upnp(listen_port).await {
+            Ok(mapping) => return Ok(mapping),
+            Err(err) => err,
+        };
+        debug!("UPnP port mapping failed: {upnp_error:#}");
+
+        Self::create_with_nat_pmp(listen_port)
+            .await
+            .with_context(|| {
+                format!("neither UPnP ({upnp_error:#}) nor NAT-PMP could map the port")
+            })
+    }
+
+    async fn create_with_upnp(listen_port: u16) -> Result<Self> {
+        let gateway = discover_upnp_gateway().await?;
+        let mapping = Self {
+            protocol: PortMappingProtocol::Upnp,
+            external_address: SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), listen_port),
+            listen_port,
+            gateway,
+        };
+
+        mapping.renew().await
+    }
+
+    async fn create_with_nat_pmp(listen_port: u16) -> Result<Self> {
+        let gateway = default_gateway().context("no default IPv4 gateway found")?;
+        let mapping = Self {
+            protocol: PortMappingProtocol::NatPmp,
+            external_address: SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), listen_port),
+            listen_port,
+            gateway: Gateway::NatPmp(SocketAddrV4::new(gateway, NAT_PMP_PORT)),
+        };
+
+        mapping.renew().await
+    }
+
+    /// Request the mapping from the router again, extending its lease. The
+    /// router may assign a different external address.
+    pub async fn renew(&self) -> Result<Self> {
+        let external_address = match &self.gateway {
+            Gateway::Upnp {
+                host,
+                control_path,
+                service_type,
+                local_ip,
+            } => {
+                let response = soap_request(
+                    *host,
+                    control_path,
+                    service_type,
+                    "GetExternalIPAddress",
+                    &[],
+                )
+                .await?;
+                let external_ip = xml_element(&response, "NewExternalIPAddress")
+                    .context("router did not report its external IP address")?
+                    .parse::<IpAddr>()
+                    .context("router reported an invalid external IP address")?;
+
+                let listen_port = self.listen_port.to_string();
+                let local_ip = local_ip.to_string();
+                let lease = MAPPING_LEASE.as_secs().to_string();
+                soap_request(
+                    *host,
+                    control_path,
+                    service_type,
+                    "AddPortMapping",
+                    &[
+                        ("NewRemoteHost", ""),
+                        ("NewExternalPort", &listen_port),
+                        ("NewProtocol", "TCP"),
+                        ("NewInternalPort", &listen_port),
+                        ("NewInternalClient", &local_ip),
+                        ("NewEnabled", "1"),
+                        ("NewPortMappingDescription", "neptune-core"),
+                        ("NewLeaseDuration", &lease),
+                    ],
+                )
+                .await?;
+
+                SocketAddr::new(external_ip, self.listen_port)
+            }
+            Gateway::NatPmp(gateway) => {
+                let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+                socket.connect(gateway).await?;
+
+                let response = nat_pmp_request(&socket, &[0, 0], 12).await?;
+                let external_ip =
+                    Ipv4Addr::new(response[8], response[9], response[10], response[11]);
+
+                let mut request = vec![0, 2, 0, 0];
+                request.extend_from_slice(&self.listen_port.to_be_bytes());
+                request.extend_from_slice(&self.listen_port.to_be_bytes());
+                request.extend_from_slice(&(MAPPING_LEASE.as_secs() as u32).to_be_bytes());
+                let response = nat_pmp_request(&socket, &request, 16).await?;
+                let external_port = u16::from_be_bytes([response[10], response[11]]);
+
+                SocketAddr::new(external_ip.into(), external_port)
+            }
+        };
+
+        Ok(Self {
+            external_address,
+            ..self.clone()
+        })
+    }
+
+    /// Renew the mapping every half lease for as long as the node runs, and
+    /// keep the announced external address up to date.
+    pub async fn keep_alive(mut self, mut global_state_lock: GlobalStateLock) {
+        loop {
+            tokio::time::sleep(MAPPING_LEASE / 2).await;
+            match self.renew().await {
+                Ok(renewed) => {
+                    if renewed.external_address != self.external_address {
+                        info!(
+                            "External address changed from {} to {}",
+                            self.external_address, renewed.external_address
+                        );
+                        global_state_lock
+                            .lock_guard_mut()
+                            .await
+                            .net
+                            .external_address = Some(renewed.external_address);
+                    }
+                    self = renewed;
+                }
+                Err(err) => warn!("Could not renew {} port mapping: {err:#}", self.protocol),
+            }
+        }
+    }
+}
+
+/// Find an internet gateway device through SSDP and look up the control
+/// endpoint of its WAN connection service.
+async fn discover_upnp_gateway() -> Result<Gateway> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    let search = "M-SEARCH * HTTP/1.1\r\n\
+        HOST: 239.255.255.250:1900\r\n\
+        ST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\
+        MAN: \"ssdp:discover\"\r\n\
+        MX: 2\r\n\r\n";
+    socket
+        .send_to(search.as_bytes(), SSDP_MULTICAST_ADDRESS)
+        .await?;
+
+    let mut buffer = [0u8; 2048];
+    let (len, _) = tokio::time::timeout(GATEWAY_TIMEOUT, socket.recv_from(&mut buffer))
+        .await
+        .context("no UPnP gateway answered")??;
+    let response = String::from_utf8_lossy(&buffer[..len]);
+    let location = http_header(&response, "location").context("SSDP response has no location")?;
+    let (host, description_path) = parse_http_url(location)?;
+
+    let mut stream = tokio::time::timeout(GATEWAY_TIMEOUT, TcpStream::connect(host))
+        .await
+        .context("timed out connecting to UPnP gateway")??;
+    let local_ip = stream.local_addr()?.ip();
+    let description = http_request(&mut stream, host, "GET", &description_path, &[], "").await?;
+
+    let (service_type, control_url) = wan_connection_service(&description)
+        .context("UPnP gateway has no WAN connection service")?;
+    let (host, control_path) = if control_url.starts_with("http://") {
+        parse_http_url(&control_url)?
+    } else if control_url.starts_with('/') {
+        (host, control_url)
+    } else {
+        (host, format!("/{control_url}"))
+    };
+
+    Ok(Gateway::Upnp {
+        host,
+        control_path,
+        service_type,
+        local_ip,
+    })
+}
+
+/// Split an `http://` URL into the address of the host and the path.
+fn parse_http_url(url: &str) -> Result<(SocketAddr, String)> {
+    let Some(rest) = url.trim().strip_prefix("http://") else {
+        bail!("not an http URL: {url}");
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+    let host = if authority.contains(':') {
+        authority.parse()
+    } else {
+        format!("{authority}:80").parse()
+    }
+    .with_context(|| format!("URL does not name an IP address: {url}"))?;
+
+    Ok((host, path.to_owned()))
+}
+
+/// Value of the named header in an HTTP message, matched case-insensitively.
+fn http_header<'a>(message: &'a str, name: &str) -> Option<&'a str> {
+    message.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
+
+/// Text content of the first element with the given tag, ignoring namespace
+/// prefixes.
+fn xml_element(xml: &str, tag: &str) -> Option<String> {
+    let pattern = format!(r"(?s)<(?:\w+:)?{tag}>\s*(.*?)\s*</(?:\w+:)?{tag}>");
+    Regex::new(&pattern)
+        .ok()?
+        .captures(xml)
+        .map(|captures| captures[1].to_owned())
+}
+
+/// Service type and control URL of the preferred WAN connection service in a
+/// device description.
+fn wan_connection_service(description: &str) -> Option<(String, String)> {
+    let services = Regex::new(r"(?s)<service>(.*?)</service>").unwrap();
+    let services = services
+        .captures_iter(description)
+        .filter_map(|service| {
+            let service_type = xml_element(&service[1], "serviceType")?;
+            let control_url = xml_element(&service[1], "controlURL")?;
+            Some((service_type, control_url))
+        })
+        .collect::<Vec<_>>();
+
+    UPNP_WAN_SERVICES.iter().find_map(|wanted| {
+        services
+            .iter()
+            .find(|(service_type, _)| service_type == wanted)
+            .cloned()
+    })
+}
+
+/// Send one HTTP request over `stream` and return the body of a successful
+/// response.
+async fn http_request(
+    stream: &mut TcpStream,
+    host: SocketAddr,
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> Result<String> {
+    let mut request = format!(
+        "{method} {path} HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\nContent-Length: {}\r\n",
+        body.len()
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{name}: {value}\r\n"));
+    }
+    request.push_str("\r\n");
+    request.push_str(body);
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = vec![];
+    tokio::time::timeout(GATEWAY_TIMEOUT, stream.read_to_end(&mut response))
+        .await
+        .context("timed out waiting for UPnP gateway")??;
+    let response = String::from_utf8_lossy(&response);
+    let Some((head, body)) = response.split_once("\r\n\r\n") else {
+        bail!("malformed HTTP response from UPnP gateway");
+    };
+
+    let status = head.lines().next().unwrap_or_default();
+    ensure!(
+        status.split_whitespace().nth(1) == Some("200"),
+        "UPnP gateway answered {method} {path} with {status}"
+    );
+
+    let is_chunked = http_header(head, "transfer-encoding")
+        .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"));
+    if is_chunked {
+        dechunk(body)
+    } else {
+        Ok(body.to_owned())
+    }
+}
+
+/// Decode an HTTP body sent with chunked transfer encoding.
+fn dechunk(mut body: &str) -> Result<String> {
+    let mut decoded = String::new();
+    loop {
+        let Some((size, rest)) = body.split_once("\r\n") else {
+            bail!("truncated chunked HTTP body");
+        };
+        let size = usize::from_str_radix(size.split(';').next().unwrap_or_default().trim(), 16)
+            .context("invalid chunk size in HTTP body")?;
+        if size == 0 {
+            return Ok(decoded);
+        }
+        ensure!(rest.len() >= size, "truncated chunked HTTP body");
+        decoded.push_str(&rest[..size]);
+        body = rest[size..].trim_start_matches("\r\n");
+    }
+}
+
+/// Invoke an action on the WAN connection service of a UPnP gateway and return
+/// the response body.
+async fn soap_request(
+    host: SocketAddr,
+    control_path: &str,
+    service_type: &str,
+    action: &str,
+    arguments: &[(&str, &str)],
+) -> Result<String> {
+    let arguments = arguments
+        .iter()
+        .map(|(name, value)| format!("<{name}>{value}</{name}>"))
+        .collect::<String>();
+    let body = format!(
+        "<?xml version=\"1.0\"?>\
+        <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+        s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+        <s:Body><u:{action} xmlns:u=\"{service_type}\">{arguments}</u:{action}></s:Body>\
+        </s:Envelope>"
+    );
+    let soap_action = format!("\"{service_type}#{action}\"");
+
+    let mut stream = tokio::time::timeout(GATEWAY_TIMEOUT, TcpStream::connect(host))
+        .await
+        .context("timed out connecting to UPnP gateway")??;
+    http_request(
+        &mut stream,
+        host,
+        "POST",
+        control_path,
+        &[
+            ("Content-Type", "text/xml; charset=\"utf-8\""),
+            ("SOAPAction", &soap_action),
+        ],
+        &body,
+    )
+    .await
+    .with_context(|| format!("UPnP action {action} failed"))
+}
+
+/// Send a NAT-PMP request and return the response, which must have the
+/// expected length, the opcode of the request plus 128, and result code zero.
+async fn nat_pmp_request(
+    socket: &UdpSocket,
+    request: &[u8],
+    response_len: usize,
+) -> Result<Vec<u8>> {
+    let mut buffer = [0u8; 16];
+
+    // requests are retried with doubling timeouts, as the protocol suggests
+    let mut timeout = Duration::from_millis(250);
+    while timeout <= GATEWAY_TIMEOUT {
+        socket.send(request).await?;
+        if let Ok(received) = tokio::time::timeout(timeout, socket.recv(&mut buffer)).await {
+            let len = received?;
+            let response = &buffer[..len];
+            ensure!(
+                len >= response_len && response[1] == request[1] + 128,
+                "malformed NAT-PMP response"
+            );
+            let result_code = u16::from_be_bytes([response[2], response[3]]);
+            ensure!(
+                result_code == 0,
+                "NAT-PMP request failed with code {result_code}"
+            );
+
+            return Ok(response.to_vec());
+        }
+        timeout *= 2;
+    }
+
+    bail!("no NAT-PMP gateway answered")
+}
+
+/// Address of the default IPv4 gateway, from the kernel's routing table.
+fn default_gateway() -> Option<Ipv4Addr> {
+    let routes = std::fs::read_to_string("/proc/net/route").ok()?;
+    default_gateway_in_route_table(&routes)
+}
+
+/// Find the gateway of the default route in the format of `/proc/net/route`,
+/// where addresses are hexadecimal in host byte order.
+fn default_gateway_in_route_table(routes: &str) -> Option<Ipv4Addr> {
+    routes.lines().skip(1).find_map(|route| {
+        let columns = route.split_whitespace().collect::<Vec<_>>();
+        if columns.get(1) != Some(&"00000000") {
+            return None;
+        }
+        let gateway = u32::from_str_radix(columns.get(2)?, 16).ok()?;
+        let gateway = Ipv4Addr::from(gateway.to_le_bytes());
+
+        (!gateway.is_unspecified()).then_some(gateway)
+    })
+}
+
+#[cfg(test)]
+mod port_mapping_tests {
+    use super::*;
+
+    #[test]
+    fn gateway_description_is_parsed() {
+        let ssdp_response = "HTTP/1.1 200 OK\r\n\
+            CACHE-CONTROL: max-age=120\r\n\
+            Location: http://192.168.1.1:5000/rootDesc.xml\r\n\
+            ST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\r\n";
+        let location = http_header(ssdp_response, "LOCATION").unwrap();
+        assert_eq!(
+            (
+                "192.168.1.1:5000".parse::<SocketAddr>().unwrap(),
+                "/rootDesc.xml".to_owned()
+            ),
+            parse_http_url(location).unwrap()
+        );
+
+        let description = r#"<root><device><serviceList>
+            <service>
+              <serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType>
+              <controlURL>/ctl/L3F</controlURL>
+            </service>
+            <service>
+              <serviceType>urn:schemas-upnp-org:service:WANPPPConnection:1</serviceType>
+              <controlURL>/ctl/PPPConn</controlURL>
+            </service>
+            <service>
+              <serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>
+              <controlURL>/ctl/IPConn</controlURL>
+            </service>
+            </serviceList></device></root>"#;
+        assert_eq!(
+            Some((
+                "urn:schemas-upnp-org:service:WANIPConnection:1".to_owned(),
+                "/ctl/IPConn".to_owned()
+            )),
+            wan_connection_service(description)
+        );
+
+        let response = "<s:Envelope><s:Body><u:GetExternalIPAddressResponse>\
+            <NewExternalIPAddress>203.0.113.7</NewExternalIPAddress>\
+            </u:GetExternalIPAddressResponse></s:Body></s:Envelope>";
+        assert_eq!(
+            Some("203.0.113.7".to_owned()),
+            xml_element(response, "NewExternalIPAddress")
+        );
+
+        assert_eq!(
+            "<root></root>",
+            dechunk("6\r\n<root>\r\n7\r\n</root>\r\n0\r\n\r\n").unwrap()
+        );
+    }
+
+    #[test]
+    fn default_gateway_is_read_from_route_table() {
+        let routes = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+            eth0\t0001A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\n\
+            eth0\t00000000\t0101A8C0\t0003\t0\t0\t0\t00000000\n";
+        assert_eq!(
+            Some(Ipv4Addr::new(192, 168, 1, 1)),
+            default_gateway_in_route_table(routes)
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! An async client for the RPC server of a running node.
+//!
+//! Wraps the generated [`RPCClient`] with connection retries, per-request
+//! deadlines and typed errors, so that the CLI, the dashboard and third-party
+//! services connect the same way.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), neptune_core::rpc_client::RpcClientError> {
+//! use neptune_core::rpc_client::NeptuneRpcClient;
+//! use neptune_core::rpc_client::RpcClientConfig;
+//!
+//! let client = NeptuneRpcClient::connect(RpcClientConfig::default()).await?;
+//! println!("tip at height {}", client.block_height().await?);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Methods without a wrapper can be called through [`NeptuneRpcClient::call`],
+//! or on the inner [`RPCClient`].
+
+use std::future::Future;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use tarpc::client;
+use tarpc::client::RpcError;
+use tarpc::context;
+use tarpc::tokio_serde::formats::Json;
+use tokio::time::sleep;
+use tokio::time::timeout;
+
+use crate::config_models::network::Network;
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::models::blockchain::block::block_info::BlockInfo;
+use crate::models::blockchain::block::block_selector::BlockSelector;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use crate::models::peer::PeerInfo;
+use crate::models::state::wallet::wallet_status::WalletStatus;
+use crate::prelude::twenty_first::math::digest::Digest;
+use crate::rpc_server::RPCClient;
+
+#[derive(Debug, Clone)]
+pub struct RpcClientConfig {
+    pub server_addr: SocketAddr,
+
+    /// Time allowed for one connection attempt
+    pub connect_timeout: Duration,
+
+    /// Number of times a failed connection attempt, or a timed out read-only
+    /// request, is retried
+    pub max_retries: u32,
+
+    /// Time between retries
+    pub retry_interval: Duration,
+
+    /// Deadline for a request, from when it is sent
+    pub request_timeout: Duration,
+}
+
+impl Default for RpcClientConfig {
+    fn default() -> Self {
+        Self {
+            server_addr: SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 9799),
+            connect_timeout: Duration::from_secs(5),
+            max_retries: 0,
+            retry_interval: Duration::from_millis(500),
+            request_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RpcClientError {
+    #[error("could not connect to node at {addr}: {source}")]
+    Connect {
+        addr: SocketAddr,
+        source: std::io::Error,
+    },
+
+    #[error("request timed out")]
+    Timeout,
+
+    #[error("connection to node was closed")]
+    Disconnected,
+
+    #[error("node failed to handle request: {0}")]
+    Server(String),
+
+    #[error("transport error: {0}")]
+    Transport(String),
+}
+
+impl RpcClientError {
+    /// Whether retrying the request may succeed.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Connect { .. } | Self::Timeout)
+    }
+}
+
+impl From<RpcError> for RpcClientError {
+    fn from(err: RpcError) -> Self {
+        match err {
+            RpcError::DeadlineExceeded => Self::Timeout,
+            RpcError::Shutdown => Self::Disconnected,
+            RpcError::Server(server_error) => Self::Server(server_error.detail),
+            other => Self::Transport(other.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NeptuneRpcClient {
+    client: RPCClient,
+    config: RpcClientConfig,
+}
+
+impl NeptuneRpcClient {
+    /// Connect to the node at `config.server_addr`, retrying up to
+    /// `config.max_retries` times.
+    pub async fn connect(config: RpcClientConfig) -> Result<Self, RpcClientError> {
+        let mut attempt = 0;
+        loop {
+            let connection = timeout(
+                config.connect_timeout,
+                tarpc::serde_transport::tcp::connect(config.server_addr, Json::default),
+            )
+            .await
+            .unwrap_or_else(|_| Err(std::io::ErrorKind::TimedOut.into()));
+
+            match connection {
+                Ok(transport) => {
+                    let client = RPCClient::new(client::Config::default(), transport).spawn();
+                    return Ok(Self { client, config });
+                }
+                Err(source) if attempt >= config.max_retries => {
+                    return Err(RpcClientError::Connect {
+                        addr: config.server_addr,
+                        source,
+                    })
+                }
+                Err(_) => {
+                    attempt += 1;
+                    sleep(config.retry_interval).await;
+                }
+            }
+        }
+    }
+
+    pub fn config(&self) -> &RpcClientConfig {
+        &self.config
+    }
+
+    /// The generated client, for callers that manage contexts themselves.
+    pub fn inner(&self) -> &RPCClient {
+        &self.client
+    }
+
+    pub fn into_inner(self) -> RPCClient {
+        self.client
+    }
+
+    /// A context with the configured request deadline.
+    pub fn context(&self) -> context::Context {
+        let mut ctx = context::current();
+        ctx.deadline = SystemTime::now() + self.config.request_timeout;
+        ctx
+    }
+
+    /// Make a request once, with the configured deadline. Use for requests
+    /// that change the node's state.
+    pub async fn call<T, F, Fut>(&self, request: F) -> Result<T, RpcClientError>
+    where
+        F: FnOnce(RPCClient, context::Context) -> Fut,
+        Fut: Future<Output = Result<T, RpcError>>,
+    {
+        Ok(request(self.client.clone(), self.context()).await?)
+    }
+
+    /// Make a read-only request, retrying up to `max_retries` times if it
+    /// times out.
+    pub async fn query<T, F, Fut>(&self, request: F) -> Result<T, RpcClientError>
+    where
+        F: Fn(RPCClient, context::Context) -> Fut,
+        Fut: Future<Output = Result<T, RpcError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match self.call(&request).await {
+                Err(err) if err.is_transient() && attempt < self.config.max_retries => {
+                    attempt += 1;
+                    sleep(self.config.retry_interval).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub async fn network(&self) -> Result<Network, RpcClientError> {
+        self.query(|client, ctx| async move { client.network(ctx).await })
+            .await
+    }
+
+    pub async fn block_height(&self) -> Result<BlockHeight, RpcClientError> {
+        self.query(|client, ctx| async move { client.block_height(ctx).await })
+            .await
+    }
+
+    pub async fn block_digest(
+        &self,
+        block_selector: BlockSelector,
+    ) -> Result<Option<Digest>, RpcClientError> {
+        self.query(|client, ctx| {
+            let block_selector = block_selector.clone();
+            async move { client.block_digest(ctx, block_selector).await }
+        })
+        .await
+    }
+
+    pub async fn block_info(
+        &self,
+        block_selector: BlockSelector,
+    ) -> Result<Option<BlockInfo>, RpcClientError> {
+        self.query(|client, ctx| {
+            let block_selector = block_selector.clone();
+            async move { client.block_info(ctx, block_selector).await }
+        })
+        .await
+    }
+
+    pub async fn peer_info(&self) -> Result<Vec<PeerInfo>, RpcClientError> {
+        self.query(|client, ctx| async move { client.peer_info(ctx).await })
+            .await
+    }
+
+    pub async fn synced_balance(&self) -> Result<NeptuneCoins, RpcClientError> {
+        self.query(|client, ctx| async move { client.synced_balance(ctx).await })
+            .await
+    }
+
+    pub async fn wallet_status(&self) -> Result<WalletStatus, RpcClientError> {
+        self.query(|client, ctx| async move { client.wallet_status(ctx).await })
+            .await
+    }
+
+    pub async fn mempool_tx_count(&self) -> Result<usize, RpcClientError> {
+        self.query(|client, ctx| async move { client.mempool_tx_count(ctx).await })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod rpc_client_tests {
+    use super::*;
+
+    #[test]
+    fn rpc_errors_map_to_typed_errors() {
+        let timeout = RpcClientError::from(RpcError::DeadlineExceeded);
+        assert!(matches!(timeout, RpcClientError::Timeout));
+        assert!(timeout.is_transient());
+
+        let shutdown = RpcClientError::from(RpcError::Shutdown);
+        assert!(matches!(shutdown, RpcClientError::Disconnected));
+        assert!(!shutdown.is_transient());
+    }
+
+    #[tokio::test]
+    async fn connecting_to_closed_port_fails_after_retries() {
+        let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let config = RpcClientConfig {
+            server_addr,
+            max_retries: 2,
+            retry_interval: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let err = NeptuneRpcClient::connect(config).await.unwrap_err();
+        assert!(matches!(err, RpcClientError::Connect { addr, .. } if addr == server_addr));
+    }
+}
@@ -1,54 +1,128 @@
 //! implements an RPC server and client based on [tarpc]
 //!
-//! at present tarpc clients must also be written in rust.
-//!
-//! In the future we may want to explore adding an rpc layer that is friendly to
-//! other languages.
+//! tarpc clients must also be written in rust. Clients in other languages can
+//! use the main read methods through the [jsonrpc] layer, or generate bindings
+//! from the schemas in [rpc_schema].
+
+pub mod jsonrpc;
+pub mod rpc_schema;
 
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::net::SocketAddr;
 use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
 
+use anyhow::anyhow;
+use anyhow::bail;
 use anyhow::Result;
 use get_size::GetSize;
+use itertools::Itertools;
+use num_traits::CheckedSub;
+use num_traits::Zero;
+use rand::seq::SliceRandom;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use systemstat::Platform;
 use systemstat::System;
 use tarpc::context;
+use tokio::sync::broadcast;
 use tokio::sync::mpsc::error::SendError;
+use tokio::sync::oneshot;
 use tracing::error;
 use tracing::info;
+use tracing::warn;
+use twenty_first::math::b_field_element::BFieldElement;
 use twenty_first::math::digest::Digest;
 
+use crate::config_models::data_directory::DataDirectory;
 use crate::config_models::network::Network;
+use crate::connect_to_peers::REACHABILITY_CHECK_TIMEOUT;
+use crate::mining::block_generation::generate_block;
+use crate::mining::block_generation::GenerateBlocksError;
 use crate::models::blockchain::block::block_header::BlockHeader;
 use crate::models::blockchain::block::block_height::BlockHeight;
 use crate::models::blockchain::block::block_info::BlockInfo;
 use crate::models::blockchain::block::block_selector::BlockSelector;
+use crate::models::blockchain::block::chain_params::ChainParams;
+use crate::models::blockchain::block::difficulty_control;
+use crate::models::blockchain::block::difficulty_control::SimulatedBlock;
+use crate::models::blockchain::block::validation_trace;
+use crate::models::blockchain::block::validation_trace::ValidationReport;
+use crate::models::blockchain::transaction::memo::Memo;
+use crate::models::blockchain::transaction::partially_proved::PartiallyProvedTransaction;
+use crate::models::blockchain::transaction::partially_proved::PsntError;
+use crate::models::blockchain::transaction::partially_proved::PsntFinalization;
+use crate::models::blockchain::transaction::transaction_kernel::TransactionKernelField;
+use crate::models::blockchain::transaction::transaction_kernel::TransactionKernelFieldDisclosure;
+use crate::models::blockchain::transaction::transaction_output::TxOutputList;
 use crate::models::blockchain::transaction::transaction_output::UtxoNotificationMedium;
+use crate::models::blockchain::transaction::Transaction;
 use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
 use crate::models::channel::RPCServerToMain;
 use crate::models::peer::InstanceId;
 use crate::models::peer::PeerInfo;
 use crate::models::peer::PeerStanding;
+use crate::models::peer::PeerViolation;
+use crate::models::peer::ReachabilityReport;
+use crate::models::proof_abstractions::tasm::proving_progress;
+use crate::models::proof_abstractions::tasm::proving_progress::ProvingJobStatus;
 use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::models::state::block_proposal::BlockSolutionError;
+use crate::models::state::block_proposal::BlockTemplate;
+use crate::models::state::block_proposal::BlockTemplateUpdate;
+use crate::models::state::checkpoint_beacon::BeaconAssessment;
+use crate::models::state::checkpoint_beacon::SignedCheckpoint;
+use crate::models::state::coinjoin::CoinJoinSessionInfo;
+use crate::models::state::coinjoin::DisclosedOutput;
+use crate::models::state::coinjoin::OwnCoinJoinContribution;
+use crate::models::state::event_journal::JournalEntry;
+use crate::models::state::fee_histogram::FeeHistogramBucket;
+use crate::models::state::fork_watch::ForkWatchStatus;
+use crate::models::state::mempool_admission;
+use crate::models::state::mempool_admission::BroadcastTransactionError;
+use crate::models::state::mining_log::MinedBlockReport;
+use crate::models::state::spend_audit_log::SpendAuditEntry;
+use crate::models::state::spend_audit_log::SpendOutcome;
+use crate::models::state::sync_progress::SyncProgressReport;
 use crate::models::state::transaction_kernel_id::TransactionKernelId;
+use crate::models::state::tx_index::IndexedUtxo;
 use crate::models::state::tx_proving_capability::TxProvingCapability;
+use crate::models::state::utxo_set_stats::UtxoSetStats;
 use crate::models::state::wallet::address::KeyType;
 use crate::models::state::wallet::address::ReceivingAddress;
+use crate::models::state::wallet::audit_export::WalletAuditExport;
+use crate::models::state::wallet::coin_selection::CoinSelectionPolicy;
 use crate::models::state::wallet::coin_with_possible_timelock::CoinWithPossibleTimeLock;
+use crate::models::state::wallet::expected_utxo::ExpectedUtxo;
 use crate::models::state::wallet::expected_utxo::UtxoNotifier;
+use crate::models::state::wallet::expected_utxo_bundle::ExpectedUtxoBundle;
+use crate::models::state::wallet::expected_utxo_bundle::ExpectedUtxoBundleError;
+use crate::models::state::wallet::expected_utxo_bundle::ExpectedUtxoImport;
+use crate::models::state::wallet::hd_derivation::AccountDescriptor;
+use crate::models::state::wallet::key_rotation::KeyRotation;
+use crate::models::state::wallet::key_rotation::KeyRotationStatus;
+use crate::models::state::wallet::key_rotation::KeyRotationStep;
+use crate::models::state::wallet::key_rotation::KeyRotationSweep;
+use crate::models::state::wallet::unconfirmed_change::UnconfirmedSpend;
+use crate::models::state::wallet::wallet_memo::WalletMemo;
 use crate::models::state::wallet::wallet_status::WalletStatus;
+use crate::models::state::wallet::wallet_summary::WalletSummary;
+use crate::models::state::GlobalState;
 use crate::models::state::GlobalStateLock;
 use crate::prelude::twenty_first;
+use crate::rpc_server::rpc_schema::RpcSchemas;
+use crate::util_types::json_schema::DigestSchema;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct DashBoardOverviewDataFromClient {
+    #[schemars(with = "DigestSchema")]
     pub tip_digest: Digest,
     pub tip_header: BlockHeader,
-    pub syncing: bool,
+    /// `None` if the node is not syncing
+    pub sync_progress: Option<SyncProgressReport>,
     pub available_balance: NeptuneCoins,
     pub timelocked_balance: NeptuneCoins,
     pub available_unconfirmed_balance: NeptuneCoins,
@@ -67,6 +141,38 @@ pub struct DashBoardOverviewDataFromClient {
 
     /// CPU temperature in degrees Celcius
     pub cpu_temp: Option<f32>,
+
+    /// Progress of in-flight proving jobs, oldest first
+    pub proving_jobs: Vec<ProvingJobStatus>,
+
+    /// Whether the network appears to have activated consensus rules this
+    /// version does not know. See [fork_watch](RPC::fork_watch).
+    pub unknown_consensus_rules: bool,
+}
+
+/// Longest a [subscribe_blocks](RPC::subscribe_blocks) call waits for a new
+/// tip.
+pub const MAX_BLOCK_SUBSCRIPTION_WAIT: Duration = Duration::from_secs(60);
+
+/// Maximum number of blocks in a [BlockUpdate].
+const MAX_BLOCK_UPDATE_BLOCKS: usize = 16;
+
+/// Maximum number of blocks by which the tip may have moved while a
+/// transaction was being created, for the transaction to be updated rather
+/// than dropped.
+const MAX_TRANSACTION_UPDATE_DEPTH: usize = 10;
+
+/// Change of the tip relative to the tip known to a subscriber.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct BlockUpdate {
+    /// Number of blocks of the known tip's chain, counting from the known tip,
+    /// that are no longer canonical. Zero if the new tip descends from the
+    /// known tip, `None` if the known tip is not known to this node.
+    pub reorg_depth: Option<u64>,
+
+    /// Blocks that became canonical, oldest first and ending with the new tip.
+    /// Only the most recent ones, if there are many.
+    pub new_blocks: Vec<BlockInfo>,
 }
 
 #[tarpc::service]
@@ -76,6 +182,18 @@ pub trait RPC {
     // Return which network the client is running
     async fn network() -> Network;
 
+    /// Returns the consensus constants in effect on the client's network
+    async fn chain_params() -> ChainParams;
+
+    /// Returns how the difficulty would evolve from the tip on if the next
+    /// blocks were spaced apart by the given intervals, at most
+    /// `MAX_SIMULATED_BLOCKS` many. The target block interval defaults to the
+    /// network's.
+    async fn simulate_difficulty(
+        block_intervals: Vec<Timestamp>,
+        target_block_interval: Option<Timestamp>,
+    ) -> Vec<SimulatedBlock>;
+
     /// Returns local socket used for incoming peer-connections. Does not show
     /// the public IP address, as the client does not know this.
     async fn own_listen_address_for_peers() -> Option<SocketAddr>;
@@ -88,6 +206,11 @@ pub trait RPC {
     /// Returns the current block height.
     async fn block_height() -> BlockHeight;
 
+    /// Returns the progress of synchronization, broken down into download,
+    /// validation and application of blocks, with rates and estimated time
+    /// left. `None` if the node is not syncing.
+    async fn sync_progress() -> Option<SyncProgressReport>;
+
     /// Returns the number of blocks (confirmations) since wallet balance last changed.
     ///
     /// returns `Option<BlockHeight>`
@@ -101,6 +224,12 @@ pub trait RPC {
     /// Return info about all peers that have been sanctioned
     async fn all_sanctioned_peers() -> HashMap<IpAddr, PeerStanding>;
 
+    /// Return the recorded violations of the peer at the given IP, oldest
+    /// first, with the evidence for each, such as the consensus rule an
+    /// invalid block failed. Taken from the connected peer with this IP if
+    /// there is one, and from the database otherwise.
+    async fn get_peer_violations(ip: IpAddr) -> Vec<PeerViolation>;
+
     /// Returns the digest of the latest n blocks
     async fn latest_tip_digests(n: usize) -> Vec<Digest>;
 
@@ -110,9 +239,34 @@ pub trait RPC {
     /// Return the digest for the specified block if found
     async fn block_digest(block_selector: BlockSelector) -> Option<Digest>;
 
+    /// Return true if the block is known and belongs to the chain this node
+    /// follows under the fork choice rule.
+    async fn is_canonical(block_digest: Digest) -> bool;
+
     /// Return the digest for the specified UTXO leaf index if found
     async fn utxo_digest(leaf_index: u64) -> Option<Digest>;
 
+    /// Return whether peers announce chains heavier than the own chain under
+    /// consensus rules this version does not know, such as block header
+    /// versions not in its activation table. If so, the network has likely
+    /// activated a consensus change and the node must be upgraded to follow
+    /// it.
+    async fn fork_watch() -> ForkWatchStatus;
+
+    /// Return the digests of the canonical blocks containing a public
+    /// announcement for the given receiver identifier, oldest first.
+    ///
+    /// Returns `None` if the node was not started with `--build-tx-index`.
+    async fn get_blocks_for_announcement(receiver_identifier: BFieldElement)
+        -> Option<Vec<Digest>>;
+
+    /// Return the UTXOs with the given lock script hash received by this
+    /// node's wallet in canonical blocks, oldest first. UTXOs are encrypted on
+    /// chain, so those of other wallets are unknown.
+    ///
+    /// Returns `None` if the node was not started with `--build-tx-index`.
+    async fn get_utxos_for_lock_script_hash(lock_script_hash: Digest) -> Option<Vec<IndexedUtxo>>;
+
     /// Return the block header for the specified block
     async fn header(block_selector: BlockSelector) -> Option<BlockHeader>;
 
@@ -137,10 +291,38 @@ pub trait RPC {
     // TODO: Change to return current size and max size
     async fn mempool_size() -> usize;
 
+    /// Estimate the fee, per 1000 bytes of transaction, for a transaction to
+    /// be mined within `target_blocks` blocks, from the fee densities in the
+    /// mempool and of the transactions included in recent blocks.
+    async fn estimate_fee(target_blocks: usize) -> NeptuneCoins;
+
+    /// Summarize the transactions in the mempool by fee density, in at most
+    /// `bucket_count` buckets of about equal combined size and in order of
+    /// descending fee density. Best effort, as the mempool keeps changing.
+    async fn mempool_fee_histogram(bucket_count: usize) -> Vec<FeeHistogramBucket>;
+
+    /// Disclose one field of the kernel of a transaction in the mempool or
+    /// sent by this wallet, e.g., its fee or timestamp, verifiable against the
+    /// kernel's MAST hash without revealing the transaction's inputs and
+    /// outputs. Returns `None` if the transaction is unknown.
+    async fn disclose_transaction_kernel_field(
+        txid: TransactionKernelId,
+        field: TransactionKernelField,
+    ) -> Option<TransactionKernelFieldDisclosure>;
+
+    /// Return statistics of the UTXO set as of the tip: UTXO count, age
+    /// distribution of addition records, density of the sliding-window Bloom
+    /// filter, and average outputs per block.
+    ///
+    /// Reads the entire inactive part of the Bloom filter, so it can be slow.
+    async fn utxo_set_stats() -> UtxoSetStats;
+
     /// Return the information used on the dashboard's overview tab
     async fn dashboard_overview_data() -> DashBoardOverviewDataFromClient;
 
-    /// Determine whether the user-supplied string is a valid address
+    /// Determine whether the user-supplied string is a valid address on the
+    /// network this node runs on. Addresses of other networks are refused,
+    /// as is a `network` argument that differs from the node's network.
     async fn validate_address(address: String, network: Network) -> Option<ReceivingAddress>;
 
     /// Determine whether the user-supplied string is a valid amount
@@ -155,6 +337,51 @@ pub trait RPC {
     /// Get CPU temperature.
     async fn cpu_temp() -> Option<f32>;
 
+    /// Return the progress of the wallet key rotation in progress, if any.
+    async fn key_rotation_status() -> Option<KeyRotationStatus>;
+
+    /// Compare the canonical chain with the latest trusted checkpoint.
+    async fn beacon_status() -> BeaconAssessment;
+
+    /// Export every monitored UTXO with its derivation index, receiver
+    /// identifier, AOCL leaf index, and confirmation block, for third-party
+    /// audit tooling. See [`audit_export`](crate::models::state::wallet::audit_export)
+    /// for the JSON schema.
+    async fn wallet_audit_export() -> WalletAuditExport;
+
+    /// List the memos attached to UTXOs this wallet sent or received, oldest
+    /// first.
+    async fn memos() -> Vec<WalletMemo>;
+
+    /// List own transactions that spend change of unconfirmed transactions,
+    /// and are held back until those are confirmed. Such a transaction is
+    /// invalidated if any of its parents is dropped from the mempool.
+    async fn unconfirmed_spends() -> Vec<UnconfirmedSpend>;
+
+    /// Whether the send RPCs require a spend passphrase. See
+    /// [`spend_authorization`](crate::models::state::wallet::spend_authorization).
+    async fn spend_passphrase_required() -> bool;
+
+    /// Aggregate wallet statistics: UTXO count, balances, pending expected
+    /// UTXOs, mempool in- and outflows, last scanned block, and the number of
+    /// UTXOs whose membership proofs lag behind the tip. Cheap to poll, as the
+    /// aggregates are cached until the tip or the wallet changes.
+    async fn wallet_summary() -> WalletSummary;
+
+    /// JSON schemas of the parameters and results of all methods, for
+    /// generating clients in other languages. See [rpc_schema].
+    async fn rpc_schemas() -> RpcSchemas;
+
+    /// Wait for the tip to differ from `known_tip`, and return the blocks that
+    /// became canonical since. Returns immediately if it already differs, and
+    /// `None` if it does not change within `max_wait_secs`, which is capped at
+    /// [MAX_BLOCK_SUBSCRIPTION_WAIT].
+    ///
+    /// Clients follow the chain by calling this in a loop, passing the new tip
+    /// of the previous update, with a context deadline beyond `max_wait_secs`.
+    async fn subscribe_blocks(known_tip: Option<Digest>, max_wait_secs: u64)
+        -> Option<BlockUpdate>;
+
     /******** CHANGE THINGS ********/
     // Place all things that change state here
 
@@ -166,12 +393,18 @@ pub trait RPC {
 
     /// Send coins to a single recipient.
     ///
+    /// `coin_selection` determines which of the wallet's UTXOs are spent. If
+    /// `None`, the policy set with the node's `--coin-selection` argument is
+    /// used.
+    ///
     /// See docs for [send_to_many()](Self::send_to_many())
     async fn send(
         amount: NeptuneCoins,
         address: ReceivingAddress,
         owned_utxo_notify_method: UtxoNotificationMedium,
         fee: NeptuneCoins,
+        spend_passphrase: Option<String>,
+        coin_selection: Option<CoinSelectionPolicy>,
     ) -> Option<TransactionKernelId>;
 
     /// Send coins to multiple recipients
@@ -197,6 +430,9 @@ pub trait RPC {
     /// `fee` represents the fee in native coins to pay the miner who mines
     /// the block that initially confirms the resulting transaction.
     ///
+    /// `spend_passphrase` must match the wallet's spend passphrase, if one is
+    /// set. Otherwise no transaction is created.
+    ///
     /// a [Digest] of the resulting [Transaction](crate::models::blockchain::transaction::Transaction) is returned on success, else [None].
     ///
     /// todo: shouldn't we return `Transaction` instead?
@@ -207,6 +443,22 @@ pub trait RPC {
         outputs: Vec<(ReceivingAddress, NeptuneCoins)>,
         owned_utxo_notify_medium: UtxoNotificationMedium,
         fee: NeptuneCoins,
+        spend_passphrase: Option<String>,
+    ) -> Option<TransactionKernelId>;
+
+    /// Like [send_to_many()](Self::send_to_many()), with an optional memo
+    /// for each output.
+    ///
+    /// A memo is encrypted along with the UTXO notification, so only sender
+    /// and receiver can read it. It is limited to
+    /// [MAX_MEMO_SIZE_IN_BYTES](crate::models::blockchain::transaction::memo::MAX_MEMO_SIZE_IN_BYTES)
+    /// bytes. Memos only reach receivers notified on-chain, and are stored in
+    /// the sender's wallet in either case.
+    async fn send_to_many_with_memos(
+        outputs: Vec<(ReceivingAddress, NeptuneCoins, Option<Memo>)>,
+        owned_utxo_notify_medium: UtxoNotificationMedium,
+        fee: NeptuneCoins,
+        spend_passphrase: Option<String>,
     ) -> Option<TransactionKernelId>;
 
     /// Stop miner if running
@@ -215,13 +467,249 @@ pub trait RPC {
     /// Start miner if not running
     async fn restart_miner();
 
+    /// Return the latest block template for external miners, or `None` if the
+    /// node does not run with `--external-mining`, is not mining, or has not
+    /// built a template on the current tip yet. Solutions are submitted with
+    /// `submit_block_solution`, quoting the template's proposal id.
+    async fn get_block_template() -> Option<BlockTemplate>;
+
+    /// Return what changed from the block template with the given proposal id
+    /// to the latest one, for external miners that already hold a template.
+    /// If that template is unknown, superseded long ago, or builds on another
+    /// block, the whole latest template is returned instead, as it is when no
+    /// proposal id is given. `None` under the same conditions as
+    /// `get_block_template`.
+    async fn get_block_template_update(
+        known_proposal_id: Option<u64>,
+    ) -> Option<BlockTemplateUpdate>;
+
+    /// Submit a nonce, and optionally a timestamp within the template's range,
+    /// solving the template with the given proposal id. If the resulting block
+    /// is valid, it becomes the new tip and is shared with peers, and its
+    /// digest is returned.
+    async fn submit_block_solution(
+        proposal_id: u64,
+        nonce: [BFieldElement; 3],
+        timestamp: Option<Timestamp>,
+    ) -> Result<Digest, BlockSolutionError>;
+
+    /// Generate `num_blocks` blocks on top of the tip instantly, each paying
+    /// the block subsidy to `address`, and return their digests. RegTest only,
+    /// for integration tests. Blocks are timestamped one target block interval
+    /// apart, which keeps the difficulty at the minimum, and the generation
+    /// stops when the next block would be too far in the future. With
+    /// `real_proofs`, blocks are proven as mined blocks are, and shared with
+    /// peers. Otherwise they carry no proofs and stay on this node.
+    async fn generate_blocks(
+        num_blocks: usize,
+        address: ReceivingAddress,
+        real_proofs: bool,
+    ) -> Result<Vec<Digest>, GenerateBlocksError>;
+
+    /// Broadcast a transaction constructed elsewhere, such as by an offline
+    /// signer, given as its bincode encoding. It may carry any kind of proof.
+    /// The transaction is checked as one received from a peer is, including
+    /// that it can be confirmed in a block following the tip, and is then
+    /// inserted into the mempool and shared with peers. A transaction carrying
+    /// a primitive witness is first proven by this node, as transactions
+    /// created by the wallet are. Unlike those, it is not tracked by the
+    /// wallet, so it is not rebuilt if a reorganization un-confirms it.
+    /// Returns the transaction's id.
+    async fn broadcast_transaction(
+        transaction_bytes: Vec<u8>,
+    ) -> Result<TransactionKernelId, BroadcastTransactionError>;
+
+    /// Create a partially proved transaction (PSNT) paying `outputs` and
+    /// `fee` from inputs of this wallet, with change returned to it, and
+    /// return its encoding. No lock script is proven yet, and the secrets
+    /// unlocking the inputs are left out. See
+    /// [partially_proved](crate::models::blockchain::transaction::partially_proved)
+    /// for the workflow.
+    async fn create_psnt(
+        outputs: Vec<(ReceivingAddress, NeptuneCoins)>,
+        owned_utxo_notify_medium: UtxoNotificationMedium,
+        fee: NeptuneCoins,
+    ) -> Option<Vec<u8>>;
+
+    /// Combine encoded PSNTs into one, and return its encoding. Copies of one
+    /// transaction are combined into one carrying all their lock-script
+    /// proofs. PSNTs of different transactions, none of which has lock-script
+    /// proofs yet, are joined into one transaction.
+    async fn combine_psnt(psnts: Vec<Vec<u8>>) -> Result<Vec<u8>, PsntError>;
+
+    /// Prove the lock scripts of the inputs of an encoded PSNT that this
+    /// wallet can unlock. If every lock script is then proven, complete the
+    /// transaction and broadcast it. Otherwise return the PSNT, to be passed on
+    /// to the parties that can unlock the remaining inputs.
+    ///
+    /// The wallet proves nothing if it would spend more than `max_net_spend`
+    /// on the transaction, counting the inputs it can unlock less the outputs
+    /// it can claim. This guards against a party that drops the wallet's
+    /// change from the PSNT before passing it on. The outputs it can claim
+    /// become expected UTXOs of the wallet.
+    ///
+    /// `spend_passphrase` must match the wallet's spend passphrase, if one is
+    /// set and the PSNT spends inputs of this wallet.
+    async fn finalize_psnt(
+        psnt: Vec<u8>,
+        max_net_spend: NeptuneCoins,
+        spend_passphrase: Option<String>,
+    ) -> Result<PsntFinalization, PsntError>;
+
+    /// Return the most recent blocks mined by this node, at most `max_num`,
+    /// oldest first, with the coinbase, fees, and number of transactions of
+    /// each block and the age of the template it was mined on.
+    async fn mined_blocks(max_num: usize) -> Vec<MinedBlockReport>;
+
+    /// Return the journal entries recorded between `from` and `to`, both
+    /// inclusive, oldest first: blocks accepted, reorganizations, UTXOs
+    /// received and spent by the wallet, and peers banned. At most `max_num`,
+    /// the most recent ones if there are more.
+    async fn event_journal(from: Timestamp, to: Timestamp, max_num: usize) -> Vec<JournalEntry>;
+
+    /// Return all entries of the spend audit log, oldest first, or `None` if
+    /// it cannot be read. Empty unless the node runs with `--spend-audit-log`.
+    async fn spend_audit_log() -> Option<Vec<SpendAuditEntry>>;
+
+    /// Return the validation reports of the most recently validated blocks,
+    /// at most `max_num`, oldest first: the consensus rules each block was
+    /// checked against, up to the first one it broke, and how long each check
+    /// took. Empty unless the node runs with `--trace-validation`.
+    async fn validation_reports(max_num: usize) -> Vec<ValidationReport>;
+
     /// mark MUTXOs as abandoned
     async fn prune_abandoned_monitored_utxos() -> usize;
 
+    /// Start rotating the wallet to a newly generated seed, or resume the
+    /// rotation in progress.
+    ///
+    /// The new seed is stored in the wallet directory next to the current
+    /// one. Back it up before sweeping funds to it.
+    ///
+    /// The new seed is stored unencrypted, so all steps of a key rotation are
+    /// refused for wallets encrypted at rest.
+    ///
+    /// Returns `None` on failure.
+    async fn key_rotation_start() -> Option<KeyRotationStatus>;
+
+    /// Spend all UTXOs of the old seed that are spendable now to the new seed,
+    /// in one transaction paying `fee`. Time-locked UTXOs are left for a later
+    /// sweep.
+    ///
+    /// `spend_passphrase` must match the wallet's spend passphrase, if one is
+    /// set. Otherwise nothing is swept.
+    ///
+    /// Returns the updated status, or `None` if no rotation is in progress or
+    /// sweeping is not the next step.
+    async fn key_rotation_sweep(
+        fee: NeptuneCoins,
+        spend_passphrase: Option<String>,
+    ) -> Option<KeyRotationStatus>;
+
+    /// Replace the wallet secret by the new seed, once all funds have been
+    /// swept and confirmed. The old secret is kept, renamed, in the wallet
+    /// directory.
+    async fn key_rotation_finish() -> bool;
+
+    /// Import a bundle of expected UTXOs from a payment processor, such that
+    /// the wallet claims them once confirmed. The bundle must be for this
+    /// network, validly signed, by `trusted_signer` if given as hex, and every
+    /// UTXO must be paid to a known address of this wallet. UTXOs that the
+    /// wallet already expects are skipped.
+    async fn import_expected_utxos(
+        bundle: ExpectedUtxoBundle,
+        trusted_signer: Option<String>,
+    ) -> Result<ExpectedUtxoImport, ExpectedUtxoBundleError>;
+
+    /// Export the descriptor of an account of the hierarchical key
+    /// derivation, from which external tools can derive the account's
+    /// receiving addresses offline. The descriptor cannot spend, but reveals
+    /// the account's incoming UTXOs. See
+    /// [hd_derivation](crate::models::state::wallet::hd_derivation).
+    ///
+    /// From then on, the wallet recognizes UTXOs sent to the first
+    /// [`ACCOUNT_KEY_LOOKAHEAD`](crate::models::state::wallet::hd_derivation::ACCOUNT_KEY_LOOKAHEAD)
+    /// addresses of each chain of the account.
+    /// Returns `None` for watch-only wallets.
+    async fn export_account_descriptor(account: u32) -> Option<AccountDescriptor>;
+
+    /// Decrypt the secret of a wallet encrypted at rest, such that it can send
+    /// and mine, for `timeout_secs` seconds if given and otherwise until
+    /// locked. See [encrypted_secret](crate::models::state::wallet::encrypted_secret).
+    ///
+    /// Returns false if the wallet is not encrypted or the passphrase is
+    /// wrong.
+    async fn wallet_unlock(passphrase: String, timeout_secs: Option<u64>) -> bool;
+
+    /// Zeroize the secret of a wallet encrypted at rest, and drop the spending
+    /// keys derived from it. Returns false if the wallet is not encrypted.
+    async fn wallet_lock() -> bool;
+
+    /// Submit a checkpoint signed by a trusted beacon key. Returns true iff
+    /// the checkpoint was valid and became the latest one.
+    async fn beacon_submit_checkpoint(checkpoint: SignedCheckpoint) -> bool;
+
+    /// Ask a few connected peers to connect back to this node's listen port,
+    /// to verify that it is reachable, e.g. that port forwarding works. Peers
+    /// connected over IPv4 and IPv6 are mixed, such that both are tested if
+    /// possible. Waits a few seconds for the answers.
+    ///
+    /// Returns an empty list if this node does not listen for peers or has no
+    /// peers.
+    async fn check_reachability() -> Vec<ReachabilityReport>;
+
+    /// The coinjoin sessions coordinated by connected peers, by coordinator,
+    /// as last announced. Also asks all connected peers for their current
+    /// sessions, such that a later call returns fresh information.
+    async fn coinjoin_sessions() -> Vec<(SocketAddr, CoinJoinSessionInfo)>;
+
+    /// Contribute a transaction to the coinjoin session of the given
+    /// coordinator, as last returned by `coinjoin_sessions`. The transaction
+    /// pays the session's denomination to a new address of this wallet, and
+    /// returns the change minus `fee` to it as well.
+    ///
+    /// The coordinator merges the contributions of all participants into one
+    /// transaction. It learns which denomination output is this wallet's, but
+    /// the rest of the network does not. If the session fails, the
+    /// contribution is broadcast on its own.
+    ///
+    /// Contributions must be supported by single proofs, so this call proves
+    /// one, which takes minutes, and fails if this node is not capable of it.
+    async fn coinjoin_join(
+        coordinator: SocketAddr,
+        fee: NeptuneCoins,
+        spend_passphrase: Option<String>,
+    ) -> Option<TransactionKernelId>;
+
+    /// Enter or leave maintenance mode.
+    ///
+    /// In maintenance mode, the node accepts no new peer connections, admits
+    /// no transactions to its mempool and refuses to send transactions.
+    /// Existing peer connections and read-only RPCs are unaffected. Entering
+    /// it waits for transactions undergoing admission and flushes all
+    /// databases, such that the data directory can be backed up.
+    ///
+    /// Returns false if the databases could not be flushed.
+    async fn maintenance_mode(on: bool) -> bool;
+
     /// Gracious shutdown.
     async fn shutdown() -> bool;
 }
 
+/// Where a transaction created by the wallet is sent.
+enum TransactionDestination {
+    /// Broadcast to all peers
+    Network,
+
+    /// Contributed to a coinjoin session coordinated by a peer, disclosing
+    /// the denomination output to the coordinator
+    CoinJoin {
+        coordinator: SocketAddr,
+        session_id: u64,
+        disclosed_output: DisclosedOutput,
+    },
+}
+
 #[derive(Clone)]
 pub struct NeptuneRPCServer {
     pub socket_address: SocketAddr,
@@ -269,20 +757,317 @@ impl NeptuneRPCServer {
     /// Locking:
     ///   * acquires `global_state_lock` for write
     async fn send_to_many_inner(
-        mut self,
+        self,
         _ctx: context::Context,
         outputs: Vec<(ReceivingAddress, NeptuneCoins)>,
         owned_utxo_notification_medium: UtxoNotificationMedium,
         fee: NeptuneCoins,
         now: Timestamp,
         tx_proving_capability: TxProvingCapability,
+    ) -> Option<TransactionKernelId> {
+        let memos = vec![None; outputs.len()];
+        self.send_to_many_with_memos_inner(
+            outputs,
+            memos,
+            owned_utxo_notification_medium,
+            fee,
+            now,
+            tx_proving_capability,
+            None,
+        )
+        .await
+    }
+
+    /// Like [Self::send_to_many_inner], attaching the memo at the same index
+    /// to each output, and selecting inputs with `coin_selection` if given.
+    #[allow(clippy::too_many_arguments)]
+    async fn send_to_many_with_memos_inner(
+        self,
+        outputs: Vec<(ReceivingAddress, NeptuneCoins)>,
+        memos: Vec<Option<Memo>>,
+        owned_utxo_notification_medium: UtxoNotificationMedium,
+        fee: NeptuneCoins,
+        now: Timestamp,
+        tx_proving_capability: TxProvingCapability,
+        coin_selection: Option<CoinSelectionPolicy>,
+    ) -> Option<TransactionKernelId> {
+        let tx_outputs = self
+            .state
+            .lock_guard()
+            .await
+            .generate_tx_outputs(outputs, owned_utxo_notification_medium);
+        let tx_outputs: TxOutputList = tx_outputs
+            .into_iter()
+            .zip(memos)
+            .map(|(output, memo)| output.with_memo(memo))
+            .collect::<Vec<_>>()
+            .into();
+
+        self.send_tx_outputs_inner(
+            tx_outputs,
+            owned_utxo_notification_medium,
+            fee,
+            now,
+            tx_proving_capability,
+            coin_selection,
+            vec![],
+            TransactionDestination::Network,
+        )
+        .await
+    }
+
+    /// Build and hand over the transaction of [RPC::coinjoin_join],
+    /// once authorized.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for write
+    async fn coinjoin_join_inner(
+        mut self,
+        coordinator: SocketAddr,
+        fee: NeptuneCoins,
+    ) -> Option<TransactionKernelId> {
+        let (session, own_address) = {
+            let mut global_state_mut = self.state.lock_guard_mut().await;
+            if global_state_mut.net.tx_proving_capability != TxProvingCapability::SingleProof {
+                warn!("Cannot join coinjoin: this node cannot produce single proofs");
+                return None;
+            }
+            let Some(session) = global_state_mut
+                .net
+                .coinjoin_sessions
+                .get(&coordinator)
+                .copied()
+            else {
+                warn!("No coinjoin session of {coordinator} is known");
+                return None;
+            };
+
+            // A fresh address, such that the denomination output cannot be
+            // linked to other UTXOs of this wallet.
+            let own_address = global_state_mut
+                .wallet_state
+                .next_unused_receiving_address(KeyType::Generation);
+            global_state_mut.persist_wallet().await.expect("flushed");
+            (session, own_address)
+        };
+
+        // The denomination output is announced off-chain, since a public
+        // announcement would set it apart from the other participants'.
+        let notification_medium = UtxoNotificationMedium::OffChain;
+        let tx_outputs = self
+            .state
+            .lock_guard()
+            .await
+            .generate_tx_outputs([(own_address, session.denomination)], notification_medium);
+        let denomination_output = tx_outputs.iter().next()?;
+        let disclosed_output = DisclosedOutput {
+            utxo: denomination_output.utxo(),
+            sender_randomness: denomination_output.sender_randomness(),
+            receiver_digest: denomination_output.receiver_digest(),
+        };
+
+        self.send_tx_outputs_inner(
+            tx_outputs,
+            notification_medium,
+            fee,
+            Timestamp::now(),
+            TxProvingCapability::SingleProof,
+            None,
+            vec![],
+            TransactionDestination::CoinJoin {
+                coordinator,
+                session_id: session.id,
+                disclosed_output,
+            },
+        )
+        .await
+    }
+
+    /// Record a call of a spend-capable method in the spend audit log, if
+    /// enabled. `params` must not include the spend passphrase.
+    fn audit_spend(
+        &self,
+        method: &str,
+        params: &impl Serialize,
+        authorized: bool,
+        txid: Option<TransactionKernelId>,
+    ) {
+        let cli = self.state.cli();
+        if !cli.spend_audit_log {
+            return;
+        }
+
+        let outcome = match (authorized, txid) {
+            (false, _) => SpendOutcome::Unauthorized,
+            (true, None) => SpendOutcome::Failed,
+            (true, Some(_)) => SpendOutcome::Sent,
+        };
+        let appended = DataDirectory::get(cli.data_dir.clone(), cli.network).and_then(|data_dir| {
+            SpendAuditEntry::append_to_log(
+                &data_dir.root_dir_path(),
+                method,
+                SpendAuditEntry::params_digest(params)?,
+                outcome,
+                txid,
+                Timestamp::now(),
+            )
+        });
+        if let Err(err) = appended {
+            error!("Could not record call of {method} in spend audit log: {err:#}");
+        }
+    }
+
+    /// Check that the wallet is unlocked and the spend passphrase of a send
+    /// request is right.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for read
+    async fn spend_authorized(&self, spend_passphrase: Option<String>) -> bool {
+        let state = self.state.lock_guard().await;
+        if state.wallet_state.is_locked() {
+            warn!("Refusing to send: wallet is locked");
+            return false;
+        }
+
+        let authorized = state
+            .wallet_state
+            .spend_authorized(spend_passphrase.as_deref());
+        if !authorized {
+            warn!("Refusing to send: spend passphrase missing or wrong");
+        }
+        authorized
+    }
+
+    /// The update from `known_tip` to the current tip.
+    async fn block_update(state: &GlobalState, known_tip: Option<Digest>) -> BlockUpdate {
+        let archival_state = state.chain.archival_state();
+        let genesis_digest = archival_state.genesis_block().hash();
+        let tip_digest = state.chain.light_state().hash();
+
+        let known_tip = match known_tip {
+            Some(digest) if archival_state.get_block_header(digest).await.is_some() => Some(digest),
+            _ => None,
+        };
+        let (reorg_depth, new_digests) = match known_tip {
+            Some(known_tip) => {
+                let (abandoned, _luca, new_digests) =
+                    archival_state.find_path(known_tip, tip_digest).await;
+                (Some(abandoned.len() as u64), new_digests)
+            }
+            None => (None, vec![tip_digest]),
+        };
+
+        let num_skipped = new_digests.len().saturating_sub(MAX_BLOCK_UPDATE_BLOCKS);
+        let mut new_blocks = vec![];
+        for digest in &new_digests[num_skipped..] {
+            if let Ok(Some(block)) = archival_state.get_block(*digest).await {
+                new_blocks.push(BlockInfo::from_block_and_digests(
+                    &block,
+                    genesis_digest,
+                    tip_digest,
+                ));
+            }
+        }
+
+        BlockUpdate {
+            reorg_depth,
+            new_blocks,
+        }
+    }
+
+    /// Status of the given key rotation, against the current tip.
+    async fn key_rotation_status_inner(&self, rotation: &KeyRotation) -> KeyRotationStatus {
+        let state = self.state.lock_guard().await;
+        let wallet_status = state.get_wallet_status_for_tip().await;
+        rotation.status(&state.wallet_state, &wallet_status, Timestamp::now())
+    }
+
+    /// Update the mutator set data, and with it the proof, of a transaction
+    /// created relative to an earlier tip. A no-op if the transaction is
+    /// synced to the current tip.
+    ///
+    /// Locking:
+    ///   * acquires `global_state_lock` for read
+    async fn update_transaction_to_tip(&self, transaction: Transaction) -> Result<Transaction> {
+        let mutator_set_hash = transaction.kernel.mutator_set_hash;
+        let Some((mutator_set_accumulator, blocks)) = self
+            .state
+            .lock_guard()
+            .await
+            .chain
+            .archival_state()
+            .blocks_since_mutator_set(mutator_set_hash, MAX_TRANSACTION_UPDATE_DEPTH)
+            .await?
+        else {
+            bail!("transaction is synced to mutator set {mutator_set_hash}, which is not recent");
+        };
+        if blocks.is_empty() {
+            return Ok(transaction);
+        }
+
+        info!(
+            "Tip moved by {} blocks while creating transaction; updating it",
+            blocks.len()
+        );
+        let mut transaction = transaction;
+        let mut previous_mutator_set_accumulator = mutator_set_accumulator;
+        for block in blocks {
+            transaction = transaction
+                .new_with_updated_mutator_set_records(
+                    &previous_mutator_set_accumulator,
+                    &block,
+                    &self.state.wait_if_busy(),
+                )
+                .await
+                .map_err(|err| anyhow!("cannot update transaction: {err:?}"))?;
+            previous_mutator_set_accumulator = block.body().mutator_set_accumulator.clone();
+        }
+
+        Ok(transaction)
+    }
+
+    /// Create a transaction with the given outputs, register the expected
+    /// UTXOs it creates for this wallet, and send it to `destination`.
+    ///
+    /// `additional_expected_utxos` are registered in addition to those
+    /// outputs that are recognized as owned by the wallet's keys.
+    ///
+    /// Inputs are selected with `coin_selection`, or with the policy set on
+    /// the command line if `None`.
+    #[allow(clippy::too_many_arguments)]
+    async fn send_tx_outputs_inner(
+        mut self,
+        tx_outputs: TxOutputList,
+        owned_utxo_notification_medium: UtxoNotificationMedium,
+        fee: NeptuneCoins,
+        now: Timestamp,
+        tx_proving_capability: TxProvingCapability,
+        coin_selection: Option<CoinSelectionPolicy>,
+        additional_expected_utxos: Vec<ExpectedUtxo>,
+        destination: TransactionDestination,
     ) -> Option<TransactionKernelId> {
         let span = tracing::debug_span!("Constructing transaction");
         let _enter = span.enter();
 
+        if self.state.lock_guard().await.net.maintenance_mode {
+            warn!("Cannot send transaction in maintenance mode");
+            return None;
+        }
+
+        if self.state.lock_guard().await.wallet_state.is_watch_only() {
+            warn!("Cannot send transaction from watch-only wallet");
+            return None;
+        }
+
         // obtain next unused symmetric key for change utxo
         let change_key = {
             let mut s = self.state.lock_guard_mut().await;
+
+            // checked under the write lock, as a locked wallet holds no keys
+            if s.wallet_state.is_locked() {
+                warn!("Cannot send transaction: wallet is locked");
+                return None;
+            }
             let key = s.wallet_state.next_unused_spending_key(KeyType::Symmetric);
 
             // write state to disk. create_transaction() may be slow.
@@ -290,8 +1075,35 @@ impl NeptuneRPCServer {
             key
         };
 
-        let state = self.state.lock_guard().await;
-        let tx_outputs = state.generate_tx_outputs(outputs, owned_utxo_notification_medium);
+        // Snapshot the spendable UTXOs and select the inputs.
+        //
+        // The lock is only held for the selection, not for proving, which
+        // takes minutes. Holding even a read lock that long would make block
+        // processing wait for it, and all other tasks wait for block
+        // processing.
+        //
+        // note: A change output will be added to tx_outputs if needed.
+        let coin_selection = coin_selection.unwrap_or(self.state.cli().coin_selection);
+        let snapshot = self
+            .state
+            .lock_guard()
+            .await
+            .create_transaction_details(
+                tx_outputs.clone(),
+                change_key,
+                owned_utxo_notification_medium,
+                fee,
+                now,
+                coin_selection,
+            )
+            .await;
+        let (transaction_details, change_outputs) = match snapshot {
+            Ok(snapshot) => snapshot,
+            Err(err) => {
+                tracing::error!("Could not create transaction: {}", err);
+                return None;
+            }
+        };
 
         // Pause miner if we are mining
         let was_mining = self.state.mining().await;
@@ -303,24 +1115,12 @@ impl NeptuneRPCServer {
         }
 
         // Create the transaction
-        //
-        // Note that create_transaction() does not modify any state and only
-        // requires acquiring a read-lock which does not block other tasks.
-        // This is important because internally it calls prove() which is a very
-        // lengthy operation.
-        //
-        // note: A change output will be added to tx_outputs if needed.
-        let (transaction, maybe_change_output) = match state
-            .create_transaction_with_prover_capability(
-                tx_outputs.clone(),
-                change_key,
-                owned_utxo_notification_medium,
-                fee,
-                now,
-                tx_proving_capability,
-                &self.state.wait_if_busy(),
-            )
-            .await
+        let transaction = match GlobalState::create_raw_transaction(
+            transaction_details,
+            tx_proving_capability,
+            &self.state.wait_if_busy(),
+        )
+        .await
         {
             Ok(tx) => tx,
             Err(err) => {
@@ -328,17 +1128,41 @@ impl NeptuneRPCServer {
                 return None;
             }
         };
-        drop(state);
 
-        let utxos_sent_to_self = self
+        // Peers reject transactions that are not synced to the tip, which
+        // may have moved while proving.
+        let transaction = match self.update_transaction_to_tip(transaction).await {
+            Ok(tx) => tx,
+            Err(err) => {
+                tracing::error!("Could not update transaction to new tip: {err}");
+                return None;
+            }
+        };
+
+        // The inputs may have been spent while proving, by a block or by
+        // another transaction sent meanwhile.
+        {
+            let state = self.state.lock_guard().await;
+            let tip_mutator_set_accumulator =
+                &state.chain.light_state().body().mutator_set_accumulator;
+            if state
+                .wallet_state
+                .spends_spent_inputs(&transaction.kernel, tip_mutator_set_accumulator)
+            {
+                tracing::error!(
+                    "Could not send transaction: its inputs were spent while creating it"
+                );
+                return None;
+            }
+        }
+
+        let mut utxos_sent_to_self = self
             .state
             .lock_guard()
             .await
             .wallet_state
-            .extract_expected_utxos(
-                tx_outputs.concat_with(maybe_change_output),
-                UtxoNotifier::Myself,
-            );
+            .extract_expected_utxos(tx_outputs.concat_with(change_outputs), UtxoNotifier::Myself);
+        utxos_sent_to_self.extend(additional_expected_utxos);
 
         // if the tx created offchain expected_utxos we must inform wallet.
         if !utxos_sent_to_self.is_empty() {
@@ -355,11 +1179,30 @@ impl NeptuneRPCServer {
             gsm.persist_wallet().await.expect("flushed wallet");
         }
 
+        if tx_outputs.iter().any(|output| output.memo().is_some()) {
+            let mut gsm = self.state.lock_guard_mut().await;
+            gsm.wallet_state.add_sent_memos(&tx_outputs, now).await;
+            gsm.persist_wallet().await.expect("flushed wallet");
+        }
+
         // Send transaction message to main
-        let response: Result<(), SendError<RPCServerToMain>> = self
-            .rpc_server_to_main_tx
-            .send(RPCServerToMain::BroadcastTx(Box::new(transaction.clone())))
-            .await;
+        let message = match destination {
+            TransactionDestination::Network => {
+                RPCServerToMain::BroadcastTx(Box::new(transaction.clone()))
+            }
+            TransactionDestination::CoinJoin {
+                coordinator,
+                session_id,
+                disclosed_output,
+            } => RPCServerToMain::CoinJoinContribute(Box::new(OwnCoinJoinContribution {
+                coordinator,
+                session_id,
+                transaction: transaction.clone(),
+                disclosed_output,
+            })),
+        };
+        let response: Result<(), SendError<RPCServerToMain>> =
+            self.rpc_server_to_main_tx.send(message).await;
 
         // Restart mining if it was paused
         if was_mining {
@@ -387,6 +1230,38 @@ impl RPC for NeptuneRPCServer {
         self.state.cli().network
     }
 
+    // documented in trait. do not add doc-comment.
+    async fn chain_params(self, _: context::Context) -> ChainParams {
+        ChainParams::for_network(self.state.cli().network)
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //
+    // documented in trait. do not add doc-comment.
+    async fn simulate_difficulty(
+        self,
+        _: context::Context,
+        block_intervals: Vec<Timestamp>,
+        target_block_interval: Option<Timestamp>,
+    ) -> Vec<SimulatedBlock> {
+        let tip_header = self
+            .state
+            .lock_guard()
+            .await
+            .chain
+            .light_state()
+            .header()
+            .clone();
+        difficulty_control::simulate(
+            tip_header.difficulty,
+            tip_header.height,
+            &block_intervals,
+            target_block_interval,
+            self.state.cli().network.params(),
+        )
+    }
+
     // documented in trait. do not add doc-comment.
     async fn own_listen_address_for_peers(self, _context: context::Context) -> Option<SocketAddr> {
         let listen_port = self.state.cli().own_listen_port();
@@ -411,6 +1286,16 @@ impl RPC for NeptuneRPCServer {
             .height
     }
 
+    // documented in trait. do not add doc-comment.
+    async fn sync_progress(self, _: context::Context) -> Option<SyncProgressReport> {
+        self.state
+            .lock_guard()
+            .await
+            .net
+            .sync_progress
+            .map(|sync_progress| sync_progress.report(Timestamp::now()))
+    }
+
     // documented in trait. do not add doc-comment.
     async fn confirmations(self, _: context::Context) -> Option<BlockHeight> {
         self.confirmations_internal().await
@@ -427,8 +1312,73 @@ impl RPC for NeptuneRPCServer {
         }
     }
 
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //
     // documented in trait. do not add doc-comment.
-    async fn block_digest(
+    async fn fork_watch(self, _: context::Context) -> ForkWatchStatus {
+        self.state
+            .lock_guard()
+            .await
+            .net
+            .fork_watch
+            .status(Timestamp::now())
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //
+    // documented in trait. do not add doc-comment.
+    async fn get_blocks_for_announcement(
+        self,
+        _: context::Context,
+        receiver_identifier: BFieldElement,
+    ) -> Option<Vec<Digest>> {
+        let state = self.state.lock_guard().await;
+        let archival_state = state.chain.archival_state();
+        let tip_digest = state.chain.light_state().hash();
+
+        let mut blocks = vec![];
+        let tx_index = archival_state.tx_index()?;
+        for digest in tx_index.blocks_for_announcement(receiver_identifier).await {
+            if archival_state
+                .block_belongs_to_canonical_chain(digest, tip_digest)
+                .await
+            {
+                blocks.push(digest);
+            }
+        }
+        Some(blocks)
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //
+    // documented in trait. do not add doc-comment.
+    async fn get_utxos_for_lock_script_hash(
+        self,
+        _: context::Context,
+        lock_script_hash: Digest,
+    ) -> Option<Vec<IndexedUtxo>> {
+        let state = self.state.lock_guard().await;
+        let archival_state = state.chain.archival_state();
+        let tip_digest = state.chain.light_state().hash();
+
+        let mut utxos = vec![];
+        let tx_index = archival_state.tx_index()?;
+        for utxo in tx_index.utxos_for_lock_script_hash(lock_script_hash).await {
+            if archival_state
+                .block_belongs_to_canonical_chain(utxo.block, tip_digest)
+                .await
+            {
+                utxos.push(utxo);
+            }
+        }
+        Some(utxos)
+    }
+
+    // documented in trait. do not add doc-comment.
+    async fn block_digest(
         self,
         _: context::Context,
         block_selector: BlockSelector,
@@ -443,6 +1393,25 @@ impl RPC for NeptuneRPCServer {
             .map(|_| digest)
     }
 
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //
+    // documented in trait. do not add doc-comment.
+    async fn is_canonical(self, _: context::Context, block_digest: Digest) -> bool {
+        let state = self.state.lock_guard().await;
+        let archival_state = state.chain.archival_state();
+        if archival_state
+            .get_block_header(block_digest)
+            .await
+            .is_none()
+        {
+            return false;
+        }
+        archival_state
+            .block_belongs_to_canonical_chain(block_digest, state.chain.light_state().hash())
+            .await
+    }
+
     // documented in trait. do not add doc-comment.
     async fn block_info(
         self,
@@ -498,7 +1467,7 @@ impl RPC for NeptuneRPCServer {
         // Get all connected peers
         for (socket_address, peer_info) in global_state.net.peer_map.iter() {
             if peer_info.standing.is_negative() {
-                sanctions_in_memory.insert(socket_address.ip(), peer_info.standing);
+                sanctions_in_memory.insert(socket_address.ip(), peer_info.standing.clone());
             }
         }
 
@@ -514,6 +1483,33 @@ impl RPC for NeptuneRPCServer {
         all_sanctions
     }
 
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //
+    // documented in trait. do not add doc-comment.
+    async fn get_peer_violations(
+        self,
+        _context: tarpc::context::Context,
+        ip: IpAddr,
+    ) -> Vec<PeerViolation> {
+        let global_state = self.state.lock_guard().await;
+
+        let connected_standing = global_state
+            .net
+            .peer_map
+            .iter()
+            .find(|(socket_address, _)| socket_address.ip() == ip)
+            .map(|(_, peer_info)| peer_info.standing.clone());
+        let standing = match connected_standing {
+            Some(standing) => Some(standing),
+            None => global_state.net.get_peer_standing_from_database(ip).await,
+        };
+
+        standing
+            .map(|standing| standing.violations)
+            .unwrap_or_default()
+    }
+
     // documented in trait. do not add doc-comment.
     async fn validate_address(
         self,
@@ -521,11 +1517,22 @@ impl RPC for NeptuneRPCServer {
         address_string: String,
         network: Network,
     ) -> Option<ReceivingAddress> {
-        let ret = if let Ok(address) = ReceivingAddress::from_bech32m(&address_string, network) {
-            Some(address)
-        } else {
-            None
-        };
+        let own_network = self.state.cli().network;
+        if network != own_network {
+            tracing::warn!(
+                "Refusing to validate address for network {network}; node runs on {own_network}"
+            );
+            return None;
+        }
+
+        let ret =
+            match ReceivingAddress::from_bech32m_for_network(&address_string, own_network, false) {
+                Ok(address) => Some(address),
+                Err(err) => {
+                    tracing::debug!("Address {address_string} is invalid: {err}");
+                    None
+                }
+            };
         tracing::debug!(
             "Responding to address validation request of {address_string}: {}",
             ret.is_some()
@@ -620,10 +1627,18 @@ impl RPC for NeptuneRPCServer {
     ) -> ReceivingAddress {
         let mut global_state_mut = self.state.lock_guard_mut().await;
 
+        // a watch-only wallet holds no keys, so it hands out the watched
+        // addresses
+        if let Some(watched) = global_state_mut.wallet_state.watched_addresses() {
+            return watched
+                .address_of_type(key_type)
+                .unwrap_or(&watched.addresses()[0])
+                .clone();
+        }
+
         let address = global_state_mut
             .wallet_state
-            .next_unused_spending_key(key_type)
-            .to_address();
+            .next_unused_receiving_address(key_type);
 
         // persist wallet state to disk
         global_state_mut.persist_wallet().await.expect("flushed");
@@ -641,6 +1656,77 @@ impl RPC for NeptuneRPCServer {
         self.state.lock_guard().await.mempool.get_size()
     }
 
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //
+    // documented in trait. do not add doc-comment.
+    async fn estimate_fee(
+        self,
+        _context: tarpc::context::Context,
+        target_blocks: usize,
+    ) -> NeptuneCoins {
+        self.state
+            .lock_guard()
+            .await
+            .mempool
+            .estimate_fee(target_blocks)
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //
+    // documented in trait. do not add doc-comment.
+    async fn mempool_fee_histogram(
+        self,
+        _context: tarpc::context::Context,
+        bucket_count: usize,
+    ) -> Vec<FeeHistogramBucket> {
+        self.state
+            .lock_guard()
+            .await
+            .mempool
+            .fee_histogram(bucket_count)
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //
+    // documented in trait. do not add doc-comment.
+    async fn disclose_transaction_kernel_field(
+        self,
+        _context: tarpc::context::Context,
+        txid: TransactionKernelId,
+        field: TransactionKernelField,
+    ) -> Option<TransactionKernelFieldDisclosure> {
+        let state = self.state.lock_guard().await;
+        let kernel = match state.mempool.get(txid) {
+            Some(transaction) => &transaction.kernel,
+            None => {
+                &state
+                    .wallet_state
+                    .own_transactions
+                    .primitive_witness(txid)?
+                    .kernel
+            }
+        };
+
+        Some(kernel.disclose_field(field))
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //
+    // documented in trait. do not add doc-comment.
+    async fn utxo_set_stats(self, _context: tarpc::context::Context) -> UtxoSetStats {
+        self.state
+            .lock_guard()
+            .await
+            .chain
+            .archival_state()
+            .utxo_set_stats()
+            .await
+    }
+
     // documented in trait. do not add doc-comment.
     async fn history(
         self,
@@ -669,7 +1755,10 @@ impl RPC for NeptuneRPCServer {
         let tip_digest = state.chain.light_state().hash();
         let tip_header = state.chain.light_state().header().clone();
         let wallet_status = state.get_wallet_status_for_tip().await;
-        let syncing = state.net.syncing;
+        let sync_progress = state
+            .net
+            .sync_progress
+            .map(|sync_progress| sync_progress.report(now));
         let mempool_size = state.mempool.get_size();
         let mempool_tx_count = state.mempool.len();
         let cpu_temp = Self::cpu_temp_inner();
@@ -681,6 +1770,7 @@ impl RPC for NeptuneRPCServer {
         let peer_count = Some(state.net.peer_map.len());
 
         let is_mining = Some(state.mining);
+        let unknown_consensus_rules = state.net.fork_watch.status(now).unknown_rules_active;
         drop(state);
 
         let confirmations = self.confirmations_internal().await;
@@ -688,7 +1778,7 @@ impl RPC for NeptuneRPCServer {
         DashBoardOverviewDataFromClient {
             tip_digest,
             tip_header,
-            syncing,
+            sync_progress,
             available_balance: wallet_status.synced_unspent_available_amount(now),
             timelocked_balance: wallet_status.synced_unspent_timelocked_amount(now),
             available_unconfirmed_balance: unconfirmed_balance,
@@ -698,6 +1788,8 @@ impl RPC for NeptuneRPCServer {
             is_mining,
             confirmations,
             cpu_temp,
+            proving_jobs: proving_progress::in_flight(),
+            unknown_consensus_rules,
         }
     }
 
@@ -758,9 +1850,35 @@ impl RPC for NeptuneRPCServer {
         address: ReceivingAddress,
         owned_utxo_notify_method: UtxoNotificationMedium,
         fee: NeptuneCoins,
+        spend_passphrase: Option<String>,
+        coin_selection: Option<CoinSelectionPolicy>,
     ) -> Option<TransactionKernelId> {
-        self.send_to_many(ctx, vec![(address, amount)], owned_utxo_notify_method, fee)
-            .await
+        let params = (
+            amount,
+            &address,
+            owned_utxo_notify_method,
+            fee,
+            coin_selection,
+        );
+        if !self.spend_authorized(spend_passphrase).await {
+            self.audit_spend("send", &params, false, None);
+            return None;
+        }
+
+        let txid = self
+            .clone()
+            .send_to_many_with_memos_inner(
+                vec![(address.clone(), amount)],
+                vec![None],
+                owned_utxo_notify_method,
+                fee,
+                Timestamp::now(),
+                TxProvingCapability::PrimitiveWitness,
+                coin_selection,
+            )
+            .await;
+        self.audit_spend("send", &params, true, txid);
+        txid
     }
 
     // Locking:
@@ -775,20 +1893,204 @@ impl RPC for NeptuneRPCServer {
         outputs: Vec<(ReceivingAddress, NeptuneCoins)>,
         owned_utxo_notification_medium: UtxoNotificationMedium,
         fee: NeptuneCoins,
+        spend_passphrase: Option<String>,
     ) -> Option<TransactionKernelId> {
+        let params = (&outputs, owned_utxo_notification_medium, fee);
+        if !self.spend_authorized(spend_passphrase).await {
+            self.audit_spend("send_to_many", &params, false, None);
+            return None;
+        }
+
         // The proving capability is set to the lowest possible value here,
         // since we don't want the client (CLI or dashboard) to hang. Instead,
         // we let (a task started by) main loop handle the proving.
         let tx_proving_capability = TxProvingCapability::PrimitiveWitness;
-        self.send_to_many_inner(
-            ctx,
-            outputs,
-            owned_utxo_notification_medium,
-            fee,
-            Timestamp::now(),
-            tx_proving_capability,
-        )
-        .await
+        let txid = self
+            .clone()
+            .send_to_many_inner(
+                ctx,
+                outputs.clone(),
+                owned_utxo_notification_medium,
+                fee,
+                Timestamp::now(),
+                tx_proving_capability,
+            )
+            .await;
+        self.audit_spend("send_to_many", &params, true, txid);
+        txid
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for write
+    //
+    // documented in trait. do not add doc-comment.
+    async fn send_to_many_with_memos(
+        self,
+        _ctx: context::Context,
+        outputs: Vec<(ReceivingAddress, NeptuneCoins, Option<Memo>)>,
+        owned_utxo_notification_medium: UtxoNotificationMedium,
+        fee: NeptuneCoins,
+        spend_passphrase: Option<String>,
+    ) -> Option<TransactionKernelId> {
+        let params = (&outputs, owned_utxo_notification_medium, fee);
+        if !self.spend_authorized(spend_passphrase).await {
+            self.audit_spend("send_to_many_with_memos", &params, false, None);
+            return None;
+        }
+
+        let (addresses_and_amounts, memos) = outputs
+            .iter()
+            .cloned()
+            .map(|(address, amount, memo)| ((address, amount), memo))
+            .unzip();
+        let txid = self
+            .clone()
+            .send_to_many_with_memos_inner(
+                addresses_and_amounts,
+                memos,
+                owned_utxo_notification_medium,
+                fee,
+                Timestamp::now(),
+                TxProvingCapability::PrimitiveWitness,
+                None,
+            )
+            .await;
+        self.audit_spend("send_to_many_with_memos", &params, true, txid);
+        txid
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for write
+    //
+    // documented in trait. do not add doc-comment.
+    async fn check_reachability(mut self, _: context::Context) -> Vec<ReachabilityReport> {
+        const NUM_PEERS_TO_ASK: usize = 4;
+        const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+        if self.state.cli().own_listen_port().is_none() {
+            return vec![];
+        }
+
+        let peers = {
+            let mut global_state_mut = self.state.lock_guard_mut().await;
+            let mut peers = global_state_mut.net.peer_map.keys().copied().collect_vec();
+            peers.shuffle(&mut rand::thread_rng());
+            let (ipv4_peers, ipv6_peers): (Vec<_>, Vec<_>) =
+                peers.into_iter().partition(|peer| peer.is_ipv4());
+            let peers = ipv4_peers
+                .into_iter()
+                .interleave(ipv6_peers)
+                .take(NUM_PEERS_TO_ASK)
+                .collect_vec();
+            for peer in &peers {
+                global_state_mut.net.reachability_reports.remove(peer);
+            }
+            peers
+        };
+        if peers.is_empty() {
+            return vec![];
+        }
+
+        if let Err(e) = self
+            .rpc_server_to_main_tx
+            .send(RPCServerToMain::CheckReachability(peers.clone()))
+            .await
+        {
+            error!("Could not send reachability check request to main task: {e}");
+            return vec![];
+        }
+
+        // Allow for the round trips on top of the time peers take to connect,
+        // while staying within the default deadline of RPC calls.
+        let deadline = Instant::now() + REACHABILITY_CHECK_TIMEOUT + Duration::from_secs(2);
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let global_state = self.state.lock_guard().await;
+            let reports = &global_state.net.reachability_reports;
+            if Instant::now() < deadline && !peers.iter().all(|peer| reports.contains_key(peer)) {
+                continue;
+            }
+
+            return peers
+                .iter()
+                .map(|&peer| {
+                    reports.get(&peer).copied().unwrap_or(ReachabilityReport {
+                        peer,
+                        address: None,
+                        reachable: None,
+                    })
+                })
+                .collect();
+        }
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //
+    // documented in trait. do not add doc-comment.
+    async fn coinjoin_sessions(
+        self,
+        _: context::Context,
+    ) -> Vec<(SocketAddr, CoinJoinSessionInfo)> {
+        if let Err(e) = self
+            .rpc_server_to_main_tx
+            .send(RPCServerToMain::RequestCoinJoinSessions)
+            .await
+        {
+            error!("Could not send coinjoin session request to main task: {e}");
+        }
+
+        self.state
+            .lock_guard()
+            .await
+            .net
+            .coinjoin_sessions
+            .iter()
+            .map(|(coordinator, session)| (*coordinator, *session))
+            .collect()
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for write
+    //
+    // documented in trait. do not add doc-comment.
+    async fn coinjoin_join(
+        self,
+        _: context::Context,
+        coordinator: SocketAddr,
+        fee: NeptuneCoins,
+        spend_passphrase: Option<String>,
+    ) -> Option<TransactionKernelId> {
+        let params = (coordinator, fee);
+        if !self.spend_authorized(spend_passphrase).await {
+            self.audit_spend("coinjoin_join", &params, false, None);
+            return None;
+        }
+
+        let txid = self.clone().coinjoin_join_inner(coordinator, fee).await;
+        self.audit_spend("coinjoin_join", &params, true, txid);
+        txid
+    }
+    // Locking:
+    //   * acquires `global_state_lock` for write
+    //
+    // documented in trait. do not add doc-comment.
+    async fn maintenance_mode(mut self, _: context::Context, on: bool) -> bool {
+        self.state.lock_guard_mut().await.net.maintenance_mode = on;
+        if !on {
+            info!("Leaving maintenance mode");
+            return true;
+        }
+
+        info!("Entering maintenance mode");
+        self.state.mempool_admission.drain().await;
+        match self.state.flush_databases().await {
+            Ok(()) => true,
+            Err(e) => {
+                error!("Could not flush databases when entering maintenance mode: {e}");
+                false
+            }
+        }
     }
 
     // documented in trait. do not add doc-comment.
@@ -827,23 +2129,411 @@ impl RPC for NeptuneRPCServer {
         }
     }
 
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //
     // documented in trait. do not add doc-comment.
-    async fn prune_abandoned_monitored_utxos(mut self, _context: tarpc::context::Context) -> usize {
-        let mut global_state_mut = self.state.lock_guard_mut().await;
-        const DEFAULT_MUTXO_PRUNE_DEPTH: usize = 200;
+    async fn get_block_template(self, _context: tarpc::context::Context) -> Option<BlockTemplate> {
+        let state = self.state.lock_guard().await;
+        let tip_header = state.chain.light_state().header();
+        state
+            .block_proposals
+            .latest_template(tip_header, Timestamp::now())
+            .filter(|template| {
+                template.header.prev_block_digest == state.chain.light_state().hash()
+            })
+    }
 
-        let prune_count_res = global_state_mut
-            .prune_abandoned_monitored_utxos(DEFAULT_MUTXO_PRUNE_DEPTH)
-            .await;
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //
+    // documented in trait. do not add doc-comment.
+    async fn get_block_template_update(
+        self,
+        _context: tarpc::context::Context,
+        known_proposal_id: Option<u64>,
+    ) -> Option<BlockTemplateUpdate> {
+        let state = self.state.lock_guard().await;
+        let tip = state.chain.light_state();
+        let (_, latest) = state.block_proposals.latest()?;
+        if latest.template.header().prev_block_digest != tip.hash() {
+            return None;
+        }
 
-        global_state_mut
-            .flush_databases()
-            .await
-            .expect("flushed DBs");
+        state
+            .block_proposals
+            .latest_update(known_proposal_id, tip.header(), Timestamp::now())
+    }
 
-        match prune_count_res {
-            Ok(prune_count) => {
-                info!("Marked {prune_count} monitored UTXOs as abandoned");
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //
+    // documented in trait. do not add doc-comment.
+    async fn submit_block_solution(
+        self,
+        _context: tarpc::context::Context,
+        proposal_id: u64,
+        nonce: [BFieldElement; 3],
+        timestamp: Option<Timestamp>,
+    ) -> Result<Digest, BlockSolutionError> {
+        let (proposal, predecessor) = {
+            let state = self.state.lock_guard().await;
+            let proposal = state
+                .block_proposals
+                .get(proposal_id)
+                .cloned()
+                .ok_or(BlockSolutionError::UnknownProposal)?;
+            let predecessor = state
+                .chain
+                .archival_state()
+                .get_block(proposal.template.header().prev_block_digest)
+                .await
+                .ok()
+                .flatten()
+                .ok_or(BlockSolutionError::UnknownPredecessor)?;
+            (proposal, predecessor)
+        };
+
+        let block = proposal.solve(&predecessor, nonce, timestamp);
+        let new_block_found = proposal.verify_solution(block, &predecessor, Timestamp::now())?;
+
+        let digest = new_block_found.block.hash();
+        info!("External miner solved proposal {proposal_id}, block {digest}");
+        let _ = self
+            .rpc_server_to_main_tx
+            .send(RPCServerToMain::BlockSolutionFound(Box::new(
+                new_block_found,
+            )))
+            .await;
+        Ok(digest)
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //
+    // documented in trait. do not add doc-comment.
+    async fn generate_blocks(
+        self,
+        _context: tarpc::context::Context,
+        num_blocks: usize,
+        address: ReceivingAddress,
+        real_proofs: bool,
+    ) -> Result<Vec<Digest>, GenerateBlocksError> {
+        if self.state.cli().network != Network::RegTest {
+            return Err(GenerateBlocksError::NotRegTest);
+        }
+
+        let mut digests = vec![];
+        for _ in 0..num_blocks {
+            let tip = self.state.lock_guard().await.chain.light_state().clone();
+            let block = match generate_block(
+                &tip,
+                address.clone(),
+                real_proofs,
+                &self.state.wait_if_busy(),
+            )
+            .await
+            {
+                Ok(block) => block,
+                Err(err) => {
+                    warn!("Could not generate block: {err:#}");
+                    return Err(err
+                        .downcast_ref::<GenerateBlocksError>()
+                        .copied()
+                        .unwrap_or(GenerateBlocksError::GenerationFailed));
+                }
+            };
+
+            let digest = block.hash();
+            let (stored_tx, stored_rx) = oneshot::channel();
+            let _ = self
+                .rpc_server_to_main_tx
+                .send(RPCServerToMain::GeneratedBlock(Box::new(block), stored_tx))
+                .await;
+            if !stored_rx.await.unwrap_or(false) {
+                return Err(GenerateBlocksError::NotStored);
+            }
+            digests.push(digest);
+        }
+
+        Ok(digests)
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //
+    // documented in trait. do not add doc-comment.
+    async fn broadcast_transaction(
+        self,
+        _context: tarpc::context::Context,
+        transaction_bytes: Vec<u8>,
+    ) -> Result<TransactionKernelId, BroadcastTransactionError> {
+        let transaction: Transaction = bincode::deserialize(&transaction_bytes)
+            .map_err(|_| BroadcastTransactionError::Malformed)?;
+
+        mempool_admission::check_stateless(
+            &transaction,
+            Timestamp::now(),
+            self.state.cli().network,
+        )
+        .await?;
+        mempool_admission::check_stateful(&transaction, &*self.state.lock_guard().await)?;
+
+        let txid = transaction.kernel.txid();
+        info!("Broadcasting transaction {txid} submitted over RPC");
+        let _ = self
+            .rpc_server_to_main_tx
+            .send(RPCServerToMain::BroadcastExternalTx(Box::new(transaction)))
+            .await;
+
+        Ok(txid)
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for write
+    //
+    // documented in trait. do not add doc-comment.
+    async fn create_psnt(
+        mut self,
+        _context: tarpc::context::Context,
+        outputs: Vec<(ReceivingAddress, NeptuneCoins)>,
+        owned_utxo_notify_medium: UtxoNotificationMedium,
+        fee: NeptuneCoins,
+    ) -> Option<Vec<u8>> {
+        if self.state.lock_guard().await.wallet_state.is_watch_only() {
+            warn!("Cannot create PSNT from watch-only wallet");
+            return None;
+        }
+
+        let change_key = {
+            let mut s = self.state.lock_guard_mut().await;
+            if s.wallet_state.is_locked() {
+                warn!("Cannot create PSNT: wallet is locked");
+                return None;
+            }
+            let key = s.wallet_state.next_unused_spending_key(KeyType::Symmetric);
+            s.persist_wallet().await.expect("flushed");
+            key
+        };
+
+        let state = self.state.lock_guard().await;
+        let tx_outputs = state.generate_tx_outputs(outputs, owned_utxo_notify_medium);
+        let details = state
+            .create_transaction_details(
+                tx_outputs.clone(),
+                change_key,
+                owned_utxo_notify_medium,
+                fee,
+                Timestamp::now(),
+                self.state.cli().coin_selection,
+            )
+            .await;
+        let (transaction_details, change_outputs) = match details {
+            Ok(details) => details,
+            Err(err) => {
+                tracing::error!("Could not create PSNT: {}", err);
+                return None;
+            }
+        };
+        let utxos_sent_to_self = state
+            .wallet_state
+            .extract_expected_utxos(tx_outputs.concat_with(change_outputs), UtxoNotifier::Myself);
+        drop(state);
+
+        // The wallet must expect the UTXOs it is notified of off-chain, in
+        // case the transaction is completed.
+        if !utxos_sent_to_self.is_empty() {
+            let mut gsm = self.state.lock_guard_mut().await;
+            gsm.wallet_state
+                .add_expected_utxos(utxos_sent_to_self)
+                .await;
+            gsm.persist_wallet().await.expect("flushed wallet");
+        }
+
+        Some(PartiallyProvedTransaction::from_details(transaction_details).to_bytes())
+    }
+
+    // documented in trait. do not add doc-comment.
+    async fn combine_psnt(
+        self,
+        _context: tarpc::context::Context,
+        psnts: Vec<Vec<u8>>,
+    ) -> Result<Vec<u8>, PsntError> {
+        let psnts = psnts
+            .iter()
+            .map(|psnt| PartiallyProvedTransaction::from_bytes(psnt))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(PartiallyProvedTransaction::combine(psnts)?.to_bytes())
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for read
+    //   * acquires `global_state_lock` for write, if the PSNT pays the wallet
+    //
+    // documented in trait. do not add doc-comment.
+    async fn finalize_psnt(
+        self,
+        _context: tarpc::context::Context,
+        psnt: Vec<u8>,
+        max_net_spend: NeptuneCoins,
+        spend_passphrase: Option<String>,
+    ) -> Result<PsntFinalization, PsntError> {
+        let mut psnt = PartiallyProvedTransaction::from_bytes(&psnt)?;
+
+        let (own_unproven_inputs, own_outputs) = {
+            let state = self.state.lock_guard().await;
+            let net_spend = psnt.net_spend(&state.wallet_state);
+            if net_spend > max_net_spend {
+                warn!(
+                    "Refusing to finalize PSNT: wallet would spend {net_spend} net, \
+                    more than the confirmed {max_net_spend}"
+                );
+                return Err(PsntError::NetSpendExceeded(net_spend, max_net_spend));
+            }
+
+            let own_unproven_inputs = psnt
+                .inputs
+                .iter()
+                .enumerate()
+                .filter(|(_, input)| input.lock_script_proof.is_none())
+                .filter_map(|(index, input)| {
+                    let spending_key =
+                        state.wallet_state.find_spending_key_for_utxo(&input.utxo)?;
+                    Some((index, spending_key.lock_script_and_witness()))
+                })
+                .collect_vec();
+
+            (own_unproven_inputs, psnt.own_outputs(&state.wallet_state))
+        };
+        if !own_unproven_inputs.is_empty() {
+            if !self.spend_authorized(spend_passphrase).await {
+                return Err(PsntError::SpendNotAuthorized);
+            }
+            info!(
+                "Proving lock scripts of {} inputs of PSNT",
+                own_unproven_inputs.len()
+            );
+            for (index, lock_script_and_witness) in own_unproven_inputs {
+                psnt.prove_lock_script(index, &lock_script_and_witness, &self.state.wait_if_busy())
+                    .await
+                    .map_err(|_| PsntError::ProvingFailed)?;
+            }
+        }
+
+        // The wallet must expect the outputs paying it, in case the party
+        // completing the transaction notifies it of them on-chain only, or
+        // not at all.
+        if !own_outputs.is_empty() {
+            let mut gsm = self.state.lock_guard_mut().await;
+            gsm.wallet_state.add_new_expected_utxos(own_outputs).await;
+            gsm.persist_wallet().await.expect("flushed wallet");
+        }
+
+        let num_unproven_inputs = psnt.num_unproven_inputs();
+        if num_unproven_inputs > 0 {
+            return Ok(PsntFinalization::Incomplete {
+                psnt: psnt.to_bytes(),
+                num_unproven_inputs,
+            });
+        }
+
+        let transaction = psnt.finalize(&self.state.wait_if_busy()).await?;
+        mempool_admission::check_stateless(
+            &transaction,
+            Timestamp::now(),
+            self.state.cli().network,
+        )
+        .await?;
+        mempool_admission::check_stateful(&transaction, &*self.state.lock_guard().await)?;
+
+        let txid = transaction.kernel.txid();
+        info!("Broadcasting transaction {txid} finalized from PSNT");
+        let _ = self
+            .rpc_server_to_main_tx
+            .send(RPCServerToMain::BroadcastTx(Box::new(transaction)))
+            .await;
+
+        Ok(PsntFinalization::Broadcast(txid))
+    }
+
+    // documented in trait. do not add doc-comment.
+    async fn mined_blocks(
+        self,
+        _context: tarpc::context::Context,
+        max_num: usize,
+    ) -> Vec<MinedBlockReport> {
+        let cli = self.state.cli();
+        let reports = DataDirectory::get(cli.data_dir.clone(), cli.network)
+            .and_then(|data_dir| MinedBlockReport::read_log(&data_dir.root_dir_path(), max_num));
+        match reports {
+            Ok(reports) => reports,
+            Err(err) => {
+                warn!("Could not read mining log: {err:#}");
+                vec![]
+            }
+        }
+    }
+
+    // documented in trait. do not add doc-comment.
+    async fn event_journal(
+        self,
+        _context: tarpc::context::Context,
+        from: Timestamp,
+        to: Timestamp,
+        max_num: usize,
+    ) -> Vec<JournalEntry> {
+        // Query a clone such that the lock is not held while reading the database.
+        let event_journal = self.state.lock_guard().await.event_journal().cloned();
+        match event_journal {
+            Some(event_journal) => event_journal.query(from, to, max_num).await,
+            None => vec![],
+        }
+    }
+
+    // documented in trait. do not add doc-comment.
+    async fn spend_audit_log(
+        self,
+        _context: tarpc::context::Context,
+    ) -> Option<Vec<SpendAuditEntry>> {
+        let cli = self.state.cli();
+        let entries = DataDirectory::get(cli.data_dir.clone(), cli.network)
+            .and_then(|data_dir| SpendAuditEntry::read_log(&data_dir.root_dir_path()));
+        match entries {
+            Ok(entries) => Some(entries),
+            Err(err) => {
+                error!("Could not read spend audit log: {err:#}");
+                None
+            }
+        }
+    }
+
+    // documented in trait. do not add doc-comment.
+    async fn validation_reports(
+        self,
+        _context: tarpc::context::Context,
+        max_num: usize,
+    ) -> Vec<ValidationReport> {
+        validation_trace::recent_reports(max_num)
+    }
+
+    // documented in trait. do not add doc-comment.
+    async fn prune_abandoned_monitored_utxos(mut self, _context: tarpc::context::Context) -> usize {
+        let mut global_state_mut = self.state.lock_guard_mut().await;
+        const DEFAULT_MUTXO_PRUNE_DEPTH: usize = 200;
+
+        let prune_count_res = global_state_mut
+            .prune_abandoned_monitored_utxos(DEFAULT_MUTXO_PRUNE_DEPTH)
+            .await;
+
+        global_state_mut
+            .flush_databases()
+            .await
+            .expect("flushed DBs");
+
+        match prune_count_res {
+            Ok(prune_count) => {
+                info!("Marked {prune_count} monitored UTXOs as abandoned");
                 prune_count
             }
             Err(err) => {
@@ -870,6 +2560,436 @@ impl RPC for NeptuneRPCServer {
     async fn cpu_temp(self, _context: tarpc::context::Context) -> Option<f32> {
         Self::cpu_temp_inner()
     }
+
+    // documented in trait. do not add doc-comment.
+    async fn key_rotation_status(self, _: context::Context) -> Option<KeyRotationStatus> {
+        let wallet_directory_path = self
+            .state
+            .lock_guard()
+            .await
+            .wallet_state
+            .wallet_directory_path()
+            .to_path_buf();
+        match KeyRotation::read(&wallet_directory_path) {
+            Ok(Some(rotation)) => Some(self.key_rotation_status_inner(&rotation).await),
+            Ok(None) => None,
+            Err(err) => {
+                error!("Could not read key rotation: {err:#}");
+                None
+            }
+        }
+    }
+
+    // documented in trait. do not add doc-comment.
+    async fn beacon_status(self, _: context::Context) -> BeaconAssessment {
+        let state = self.state.lock_guard().await;
+        let tip = state.chain.light_state();
+        state
+            .net
+            .beacon
+            .assess(
+                state.chain.archival_state(),
+                tip.hash(),
+                tip.header().height,
+            )
+            .await
+    }
+
+    // documented in trait. do not add doc-comment.
+    async fn wallet_audit_export(self, _: context::Context) -> WalletAuditExport {
+        let network = self.state.cli().network;
+        let state = self.state.lock_guard().await;
+        state
+            .wallet_state
+            .audit_export(network, state.chain.light_state().hash())
+            .await
+    }
+
+    // documented in trait. do not add doc-comment.
+    async fn memos(self, _: context::Context) -> Vec<WalletMemo> {
+        self.state.lock_guard().await.wallet_state.memos().await
+    }
+
+    // documented in trait. do not add doc-comment.
+    async fn unconfirmed_spends(self, _: context::Context) -> Vec<UnconfirmedSpend> {
+        self.state
+            .lock_guard()
+            .await
+            .wallet_state
+            .own_transactions
+            .unconfirmed_spends()
+    }
+
+    // documented in trait. do not add doc-comment.
+    async fn spend_passphrase_required(self, _: context::Context) -> bool {
+        self.state
+            .lock_guard()
+            .await
+            .wallet_state
+            .spend_passphrase_required()
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for read, repeatedly while waiting
+    //
+    // documented in trait. do not add doc-comment.
+    async fn subscribe_blocks(
+        self,
+        _: context::Context,
+        known_tip: Option<Digest>,
+        max_wait_secs: u64,
+    ) -> Option<BlockUpdate> {
+        let deadline = tokio::time::Instant::now()
+            + Duration::from_secs(max_wait_secs).min(MAX_BLOCK_SUBSCRIPTION_WAIT);
+
+        // Subscribe before reading the tip, so no new tip is missed in between
+        let mut events = self.state.lock_guard().await.subscribe_to_events();
+        loop {
+            {
+                let state = self.state.lock_guard().await;
+                if Some(state.chain.light_state().hash()) != known_tip {
+                    return Some(Self::block_update(&state, known_tip).await);
+                }
+            }
+
+            // Any event, or a lag, is reason to check the tip again
+            match tokio::time::timeout_at(deadline, events.recv()).await {
+                Err(_elapsed) => return None,
+                Ok(Err(broadcast::error::RecvError::Closed)) => return None,
+                Ok(_) => (),
+            }
+        }
+    }
+
+    // documented in trait. do not add doc-comment.
+    async fn wallet_summary(self, _: context::Context) -> WalletSummary {
+        let state = self.state.lock_guard().await;
+        let tip_digest = state.chain.light_state().hash();
+        let sync_label = state.wallet_state.wallet_db.get_sync_label().await;
+        let last_scan_height = state
+            .chain
+            .archival_state()
+            .get_block_header(sync_label)
+            .await
+            .map(|header| header.height);
+        state
+            .wallet_state
+            .summary(tip_digest, Timestamp::now(), last_scan_height)
+            .await
+    }
+
+    // documented in trait. do not add doc-comment.
+    async fn rpc_schemas(self, _: context::Context) -> RpcSchemas {
+        RpcSchemas::generate()
+    }
+
+    // documented in trait. do not add doc-comment.
+    async fn key_rotation_start(self, _: context::Context) -> Option<KeyRotationStatus> {
+        let wallet_directory_path = {
+            let state = self.state.lock_guard().await;
+            if state.wallet_state.is_encrypted() {
+                error!("Cannot rotate keys of a wallet encrypted at rest");
+                return None;
+            }
+            state.wallet_state.wallet_directory_path().to_path_buf()
+        };
+        match KeyRotation::start(&wallet_directory_path, Timestamp::now()) {
+            Ok(rotation) => {
+                info!(
+                    "Key rotation in progress since {}. Back up the new seed in {}.",
+                    rotation.started.standard_format(),
+                    wallet_directory_path.display()
+                );
+                Some(self.key_rotation_status_inner(&rotation).await)
+            }
+            Err(err) => {
+                error!("Could not start key rotation: {err:#}");
+                None
+            }
+        }
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for write
+    //
+    // documented in trait. do not add doc-comment.
+    async fn key_rotation_sweep(
+        self,
+        _: context::Context,
+        fee: NeptuneCoins,
+        spend_passphrase: Option<String>,
+    ) -> Option<KeyRotationStatus> {
+        if !self.spend_authorized(spend_passphrase).await {
+            self.audit_spend("key_rotation_sweep", &fee, false, None);
+            return None;
+        }
+
+        let wallet_directory_path = {
+            let state = self.state.lock_guard().await;
+            if state.wallet_state.is_encrypted() {
+                error!("Cannot rotate keys of a wallet encrypted at rest");
+                return None;
+            }
+            state.wallet_state.wallet_directory_path().to_path_buf()
+        };
+        let mut rotation = match KeyRotation::read(&wallet_directory_path) {
+            Ok(Some(rotation)) => rotation,
+            Ok(None) => {
+                error!("Cannot sweep: no key rotation in progress");
+                return None;
+            }
+            Err(err) => {
+                error!("Could not read key rotation: {err:#}");
+                return None;
+            }
+        };
+
+        let status = self.key_rotation_status_inner(&rotation).await;
+        if status.next_step != KeyRotationStep::Sweep {
+            error!("Cannot sweep: next step is to {}", status.next_step);
+            return None;
+        }
+        let Some(amount) = status.sweepable.checked_sub(&fee).filter(|a| !a.is_zero()) else {
+            error!(
+                "Cannot sweep: fee {fee} is not less than sweepable amount {}",
+                status.sweepable
+            );
+            return None;
+        };
+        let next_spending_key = match KeyRotation::next_spending_key(&wallet_directory_path) {
+            Ok(key) => key,
+            Err(err) => {
+                error!("Could not read new seed: {err:#}");
+                return None;
+            }
+        };
+
+        // Funds sent to the new seed are announced on chain, so the new seed
+        // can recover them on its own, and are tracked by this wallet as
+        // expected UTXOs, so their membership proofs are ready once the new
+        // seed is installed.
+        let notification_medium = UtxoNotificationMedium::OnChain;
+        let tx_outputs = self.state.lock_guard().await.generate_tx_outputs(
+            [(rotation.destination.clone(), amount)],
+            notification_medium,
+        );
+        let sweep_expected_utxos = tx_outputs
+            .iter()
+            .map(|tx_output| {
+                ExpectedUtxo::new(
+                    tx_output.utxo(),
+                    tx_output.sender_randomness(),
+                    next_spending_key.privacy_preimage(),
+                    UtxoNotifier::Myself,
+                )
+            })
+            .collect();
+
+        let now = Timestamp::now();
+        let txid = self
+            .clone()
+            .send_tx_outputs_inner(
+                tx_outputs,
+                notification_medium,
+                fee,
+                now,
+                TxProvingCapability::PrimitiveWitness,
+                None,
+                sweep_expected_utxos,
+                TransactionDestination::Network,
+            )
+            .await;
+        self.audit_spend("key_rotation_sweep", &fee, true, txid);
+        let txid = txid?;
+
+        rotation.sweeps.push(KeyRotationSweep {
+            txid,
+            amount,
+            fee,
+            timestamp: now,
+        });
+        if let Err(err) = rotation.write(&wallet_directory_path) {
+            error!("Could not record key rotation sweep {txid}: {err:#}");
+        }
+        info!("Key rotation swept {amount} in transaction {txid}");
+
+        Some(self.key_rotation_status_inner(&rotation).await)
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for write
+    //
+    // documented in trait. do not add doc-comment.
+    async fn key_rotation_finish(self, _: context::Context) -> bool {
+        let mut state = self.state.lock_guard_mut().await;
+        if state.wallet_state.is_encrypted() {
+            error!("Cannot rotate keys of a wallet encrypted at rest");
+            return false;
+        }
+        let wallet_directory_path = state.wallet_state.wallet_directory_path().to_path_buf();
+        let rotation = match KeyRotation::read(&wallet_directory_path) {
+            Ok(Some(rotation)) => rotation,
+            Ok(None) => {
+                error!("Cannot finish: no key rotation in progress");
+                return false;
+            }
+            Err(err) => {
+                error!("Could not read key rotation: {err:#}");
+                return false;
+            }
+        };
+
+        let wallet_status = state.get_wallet_status_for_tip().await;
+        let now = Timestamp::now();
+        let status = rotation.status(&state.wallet_state, &wallet_status, now);
+        match rotation.finish(&wallet_directory_path, &status, now) {
+            Ok(next_wallet_secret) => {
+                state.wallet_state.set_wallet_secret(next_wallet_secret);
+                info!("Key rotation finished; wallet now uses the new seed");
+                true
+            }
+            Err(err) => {
+                error!("Could not finish key rotation: {err:#}");
+                false
+            }
+        }
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for write
+    //
+    // documented in trait. do not add doc-comment.
+    async fn import_expected_utxos(
+        mut self,
+        _: context::Context,
+        bundle: ExpectedUtxoBundle,
+        trusted_signer: Option<String>,
+    ) -> Result<ExpectedUtxoImport, ExpectedUtxoBundleError> {
+        bundle.verify(self.state.cli().network, trusted_signer.as_deref())?;
+
+        let mut global_state_mut = self.state.lock_guard_mut().await;
+        let import = global_state_mut
+            .wallet_state
+            .import_expected_utxo_bundle(&bundle)
+            .await?;
+        global_state_mut
+            .persist_wallet()
+            .await
+            .expect("flushed wallet");
+
+        info!(
+            "Imported {} expected UTXOs from bundle signed by {}, skipped {} duplicates",
+            import.imported,
+            bundle.signer_hex(),
+            import.duplicates
+        );
+        Ok(import)
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for write
+    //
+    // documented in trait. do not add doc-comment.
+    async fn export_account_descriptor(
+        mut self,
+        _: context::Context,
+        account: u32,
+    ) -> Option<AccountDescriptor> {
+        let network = self.state.cli().network;
+        let mut global_state_mut = self.state.lock_guard_mut().await;
+        match global_state_mut
+            .wallet_state
+            .export_account_descriptor(network, account)
+            .await
+        {
+            Ok(descriptor) => {
+                info!("Exported descriptor of account {account}");
+                Some(descriptor)
+            }
+            Err(err) => {
+                warn!("Could not export descriptor of account {account}: {err}");
+                None
+            }
+        }
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for write
+    //
+    // documented in trait. do not add doc-comment.
+    async fn wallet_unlock(
+        self,
+        _: context::Context,
+        passphrase: String,
+        timeout_secs: Option<u64>,
+    ) -> bool {
+        let timeout = timeout_secs.map(Duration::from_secs);
+        if let Err(err) = self
+            .state
+            .lock_guard_mut()
+            .await
+            .wallet_state
+            .unlock(&passphrase, timeout)
+            .await
+        {
+            warn!("Could not unlock wallet: {err}");
+            return false;
+        }
+
+        match timeout {
+            Some(timeout) => {
+                info!("Wallet unlocked for {} seconds", timeout.as_secs());
+                let state = self.state.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(timeout).await;
+                    state.lock_guard_mut().await.wallet_state.relock_if_due();
+                });
+            }
+            None => info!("Wallet unlocked"),
+        }
+        true
+    }
+
+    // Locking:
+    //   * acquires `global_state_lock` for write
+    //
+    // documented in trait. do not add doc-comment.
+    async fn wallet_lock(self, _: context::Context) -> bool {
+        let locked = self.state.lock_guard_mut().await.wallet_state.lock();
+        if locked {
+            info!("Wallet locked");
+        } else {
+            warn!("Cannot lock wallet: wallet is not encrypted");
+        }
+        locked
+    }
+
+    // documented in trait. do not add doc-comment.
+    async fn beacon_submit_checkpoint(
+        self,
+        _: context::Context,
+        checkpoint: SignedCheckpoint,
+    ) -> bool {
+        let network = self.state.cli().network;
+        let (height, digest) = (checkpoint.height, checkpoint.digest);
+        match self
+            .state
+            .lock_guard_mut()
+            .await
+            .net
+            .beacon
+            .accept(network, checkpoint)
+        {
+            Ok(()) => {
+                info!("Accepted checkpoint {} at height {height}", digest.to_hex());
+                true
+            }
+            Err(err) => {
+                warn!("Rejected checkpoint at height {height}: {err}");
+                false
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -889,20 +3009,29 @@ mod rpc_server_tests {
     use ReceivingAddress;
 
     use super::*;
+    use crate::config_models::cli_args;
     use crate::config_models::network::Network;
     use crate::database::storage::storage_vec::traits::*;
     use crate::models::peer::PeerSanctionReason;
+    use crate::models::state::mempool_admission::AdmissionRejection;
     use crate::models::state::wallet::address::generation_address::GenerationReceivingAddress;
+    use crate::models::state::wallet::encrypted_secret::EncryptedWalletSecret;
     use crate::models::state::wallet::expected_utxo::ExpectedUtxo;
     use crate::models::state::wallet::expected_utxo::UtxoNotifier;
+    use crate::models::state::wallet::key_rotation::KeyRotation;
+    use crate::models::state::wallet::key_rotation::KeyRotationStatus;
+    use crate::models::state::wallet::key_rotation::KeyRotationStep;
+    use crate::models::state::wallet::spend_authorization::SpendAuthorization;
     use crate::models::state::wallet::WalletSecret;
     use crate::rpc_server::NeptuneRPCServer;
     use crate::tests::shared::make_mock_block;
+    use crate::tests::shared::make_mock_transaction;
     use crate::tests::shared::mock_genesis_global_state;
+    use crate::tests::shared::random_transaction_kernel;
     use crate::Block;
     use crate::RPC_CHANNEL_CAPACITY;
 
-    async fn test_rpc_server(
+    pub(super) async fn test_rpc_server(
         network: Network,
         wallet_secret: WalletSecret,
         peer_count: u8,
@@ -932,7 +3061,14 @@ mod rpc_server_tests {
         // Verify that a wallet not receiving a premine is empty at startup
         for network in Network::iter() {
             let (rpc_server, _) = test_rpc_server(network, WalletSecret::new_random(), 2).await;
-            assert_eq!(network, rpc_server.network(context::current()).await);
+            assert_eq!(
+                network,
+                rpc_server.clone().network(context::current()).await
+            );
+            assert_eq!(
+                network,
+                rpc_server.chain_params(context::current()).await.network
+            );
         }
 
         Ok(())
@@ -951,11 +3087,21 @@ mod rpc_server_tests {
             test_rpc_server(network, WalletSecret::new_pseudorandom(rng.gen()), 2).await;
         let ctx = context::current();
         let _ = rpc_server.clone().network(ctx).await;
+        let _ = rpc_server.clone().chain_params(ctx).await;
+        let _ = rpc_server
+            .clone()
+            .simulate_difficulty(ctx, vec![Timestamp::minutes(5)], None)
+            .await;
         let _ = rpc_server.clone().own_listen_address_for_peers(ctx).await;
         let _ = rpc_server.clone().own_instance_id(ctx).await;
         let _ = rpc_server.clone().block_height(ctx).await;
+        let _ = rpc_server.clone().sync_progress(ctx).await;
         let _ = rpc_server.clone().peer_info(ctx).await;
         let _ = rpc_server.clone().all_sanctioned_peers(ctx).await;
+        let _ = rpc_server
+            .clone()
+            .get_peer_violations(ctx, "127.0.0.1".parse().unwrap())
+            .await;
         let _ = rpc_server.clone().latest_tip_digests(ctx, 2).await;
         let _ = rpc_server
             .clone()
@@ -967,9 +3113,22 @@ mod rpc_server_tests {
             .await;
         let _ = rpc_server
             .clone()
-            .block_digest(ctx, BlockSelector::Digest(Digest::default()))
+            .block_digest(ctx, BlockSelector::Digest(Digest::default()))
+            .await;
+        let _ = rpc_server
+            .clone()
+            .is_canonical(ctx, Digest::default())
+            .await;
+        let _ = rpc_server.clone().utxo_digest(ctx, 0).await;
+        let _ = rpc_server.clone().fork_watch(ctx).await;
+        let _ = rpc_server
+            .clone()
+            .get_blocks_for_announcement(ctx, BFieldElement::new(0))
+            .await;
+        let _ = rpc_server
+            .clone()
+            .get_utxos_for_lock_script_hash(ctx, Digest::default())
             .await;
-        let _ = rpc_server.clone().utxo_digest(ctx, 0).await;
         let _ = rpc_server.clone().synced_balance(ctx).await;
         let _ = rpc_server.clone().history(ctx).await;
         let _ = rpc_server.clone().wallet_status(ctx).await;
@@ -979,6 +3138,17 @@ mod rpc_server_tests {
             .await;
         let _ = rpc_server.clone().mempool_tx_count(ctx).await;
         let _ = rpc_server.clone().mempool_size(ctx).await;
+        let _ = rpc_server.clone().estimate_fee(ctx, 1).await;
+        let _ = rpc_server.clone().mempool_fee_histogram(ctx, 10).await;
+        let _ = rpc_server
+            .clone()
+            .disclose_transaction_kernel_field(
+                ctx,
+                random_transaction_kernel().txid(),
+                TransactionKernelField::Fee,
+            )
+            .await;
+        let _ = rpc_server.clone().utxo_set_stats(ctx).await;
         let _ = rpc_server.clone().dashboard_overview_data(ctx).await;
         let _ = rpc_server
             .clone()
@@ -997,6 +3167,8 @@ mod rpc_server_tests {
                 own_receiving_address.clone(),
                 UtxoNotificationMedium::OffChain,
                 NeptuneCoins::one(),
+                None,
+                None,
             )
             .await;
 
@@ -1011,19 +3183,130 @@ mod rpc_server_tests {
             .clone()
             .send_to_many_inner(
                 ctx,
-                vec![(own_receiving_address, NeptuneCoins::one())],
+                vec![(own_receiving_address.clone(), NeptuneCoins::one())],
                 UtxoNotificationMedium::OffChain,
                 NeptuneCoins::one(),
                 transaction_timestamp,
                 proving_capability,
             )
             .await;
+        let _ = rpc_server
+            .clone()
+            .send_to_many_with_memos(
+                ctx,
+                vec![],
+                UtxoNotificationMedium::OffChain,
+                NeptuneCoins::one(),
+                None,
+            )
+            .await;
         let _ = rpc_server.clone().pause_miner(ctx).await;
+        let _ = rpc_server.clone().mined_blocks(ctx, 10).await;
+        let _ = rpc_server
+            .clone()
+            .event_journal(ctx, Timestamp::now(), Timestamp::now(), 10)
+            .await;
+        let _ = rpc_server.clone().spend_audit_log(ctx).await;
+        let _ = rpc_server.clone().validation_reports(ctx, 10).await;
         let _ = rpc_server.clone().restart_miner(ctx).await;
+        let _ = rpc_server.clone().get_block_template(ctx).await;
+        let _ = rpc_server
+            .clone()
+            .get_block_template_update(ctx, Some(0))
+            .await;
+        let _ = rpc_server
+            .clone()
+            .submit_block_solution(ctx, 0, Default::default(), None)
+            .await;
+        let _ = rpc_server
+            .clone()
+            .generate_blocks(ctx, 1, own_receiving_address, false)
+            .await;
+        let _ = rpc_server
+            .clone()
+            .broadcast_transaction(ctx, vec![0u8; 10])
+            .await;
+        let _ = rpc_server
+            .clone()
+            .create_psnt(
+                ctx,
+                vec![],
+                UtxoNotificationMedium::OffChain,
+                NeptuneCoins::one(),
+            )
+            .await;
+        let _ = rpc_server
+            .clone()
+            .combine_psnt(ctx, vec![vec![0u8; 10]])
+            .await;
+        let _ = rpc_server
+            .clone()
+            .finalize_psnt(ctx, vec![0u8; 10], NeptuneCoins::zero(), None)
+            .await;
         let _ = rpc_server
             .clone()
             .prune_abandoned_monitored_utxos(ctx)
             .await;
+        let _ = rpc_server.clone().key_rotation_status(ctx).await;
+        let _ = rpc_server.clone().beacon_status(ctx).await;
+        let _ = rpc_server.clone().wallet_audit_export(ctx).await;
+        let _ = rpc_server.clone().memos(ctx).await;
+        let _ = rpc_server.clone().unconfirmed_spends(ctx).await;
+        let _ = rpc_server.clone().spend_passphrase_required(ctx).await;
+        let _ = rpc_server.clone().wallet_summary(ctx).await;
+        let _ = rpc_server.clone().rpc_schemas(ctx).await;
+        let _ = rpc_server.clone().subscribe_blocks(ctx, None, 0).await;
+        let _ = rpc_server.clone().key_rotation_start(ctx).await;
+        let _ = rpc_server
+            .clone()
+            .key_rotation_sweep(ctx, NeptuneCoins::one(), None)
+            .await;
+        let _ = rpc_server.clone().key_rotation_finish(ctx).await;
+        let _ = rpc_server
+            .clone()
+            .import_expected_utxos(
+                ctx,
+                ExpectedUtxoBundle {
+                    network,
+                    utxos: vec![],
+                    signer: [0; 32],
+                    signature: vec![],
+                },
+                None,
+            )
+            .await;
+        let _ = rpc_server.clone().export_account_descriptor(ctx, 0).await;
+        let _ = rpc_server
+            .clone()
+            .wallet_unlock(ctx, "passphrase".to_owned(), Some(1))
+            .await;
+        let _ = rpc_server.clone().wallet_lock(ctx).await;
+        let _ = rpc_server
+            .clone()
+            .beacon_submit_checkpoint(
+                ctx,
+                SignedCheckpoint {
+                    network,
+                    height: BlockHeight::genesis(),
+                    digest: Digest::default(),
+                    signer: [0; 32],
+                    signature: vec![],
+                },
+            )
+            .await;
+        let _ = rpc_server.clone().check_reachability(ctx).await;
+        let _ = rpc_server.clone().coinjoin_sessions(ctx).await;
+        let _ = rpc_server
+            .clone()
+            .coinjoin_join(
+                ctx,
+                "127.0.0.1:9798".parse().unwrap(),
+                NeptuneCoins::zero(),
+                None,
+            )
+            .await;
+        let _ = rpc_server.clone().maintenance_mode(ctx, true).await;
+        let _ = rpc_server.clone().maintenance_mode(ctx, false).await;
         let _ = rpc_server.shutdown(ctx).await;
 
         Ok(())
@@ -1040,6 +3323,66 @@ mod rpc_server_tests {
         Ok(())
     }
 
+    #[traced_test]
+    #[tokio::test]
+    async fn validate_address_refuses_addresses_of_other_networks() {
+        let network = Network::RegTest;
+        let (rpc_server, _) = test_rpc_server(network, WalletSecret::new_random(), 2).await;
+        let ctx = context::current();
+        let address =
+            ReceivingAddress::from(GenerationReceivingAddress::derive_from_seed(rand::random()));
+        let own = address.to_bech32m(network).unwrap();
+        let other = address.to_bech32m(Network::Testnet).unwrap();
+
+        assert_eq!(
+            Some(address),
+            rpc_server
+                .clone()
+                .validate_address(ctx, own.clone(), network)
+                .await
+        );
+        assert!(rpc_server
+            .clone()
+            .validate_address(ctx, other.clone(), network)
+            .await
+            .is_none());
+
+        // the client cannot ask for another network than the node's
+        assert!(rpc_server
+            .clone()
+            .validate_address(ctx, own, Network::Testnet)
+            .await
+            .is_none());
+        assert!(rpc_server
+            .validate_address(ctx, other, Network::Testnet)
+            .await
+            .is_none());
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn broadcast_transaction_rejects_malformed_and_unproven_transactions() {
+        let (rpc_server, _) = test_rpc_server(Network::Alpha, WalletSecret::new_random(), 2).await;
+        assert_eq!(
+            Err(BroadcastTransactionError::Malformed),
+            rpc_server
+                .clone()
+                .broadcast_transaction(context::current(), vec![1, 2, 3])
+                .await
+        );
+
+        let transaction = make_mock_transaction(vec![], vec![]);
+        let transaction_bytes = bincode::serialize(&transaction).unwrap();
+        assert_eq!(
+            Err(BroadcastTransactionError::Rejected(
+                AdmissionRejection::InvalidProof
+            )),
+            rpc_server
+                .broadcast_transaction(context::current(), transaction_bytes)
+                .await
+        );
+    }
+
     #[allow(clippy::shadow_unrelated)]
     #[traced_test]
     #[tokio::test]
@@ -1082,8 +3425,12 @@ mod rpc_server_tests {
                 .and_modify(|p| {
                     p.standing.sanction(PeerSanctionReason::DifferentGenesis);
                 });
-            let standing_0 = global_state_mut.net.peer_map[&peer_address_0].standing;
-            let standing_1 = global_state_mut.net.peer_map[&peer_address_1].standing;
+            let standing_0 = global_state_mut.net.peer_map[&peer_address_0]
+                .standing
+                .clone();
+            let standing_1 = global_state_mut.net.peer_map[&peer_address_1]
+                .standing
+                .clone();
             (standing_0, standing_1)
         };
 
@@ -1129,14 +3476,14 @@ mod rpc_server_tests {
                 .net
                 .get_peer_standing_from_database(peer_address_0.ip())
                 .await;
-            assert_ne!(0, peer_standing_0.unwrap().standing);
-            assert_ne!(None, peer_standing_0.unwrap().latest_sanction);
+            assert_ne!(0, peer_standing_0.as_ref().unwrap().standing);
+            assert_ne!(None, peer_standing_0.as_ref().unwrap().latest_sanction);
             let peer_standing_1 = global_state
                 .net
                 .get_peer_standing_from_database(peer_address_1.ip())
                 .await;
-            assert_ne!(0, peer_standing_1.unwrap().standing);
-            assert_ne!(None, peer_standing_1.unwrap().latest_sanction);
+            assert_ne!(0, peer_standing_1.as_ref().unwrap().standing);
+            assert_ne!(None, peer_standing_1.as_ref().unwrap().latest_sanction);
             drop(global_state);
 
             // Clear standing of #0
@@ -1153,14 +3500,14 @@ mod rpc_server_tests {
                 .net
                 .get_peer_standing_from_database(peer_address_0.ip())
                 .await;
-            assert_eq!(0, peer_standing_0.unwrap().standing);
-            assert_eq!(None, peer_standing_0.unwrap().latest_sanction);
+            assert_eq!(0, peer_standing_0.as_ref().unwrap().standing);
+            assert_eq!(None, peer_standing_0.as_ref().unwrap().latest_sanction);
             let peer_standing_1 = global_state
                 .net
                 .get_peer_standing_from_database(peer_address_1.ip())
                 .await;
-            assert_ne!(0, peer_standing_1.unwrap().standing);
-            assert_ne!(None, peer_standing_1.unwrap().latest_sanction);
+            assert_ne!(0, peer_standing_1.as_ref().unwrap().standing);
+            assert_ne!(None, peer_standing_1.as_ref().unwrap().latest_sanction);
 
             // Verify expected resulting conditions in peer map
             let peer_standing_0_from_memory = global_state.net.peer_map[&peer_address_0].clone();
@@ -1201,8 +3548,8 @@ mod rpc_server_tests {
             state.net.peer_map.entry(peer_address_1).and_modify(|p| {
                 p.standing.sanction(PeerSanctionReason::DifferentGenesis);
             });
-            let standing_0 = state.net.peer_map[&peer_address_0].standing;
-            let standing_1 = state.net.peer_map[&peer_address_1].standing;
+            let standing_0 = state.net.peer_map[&peer_address_0].standing.clone();
+            let standing_1 = state.net.peer_map[&peer_address_1].standing.clone();
             (standing_0, standing_1)
         };
 
@@ -1225,8 +3572,8 @@ mod rpc_server_tests {
                 .net
                 .get_peer_standing_from_database(peer_address_0.ip())
                 .await;
-            assert_ne!(0, peer_standing_0.unwrap().standing);
-            assert_ne!(None, peer_standing_0.unwrap().latest_sanction);
+            assert_ne!(0, peer_standing_0.as_ref().unwrap().standing);
+            assert_ne!(None, peer_standing_0.as_ref().unwrap().latest_sanction);
         }
 
         {
@@ -1236,8 +3583,8 @@ mod rpc_server_tests {
                 .net
                 .get_peer_standing_from_database(peer_address_1.ip())
                 .await;
-            assert_ne!(0, peer_standing_1.unwrap().standing);
-            assert_ne!(None, peer_standing_1.unwrap().latest_sanction);
+            assert_ne!(0, peer_standing_1.as_ref().unwrap().standing);
+            assert_ne!(None, peer_standing_1.as_ref().unwrap().latest_sanction);
         }
 
         // Verify expected reading through an RPC call
@@ -1262,8 +3609,8 @@ mod rpc_server_tests {
                 .net
                 .get_peer_standing_from_database(peer_address_0.ip())
                 .await;
-            assert_eq!(0, peer_standing_0.unwrap().standing);
-            assert_eq!(None, peer_standing_0.unwrap().latest_sanction);
+            assert_eq!(0, peer_standing_0.as_ref().unwrap().standing);
+            assert_eq!(None, peer_standing_0.as_ref().unwrap().latest_sanction);
         }
 
         {
@@ -1271,8 +3618,11 @@ mod rpc_server_tests {
                 .net
                 .get_peer_standing_from_database(peer_address_1.ip())
                 .await;
-            assert_eq!(0, peer_still_standing_1.unwrap().standing);
-            assert_eq!(None, peer_still_standing_1.unwrap().latest_sanction);
+            assert_eq!(0, peer_still_standing_1.as_ref().unwrap().standing);
+            assert_eq!(
+                None,
+                peer_still_standing_1.as_ref().unwrap().latest_sanction
+            );
         }
 
         // Verify expected resulting conditions in peer map
@@ -1468,6 +3818,41 @@ mod rpc_server_tests {
             .block_digest(ctx, BlockSelector::Digest(Digest::default()))
             .await
             .is_none());
+
+        // the tip is at depth zero, and there is nothing below genesis
+        assert_eq!(
+            rpc_server
+                .clone()
+                .block_digest(ctx, BlockSelector::Tip)
+                .await,
+            rpc_server
+                .clone()
+                .block_digest(ctx, BlockSelector::Depth(0))
+                .await
+        );
+        assert!(rpc_server
+            .clone()
+            .block_digest(ctx, BlockSelector::Depth(u64::MAX))
+            .await
+            .is_none());
+
+        // selecting by the genesis timestamp finds genesis, earlier finds nothing
+        let genesis_timestamp = Block::genesis_block(network).header().timestamp;
+        assert_eq!(
+            Some(genesis_hash),
+            rpc_server
+                .clone()
+                .block_digest(ctx, BlockSelector::Timestamp(genesis_timestamp))
+                .await
+        );
+        assert!(rpc_server
+            .clone()
+            .block_digest(
+                ctx,
+                BlockSelector::Timestamp(genesis_timestamp - Timestamp::millis(1))
+            )
+            .await
+            .is_none());
     }
 
     #[traced_test]
@@ -1480,6 +3865,313 @@ mod rpc_server_tests {
         let _current_server_temperature = rpc_server.cpu_temp(context::current()).await;
     }
 
+    #[traced_test]
+    #[tokio::test]
+    async fn subscribe_blocks_waits_for_new_tip() {
+        let network = Network::RegTest;
+        let (rpc_server, mut state_lock) =
+            test_rpc_server(network, WalletSecret::new_random(), 2).await;
+        let ctx = context::current();
+        let genesis = Block::genesis_block(network);
+
+        // Unknown tip: the current tip is returned right away
+        let update = rpc_server
+            .clone()
+            .subscribe_blocks(ctx, None, 0)
+            .await
+            .unwrap();
+        assert_eq!(None, update.reorg_depth);
+        assert_eq!(
+            vec![genesis.hash()],
+            update.new_blocks.iter().map(|b| b.digest).collect_vec()
+        );
+
+        // Known tip: nothing happens before the timeout
+        assert!(rpc_server
+            .clone()
+            .subscribe_blocks(ctx, Some(genesis.hash()), 0)
+            .await
+            .is_none());
+
+        // A new tip while waiting ends the wait
+        let subscription = tokio::spawn(rpc_server.clone().subscribe_blocks(
+            ctx,
+            Some(genesis.hash()),
+            MAX_BLOCK_SUBSCRIPTION_WAIT.as_secs(),
+        ));
+        let address = WalletSecret::new_random()
+            .nth_generation_spending_key_for_tests(0)
+            .to_address();
+        let (block_1, _, _) = make_mock_block(&genesis, None, address, rand::random());
+        state_lock.set_new_tip(block_1.clone()).await.unwrap();
+        let update = subscription.await.unwrap().unwrap();
+        assert_eq!(Some(0), update.reorg_depth);
+        assert_eq!(
+            vec![block_1.hash()],
+            update.new_blocks.iter().map(|b| b.digest).collect_vec()
+        );
+
+        // A competing block at the same height is a reorganization of depth 1
+        let (block_1b, _, _) = make_mock_block(&genesis, None, address, rand::random());
+        let (block_2b, _, _) = make_mock_block(&block_1b, None, address, rand::random());
+        state_lock.set_new_tip(block_1b.clone()).await.unwrap();
+        state_lock.set_new_tip(block_2b.clone()).await.unwrap();
+        let update = rpc_server
+            .subscribe_blocks(ctx, Some(block_1.hash()), 0)
+            .await
+            .unwrap();
+        assert_eq!(Some(1), update.reorg_depth);
+        assert_eq!(
+            vec![block_1b.hash(), block_2b.hash()],
+            update.new_blocks.iter().map(|b| b.digest).collect_vec()
+        );
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn wallet_summary_of_premine_recipient() {
+        let (rpc_server, _) =
+            test_rpc_server(Network::Main, WalletSecret::devnet_wallet(), 2).await;
+        let ctx = context::current();
+        let summary = rpc_server.clone().wallet_summary(ctx).await;
+        assert_eq!(1, summary.utxo_count);
+        assert_eq!(NeptuneCoins::new(20), summary.total_balance);
+        assert_eq!(
+            summary.total_balance,
+            summary.spendable_balance + summary.timelocked_balance
+        );
+        assert_eq!(0, summary.expected_utxos_pending);
+        assert_eq!(Some(BlockHeight::genesis()), summary.last_scan_height);
+        assert_eq!(0, summary.proof_maintenance_backlog);
+
+        // served from the cache
+        assert_eq!(summary, rpc_server.wallet_summary(ctx).await);
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn send_requires_spend_passphrase_if_set() {
+        let (rpc_server, global_state_lock) =
+            test_rpc_server(Network::RegTest, WalletSecret::devnet_wallet(), 2).await;
+        let ctx = context::current();
+        assert!(!rpc_server.clone().spend_passphrase_required(ctx).await);
+
+        global_state_lock
+            .lock_guard_mut()
+            .await
+            .wallet_state
+            .spend_authorization = Some(SpendAuthorization::new("secret").unwrap());
+        assert!(rpc_server.clone().spend_passphrase_required(ctx).await);
+
+        let address =
+            ReceivingAddress::from(GenerationReceivingAddress::derive_from_seed(rand::random()));
+        for spend_passphrase in [None, Some("wrong".to_owned())] {
+            assert!(rpc_server
+                .clone()
+                .send(
+                    ctx,
+                    NeptuneCoins::one(),
+                    address.clone(),
+                    UtxoNotificationMedium::OffChain,
+                    NeptuneCoins::zero(),
+                    spend_passphrase,
+                    None,
+                )
+                .await
+                .is_none());
+        }
+        assert!(global_state_lock
+            .lock_guard()
+            .await
+            .wallet_state
+            .spend_authorized(Some("secret")));
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn finalize_psnt_refuses_psnt_not_paying_back_change() {
+        let wallet_secret = WalletSecret::devnet_wallet();
+        let change_key = wallet_secret.nth_generation_spending_key_for_tests(0);
+        let (rpc_server, global_state_lock) =
+            test_rpc_server(Network::Main, wallet_secret, 2).await;
+        let ctx = context::current();
+
+        let fee = NeptuneCoins::new(1);
+        let psnt = {
+            let state = global_state_lock.lock_guard().await;
+            let in_seven_months =
+                state.chain.light_state().header().timestamp + Timestamp::months(7);
+            let (details, _) = state
+                .create_transaction_details(
+                    vec![].into(),
+                    change_key.into(),
+                    UtxoNotificationMedium::OffChain,
+                    fee,
+                    in_seven_months,
+                    CoinSelectionPolicy::default(),
+                )
+                .await
+                .unwrap();
+            PartiallyProvedTransaction::from_details(details)
+        };
+        assert!(!psnt.outputs.is_empty());
+        assert_eq!(
+            fee,
+            psnt.net_spend(&global_state_lock.lock_guard().await.wallet_state)
+        );
+
+        let total_input: NeptuneCoins = psnt
+            .inputs
+            .iter()
+            .map(|input| input.utxo.get_native_currency_amount())
+            .sum();
+        let mut dropped_change = psnt.clone();
+        dropped_change.outputs.clear();
+        let mut redirected_change = psnt;
+        for output in &mut redirected_change.outputs {
+            output.receiver_digest = rand::random();
+        }
+        for tampered in [dropped_change, redirected_change] {
+            assert_eq!(
+                Err(PsntError::NetSpendExceeded(total_input, fee)),
+                rpc_server
+                    .clone()
+                    .finalize_psnt(ctx, tampered.to_bytes(), fee, None)
+                    .await
+            );
+        }
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn key_rotation_sweep_requires_spend_passphrase_if_set() {
+        let network = Network::RegTest;
+        let (rpc_server, mut global_state_lock) =
+            test_rpc_server(network, WalletSecret::new_random(), 2).await;
+        let data_dir =
+            std::env::temp_dir().join(format!("key-rotation-sweep-{}", rand::random::<u64>()));
+        let audited_data_dir = DataDirectory::get(Some(data_dir.clone()), network).unwrap();
+        std::fs::create_dir_all(audited_data_dir.root_dir_path()).unwrap();
+        global_state_lock
+            .set_cli(cli_args::Args {
+                network,
+                data_dir: Some(data_dir.clone()),
+                spend_audit_log: true,
+                ..Default::default()
+            })
+            .await;
+        global_state_lock
+            .lock_guard_mut()
+            .await
+            .wallet_state
+            .spend_authorization = Some(SpendAuthorization::new("secret").unwrap());
+        let rpc_server = NeptuneRPCServer {
+            state: global_state_lock.clone(),
+            ..rpc_server
+        };
+        let ctx = context::current();
+
+        for spend_passphrase in [None, Some("wrong".to_owned())] {
+            assert!(rpc_server
+                .clone()
+                .key_rotation_sweep(ctx, NeptuneCoins::one(), spend_passphrase)
+                .await
+                .is_none());
+        }
+
+        let refusals = rpc_server
+            .spend_audit_log(ctx)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.method, entry.outcome))
+            .collect_vec();
+        assert_eq!(
+            vec![("key_rotation_sweep".to_owned(), SpendOutcome::Unauthorized); 2],
+            refusals
+        );
+
+        std::fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn key_rotation_is_refused_for_encrypted_wallet() {
+        let wallet_secret = WalletSecret::new_random();
+        let (rpc_server, global_state_lock) =
+            test_rpc_server(Network::RegTest, wallet_secret.clone(), 2).await;
+        let ctx = context::current();
+        let wallet_dir = global_state_lock
+            .lock_guard()
+            .await
+            .wallet_state
+            .wallet_directory_path()
+            .to_path_buf();
+        std::fs::create_dir_all(&wallet_dir).unwrap();
+
+        // a rotation started before the wallet was encrypted
+        let status: KeyRotationStatus = rpc_server.clone().key_rotation_start(ctx).await.unwrap();
+        assert_eq!(KeyRotationStep::Finish, status.next_step);
+
+        let encrypted = EncryptedWalletSecret::encrypt(&wallet_secret, "passphrase").unwrap();
+        global_state_lock
+            .lock_guard_mut()
+            .await
+            .wallet_state
+            .enable_encryption(encrypted);
+
+        assert!(rpc_server.clone().key_rotation_start(ctx).await.is_none());
+        assert!(rpc_server
+            .clone()
+            .key_rotation_sweep(ctx, NeptuneCoins::zero(), None)
+            .await
+            .is_none());
+        assert!(!rpc_server.clone().key_rotation_finish(ctx).await);
+        assert!(KeyRotation::read(&wallet_dir).unwrap().is_some());
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn utxo_set_stats_of_genesis() {
+        let network = Network::Alpha;
+        let (rpc_server, _) = test_rpc_server(network, WalletSecret::new_random(), 2).await;
+        let stats = rpc_server.utxo_set_stats(context::current()).await;
+
+        let num_premine_outputs = Block::genesis_block(network)
+            .body()
+            .transaction_kernel
+            .outputs
+            .len() as u64;
+        assert_eq!(BlockHeight::genesis(), stats.tip_height);
+        assert_eq!(num_premine_outputs, stats.addition_record_count);
+        assert_eq!(num_premine_outputs, stats.utxo_count);
+        assert_eq!(0, stats.removal_record_count);
+        assert!(stats
+            .addition_record_ages
+            .iter()
+            .all(|age| age.count == Some(num_premine_outputs)));
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn maintenance_mode_test() {
+        let (rpc_server, state_lock) =
+            test_rpc_server(Network::Alpha, WalletSecret::new_random(), 2).await;
+        let ctx = context::current();
+
+        assert!(rpc_server.clone().maintenance_mode(ctx, true).await);
+        assert!(state_lock.lock_guard().await.net.maintenance_mode);
+
+        // read-only requests are still served
+        assert_eq!(
+            BlockHeight::genesis(),
+            rpc_server.clone().block_height(ctx).await
+        );
+
+        assert!(rpc_server.clone().maintenance_mode(ctx, false).await);
+        assert!(!state_lock.lock_guard().await.net.maintenance_mode);
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn send_to_many_test() -> Result<()> {
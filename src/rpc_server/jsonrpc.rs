@@ -0,0 +1,443 @@
+//! JSON-RPC 2.0 over HTTP, for clients not written in Rust.
+//!
+//! Serves the main read-only methods of [RPC] to explorers and exchange
+//! integrations that cannot use a tarpc client. Enabled with
+//! `--jsonrpc-port`, and like the tarpc server it only listens on localhost.
+//!
+//! A request is an HTTP `POST` whose body is a JSON-RPC request object, or a
+//! batch of them. Each connection serves a single request.
+//!
+//! | method                       | params             | result                  |
+//! |------------------------------|--------------------|-------------------------|
+//! | `network`                    |                    | network name            |
+//! | `block_height`               |                    | height of the tip       |
+//! | `tip_digest`                 |                    | digest of the tip       |
+//! | `tip_header`                 |                    | header of the tip       |
+//! | `block_digest`               | `[block_selector]` | digest, or null         |
+//! | `block_info`                 | `[block_selector]` | block summary, or null  |
+//! | `header`                     | `[block_selector]` | block header, or null   |
+//! | `mempool_info`               |                    | `{tx_count, size}`      |
+//! | `synced_balance`             |                    | spendable balance       |
+//! | `synced_balance_unconfirmed` |                    | balance after mempool   |
+//! | `utxo_set_stats`             |                    | UTXO set statistics     |
+//! | `rpc_schemas`                |                    | schemas of all methods  |
+//!
+//! A block selector is one of `genesis`, `tip`, `height/<n>`, `digest/<hex>`,
+//! `timestamp/<t>`, or `depth/<n>`, given either positionally or by name:
+//!
+//! ```text
+//! {"jsonrpc": "2.0", "method": "block_info", "params": ["height/7"], "id": 1}
+//! {"jsonrpc": "2.0", "method": "block_info", "params": {"block_selector": "height/7"}, "id": 1}
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::bail;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use serde_json::Value;
+use tarpc::context;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::sync::Semaphore;
+use tracing::debug;
+use tracing::warn;
+
+use super::NeptuneRPCServer;
+use super::RPC;
+use crate::models::blockchain::block::block_selector::BlockSelector;
+use crate::models::channel::RPCServerToMain;
+use crate::models::state::GlobalStateLock;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// Same limit as for tarpc channels
+const MAX_CONCURRENT_CONNECTIONS: usize = 10;
+const MAX_HEADER_BYTES: usize = 8 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Deserialize)]
+struct JsonRpcRequest {
+    jsonrpc: String,
+    method: String,
+    #[serde(default)]
+    params: Value,
+
+    /// `None` for notifications, which get no response
+    #[serde(default)]
+    id: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl JsonRpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct JsonRpcResponse {
+    jsonrpc: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<JsonRpcError>,
+    id: Value,
+}
+
+impl JsonRpcResponse {
+    fn new(id: Value, result: Result<Value, JsonRpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            jsonrpc: "2.0",
+            result,
+            error,
+            id,
+        }
+    }
+}
+
+/// Accept connections until the listener fails.
+pub(crate) async fn run(
+    listener: TcpListener,
+    state: GlobalStateLock,
+    rpc_server_to_main_tx: mpsc::Sender<RPCServerToMain>,
+) {
+    let connection_slots = Arc::new(Semaphore::new(MAX_CONCURRENT_CONNECTIONS));
+    loop {
+        let Ok(slot) = connection_slots.clone().acquire_owned().await else {
+            return;
+        };
+        let (stream, socket_address) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!("Could not accept JSON-RPC connection: {e}");
+                continue;
+            }
+        };
+        let server = NeptuneRPCServer {
+            socket_address,
+            state: state.clone(),
+            rpc_server_to_main_tx: rpc_server_to_main_tx.clone(),
+        };
+        tokio::spawn(async move {
+            match tokio::time::timeout(CONNECTION_TIMEOUT, handle_connection(stream, server)).await
+            {
+                Ok(Ok(())) => (),
+                Ok(Err(e)) => debug!("JSON-RPC connection from {socket_address} failed: {e}"),
+                Err(_) => debug!("JSON-RPC connection from {socket_address} timed out"),
+            }
+            drop(slot);
+        });
+    }
+}
+
+async fn handle_connection(stream: TcpStream, server: NeptuneRPCServer) -> Result<()> {
+    let mut reader = BufReader::new(stream);
+
+    // Request line and headers
+    let mut head_bytes = 0;
+    let mut request_line = String::new();
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        let remaining = MAX_HEADER_BYTES.saturating_sub(head_bytes) as u64;
+        let num_read = (&mut reader).take(remaining).read_line(&mut line).await?;
+        head_bytes += num_read;
+        if num_read == 0 || !line.ends_with('\n') {
+            bail!("request head incomplete or larger than {MAX_HEADER_BYTES} bytes");
+        }
+        let line = line.trim_end();
+        if request_line.is_empty() {
+            request_line = line.to_owned();
+            continue;
+        }
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    if !request_line.starts_with("POST ") {
+        return write_response(reader.get_mut(), "405 Method Not Allowed", None).await;
+    }
+    let Some(content_length) = content_length else {
+        return write_response(reader.get_mut(), "411 Length Required", None).await;
+    };
+    if content_length > MAX_BODY_BYTES {
+        return write_response(reader.get_mut(), "413 Payload Too Large", None).await;
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+    match handle_body(server, &body).await {
+        Some(response) => write_response(reader.get_mut(), "200 OK", Some(&response)).await,
+        None => write_response(reader.get_mut(), "204 No Content", None).await,
+    }
+}
+
+async fn write_response(stream: &mut TcpStream, status: &str, body: Option<&[u8]>) -> Result<()> {
+    let body = body.unwrap_or_default();
+    let head = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Handle a request or a batch of requests. Returns the serialized response,
+/// or `None` if there is nothing to respond, because the body only holds
+/// notifications.
+async fn handle_body(server: NeptuneRPCServer, body: &[u8]) -> Option<Vec<u8>> {
+    let response = match serde_json::from_slice::<Value>(body) {
+        Err(e) => Some(json!(JsonRpcResponse::new(
+            Value::Null,
+            Err(JsonRpcError::new(PARSE_ERROR, e.to_string())),
+        ))),
+        Ok(Value::Array(requests)) if requests.is_empty() => Some(json!(JsonRpcResponse::new(
+            Value::Null,
+            Err(JsonRpcError::new(INVALID_REQUEST, "empty batch")),
+        ))),
+        Ok(Value::Array(requests)) => {
+            let mut responses = vec![];
+            for request in requests {
+                responses.extend(handle_request(server.clone(), request).await);
+            }
+            (!responses.is_empty()).then(|| json!(responses))
+        }
+        Ok(request) => handle_request(server, request).await.map(|r| json!(r)),
+    };
+
+    response.map(|response| response.to_string().into_bytes())
+}
+
+async fn handle_request(server: NeptuneRPCServer, request: Value) -> Option<JsonRpcResponse> {
+    let request = match serde_json::from_value::<JsonRpcRequest>(request) {
+        Ok(request) if request.jsonrpc == "2.0" => request,
+        _ => {
+            return Some(JsonRpcResponse::new(
+                Value::Null,
+                Err(JsonRpcError::new(
+                    INVALID_REQUEST,
+                    "not a JSON-RPC 2.0 request",
+                )),
+            ))
+        }
+    };
+
+    let result = call(server, &request.method, &request.params).await;
+    let id = request.id?;
+    Some(JsonRpcResponse::new(id, result))
+}
+
+async fn call(
+    server: NeptuneRPCServer,
+    method: &str,
+    params: &Value,
+) -> Result<Value, JsonRpcError> {
+    let ctx = context::current();
+    let result = match method {
+        "network" => json!(server.network(ctx).await.to_string()),
+        "block_height" => json!(server.block_height(ctx).await),
+        "tip_digest" => json!(server.block_digest(ctx, BlockSelector::Tip).await),
+        "tip_header" => json!(server.header(ctx, BlockSelector::Tip).await),
+        "block_digest" => json!(server.block_digest(ctx, block_selector(params)?).await),
+        "block_info" => json!(server.block_info(ctx, block_selector(params)?).await),
+        "header" => json!(server.header(ctx, block_selector(params)?).await),
+        "mempool_info" => json!({
+            "tx_count": server.clone().mempool_tx_count(ctx).await,
+            "size": server.mempool_size(ctx).await,
+        }),
+        "synced_balance" => json!(server.synced_balance(ctx).await),
+        "synced_balance_unconfirmed" => json!(server.synced_balance_unconfirmed(ctx).await),
+        "utxo_set_stats" => json!(server.utxo_set_stats(ctx).await),
+        "rpc_schemas" => json!(server.rpc_schemas(ctx).await),
+        _ => {
+            return Err(JsonRpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method {method}"),
+            ))
+        }
+    };
+
+    Ok(result)
+}
+
+/// The single block selector parameter, positional or named.
+fn block_selector(params: &Value) -> Result<BlockSelector, JsonRpcError> {
+    let selector = match params {
+        Value::Array(params) if params.len() == 1 => params[0].as_str(),
+        Value::Object(params) if params.len() == 1 => {
+            params.get("block_selector").and_then(Value::as_str)
+        }
+        _ => None,
+    };
+    let selector = selector.ok_or_else(|| {
+        JsonRpcError::new(INVALID_PARAMS, "expected a single block_selector parameter")
+    })?;
+
+    selector
+        .parse()
+        .map_err(|e| JsonRpcError::new(INVALID_PARAMS, format!("{e}")))
+}
+
+#[cfg(test)]
+mod jsonrpc_tests {
+    use tracing_test::traced_test;
+
+    use super::*;
+    use crate::config_models::network::Network;
+    use crate::models::state::wallet::WalletSecret;
+    use crate::rpc_server::rpc_server_tests::test_rpc_server;
+
+    async fn call_body(server: NeptuneRPCServer, body: &str) -> Value {
+        let response = handle_body(server, body.as_bytes()).await.unwrap();
+        serde_json::from_slice(&response).unwrap()
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn methods_agree_with_tarpc_server() {
+        let (rpc_server, _) =
+            test_rpc_server(Network::RegTest, WalletSecret::new_random(), 2).await;
+        let ctx = context::current();
+
+        let response = call_body(
+            rpc_server.clone(),
+            r#"{"jsonrpc": "2.0", "method": "tip_header", "id": 7}"#,
+        )
+        .await;
+        assert_eq!(json!(7), response["id"]);
+        assert_eq!(
+            json!(rpc_server.clone().header(ctx, BlockSelector::Tip).await),
+            response["result"]
+        );
+
+        for params in [r#"["genesis"]"#, r#"{"block_selector": "height/0"}"#] {
+            let body = format!(
+                r#"{{"jsonrpc": "2.0", "method": "block_info", "params": {params}, "id": "a"}}"#
+            );
+            let response = call_body(rpc_server.clone(), &body).await;
+            assert_eq!(
+                json!(
+                    rpc_server
+                        .clone()
+                        .block_info(ctx, BlockSelector::Genesis)
+                        .await
+                ),
+                response["result"]
+            );
+        }
+
+        let response = call_body(
+            rpc_server.clone(),
+            r#"{"jsonrpc": "2.0", "method": "mempool_info", "id": 1}"#,
+        )
+        .await;
+        assert_eq!(
+            json!({
+                "tx_count": rpc_server.clone().mempool_tx_count(ctx).await,
+                "size": rpc_server.mempool_size(ctx).await,
+            }),
+            response["result"]
+        );
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn errors_batches_and_notifications() {
+        let (rpc_server, _) =
+            test_rpc_server(Network::RegTest, WalletSecret::new_random(), 2).await;
+
+        let response = call_body(rpc_server.clone(), "{").await;
+        assert_eq!(json!(PARSE_ERROR), response["error"]["code"]);
+
+        let response = call_body(
+            rpc_server.clone(),
+            r#"{"jsonrpc": "2.0", "method": "send", "id": 1}"#,
+        )
+        .await;
+        assert_eq!(json!(METHOD_NOT_FOUND), response["error"]["code"]);
+
+        let response = call_body(
+            rpc_server.clone(),
+            r#"{"jsonrpc": "2.0", "method": "header", "params": ["height/x"], "id": 1}"#,
+        )
+        .await;
+        assert_eq!(json!(INVALID_PARAMS), response["error"]["code"]);
+
+        // The notification in the batch gets no response
+        let response = call_body(
+            rpc_server.clone(),
+            r#"[{"jsonrpc": "2.0", "method": "network", "id": 1},
+                {"jsonrpc": "2.0", "method": "network"}]"#,
+        )
+        .await;
+        assert_eq!(
+            json!([{"jsonrpc": "2.0", "result": "regtest", "id": 1}]),
+            response
+        );
+
+        assert!(
+            handle_body(rpc_server, br#"{"jsonrpc": "2.0", "method": "network"}"#)
+                .await
+                .is_none()
+        );
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn serves_http_post() {
+        let (rpc_server, state) =
+            test_rpc_server(Network::RegTest, WalletSecret::new_random(), 2).await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let expected = json!(rpc_server.clone().block_height(context::current()).await);
+        tokio::spawn(run(listener, state, rpc_server.rpc_server_to_main_tx));
+
+        let body = r#"{"jsonrpc": "2.0", "method": "block_height", "id": 1}"#;
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        let request = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(expected, body["result"]);
+    }
+}
@@ -0,0 +1,363 @@
+//! JSON schemas of the parameters and results of all [RPC](super::RPC)
+//! methods, for clients not written in Rust to generate typed bindings and to
+//! validate payloads.
+//!
+//! The schemas are derived from the Rust types at compile time, so the
+//! schemas served by a node always match the version it runs. Methods are
+//! called over tarpc's JSON transport with the parameters as an object keyed
+//! by parameter name.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+
+use schemars::gen::SchemaGenerator;
+use schemars::gen::SchemaSettings;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::BlockUpdate;
+use super::DashBoardOverviewDataFromClient;
+use crate::config_models::network::Network;
+use crate::mining::block_generation::GenerateBlocksError;
+use crate::models::blockchain::block::block_header::BlockHeader;
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::models::blockchain::block::block_info::BlockInfo;
+use crate::models::blockchain::block::block_selector::BlockSelector;
+use crate::models::blockchain::block::chain_params::ChainParams;
+use crate::models::blockchain::block::difficulty_control::SimulatedBlock;
+use crate::models::blockchain::block::validation_trace::ValidationReport;
+use crate::models::blockchain::transaction::memo::Memo;
+use crate::models::blockchain::transaction::partially_proved::PsntError;
+use crate::models::blockchain::transaction::partially_proved::PsntFinalization;
+use crate::models::blockchain::transaction::transaction_kernel::TransactionKernelField;
+use crate::models::blockchain::transaction::transaction_kernel::TransactionKernelFieldDisclosure;
+use crate::models::blockchain::transaction::transaction_output::UtxoNotificationMedium;
+use crate::models::blockchain::type_scripts::neptune_coins::NeptuneCoins;
+use crate::models::peer::InstanceId;
+use crate::models::peer::PeerInfo;
+use crate::models::peer::PeerStanding;
+use crate::models::peer::PeerViolation;
+use crate::models::peer::ReachabilityReport;
+use crate::models::proof_abstractions::timestamp::Timestamp;
+use crate::models::state::block_proposal::BlockSolutionError;
+use crate::models::state::block_proposal::BlockTemplate;
+use crate::models::state::block_proposal::BlockTemplateUpdate;
+use crate::models::state::checkpoint_beacon::BeaconAssessment;
+use crate::models::state::checkpoint_beacon::SignedCheckpoint;
+use crate::models::state::coinjoin::CoinJoinSessionInfo;
+use crate::models::state::event_journal::JournalEntry;
+use crate::models::state::fee_histogram::FeeHistogramBucket;
+use crate::models::state::fork_watch::ForkWatchStatus;
+use crate::models::state::mempool_admission::BroadcastTransactionError;
+use crate::models::state::mining_log::MinedBlockReport;
+use crate::models::state::spend_audit_log::SpendAuditEntry;
+use crate::models::state::sync_progress::SyncProgressReport;
+use crate::models::state::transaction_kernel_id::TransactionKernelId;
+use crate::models::state::tx_index::IndexedUtxo;
+use crate::models::state::utxo_set_stats::UtxoSetStats;
+use crate::models::state::wallet::address::KeyType;
+use crate::models::state::wallet::address::ReceivingAddress;
+use crate::models::state::wallet::audit_export::WalletAuditExport;
+use crate::models::state::wallet::coin_selection::CoinSelectionPolicy;
+use crate::models::state::wallet::coin_with_possible_timelock::CoinWithPossibleTimeLock;
+use crate::models::state::wallet::expected_utxo_bundle::ExpectedUtxoBundle;
+use crate::models::state::wallet::expected_utxo_bundle::ExpectedUtxoBundleError;
+use crate::models::state::wallet::expected_utxo_bundle::ExpectedUtxoImport;
+use crate::models::state::wallet::hd_derivation::AccountDescriptor;
+use crate::models::state::wallet::key_rotation::KeyRotationStatus;
+use crate::models::state::wallet::unconfirmed_change::UnconfirmedSpend;
+use crate::models::state::wallet::wallet_memo::WalletMemo;
+use crate::models::state::wallet::wallet_status::WalletStatus;
+use crate::models::state::wallet::wallet_summary::WalletSummary;
+use crate::util_types::json_schema::BFieldElementSchema;
+use crate::util_types::json_schema::DigestSchema;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ParamSchema {
+    pub name: String,
+    pub schema: Schema,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MethodSchema {
+    /// In the order of the method's parameters
+    pub params: Vec<ParamSchema>,
+    pub result: Schema,
+}
+
+/// Schemas of all RPC methods. Schemas of named types are references into
+/// `definitions`, as in a JSON Schema document.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RpcSchemas {
+    /// Version of the node serving these schemas
+    pub node_version: String,
+
+    /// Version of JSON Schema the schemas are written in
+    pub meta_schema: Option<String>,
+    pub methods: BTreeMap<String, MethodSchema>,
+    pub definitions: BTreeMap<String, Schema>,
+}
+
+/// Adds a method's schema to the map of methods. Parameter and result types
+/// from dependencies are given as the types in
+/// [json_schema](crate::util_types::json_schema) describing them.
+macro_rules! method_schemas {
+    ($gen:ident; $($method:ident($($param:ident: $param_type:ty),*) -> $result:ty;)*) => {{
+        let mut methods = BTreeMap::new();
+        $(
+            let method = MethodSchema {
+                params: vec![$(ParamSchema {
+                    name: stringify!($param).to_owned(),
+                    schema: $gen.subschema_for::<$param_type>(),
+                }),*],
+                result: $gen.subschema_for::<$result>(),
+            };
+            methods.insert(stringify!($method).to_owned(), method);
+        )*
+        methods
+    }};
+}
+
+impl RpcSchemas {
+    /// Generate the schemas of all RPC methods. Every method of
+    /// [RPC](super::RPC) must be listed here.
+    pub fn generate() -> Self {
+        let settings = SchemaSettings::draft07();
+        let meta_schema = settings.meta_schema.clone();
+        let mut gen: SchemaGenerator = settings.into_generator();
+
+        let methods = method_schemas! { gen;
+            network() -> Network;
+            chain_params() -> ChainParams;
+            simulate_difficulty(
+                block_intervals: Vec<Timestamp>,
+                target_block_interval: Option<Timestamp>
+            ) -> Vec<SimulatedBlock>;
+            own_listen_address_for_peers() -> Option<SocketAddr>;
+            own_instance_id() -> InstanceId;
+            block_height() -> BlockHeight;
+            sync_progress() -> Option<SyncProgressReport>;
+            confirmations() -> Option<BlockHeight>;
+            peer_info() -> Vec<PeerInfo>;
+            all_sanctioned_peers() -> HashMap<IpAddr, PeerStanding>;
+            get_peer_violations(ip: IpAddr) -> Vec<PeerViolation>;
+            latest_tip_digests(n: usize) -> Vec<DigestSchema>;
+            block_info(block_selector: BlockSelector) -> Option<BlockInfo>;
+            block_digest(block_selector: BlockSelector) -> Option<DigestSchema>;
+            is_canonical(block_digest: DigestSchema) -> bool;
+            utxo_digest(leaf_index: u64) -> Option<DigestSchema>;
+            fork_watch() -> ForkWatchStatus;
+            get_blocks_for_announcement(receiver_identifier: BFieldElementSchema)
+                -> Option<Vec<DigestSchema>>;
+            get_utxos_for_lock_script_hash(lock_script_hash: DigestSchema)
+                -> Option<Vec<IndexedUtxo>>;
+            header(block_selector: BlockSelector) -> Option<BlockHeader>;
+            synced_balance() -> NeptuneCoins;
+            synced_balance_unconfirmed() -> NeptuneCoins;
+            history() -> Vec<(DigestSchema, BlockHeight, Timestamp, NeptuneCoins)>;
+            wallet_status() -> WalletStatus;
+            next_receiving_address(key_type: KeyType) -> ReceivingAddress;
+            mempool_tx_count() -> usize;
+            mempool_size() -> usize;
+            estimate_fee(target_blocks: usize) -> NeptuneCoins;
+            mempool_fee_histogram(bucket_count: usize) -> Vec<FeeHistogramBucket>;
+            disclose_transaction_kernel_field(txid: TransactionKernelId, field: TransactionKernelField)
+                -> Option<TransactionKernelFieldDisclosure>;
+            utxo_set_stats() -> UtxoSetStats;
+            dashboard_overview_data() -> DashBoardOverviewDataFromClient;
+            validate_address(address: String, network: Network) -> Option<ReceivingAddress>;
+            validate_amount(amount: String) -> Option<NeptuneCoins>;
+            amount_leq_synced_balance(amount: NeptuneCoins) -> bool;
+            list_own_coins() -> Vec<CoinWithPossibleTimeLock>;
+            cpu_temp() -> Option<f32>;
+            key_rotation_status() -> Option<KeyRotationStatus>;
+            beacon_status() -> BeaconAssessment;
+            wallet_audit_export() -> WalletAuditExport;
+            memos() -> Vec<WalletMemo>;
+            unconfirmed_spends() -> Vec<UnconfirmedSpend>;
+            spend_passphrase_required() -> bool;
+            wallet_summary() -> WalletSummary;
+            rpc_schemas() -> RpcSchemasSchema;
+            subscribe_blocks(known_tip: Option<DigestSchema>, max_wait_secs: u64)
+                -> Option<BlockUpdate>;
+            clear_all_standings() -> ();
+            clear_standing_by_ip(ip: IpAddr) -> ();
+            send(
+                amount: NeptuneCoins,
+                address: ReceivingAddress,
+                owned_utxo_notify_method: UtxoNotificationMedium,
+                fee: NeptuneCoins,
+                spend_passphrase: Option<String>,
+                coin_selection: Option<CoinSelectionPolicy>
+            ) -> Option<TransactionKernelId>;
+            send_to_many(
+                outputs: Vec<(ReceivingAddress, NeptuneCoins)>,
+                owned_utxo_notify_medium: UtxoNotificationMedium,
+                fee: NeptuneCoins,
+                spend_passphrase: Option<String>
+            ) -> Option<TransactionKernelId>;
+            send_to_many_with_memos(
+                outputs: Vec<(ReceivingAddress, NeptuneCoins, Option<Memo>)>,
+                owned_utxo_notify_medium: UtxoNotificationMedium,
+                fee: NeptuneCoins,
+                spend_passphrase: Option<String>
+            ) -> Option<TransactionKernelId>;
+            pause_miner() -> ();
+            restart_miner() -> ();
+            get_block_template() -> Option<BlockTemplate>;
+            get_block_template_update(known_proposal_id: Option<u64>) -> Option<BlockTemplateUpdate>;
+            submit_block_solution(
+                proposal_id: u64,
+                nonce: [BFieldElementSchema; 3],
+                timestamp: Option<Timestamp>
+            ) -> Result<DigestSchema, BlockSolutionError>;
+            generate_blocks(num_blocks: usize, address: ReceivingAddress, real_proofs: bool)
+                -> Result<Vec<DigestSchema>, GenerateBlocksError>;
+            broadcast_transaction(transaction_bytes: Vec<u8>)
+                -> Result<TransactionKernelId, BroadcastTransactionError>;
+            create_psnt(
+                outputs: Vec<(ReceivingAddress, NeptuneCoins)>,
+                owned_utxo_notify_medium: UtxoNotificationMedium,
+                fee: NeptuneCoins
+            ) -> Option<Vec<u8>>;
+            combine_psnt(psnts: Vec<Vec<u8>>) -> Result<Vec<u8>, PsntError>;
+            finalize_psnt(
+                psnt: Vec<u8>,
+                max_net_spend: NeptuneCoins,
+                spend_passphrase: Option<String>
+            ) -> Result<PsntFinalization, PsntError>;
+            mined_blocks(max_num: usize) -> Vec<MinedBlockReport>;
+            event_journal(from: Timestamp, to: Timestamp, max_num: usize) -> Vec<JournalEntry>;
+            spend_audit_log() -> Option<Vec<SpendAuditEntry>>;
+            validation_reports(max_num: usize) -> Vec<ValidationReport>;
+            prune_abandoned_monitored_utxos() -> usize;
+            key_rotation_start() -> Option<KeyRotationStatus>;
+            key_rotation_sweep(fee: NeptuneCoins, spend_passphrase: Option<String>)
+                -> Option<KeyRotationStatus>;
+            key_rotation_finish() -> bool;
+            import_expected_utxos(bundle: ExpectedUtxoBundle, trusted_signer: Option<String>)
+                -> Result<ExpectedUtxoImport, ExpectedUtxoBundleError>;
+            export_account_descriptor(account: u32) -> Option<AccountDescriptor>;
+            wallet_unlock(passphrase: String, timeout_secs: Option<u64>) -> bool;
+            wallet_lock() -> bool;
+            beacon_submit_checkpoint(checkpoint: SignedCheckpoint) -> bool;
+            check_reachability() -> Vec<ReachabilityReport>;
+            coinjoin_sessions() -> Vec<(SocketAddr, CoinJoinSessionInfo)>;
+            coinjoin_join(
+                coordinator: SocketAddr,
+                fee: NeptuneCoins,
+                spend_passphrase: Option<String>
+            ) -> Option<TransactionKernelId>;
+            maintenance_mode(on: bool) -> bool;
+            shutdown() -> bool;
+        };
+
+        Self {
+            node_version: env!("CARGO_PKG_VERSION").to_owned(),
+            meta_schema,
+            methods,
+            definitions: gen.take_definitions().into_iter().collect(),
+        }
+    }
+}
+
+/// The result of the `rpc_schemas` method itself, which is not worth
+/// describing in detail.
+struct RpcSchemasSchema;
+
+impl JsonSchema for RpcSchemasSchema {
+    fn schema_name() -> String {
+        "RpcSchemas".to_owned()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        serde_json::Value::json_schema(gen)
+    }
+}
+
+#[cfg(test)]
+mod rpc_schema_tests {
+    use itertools::Itertools;
+    use serde_json::Value;
+
+    use super::*;
+
+    /// The names of the methods of the RPC trait, read from its source.
+    fn rpc_trait_methods() -> Vec<String> {
+        let source = include_str!("../rpc_server.rs");
+        let start = source.find("pub trait RPC {").unwrap();
+        let end = start + source[start..].find("\n}\n").unwrap();
+        source[start..end]
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("async fn "))
+            .map(|rest| rest.split('(').next().unwrap().to_owned())
+            .sorted()
+            .collect()
+    }
+
+    fn collect_refs(value: &Value, refs: &mut Vec<String>) {
+        match value {
+            Value::Object(object) => {
+                if let Some(Value::String(reference)) = object.get("$ref") {
+                    refs.push(reference.clone());
+                }
+                object.values().for_each(|v| collect_refs(v, refs));
+            }
+            Value::Array(array) => array.iter().for_each(|v| collect_refs(v, refs)),
+            _ => (),
+        }
+    }
+
+    #[test]
+    fn every_rpc_method_has_a_schema() {
+        let schemas = RpcSchemas::generate();
+        assert_eq!(
+            rpc_trait_methods(),
+            schemas.methods.keys().cloned().collect_vec()
+        );
+
+        let send = &schemas.methods["send"];
+        assert_eq!(
+            vec![
+                "amount",
+                "address",
+                "owned_utxo_notify_method",
+                "fee",
+                "spend_passphrase",
+                "coin_selection"
+            ],
+            send.params.iter().map(|p| p.name.as_str()).collect_vec()
+        );
+    }
+
+    #[test]
+    fn all_references_resolve() {
+        let schemas = RpcSchemas::generate();
+        let document = serde_json::to_value(&schemas).unwrap();
+        let mut refs = vec![];
+        collect_refs(&document, &mut refs);
+        assert!(!refs.is_empty());
+
+        for reference in refs {
+            let name = reference.strip_prefix("#/definitions/").unwrap();
+            assert!(schemas.definitions.contains_key(name), "{reference}");
+        }
+
+        // the serialized forms of dependencies' types are described
+        for name in ["Digest", "BFieldElement", "MmrMembershipProof", "Memo"] {
+            assert!(schemas.definitions.contains_key(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn schemas_survive_serialization() {
+        let schemas = RpcSchemas::generate();
+        let json = serde_json::to_string(&schemas).unwrap();
+        let deserialized: RpcSchemas = serde_json::from_str(&json).unwrap();
+        assert_eq!(schemas.methods.len(), deserialized.methods.len());
+    }
+}
@@ -0,0 +1,313 @@
+//! Safe mode, which the node boots into instead of crashing if its databases
+//! are inconsistent, or if started with `--safe-mode`.
+//!
+//! In safe mode the node connects to no peers and does not mine. It only
+//! serves [SafeModeRPC] on the RPC port, for inspecting the databases,
+//! exporting the wallet files, and quarantining broken databases. A
+//! quarantined database is recreated empty on the next regular start: the
+//! chain is downloaded again from peers, and the wallet database is restored
+//! from the wallet files.
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Context;
+use anyhow::Result;
+use futures::future;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde::Serialize;
+use tarpc::context;
+use tarpc::server;
+use tarpc::server::Channel;
+use tarpc::tokio_serde::formats::Json;
+use tokio::sync::mpsc;
+use tokio::sync::Mutex;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::config_models::cli_args::Args;
+use crate::config_models::data_directory::DataDirectory;
+use crate::config_models::network::Network;
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::models::state::archival_state::ArchivalState;
+use crate::models::state::db_diagnostics;
+use crate::models::state::db_diagnostics::DatabaseKind;
+use crate::models::state::db_diagnostics::DbIssue;
+use crate::prelude::twenty_first::math::digest::Digest;
+use crate::sd_notify;
+
+/// Maximum number of heights returned by one [SafeModeRPC::dump_block_index]
+/// request
+pub const MAX_BLOCK_INDEX_DUMP_HEIGHTS: u64 = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockIndexDump {
+    pub tip_digest: Digest,
+
+    /// The digests of the blocks recorded at each height
+    pub heights: Vec<(BlockHeight, Vec<Digest>)>,
+}
+
+#[tarpc::service]
+pub trait SafeModeRPC {
+    /// The network the node runs on
+    async fn network() -> Network;
+
+    /// The issues that made the node start in safe mode
+    async fn startup_issues() -> Vec<DbIssue>;
+
+    /// The block index entries for at most
+    /// [MAX_BLOCK_INDEX_DUMP_HEIGHTS] heights, starting at `from`
+    async fn dump_block_index(from: BlockHeight, count: u64) -> Result<BlockIndexDump, String>;
+
+    /// Check the canonical blocks with heights in `from..=to`. See
+    /// [ArchivalState::verify_canonical_range].
+    async fn verify_block_range(from: BlockHeight, to: BlockHeight)
+        -> Result<Vec<DbIssue>, String>;
+
+    /// Copy the wallet files, which hold the wallet secret and the data needed
+    /// to restore the wallet database, into `target_dir` on the node's machine.
+    /// Existing files are not overwritten. Returns the copied files.
+    async fn export_wallet_files(target_dir: PathBuf) -> Result<Vec<PathBuf>, String>;
+
+    /// Move the given databases into the quarantine directory, such that they
+    /// are recreated on the next start. Returns the directory they were moved
+    /// to.
+    async fn quarantine(database: DatabaseKind) -> Result<PathBuf, String>;
+
+    /// Stop the node
+    async fn shutdown();
+}
+
+struct SafeModeState {
+    data_dir: DataDirectory,
+    network: Network,
+    issues: Vec<DbIssue>,
+
+    /// `None` if the chain databases could not be opened, or have been
+    /// quarantined
+    archival_state: Mutex<Option<ArchivalState>>,
+    shutdown_tx: mpsc::Sender<()>,
+}
+
+#[derive(Clone)]
+pub struct SafeModeRPCServer {
+    state: Arc<SafeModeState>,
+}
+
+impl SafeModeRPCServer {
+    const CHAIN_UNAVAILABLE: &'static str =
+        "chain databases cannot be opened or have been quarantined";
+}
+
+impl SafeModeRPC for SafeModeRPCServer {
+    async fn network(self, _: context::Context) -> Network {
+        self.state.network
+    }
+
+    async fn startup_issues(self, _: context::Context) -> Vec<DbIssue> {
+        self.state.issues.clone()
+    }
+
+    async fn dump_block_index(
+        self,
+        _: context::Context,
+        from: BlockHeight,
+        count: u64,
+    ) -> Result<BlockIndexDump, String> {
+        let archival_state = self.state.archival_state.lock().await;
+        let archival_state = archival_state
+            .as_ref()
+            .ok_or_else(|| Self::CHAIN_UNAVAILABLE.to_owned())?;
+
+        let mut heights = vec![];
+        for height in u64::from(from)..u64::from(from) + count.min(MAX_BLOCK_INDEX_DUMP_HEIGHTS) {
+            let height = BlockHeight::from(height);
+            let digests = archival_state.block_height_to_block_digests(height).await;
+            heights.push((height, digests));
+        }
+
+        Ok(BlockIndexDump {
+            tip_digest: archival_state.get_tip_digest().await,
+            heights,
+        })
+    }
+
+    async fn verify_block_range(
+        self,
+        _: context::Context,
+        from: BlockHeight,
+        to: BlockHeight,
+    ) -> Result<Vec<DbIssue>, String> {
+        let archival_state = self.state.archival_state.lock().await;
+        let archival_state = archival_state
+            .as_ref()
+            .ok_or_else(|| Self::CHAIN_UNAVAILABLE.to_owned())?;
+
+        Ok(archival_state.verify_canonical_range(from, to).await)
+    }
+
+    async fn export_wallet_files(
+        self,
+        _: context::Context,
+        target_dir: PathBuf,
+    ) -> Result<Vec<PathBuf>, String> {
+        export_wallet_files(&self.state.data_dir, &target_dir).map_err(|err| format!("{err:#}"))
+    }
+
+    async fn quarantine(
+        self,
+        _: context::Context,
+        database: DatabaseKind,
+    ) -> Result<PathBuf, String> {
+        let mut archival_state = self.state.archival_state.lock().await;
+        if database == DatabaseKind::Chain {
+            // close the databases before moving them
+            drop(archival_state.take());
+        }
+
+        let target_dir = db_diagnostics::quarantine(&self.state.data_dir, database)
+            .map_err(|err| format!("{err:#}"))?;
+        warn!(
+            "Quarantined {database} databases to {}",
+            target_dir.display()
+        );
+
+        Ok(target_dir)
+    }
+
+    async fn shutdown(self, _: context::Context) {
+        let _ = self.state.shutdown_tx.send(()).await;
+    }
+}
+
+fn export_wallet_files(data_dir: &DataDirectory, target_dir: &Path) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(target_dir)
+        .with_context(|| format!("could not create {}", target_dir.display()))?;
+
+    let wallet_dir = data_dir.wallet_directory_path();
+    let mut exported = vec![];
+    for entry in std::fs::read_dir(&wallet_dir)
+        .with_context(|| format!("could not read {}", wallet_dir.display()))?
+    {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+
+        let target = target_dir.join(path.file_name().unwrap_or(path.as_os_str()));
+        if target.exists() {
+            anyhow::bail!("{} already exists", target.display());
+        }
+        std::fs::copy(&path, &target)
+            .with_context(|| format!("could not copy {}", path.display()))?;
+        exported.push(target);
+    }
+
+    Ok(exported)
+}
+
+/// Open the chain databases, if possible.
+async fn open_archival_state(data_dir: &DataDirectory, network: Network) -> Result<ArchivalState> {
+    let block_index_db = ArchivalState::initialize_block_index_database(data_dir).await?;
+    let archival_mutator_set = ArchivalState::initialize_mutator_set(data_dir).await?;
+
+    Ok(ArchivalState::new(
+        data_dir.clone(),
+        block_index_db,
+        archival_mutator_set,
+        network,
+    )
+    .await)
+}
+
+/// Serve [SafeModeRPC] until shut down by RPC or Ctrl+C.
+pub(crate) async fn run(cli_args: Args, issues: Vec<DbIssue>) -> Result<()> {
+    warn!("Starting in safe mode. Not connecting to peers, not mining.");
+    for issue in &issues {
+        error!("Database issue: {issue}");
+    }
+
+    let data_dir = DataDirectory::get(cli_args.data_dir.clone(), cli_args.network)?;
+    let archival_state = match open_archival_state(&data_dir, cli_args.network).await {
+        Ok(archival_state) => Some(archival_state),
+        Err(err) => {
+            warn!("Could not open chain databases: {err:#}");
+            None
+        }
+    };
+
+    let (shutdown_tx, mut shutdown_rx) = mpsc::channel(1);
+    let state = Arc::new(SafeModeState {
+        data_dir,
+        network: cli_args.network,
+        issues,
+        archival_state: Mutex::new(archival_state),
+        shutdown_tx,
+    });
+
+    let mut rpc_listener = tarpc::serde_transport::tcp::listen(
+        format!("127.0.0.1:{}", cli_args.rpc_port),
+        Json::default,
+    )
+    .await?;
+    rpc_listener.config_mut().max_frame_length(usize::MAX);
+
+    let rpc_join_handle = tokio::spawn(async move {
+        rpc_listener
+            .filter_map(|r| future::ready(r.ok()))
+            .map(server::BaseChannel::with_defaults)
+            .map(move |channel| {
+                let server = SafeModeRPCServer {
+                    state: state.clone(),
+                };
+                channel.execute(server.serve()).for_each(|response| async {
+                    tokio::spawn(response);
+                })
+            })
+            .buffer_unordered(10)
+            .for_each(|_| async {})
+            .await;
+    });
+    info!(
+        "Serving safe mode RPC on port {}. Use `neptune-cli safe-mode-*` commands.",
+        cli_args.rpc_port
+    );
+    sd_notify::ready("Safe mode");
+
+    tokio::select! {
+        _ = shutdown_rx.recv() => info!("Shutdown requested over RPC"),
+        _ = tokio::signal::ctrl_c() => info!("Detected Ctrl+c signal."),
+    }
+    sd_notify::stopping();
+    rpc_join_handle.abort();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod safe_mode_tests {
+    use super::*;
+    use crate::models::state::wallet::WalletSecret;
+    use crate::tests::shared::unit_test_data_directory;
+
+    #[tokio::test]
+    async fn wallet_files_are_exported_without_overwriting() {
+        let network = Network::RegTest;
+        let data_dir = unit_test_data_directory(network).unwrap();
+        let wallet_dir = data_dir.wallet_directory_path();
+        std::fs::create_dir_all(&wallet_dir).unwrap();
+        WalletSecret::read_from_file_or_create(&wallet_dir).unwrap();
+
+        let target_dir = data_dir.root_dir_path().join("export");
+        let exported = export_wallet_files(&data_dir, &target_dir).unwrap();
+        assert!(exported
+            .iter()
+            .any(|path| path.ends_with(crate::models::state::wallet::WALLET_SECRET_FILE_NAME)));
+
+        assert!(export_wallet_files(&data_dir, &target_dir).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+//! Notifications to the service manager, for nodes run as systemd units with
+//! `Type=notify`.
+//!
+//! systemd passes the path of a datagram socket in `NOTIFY_SOCKET` to services
+//! it expects notifications from. The node reports readiness once its RPC
+//! server accepts connections, a human-readable status line as it runs, and
+//! that it is stopping once shutdown begins. If the unit sets `WatchdogSec=`,
+//! systemd also passes `WATCHDOG_USEC`, and the main loop pings the watchdog
+//! at half that interval, such that a hung main loop gets the node restarted.
+//!
+//! Without `NOTIFY_SOCKET`, for instance when not run by systemd, all
+//! notifications are no-ops.
+
+use std::time::Duration;
+
+use tracing::debug;
+
+const NOTIFY_SOCKET_ENV: &str = "NOTIFY_SOCKET";
+const WATCHDOG_USEC_ENV: &str = "WATCHDOG_USEC";
+const WATCHDOG_PID_ENV: &str = "WATCHDOG_PID";
+
+/// The node is up: its RPC server accepts connections.
+pub fn ready(status: &str) {
+    notify(&format!("READY=1\nSTATUS={status}"));
+}
+
+/// Update the status line shown by `systemctl status`.
+pub fn status(status: &str) {
+    notify(&format!("STATUS={status}"));
+}
+
+/// Shutdown has begun.
+pub fn stopping() {
+    notify("STOPPING=1\nSTATUS=Shutting down");
+}
+
+/// Tell the watchdog that the main loop is alive.
+pub fn watchdog_ping() {
+    notify("WATCHDOG=1");
+}
+
+/// How often to ping the watchdog, if the service manager expects pings from
+/// this process: half the watchdog timeout, as systemd recommends.
+pub fn watchdog_interval() -> Option<Duration> {
+    std::env::var(NOTIFY_SOCKET_ENV).ok()?;
+    if let Ok(pid) = std::env::var(WATCHDOG_PID_ENV) {
+        if pid.parse::<u32>().ok() != Some(std::process::id()) {
+            return None;
+        }
+    }
+
+    let timeout_usec = std::env::var(WATCHDOG_USEC_ENV).ok()?.parse::<u64>().ok()?;
+    Some(Duration::from_micros(timeout_usec) / 2).filter(|interval| !interval.is_zero())
+}
+
+fn notify(state: &str) {
+    let Ok(socket_path) = std::env::var(NOTIFY_SOCKET_ENV) else {
+        return;
+    };
+
+    // Notifications are best effort: the node runs just the same if the
+    // service manager does not get them.
+    if let Err(err) = send(&socket_path, state) {
+        debug!("Could not notify service manager at {socket_path}: {err}");
+    }
+}
+
+#[cfg(unix)]
+fn send(socket_path: &str, state: &str) -> std::io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let socket = UnixDatagram::unbound()?;
+    match socket_path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(abstract_name) => {
+            use std::os::linux::net::SocketAddrExt;
+            use std::os::unix::net::SocketAddr;
+
+            let address = SocketAddr::from_abstract_name(abstract_name)?;
+            socket.send_to_addr(state.as_bytes(), &address)?;
+        }
+        _ => {
+            socket.send_to(state.as_bytes(), socket_path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send(_socket_path: &str, _state: &str) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod sd_notify_tests {
+    use std::os::unix::net::UnixDatagram;
+
+    use super::*;
+
+    #[test]
+    fn notifications_reach_the_socket() {
+        let socket_path = std::env::temp_dir().join(format!("notify-{}", rand::random::<u64>()));
+        let receiver = UnixDatagram::bind(&socket_path).unwrap();
+
+        send(socket_path.to_str().unwrap(), "READY=1\nSTATUS=Running").unwrap();
+        let mut buffer = [0u8; 64];
+        let len = receiver.recv(&mut buffer).unwrap();
+        assert_eq!(b"READY=1\nSTATUS=Running", &buffer[..len]);
+
+        std::fs::remove_file(&socket_path).unwrap();
+    }
+}
@@ -0,0 +1,194 @@
+//! Harness verifying the node against the conformance fixtures in
+//! `test_data/conformance`.
+//!
+//! The fixtures are machine-readable descriptions of the wire formats of the
+//! peer protocol and the RPC interface, meant for alternative client
+//! implementations to test against. `peer_messages.json` holds byte-level
+//! frames of peer messages, and `rpc.json` holds the JSON encoding of RPC
+//! requests along with the responses of a fresh regtest node. The tests here
+//! keep the fixtures and the node in agreement: a change to the encoding of a
+//! message or to the response of an RPC breaks them, and should only be made
+//! together with an update of the fixtures.
+
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use bytes::BytesMut;
+use itertools::Itertools;
+use serde::Deserialize;
+use tarpc::context;
+use tarpc::server::Serve;
+use tokio_util::codec::Decoder;
+use tracing_test::traced_test;
+
+use crate::config_models::network::Network;
+use crate::models::peer::message_codec::MessageSizeLimits;
+use crate::models::peer::message_codec::PeerMessageCodec;
+use crate::models::peer::BlockRequestBatch;
+use crate::models::peer::ConnectionRefusedReason;
+use crate::models::peer::ConnectionStatus;
+use crate::models::peer::PeerMessage;
+use crate::models::state::wallet::WalletSecret;
+use crate::rpc_server::NeptuneRPCServer;
+use crate::rpc_server::RPCRequest;
+use crate::rpc_server::RPC;
+use crate::tests::shared::mock_genesis_global_state;
+use crate::tests::shared::to_bytes;
+use crate::RPC_CHANNEL_CAPACITY;
+
+const TEST_DATA_DIR: &str = "test_data";
+const CONFORMANCE_DIR: &str = "conformance";
+const PEER_MESSAGES_FILE_NAME: &str = "peer_messages.json";
+const RPC_FILE_NAME: &str = "rpc.json";
+
+#[derive(Debug, Deserialize)]
+struct PeerMessageFixtures {
+    messages: Vec<PeerMessageFixture>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PeerMessageFixture {
+    name: String,
+
+    /// hex encoding of the length-delimited frame
+    frame: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcFixtures {
+    calls: Vec<RpcFixture>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcFixture {
+    method: String,
+    request: serde_json::Value,
+    response: serde_json::Value,
+}
+
+fn read_fixtures<T: for<'de> Deserialize<'de>>(file_name: &str) -> T {
+    let path = PathBuf::from(TEST_DATA_DIR)
+        .join(CONFORMANCE_DIR)
+        .join(file_name);
+    let json = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("could not read {}: {e}", path.display()));
+    serde_json::from_str(&json)
+        .unwrap_or_else(|e| panic!("could not parse {}: {e}", path.display()))
+}
+
+fn decode_hex(hex: &str) -> Vec<u8> {
+    assert!(hex.len() % 2 == 0, "odd number of hex digits in {hex}");
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).join("")
+}
+
+/// The message a peer message fixture describes.
+fn peer_message(name: &str) -> PeerMessage {
+    let endpoint = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9798);
+    match name {
+        "block_notification_request" => PeerMessage::BlockNotificationRequest,
+        "peer_list_request" => PeerMessage::PeerListRequest,
+        "bye" => PeerMessage::Bye,
+        "rendezvous_request" => PeerMessage::RendezvousRequest,
+        "reachability_request" => PeerMessage::ReachabilityRequest,
+        "connection_status_accepted" => PeerMessage::ConnectionStatus(ConnectionStatus::Accepted),
+        "connection_status_refused_bad_standing" => PeerMessage::ConnectionStatus(
+            ConnectionStatus::Refused(ConnectionRefusedReason::BadStanding),
+        ),
+        "block_request_batch_empty" => PeerMessage::BlockRequestBatch(BlockRequestBatch {
+            known_blocks: vec![],
+            max_response_len: 10,
+        }),
+        "block_response_batch_empty" => PeerMessage::BlockResponseBatch(vec![]),
+        "peer_list_response" => PeerMessage::PeerListResponse(vec![(endpoint, 42)]),
+        "rendezvous_introduction" => PeerMessage::RendezvousIntroduction {
+            endpoint,
+            initiator: true,
+        },
+        "reachability_response_unreachable" => PeerMessage::ReachabilityResponse {
+            address: None,
+            reachable: false,
+        },
+        "reachability_response_reachable" => PeerMessage::ReachabilityResponse {
+            address: Some(endpoint),
+            reachable: true,
+        },
+        _ => panic!("no peer message known for fixture {name}"),
+    }
+}
+
+#[test]
+fn peer_messages_match_conformance_fixtures() {
+    let fixtures: PeerMessageFixtures = read_fixtures(PEER_MESSAGES_FILE_NAME);
+    assert!(!fixtures.messages.is_empty());
+
+    for fixture in fixtures.messages {
+        let message = peer_message(&fixture.name);
+        let frame = decode_hex(&fixture.frame);
+        assert_eq!(
+            fixture.frame,
+            encode_hex(&to_bytes(&message).unwrap()),
+            "encoding of {} differs from fixture",
+            fixture.name
+        );
+
+        let mut src = BytesMut::from(frame.as_slice());
+        let payload = PeerMessageCodec::new(MessageSizeLimits::STANDARD)
+            .decode(&mut src)
+            .unwrap()
+            .unwrap_or_else(|| panic!("incomplete frame in fixture {}", fixture.name));
+        assert!(src.is_empty(), "trailing bytes in fixture {}", fixture.name);
+        let decoded: PeerMessage = bincode::deserialize(&payload).unwrap();
+        assert_eq!(message, decoded, "decoding of {} differs", fixture.name);
+    }
+}
+
+#[traced_test]
+#[tokio::test]
+async fn rpc_calls_match_conformance_fixtures() {
+    let fixtures: RpcFixtures = read_fixtures(RPC_FILE_NAME);
+    assert!(!fixtures.calls.is_empty());
+
+    let global_state_lock =
+        mock_genesis_global_state(Network::RegTest, 0, WalletSecret::devnet_wallet()).await;
+    let (rpc_server_to_main_tx, mut rpc_server_to_main_rx) =
+        tokio::sync::mpsc::channel(RPC_CHANNEL_CAPACITY);
+    tokio::spawn(async move { while rpc_server_to_main_rx.recv().await.is_some() {} });
+    let rpc_server = NeptuneRPCServer {
+        socket_address: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8080),
+        state: global_state_lock,
+        rpc_server_to_main_tx,
+    };
+
+    for fixture in fixtures.calls {
+        let request: RPCRequest = serde_json::from_value(fixture.request.clone())
+            .unwrap_or_else(|e| panic!("request of {} does not parse: {e}", fixture.method));
+        assert_eq!(
+            fixture.request,
+            serde_json::to_value(&request).unwrap(),
+            "encoding of {} request differs from fixture",
+            fixture.method
+        );
+
+        let response = rpc_server
+            .clone()
+            .serve()
+            .serve(context::current(), request)
+            .await
+            .unwrap();
+        assert_eq!(
+            fixture.response,
+            serde_json::to_value(&response).unwrap(),
+            "response to {} differs from fixture",
+            fixture.method
+        );
+    }
+}
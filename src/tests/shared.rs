@@ -47,6 +47,7 @@ use twenty_first::util_types::mmr::mmr_trait::Mmr;
 use crate::config_models::cli_args;
 use crate::config_models::data_directory::DataDirectory;
 use crate::config_models::network::Network;
+use crate::config_models::network_params::NetworkParams;
 use crate::database::NeptuneLevelDb;
 use crate::mine_loop::make_coinbase_transaction;
 use crate::mine_loop::mine_loop_tests::mine_iteration_for_tests;
@@ -74,6 +75,7 @@ use crate::models::channel::PeerTaskToMain;
 use crate::models::database::BlockIndexKey;
 use crate::models::database::BlockIndexValue;
 use crate::models::database::PeerDatabases;
+use crate::models::peer::transfer_transaction::AcceptedTransactionProofs;
 use crate::models::peer::HandshakeData;
 use crate::models::peer::PeerInfo;
 use crate::models::peer::PeerMessage;
@@ -83,6 +85,7 @@ use crate::models::proof_abstractions::timestamp::Timestamp;
 use crate::models::state::archival_state::ArchivalState;
 use crate::models::state::blockchain_state::BlockchainArchivalState;
 use crate::models::state::blockchain_state::BlockchainState;
+use crate::models::state::checkpoint_beacon::CheckpointBeacon;
 use crate::models::state::light_state::LightState;
 use crate::models::state::mempool::Mempool;
 use crate::models::state::networking_state::NetworkingState;
@@ -143,6 +146,8 @@ pub(crate) fn get_dummy_peer(address: SocketAddr) -> PeerInfo {
         version: get_dummy_version(),
         port_for_incoming_connections: Some(8080),
         is_archival_node: true,
+        own_block_latency: None,
+        round_trip_time: None,
     }
 }
 
@@ -159,6 +164,9 @@ pub async fn get_dummy_handshake_data_for_genesis(network: Network) -> Handshake
         network,
         version: get_dummy_version(),
         is_archival_node: true,
+        external_address: None,
+        accepted_tx_proofs: AcceptedTransactionProofs::new(false),
+        supports_ping: true,
     }
 }
 
@@ -199,7 +207,13 @@ pub(crate) async fn mock_genesis_global_state(
             std::net::SocketAddr::from_str(&format!("123.123.123.{}:8080", i)).unwrap();
         peer_map.insert(peer_address, get_dummy_peer(peer_address));
     }
-    let networking_state = NetworkingState::new(peer_map, peer_db, syncing, None);
+    let networking_state = NetworkingState::new(
+        peer_map,
+        peer_db,
+        syncing,
+        None,
+        CheckpointBeacon::default(),
+    );
     let genesis_block = archival_state.get_tip().await;
 
     // Sanity check
@@ -219,7 +233,7 @@ pub(crate) async fn mock_genesis_global_state(
         ..Default::default()
     };
     let mempool = Mempool::new(
-        cli_args.max_mempool_size,
+        cli_args.mempool_size_limit(),
         cli_args.max_mempool_num_tx,
         genesis_block.hash(),
     );
@@ -695,6 +709,7 @@ pub(crate) fn make_mock_block(
         previous_block.header().difficulty,
         None,
         previous_block.header().height,
+        NetworkParams::in_effect(),
     );
     let block_header = BlockHeader {
         version: zero,
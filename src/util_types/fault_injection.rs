@@ -0,0 +1,250 @@
+//! Programmable faults in block validation and database writes, for testing
+//! crash recovery, rollback of partially applied state, and peer sanctioning.
+//!
+//! Faults are only available in unit tests and when the crate is built with
+//! the `fault-injection` feature. In all other builds the hooks compile to
+//! constant `false`, so production code pays nothing for them.
+//!
+//! Faults are keyed by the block digest or by the database path they apply to,
+//! such that tests running in parallel do not trip each other's faults. An
+//! armed fault fires once, after being hit a configurable number of times, and
+//! is then disarmed.
+//!
+//! ```ignore
+//! fault_injection::arm(
+//!     FaultPoint::BlockValidation {
+//!         block_digest: block.hash(),
+//!         step: BlockValidationStep::Coinbase,
+//!     },
+//!     0,
+//! );
+//! assert!(!block.is_valid(&predecessor, now));
+//! ```
+
+use std::path::Path;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::prelude::twenty_first::math::digest::Digest;
+
+/// The consensus checks of `Block::is_valid_extended`, labelled as in its
+/// documentation. Also reported as the reason a block is invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum BlockValidationStep {
+    /// 0.a) Block height is previous plus one
+    Height,
+    /// 0.b) Block header points to previous block
+    PrevBlockDigest,
+    /// 0.c) Block mmr updated correctly
+    BlockMmr,
+    /// 0.d) Minimum block time has passed
+    MinimumBlockTime,
+    /// 0.e) Difficulty and cumulative proof-of-work are correct
+    Difficulty,
+    /// 0.f) Block timestamp is not too far in the future
+    FutureDating,
+    /// 0.g) Lock-free MMR is unchanged
+    LockFreeMmr,
+    /// 1.a) Appendix contains required claims
+    AppendixClaims,
+    /// 1.b) Block proof is valid
+    Proof,
+    /// 1.c) Max block size is not exceeded
+    BlockSize,
+    /// 2.a) Removal records are valid
+    RemovalRecords,
+    /// 2.b) Removal records have unique index sets
+    UniqueIndexSets,
+    /// 2.c) Mutator set update is consistent
+    MutatorSetUpdate,
+    /// 2.d) Transaction timestamp does not exceed block timestamp
+    TransactionTimestamp,
+    /// 2.e) Coinbase is covered by subsidy and fee
+    Coinbase,
+    /// Block matches the validation checkpoint at its height, if any. Only
+    /// checked by `Block::is_valid_with_checkpoints`.
+    Checkpoint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FaultPoint {
+    /// Make the given validation step reject the block with this digest.
+    BlockValidation {
+        block_digest: Digest,
+        step: BlockValidationStep,
+    },
+
+    /// Panic, before writing, on a write (`put`, `batch_write`, or `delete`) to
+    /// the database at this path, as if the process crashed.
+    DbWrite { db_path: std::path::PathBuf },
+}
+
+#[cfg(any(test, feature = "fault-injection"))]
+mod registry {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::sync::OnceLock;
+
+    use super::FaultPoint;
+
+    /// Armed faults, with the number of hits to let pass before firing.
+    fn armed() -> &'static Mutex<HashMap<FaultPoint, usize>> {
+        static ARMED: OnceLock<Mutex<HashMap<FaultPoint, usize>>> = OnceLock::new();
+        ARMED.get_or_init(Default::default)
+    }
+
+    /// Arm a fault that fires after being hit `skip` times without firing.
+    pub fn arm(point: FaultPoint, skip: usize) {
+        armed().lock().unwrap().insert(point, skip);
+    }
+
+    /// Disarm a fault that has not fired yet.
+    pub fn disarm(point: &FaultPoint) {
+        armed().lock().unwrap().remove(point);
+    }
+
+    pub(super) fn hit(point: &FaultPoint) -> bool {
+        let mut armed = armed().lock().unwrap();
+        match armed.get_mut(point) {
+            None => false,
+            Some(0) => {
+                armed.remove(point);
+                true
+            }
+            Some(skip) => {
+                *skip -= 1;
+                false
+            }
+        }
+    }
+}
+
+#[cfg(any(test, feature = "fault-injection"))]
+pub use registry::arm;
+#[cfg(any(test, feature = "fault-injection"))]
+pub use registry::disarm;
+
+#[cfg(any(test, feature = "fault-injection"))]
+fn hit(point: &FaultPoint) -> bool {
+    registry::hit(point)
+}
+
+#[cfg(not(any(test, feature = "fault-injection")))]
+#[inline(always)]
+fn hit(_point: &FaultPoint) -> bool {
+    false
+}
+
+/// Whether validation of the given block should fail at the given step.
+#[inline]
+pub(crate) fn block_validation_fails(block_digest: Digest, step: BlockValidationStep) -> bool {
+    if !cfg!(any(test, feature = "fault-injection")) {
+        return false;
+    }
+
+    let fails = hit(&FaultPoint::BlockValidation { block_digest, step });
+    if fails {
+        tracing::warn!("Injected fault in block validation step {step:?}");
+    }
+    fails
+}
+
+/// Panic if a write to the database at the given path should fail.
+#[inline]
+pub(crate) fn check_db_write(db_path: &Path) {
+    if !cfg!(any(test, feature = "fault-injection")) {
+        return;
+    }
+
+    let point = FaultPoint::DbWrite {
+        db_path: db_path.to_path_buf(),
+    };
+    if hit(&point) {
+        panic!("Injected fault on write to database {}", db_path.display());
+    }
+}
+
+#[cfg(test)]
+mod fault_injection_tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::database::NeptuneLevelDb;
+
+    #[test]
+    fn fault_fires_once_after_skipped_hits() {
+        let db_path = PathBuf::from("/nonexistent/fault_fires_once_after_skipped_hits");
+        let point = FaultPoint::DbWrite {
+            db_path: db_path.clone(),
+        };
+        arm(point, 2);
+
+        check_db_write(&db_path);
+        check_db_write(&db_path);
+        assert!(std::panic::catch_unwind(|| check_db_write(&db_path)).is_err());
+        check_db_write(&db_path);
+    }
+
+    #[test]
+    fn disarmed_fault_does_not_fire() {
+        let point = FaultPoint::BlockValidation {
+            block_digest: Digest::default(),
+            step: BlockValidationStep::Proof,
+        };
+        arm(point.clone(), 0);
+        disarm(&point);
+        assert!(!block_validation_fails(
+            Digest::default(),
+            BlockValidationStep::Proof
+        ));
+    }
+
+    #[tokio::test]
+    async fn db_write_fault_leaves_earlier_writes_intact() {
+        let mut db = NeptuneLevelDb::<u64, u64>::open_new_test_database(true, None, None, None)
+            .await
+            .unwrap();
+        arm(
+            FaultPoint::DbWrite {
+                db_path: db.path().clone(),
+            },
+            1,
+        );
+
+        db.put(1, 10).await;
+        let mut db_clone = db.clone();
+        let crashed_write = tokio::spawn(async move { db_clone.put(2, 20).await }).await;
+        assert!(crashed_write.is_err());
+
+        assert_eq!(Some(10), db.get(1).await);
+        assert_eq!(None, db.get(2).await);
+    }
+
+    #[test]
+    fn faults_are_keyed_by_step() {
+        let block_digest = Digest::new([
+            1u64.into(),
+            2u64.into(),
+            3u64.into(),
+            4u64.into(),
+            5u64.into(),
+        ]);
+        arm(
+            FaultPoint::BlockValidation {
+                block_digest,
+                step: BlockValidationStep::Coinbase,
+            },
+            0,
+        );
+        assert!(!block_validation_fails(
+            block_digest,
+            BlockValidationStep::Height
+        ));
+        assert!(block_validation_fails(
+            block_digest,
+            BlockValidationStep::Coinbase
+        ));
+    }
+}
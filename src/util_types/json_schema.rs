@@ -0,0 +1,109 @@
+//! JSON schemas of the serialized forms of types from dependencies, for use
+//! with `#[schemars(with = "...")]` on fields of these types.
+//!
+//! The schemas describe the JSON produced by the types' serde
+//! implementations, which is what the RPC server sends and expects.
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::ArrayValidation;
+use schemars::schema::InstanceType;
+use schemars::schema::Metadata;
+use schemars::schema::ObjectValidation;
+use schemars::schema::Schema;
+use schemars::schema::SchemaObject;
+use schemars::JsonSchema;
+
+use crate::prelude::twenty_first::math::digest::Digest;
+
+fn described(mut schema: SchemaObject, description: &str) -> Schema {
+    schema.metadata = Some(Box::new(Metadata {
+        description: Some(description.to_owned()),
+        ..Default::default()
+    }));
+    schema.into()
+}
+
+/// A `BFieldElement`, serialized as its canonical value.
+pub struct BFieldElementSchema;
+
+impl JsonSchema for BFieldElementSchema {
+    fn schema_name() -> String {
+        "BFieldElement".to_owned()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        described(
+            u64::json_schema(gen).into_object(),
+            "Element of the field with 2^64 - 2^32 + 1 elements, as its canonical value",
+        )
+    }
+}
+
+/// A `Digest`, serialized as its array of [`Digest::LEN`] field elements.
+pub struct DigestSchema;
+
+impl JsonSchema for DigestSchema {
+    fn schema_name() -> String {
+        "Digest".to_owned()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let schema = SchemaObject {
+            instance_type: Some(InstanceType::Array.into()),
+            array: Some(Box::new(ArrayValidation {
+                items: Some(gen.subschema_for::<BFieldElementSchema>().into()),
+                min_items: Some(Digest::LEN as u32),
+                max_items: Some(Digest::LEN as u32),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        described(schema, "Tip5 digest")
+    }
+}
+
+/// An `MmrMembershipProof`, serialized as its authentication path.
+pub struct MmrMembershipProofSchema;
+
+impl JsonSchema for MmrMembershipProofSchema {
+    fn schema_name() -> String {
+        "MmrMembershipProof".to_owned()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let mut object = ObjectValidation::default();
+        object.properties.insert(
+            "authentication_path".to_owned(),
+            gen.subschema_for::<Vec<DigestSchema>>(),
+        );
+        object.required.insert("authentication_path".to_owned());
+        let schema = SchemaObject {
+            instance_type: Some(InstanceType::Object.into()),
+            object: Some(Box::new(object)),
+            ..Default::default()
+        };
+        described(schema, "Membership proof in a Merkle mountain range")
+    }
+}
+
+/// A lattice-based KEM public key. Its serialized form is an implementation
+/// detail of the lattice library, so clients should pass it on as received.
+pub struct KemPublicKeySchema;
+
+impl JsonSchema for KemPublicKeySchema {
+    fn schema_name() -> String {
+        "KemPublicKey".to_owned()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        let schema = SchemaObject {
+            instance_type: Some(InstanceType::Object.into()),
+            ..Default::default()
+        };
+        described(
+            schema,
+            "Lattice-based KEM public key. Opaque: pass on as received from the node, or use \
+            the bech32m encoding of the address instead.",
+        )
+    }
+}
@@ -1,3 +1,5 @@
+pub mod fault_injection;
+pub mod json_schema;
 pub mod mutator_set;
 
 #[cfg(test)]
@@ -1,5 +1,6 @@
 use arbitrary::Arbitrary;
 use get_size::GetSize;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use tasm_lib::prelude::TasmObject;
@@ -7,6 +8,7 @@ use twenty_first::math::bfield_codec::BFieldCodec;
 use twenty_first::math::tip5::Digest;
 
 use crate::prelude::twenty_first;
+use crate::util_types::json_schema::DigestSchema;
 
 #[derive(
     Clone,
@@ -21,8 +23,10 @@ use crate::prelude::twenty_first;
     BFieldCodec,
     Arbitrary,
     TasmObject,
+    JsonSchema,
 )]
 pub struct AdditionRecord {
+    #[schemars(with = "DigestSchema")]
     pub canonical_commitment: Digest,
 }
 
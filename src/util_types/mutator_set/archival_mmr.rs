@@ -191,19 +191,40 @@ impl<Storage: StorageVec<Digest>> ArchivalMmr<Storage> {
 
     /// Return membership proof
     pub async fn prove_membership_async(&self, leaf_index: u64) -> MmrMembershipProof {
+        let num_leafs = self.num_leafs().await;
+        self.prove_membership_relative_to(leaf_index, num_leafs)
+            .await
+    }
+
+    /// Return the peaks of the MMR as it was when it had `num_leafs` leafs.
+    /// Only meaningful if no leaf was mutated since, as in an append-only
+    /// list.
+    pub async fn peaks_at(&self, num_leafs: u64) -> Vec<Digest> {
+        assert!(num_leafs <= self.num_leafs().await);
+        let (_, peak_node_indices) = get_peak_heights_and_peak_node_indices(num_leafs);
+        self.digests.get_many(&peak_node_indices).await
+    }
+
+    /// Return membership proof relative to the MMR as it was when it had
+    /// `num_leafs` leafs. Only meaningful if no leaf was mutated since, as in
+    /// an append-only list.
+    pub async fn prove_membership_relative_to(
+        &self,
+        leaf_index: u64,
+        num_leafs: u64,
+    ) -> MmrMembershipProof {
         // A proof consists of an authentication path
         // and a list of peaks
-        let num_leafs = self.num_leafs().await;
         assert!(
-            leaf_index < num_leafs,
-            "Cannot prove membership of leaf outside of range. Got leaf_index {leaf_index}. Leaf count is {}", self.num_leafs().await
+            leaf_index < num_leafs && num_leafs <= self.num_leafs().await,
+            "Cannot prove membership of leaf outside of range. Got leaf_index {leaf_index}. Leaf count is {num_leafs}"
         );
 
         let node_index = shared_advanced::leaf_index_to_node_index(leaf_index);
         let (_, own_index_into_peaks_list) =
             leaf_index_to_mt_index_and_peak_index(leaf_index, num_leafs);
         let (_, peak_indices) = get_peak_heights_and_peak_node_indices(num_leafs);
-        let num_nodes = self.digests.len().await;
+        let num_nodes = shared_advanced::leaf_index_to_node_index(num_leafs);
         let sibling_indices = get_authentication_path_node_indices(
             node_index,
             peak_indices[own_index_into_peaks_list as usize],
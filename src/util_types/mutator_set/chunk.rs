@@ -1,6 +1,7 @@
 use arbitrary::Arbitrary;
 use get_size::GetSize;
 use itertools::Itertools;
+use schemars::JsonSchema;
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
 use tasm_lib::prelude::TasmObject;
@@ -9,7 +10,18 @@ use twenty_first::math::bfield_codec::BFieldCodec;
 use super::shared::CHUNK_SIZE;
 use crate::prelude::twenty_first;
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, GetSize, BFieldCodec, TasmObject)]
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    GetSize,
+    BFieldCodec,
+    TasmObject,
+    JsonSchema,
+)]
 pub struct Chunk {
     pub relative_indices: Vec<u32>,
 }
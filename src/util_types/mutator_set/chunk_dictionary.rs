@@ -9,6 +9,7 @@ use rand::rngs::StdRng;
 use rand::Rng;
 use rand::RngCore;
 use rand::SeedableRng;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use tasm_lib::prelude::TasmObject;
@@ -21,6 +22,7 @@ use super::chunk::Chunk;
 use crate::models::blockchain::shared::Hash;
 use crate::prelude::triton_vm;
 use crate::prelude::twenty_first;
+use crate::util_types::json_schema::MmrMembershipProofSchema;
 
 type AuthenticatedChunk = (MmrMembershipProof, Chunk);
 type ChunkIndex = u64;
@@ -37,12 +39,14 @@ type ChunkIndex = u64;
     Arbitrary,
     BFieldCodec,
     TasmObject,
+    JsonSchema,
 )]
 pub struct ChunkDictionary {
     // {chunk index => (MMR membership proof for the whole chunk to which index belongs, chunk value)}
     // This list is always sorted. It has max. NUM_TRIALS=45 elements, so we
     // don't care about the cost of reallocation when `insert`ing or
     // `remove`ing.
+    #[schemars(with = "Vec<(u64, (MmrMembershipProofSchema, Chunk))>")]
     dictionary: Vec<(u64, (MmrMembershipProof, Chunk))>,
 }
 
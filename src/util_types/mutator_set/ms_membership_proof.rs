@@ -10,6 +10,7 @@ use itertools::Itertools;
 use rand::rngs::StdRng;
 use rand::Rng;
 use rand::SeedableRng;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use tasm_lib::structure::tasm_object::TasmObject;
@@ -36,6 +37,8 @@ use super::shared::BATCH_SIZE;
 use super::shared::CHUNK_SIZE;
 use crate::models::blockchain::shared::Hash;
 use crate::prelude::twenty_first;
+use crate::util_types::json_schema::DigestSchema;
+use crate::util_types::json_schema::MmrMembershipProofSchema;
 impl Error for MembershipProofError {}
 
 impl fmt::Display for MembershipProofError {
@@ -52,11 +55,24 @@ pub enum MembershipProofError {
 
 // In order to store this structure in the database, it needs to be serializable.
 #[derive(
-    Debug, Clone, PartialEq, Eq, Serialize, Deserialize, GetSize, BFieldCodec, TasmObject, Arbitrary,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    GetSize,
+    BFieldCodec,
+    TasmObject,
+    Arbitrary,
+    JsonSchema,
 )]
 pub struct MsMembershipProof {
+    #[schemars(with = "DigestSchema")]
     pub sender_randomness: Digest,
+    #[schemars(with = "DigestSchema")]
     pub receiver_preimage: Digest,
+    #[schemars(with = "MmrMembershipProofSchema")]
     pub auth_path_aocl: MmrMembershipProof,
     pub aocl_leaf_index: u64,
     pub target_chunks: ChunkDictionary,
@@ -179,36 +179,45 @@ impl MutatorSetAccumulator {
     /// Check if a removal record can be applied to a mutator set. Returns false if either
     /// the MMR membership proofs are unsynced, or if all its indices are already set.
     pub fn can_remove(&self, removal_record: &RemovalRecord) -> bool {
-        let mut have_absent_index = false;
+        self.absent_indices(removal_record)
+            .is_some_and(|absent_indices| !absent_indices.is_empty())
+    }
+
+    /// The indices of the removal record that are not yet set in the
+    /// sliding-window Bloom filter, or `None` if the removal record is not
+    /// valid for this mutator set.
+    pub fn absent_indices(&self, removal_record: &RemovalRecord) -> Option<Vec<u128>> {
         if !removal_record.validate(self) {
-            return false;
+            return None;
         }
 
-        for inserted_index in removal_record.absolute_indices.to_vec().into_iter() {
-            // determine if inserted index lives in active window
-            let active_window_start =
-                (self.aocl.num_leafs() / BATCH_SIZE as u64) as u128 * CHUNK_SIZE as u128;
+        // determine if inserted index lives in active window
+        let active_window_start =
+            (self.aocl.num_leafs() / BATCH_SIZE as u64) as u128 * CHUNK_SIZE as u128;
+        let is_absent = |inserted_index: u128| {
             if inserted_index < active_window_start {
                 let inserted_index_chunkidx = (inserted_index / CHUNK_SIZE as u128) as u64;
-                if let Some((_mmr_mp, chunk)) =
-                    removal_record.target_chunks.get(&inserted_index_chunkidx)
-                {
-                    let relative_index = (inserted_index % CHUNK_SIZE as u128) as u32;
-                    if !chunk.contains(relative_index) {
-                        have_absent_index = true;
-                        break;
-                    }
-                }
+                removal_record
+                    .target_chunks
+                    .get(&inserted_index_chunkidx)
+                    .is_some_and(|(_mmr_mp, chunk)| {
+                        let relative_index = (inserted_index % CHUNK_SIZE as u128) as u32;
+                        !chunk.contains(relative_index)
+                    })
             } else {
                 let relative_index = (inserted_index - active_window_start) as u32;
-                if !self.swbf_active.contains(relative_index) {
-                    have_absent_index = true;
-                    break;
-                }
+                !self.swbf_active.contains(relative_index)
             }
-        }
+        };
 
-        have_absent_index
+        Some(
+            removal_record
+                .absolute_indices
+                .to_vec()
+                .into_iter()
+                .filter(|&inserted_index| is_absent(inserted_index))
+                .collect(),
+        )
     }
 }
 
@@ -1,25 +1,65 @@
+use std::collections::BTreeSet;
+
+use serde::Deserialize;
+use serde::Serialize;
 use twenty_first::math::tip5::Digest;
+use twenty_first::util_types::algebraic_hasher::AlgebraicHasher;
+use twenty_first::util_types::mmr::mmr_accumulator::MmrAccumulator;
 
 use super::active_window::ActiveWindow;
 use super::archival_mmr::ArchivalMmr;
 use super::archival_mutator_set::ArchivalMutatorSet;
 use super::chunk::Chunk;
+use super::chunk_dictionary::ChunkDictionary;
+use super::get_swbf_indices;
+use super::ms_membership_proof::MsMembershipProof;
+use super::mutator_set_accumulator::MutatorSetAccumulator;
+use super::removal_record::RemovalRecord;
+use super::shared::CHUNK_SIZE;
 use crate::database::storage::storage_schema::traits::*;
 use crate::database::storage::storage_schema::DbtSingleton;
 use crate::database::storage::storage_schema::DbtVec;
 use crate::database::storage::storage_schema::RustyKey;
 use crate::database::storage::storage_schema::RustyValue;
 use crate::database::storage::storage_schema::SimpleRustyStorage;
+use crate::database::storage::storage_vec::traits::*;
+use crate::database::storage::storage_vec::OrdinaryVec;
 use crate::database::NeptuneLevelDb;
+use crate::models::blockchain::block::block_height::BlockHeight;
+use crate::models::blockchain::shared::Hash;
 use crate::prelude::twenty_first;
 
 type AmsMmrStorage = DbtVec<Digest>;
 type AmsChunkStorage = DbtVec<Chunk>;
+
+/// The parts of the mutator set, as of some block, that cannot be read off
+/// the AOCL and the chunk history. The AOCL is append-only, so its state as of
+/// that block is determined by its leaf count.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MutatorSetSnapshot {
+    pub aocl_leaf_count: u64,
+    pub num_chunks: u64,
+    pub active_window: Vec<u32>,
+}
+
+/// An archival mutator set, persisted to a database.
+///
+/// Besides the current state, it keeps the history of the inactive part of
+/// the sliding-window Bloom filter (SWBF) for every block since it started
+/// recording, such that membership can be queried, and membership proofs
+/// restored, relative to the mutator set as of any of those blocks.
 pub struct RustyArchivalMutatorSet {
     ams: ArchivalMutatorSet<AmsMmrStorage, AmsChunkStorage>,
     storage: SimpleRustyStorage,
     active_window_storage: DbtSingleton<Vec<u32>>,
     sync_label: DbtSingleton<Digest>,
+
+    /// For every chunk, its value after each block that changed it
+    chunk_history: DbtVec<Vec<(BlockHeight, Chunk)>>,
+
+    /// One snapshot per block, starting at height `history_start`
+    snapshots: DbtVec<MutatorSetSnapshot>,
+    history_start: DbtSingleton<BlockHeight>,
 }
 
 impl RustyArchivalMutatorSet {
@@ -38,6 +78,18 @@ impl RustyArchivalMutatorSet {
             .new_singleton::<Vec<u32>>("active_window")
             .await;
         let sync_label = storage.schema.new_singleton::<Digest>("sync_label").await;
+        let chunk_history = storage
+            .schema
+            .new_vec::<Vec<(BlockHeight, Chunk)>>("chunk_history")
+            .await;
+        let snapshots = storage
+            .schema
+            .new_vec::<MutatorSetSnapshot>("snapshots")
+            .await;
+        let history_start = storage
+            .schema
+            .new_singleton::<BlockHeight>("history_start")
+            .await;
 
         let ams = ArchivalMutatorSet::<AmsMmrStorage, AmsChunkStorage> {
             chunks,
@@ -51,6 +103,9 @@ impl RustyArchivalMutatorSet {
             storage,
             sync_label,
             active_window_storage: active_window,
+            chunk_history,
+            snapshots,
+            history_start,
         }
     }
 
@@ -83,6 +138,239 @@ impl RustyArchivalMutatorSet {
         // populate active window
         self.ams_mut().swbf_active.sbf = self.active_window_storage.get().await;
     }
+
+    /// The indices of the chunks that removing the given removal records
+    /// can modify.
+    pub fn touched_chunk_indices(removal_records: &[RemovalRecord]) -> BTreeSet<u64> {
+        removal_records
+            .iter()
+            .flat_map(|rr| rr.absolute_indices.to_vec())
+            .map(|index| (index / CHUNK_SIZE as u128) as u64)
+            .collect()
+    }
+
+    async fn current_snapshot(&self) -> MutatorSetSnapshot {
+        MutatorSetSnapshot {
+            aocl_leaf_count: self.ams.aocl.num_leafs().await,
+            num_chunks: self.ams.chunks.len().await,
+            active_window: self.ams.swbf_active.sbf.clone(),
+        }
+    }
+
+    /// Start recording history, unless already recording. `height` is the
+    /// height of the block the mutator set is currently synced to.
+    pub async fn ensure_history(&mut self, height: BlockHeight) {
+        if !self.snapshots.is_empty().await {
+            return;
+        }
+
+        self.history_start.set(height).await;
+        let snapshot = self.current_snapshot().await;
+        self.snapshots.push(snapshot).await;
+        self.chunk_history.clear().await;
+        for chunk in self.ams.chunks.get_all().await {
+            self.chunk_history.push(vec![(height, chunk)]).await;
+        }
+    }
+
+    /// Record the state after applying the block of the given height, which
+    /// removed items from the given chunks.
+    pub async fn record_history(&mut self, height: BlockHeight, touched_chunks: &BTreeSet<u64>) {
+        debug_assert_eq!(
+            u64::from(self.history_start.get().await) + self.snapshots.len().await,
+            u64::from(height),
+            "history must be recorded block by block"
+        );
+
+        let num_recorded = self.chunk_history.len().await;
+        for &chunk_index in touched_chunks.range(..num_recorded) {
+            let mut history = self.chunk_history.get(chunk_index).await;
+            history.push((height, self.ams.chunks.get(chunk_index).await));
+            self.chunk_history.set(chunk_index, history).await;
+        }
+        for chunk_index in num_recorded..self.ams.chunks.len().await {
+            let chunk = self.ams.chunks.get(chunk_index).await;
+            self.chunk_history.push(vec![(height, chunk)]).await;
+        }
+
+        let snapshot = self.current_snapshot().await;
+        self.snapshots.push(snapshot).await;
+    }
+
+    /// Forget the history of the block of the given height, after it was
+    /// rolled back. `touched_chunks` are the chunks the block removed items
+    /// from. Rolling back past the first recorded block clears the history.
+    pub async fn revert_history(&mut self, height: BlockHeight, touched_chunks: &BTreeSet<u64>) {
+        if self.snapshots.is_empty().await {
+            return;
+        }
+
+        if height <= self.history_start.get().await {
+            self.snapshots.clear().await;
+            self.chunk_history.clear().await;
+            return;
+        }
+
+        self.snapshots.pop().await;
+        let num_chunks = self.ams.chunks.len().await;
+        while self.chunk_history.len().await > num_chunks {
+            self.chunk_history.pop().await;
+        }
+        for &chunk_index in touched_chunks.range(..num_chunks) {
+            let mut history = self.chunk_history.get(chunk_index).await;
+            history.retain(|(h, _)| *h < height);
+            self.chunk_history.set(chunk_index, history).await;
+        }
+    }
+
+    /// The snapshot as of the block of the given height, if recorded.
+    pub async fn snapshot_as_of(&self, height: BlockHeight) -> Option<MutatorSetSnapshot> {
+        let offset = u64::from(height).checked_sub(self.history_start.get().await.into())?;
+        if offset >= self.snapshots.len().await {
+            return None;
+        }
+
+        Some(self.snapshots.get(offset).await)
+    }
+
+    /// The value of an inactive chunk as of the block of the given height, if
+    /// recorded.
+    pub async fn chunk_as_of(&self, chunk_index: u64, height: BlockHeight) -> Option<Chunk> {
+        let snapshot = self.snapshot_as_of(height).await?;
+        if chunk_index >= snapshot.num_chunks {
+            return None;
+        }
+
+        self.chunk_history
+            .get(chunk_index)
+            .await
+            .into_iter()
+            .rev()
+            .find(|(h, _)| *h <= height)
+            .map(|(_, chunk)| chunk)
+    }
+
+    /// Determine whether the index `index` was set in the Bloom filter as of
+    /// the block of the given height, if recorded.
+    pub async fn bloom_filter_contains_as_of(
+        &self,
+        index: u128,
+        height: BlockHeight,
+    ) -> Option<bool> {
+        let snapshot = self.snapshot_as_of(height).await?;
+        let active_window_start = snapshot.num_chunks as u128 * CHUNK_SIZE as u128;
+
+        if index >= active_window_start {
+            let relative_index = (index - active_window_start) as u32;
+            Some(ActiveWindow::from_vec_u32(&snapshot.active_window).contains(relative_index))
+        } else {
+            let chunk_index = (index / CHUNK_SIZE as u128) as u64;
+            let relative_index = (index % CHUNK_SIZE as u128) as u32;
+            let chunk = self.chunk_as_of(chunk_index, height).await?;
+            Some(chunk.contains(relative_index))
+        }
+    }
+
+    /// Determine whether the removal record could be applied to the mutator
+    /// set as of the block of the given height, i.e., whether one of its
+    /// indices was not yet set. Returns `None` if that block is not recorded.
+    pub async fn can_remove_as_of(
+        &self,
+        removal_record: &RemovalRecord,
+        height: BlockHeight,
+    ) -> Option<bool> {
+        for index in removal_record.absolute_indices.to_array() {
+            if !self.bloom_filter_contains_as_of(index, height).await? {
+                return Some(true);
+            }
+        }
+
+        Some(false)
+    }
+
+    /// Rebuild the SWBF MMR as of the given snapshot in memory.
+    async fn swbf_inactive_as_of(
+        &self,
+        snapshot: &MutatorSetSnapshot,
+        height: BlockHeight,
+    ) -> ArchivalMmr<OrdinaryVec<Digest>> {
+        let mut swbf_inactive = ArchivalMmr::new(OrdinaryVec::from(vec![])).await;
+        for chunk_index in 0..snapshot.num_chunks {
+            let chunk = self
+                .chunk_as_of(chunk_index, height)
+                .await
+                .expect("history of all chunks of snapshot must be recorded");
+            swbf_inactive.append(Hash::hash(&chunk)).await;
+        }
+
+        swbf_inactive
+    }
+
+    /// The mutator set accumulator as of the block of the given height, if
+    /// recorded.
+    pub async fn accumulator_as_of(&self, height: BlockHeight) -> Option<MutatorSetAccumulator> {
+        let snapshot = self.snapshot_as_of(height).await?;
+        let swbf_inactive = self.swbf_inactive_as_of(&snapshot, height).await;
+
+        Some(MutatorSetAccumulator {
+            aocl: MmrAccumulator::init(
+                self.ams.aocl.peaks_at(snapshot.aocl_leaf_count).await,
+                snapshot.aocl_leaf_count,
+            ),
+            swbf_inactive: MmrAccumulator::init(swbf_inactive.peaks().await, snapshot.num_chunks),
+            swbf_active: ActiveWindow::from_vec_u32(&snapshot.active_window),
+        })
+    }
+
+    /// Restore a membership proof relative to the mutator set as of the block
+    /// of the given height. Returns `None` if that block is not recorded or
+    /// the item was not yet added. Like
+    /// [`ArchivalMutatorSet::restore_membership_proof`], this leaks privacy
+    /// when called on someone else's UTXO.
+    pub async fn restore_membership_proof_as_of(
+        &self,
+        item: Digest,
+        sender_randomness: Digest,
+        receiver_preimage: Digest,
+        aocl_leaf_index: u64,
+        height: BlockHeight,
+    ) -> Option<MsMembershipProof> {
+        let snapshot = self.snapshot_as_of(height).await?;
+        if aocl_leaf_index >= snapshot.aocl_leaf_count {
+            return None;
+        }
+
+        let auth_path_aocl = self
+            .ams
+            .aocl
+            .prove_membership_relative_to(aocl_leaf_index, snapshot.aocl_leaf_count)
+            .await;
+
+        let swbf_indices =
+            get_swbf_indices(item, sender_randomness, receiver_preimage, aocl_leaf_index);
+        let window_start = snapshot.num_chunks as u128 * CHUNK_SIZE as u128;
+        let chunk_indices: BTreeSet<u64> = swbf_indices
+            .iter()
+            .filter(|bi| **bi < window_start)
+            .map(|bi| (*bi / CHUNK_SIZE as u128) as u64)
+            .collect();
+
+        let swbf_inactive = self.swbf_inactive_as_of(&snapshot, height).await;
+        let mut target_chunks = ChunkDictionary::default();
+        for chunk_index in chunk_indices {
+            let chunk = self.chunk_as_of(chunk_index, height).await?;
+            let chunk_membership_proof = swbf_inactive.prove_membership_async(chunk_index).await;
+            target_chunks.insert(chunk_index, (chunk_membership_proof, chunk));
+        }
+
+        Some(MsMembershipProof {
+            sender_randomness,
+            receiver_preimage,
+            auth_path_aocl,
+            aocl_leaf_index,
+            target_chunks,
+        })
+    }
 }
 
 impl StorageWriter for RustyArchivalMutatorSet {
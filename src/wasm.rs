@@ -0,0 +1,90 @@
+//! JavaScript bindings for deriving and parsing addresses, enabled by the
+//! `wasm` feature.
+//!
+//! Browser wallet front-ends use these to show receiving addresses and check
+//! user input without running a node. Addresses are derived by the same code
+//! as the node's wallet, so a front-end and a node holding the same secret seed
+//! phrase agree on every address. Seed phrases are the 18 BIP-39 words of the
+//! secret, separated by whitespace, and networks are named as on the command
+//! line, e.g. `main` or `testnet`. Errors become JavaScript exceptions carrying
+//! the error message.
+
+use std::str::FromStr;
+
+use itertools::Itertools;
+use wasm_bindgen::prelude::*;
+
+use crate::config_models::network::Network;
+use crate::models::state::wallet::address::ReceivingAddress;
+use crate::models::state::wallet::WalletSecret;
+
+fn parse_network(network: &str) -> Result<Network, JsError> {
+    Network::from_str(network).map_err(|err| JsError::new(&err))
+}
+
+fn parse_seed_phrase(seed_phrase: &str) -> Result<WalletSecret, JsError> {
+    let words = seed_phrase
+        .split_whitespace()
+        .map(|word| word.to_string())
+        .collect_vec();
+    WalletSecret::from_phrase(&words).map_err(|err| JsError::new(&err.to_string()))
+}
+
+/// Generate the seed phrase of a new, random wallet secret.
+#[wasm_bindgen(js_name = generateSeedPhrase)]
+pub fn generate_seed_phrase() -> String {
+    WalletSecret::new_random().to_phrase().join(" ")
+}
+
+/// The bech32m encoding of the generation address with the given index of the
+/// wallet with the given seed phrase.
+#[wasm_bindgen(js_name = deriveGenerationAddress)]
+pub fn derive_generation_address(
+    seed_phrase: &str,
+    index: u16,
+    network: &str,
+) -> Result<String, JsError> {
+    let network = parse_network(network)?;
+    parse_seed_phrase(seed_phrase)?
+        .nth_generation_spending_key(index)
+        .to_address()
+        .to_bech32m(network)
+        .map_err(|err| JsError::new(&err.to_string()))
+}
+
+/// A receiving address parsed from its bech32m encoding.
+#[wasm_bindgen]
+pub struct ParsedAddress {
+    address: ReceivingAddress,
+}
+
+#[wasm_bindgen]
+impl ParsedAddress {
+    /// Parse a bech32m-encoded address for the given network.
+    #[wasm_bindgen(constructor)]
+    pub fn parse(encoded: &str, network: &str) -> Result<ParsedAddress, JsError> {
+        let network = parse_network(network)?;
+        let address = ReceivingAddress::from_bech32m(encoded.trim(), network)
+            .map_err(|err| JsError::new(&err.to_string()))?;
+        Ok(Self { address })
+    }
+
+    /// The identifier by which the receiver recognizes UTXO notifications, as
+    /// a decimal string since it may exceed the range of JavaScript numbers.
+    #[wasm_bindgen(getter, js_name = receiverIdentifier)]
+    pub fn receiver_identifier(&self) -> String {
+        self.address.receiver_identifier().to_string()
+    }
+
+    /// The hash of the lock script of UTXOs sent to this address, hex-encoded.
+    #[wasm_bindgen(getter, js_name = lockScriptHash)]
+    pub fn lock_script_hash(&self) -> String {
+        self.address.lock_script().hash().to_hex()
+    }
+}
+
+/// Whether `encoded` is a valid receiving address on the given network.
+#[wasm_bindgen(js_name = isValidAddress)]
+pub fn is_valid_address(encoded: &str, network: &str) -> bool {
+    ParsedAddress::parse(encoded, network).is_ok()
+}